@@ -0,0 +1,395 @@
+# G-DriveXP: English messages (base locale).
+# Every id here must also exist in ../es/main.ftl.
+
+## Shared by the window and the tray
+
+open-in-files = Open in Files
+pause-sync = Pause sync
+sync-paused = Sync paused
+sync-resumed = Sync resumed
+network-offline = Offline
+network-metered = Metered connection
+network-uploads-paused = { $reason }: uploads paused
+backpressure-backlog = Too many changes waiting to upload
+backpressure-disk = Low disk space
+backpressure-writes-held = { $reason }: writes held back
+
+## Tray
+
+tray-all-good = All good
+tray-pending-files =
+    { $count ->
+        [one] 1 file pending
+       *[other] { $count } files pending
+    }
+tray-changes-applied = { $applied }/{ $detected } changes applied
+tray-uploading = uploading { $name } { $percent }%
+tray-downloading = downloading { $name } { $percent }%
+tray-upload-errors = { $count } with errors
+tray-recent-activity = Recent activity
+tray-open-panel = Open panel
+tray-sync-now = Sync now
+tray-quit = Quit
+
+## Relative times in the activity list
+
+time-now = now
+time-minutes-short = { $count }m
+time-hours-short = { $count }h
+
+## Backend startup status
+
+status-initializing = Starting backend...
+status-checking-auth = Checking authentication...
+status-credentials-missing = Error: credentials.json not found
+status-auth-ok = Authenticated
+status-offline-mode = Offline: working in offline mode
+status-loading-db = Loading database...
+status-fetching-root = Fetching root folder ID...
+status-loading-structure = Loading initial structure...
+status-repairing-metadata = Recovering metadata...
+status-syncing-recent = Syncing recent changes...
+status-mounted = File system mounted and active
+status-shutting-down = Shutting down subsystems...
+status-unmounting = Unmounting...
+status-remounting = Drive folder lost, remounting (attempt { $attempt })...
+
+## Main window
+
+gui-starting = Starting G-DriveXP...
+gui-subtitle-main = Google Drive client
+gui-status = Status
+gui-connection = Connection
+gui-connected = Connected to Google Drive
+gui-disconnected = Disconnected
+gui-read-only-refused = Read-only mode: nothing is changed in Google Drive
+gui-scanning = Scanning...
+gui-scanned-of = Scanned { $count } of ~{ $expected } files (page { $pages })
+gui-scanned = Scanned { $count } files (page { $pages })
+gui-changes-queued = { $reason }: { $count } changes queued
+gui-changes-applied = { $applied }/{ $detected } changes applied
+gui-changes-pending = { $count } changes pending
+gui-syncing = Syncing...
+gui-all-synced = Everything is up to date
+gui-recent-activity = Recent activity
+gui-overall-status = Overall status
+gui-search-drive = Search Drive
+gui-search-placeholder = File or folder name
+gui-no-results = No results
+gui-my-drive = My Drive
+gui-files = Files
+gui-share-link = Copy share link
+gui-share-link-subtitle = Creates a read-only link and copies it to the clipboard
+gui-properties-and-versions = Properties and versions
+gui-properties-and-versions-subtitle = Browse a file's history and recover earlier versions
+gui-settings = Settings
+gui-appearance = Appearance
+gui-appearance-subtitle = Window color scheme
+appearance-system = Follow system
+appearance-light = Light
+appearance-dark = Dark
+gui-autostart = Start on login
+gui-autostart-subtitle = Opens in the system tray when you sign in
+gui-autostart-failed = Could not change start on login: { $error }
+gui-show-computers = Show computers
+gui-show-computers-subtitle = Backups of other computers from the Drive "Computers" section, under Computers/
+gui-sync-now-subtitle = Upload and download changes without waiting for the next cycle
+gui-sync-now-window = Syncs automatically only from { $start } to { $end }
+gui-sync-now-manual = Automatic sync is off: changes sync when you press here
+gui-sync-now-requested = Syncing…
+gui-rebuild-metadata = Rebuild metadata
+gui-rebuild-metadata-subtitle = Compares every file in Drive with the local copy and fixes differences, keeping downloaded files
+gui-rebuild-metadata-requested = Rebuilding metadata…
+gui-pause-sync-subtitle = Temporarily stops syncing
+gui-diagnostics = Diagnostics
+gui-view-log = View log
+gui-view-log-subtitle = Latest messages from the application log
+gui-log = Log
+gui-log-read-failed = Could not read the log: { $error }
+gui-export-diagnostics = Export diagnostics
+gui-export-diagnostics-subtitle = Bundles logs and settings to report a bug
+gui-diagnostics-saved = Diagnostics saved to { $path }
+gui-diagnostics-failed = Could not export diagnostics: { $error }
+gui-local-folders = Synced local folders
+gui-local-folders-description = Choose folders outside the Google Drive mirror to sync
+gui-add-folder = Add folder...
+gui-no-extra-folders = No additional folders configured
+gui-dir-syncing = Actively syncing
+gui-dir-paused = Sync paused for this folder
+gui-select-sync-folder = Select folder to sync
+gui-account = Account
+gui-logout = Log out
+gui-logout-subtitle = Unlinks this Google account
+gui-hard-reset = Hard Reset
+gui-hard-reset-subtitle = FULL WIPE: resets the database, cache and files.
+gui-hard-reset-running = Purging the system, please wait...
+gui-login-prompt = Sign in to continue
+gui-login = Sign in
+gui-login-generating = Generating link...
+
+## Cache usage
+
+gui-cache = Cache
+gui-cache-description = Content downloaded on demand for online-only files
+gui-cache-total = Total size
+gui-cache-on-disk = Space on disk
+gui-cache-pinned = Pinned offline
+gui-cache-by-folder = By folder
+gui-cache-root-files = Other files
+gui-cache-folder-pinned = { $size } pinned
+gui-cache-empty = The cache is empty
+gui-cache-evict-unpinned = Free unpinned data
+gui-cache-evict-unpinned-subtitle = Keeps pinned files and changes not yet uploaded
+gui-cache-clear = Clear cache
+gui-cache-clear-subtitle = Files are downloaded again when opened
+gui-cache-clear-confirm = Clear the whole cache?
+gui-cache-clear-confirm-body = Pinned files will also be downloaded again when opened. Changes not yet uploaded are kept.
+gui-cancel = Cancel
+gui-cache-freed =
+    { $kept ->
+        [0] { $size } freed
+        [one] { $size } freed (1 file with pending changes kept)
+       *[other] { $size } freed ({ $kept } files with pending changes kept)
+    }
+gui-cache-failed = Could not free the cache: { $error }
+gui-trash = Local trash
+gui-trash-description = Deleted on this computer. It stays in the Google Drive trash until you delete it permanently.
+gui-delete-permanently = Delete permanently
+gui-delete-permanently-confirm = Permanently delete “{ $name }”?
+gui-delete-permanently-confirm-body = It is removed from Google Drive without going to the trash. This cannot be undone.
+gui-delete-permanently-queued = “{ $name }” will be deleted permanently
+gui-delete-permanently-failed = Could not delete: { $error }
+
+## Backend supervisor
+
+gui-backend-reconnecting = Reconnecting the service (attempt { $attempt })...
+gui-backend-retrying = Service unavailable: { $error }. Retrying in { $seconds } s
+gui-backend-needs-action = Service unavailable: { $error }. Fix it and press Reconnect
+gui-backend-crashed = The service stopped because of an error: { $error }
+gui-backend-retry-status = Service unavailable, retrying in { $seconds } s
+gui-backend-stopped = Service stopped
+gui-reconnect = Reconnect
+gui-restart = Restart
+gui-reconnecting = Reconnecting...
+
+gui-storage-full = Your Google Drive storage is full. New or changed files can't be saved until you free up space.
+gui-storage-full-retry = Retry
+
+## Configuration problems
+
+gui-config-invalid = Invalid configuration
+gui-config-invalid-body = Fix these problems in { $path } (or in the command-line options) and press Reconnect:
+gui-open-config = Open configuration file
+gui-close = Close
+gui-mount-not-empty = Mount folder is not empty
+gui-mount-not-empty-body =
+    { $count ->
+        [one] { $path } contains an item that mounting Google Drive there would hide. Choose another folder or move it into your Drive:
+       *[other] { $path } contains { $count } items that mounting Google Drive there would hide. Choose another folder or move them into your Drive:
+    }
+gui-mount-not-empty-more = … and { $count } more
+gui-mount-choose-other = Choose another folder
+gui-mount-migrate = Move into Drive
+gui-mount-point-failed = Could not save the mount folder: { $error }
+config-problem-unreadable = { $path } can't be read: { $error }
+config-problem-not-a-directory = { $field }: { $path } is not a directory
+config-problem-not-writable = { $field }: no write permission on { $path }
+config-problem-nested = { $field } ({ $path }) can't be inside { $outer }
+config-problem-zero-interval = sync_interval_secs must be greater than 0
+config-problem-cache-too-small = max_cache_size_mb is { $mb } MB; the minimum is { $min } MB
+config-problem-prefetch-exceeds-cache = prefetch_budget_mb ({ $prefetch } MB) can't exceed max_cache_size_mb ({ $cache } MB)
+config-problem-invalid-ignore-pattern = ignore_patterns: "{ $pattern }" is not a valid pattern (a single name with *, ? or [..], no /)
+config-problem-invalid-sync-schedule = sync_schedule: the window needs two different times in HH:MM format (start and end)
+config-problem-metrics-not-loopback = metrics_listen ({ $addr }) must be a loopback address (127.0.0.1 or ::1): the metrics endpoint has no authentication
+
+## Activity view
+
+gui-downloading-heading = Downloading from Google Drive
+gui-uploading-heading = Uploading to Google Drive
+gui-problems = Problems
+gui-history = History
+gui-no-recent-activity = No recent activity
+gui-no-retry = Will not be retried
+gui-retry-in = Retry { $attempt } in { $minutes } min
+gui-retry-pending = Retry { $attempt } pending
+gui-retry-now = Retry now
+
+## Conflict center
+
+gui-conflicts = Conflicts
+gui-conflicts-subtitle = Files edited both here and in Drive at the same time
+gui-conflicts-unresolved = { $count } unresolved
+gui-no-conflicts = No conflicts
+gui-no-conflicts-description = No file was edited here and in Drive at the same time
+gui-conflicts-explanation = These files changed in Drive while they were being edited here. The local version was saved as a copy: choose which one to keep.
+gui-conflict-copy = Local copy: { $name }
+gui-conflict-edited = Edited here { $local } · in Drive { $remote }
+gui-unknown-date = unknown
+gui-show-diff = Show differences
+gui-differences = Differences
+gui-diff-failed = Cannot show the differences: { $error }
+gui-keep-local = Keep the local version
+gui-keep-remote = Keep the Drive version
+gui-dismiss-conflict = Dismiss (keep both files)
+gui-conflict-kept-local = kept the local version
+gui-conflict-kept-remote = kept the Drive version
+gui-conflict-kept-both = kept both files
+gui-conflict-failed = Could not resolve the conflict: { $error }
+
+## Sharing, properties and versions
+
+gui-select-file-to-share = Select file to share
+gui-link-copied = Link copied to the clipboard
+gui-link-failed = Error creating link: { $error }
+gui-select-file = Select file
+gui-properties = Properties
+gui-file = File
+gui-name = Name
+gui-location = Location
+gui-size = Size
+gui-starred = Starred
+gui-starred-subtitle = Also shown in the Starred folder
+gui-starred-added = { $name } added to starred
+gui-starred-removed = { $name } removed from starred
+gui-starred-failed = Could not change the star: { $error }
+gui-drive-details = Google Drive
+gui-md5 = MD5 checksum
+gui-owner = Owner
+gui-created = Created
+gui-created-by = Created on
+gui-origin-this-device = This device
+gui-origin-other-device = Another device running G-DriveXP
+gui-description = Description
+gui-description-saved = Description of { $name } updated
+gui-description-failed = Could not change the description: { $error }
+gui-folder-color = Folder color
+gui-owner-me = You
+gui-sharing = Sharing
+gui-sharing-shared = Shared with others
+gui-sharing-private = Only you
+gui-sync-state = Sync status
+gui-availability = Availability
+gui-availability-local = Always on this device
+gui-availability-online = Online only
+gui-sync-synced = Synced
+gui-sync-cloud-only = Online only
+gui-sync-local-only = Waiting to upload
+gui-sync-syncing = Uploading
+gui-sync-error = Upload error
+gui-sync-downloading = Downloading ({ $percent }%)
+gui-sync-uploading = Uploading ({ $percent }%)
+gui-cached = Cached
+gui-cached-percent = { $percent }% downloaded
+gui-not-available = Not available
+gui-open-in-browser = Open in browser
+gui-copy-share-link = Copy share link
+gui-pin-offline = Always keep on this device
+gui-pinned-offline = { $name } will stay on this device
+gui-pin-failed = Could not keep it on this device: { $error }
+gui-free-up-space = Free up space
+gui-freed-space = { $name } is now online-only: its downloaded content was freed
+gui-free-failed = Could not free up space: { $error }
+gui-browser-failed = Could not open the file in the browser: { $error }
+gui-versions = Versions
+gui-versions-description = Restoring uploads the chosen version as a new one; the current one stays in the history
+gui-version = Version { $number }
+gui-version-current = Version { $number } (current)
+gui-no-versions = No earlier versions
+gui-save-as-copy = Save as a copy
+gui-restore-version = Restore this version
+gui-loading-versions = Loading versions...
+gui-versions-count =
+    { $count ->
+        [one] 1 version
+       *[other] { $count } versions
+    }
+gui-versions-failed = Could not load the versions: { $error }
+gui-version-restored = Version recovered into { $name }
+gui-version-restore-failed = Could not recover the version: { $error }
+
+## Activity history entries (window and tray)
+
+activity-offline-queued = Offline: local changes are queued
+activity-connection-restored = Connection restored: syncing queued changes
+activity-metered-paused = Metered connection: uploads and prefetch paused
+activity-metered-resumed = Unmetered connection: sync resumed
+activity-scan-complete = Scan complete: { $count } files
+activity-scan-resumed = Resuming interrupted scan from page { $page } ({ $count } files already scanned)
+activity-remote-synced = Synced { $count } remote changes
+activity-remote-sync-error = Remote sync error
+activity-rebuild-complete = Metadata rebuilt: { $checked } checked, { $added } added, { $removed } removed
+activity-rebuild-failed = Could not rebuild metadata: { $error }
+activity-downloading = Downloading: { $name }
+activity-downloaded = Downloaded: { $name }
+activity-downloading-folder = Downloading folder: { $name }
+activity-prefetched = Prefetched { $count } files from frequent folders
+activity-cache-discarded = Cache discarded for { $name }: { $reason }
+activity-upload-error = Error uploading { $name }: { $error }
+activity-storage-full = Google Drive storage is full: new writes are rejected
+activity-backpressure-backlog = Writes held back: more than { $mb } MB waiting to upload
+activity-backpressure-disk = Writes held back: less than { $mb } MB free on the cache disk
+activity-backpressure-relieved = Local writes resumed
+activity-folder-created = Folder created: { $name }
+activity-file-created = File created: { $name }
+activity-workspace-file-created = Google document created: { $name }
+activity-restored-in-drive = Restored in Drive (deleted remotely): { $name }
+activity-move-reverted = Move blocked and reverted: { $name }
+activity-moved = Moved: { $from } → { $to }
+activity-renamed = Renamed: { $from } → { $to }
+activity-state-fixed = State fixed (no cache): { $name }
+activity-verified-unchanged = Verified unchanged: { $name }
+activity-uploaded = Uploaded: { $name }
+activity-shared-restored = Shared file restored: { $name } (no permission to delete)
+activity-file-deleted = File deleted: { $name }
+activity-file-purged = File permanently deleted: { $name }
+activity-already-deleted = File already deleted in Drive: { $name }
+activity-conflict-copy = Conflict resolved: { $name }
+activity-local-sync = Local sync: { $path }
+activity-freeing-folder = Freeing space in folder: { $name }
+activity-cannot-free = Cannot free: { $name } (pending upload)
+activity-online-only = Online only: { $name }
+activity-moving = Moving: { $name }
+activity-move-blocked = Move blocked: { $name }
+activity-local-created = Created locally: { $name }
+activity-modified = Modified: { $name }
+activity-deleted = Deleted: { $name }
+activity-conflict-resolved = Conflict resolved ({ $action }): { $name }
+activity-conflict-failed = Could not resolve the conflict for { $name }: { $error }
+activity-backend-reconnected = Service reconnected
+activity-backend-stopped = The service stopped: { $error }
+activity-mount-migrated =
+    { $count ->
+        [one] Moved 1 file from the mount folder into Drive
+       *[other] Moved { $count } files from the mount folder into Drive
+    }
+activity-mount-lost = The Drive folder stopped responding; remounting
+activity-mount-restored = Drive folder mounted again
+activity-imported-pins =
+    { $count ->
+        [one] Keeping 1 item kept on the previous computer
+       *[other] Keeping { $count } items kept on the previous computer
+    }
+activity-pins-from-other-device =
+    { $count ->
+        [one] Keeping 1 item that another device keeps on disk
+       *[other] Keeping { $count } items that other devices keep on disk
+    }
+activity-link-shared = Link shared: { $url }
+activity-starred = Starred: { $name }
+activity-unstarred = No longer starred: { $name }
+activity-version-restored = Version recovered: { $name }
+
+## Command line (state export and import)
+
+cli-export-done =
+    { $count ->
+        [one] State exported to { $path } (1 item kept on this device)
+       *[other] State exported to { $path } ({ $count } items kept on this device)
+    }
+cli-export-without-db = The metadata database was not included: the new computer will scan your Drive again. Use --include-db to skip the scan.
+cli-import-app-running = G-DriveXP is running. Quit it (tray menu) before importing.
+cli-import-done =
+    { $count ->
+        [one] State imported (1 item will be kept on this device). Start G-DriveXP and sign in again.
+       *[other] State imported ({ $count } items will be kept on this device). Start G-DriveXP and sign in again.
+    }
@@ -0,0 +1,395 @@
+# G-DriveXP: mensajes en español.
+# Mismos ids que ../en/main.ftl (el catálogo base).
+
+## Compartidos por la ventana y la bandeja
+
+open-in-files = Abrir en Archivos
+pause-sync = Pausar sincronización
+sync-paused = Sincronización pausada
+sync-resumed = Sincronización reanudada
+network-offline = Sin conexión
+network-metered = Conexión medida
+network-uploads-paused = { $reason }: subidas en pausa
+backpressure-backlog = Demasiados cambios pendientes de subir
+backpressure-disk = Poco espacio libre en disco
+backpressure-writes-held = { $reason }: escrituras frenadas
+
+## Bandeja
+
+tray-all-good = Todo en orden
+tray-pending-files =
+    { $count ->
+        [one] 1 archivo pendiente
+       *[other] { $count } archivos pendientes
+    }
+tray-changes-applied = { $applied }/{ $detected } cambios aplicados
+tray-uploading = subiendo { $name } { $percent }%
+tray-downloading = descargando { $name } { $percent }%
+tray-upload-errors = { $count } con error
+tray-recent-activity = Actividad reciente
+tray-open-panel = Abrir Panel
+tray-sync-now = Sincronizar ahora
+tray-quit = Salir
+
+## Tiempos relativos en la lista de actividad
+
+time-now = ahora
+time-minutes-short = { $count }m
+time-hours-short = { $count }h
+
+## Estado del arranque del backend
+
+status-initializing = Inicializando backend...
+status-checking-auth = Verificando autenticación...
+status-credentials-missing = Error: credentials.json no encontrado
+status-auth-ok = Autenticación correcta
+status-offline-mode = Sin conexión: modo offline
+status-loading-db = Cargando base de datos...
+status-fetching-root = Obteniendo ID de carpeta raíz...
+status-loading-structure = Cargando estructura inicial...
+status-repairing-metadata = Recuperando metadatos...
+status-syncing-recent = Sincronizando cambios recientes...
+status-mounted = Sistema de archivos montado y activo
+status-shutting-down = Cerrando subsistemas...
+status-unmounting = Desmontando...
+status-remounting = Se perdió la carpeta de Drive, remontando (intento { $attempt })...
+
+## Ventana principal
+
+gui-starting = Iniciando G-DriveXP...
+gui-subtitle-main = Cliente de Google Drive
+gui-status = Estado
+gui-connection = Conexión
+gui-connected = Conectado a Google Drive
+gui-disconnected = Desconectado
+gui-read-only-refused = Modo solo lectura: no se modifica nada en Google Drive
+gui-scanning = Escaneando...
+gui-scanned-of = Escaneados { $count } de ~{ $expected } archivos (página { $pages })
+gui-scanned = Escaneados { $count } archivos (página { $pages })
+gui-changes-queued = { $reason }: { $count } cambios en cola
+gui-changes-applied = { $applied }/{ $detected } Cambios aplicados
+gui-changes-pending = { $count } Cambios pendientes
+gui-syncing = Sincronizando...
+gui-all-synced = Sin Novedad, mi general
+gui-recent-activity = Actividad Reciente
+gui-overall-status = Estado General
+gui-search-drive = Buscar en Drive
+gui-search-placeholder = Nombre de archivo o carpeta
+gui-no-results = Sin resultados
+gui-my-drive = Mi unidad
+gui-files = Archivos
+gui-share-link = Copiar enlace para compartir
+gui-share-link-subtitle = Crea un enlace de solo lectura y lo copia al portapapeles
+gui-properties-and-versions = Propiedades y versiones
+gui-properties-and-versions-subtitle = Consulta el historial de un archivo y recupera versiones anteriores
+gui-settings = Configuración
+gui-appearance = Apariencia
+gui-appearance-subtitle = Esquema de color de la ventana
+appearance-system = Según el sistema
+appearance-light = Claro
+appearance-dark = Oscuro
+gui-autostart = Iniciar al iniciar sesión
+gui-autostart-subtitle = Se abre en la bandeja del sistema al entrar en la sesión
+gui-autostart-failed = No se pudo cambiar el inicio automático: { $error }
+gui-show-computers = Mostrar ordenadores
+gui-show-computers-subtitle = Copias de otros equipos de la sección "Ordenadores" de Drive, en Computers/
+gui-sync-now-subtitle = Sube y descarga los cambios sin esperar al próximo ciclo
+gui-sync-now-window = Solo se sincroniza automáticamente de { $start } a { $end }
+gui-sync-now-manual = La sincronización automática está desactivada: los cambios se sincronizan al pulsar aquí
+gui-sync-now-requested = Sincronizando…
+gui-rebuild-metadata = Reconstruir metadatos
+gui-rebuild-metadata-subtitle = Compara todos los archivos de Drive con la copia local y corrige las diferencias, conservando los archivos descargados
+gui-rebuild-metadata-requested = Reconstruyendo metadatos…
+gui-pause-sync-subtitle = Detiene temporalmente la sincronización
+gui-diagnostics = Diagnóstico
+gui-view-log = Ver registro
+gui-view-log-subtitle = Últimos mensajes del log de la aplicación
+gui-log = Registro
+gui-log-read-failed = No se pudo leer el registro: { $error }
+gui-export-diagnostics = Exportar diagnóstico
+gui-export-diagnostics-subtitle = Empaqueta logs y configuración para reportar un error
+gui-diagnostics-saved = Diagnóstico guardado en { $path }
+gui-diagnostics-failed = No se pudo exportar el diagnóstico: { $error }
+gui-local-folders = Carpetas Locales Sincronizadas
+gui-local-folders-description = Seleccione directorios fuera del espejo de Google Drive para sincronizar
+gui-add-folder = Añadir Carpeta...
+gui-no-extra-folders = Ninguna carpeta adicional configurada
+gui-dir-syncing = Sincronizando activamente
+gui-dir-paused = Sincronización pausada para esta carpeta
+gui-select-sync-folder = Seleccionar carpeta para sincronizar
+gui-account = Cuenta
+gui-logout = Cerrar sesión
+gui-logout-subtitle = Desvincula esta cuenta de Google
+gui-hard-reset = Hard Reset
+gui-hard-reset-subtitle = BORRADO TOTAL: Reinicia DB, Cache y Archivos.
+gui-hard-reset-running = Purgando sistema, por favor espere...
+gui-login-prompt = Inicie sesión para continuar
+gui-login = Iniciar Sesión
+gui-login-generating = Generando enlace...
+
+## Uso de la caché
+
+gui-cache = Caché
+gui-cache-description = Contenido descargado bajo demanda de los archivos solo online
+gui-cache-total = Tamaño total
+gui-cache-on-disk = Espacio en disco
+gui-cache-pinned = Anclado sin conexión
+gui-cache-by-folder = Por carpeta
+gui-cache-root-files = Otros archivos
+gui-cache-folder-pinned = { $size } anclado
+gui-cache-empty = La caché está vacía
+gui-cache-evict-unpinned = Liberar datos no anclados
+gui-cache-evict-unpinned-subtitle = Conserva los archivos anclados y los cambios sin subir
+gui-cache-clear = Vaciar caché
+gui-cache-clear-subtitle = Los archivos se vuelven a descargar al abrirlos
+gui-cache-clear-confirm = ¿Vaciar toda la caché?
+gui-cache-clear-confirm-body = Los archivos anclados también se volverán a descargar al abrirlos. Los cambios sin subir se conservan.
+gui-cancel = Cancelar
+gui-cache-freed =
+    { $kept ->
+        [0] { $size } liberados
+        [one] { $size } liberados (se conserva 1 archivo con cambios pendientes)
+       *[other] { $size } liberados (se conservan { $kept } archivos con cambios pendientes)
+    }
+gui-cache-failed = No se pudo liberar la caché: { $error }
+gui-trash = Papelera local
+gui-trash-description = Eliminado en esta computadora. Sigue en la papelera de Google Drive hasta que lo elimines definitivamente.
+gui-delete-permanently = Eliminar definitivamente
+gui-delete-permanently-confirm = ¿Eliminar «{ $name }» definitivamente?
+gui-delete-permanently-confirm-body = Se elimina de Google Drive sin pasar por la papelera. No se puede deshacer.
+gui-delete-permanently-queued = «{ $name }» se eliminará definitivamente
+gui-delete-permanently-failed = No se pudo eliminar: { $error }
+
+## Supervisor del backend
+
+gui-backend-reconnecting = Reconectando el servicio (intento { $attempt })...
+gui-backend-retrying = Sin servicio: { $error }. Nuevo intento en { $seconds } s
+gui-backend-needs-action = Sin servicio: { $error }. Corrígelo y pulsa Reconectar
+gui-backend-crashed = El servicio se detuvo por un error: { $error }
+gui-backend-retry-status = Sin servicio, reintento en { $seconds } s
+gui-backend-stopped = Servicio detenido
+gui-reconnect = Reconectar
+gui-restart = Reiniciar
+gui-reconnecting = Reconectando...
+
+gui-storage-full = Tu almacenamiento de Google Drive está lleno. Los archivos nuevos o modificados no se pueden guardar hasta que liberes espacio.
+gui-storage-full-retry = Reintentar
+
+## Problemas de configuración
+
+gui-config-invalid = Configuración inválida
+gui-config-invalid-body = Corrige estos problemas en { $path } (o en las opciones de línea de comandos) y pulsa Reconectar:
+gui-open-config = Abrir archivo de configuración
+gui-close = Cerrar
+gui-mount-not-empty = La carpeta de montaje no está vacía
+gui-mount-not-empty-body =
+    { $count ->
+        [one] { $path } contiene un elemento que quedaría oculto al montar Google Drive ahí. Elige otra carpeta o muévelo a tu Drive:
+       *[other] { $path } contiene { $count } elementos que quedarían ocultos al montar Google Drive ahí. Elige otra carpeta o muévelos a tu Drive:
+    }
+gui-mount-not-empty-more = … y { $count } más
+gui-mount-choose-other = Elegir otra carpeta
+gui-mount-migrate = Mover a Drive
+gui-mount-point-failed = No se pudo guardar la carpeta de montaje: { $error }
+config-problem-unreadable = { $path } no se puede leer: { $error }
+config-problem-not-a-directory = { $field }: { $path } no es un directorio
+config-problem-not-writable = { $field }: sin permiso de escritura en { $path }
+config-problem-nested = { $field } ({ $path }) no puede estar dentro de { $outer }
+config-problem-zero-interval = sync_interval_secs debe ser mayor que 0
+config-problem-cache-too-small = max_cache_size_mb es { $mb } MB; el mínimo es { $min } MB
+config-problem-prefetch-exceeds-cache = prefetch_budget_mb ({ $prefetch } MB) no puede superar max_cache_size_mb ({ $cache } MB)
+config-problem-invalid-ignore-pattern = ignore_patterns: "{ $pattern }" no es un patrón válido (un solo nombre con *, ? o [..], sin /)
+config-problem-invalid-sync-schedule = sync_schedule: la franja necesita dos horas distintas en formato HH:MM (start y end)
+config-problem-metrics-not-loopback = metrics_listen ({ $addr }) debe ser una dirección de loopback (127.0.0.1 o ::1): el endpoint de métricas no tiene autenticación
+
+## Vista de actividad
+
+gui-downloading-heading = Descargando de Google Drive
+gui-uploading-heading = Subiendo a Google Drive
+gui-problems = Problemas
+gui-history = Historial
+gui-no-recent-activity = Sin actividad reciente
+gui-no-retry = No se reintentará
+gui-retry-in = Reintento { $attempt } en { $minutes } min
+gui-retry-pending = Reintento { $attempt } pendiente
+gui-retry-now = Reintentar ahora
+
+## Centro de conflictos
+
+gui-conflicts = Conflictos
+gui-conflicts-subtitle = Archivos editados a la vez aquí y en Drive
+gui-conflicts-unresolved = { $count } sin resolver
+gui-no-conflicts = Sin conflictos
+gui-no-conflicts-description = Ningún archivo se editó a la vez aquí y en Drive
+gui-conflicts-explanation = Estos archivos cambiaron en Drive mientras se editaban aquí. La versión local se guardó como copia: elige con cuál quedarte.
+gui-conflict-copy = Copia local: { $name }
+gui-conflict-edited = Editado aquí { $local } · en Drive { $remote }
+gui-unknown-date = desconocida
+gui-show-diff = Ver diferencias
+gui-differences = Diferencias
+gui-diff-failed = No se pueden mostrar las diferencias: { $error }
+gui-keep-local = Quedarse con la versión local
+gui-keep-remote = Quedarse con la versión de Drive
+gui-dismiss-conflict = Descartar aviso (conservar ambos archivos)
+gui-conflict-kept-local = se conserva la versión local
+gui-conflict-kept-remote = se conserva la versión de Drive
+gui-conflict-kept-both = se conservan ambos archivos
+gui-conflict-failed = No se pudo resolver el conflicto: { $error }
+
+## Compartir, propiedades y versiones
+
+gui-select-file-to-share = Seleccionar archivo para compartir
+gui-link-copied = Enlace copiado al portapapeles
+gui-link-failed = Error creando enlace: { $error }
+gui-select-file = Seleccionar archivo
+gui-properties = Propiedades
+gui-file = Archivo
+gui-name = Nombre
+gui-location = Ubicación
+gui-size = Tamaño
+gui-starred = Destacado
+gui-starred-subtitle = Visible también en la carpeta Starred
+gui-starred-added = { $name } añadido a destacados
+gui-starred-removed = { $name } quitado de destacados
+gui-starred-failed = No se pudo cambiar el destacado: { $error }
+gui-drive-details = Google Drive
+gui-md5 = Suma MD5
+gui-owner = Propietario
+gui-created = Creado
+gui-created-by = Creado en
+gui-origin-this-device = Este dispositivo
+gui-origin-other-device = Otro dispositivo con G-DriveXP
+gui-description = Descripción
+gui-description-saved = Descripción de { $name } actualizada
+gui-description-failed = No se pudo cambiar la descripción: { $error }
+gui-folder-color = Color de la carpeta
+gui-owner-me = Tú
+gui-sharing = Compartido
+gui-sharing-shared = Compartido con otras personas
+gui-sharing-private = Solo tú
+gui-sync-state = Estado de sincronización
+gui-availability = Disponibilidad
+gui-availability-local = Siempre en este dispositivo
+gui-availability-online = Solo online
+gui-sync-synced = Sincronizado
+gui-sync-cloud-only = Solo online
+gui-sync-local-only = Pendiente de subir
+gui-sync-syncing = Subiendo
+gui-sync-error = Error de subida
+gui-sync-downloading = Descargando ({ $percent }%)
+gui-sync-uploading = Subiendo ({ $percent }%)
+gui-cached = En caché
+gui-cached-percent = { $percent }% descargado
+gui-not-available = No disponible
+gui-open-in-browser = Abrir en el navegador
+gui-copy-share-link = Copiar enlace para compartir
+gui-pin-offline = Mantener siempre en este dispositivo
+gui-pinned-offline = { $name } se mantendrá en este dispositivo
+gui-pin-failed = No se pudo mantener en este dispositivo: { $error }
+gui-free-up-space = Liberar espacio
+gui-freed-space = { $name } queda solo online: se liberó su contenido descargado
+gui-free-failed = No se pudo liberar espacio: { $error }
+gui-browser-failed = No se pudo abrir el archivo en el navegador: { $error }
+gui-versions = Versiones
+gui-versions-description = Restaurar sube la versión elegida como una nueva; la actual sigue en el historial
+gui-version = Versión { $number }
+gui-version-current = Versión { $number } (actual)
+gui-no-versions = Sin versiones anteriores
+gui-save-as-copy = Guardar como copia
+gui-restore-version = Restaurar esta versión
+gui-loading-versions = Consultando versiones...
+gui-versions-count =
+    { $count ->
+        [one] 1 versión
+       *[other] { $count } versiones
+    }
+gui-versions-failed = No se pudieron consultar las versiones: { $error }
+gui-version-restored = Versión recuperada en { $name }
+gui-version-restore-failed = No se pudo recuperar la versión: { $error }
+
+## Entradas del historial de actividad (ventana y bandeja)
+
+activity-offline-queued = Sin conexión: los cambios locales quedan en cola
+activity-connection-restored = Conexión restablecida: sincronizando cambios en cola
+activity-metered-paused = Conexión medida: subidas y precarga en pausa
+activity-metered-resumed = Conexión sin límite de datos: sincronización reanudada
+activity-scan-complete = Escaneo completado: { $count } archivos
+activity-scan-resumed = Reanudando el escaneo interrumpido desde la página { $page } ({ $count } archivos ya escaneados)
+activity-remote-synced = Sincronizados { $count } cambios remotos
+activity-remote-sync-error = Error en sincronización remota
+activity-rebuild-complete = Metadatos reconstruidos: { $checked } revisados, { $added } añadidos, { $removed } eliminados
+activity-rebuild-failed = No se pudieron reconstruir los metadatos: { $error }
+activity-downloading = Descargando: { $name }
+activity-downloaded = Descargado: { $name }
+activity-downloading-folder = Descargando carpeta: { $name }
+activity-prefetched = Precargados { $count } archivos de carpetas frecuentes
+activity-cache-discarded = Caché descartada para { $name }: { $reason }
+activity-upload-error = Error subiendo { $name }: { $error }
+activity-storage-full = Almacenamiento de Google Drive lleno: no se aceptan escrituras nuevas
+activity-backpressure-backlog = Escrituras frenadas: más de { $mb } MB pendientes de subir
+activity-backpressure-disk = Escrituras frenadas: quedan menos de { $mb } MB libres en el disco de la caché
+activity-backpressure-relieved = Escrituras locales reanudadas
+activity-folder-created = Carpeta creada: { $name }
+activity-file-created = Archivo creado: { $name }
+activity-workspace-file-created = Documento de Google creado: { $name }
+activity-restored-in-drive = Restaurado en Drive (eliminado en remoto): { $name }
+activity-move-reverted = Movimiento bloqueado y revertido: { $name }
+activity-moved = Movido: { $from } → { $to }
+activity-renamed = Renombrado: { $from } → { $to }
+activity-state-fixed = Estado corregido (sin caché): { $name }
+activity-verified-unchanged = Verificado sin cambios: { $name }
+activity-uploaded = Subido: { $name }
+activity-shared-restored = Archivo compartido restaurado: { $name } (sin permisos de eliminación)
+activity-file-deleted = Archivo eliminado: { $name }
+activity-file-purged = Archivo eliminado definitivamente: { $name }
+activity-already-deleted = Archivo ya eliminado en Drive: { $name }
+activity-conflict-copy = Conflicto resuelto: { $name }
+activity-local-sync = Local sync: { $path }
+activity-freeing-folder = Liberando espacio en carpeta: { $name }
+activity-cannot-free = No se puede liberar: { $name } (Pendiente a subir)
+activity-online-only = Solo online: { $name }
+activity-moving = Moviendo: { $name }
+activity-move-blocked = Movimiento bloqueado: { $name }
+activity-local-created = Local Creado: { $name }
+activity-modified = Modificado: { $name }
+activity-deleted = Eliminado: { $name }
+activity-conflict-resolved = Conflicto resuelto ({ $action }): { $name }
+activity-conflict-failed = No se pudo resolver el conflicto de { $name }: { $error }
+activity-backend-reconnected = Servicio reconectado
+activity-backend-stopped = El servicio se detuvo: { $error }
+activity-mount-migrated =
+    { $count ->
+        [one] Se movió 1 archivo de la carpeta de montaje a Drive
+       *[other] Se movieron { $count } archivos de la carpeta de montaje a Drive
+    }
+activity-mount-lost = La carpeta de Drive dejó de responder; remontando
+activity-mount-restored = Carpeta de Drive montada de nuevo
+activity-imported-pins =
+    { $count ->
+        [one] Manteniendo 1 elemento que se mantenía en el equipo anterior
+       *[other] Manteniendo { $count } elementos que se mantenían en el equipo anterior
+    }
+activity-pins-from-other-device =
+    { $count ->
+        [one] Manteniendo 1 elemento que otro dispositivo guarda en disco
+       *[other] Manteniendo { $count } elementos que otros dispositivos guardan en disco
+    }
+activity-link-shared = Enlace compartido: { $url }
+activity-starred = Destacado: { $name }
+activity-unstarred = Ya no destacado: { $name }
+activity-version-restored = Versión recuperada: { $name }
+
+## Línea de comandos (exportar e importar el estado)
+
+cli-export-done =
+    { $count ->
+        [one] Estado exportado a { $path } (1 elemento mantenido en este dispositivo)
+       *[other] Estado exportado a { $path } ({ $count } elementos mantenidos en este dispositivo)
+    }
+cli-export-without-db = No se incluyó la base de datos de metadatos: el equipo nuevo volverá a escanear tu Drive. Usa --include-db para evitar el escaneo.
+cli-import-app-running = G-DriveXP está en marcha. Ciérralo (menú de la bandeja) antes de importar.
+cli-import-done =
+    { $count ->
+        [one] Estado importado (1 elemento se mantendrá en este dispositivo). Inicia G-DriveXP y vuelve a iniciar sesión.
+       *[other] Estado importado ({ $count } elementos se mantendrán en este dispositivo). Inicia G-DriveXP y vuelve a iniciar sesión.
+    }
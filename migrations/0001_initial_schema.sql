@@ -1,3 +1,10 @@
+-- Esquema base versionado (v1).
+-- Las bases de datos anteriores al sistema de migraciones se convierten a este
+-- esquema en `MetadataRepository::convert_legacy_schema` antes de aplicarlo,
+-- por eso todas las sentencias usan IF NOT EXISTS.
+-- NOTA: journal_mode/synchronous se configuran en la conexión (no en migraciones,
+-- que se ejecutan dentro de una transacción).
+
 -- Mapeo bidireccional GDrive ID <-> Inode POSIX
 CREATE TABLE IF NOT EXISTS inodes (
     inode INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -28,7 +35,10 @@ CREATE TABLE IF NOT EXISTS attrs (
     is_dir BOOLEAN NOT NULL,
     mime_type TEXT,
     can_move BOOLEAN DEFAULT 1,
+    shared BOOLEAN DEFAULT 0,
+    owned_by_me BOOLEAN DEFAULT 1,
     shortcut_target_id TEXT,
+    web_view_link TEXT,  -- URL en drive.google.com (webViewLink)
     FOREIGN KEY (inode) REFERENCES inodes(inode)
 );
 
@@ -40,10 +50,12 @@ CREATE TABLE IF NOT EXISTS sync_state (
     md5_checksum TEXT,
     deleted_at INTEGER DEFAULT NULL,  -- Timestamp de soft delete
     remote_md5 TEXT,  -- MD5 de la versión remota conocida (para detección de conflictos)
+    availability TEXT DEFAULT 'online_only',  -- 'online_only' o 'local_online'
+    uploading BOOLEAN DEFAULT 0,  -- Upload en curso (estado Syncing para IPC)
     FOREIGN KEY (inode) REFERENCES inodes(inode)
 );
 CREATE INDEX IF NOT EXISTS idx_dirty ON sync_state(inode) WHERE dirty=1;
-
+CREATE INDEX IF NOT EXISTS idx_sync_deleted ON sync_state(deleted_at) WHERE deleted_at IS NOT NULL;
 
 -- Token de sincronización para changes.list
 CREATE TABLE IF NOT EXISTS sync_meta (
@@ -63,9 +75,14 @@ CREATE TABLE IF NOT EXISTS dentry_deleted (
 );
 CREATE INDEX IF NOT EXISTS idx_tombstone_deleted_at ON dentry_deleted(deleted_at);
 
--- Optimizaciones de WAL (Write-Ahead Logging) para concurrencia
-PRAGMA journal_mode=WAL;
-PRAGMA synchronous=NORMAL;
+-- Rangos de bytes descargados en la caché de cada archivo
+CREATE TABLE IF NOT EXISTS file_cache_chunks (
+    inode INTEGER NOT NULL,
+    start_offset INTEGER NOT NULL,
+    end_offset INTEGER NOT NULL,
+    PRIMARY KEY (inode, start_offset),
+    FOREIGN KEY (inode) REFERENCES inodes(inode) ON DELETE CASCADE
+);
 
 -- Directorios locales a sincronizar con Google Drive
 CREATE TABLE IF NOT EXISTS local_sync_dirs (
@@ -78,6 +95,31 @@ CREATE TABLE IF NOT EXISTS local_sync_dirs (
 );
 CREATE INDEX IF NOT EXISTS idx_local_sync_enabled ON local_sync_dirs(enabled) WHERE enabled=1;
 
+-- Archivos de los directorios Local Sync
+CREATE TABLE IF NOT EXISTS local_sync_files (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    sync_dir_id INTEGER NOT NULL REFERENCES local_sync_dirs(id) ON DELETE CASCADE,
+    relative_path TEXT NOT NULL,
+    is_dir INTEGER NOT NULL DEFAULT 0,
+
+    availability TEXT NOT NULL DEFAULT 'local_online',
+
+    local_mtime INTEGER,
+    local_size INTEGER,
+    local_md5 TEXT,
+
+    gdrive_id TEXT,
+    remote_md5 TEXT,
+    remote_mtime INTEGER,
+
+    dirty INTEGER NOT NULL DEFAULT 1,
+    last_synced INTEGER,
+
+    UNIQUE(sync_dir_id, relative_path)
+);
+CREATE INDEX IF NOT EXISTS idx_local_sync_files_dirty ON local_sync_files(dirty) WHERE dirty = 1;
+CREATE INDEX IF NOT EXISTS idx_local_sync_files_gdrive ON local_sync_files(gdrive_id);
+
 -- Contadores pre-calculados de estado por directorio (Protocolo "Burbujeo de Estados")
 -- Permite respuestas O(1) a consultas IPC de estado de directorios
 CREATE TABLE IF NOT EXISTS dir_counters (
@@ -0,0 +1,26 @@
+-- Índice de búsqueda de texto completo sobre los nombres de archivo (dentry.name)
+-- Tabla FTS5 de contenido externo: el texto vive en `dentry` y los triggers
+-- mantienen el índice sincronizado por rowid.
+CREATE VIRTUAL TABLE IF NOT EXISTS dentry_fts USING fts5(
+    name,
+    content = 'dentry',
+    content_rowid = 'rowid',
+    tokenize = "unicode61 remove_diacritics 2",
+    prefix = '2 3'
+);
+
+CREATE TRIGGER IF NOT EXISTS dentry_fts_ai AFTER INSERT ON dentry BEGIN
+    INSERT INTO dentry_fts(rowid, name) VALUES (new.rowid, new.name);
+END;
+
+CREATE TRIGGER IF NOT EXISTS dentry_fts_ad AFTER DELETE ON dentry BEGIN
+    INSERT INTO dentry_fts(dentry_fts, rowid, name) VALUES ('delete', old.rowid, old.name);
+END;
+
+CREATE TRIGGER IF NOT EXISTS dentry_fts_au AFTER UPDATE OF name ON dentry BEGIN
+    INSERT INTO dentry_fts(dentry_fts, rowid, name) VALUES ('delete', old.rowid, old.name);
+    INSERT INTO dentry_fts(rowid, name) VALUES (new.rowid, new.name);
+END;
+
+-- Indexar las entradas existentes
+INSERT INTO dentry_fts(dentry_fts) VALUES ('rebuild');
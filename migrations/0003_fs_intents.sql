@@ -0,0 +1,8 @@
+-- Intent log de mutaciones FUSE (write-ahead).
+-- Cada operación multi-paso registra su intención antes de tocar caché o DB y la
+-- borra al terminar; las filas que sobreviven a un crash se reaplican al arrancar.
+CREATE TABLE IF NOT EXISTS fs_intents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    payload TEXT NOT NULL,           -- JSON de `fuse::journal::FsIntent`
+    created_at INTEGER NOT NULL
+);
@@ -0,0 +1,6 @@
+-- Nombres duplicados en Drive.
+-- Drive permite varios archivos con el mismo nombre en una carpeta, pero `dentry`
+-- es única por (parent_inode, name). Los duplicados se exponen con un sufijo
+-- determinista (`informe (2).pdf`) y aquí se guarda el nombre real en Drive.
+-- NULL = el nombre local coincide con el remoto.
+ALTER TABLE dentry ADD COLUMN remote_name TEXT;
@@ -0,0 +1,4 @@
+-- Fracción de segundo del mtime (nanosegundos).
+-- Drive guarda modifiedTime con milisegundos; sin este campo cada sincronización
+-- truncaba el timestamp y las fechas divergían entre máquinas.
+ALTER TABLE attrs ADD COLUMN mtime_nsec INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,7 @@
+-- Máquina de estados de subida por inode.
+-- queued → uploading → synced | error (con el mensaje en last_error).
+-- `dirty` sigue siendo la fuente de verdad de "hay cambios pendientes";
+-- `status` describe en qué punto del ciclo del uploader está cada archivo.
+ALTER TABLE sync_state ADD COLUMN status TEXT NOT NULL DEFAULT 'synced';
+ALTER TABLE sync_state ADD COLUMN last_error TEXT;
+UPDATE sync_state SET status = 'queued' WHERE dirty = 1;
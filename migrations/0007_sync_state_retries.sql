@@ -0,0 +1,7 @@
+-- Reintentos de subida por inode.
+-- retry_count: intentos fallidos consecutivos (se reinicia al subir o al volver a modificar).
+-- next_retry_at: epoch en segundos antes del cual el uploader no vuelve a intentar.
+-- error_permanent: el último error no se resuelve reintentando; se espera al usuario.
+ALTER TABLE sync_state ADD COLUMN retry_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE sync_state ADD COLUMN next_retry_at INTEGER;
+ALTER TABLE sync_state ADD COLUMN error_permanent INTEGER NOT NULL DEFAULT 0;
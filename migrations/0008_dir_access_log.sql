@@ -0,0 +1,9 @@
+-- Registro de accesos a directorios para el prefetcher de caché.
+-- Una fila por directorio: último acceso (epoch en segundos) y accesos acumulados.
+CREATE TABLE IF NOT EXISTS dir_access_log (
+    inode INTEGER PRIMARY KEY,
+    last_access INTEGER NOT NULL,
+    access_count INTEGER NOT NULL DEFAULT 1,
+    FOREIGN KEY (inode) REFERENCES inodes(inode)
+);
+CREATE INDEX IF NOT EXISTS idx_dir_access_last ON dir_access_log(last_access);
@@ -0,0 +1,3 @@
+-- Archivos destacados en Drive (campo `starred`), para la carpeta virtual Starred.
+ALTER TABLE attrs ADD COLUMN starred BOOLEAN NOT NULL DEFAULT 0;
+CREATE INDEX IF NOT EXISTS idx_attrs_starred ON attrs(starred) WHERE starred = 1;
@@ -0,0 +1,10 @@
+-- Permisos y propietarios de cada archivo según Drive.
+-- can_edit / can_trash: capabilities.canEdit / canTrash (sin dato = permitido).
+-- owners: JSON con los propietarios ("Nombre <correo>").
+CREATE TABLE IF NOT EXISTS file_capabilities (
+    inode INTEGER PRIMARY KEY,
+    can_edit BOOLEAN NOT NULL DEFAULT 1,
+    can_trash BOOLEAN NOT NULL DEFAULT 1,
+    owners TEXT NOT NULL DEFAULT '[]',
+    FOREIGN KEY (inode) REFERENCES inodes(inode)
+);
@@ -0,0 +1,5 @@
+-- Archivos creados localmente que aún no existen en Drive.
+-- El gdrive_id puede ser un ID reservado con files.generateIds (ya definitivo)
+-- o un `temp_<uuid>` provisional; este flag, no el prefijo, indica que falta crearlo.
+ALTER TABLE inodes ADD COLUMN pending_create BOOLEAN NOT NULL DEFAULT 0;
+UPDATE inodes SET pending_create = 1 WHERE gdrive_id LIKE 'temp\_%' ESCAPE '\';
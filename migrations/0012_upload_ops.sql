@@ -0,0 +1,11 @@
+-- Diario de operaciones locales pendientes de subir, en orden de llegada.
+-- El uploader sube el estado actual de cada inode, pero ordena los inodes por
+-- su última operación y serializa renombrados y eliminaciones.
+CREATE TABLE IF NOT EXISTS upload_ops (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    inode INTEGER NOT NULL,
+    op TEXT NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (CAST(strftime('%s', 'now') AS INTEGER))
+);
+
+CREATE INDEX IF NOT EXISTS idx_upload_ops_inode ON upload_ops(inode);
@@ -0,0 +1,17 @@
+-- Conflictos de sincronización: la edición local se subió como copia
+-- (`nombre (Conflicto local ...)`) porque el original cambió en Drive.
+-- Quedan pendientes hasta que el usuario elige una versión o los descarta.
+CREATE TABLE IF NOT EXISTS conflicts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    inode INTEGER NOT NULL,
+    original_gdrive_id TEXT NOT NULL,
+    conflict_gdrive_id TEXT NOT NULL,
+    conflict_name TEXT NOT NULL,
+    local_mtime INTEGER NOT NULL,
+    remote_mtime INTEGER,
+    created_at INTEGER NOT NULL DEFAULT (CAST(strftime('%s', 'now') AS INTEGER)),
+    resolved_at INTEGER,
+    resolution TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_conflicts_unresolved ON conflicts(created_at) WHERE resolved_at IS NULL;
@@ -0,0 +1,3 @@
+-- Eliminación definitiva pedida por el usuario (papelera local `.Trash` o GUI):
+-- el uploader llama a files.delete en vez de enviar el tombstone a la papelera.
+ALTER TABLE sync_state ADD COLUMN purge_requested BOOLEAN NOT NULL DEFAULT 0;
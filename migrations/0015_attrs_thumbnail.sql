@@ -0,0 +1,6 @@
+-- Miniaturas de Drive para la caché de miniaturas del escritorio (Freedesktop).
+-- `thumbnail_link` es el `thumbnailLink` de Drive (caduca en horas: se renueva
+-- con files.get). `thumbnail_mtime` es el mtime del archivo cuando se escribió
+-- su miniatura; si el archivo cambia, se vuelve a descargar.
+ALTER TABLE attrs ADD COLUMN thumbnail_link TEXT;
+ALTER TABLE attrs ADD COLUMN thumbnail_mtime INTEGER;
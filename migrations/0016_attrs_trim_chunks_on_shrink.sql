@@ -0,0 +1,11 @@
+-- Al reducir `attrs.size` (truncate en FUSE, archivo más corto en Drive,
+-- escaneo, recuperación del journal) los chunks por encima del nuevo final
+-- dejan de ser contenido del archivo: se recortan en la misma sentencia para
+-- que ninguna lectura posterior los dé por cacheados.
+CREATE TRIGGER IF NOT EXISTS attrs_trim_chunks_on_shrink
+AFTER UPDATE OF size ON attrs
+WHEN new.size < old.size
+BEGIN
+    DELETE FROM file_cache_chunks WHERE inode = new.inode AND start_offset >= new.size;
+    UPDATE file_cache_chunks SET end_offset = new.size - 1 WHERE inode = new.inode AND end_offset >= new.size;
+END;
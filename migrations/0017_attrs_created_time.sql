@@ -0,0 +1,4 @@
+-- Fecha de creación en Drive (`createdTime`, epoch en segundos). NULL si aún
+-- no se conoce: archivos cargados antes de esta migración (se rellena con el
+-- siguiente cambio remoto o la próxima reconstrucción de metadatos).
+ALTER TABLE attrs ADD COLUMN created_time INTEGER;
@@ -0,0 +1,6 @@
+-- Marcas `appProperties` de G-DriveXP (`gdrive::app_properties`): instalación
+-- que creó el archivo (NULL si no lo creó G-DriveXP) y si algún dispositivo lo
+-- mantiene descargado. Se rellenan con el siguiente cambio remoto o la próxima
+-- reconstrucción de metadatos.
+ALTER TABLE attrs ADD COLUMN origin_client TEXT;
+ALTER TABLE attrs ADD COLUMN pin_hint INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- Descripción (`description`) y color de carpeta (`folderColorRgb`, "#rrggbb")
+-- de Drive. NULL si no tienen o aún no se conocen: se rellenan con el
+-- siguiente cambio remoto o la próxima reconstrucción de metadatos.
+ALTER TABLE attrs ADD COLUMN description TEXT;
+ALTER TABLE attrs ADD COLUMN folder_color TEXT;
@@ -0,0 +1,4 @@
+-- Marca de anclado cambiada aquí y aún no publicada en Drive: mientras está
+-- pendiente, el valor remoto (todavía el viejo) no la sobrescribe y el
+-- sincronizador reintenta publicarla en cada ciclo.
+ALTER TABLE attrs ADD COLUMN pin_hint_pending INTEGER NOT NULL DEFAULT 0;
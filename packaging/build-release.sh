@@ -54,6 +54,7 @@ mkdir -p "$DIST_DIR/${NAME}-${VERSION}"
 cp "$REPO_ROOT/target/release/g-drive-xp" "$DIST_DIR/${NAME}-${VERSION}/"
 cp "$NAUTILUS_DIR/target/release/libgdrivexp_nautilus.so" "$DIST_DIR/${NAME}-${VERSION}/"
 cp "$REPO_ROOT/data/org.gnome.FedoraDrive.desktop" "$DIST_DIR/${NAME}-${VERSION}/"
+cp "$REPO_ROOT/data/org.gnome.FedoraDrive.search-provider.ini" "$DIST_DIR/${NAME}-${VERSION}/"
 cp "$REPO_ROOT/assets/icons/org.gnome.FedoraDrive-48.png" "$DIST_DIR/${NAME}-${VERSION}/"
 cp "$REPO_ROOT/assets/icons/org.gnome.FedoraDrive-128.png" "$DIST_DIR/${NAME}-${VERSION}/"
 cp "$REPO_ROOT/assets/icons/org.gnome.FedoraDrive-256.png" "$DIST_DIR/${NAME}-${VERSION}/"
@@ -29,6 +29,7 @@ bidireccionalmente, e incluye una extensión de Nautilus con emblemas de estado.
 install -Dm755 g-drive-xp %{buildroot}%{_bindir}/g-drive-xp
 install -Dm755 libgdrivexp_nautilus.so %{buildroot}%{_libdir}/nautilus/extensions-4/libgdrivexp-nautilus.so
 install -Dm644 org.gnome.FedoraDrive.desktop %{buildroot}%{_datadir}/applications/org.gnome.FedoraDrive.desktop
+install -Dm644 org.gnome.FedoraDrive.search-provider.ini %{buildroot}%{_datadir}/gnome-shell/search-providers/org.gnome.FedoraDrive.search-provider.ini
 install -Dm644 org.gnome.FedoraDrive-48.png %{buildroot}%{_datadir}/icons/hicolor/48x48/apps/org.gnome.FedoraDrive.png
 install -Dm644 org.gnome.FedoraDrive-128.png %{buildroot}%{_datadir}/icons/hicolor/128x128/apps/org.gnome.FedoraDrive.png
 install -Dm644 org.gnome.FedoraDrive-256.png %{buildroot}%{_datadir}/icons/hicolor/256x256/apps/org.gnome.FedoraDrive.png
@@ -49,6 +50,7 @@ install -Dm644 emblem-gdrivexp-error.svg %{buildroot}%{_datadir}/icons/hicolor/s
 %{_bindir}/g-drive-xp
 %{_libdir}/nautilus/extensions-4/libgdrivexp-nautilus.so
 %{_datadir}/applications/org.gnome.FedoraDrive.desktop
+%{_datadir}/gnome-shell/search-providers/org.gnome.FedoraDrive.search-provider.ini
 %{_datadir}/icons/hicolor/48x48/apps/org.gnome.FedoraDrive.png
 %{_datadir}/icons/hicolor/128x128/apps/org.gnome.FedoraDrive.png
 %{_datadir}/icons/hicolor/256x256/apps/org.gnome.FedoraDrive.png
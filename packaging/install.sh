@@ -54,6 +54,9 @@ sudo install -Dm755 "$SRC/libgdrivexp_nautilus.so" /usr/lib64/nautilus/extension
 info "Instalando archivo .desktop..."
 sudo install -Dm644 "$SRC/org.gnome.FedoraDrive.desktop" /usr/share/applications/org.gnome.FedoraDrive.desktop
 
+info "Instalando proveedor de búsqueda de GNOME Shell..."
+sudo install -Dm644 "$SRC/org.gnome.FedoraDrive.search-provider.ini" /usr/share/gnome-shell/search-providers/org.gnome.FedoraDrive.search-provider.ini
+
 info "Instalando iconos..."
 sudo install -Dm644 "$SRC/org.gnome.FedoraDrive-48.png" /usr/share/icons/hicolor/48x48/apps/org.gnome.FedoraDrive.png
 sudo install -Dm644 "$SRC/org.gnome.FedoraDrive-128.png" /usr/share/icons/hicolor/128x128/apps/org.gnome.FedoraDrive.png
@@ -34,6 +34,12 @@ fi
 echo "  → Instalando archivo .desktop..."
 cp "$PROJECT_DIR/data/${APP_ID}.desktop" ~/.local/share/applications/
 
+# GNOME Shell solo lee proveedores de búsqueda de los directorios de datos del sistema
+echo "  → Instalando proveedor de búsqueda de GNOME Shell (requiere sudo)..."
+sudo install -Dm644 "$PROJECT_DIR/data/${APP_ID}.search-provider.ini" \
+    /usr/share/gnome-shell/search-providers/${APP_ID}.search-provider.ini \
+    || echo "  ⚠️  No se pudo instalar el proveedor de búsqueda."
+
 # Crear symlink del binario en ~/.local/bin/ (GIO valida Exec y descarta el .desktop si no encuentra el binario)
 echo "  → Creando symlink del binario en ~/.local/bin/..."
 mkdir -p ~/.local/bin
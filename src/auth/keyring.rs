@@ -48,6 +48,31 @@ impl TokenStorage {
         entry.map(|e| e.get_password().is_ok()).unwrap_or(false)
     }
     
+    /// Recupera la clave maestra del cifrado de caché (None si nunca se generó)
+    pub fn load_cache_master_key(&self) -> Result<Option<Vec<u8>>> {
+        let entry = Entry::new(&self.service, "cache_master_key")?;
+        match entry.get_secret() {
+            Ok(key) => Ok(Some(key)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Guarda la clave maestra del cifrado de caché
+    pub fn save_cache_master_key(&self, key: &[u8]) -> Result<()> {
+        let entry = Entry::new(&self.service, "cache_master_key")?;
+        entry.set_secret(key)?;
+        tracing::info!("Clave maestra de caché almacenada en GNOME Keyring");
+        Ok(())
+    }
+
+    /// Elimina la clave maestra de caché (solo junto con la caché: sin ella es ilegible)
+    pub fn delete_cache_master_key(&self) {
+        if let Ok(entry) = Entry::new(&self.service, "cache_master_key") {
+            let _ = entry.delete_credential(); // Ignorar error si no existe
+        }
+    }
+
     /// Limpia todas las credenciales del keyring
     #[allow(dead_code)] // Método auxiliar, usado indirectamente por clear_all_auth_data()
     pub fn clear_all_credentials(&self) -> Result<()> {
@@ -11,12 +11,14 @@ use std::future::Future;
 use std::pin::Pin;
 
 use super::TokenStorage;
+use crate::utils::proxy::{HttpSettings, ProxyHttpsConnector};
 
 /// Gestor de autenticación OAuth2 para Google Drive
 pub struct OAuth2Manager {
     app_secret: ApplicationSecret,
     #[allow(dead_code)] // Será usado para logout
     token_storage: Arc<TokenStorage>,
+    http_settings: HttpSettings,
 }
 
 /// Delegado para capturar la URL de autenticación y enviarla a la GUI
@@ -48,9 +50,16 @@ impl OAuth2Manager {
         Self {
             app_secret,
             token_storage: Arc::new(TokenStorage::new()),
+            http_settings: HttpSettings::default(),
         }
     }
 
+    /// Proxy y CA adicional para las peticiones a los servidores OAuth de Google
+    pub fn with_http_settings(mut self, http_settings: HttpSettings) -> Self {
+        self.http_settings = http_settings;
+        self
+    }
+
     /// Crea una nueva instancia cargando el secreto desde un archivo JSON
     pub async fn new_from_file(path: &str) -> Result<Self> {
         let secret = yup_oauth2::read_application_secret(path)
@@ -61,20 +70,24 @@ impl OAuth2Manager {
     }
     
     /// Construye y retorna el autenticador configurado
-    pub async fn get_authenticator(&self, ui_sender: Option<relm4::ComponentSender<crate::gui::app_model::AppModel>>) -> Result<yup_oauth2::authenticator::Authenticator<yup_oauth2::hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>> {
-        // Resolver la ruta del home correctamente (~ no funciona en Rust)
-        let home = std::env::var("HOME").context("No se pudo obtener variable HOME")?;
-        let token_path = format!("{}/.config/fedoradrive/tokens.json", home);
+    pub async fn get_authenticator(&self, ui_sender: Option<relm4::ComponentSender<crate::gui::app_model::AppModel>>) -> Result<yup_oauth2::authenticator::Authenticator<ProxyHttpsConnector>> {
+        let token_path = crate::utils::paths::tokens_path();
         
         // Asegurar que el directorio padre existe
-        let token_dir = std::path::Path::new(&token_path).parent();
+        let token_dir = token_path.parent();
         if let Some(dir) = token_dir {
             std::fs::create_dir_all(dir).ok();
         }
         
-        let mut builder = InstalledFlowAuthenticator::builder(
+        // Sin pool de conexiones, igual que el cliente por defecto de yup_oauth2
+        let client = hyper::Client::builder()
+            .pool_max_idle_per_host(0)
+            .build::<_, hyper::Body>(self.http_settings.https_connector()?);
+
+        let mut builder = InstalledFlowAuthenticator::with_client(
             self.app_secret.clone(),
             InstalledFlowReturnMethod::HTTPRedirect,
+            client,
         );
 
         if ui_sender.is_some() {
@@ -114,11 +127,10 @@ impl OAuth2Manager {
         self.token_storage.delete_refresh_token().await?;
         
         // 2. Eliminar tokens.json del disco
-        let home = std::env::var("HOME").context("No se pudo obtener HOME")?;
-        let token_path = format!("{}/.config/fedoradrive/tokens.json", home);
-        if std::path::Path::new(&token_path).exists() {
+        let token_path = crate::utils::paths::tokens_path();
+        if token_path.exists() {
             std::fs::remove_file(&token_path)?;
-            tracing::info!("Archivo de tokens eliminado: {}", token_path);
+            tracing::info!("Archivo de tokens eliminado: {:?}", token_path);
         }
         
         // TODO: Revocar el token en los servidores de Google
@@ -137,11 +149,9 @@ impl OAuth2Manager {
 /// Función independiente para limpiar todos los datos de autenticación
 /// Útil para llamar desde la GUI sin necesidad de instancia de OAuth2Manager
 pub fn clear_all_auth_data() -> Result<()> {
-    let home = std::env::var("HOME").context("No se pudo obtener HOME")?;
-    
     // 1. Eliminar tokens.json
-    let token_path = format!("{}/.config/fedoradrive/tokens.json", home);
-    if std::path::Path::new(&token_path).exists() {
+    let token_path = crate::utils::paths::tokens_path();
+    if token_path.exists() {
         std::fs::remove_file(&token_path)?;
         tracing::info!("Archivo de tokens eliminado");
     }
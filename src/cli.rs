@@ -0,0 +1,140 @@
+//! Argumentos de línea de comandos
+//!
+//! Cada opción también se puede dar con una variable `GDRIVEXP_*` (la línea de
+//! comandos gana). Los valores prevalecen sobre `config.json` sin guardarse en
+//! él: sirven para lanzamientos desde scripts o con otro perfil de datos.
+
+use clap::Parser;
+use clap::builder::FalseyValueParser;
+use std::path::PathBuf;
+
+use crate::config::ConfigOverrides;
+
+#[derive(Debug, Parser)]
+#[command(name = "g-drive-xp", version, about = "Cliente de Google Drive para GNOME")]
+pub struct Cli {
+    /// Arrancar solo con el icono de bandeja (inicio automático)
+    #[arg(long)]
+    pub start_hidden: bool,
+
+    /// Directorio espejo visible; el montaje FUSE va en su `FUSE_Mount`
+    #[arg(long, env = "GDRIVEXP_MOUNT_POINT", value_name = "DIR")]
+    pub mount_point: Option<PathBuf>,
+
+    /// Directorio de la caché de contenido
+    #[arg(long, env = "GDRIVEXP_CACHE_DIR", value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Base de datos de metadatos (SQLite)
+    #[arg(long, env = "GDRIVEXP_DB_PATH", value_name = "FILE")]
+    pub db_path: Option<PathBuf>,
+
+    /// Intervalo de sincronización con Drive, en segundos
+    #[arg(long, env = "GDRIVEXP_SYNC_INTERVAL", value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub sync_interval: Option<u64>,
+
+    /// Montar en solo lectura y no subir cambios locales
+    #[arg(long, env = "GDRIVEXP_READ_ONLY", value_parser = FalseyValueParser::new())]
+    pub read_only: bool,
+
+    /// Exportar configuración, exclusiones y elementos mantenidos a un .tar.gz y salir
+    #[arg(long, value_name = "FILE", conflicts_with = "import_state")]
+    pub export_state: Option<PathBuf>,
+
+    /// Con `--export-state`: incluir la base de datos de metadatos (sin la caché)
+    #[arg(long, requires = "export_state")]
+    pub include_db: bool,
+
+    /// Restaurar un estado exportado en otro equipo y salir (con la aplicación cerrada)
+    #[arg(long, value_name = "FILE")]
+    pub import_state: Option<PathBuf>,
+}
+
+/// Migración a otro equipo (`utils::state_archive`): se ejecuta sin abrir la interfaz
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateCommand {
+    Export { dest: PathBuf, include_db: bool },
+    Import { archive: PathBuf },
+}
+
+impl Cli {
+    /// Valores que prevalecen sobre la configuración guardada
+    pub fn overrides(&self) -> ConfigOverrides {
+        ConfigOverrides {
+            mount_point: self.mount_point.clone(),
+            cache_dir: self.cache_dir.clone(),
+            db_path: self.db_path.clone(),
+            sync_interval_secs: self.sync_interval,
+            read_only: self.read_only,
+        }
+    }
+
+    /// Exportación o importación pedida en la línea de comandos
+    pub fn state_command(&self) -> Option<StateCommand> {
+        if let Some(dest) = &self.export_state {
+            return Some(StateCommand::Export { dest: dest.clone(), include_db: self.include_db });
+        }
+        self.import_state.clone().map(|archive| StateCommand::Import { archive })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    fn test_start_hidden_flag_matches_autostart_entry() {
+        let cli = Cli::try_parse_from(["g-drive-xp", crate::utils::autostart::START_HIDDEN_FLAG]).unwrap();
+        assert!(cli.start_hidden);
+        assert_eq!(cli.overrides(), ConfigOverrides::default());
+        assert_eq!(cli.state_command(), None);
+    }
+
+    #[rstest]
+    #[case::export(&["g-drive-xp", "--export-state", "estado.tar.gz"], StateCommand::Export { dest: "estado.tar.gz".into(), include_db: false })]
+    #[case::export_with_db(&["g-drive-xp", "--export-state", "estado.tar.gz", "--include-db"], StateCommand::Export { dest: "estado.tar.gz".into(), include_db: true })]
+    #[case::import(&["g-drive-xp", "--import-state", "estado.tar.gz"], StateCommand::Import { archive: "estado.tar.gz".into() })]
+    fn test_state_commands(#[case] args: &[&str], #[case] expected: StateCommand) {
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert_eq!(cli.state_command(), Some(expected));
+    }
+
+    #[rstest]
+    fn test_flags_become_overrides() {
+        let cli = Cli::try_parse_from([
+            "g-drive-xp",
+            "--mount-point",
+            "/srv/drive",
+            "--cache-dir",
+            "/srv/cache",
+            "--db-path",
+            "/srv/metadata.db",
+            "--sync-interval",
+            "300",
+            "--read-only",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            cli.overrides(),
+            ConfigOverrides {
+                mount_point: Some(PathBuf::from("/srv/drive")),
+                cache_dir: Some(PathBuf::from("/srv/cache")),
+                db_path: Some(PathBuf::from("/srv/metadata.db")),
+                sync_interval_secs: Some(300),
+                read_only: true,
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::zero_interval(&["g-drive-xp", "--sync-interval", "0"])]
+    #[case::not_a_number(&["g-drive-xp", "--sync-interval", "rápido"])]
+    #[case::unknown_flag(&["g-drive-xp", "--no-existe"])]
+    #[case::include_db_without_export(&["g-drive-xp", "--include-db"])]
+    #[case::export_and_import(&["g-drive-xp", "--export-state", "a.tar.gz", "--import-state", "b.tar.gz"])]
+    fn test_invalid_arguments_are_rejected(#[case] args: &[&str]) {
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+}
@@ -1,3 +1,6 @@
+mod path_cache;
 pub mod repository;
+#[cfg(test)]
+pub mod test_fixtures;
 
-pub use repository::{MetadataRepository, LocalSyncDir, LocalSyncFile, BulkFileMetadata, BulkDentry};
+pub use repository::{MetadataRepository, LocalSyncDir, LocalSyncFile, BulkFileMetadata, BulkDentry, FileCapabilities, UploadOp, PendingOps, UploadStatus, UploadProblem, SyncConflict, CachedFileSample, CacheEntry, PrefetchCandidate, ThumbnailCandidate, COMPUTERS_DIR_NAME, COMPUTERS_GDRIVE_ID};
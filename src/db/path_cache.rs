@@ -0,0 +1,154 @@
+//! Caché de `MetadataRepository::resolve_path_components`
+//!
+//! Nautilus pide por IPC el estado de cada archivo visible, así que las mismas
+//! rutas se resuelven una y otra vez. La caché guarda ruta → `(inode, gdrive_id)`
+//! (o su ausencia) junto con la generación de la base al leerla, y solo sirve
+//! entradas de la generación vigente.
+//!
+//! La generación la mueve SQLite, no los métodos del repositorio: un hook en
+//! las conexiones de escritura la incrementa con cada fila escrita en `dentry`
+//! o `inodes` (sea cual sea el método o la transacción) y marca la escritura
+//! como pendiente. Al devolver la conexión al pool, ya confirmada, vuelve a
+//! incrementarla y limpia la marca. Mientras hay una escritura pendiente la
+//! caché no responde ni guarda nada: una lectura hecha a mitad de una
+//! transacción nunca queda como válida.
+
+use dashmap::DashMap;
+use sqlx::sqlite::{SqliteConnection, SqlitePoolOptions};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Rutas recordadas como máximo (al superarlas se vacía la caché)
+const CAPACITY: usize = 20_000;
+
+/// Tablas cuyas escrituras cambian la resolución de una ruta
+const WATCHED_TABLES: [&str; 2] = ["dentry", "inodes"];
+
+/// Resultado de resolver una ruta (`None`: no existe)
+pub type Resolved = Option<(u64, String)>;
+
+#[derive(Default)]
+pub struct PathCache {
+    entries: DashMap<String, (u64, Resolved)>,
+    generation: AtomicU64,
+    /// Hay filas de `dentry`/`inodes` escritas cuya conexión aún no volvió al pool
+    pending: AtomicBool,
+}
+
+impl std::fmt::Debug for PathCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PathCache")
+            .field("entries", &self.entries.len())
+            .field("generation", &self.generation.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+impl PathCache {
+    /// Clave de una ruta (los nombres no contienen `/`)
+    pub fn key(parts: &[&str]) -> String {
+        parts.join("/")
+    }
+
+    /// Generación a partir de la cual se leerá una ruta, o `None` si hay una
+    /// escritura pendiente y no se debe usar la caché
+    pub fn snapshot(&self) -> Option<u64> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        (!self.pending.load(Ordering::SeqCst)).then_some(generation)
+    }
+
+    /// Ruta resuelta en la generación vigente
+    pub fn get(&self, key: &str) -> Option<Resolved> {
+        let generation = self.snapshot()?;
+        self.entries
+            .get(key)
+            .filter(|entry| entry.0 == generation)
+            .map(|entry| entry.1.clone())
+    }
+
+    /// Recuerda una ruta leída desde `generation` (ver `snapshot`). Se descarta
+    /// si algo escribió mientras tanto
+    pub fn put(&self, key: String, generation: u64, resolved: Resolved) {
+        if self.snapshot() != Some(generation) {
+            return;
+        }
+        if self.entries.len() >= CAPACITY {
+            self.entries.clear();
+        }
+        self.entries.insert(key, (generation, resolved));
+    }
+
+    fn table_changed(&self) {
+        self.pending.store(true, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn released(&self) {
+        if self.pending.swap(false, Ordering::SeqCst) {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Agrega a un pool de escritura los hooks que mantienen la generación
+    pub fn watch(self: &Arc<Self>, options: SqlitePoolOptions) -> SqlitePoolOptions {
+        let on_connect = self.clone();
+        let on_release = self.clone();
+        options
+            .after_connect(move |conn, _meta| {
+                let cache = on_connect.clone();
+                Box::pin(async move { cache.install_hook(conn).await })
+            })
+            .after_release(move |_conn, _meta| {
+                let cache = on_release.clone();
+                Box::pin(async move {
+                    cache.released();
+                    Ok(true)
+                })
+            })
+    }
+
+    async fn install_hook(self: Arc<Self>, conn: &mut SqliteConnection) -> sqlx::Result<()> {
+        conn.lock_handle().await?.set_update_hook(move |change| {
+            if WATCHED_TABLES.contains(&change.table) {
+                self.table_changed();
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_expire_with_generation() {
+        let cache = PathCache::default();
+        let generation = cache.snapshot().unwrap();
+        cache.put(PathCache::key(&["a", "b"]), generation, Some((3, "id-b".into())));
+        assert_eq!(cache.get("a/b"), Some(Some((3, "id-b".into()))));
+
+        cache.table_changed();
+        assert_eq!(cache.snapshot(), None, "escritura pendiente");
+        assert_eq!(cache.get("a/b"), None);
+
+        cache.released();
+        assert_eq!(cache.get("a/b"), None, "la entrada es de otra generación");
+    }
+
+    #[test]
+    fn test_reads_during_a_write_are_not_kept() {
+        let cache = PathCache::default();
+        let generation = cache.snapshot().unwrap();
+        cache.table_changed();
+        cache.released();
+        cache.put("a".into(), generation, None);
+        assert_eq!(cache.get("a"), None, "leída antes de la escritura");
+
+        cache.table_changed();
+        let during = cache.generation.load(Ordering::SeqCst);
+        cache.put("a".into(), during, None);
+        cache.released();
+        assert_eq!(cache.get("a"), None, "leída a mitad de la escritura");
+    }
+}
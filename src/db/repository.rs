@@ -1,17 +1,74 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use sqlx::{sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions}, SqlitePool};
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::gdrive::app_properties::AppMarks;
+use super::path_cache::PathCache;
+
+/// Migraciones versionadas embebidas desde `migrations/`.
+/// El historial aplicado se guarda en la tabla `_sqlx_migrations`.
+static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations");
+
+/// Filas por transacción en las operaciones masivas (bootstrap)
+const BULK_BATCH_SIZE: usize = 500;
+
+/// Clave en sync_meta de la generación vigente de inodes. Se incrementa en cada
+/// hard delete, de modo que un número de inode reutilizado nunca repite generación.
+const INODE_GENERATION_KEY: &str = "inode_generation";
+
+/// Clave en sync_meta del ID de esta instalación (`MetadataRepository::client_id`)
+const CLIENT_ID_KEY: &str = "client_id";
+
+/// Inserta un inode nuevo con la generación vigente
+const INSERT_INODE_SQL: &str = r#"
+    INSERT INTO inodes (gdrive_id, created_at, generation)
+    VALUES (?, ?, COALESCE((SELECT CAST(value AS INTEGER) FROM sync_meta WHERE key = ?), 0))
+"#;
+
+/// `gdrive_id` del directorio virtual que agrupa las carpetas de la sección
+/// "Ordenadores" de Drive (copias de Backup and Sync / Drive para escritorio).
+/// No existe en Drive: el uploader nunca crea nada directamente dentro.
+pub const COMPUTERS_GDRIVE_ID: &str = "virtual_computers";
+
+/// Nombre del directorio virtual de "Ordenadores" en la raíz del montaje
+pub const COMPUTERS_DIR_NAME: &str = "Computers";
+
+/// Conexiones del pool de escritura. Una sola: las escrituras quedan serializadas
+/// en el pool (con más, los escritores concurrentes se topaban con SQLITE_BUSY).
+/// Las consultas que no escriben van a `read_pool` para no esperar detrás de los
+/// lotes del bootstrap (ver `load_getattr_latency_under_bootstrap`)
+const WRITE_CONNECTIONS: u32 = 1;
+/// Conexiones de solo lectura para las consultas calientes de FUSE (lookup,
+/// getattr, readdir, caché de contenido). Con WAL leen mientras el syncer o
+/// el bootstrap escriben
+const READ_CONNECTIONS: u32 = 8;
+/// Sentencias preparadas que cada conexión de lectura mantiene en caché
+/// (sqlx reutiliza la sentencia de una misma consulta; por defecto 100)
+const READ_STATEMENT_CACHE: usize = 256;
 
 /// Repositorio principal de metadatos basado en SQLite
 #[derive(Debug)]
 pub struct MetadataRepository {
+    /// Escrituras y el resto de consultas
     pool: SqlitePool,
+    /// Solo lectura: las consultas del camino caliente de FUSE
+    read_pool: SqlitePool,
+    /// Rutas ya resueltas por `resolve_path_components`
+    paths: Arc<PathCache>,
 }
 
 impl MetadataRepository {
-    /// Inicializa la conexión a la base de datos y aplica el esquema
+    /// Inicializa la conexión a la base de datos y aplica las migraciones
     pub async fn new(db_path: &Path) -> Result<Self> {
+        Self::open(db_path, WRITE_CONNECTIONS, Some(READ_CONNECTIONS)).await
+    }
+
+    /// `new` con el tamaño de los pools explícito. `read_connections = None`
+    /// deja las lecturas en el pool de escritura (la disposición anterior a
+    /// `read_pool`, que compara la prueba de carga)
+    async fn open(db_path: &Path, write_connections: u32, read_connections: Option<u32>) -> Result<Self> {
         // Asegurarse de que el archivo existe (sqlx requiere esto para SQLite)
         if !db_path.exists() {
             if let Some(parent) = db_path.parent() {
@@ -26,104 +83,162 @@ impl MetadataRepository {
             .pragma("synchronous", "NORMAL")
             .create_if_missing(true);
 
-        let pool = SqlitePoolOptions::new()
-            .max_connections(5)
+        let paths = Arc::new(PathCache::default());
+        let pool = paths.watch(SqlitePoolOptions::new())
+            .max_connections(write_connections)
             .acquire_timeout(std::time::Duration::from_secs(60))
-            .connect_with(options)
+            .connect_with(options.clone())
             .await?;
+        let Some(read_connections) = read_connections else {
+            return Self::init(pool.clone(), pool, paths).await;
+        };
 
-        // Inicializar esquema (crea tablas si no existen)
-        sqlx::query(include_str!("schema.sql"))
-            .execute(&pool)
+        // Después del pool de escritura, que ya dejó la base en modo WAL
+        let read_options = options
+            .read_only(true)
+            .statement_cache_capacity(READ_STATEMENT_CACHE);
+        let read_pool = SqlitePoolOptions::new()
+            .max_connections(read_connections)
+            .acquire_timeout(std::time::Duration::from_secs(60))
+            .connect_with(read_options)
             .await?;
-        
-        let repo = Self { pool };
 
-        // Aplicar migraciones necesarias para bases de datos existentes
-        repo.apply_migrations().await?;
-        
-        Ok(repo)
+        Self::init(pool, read_pool, paths).await
     }
 
-    /// Aplica migraciones manuales para asegurar que el esquema está actualizado
-    async fn apply_migrations(&self) -> Result<()> {
-        // 1. Verificar si la columna deleted_at existe en sync_state
-        let has_deleted_at = sqlx::query("PRAGMA table_info(sync_state)")
-            .fetch_all(&self.pool)
-            .await?
-            .iter()
-            .any(|row: &sqlx::sqlite::SqliteRow| {
-                use sqlx::Row;
-                let name: String = row.get("name");
-                name == "deleted_at"
-            });
+    /// Base de datos en memoria (tests): sin WAL ni archivo. Todas las
+    /// conexiones del pool comparten la misma base, que vive mientras el pool
+    /// mantenga al menos una conexión abierta. Las lecturas usan el mismo pool.
+    #[cfg(test)]
+    pub async fn in_memory() -> Result<Self> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")?
+            .pragma("busy_timeout", "60000");
 
-        if !has_deleted_at {
-            sqlx::query("ALTER TABLE sync_state ADD COLUMN deleted_at INTEGER DEFAULT NULL")
-                .execute(&self.pool)
-                .await?;
+        let paths = Arc::new(PathCache::default());
+        let pool = paths.watch(SqlitePoolOptions::new())
+            .max_connections(5)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .acquire_timeout(std::time::Duration::from_secs(60))
+            .connect_with(options)
+            .await?;
+
+        Self::init(pool.clone(), pool, paths).await
+    }
+
+    /// Aplica las migraciones (convirtiendo antes una base legacy) y limpia
+    /// el estado que no sobrevive a un reinicio
+    async fn init(pool: SqlitePool, read_pool: SqlitePool, paths: Arc<PathCache>) -> Result<Self> {
+        let repo = Self { pool, read_pool, paths };
+
+        // Bases de datos anteriores al sistema de migraciones: convertir una única vez
+        let is_legacy = repo.is_legacy_database().await?;
+        if is_legacy {
+            tracing::info!("Convirtiendo base de datos existente al esquema versionado...");
+            repo.convert_legacy_schema().await?;
         }
 
-        // 2. Verificar si la columna remote_md5 existe en sync_state
-        let has_remote_md5 = sqlx::query("PRAGMA table_info(sync_state)")
-            .fetch_all(&self.pool)
-            .await?
-            .iter()
-            .any(|row: &sqlx::sqlite::SqliteRow| {
-                use sqlx::Row;
-                let name: String = row.get("name");
-                name == "remote_md5"
-            });
+        MIGRATOR.run(&repo.pool)
+            .await
+            .context("Error aplicando migraciones de la base de datos")?;
 
-        if !has_remote_md5 {
-            sqlx::query("ALTER TABLE sync_state ADD COLUMN remote_md5 TEXT")
-                .execute(&self.pool)
-                .await?;
+        if is_legacy {
+            repo.rebuild_dir_counters_if_empty().await?;
+            tracing::info!("Conversión completada (esquema v{})", repo.schema_version().await?);
         }
 
-        // Asegurar que el índice existe (CREATE INDEX IF NOT EXISTS es seguro)
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_sync_deleted ON sync_state(deleted_at) WHERE deleted_at IS NOT NULL")
-            .execute(&self.pool)
+        // Un upload no sobrevive a un reinicio: limpiar marcas residuales
+        sqlx::query("UPDATE sync_state SET uploading = 0 WHERE uploading = 1")
+            .execute(&repo.pool)
             .await?;
 
-        // 3. Crear tabla file_cache_chunks si no existe
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS file_cache_chunks (
-                inode INTEGER NOT NULL,
-                start_offset INTEGER NOT NULL,
-                end_offset INTEGER NOT NULL,
-                PRIMARY KEY (inode, start_offset),
-                FOREIGN KEY (inode) REFERENCES inodes(inode) ON DELETE CASCADE
-            )
-            "#
+        Ok(repo)
+    }
+
+    /// Versión del esquema: última migración aplicada con éxito (0 si ninguna)
+    pub async fn schema_version(&self) -> Result<i64> {
+        let version: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations WHERE success = 1"
         )
-        .execute(&self.pool)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(version)
+    }
+
+    /// Verifica si una tabla existe
+    async fn has_table(&self, table: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?"
+        )
+        .bind(table)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count > 0)
+    }
+
+    /// Verifica si una columna existe en una tabla
+    async fn has_column(&self, table: &str, column: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?"
+        )
+        .bind(table)
+        .bind(column)
+        .fetch_one(&self.pool)
         .await?;
+        Ok(count > 0)
+    }
+
+    /// Una base de datos es "legacy" si tiene datos pero nunca pasó por el migrador
+    async fn is_legacy_database(&self) -> Result<bool> {
+        Ok(self.has_table("inodes").await? && !self.has_table("_sqlx_migrations").await?)
+    }
+
+    /// Convierte una base de datos creada antes del sistema de migraciones al
+    /// esquema v1 (`migrations/0001_initial_schema.sql`).
+    /// Solo agrega columnas faltantes y corrige estructuras antiguas; las tablas
+    /// e índices inexistentes los crea la migración inicial.
+    async fn convert_legacy_schema(&self) -> Result<()> {
+        // 1. Columnas agregadas incrementalmente en versiones anteriores
+        let columns: &[(&str, &str, &str)] = &[
+            ("sync_state", "deleted_at", "INTEGER DEFAULT NULL"),
+            ("sync_state", "remote_md5", "TEXT"),
+            ("sync_state", "availability", "TEXT DEFAULT 'online_only'"),
+            ("sync_state", "uploading", "BOOLEAN DEFAULT 0"),
+            ("attrs", "can_move", "BOOLEAN DEFAULT 1"),
+            ("attrs", "shared", "BOOLEAN DEFAULT 0"),
+            ("attrs", "owned_by_me", "BOOLEAN DEFAULT 1"),
+            ("attrs", "shortcut_target_id", "TEXT"),
+            ("attrs", "web_view_link", "TEXT"),
+        ];
+
+        for (table, column, definition) in columns {
+            if self.has_table(table).await? && !self.has_column(table, column).await? {
+                tracing::info!("Migrando: agregando columna {}.{}", table, column);
+                sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
 
-        // 4. Migración: Corregir PRIMARY KEY de dentry_deleted
-        // Verificar si la tabla tiene la PK incorrecta
-        let has_old_pk = sqlx::query(
+        // 2. Corregir PRIMARY KEY de dentry_deleted (antes era (parent_inode, name))
+        let has_old_pk = sqlx::query_scalar::<_, String>(
             "SELECT sql FROM sqlite_master WHERE type='table' AND name='dentry_deleted'"
         )
         .fetch_optional(&self.pool)
         .await?
-        .and_then(|row: sqlx::sqlite::SqliteRow| {
-            use sqlx::Row;
-            let sql: String = row.get("sql");
-            Some(sql.contains("PRIMARY KEY (parent_inode, name)"))
-        })
+        .map(|sql| sql.contains("PRIMARY KEY (parent_inode, name)"))
         .unwrap_or(false);
 
         if has_old_pk {
             tracing::info!("Aplicando migración: Corrigiendo PRIMARY KEY de dentry_deleted");
-            
-            // Renombrar tabla vieja
+
+            let mut tx = self.pool.begin().await?;
+
             sqlx::query("ALTER TABLE dentry_deleted RENAME TO dentry_deleted_old")
-                .execute(&self.pool)
+                .execute(&mut *tx)
                 .await?;
-            
-            // Crear nueva tabla con PK correcto
+
             sqlx::query(
                 r#"
                 CREATE TABLE dentry_deleted (
@@ -135,9 +250,9 @@ impl MetadataRepository {
                 )
                 "#
             )
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
-            
+
             // Migrar datos (eliminando duplicados por child_inode)
             sqlx::query(
                 r#"
@@ -146,160 +261,25 @@ impl MetadataRepository {
                 FROM dentry_deleted_old
                 "#
             )
-            .execute(&self.pool)
-            .await?;
-            
-            // Eliminar tabla vieja
-            sqlx::query("DROP TABLE dentry_deleted_old")
-                .execute(&self.pool)
-                .await?;
-            
-            // Recrear índice
-            sqlx::query("CREATE INDEX IF NOT EXISTS idx_tombstone_deleted_at ON dentry_deleted(deleted_at)")
-                .execute(&self.pool)
-                .await?;
-            
-            tracing::info!("Migración de dentry_deleted completada");
-        }
-
-        // 5. Crear tabla local_sync_files para Local Sync híbrido
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS local_sync_files (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                sync_dir_id INTEGER NOT NULL REFERENCES local_sync_dirs(id) ON DELETE CASCADE,
-                relative_path TEXT NOT NULL,
-                is_dir INTEGER NOT NULL DEFAULT 0,
-                
-                availability TEXT NOT NULL DEFAULT 'local_online',
-                
-                local_mtime INTEGER,
-                local_size INTEGER,
-                local_md5 TEXT,
-                
-                gdrive_id TEXT,
-                remote_md5 TEXT,
-                remote_mtime INTEGER,
-                
-                dirty INTEGER NOT NULL DEFAULT 1,
-                last_synced INTEGER,
-                
-                UNIQUE(sync_dir_id, relative_path)
-            )
-            "#
-        )
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_local_sync_files_dirty ON local_sync_files(dirty) WHERE dirty = 1")
-            .execute(&self.pool)
-            .await?;
-
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_local_sync_files_gdrive ON local_sync_files(gdrive_id)")
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
 
-        // 6. Verificar si la columna availability existe en sync_state
-        let has_availability = sqlx::query("PRAGMA table_info(sync_state)")
-            .fetch_all(&self.pool)
-            .await?
-            .iter()
-            .any(|row: &sqlx::sqlite::SqliteRow| {
-                use sqlx::Row;
-                let name: String = row.get("name");
-                name == "availability"
-            });
-
-        if !has_availability {
-            // Default a 'online_only' (nube) para no descargar todo por defecto
-            sqlx::query("ALTER TABLE sync_state ADD COLUMN availability TEXT DEFAULT 'online_only'")
-                .execute(&self.pool)
-                .await?;
-        }
-
-        // 7. Verificar si la columna can_move existe en attrs
-        let has_can_move = sqlx::query("PRAGMA table_info(attrs)")
-            .fetch_all(&self.pool)
-            .await?
-            .iter()
-            .any(|row: &sqlx::sqlite::SqliteRow| {
-                use sqlx::Row;
-                let name: String = row.get("name");
-                name == "can_move"
-            });
-
-        if !has_can_move {
-            sqlx::query("ALTER TABLE attrs ADD COLUMN can_move BOOLEAN DEFAULT 1")
-                .execute(&self.pool)
-                .await?;
-        }
-
-        // 8. Verificar si la columna shared existe en attrs
-        let has_shared = sqlx::query("PRAGMA table_info(attrs)")
-            .fetch_all(&self.pool)
-            .await?
-            .iter()
-            .any(|row: &sqlx::sqlite::SqliteRow| {
-                use sqlx::Row;
-                let name: String = row.get("name");
-                name == "shared"
-            });
-
-        if !has_shared {
-            sqlx::query("ALTER TABLE attrs ADD COLUMN shared BOOLEAN DEFAULT 0")
-                .execute(&self.pool)
-                .await?;
-        }
-
-        // 9. Verificar si la columna owned_by_me existe en attrs
-        let has_owned_by_me = sqlx::query("PRAGMA table_info(attrs)")
-            .fetch_all(&self.pool)
-            .await?
-            .iter()
-            .any(|row: &sqlx::sqlite::SqliteRow| {
-                use sqlx::Row;
-                let name: String = row.get("name");
-                name == "owned_by_me"
-            });
-
-        if !has_owned_by_me {
-            sqlx::query("ALTER TABLE attrs ADD COLUMN owned_by_me BOOLEAN DEFAULT 1")
-                .execute(&self.pool)
+            // El índice viejo se elimina junto con la tabla; la migración inicial lo recrea
+            sqlx::query("DROP TABLE dentry_deleted_old")
+                .execute(&mut *tx)
                 .await?;
-        }
 
-        // 10. Verificar si la columna shortcut_target_id existe en attrs
-        let has_shortcut_target = sqlx::query("PRAGMA table_info(attrs)")
-            .fetch_all(&self.pool)
-            .await?
-            .iter()
-            .any(|row: &sqlx::sqlite::SqliteRow| {
-                use sqlx::Row;
-                let name: String = row.get("name");
-                name == "shortcut_target_id"
-            });
+            tx.commit().await?;
 
-        if !has_shortcut_target {
-            sqlx::query("ALTER TABLE attrs ADD COLUMN shortcut_target_id TEXT")
-                .execute(&self.pool)
-                .await?;
+            tracing::info!("Migración de dentry_deleted completada");
         }
 
-        // 11. Crear tabla dir_counters (Protocolo Burbujeo de Estados)
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS dir_counters (
-                inode INTEGER PRIMARY KEY,
-                dirty_desc_count INTEGER NOT NULL DEFAULT 0,
-                synced_desc_count INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (inode) REFERENCES inodes(inode) ON DELETE CASCADE
-            )
-            "#
-        )
-        .execute(&self.pool)
-        .await?;
+        Ok(())
+    }
 
-        // Si la tabla existe pero está vacía y hay datos en dentry, recalcular contadores
+    /// Si `dir_counters` está vacía pero hay directorios, recalcula los contadores
+    /// (bases de datos anteriores al Protocolo Burbujeo de Estados)
+    async fn rebuild_dir_counters_if_empty(&self) -> Result<()> {
         let counters_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM dir_counters")
             .fetch_one(&self.pool)
             .await?;
@@ -311,7 +291,6 @@ impl MetadataRepository {
             self.rebuild_all_dir_counters().await?;
             tracing::info!("Migración de dir_counters completada");
         }
-
         Ok(())
     }
 
@@ -321,7 +300,7 @@ impl MetadataRepository {
             "SELECT availability FROM sync_state WHERE inode = ?"
         )
         .bind(inode as i64)
-        .fetch_optional(&self.pool)
+        .fetch_optional(&self.read_pool)
         .await?;
 
         Ok(row.unwrap_or_else(|| "online_only".to_string()))
@@ -448,7 +427,7 @@ impl MetadataRepository {
               AND (s.deleted_at IS NULL OR s.deleted_at = 0)
             "#
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&self.read_pool)
         .await?;
 
         let mut results: Vec<(u64, String)> = rows.into_iter()
@@ -500,7 +479,7 @@ impl MetadataRepository {
               AND (s.deleted_at IS NULL OR s.deleted_at = 0) -- No eliminados
             "#
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&self.read_pool)
         .await?;
         
         Ok(rows.into_iter()
@@ -508,10 +487,35 @@ impl MetadataRepository {
             .collect())
     }
 
+    /// Ejecuta un checkpoint del WAL. Con `truncate=false` usa modo PASSIVE
+    /// (no bloquea lectores ni escritores); con `truncate=true` espera a los
+    /// escritores y trunca el archivo `-wal` a 0 bytes.
+    /// Retorna (páginas en el WAL, páginas transferidas a la base de datos).
+    pub async fn wal_checkpoint(&self, truncate: bool) -> Result<(i64, i64)> {
+        let sql = if truncate {
+            "PRAGMA wal_checkpoint(TRUNCATE)"
+        } else {
+            "PRAGMA wal_checkpoint(PASSIVE)"
+        };
+        let (busy, log, checkpointed): (i64, i64, i64) = sqlx::query_as(sql)
+            .fetch_one(&self.pool)
+            .await?;
+        if busy != 0 {
+            tracing::debug!("Checkpoint WAL parcial: conexiones ocupadas");
+        }
+        Ok((log, checkpointed))
+    }
+
     /// Obtiene el pool de conexiones crudo si es necesario
     pub fn pool(&self) -> &SqlitePool {
         &self.pool
     }
+
+    /// Cierra los dos pools (antes de reemplazar o copiar el archivo de la base)
+    pub async fn close(&self) {
+        self.read_pool.close().await;
+        self.pool.close().await;
+    }
     
     /// Buscar inodo por directorio padre y nombre (operación lookup)
     pub async fn lookup(&self, parent: u64, name: &str) -> Result<Option<u64>> {
@@ -520,7 +524,7 @@ impl MetadataRepository {
         )
         .bind(parent as i64)
         .bind(name)
-        .fetch_optional(&self.pool)
+        .fetch_optional(&self.read_pool)
         .await?;
         
         Ok(row.map(|i| i as u64))
@@ -532,7 +536,7 @@ impl MetadataRepository {
             "SELECT COUNT(*) FROM dentry WHERE child_inode = ?"
         )
         .bind(inode as i64)
-        .fetch_one(&self.pool)
+        .fetch_one(&self.read_pool)
         .await?;
         Ok(count > 0)
     }
@@ -544,1885 +548,5743 @@ impl MetadataRepository {
             let row = sqlx::query_as::<_, crate::fuse::attr::FileAttributes>(
                 "SELECT * FROM attrs WHERE inode = 1"
             )
-            .fetch_optional(&self.pool)
+            .fetch_optional(&self.read_pool)
             .await?;
 
             return Ok(row.unwrap_or_else(crate::fuse::attr::FileAttributes::root));
         }
 
         let attrs = sqlx::query_as::<_, crate::fuse::attr::FileAttributes>(
-            "SELECT * FROM attrs WHERE inode = ?"
+            r#"
+            SELECT a.*, COALESCE(i.generation, 0) AS generation
+            FROM attrs a
+            LEFT JOIN inodes i ON i.inode = a.inode
+            WHERE a.inode = ?
+            "#
         )
         .bind(inode as i64)
-        .fetch_one(&self.pool)
+        .fetch_one(&self.read_pool)
         .await?;
         
         Ok(attrs)
     }
-    /// Listar contenido de un directorio con metadatos extendidos (para readdirplus)
-    pub async fn list_children_extended(&self, parent_inode: u64) -> Result<Vec<(u64, String, bool, Option<String>, String)>> {
-        let children = sqlx::query_as::<_, (i64, String, bool, Option<String>, String)>(
+
+    /// Obtiene el gdrive_id de un inode
+    pub async fn get_gdrive_id(&self, inode: u64) -> Result<Option<String>> {
+        let gdrive_id = sqlx::query_scalar::<_, String>("SELECT gdrive_id FROM inodes WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(gdrive_id)
+    }
+
+    /// Reemplaza el gdrive_id de un inode (ej: ID temporal → ID real tras subir)
+    pub async fn set_gdrive_id(&self, inode: u64, gdrive_id: &str) -> Result<()> {
+        sqlx::query("UPDATE inodes SET gdrive_id = ? WHERE inode = ?")
+            .bind(gdrive_id)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Crea el inode de un archivo nuevo local (FUSE, mirror) que falta crear
+    /// en Drive. `gdrive_id` es un ID reservado o un `temp_<uuid>`.
+    pub async fn create_local_inode(&self, gdrive_id: &str) -> Result<u64> {
+        let inode = self.get_or_create_inode(gdrive_id).await?;
+        sqlx::query("UPDATE inodes SET pending_create = 1 WHERE inode = ?")
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(inode)
+    }
+
+    /// Indica si el inode aún no se creó en Drive
+    pub async fn is_pending_create(&self, inode: u64) -> Result<bool> {
+        let pending: Option<bool> = sqlx::query_scalar("SELECT pending_create FROM inodes WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(pending.unwrap_or(false))
+    }
+
+    /// Indica si la carpeta padre del inode aún no se creó en Drive
+    pub async fn parent_pending_create(&self, inode: u64) -> Result<bool> {
+        let pending: Option<bool> = sqlx::query_scalar(
             r#"
-            SELECT 
-                d.child_inode, 
-                d.name, 
-                a.is_dir,
-                a.mime_type,
-                i.gdrive_id
+            SELECT i.pending_create
             FROM dentry d
-            JOIN attrs a ON d.child_inode = a.inode
-            JOIN inodes i ON d.child_inode = i.inode
-            WHERE d.parent_inode = ?
-            ORDER BY d.name
+            JOIN inodes i ON i.inode = d.parent_inode
+            WHERE d.child_inode = ?
+            LIMIT 1
             "#
         )
-        .bind(parent_inode as i64)
-        .fetch_all(&self.pool)
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
         .await?;
-        
-        Ok(children.into_iter()
-            .map(|(inode, name, is_dir, mime, gdrive_id)| (inode as u64, name, is_dir, mime, gdrive_id))
-            .collect())
+        Ok(pending.unwrap_or(false))
     }
 
-    /// Listar contenido compartido de la raíz (archivos no propios que cuelgan del inode 1)
-    pub async fn list_non_owned_root_children(&self) -> Result<Vec<(u64, String, bool, Option<String>, String)>> {
-        let children = sqlx::query_as::<_, (i64, String, bool, Option<String>, String)>(
+    /// El archivo ya existe en Drive con `gdrive_id` (el reservado o el que
+    /// asignó Drive en lugar del `temp_<uuid>`)
+    pub async fn mark_created(&self, inode: u64, gdrive_id: &str) -> Result<()> {
+        mark_created_in(&mut *self.pool.acquire().await?, inode, gdrive_id).await
+    }
+
+    /// Si un rename de `source` encima de `target` se puede aplicar como nueva
+    /// versión de `target` (`adopt_replaced_file`) en vez de enviarlo a la
+    /// papelera. `target` es un archivo de Drive editable que no es acceso
+    /// directo ni documento de Google; `source` es un archivo con contenido
+    /// propio que aún no existe en Drive (guardado atómico) o que Drive deja
+    /// enviar a la papelera.
+    pub async fn can_replace_in_place(&self, source: u64, target: u64) -> Result<bool> {
+        let matches: Option<i64> = sqlx::query_scalar(
             r#"
-            SELECT 
-                d.child_inode, 
-                d.name, 
-                a.is_dir,
-                a.mime_type,
-                i.gdrive_id
-            FROM dentry d
-            JOIN attrs a ON d.child_inode = a.inode
-            JOIN inodes i ON d.child_inode = i.inode
-            WHERE d.parent_inode = 1 AND a.owned_by_me = 0
-            ORDER BY d.name
+            SELECT 1
+            FROM inodes si
+            JOIN attrs sa ON sa.inode = si.inode
+            LEFT JOIN file_capabilities sc ON sc.inode = si.inode
+            JOIN inodes ti ON ti.inode = ?2
+            JOIN attrs ta ON ta.inode = ti.inode
+            LEFT JOIN file_capabilities tc ON tc.inode = ti.inode
+            WHERE si.inode = ?1 AND ?1 != ?2
+              AND sa.is_dir = 0 AND sa.shortcut_target_id IS NULL
+              AND COALESCE(sa.mime_type, '') NOT LIKE 'application/vnd.google-apps.%'
+              AND (si.pending_create = 1 OR COALESCE(sc.can_trash, 1) = 1)
+              AND ti.pending_create = 0 AND ta.is_dir = 0
+              AND ta.shortcut_target_id IS NULL
+              AND COALESCE(ta.mime_type, '') NOT LIKE 'application/vnd.google-apps.%'
+              AND COALESCE(tc.can_edit, 1) = 1
             "#
         )
-        .fetch_all(&self.pool)
+        .bind(source as i64)
+        .bind(target as i64)
+        .fetch_optional(&self.read_pool)
         .await?;
-        
-        Ok(children.into_iter()
-            .map(|(inode, name, is_dir, mime, gdrive_id)| (inode as u64, name, is_dir, mime, gdrive_id))
-            .collect())
+        Ok(matches.is_some())
     }
 
-    /// Resuelve un path relativo (desde el root del mirror) a su inode
-    pub async fn resolve_relative_path_to_inode(&self, relative_path: &str) -> Result<Option<u64>> {
-        let parts: Vec<&str> = relative_path.split('/').filter(|s| !s.is_empty()).collect();
-        
-        let mut current_inode = 1u64; // Root inode
-        
-        // Si el path empieza con "SHARED", saltamos ese segmento virtual
-        // y seguimos resolviendo desde root (inode 1)
-        let parts_to_resolve = if parts.first() == Some(&"SHARED") {
-            &parts[1..]
-        } else {
-            &parts[..]
+    /// Rename encima de un archivo de Drive (ver `can_replace_in_place`).
+    /// `inode` pasa a ser el archivo `replaced`: toma su `gdrive_id` (y con él
+    /// enlaces, comparticiones y revisiones), sus metadatos de Drive, su
+    /// disponibilidad, su `remote_md5` y sus conflictos, y conserva su propio
+    /// contenido (tamaño, mtime y chunks). Queda dirty sin operaciones en el
+    /// diario: el llamador anota la escritura y mueve su dentry y su caché.
+    ///
+    /// Si `inode` aún no existía en Drive (guardado atómico), `replaced`
+    /// desaparece de la DB sin tocar Drive. Si ya existía, su archivo de Drive
+    /// queda huérfano: `replaced` se queda con ese ID y pasa a tombstone con
+    /// una operación `Delete`, así el uploader lo envía a la papelera.
+    /// Retorna el `gdrive_id` adoptado.
+    pub async fn adopt_replaced_file(&self, inode: u64, replaced: u64) -> Result<String> {
+        // Todo o nada: un fallo a medias dejaría el inode medio adoptado
+        let mut tx = self.pool.begin().await?;
+        let id_of = |target: u64| {
+            sqlx::query_scalar::<_, String>("SELECT gdrive_id FROM inodes WHERE inode = ?").bind(target as i64)
         };
-        
-        for part in parts_to_resolve {
-            match self.lookup(current_inode, part).await? {
-                Some(child_inode) => current_inode = child_inode,
-                None => return Ok(None),
-            }
-        }
-        
-        Ok(Some(current_inode))
-    }
+        let gdrive_id = id_of(replaced).fetch_optional(&mut *tx).await?
+            .with_context(|| format!("inode {} sin gdrive_id", replaced))?;
+        let own_id = id_of(inode).fetch_optional(&mut *tx).await?
+            .with_context(|| format!("inode {} sin gdrive_id", inode))?;
+        let was_created = !sqlx::query_scalar::<_, bool>("SELECT pending_create FROM inodes WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_one(&mut *tx)
+            .await?;
 
-    /// Resuelve un inode a su path relativo reconstruyendo la jerarquía
-    pub async fn resolve_inode_to_relative_path(&self, inode: u64) -> Result<Option<String>> {
-        if inode == 1 {
-            return Ok(Some("".to_string()));
-        }
+        // Un temporal ignorado (ej. `*.tmp`) vuelve a contar como pendiente
+        sqlx::query("UPDATE sync_state SET status = 'synced' WHERE inode = ? AND status = 'ignored'")
+            .bind(inode as i64)
+            .execute(&mut *tx)
+            .await?;
+        mark_dirty_in(&mut tx, inode).await?;
 
-        let mut current_inode = inode;
-        let mut path_parts = Vec::new();
-
-        while current_inode != 1 {
-            let row = sqlx::query_as::<_, (i64, String)>(
-                "SELECT parent_inode, name FROM dentry WHERE child_inode = ?"
-            )
-            .bind(current_inode as i64)
-            .fetch_optional(&self.pool)
+        sqlx::query(
+            "UPDATE attrs SET (ctime, mode, mime_type, can_move, shared, owned_by_me, web_view_link, starred, origin_client, pin_hint, description) =
+                (SELECT ctime, mode, mime_type, can_move, shared, owned_by_me, web_view_link, starred, origin_client, pin_hint, description
+                 FROM attrs WHERE inode = ?2)
+             WHERE inode = ?1"
+        )
+        .bind(inode as i64)
+        .bind(replaced as i64)
+        .execute(&mut *tx)
+        .await?;
+        // Mientras es dirty la disponibilidad no cuenta en los contadores de carpetas
+        sqlx::query(
+            "UPDATE sync_state SET (version, availability, remote_md5) =
+                (SELECT version, availability, remote_md5 FROM sync_state WHERE inode = ?2)
+             WHERE inode = ?1 AND EXISTS (SELECT 1 FROM sync_state WHERE inode = ?2)"
+        )
+        .bind(inode as i64)
+        .bind(replaced as i64)
+        .execute(&mut *tx)
+        .await?;
+        // Las capacidades siguen a su archivo de Drive: se intercambian (el
+        // huérfano conserva su `can_trash`). Se borran y se vuelven a insertar
+        // porque `inode` es la clave y debe existir en `inodes`
+        let caps = sqlx::query_as::<_, (i64, bool, bool, String)>(
+            "SELECT inode, can_edit, can_trash, owners FROM file_capabilities WHERE inode IN (?, ?)"
+        )
+        .bind(inode as i64)
+        .bind(replaced as i64)
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM file_capabilities WHERE inode IN (?, ?)")
+            .bind(inode as i64)
+            .bind(replaced as i64)
+            .execute(&mut *tx)
+            .await?;
+        for (owner, can_edit, can_trash, owners) in caps {
+            let target = if owner == inode as i64 { replaced } else { inode };
+            sqlx::query("INSERT INTO file_capabilities (inode, can_edit, can_trash, owners) VALUES (?, ?, ?, ?)")
+                .bind(target as i64)
+                .bind(can_edit)
+                .bind(can_trash)
+                .bind(owners)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query("UPDATE conflicts SET inode = ? WHERE inode = ?")
+            .bind(inode as i64)
+            .bind(replaced as i64)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM upload_ops WHERE inode IN (?, ?)")
+            .bind(inode as i64)
+            .bind(replaced as i64)
+            .execute(&mut *tx)
             .await?;
 
-            if let Some((parent_inode, name)) = row {
-                path_parts.push(name);
-                current_inode = parent_inode as u64;
-            } else {
-                return Ok(None); // Inodo huérfano
+        if was_created {
+            // El contenido del original queda en la caché con su ID, que ahora
+            // es de `inode`: el tombstone no conserva chunks
+            sqlx::query("DELETE FROM file_cache_chunks WHERE inode = ?")
+                .bind(replaced as i64)
+                .execute(&mut *tx)
+                .await?;
+            // `gdrive_id` es UNIQUE: el intercambio pasa por un ID provisional
+            for (id, target) in [(format!("{}~", own_id), inode), (own_id.clone(), replaced)] {
+                sqlx::query("UPDATE inodes SET gdrive_id = ? WHERE inode = ?")
+                    .bind(id)
+                    .bind(target as i64)
+                    .execute(&mut *tx)
+                    .await?;
             }
+            mark_created_in(&mut tx, inode, &gdrive_id).await?;
+            soft_delete_in(&mut tx, &own_id).await?;
+            sqlx::query("INSERT INTO upload_ops (inode, op) VALUES (?, ?)")
+                .bind(replaced as i64)
+                .bind(UploadOp::Delete.as_str())
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            // El original sale de la DB (descuenta sus contadores) y su ID pasa al nuevo
+            hard_delete_in(&mut tx, replaced).await?;
+            mark_created_in(&mut tx, inode, &gdrive_id).await?;
         }
+        tx.commit().await?;
+        Ok(gdrive_id)
+    }
 
-        path_parts.reverse();
-        Ok(Some(path_parts.join("/")))
+    /// Obtiene el nombre (dentry) de un inode
+    pub async fn get_file_name(&self, inode: u64) -> Result<Option<String>> {
+        let name = sqlx::query_scalar::<_, String>("SELECT name FROM dentry WHERE child_inode = ? LIMIT 1")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(name)
     }
 
-    /// Listar contenido de un directorio (para readdir simple)
-    pub async fn list_children(&self, parent_inode: u64) -> Result<Vec<(u64, String, bool)>> {
-        let children = sqlx::query_as::<_, (i64, String, bool)>(
-            r#"
-            SELECT d.child_inode, d.name, a.is_dir 
-            FROM dentry d
-            JOIN attrs a ON d.child_inode = a.inode
-            WHERE d.parent_inode = ?
-            ORDER BY d.name
-            "#
+    /// Nombre del archivo en Drive (difiere del local solo en duplicados con sufijo)
+    pub async fn get_remote_name(&self, inode: u64) -> Result<Option<String>> {
+        let name = sqlx::query_scalar::<_, String>(
+            "SELECT COALESCE(remote_name, name) FROM dentry WHERE child_inode = ? LIMIT 1"
         )
-        .bind(parent_inode as i64)
-        .fetch_all(&self.pool)
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
         .await?;
-        
-        Ok(children.into_iter()
-            .map(|(inode, name, is_dir)| (inode as u64, name, is_dir))
-            .collect())
+        Ok(name)
     }
 
-    /// Cuenta el número de hijos de un directorio (para verificación rápida de paginación)
-    /// Esta operación es O(1) con el índice de parent_inode
-    pub async fn count_children(&self, parent_inode: u64) -> Result<u64> {
-        let count: i64 = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM dentry WHERE parent_inode = ?"
-        )
-        .bind(parent_inode as i64)
-        .fetch_one(&self.pool)
-        .await?;
-        
-        Ok(count as u64)
+    /// Obtiene el inode del directorio padre de un inode
+    pub async fn get_parent_inode(&self, inode: u64) -> Result<Option<u64>> {
+        let parent = sqlx::query_scalar::<_, i64>("SELECT parent_inode FROM dentry WHERE child_inode = ? LIMIT 1")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(parent.map(|p| p as u64))
     }
 
-    /// Cuenta el número de hijos de un directorio que NO son propiedad del usuario
-    /// y que están en el root (usado para la carpeta compartida virtual)
-    pub async fn count_non_owned_root_children(&self) -> Result<u64> {
-        let count: i64 = sqlx::query_scalar(
-            r#"
-            SELECT COUNT(*) 
-            FROM dentry d
-            JOIN attrs a ON d.child_inode = a.inode
-            WHERE d.parent_inode = 1 AND a.owned_by_me = 0
-            "#
+    /// Carpeta y nombre con los que aparece un inode
+    pub async fn get_dentry(&self, inode: u64) -> Result<Option<(u64, String)>> {
+        let dentry = sqlx::query_as::<_, (i64, String)>(
+            "SELECT parent_inode, name FROM dentry WHERE child_inode = ? LIMIT 1"
         )
-        .fetch_one(&self.pool)
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
         .await?;
-        
-        Ok(count as u64)
+        Ok(dentry.map(|(parent, name)| (parent as u64, name)))
     }
 
-    /// Verifica si la tabla de inodos está vacía (excepto el root si existe)
-    pub async fn is_empty(&self) -> Result<bool> {
-        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM inodes")
-            .fetch_one(&self.pool)
+    /// Cambia el nombre de la dentry de un inode (mismo padre)
+    pub async fn rename_dentry(&self, inode: u64, new_name: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let parent = sqlx::query_scalar::<_, i64>("SELECT parent_inode FROM dentry WHERE child_inode = ? LIMIT 1")
+            .bind(inode as i64)
+            .fetch_optional(&mut *tx)
             .await?;
-        Ok(count <= 1) // 1 si solo existe el root, 0 si está totalmente vacía
+        sqlx::query("UPDATE dentry SET name = ? WHERE child_inode = ?")
+            .bind(new_name)
+            .bind(inode as i64)
+            .execute(&mut *tx)
+            .await?;
+        touch_dirs(&mut tx, parent.map(|p| p as u64)).await?;
+        tx.commit().await?;
+        Ok(())
     }
 
-    /// Obtiene o desarrolla un inodo para un gdrive_id dado
-    pub async fn get_or_create_inode(&self, gdrive_id: &str) -> Result<u64> {
-        // Intentar obtener existente
-        let existing = sqlx::query_scalar::<_, i64>("SELECT inode FROM inodes WHERE gdrive_id = ?")
-            .bind(gdrive_id)
-            .fetch_optional(&self.pool)
+    /// Mueve la dentry de un inode a otro padre y nombre
+    pub async fn move_dentry(&self, inode: u64, new_parent: u64, new_name: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let old_parent = sqlx::query_scalar::<_, i64>("SELECT parent_inode FROM dentry WHERE child_inode = ? LIMIT 1")
+            .bind(inode as i64)
+            .fetch_optional(&mut *tx)
+            .await?;
+        sqlx::query("UPDATE dentry SET parent_inode = ?, name = ? WHERE child_inode = ?")
+            .bind(new_parent as i64)
+            .bind(new_name)
+            .bind(inode as i64)
+            .execute(&mut *tx)
             .await?;
+        touch_dirs(&mut tx, old_parent.map(|p| p as u64).into_iter().chain([new_parent])).await?;
+        tx.commit().await?;
+        Ok(())
+    }
 
-        if let Some(inode) = existing {
-            return Ok(inode as u64);
+    /// Elimina la dentry (parent, name)
+    pub async fn remove_dentry(&self, parent: u64, name: &str) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query("DELETE FROM dentry WHERE parent_inode = ? AND name = ?")
+            .bind(parent as i64)
+            .bind(name)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() > 0 {
+            touch_dirs(&mut tx, [parent]).await?;
         }
+        tx.commit().await?;
+        Ok(())
+    }
 
-        // Crear nuevo
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64;
-
-        let insert_result = sqlx::query("INSERT INTO inodes (gdrive_id, created_at) VALUES (?, ?)")
-            .bind(gdrive_id)
-            .bind(now)
-            .execute(&self.pool)
-            .await;
+    /// Obtiene el estado de sincronización de un inode (None si no tiene fila en sync_state)
+    pub async fn sync_state(&self, inode: u64) -> Result<Option<SyncStateRow>> {
+        let row = sqlx::query_as::<_, (Option<bool>, Option<i64>, bool, String, i64)>(
+            "SELECT dirty, deleted_at, COALESCE(uploading, 0), status, retry_count FROM sync_state WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
 
-        match insert_result {
-            Ok(result) => Ok(result.last_insert_rowid() as u64),
-            Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
-                // Si hubo una colisión durante la inserción simultánea, simplemente lo leemos
-                let existing = sqlx::query_scalar::<_, i64>("SELECT inode FROM inodes WHERE gdrive_id = ?")
-                    .bind(gdrive_id)
-                    .fetch_one(&self.pool)
-                    .await?;
-                Ok(existing as u64)
-            }
-            Err(e) => Err(e.into()),
-        }
+        Ok(row.map(|(dirty, deleted_at, uploading, status, retry_count)| SyncStateRow {
+            dirty: dirty.unwrap_or(false),
+            deleted_at,
+            uploading,
+            status: UploadStatus::parse(&status),
+            retry_count: retry_count as u32,
+        }))
     }
 
-    /// Obtiene o crea inodos para una lista de gdrive_ids de forma masiva
-    pub async fn get_or_create_inodes_bulk(&self, gdrive_ids: &[String]) -> Result<std::collections::HashMap<String, u64>> {
-        if gdrive_ids.is_empty() {
-            return Ok(std::collections::HashMap::new());
-        }
-
-        let mut results = std::collections::HashMap::new();
+    /// Mensaje del último intento de subida fallido (solo si el estado es `error`)
+    pub async fn upload_error(&self, inode: u64) -> Result<Option<String>> {
+        let message = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT last_error FROM sync_state WHERE inode = ? AND status = 'error'"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?
+        .flatten();
+        Ok(message)
+    }
 
-        for batch in gdrive_ids.chunks(500) {
-            let mut tx = self.pool.begin().await?;
+    /// Registra un intento de subida fallido e incrementa `retry_count`.
+    /// `retry_at` (epoch en segundos) fija el próximo intento; `None` marca el
+    /// error como permanente y el uploader deja de reintentar hasta un nuevo cambio.
+    /// Retorna true si el inode no estaba ya en error con el mismo mensaje
+    /// (para no repetir la notificación en cada reintento).
+    pub async fn set_upload_error(&self, inode: u64, message: &str, retry_at: Option<i64>) -> Result<bool> {
+        let previous = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT last_error FROM sync_state WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.pool)
+        .await?
+        .flatten();
 
-            for id in batch {
-                // Intentar obtener existente
-                let existing: Option<i64> = sqlx::query_scalar("SELECT inode FROM inodes WHERE gdrive_id = ?")
-                    .bind(id)
-                    .fetch_optional(&mut *tx)
-                    .await?;
+        sqlx::query(
+            "UPDATE sync_state SET status = 'error', last_error = ?1, retry_count = retry_count + 1,
+                next_retry_at = ?2, error_permanent = (?2 IS NULL)
+             WHERE inode = ?3"
+        )
+            .bind(message)
+            .bind(retry_at)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
 
-                if let Some(inode) = existing {
-                    results.insert(id.clone(), inode as u64);
-                    continue;
-                }
+        Ok(previous.as_deref() != Some(message))
+    }
 
-                // Crear nuevo
-                let now = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)?
-                    .as_secs() as i64;
+    /// Lista los inodes dirty listos para subir: (inode, gdrive_id, eliminado).
+    /// Omite los que esperan su backoff (`next_retry_at` futuro) o tienen un error permanente.
+    pub async fn list_dirty_inodes(&self) -> Result<Vec<(u64, String, bool)>> {
+        let rows = sqlx::query_as::<_, (i64, String, Option<i64>)>(
+            "SELECT i.inode, i.gdrive_id, s.deleted_at
+             FROM inodes i
+             INNER JOIN sync_state s ON i.inode = s.inode
+             WHERE s.dirty = 1
+               AND s.error_permanent = 0
+               AND (s.next_retry_at IS NULL OR s.next_retry_at <= CAST(strftime('%s', 'now') AS INTEGER))"
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
 
-                let insert_result = sqlx::query("INSERT INTO inodes (gdrive_id, created_at) VALUES (?, ?)")
-                    .bind(id)
-                    .bind(now)
-                    .execute(&mut *tx)
-                    .await;
+        Ok(rows.into_iter()
+            .map(|(inode, gdrive_id, deleted_at)| (inode as u64, gdrive_id, deleted_at.is_some()))
+            .collect())
+    }
 
-                match insert_result {
-                    Ok(res) => {
-                        results.insert(id.clone(), res.last_insert_rowid() as u64);
-                    }
-                    Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
-                        let inode: i64 = sqlx::query_scalar("SELECT inode FROM inodes WHERE gdrive_id = ?")
-                            .bind(id)
-                            .fetch_one(&mut *tx)
-                            .await?;
-                        results.insert(id.clone(), inode as u64);
-                    }
-                    Err(e) => return Err(e.into()),
-                }
-            }
+    /// Archivos con la subida en error (reintentando o permanentes), para la lista
+    /// de problemas de la GUI. Los permanentes primero.
+    pub async fn list_upload_problems(&self) -> Result<Vec<UploadProblem>> {
+        let rows = sqlx::query_as::<_, (i64, Option<String>, String, i64, Option<i64>, bool)>(
+            "SELECT s.inode,
+                    COALESCE((SELECT name FROM dentry WHERE child_inode = s.inode LIMIT 1),
+                             (SELECT name FROM dentry_deleted WHERE child_inode = s.inode LIMIT 1)),
+                    COALESCE(s.last_error, ''), s.retry_count, s.next_retry_at, s.error_permanent
+             FROM sync_state s
+             WHERE s.status = 'error'
+             ORDER BY s.error_permanent DESC, s.retry_count DESC, s.inode"
+        )
+        .fetch_all(&self.pool)
+        .await?;
 
-            tx.commit().await?;
+        let mut problems = Vec::with_capacity(rows.len());
+        for (inode, name, error, retry_count, next_retry_at, permanent) in rows {
+            let inode = inode as u64;
+            // Ruta relativa si el archivo sigue en el árbol; si no, solo el nombre
+            let path = match self.resolve_inode_to_relative_path(inode).await? {
+                Some(path) => path,
+                None => name.unwrap_or_else(|| format!("inode {}", inode)),
+            };
+            problems.push(UploadProblem {
+                inode,
+                path,
+                error,
+                retry_count: retry_count as u32,
+                next_retry_at,
+                permanent,
+            });
         }
-
-        Ok(results)
+        Ok(problems)
     }
 
-    /// Inserta o actualiza metadatos de un archivo
-    pub async fn upsert_file_metadata(
+    /// Registra un conflicto: la edición local del inode se subió como
+    /// `conflict_name` (`conflict_gdrive_id`) porque el original cambió en Drive.
+    /// Retorna el id del conflicto.
+    pub async fn record_conflict(
         &self,
         inode: u64,
-        size: i64,
-        mtime: i64,
-        mode: u32,
-        is_dir: bool,
-        mime_type: Option<&str>,
-        can_move: bool,
-        shared: bool,
-        owned_by_me: bool,
-    ) -> Result<()> {
-        sqlx::query(
-            r#"
-            INSERT INTO attrs (inode, size, mtime, ctime, mode, is_dir, mime_type, can_move, shared, owned_by_me)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(inode) DO UPDATE SET
-                size = excluded.size,
-                mtime = excluded.mtime,
-                mode = excluded.mode,
-                is_dir = excluded.is_dir,
-                mime_type = excluded.mime_type,
-                can_move = excluded.can_move,
-                shared = excluded.shared,
-                owned_by_me = excluded.owned_by_me
-            "#
+        original_gdrive_id: &str,
+        conflict_gdrive_id: &str,
+        conflict_name: &str,
+        local_mtime: i64,
+        remote_mtime: Option<i64>,
+    ) -> Result<i64> {
+        let id = sqlx::query(
+            "INSERT INTO conflicts (inode, original_gdrive_id, conflict_gdrive_id, conflict_name, local_mtime, remote_mtime)
+             VALUES (?, ?, ?, ?, ?, ?)"
         )
         .bind(inode as i64)
-        .bind(size)
-        .bind(mtime)
-        .bind(mtime) // Usamos mtime como ctime por simplicidad inicial
-        .bind(mode as i32)
-        .bind(is_dir)
-        .bind(mime_type)
-        .bind(can_move)
-        .bind(shared)
-        .bind(owned_by_me)
+        .bind(original_gdrive_id)
+        .bind(conflict_gdrive_id)
+        .bind(conflict_name)
+        .bind(local_mtime)
+        .bind(remote_mtime)
         .execute(&self.pool)
-        .await?;
-
-        Ok(())
+        .await?
+        .last_insert_rowid();
+        Ok(id)
     }
 
-    /// Actualiza específicamente el campo de propiedad (para correcciones masivas)
-    pub async fn update_ownership(&self, inode: u64, owned_by_me: bool) -> Result<()> {
-        sqlx::query("UPDATE attrs SET owned_by_me = ? WHERE inode = ?")
-            .bind(owned_by_me)
-            .bind(inode as i64)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
-    }
+    /// Conflictos sin resolver, el más reciente primero
+    pub async fn list_conflicts(&self) -> Result<Vec<SyncConflict>> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            "SELECT id FROM conflicts WHERE resolved_at IS NULL ORDER BY created_at DESC, id DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
 
-    /// Actualiza la propiedad de múltiples archivos en una sola transacción
-    pub async fn update_bulk_ownership(&self, items: &[(u64, bool)]) -> Result<()> {
-        if items.is_empty() { return Ok(()); }
-        let mut tx = self.pool.begin().await?;
-        for (inode, owned) in items {
-            sqlx::query("UPDATE attrs SET owned_by_me = ? WHERE inode = ?")
-                .bind(owned)
-                .bind(*inode as i64)
-                .execute(&mut *tx)
-                .await?;
+        let mut conflicts = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(conflict) = self.get_conflict(id).await? {
+                conflicts.push(conflict);
+            }
         }
-        tx.commit().await?;
-        Ok(())
+        Ok(conflicts)
     }
 
-    /// Inserta o actualiza metadatos de múltiples archivos en una sola transacción
-    pub async fn upsert_bulk_file_metadata(&self, items: &[BulkFileMetadata]) -> Result<()> {
-        if items.is_empty() {
-            return Ok(());
-        }
-
-        let mut tx = self.pool.begin().await?;
-
-        for item in items {
-            sqlx::query(
-                r#"
-                INSERT INTO attrs (inode, size, mtime, ctime, mode, is_dir, mime_type, can_move, shared, owned_by_me)
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                ON CONFLICT(inode) DO UPDATE SET
-                    size = excluded.size,
-                    mtime = excluded.mtime,
-                    mode = excluded.mode,
-                    is_dir = excluded.is_dir,
-                    mime_type = excluded.mime_type,
-                    can_move = excluded.can_move,
-                    shared = excluded.shared,
-                    owned_by_me = excluded.owned_by_me
-                "#
-            )
-            .bind(item.inode as i64)
-            .bind(item.size)
-            .bind(item.mtime)
-            .bind(item.mtime)
-            .bind(item.mode as i32)
-            .bind(item.is_dir)
-            .bind(item.mime_type.as_deref())
-            .bind(item.can_move)
-            .bind(item.shared)
-            .bind(item.owned_by_me)
-            .execute(&mut *tx)
-            .await?;
-        }
+    /// Un conflicto (resuelto o no) con la ruta actual del original
+    pub async fn get_conflict(&self, id: i64) -> Result<Option<SyncConflict>> {
+        let row = sqlx::query_as::<_, (i64, String, String, String, i64, Option<i64>, i64, Option<String>)>(
+            "SELECT c.inode, c.original_gdrive_id, c.conflict_gdrive_id, c.conflict_name,
+                    c.local_mtime, c.remote_mtime, c.created_at,
+                    COALESCE((SELECT name FROM dentry WHERE child_inode = c.inode LIMIT 1),
+                             (SELECT name FROM dentry_deleted WHERE child_inode = c.inode LIMIT 1))
+             FROM conflicts c
+             WHERE c.id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
 
-        tx.commit().await?;
-        Ok(())
+        let Some((inode, original_gdrive_id, conflict_gdrive_id, conflict_name, local_mtime, remote_mtime, created_at, name)) = row else {
+            return Ok(None);
+        };
+        let inode = inode as u64;
+        // Ruta relativa si el original sigue en el árbol; si no, solo el nombre
+        let path = match self.resolve_inode_to_relative_path(inode).await? {
+            Some(path) => path,
+            None => name.unwrap_or_else(|| format!("inode {}", inode)),
+        };
+        Ok(Some(SyncConflict {
+            id,
+            inode,
+            path,
+            original_gdrive_id,
+            conflict_gdrive_id,
+            conflict_name,
+            local_mtime,
+            remote_mtime,
+            created_at,
+        }))
     }
 
-    /// Inserta o actualiza una entrada de directorio
-    /// IMPORTANTE: Un archivo solo puede tener UN parent. Antes de insertar,
-    /// eliminamos cualquier dentry existente para este child_inode.
-    pub async fn upsert_dentry(&self, parent_inode: u64, child_inode: u64, name: &str) -> Result<()> {
-        // 1. Eliminar cualquier dentry anterior para este child_inode
-        //    (un archivo solo puede estar en un directorio a la vez)
-        sqlx::query("DELETE FROM dentry WHERE child_inode = ?")
-            .bind(child_inode as i64)
-            .execute(&self.pool)
-            .await?;
-
-        // 2. Insertar el nuevo dentry
+    /// Marca un conflicto como resuelto (`resolution`: cómo lo resolvió el usuario)
+    pub async fn resolve_conflict(&self, id: i64, resolution: &str) -> Result<()> {
         sqlx::query(
-            r#"
-            INSERT INTO dentry (parent_inode, child_inode, name)
-            VALUES (?, ?, ?)
-            ON CONFLICT(parent_inode, name) DO UPDATE SET
-                child_inode = excluded.child_inode
-            "#
+            "UPDATE conflicts SET resolved_at = CAST(strftime('%s', 'now') AS INTEGER), resolution = ?
+             WHERE id = ? AND resolved_at IS NULL"
         )
-        .bind(parent_inode as i64)
-        .bind(child_inode as i64)
-        .bind(name)
+        .bind(resolution)
+        .bind(id)
         .execute(&self.pool)
         .await?;
-
         Ok(())
     }
 
-    // ============================================================
-    // Métodos para Sync Meta (persistencia de page tokens)
-    // ============================================================
-
-    /// Guarda o actualiza un valor en sync_meta
-    pub async fn set_sync_meta(&self, key: &str, value: &str) -> Result<()> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64;
-
+    /// Reinicia el backoff de un inode para que el próximo ciclo lo reintente
+    pub async fn retry_upload_now(&self, inode: u64) -> Result<()> {
         sqlx::query(
-            r#"
-            INSERT INTO sync_meta (key, value, updated_at)
-            VALUES (?, ?, ?)
-            ON CONFLICT(key) DO UPDATE SET
-                value = excluded.value,
-                updated_at = excluded.updated_at
-            "#
+            "UPDATE sync_state SET retry_count = 0, next_retry_at = NULL, error_permanent = 0
+             WHERE inode = ?"
         )
-        .bind(key)
-        .bind(value)
-        .bind(now)
+        .bind(inode as i64)
         .execute(&self.pool)
         .await?;
-
         Ok(())
     }
 
-    /// Inserta o actualiza múltiples entradas de directorio en una sola transacción
-    pub async fn upsert_bulk_dentries(&self, items: &[BulkDentry]) -> Result<()> {
-        if items.is_empty() {
-            return Ok(());
-        }
+    /// Bytes de contenido local pendiente de subir: tamaño de los archivos
+    /// dirty con una creación o escritura en el diario (los renombrados y las
+    /// eliminaciones no ocupan caché nueva)
+    pub async fn dirty_backlog_bytes(&self) -> Result<u64> {
+        let bytes: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(a.size), 0)
+             FROM sync_state s
+             INNER JOIN attrs a ON a.inode = s.inode
+             WHERE s.dirty = 1
+               AND s.deleted_at IS NULL
+               AND a.is_dir = 0
+               AND EXISTS (SELECT 1 FROM upload_ops o WHERE o.inode = s.inode AND o.op IN ('create', 'write'))"
+        )
+        .fetch_one(&self.read_pool)
+        .await?;
+        Ok(bytes.max(0) as u64)
+    }
 
-        let mut tx = self.pool.begin().await?;
+    /// Cuenta inodes dirty (FUSE) y archivos dirty de Local Sync
+    pub async fn count_dirty(&self) -> Result<(u64, u64)> {
+        let fuse: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sync_state WHERE dirty = 1")
+            .fetch_one(&self.read_pool)
+            .await?;
+        let local: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM local_sync_files WHERE dirty = 1")
+            .fetch_one(&self.read_pool)
+            .await?;
+        Ok((fuse as u64, local as u64))
+    }
 
-        for item in items {
-            // 1. Eliminar cualquier dentry anterior para este child_inode
-            sqlx::query("DELETE FROM dentry WHERE child_inode = ?")
-                .bind(item.child_inode as i64)
-                .execute(&mut *tx)
-                .await?;
+    /// Cuenta inodes cuya última subida falló (los de `list_upload_problems`)
+    pub async fn count_upload_errors(&self) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sync_state WHERE status = 'error'")
+            .fetch_one(&self.read_pool)
+            .await?;
+        Ok(count as u64)
+    }
 
-            // 2. Insertar el nuevo dentry
-            sqlx::query(
-                r#"
-                INSERT INTO dentry (parent_inode, child_inode, name)
-                VALUES (?, ?, ?)
-                ON CONFLICT(parent_inode, name) DO UPDATE SET
-                    child_inode = excluded.child_inode
-                "#
-            )
-            .bind(item.parent_inode as i64)
-            .bind(item.child_inode as i64)
-            .bind(&item.name)
-            .execute(&mut *tx)
+    /// Deshace un soft delete pendiente (sin tocar dirty ni contadores)
+    pub async fn clear_deleted_at(&self, inode: u64) -> Result<()> {
+        sqlx::query("UPDATE sync_state SET deleted_at = NULL WHERE inode = ?")
+            .bind(inode as i64)
+            .execute(&self.pool)
             .await?;
-        }
+        Ok(())
+    }
 
-        tx.commit().await?;
+    /// Verifica si un inode es directorio (None si no tiene attrs)
+    pub async fn is_dir(&self, inode: u64) -> Result<Option<bool>> {
+        let is_dir = sqlx::query_scalar::<_, bool>("SELECT is_dir FROM attrs WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(is_dir)
+    }
+
+    /// Obtiene el tamaño registrado de un inode
+    pub async fn get_size(&self, inode: u64) -> Result<Option<i64>> {
+        let size = sqlx::query_scalar::<_, i64>("SELECT size FROM attrs WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(size)
+    }
+
+    /// Obtiene el mtime registrado de un inode
+    pub async fn get_mtime(&self, inode: u64) -> Result<Option<i64>> {
+        let mtime = sqlx::query_scalar::<_, i64>("SELECT mtime FROM attrs WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(mtime)
+    }
+
+    pub async fn set_size(&self, inode: u64, size: i64) -> Result<()> {
+        sqlx::query("UPDATE attrs SET size = ? WHERE inode = ?")
+            .bind(size)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
         Ok(())
     }
 
-    /// Obtiene un valor de sync_meta
-    pub async fn get_sync_meta(&self, key: &str) -> Result<Option<String>> {
-        let row = sqlx::query_scalar::<_, String>(
-            "SELECT value FROM sync_meta WHERE key = ?"
-        )
-        .bind(key)
-        .fetch_optional(&self.pool)
-        .await?;
+    /// MIME type de Drive (p. ej. al convertirse en documento de Google)
+    pub async fn set_mime_type(&self, inode: u64, mime_type: &str) -> Result<()> {
+        sqlx::query("UPDATE attrs SET mime_type = ? WHERE inode = ?")
+            .bind(mime_type)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
-        Ok(row)
+    /// Fija el mtime con precisión de nanosegundos
+    pub async fn set_mtime(&self, inode: u64, mtime: i64, nsec: u32) -> Result<()> {
+        sqlx::query("UPDATE attrs SET mtime = ?, mtime_nsec = ? WHERE inode = ?")
+            .bind(mtime)
+            .bind(nsec as i64)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
     }
 
-    /// Elimina una clave de sync_meta
-    pub async fn delete_sync_meta(&self, key: &str) -> Result<()> {
-        sqlx::query("DELETE FROM sync_meta WHERE key = ?")
-            .bind(key)
+    /// Fecha de creación (`createdTime` de Drive, o la hora de `create`/`mkdir` local)
+    pub async fn set_created_time(&self, inode: u64, created_time: i64) -> Result<()> {
+        sqlx::query("UPDATE attrs SET created_time = ? WHERE inode = ?")
+            .bind(created_time)
+            .bind(inode as i64)
             .execute(&self.pool)
             .await?;
         Ok(())
     }
 
-    /// Verifica si existen chunks cacheados para un inodo
-    pub async fn has_any_chunks(&self, inode: u64) -> Result<bool> {
-        let count: i64 = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM file_cache_chunks WHERE inode = ?"
-        )
-        .bind(inode as i64)
-        .fetch_one(&self.pool)
-        .await?;
+    /// Actualiza tamaño y mtime tras una escritura
+    pub async fn set_size_and_mtime(&self, inode: u64, size: i64, mtime: i64) -> Result<()> {
+        sqlx::query("UPDATE attrs SET size = ?, mtime = ?, mtime_nsec = 0 WHERE inode = ?")
+            .bind(size)
+            .bind(mtime)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
-        Ok(count > 0)
+    pub async fn set_mode(&self, inode: u64, mode: u32) -> Result<()> {
+        sqlx::query("UPDATE attrs SET mode = ? WHERE inode = ?")
+            .bind(mode)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
     }
 
-    // ============================================================
-    // Métodos para Conflict Detection (Remote MD5 Tracking)
-    // ============================================================
+    pub async fn set_can_move(&self, inode: u64, can_move: bool) -> Result<()> {
+        sqlx::query("UPDATE attrs SET can_move = ? WHERE inode = ?")
+            .bind(can_move)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
-    /// Obtiene el MD5 remoto conocido para un archivo
-    pub async fn get_remote_md5(&self, inode: u64) -> Result<Option<String>> {
-        let row = sqlx::query_scalar::<_, String>(
-            "SELECT remote_md5 FROM sync_state WHERE inode = ?"
+    /// Datos necesarios para leer el contenido de un inode:
+    /// (gdrive_id, mime_type, size, shortcut_target_id)
+    pub async fn get_content_source(&self, inode: u64) -> Result<Option<(String, Option<String>, i64, Option<String>)>> {
+        let row = sqlx::query_as::<_, (String, Option<String>, Option<i64>, Option<String>)>(
+            "SELECT i.gdrive_id, a.mime_type, a.size, a.shortcut_target_id
+             FROM inodes i
+             LEFT JOIN attrs a ON i.inode = a.inode
+             WHERE i.inode = ?"
         )
         .bind(inode as i64)
-        .fetch_optional(&self.pool)
+        .fetch_optional(&self.read_pool)
         .await?;
 
-        Ok(row)
+        Ok(row.map(|(gdrive_id, mime, size, target)| (gdrive_id, mime, size.unwrap_or(0), target)))
     }
 
-    /// Actualiza el MD5 remoto conocido para un archivo
-    pub async fn set_remote_md5(&self, inode: u64, md5: &str) -> Result<()> {
-        sqlx::query(
-            r#"
-            INSERT INTO sync_state (inode, dirty, version, remote_md5)
-            VALUES (?, 0, 0, ?)
-            ON CONFLICT(inode) DO UPDATE SET remote_md5 = excluded.remote_md5
-            "#
+    /// Obtiene los contadores de descendientes (dirty, synced) de un directorio
+    pub async fn get_dir_counters(&self, inode: u64) -> Result<Option<(i64, i64)>> {
+        let counters = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT dirty_desc_count, synced_desc_count FROM dir_counters WHERE inode = ?"
         )
         .bind(inode as i64)
-        .bind(md5)
-        .execute(&self.pool)
+        .fetch_optional(&self.read_pool)
         .await?;
-
-        Ok(())
+        Ok(counters)
     }
 
-    // ============================================================
-    // Protocolo "Burbujeo de Estados" — Contadores pre-calculados
-    // ============================================================
-
-    /// Burbujea un cambio de estado desde un archivo hacia todos sus directorios ancestros.
-    /// `delta_dirty` y `delta_synced` son incrementos (pueden ser negativos).
-    /// Ejemplo: archivo pasa de synced→dirty → delta_dirty=+1, delta_synced=-1
-    pub async fn bubble_state_change(
-        &self,
-        child_inode: u64,
-        delta_dirty: i32,
-        delta_synced: i32,
-    ) -> Result<()> {
-        if delta_dirty == 0 && delta_synced == 0 {
-            return Ok(());
-        }
-
+    /// Aplica un delta a los contadores de `dir_inode` y todos sus ancestros.
+    /// A diferencia de `bubble_state_change`, empieza en el propio directorio
+    /// (útil cuando la dentry del hijo ya apunta a otro padre). Nunca baja de 0.
+    pub async fn adjust_dir_counters_from(&self, dir_inode: u64, dirty_delta: i32, synced_delta: i32) -> Result<()> {
         sqlx::query(
             r#"
             WITH RECURSIVE ancestors AS (
-                SELECT parent_inode FROM dentry WHERE child_inode = ?1
+                SELECT ?1 as anc_inode
                 UNION ALL
                 SELECT d.parent_inode FROM dentry d
-                JOIN ancestors a ON d.child_inode = a.parent_inode
-                WHERE a.parent_inode > 1
+                JOIN ancestors a ON d.child_inode = a.anc_inode
+                WHERE a.anc_inode > 0
             )
             UPDATE dir_counters
             SET dirty_desc_count = MAX(0, dirty_desc_count + ?2),
                 synced_desc_count = MAX(0, synced_desc_count + ?3)
-            WHERE inode IN (SELECT parent_inode FROM ancestors)
+            WHERE inode IN (SELECT anc_inode FROM ancestors)
             "#
         )
-        .bind(child_inode as i64)
-        .bind(delta_dirty)
-        .bind(delta_synced)
+        .bind(dir_inode as i64)
+        .bind(dirty_delta)
+        .bind(synced_delta)
         .execute(&self.pool)
         .await?;
+        Ok(())
+    }
 
-        // También actualizar root (inode 1) si el archivo cuelga de él
-        sqlx::query(
+    /// Página de hijos para readdirplus: `limit` hijos a partir de `offset`
+    /// en el mismo orden por nombre, cada uno con su fila completa de `attrs`
+    /// (`ChildEntry`) para no consultar los atributos entrada por entrada. En
+    /// la raíz omite lo que no es propio (se lista en `SHARED`), para que el
+    /// offset de FUSE cuente solo las entradas que se muestran
+    pub async fn list_children_extended_page(&self, parent_inode: u64, offset: u64, limit: u64) -> Result<Vec<ChildEntry>> {
+        let children = sqlx::query_as::<_, ChildEntry>(
             r#"
-            UPDATE dir_counters
-            SET dirty_desc_count = MAX(0, dirty_desc_count + ?2),
-                synced_desc_count = MAX(0, synced_desc_count + ?3)
-            WHERE inode = 1 AND EXISTS (
-                WITH RECURSIVE ancestors AS (
-                    SELECT parent_inode FROM dentry WHERE child_inode = ?1
-                    UNION ALL
-                    SELECT d.parent_inode FROM dentry d
-                    JOIN ancestors a ON d.child_inode = a.parent_inode
-                    WHERE a.parent_inode > 1
-                )
-                SELECT 1 FROM ancestors WHERE parent_inode = 1
-            )
+            SELECT
+                a.*,
+                COALESCE(i.generation, 0) AS generation,
+                d.name,
+                i.gdrive_id
+            FROM dentry d
+            JOIN attrs a ON d.child_inode = a.inode
+            JOIN inodes i ON d.child_inode = i.inode
+            WHERE d.parent_inode = ? AND (d.parent_inode != 1 OR a.owned_by_me = 1)
+            ORDER BY d.name
+            LIMIT ? OFFSET ?
             "#
         )
-        .bind(child_inode as i64)
-        .bind(delta_dirty)
-        .bind(delta_synced)
-        .execute(&self.pool)
+        .bind(parent_inode as i64)
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(&self.read_pool)
         .await?;
 
-        Ok(())
+        Ok(children)
     }
 
-    /// Inicializa una fila en dir_counters para un directorio si no existe.
-    pub async fn ensure_dir_counter(&self, inode: u64) -> Result<()> {
-        sqlx::query(
-            "INSERT OR IGNORE INTO dir_counters (inode, dirty_desc_count, synced_desc_count) VALUES (?, 0, 0)"
-        )
-        .bind(inode as i64)
-        .execute(&self.pool)
+    /// Listar contenido compartido de la raíz (archivos no propios que cuelgan del inode 1)
+    pub async fn list_non_owned_root_children(&self) -> Result<Vec<(u64, String, bool, Option<String>, String)>> {
+        let children = sqlx::query_as::<_, (i64, String, bool, Option<String>, String)>(
+            r#"
+            SELECT 
+                d.child_inode, 
+                d.name, 
+                a.is_dir,
+                a.mime_type,
+                i.gdrive_id
+            FROM dentry d
+            JOIN attrs a ON d.child_inode = a.inode
+            JOIN inodes i ON d.child_inode = i.inode
+            WHERE d.parent_inode = 1 AND a.owned_by_me = 0
+            ORDER BY d.name
+            "#
+        )
+        .fetch_all(&self.read_pool)
         .await?;
-        Ok(())
+        
+        Ok(children.into_iter()
+            .map(|(inode, name, is_dir, mime, gdrive_id)| (inode as u64, name, is_dir, mime, gdrive_id))
+            .collect())
     }
 
-    /// Marca un inode como dirty y burbujea el cambio a sus ancestros.
-    /// Detecta automáticamente el estado previo para calcular el delta correcto.
-    /// Solo burbujea para archivos (is_dir=0).
-    pub async fn set_dirty_and_bubble(&self, inode: u64) -> Result<()> {
-        // Obtener estado previo y si es directorio
-        let prev = sqlx::query_as::<_, (Option<String>, Option<bool>, Option<i64>)>(
-            "SELECT s.availability, s.dirty, s.deleted_at FROM sync_state s WHERE s.inode = ?"
+    /// Lista los archivos y carpetas destacados (carpeta virtual Starred),
+    /// estén donde estén en el árbol. Mismo formato que `list_non_owned_root_children`.
+    pub async fn list_starred(&self) -> Result<Vec<(u64, String, bool, Option<String>, String)>> {
+        let children = sqlx::query_as::<_, (i64, String, bool, Option<String>, String)>(
+            r#"
+            SELECT
+                d.child_inode,
+                d.name,
+                a.is_dir,
+                a.mime_type,
+                i.gdrive_id
+            FROM dentry d
+            JOIN attrs a ON d.child_inode = a.inode
+            JOIN inodes i ON d.child_inode = i.inode
+            WHERE a.starred = 1
+            ORDER BY d.name
+            "#
         )
-        .bind(inode as i64)
-        .fetch_optional(&self.pool)
+        .fetch_all(&self.read_pool)
         .await?;
 
-        let was_dirty = prev.as_ref().map(|(_, d, del)| {
-            d.unwrap_or(false) || del.map(|v| v > 0).unwrap_or(false)
-        }).unwrap_or(false);
-
-        let was_synced = prev.as_ref().map(|(av, d, del)| {
-            let is_local_online = av.as_deref().unwrap_or("online_only") == "local_online";
-            let not_dirty = !d.unwrap_or(false);
-            let not_deleted = del.unwrap_or(0) == 0;
-            is_local_online && not_dirty && not_deleted
-        }).unwrap_or(false);
-
-        // Marcar como dirty
-        sqlx::query(
-            "INSERT INTO sync_state (inode, dirty, version, md5_checksum) VALUES (?, 1, 0, NULL) ON CONFLICT(inode) DO UPDATE SET dirty = 1"
-        )
-        .bind(inode as i64)
-        .execute(&self.pool)
-        .await?;
+        Ok(children.into_iter()
+            .map(|(inode, name, is_dir, mime, gdrive_id)| (inode as u64, name, is_dir, mime, gdrive_id))
+            .collect())
+    }
 
-        // Solo burbujear para archivos
-        let is_dir: Option<bool> = sqlx::query_scalar(
-            "SELECT is_dir FROM attrs WHERE inode = ?"
+    /// Busca por nombre un destacado (lookup dentro de Starred/). Con nombres
+    /// repetidos gana el inode más antiguo, igual que en el listado.
+    pub async fn lookup_starred(&self, name: &str) -> Result<Option<u64>> {
+        let inode: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT d.child_inode
+            FROM dentry d
+            JOIN attrs a ON d.child_inode = a.inode
+            WHERE a.starred = 1 AND d.name = ?
+            ORDER BY d.child_inode
+            LIMIT 1
+            "#
         )
-        .bind(inode as i64)
-        .fetch_optional(&self.pool)
+        .bind(name)
+        .fetch_optional(&self.read_pool)
         .await?;
+        Ok(inode.map(|i| i as u64))
+    }
 
-        if is_dir == Some(false) {
-            // El archivo ahora es dirty seguro
-            if was_dirty {
-                // Ya era dirty, no hay cambio en contadores
-            } else if was_synced {
-                // Era synced, ahora es dirty
-                self.bubble_state_change(inode, 1, -1).await?;
-            } else {
-                // Archivo no era dirty ni synced (ej: online_only), ahora es dirty
-                self.bubble_state_change(inode, 1, 0).await?;
-            }
-        }
+    /// Indica si un inode está destacado en Drive
+    pub async fn is_starred(&self, inode: u64) -> Result<bool> {
+        let starred: Option<bool> = sqlx::query_scalar("SELECT starred FROM attrs WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(starred.unwrap_or(false))
+    }
 
+    /// Guarda el flag `starred` de Drive para un inode
+    pub async fn set_starred(&self, inode: u64, starred: bool) -> Result<()> {
+        sqlx::query("UPDATE attrs SET starred = ? WHERE inode = ?")
+            .bind(starred)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
         Ok(())
     }
 
-    /// Limpia el flag dirty y burbujea el cambio a los ancestros.
-    /// Solo burbujea para archivos (is_dir=0).
-    pub async fn clear_dirty_and_bubble(&self, inode: u64) -> Result<()> {
-        // Verificar estado previo
-        let prev = sqlx::query_as::<_, (Option<String>, bool, Option<i64>)>(
-            "SELECT availability, dirty, deleted_at FROM sync_state WHERE inode = ?"
+    /// Guarda las marcas `appProperties` de Drive de un inode. Una marca de
+    /// anclado cambiada aquí y aún sin publicar (`pin_hint_pending`) prevalece
+    /// sobre la remota
+    pub async fn set_app_marks(&self, inode: u64, marks: &AppMarks) -> Result<()> {
+        sqlx::query(
+            "UPDATE attrs SET origin_client = ?, pin_hint = CASE WHEN pin_hint_pending THEN pin_hint ELSE ? END
+             WHERE inode = ?"
         )
-        .bind(inode as i64)
-        .fetch_optional(&self.pool)
-        .await?;
-
-        let was_dirty = prev.as_ref().map(|(_, d, del)| {
-            *d || del.map(|v| v > 0).unwrap_or(false)
-        }).unwrap_or(false);
+            .bind(marks.origin_client.as_deref())
+            .bind(marks.pinned)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
-        let was_synced = prev.as_ref().map(|(av, d, del)| {
-            let is_local_online = av.as_deref().unwrap_or("online_only") == "local_online";
-            let not_dirty = !d;
-            let not_deleted = del.unwrap_or(0) == 0;
-            is_local_online && not_dirty && not_deleted
-        }).unwrap_or(false);
+    /// Instalación de G-DriveXP que creó el archivo (`None` si no lo creó G-DriveXP)
+    pub async fn get_origin_client(&self, inode: u64) -> Result<Option<String>> {
+        let origin: Option<Option<String>> = sqlx::query_scalar("SELECT origin_client FROM attrs WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(origin.flatten())
+    }
 
-        // Limpiar dirty
-        sqlx::query("UPDATE sync_state SET dirty = 0 WHERE inode = ?")
+    /// Guarda la descripción de Drive de un inode (`None` si no tiene)
+    pub async fn set_description(&self, inode: u64, description: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE attrs SET description = ? WHERE inode = ?")
+            .bind(description)
             .bind(inode as i64)
             .execute(&self.pool)
             .await?;
+        Ok(())
+    }
 
-        // Solo burbujear para archivos
-        let is_dir: Option<bool> = sqlx::query_scalar(
-            "SELECT is_dir FROM attrs WHERE inode = ?"
-        )
-        .bind(inode as i64)
-        .fetch_optional(&self.pool)
-        .await?;
-
-        if is_dir == Some(false) {
-            let curr = sqlx::query_as::<_, (Option<String>, bool, Option<i64>)>(
-                "SELECT availability, dirty, deleted_at FROM sync_state WHERE inode = ?"
-            )
+    /// Descripción de Drive del archivo o carpeta
+    pub async fn get_description(&self, inode: u64) -> Result<Option<String>> {
+        let description: Option<Option<String>> = sqlx::query_scalar("SELECT description FROM attrs WHERE inode = ?")
             .bind(inode as i64)
-            .fetch_one(&self.pool)
+            .fetch_optional(&self.read_pool)
             .await?;
+        Ok(description.flatten())
+    }
 
-            let is_dirty = curr.1 || curr.2.unwrap_or(0) > 0;
-            let is_synced = {
-                let is_local_online = curr.0.as_deref().unwrap_or("online_only") == "local_online";
-                let not_dirty = !curr.1;
-                let not_deleted = curr.2.unwrap_or(0) == 0;
-                is_local_online && not_dirty && not_deleted
-            };
+    /// Guarda el color de carpeta de Drive ("#rrggbb", `None` si no tiene)
+    pub async fn set_folder_color(&self, inode: u64, color: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE attrs SET folder_color = ? WHERE inode = ?")
+            .bind(color)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
-            let delta_dirty = if was_dirty && !is_dirty { -1 } else if !was_dirty && is_dirty { 1 } else { 0 };
-            let delta_synced = if was_synced && !is_synced { -1 } else if !was_synced && is_synced { 1 } else { 0 };
+    /// Color de carpeta de Drive ("#rrggbb")
+    pub async fn get_folder_color(&self, inode: u64) -> Result<Option<String>> {
+        let color: Option<Option<String>> = sqlx::query_scalar("SELECT folder_color FROM attrs WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(color.flatten())
+    }
 
-            if delta_dirty != 0 || delta_synced != 0 {
-                self.bubble_state_change(inode, delta_dirty, delta_synced).await?;
-            }
-        }
+    /// Guarda la marca de "mantenido en algún dispositivo" que el usuario
+    /// acaba de cambiar aquí. Queda pendiente de publicar en Drive hasta
+    /// `pin_hint_published`
+    pub async fn set_pin_hint(&self, inode: u64, pinned: bool) -> Result<()> {
+        sqlx::query("UPDATE attrs SET pin_hint = ?, pin_hint_pending = 1 WHERE inode = ?")
+            .bind(pinned)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
+    /// Marca como publicada la marca de anclado `pinned`. Si el usuario la
+    /// cambió otra vez mientras tanto, sigue pendiente
+    pub async fn pin_hint_published(&self, inode: u64, pinned: bool) -> Result<()> {
+        sqlx::query("UPDATE attrs SET pin_hint_pending = 0 WHERE inode = ? AND pin_hint = ?")
+            .bind(inode as i64)
+            .bind(pinned)
+            .execute(&self.pool)
+            .await?;
         Ok(())
     }
 
-    /// Limpia entradas dirty-delete que quedaron pendientes de sesiones anteriores.
-    /// Esto previene que el uploader envíe a papelera archivos que no fueron
-    /// realmente eliminados por el usuario (e.g., limpieza de huérfanos del mirror).
-    pub async fn clear_stale_dirty_deletes(&self) -> Result<usize> {
-        let result = sqlx::query(
-            "UPDATE sync_state SET dirty = 0, deleted_at = NULL WHERE dirty = 1 AND deleted_at IS NOT NULL"
+    /// Marcas de anclado cambiadas aquí que aún no se publicaron en Drive:
+    /// (inode, gdrive_id, anclado)
+    pub async fn unpublished_pin_hints(&self) -> Result<Vec<(u64, String, bool)>> {
+        let rows = sqlx::query_as::<_, (i64, String, bool)>(
+            r#"
+            SELECT a.inode, i.gdrive_id, a.pin_hint FROM attrs a
+            JOIN inodes i ON i.inode = a.inode
+            WHERE a.pin_hint_pending = 1
+            "#
         )
-        .execute(&self.pool)
+        .fetch_all(&self.read_pool)
         .await?;
-        let count = result.rows_affected() as usize;
-        if count > 0 {
-            tracing::info!("🧹 Limpiadas {} entradas dirty-delete stale de sesión anterior", count);
-        }
-        Ok(count)
+        Ok(rows.into_iter().map(|(inode, gdrive_id, pinned)| (inode as u64, gdrive_id, pinned)).collect())
     }
 
-    /// Recalcula todos los contadores de directorio desde cero.
-    /// Optimizado: Calcula de abajo hacia arriba (bottom-up) para evitar subconsultas recursivas lentas.
-    pub async fn rebuild_all_dir_counters(&self) -> Result<()> {
-        tracing::info!("Iniciando rebuild de contadores de directorio (optimizado)...");
+    /// Archivos y carpetas que otro dispositivo mantiene (`pin_hint`) y aquí
+    /// aún no: (inode, es carpeta)
+    pub async fn pending_pin_hints(&self) -> Result<Vec<(u64, bool)>> {
+        let rows = sqlx::query_as::<_, (i64, bool)>(
+            r#"
+            SELECT a.inode, a.is_dir FROM attrs a
+            LEFT JOIN sync_state s ON s.inode = a.inode
+            WHERE a.pin_hint = 1
+              AND COALESCE(s.availability, 'online_only') != 'local_online'
+              AND COALESCE(s.deleted_at, 0) = 0
+            "#
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+        Ok(rows.into_iter().map(|(inode, is_dir)| (inode as u64, is_dir)).collect())
+    }
 
-        // Fase 1 (TX corta): Limpiar tabla y rellenar con contadores a 0
-        {
-            let mut tx = self.pool.begin().await?;
-            sqlx::query("DELETE FROM dir_counters").execute(&mut *tx).await?;
-            sqlx::query(
-                "INSERT INTO dir_counters (inode, dirty_desc_count, synced_desc_count) SELECT inode, 0, 0 FROM attrs WHERE is_dir = 1"
-            )
-            .execute(&mut *tx)
-            .await?;
-            tx.commit().await?;
-        }
+    /// Resuelve un path relativo (desde el root del mirror) a su inode
+    pub async fn resolve_relative_path_to_inode(&self, relative_path: &str) -> Result<Option<u64>> {
+        let parts: Vec<&str> = relative_path.split('/').filter(|s| !s.is_empty()).collect();
 
-        // Fase 2 (solo lectura, sin TX de escritura): Obtener datos para cálculo en memoria
-        let dentry_rows: Vec<(i64, i64)> = sqlx::query_as("SELECT parent_inode, child_inode FROM dentry")
-            .fetch_all(&self.pool)
-            .await?;
+        // Si el path empieza con "SHARED", saltamos ese segmento virtual
+        // y seguimos resolviendo desde root (inode 1)
+        let parts_to_resolve = if parts.first() == Some(&"SHARED") {
+            &parts[1..]
+        } else {
+            &parts[..]
+        };
 
-        let file_states: Vec<(i64, bool, bool)> = sqlx::query_as(
+        Ok(self.resolve_path_components(parts_to_resolve).await?.map(|(inode, _)| inode))
+    }
+
+    /// Resuelve una secuencia de nombres desde root a (inode, gdrive_id) en una sola consulta.
+    ///
+    /// Las rutas ya resueltas salen de `PathCache` mientras nada cambie en
+    /// `dentry` ni `inodes`. Si no, un CTE recursivo recorre la jerarquía usando
+    /// la PK `(parent_inode, name)` de `dentry` en cada nivel, en lugar de una
+    /// consulta por componente.
+    pub async fn resolve_path_components(&self, parts: &[&str]) -> Result<Option<(u64, String)>> {
+        let key = PathCache::key(parts);
+        if let Some(resolved) = self.paths.get(&key) {
+            return Ok(resolved);
+        }
+        let generation = self.paths.snapshot();
+        let parts_json = serde_json::to_string(parts)?;
+
+        let row = sqlx::query_as::<_, (i64, String)>(
             r#"
-            SELECT
-                a.inode,
-                (s.dirty = 1 OR (s.deleted_at IS NOT NULL AND s.deleted_at > 0)) as is_dirty,
-                (COALESCE(s.availability, 'online_only') = 'local_online' AND COALESCE(s.dirty, 0) = 0 AND (s.deleted_at IS NULL OR s.deleted_at = 0)) as is_synced
-            FROM attrs a
-            LEFT JOIN sync_state s ON a.inode = s.inode
-            WHERE a.is_dir = 0
+            WITH RECURSIVE
+                parts(depth, name) AS (
+                    SELECT key + 1, value FROM json_each(?)
+                ),
+                walk(depth, inode) AS (
+                    SELECT 0, 1
+                    UNION ALL
+                    SELECT w.depth + 1, d.child_inode
+                    FROM walk w
+                    CROSS JOIN parts p
+                    CROSS JOIN dentry d
+                    WHERE p.depth = w.depth + 1
+                      AND d.parent_inode = w.inode
+                      AND d.name = p.name
+                )
+            SELECT inode, gdrive_id FROM inodes
+            WHERE inode = (SELECT inode FROM walk WHERE depth = ?)
             "#
         )
-        .fetch_all(&self.pool)
+        .bind(parts_json)
+        .bind(parts.len() as i64)
+        .fetch_optional(&self.read_pool)
         .await?;
 
-        // Fase 3 (cálculo en memoria, sin DB): Construir contadores
-        let mut child_to_parent = std::collections::HashMap::new();
-        for (parent, child) in dentry_rows {
-            child_to_parent.insert(child, parent);
+        let resolved = row.map(|(inode, gdrive_id)| (inode as u64, gdrive_id));
+        if let Some(generation) = generation {
+            self.paths.put(key, generation, resolved.clone());
         }
+        Ok(resolved)
+    }
 
-        let mut dirty_counts = std::collections::HashMap::new();
-        let mut synced_counts = std::collections::HashMap::new();
+    /// Busca archivos y carpetas por nombre en todo el Drive (índice FTS5 `dentry_fts`).
+    ///
+    /// Cada palabra de la consulta se trata como prefijo y todas deben aparecer
+    /// (sin distinguir mayúsculas ni acentos). Retorna `(inode, nombre, is_dir)`
+    /// ordenado por relevancia.
+    pub async fn search_names(&self, query: &str, limit: u32) -> Result<Vec<(u64, String, bool)>> {
+        let Some(fts_query) = fts_prefix_query(query) else {
+            return Ok(Vec::new());
+        };
 
-        for (inode, is_dirty, is_synced) in file_states {
-            if !is_dirty && !is_synced { continue; }
+        let rows = sqlx::query_as::<_, (i64, String, bool)>(
+            r#"
+            SELECT d.child_inode, d.name, COALESCE(a.is_dir, 0)
+            FROM dentry_fts f
+            JOIN dentry d ON d.rowid = f.rowid
+            LEFT JOIN attrs a ON a.inode = d.child_inode
+            WHERE dentry_fts MATCH ?
+            ORDER BY f.rank
+            LIMIT ?
+            "#
+        )
+        .bind(fts_query)
+        .bind(limit as i64)
+        .fetch_all(&self.read_pool)
+        .await?;
 
-            let mut curr = inode;
-            while let Some(&parent) = child_to_parent.get(&curr) {
-                if is_dirty {
-                    *dirty_counts.entry(parent).or_insert(0) += 1;
-                }
-                if is_synced {
-                    *synced_counts.entry(parent).or_insert(0) += 1;
-                }
-                if parent == 1 { break; }
-                curr = parent;
-            }
+        Ok(rows.into_iter()
+            .map(|(inode, name, is_dir)| (inode as u64, name, is_dir))
+            .collect())
+    }
+
+    /// Resuelve un inode a su path relativo reconstruyendo la jerarquía
+    pub async fn resolve_inode_to_relative_path(&self, inode: u64) -> Result<Option<String>> {
+        if inode == 1 {
+            return Ok(Some("".to_string()));
         }
 
-        // Fase 4 (escritura en batches): Volcar resultados a la DB
-        let all_updates: Vec<(i64, i64, i64)> = {
-            let mut updates = std::collections::HashMap::new();
-            for (inode, count) in dirty_counts {
-                updates.entry(inode).or_insert((0i64, 0i64)).0 = count;
-            }
-            for (inode, count) in synced_counts {
-                updates.entry(inode).or_insert((0i64, 0i64)).1 = count;
-            }
-            updates.into_iter().map(|(inode, (d, s))| (inode, d, s)).collect()
-        };
+        let mut current_inode = inode;
+        let mut path_parts = Vec::new();
 
-        for batch in all_updates.chunks(500) {
-            let mut tx = self.pool.begin().await?;
-            for &(inode, dirty, synced) in batch {
-                sqlx::query("UPDATE dir_counters SET dirty_desc_count = ?, synced_desc_count = ? WHERE inode = ?")
-                    .bind(dirty)
-                    .bind(synced)
-                    .bind(inode)
-                    .execute(&mut *tx)
-                    .await?;
+        while current_inode != 1 {
+            let row = sqlx::query_as::<_, (i64, String)>(
+                "SELECT parent_inode, name FROM dentry WHERE child_inode = ?"
+            )
+            .bind(current_inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+
+            if let Some((parent_inode, name)) = row {
+                path_parts.push(name);
+                current_inode = parent_inode as u64;
+            } else {
+                return Ok(None); // Inodo huérfano
             }
-            tx.commit().await?;
         }
 
-        tracing::info!("Contadores de directorio recalculados (operación optimizada)");
-        Ok(())
-    }
-
-    /// Verifica si un inode tiene cambios locales pendientes de subir
-    pub async fn is_dirty(&self, inode: u64) -> Result<bool> {
-        let dirty = sqlx::query_scalar::<_, bool>(
-            "SELECT dirty FROM sync_state WHERE inode = ?"
-        )
-        .bind(inode as i64)
-        .fetch_optional(&self.pool)
-        .await?
-        .unwrap_or(false);
-
-        Ok(dirty)
+        path_parts.reverse();
+        Ok(Some(path_parts.join("/")))
     }
 
-    /// Calcula el estado de sincronización agregado de todos los archivos
-    /// descendientes de un directorio, de forma recursiva via CTE.
-    /// Retorna (has_local_only, has_synced, total_files).
-    pub async fn get_directory_aggregate_status(&self, parent_inode: u64) -> Result<(bool, bool, i64)> {
-        // O(1): lectura directa de contadores pre-calculados
-        let row = sqlx::query_as::<_, (i64, i64)>(
-            "SELECT dirty_desc_count, synced_desc_count FROM dir_counters WHERE inode = ?"
+    /// Listar contenido de un directorio (para readdir simple)
+    pub async fn list_children(&self, parent_inode: u64) -> Result<Vec<(u64, String, bool)>> {
+        let children = sqlx::query_as::<_, (i64, String, bool)>(
+            r#"
+            SELECT d.child_inode, d.name, a.is_dir 
+            FROM dentry d
+            JOIN attrs a ON d.child_inode = a.inode
+            WHERE d.parent_inode = ?
+            ORDER BY d.name
+            "#
         )
         .bind(parent_inode as i64)
-        .fetch_optional(&self.pool)
+        .fetch_all(&self.read_pool)
         .await?;
-
-        match row {
-            Some((dirty, synced)) => {
-                let total = dirty + synced;
-                Ok((dirty > 0, synced > 0, total))
-            }
-            None => Ok((false, false, 0)),
-        }
+        
+        Ok(children.into_iter()
+            .map(|(inode, name, is_dir)| (inode as u64, name, is_dir))
+            .collect())
     }
 
-    /// Estado agregado para la carpeta virtual SHARED.
-    /// Usa contadores pre-calculados con SHARED_INODE como clave.
-    pub async fn get_shared_directory_aggregate_status(&self) -> Result<(bool, bool, i64)> {
-        // SHARED_INODE = 0xFFFFFFFFFFFFFFFE
-        let shared_inode = 0xFFFFFFFFFFFFFFFEu64;
-        let row = sqlx::query_as::<_, (i64, i64)>(
-            "SELECT dirty_desc_count, synced_desc_count FROM dir_counters WHERE inode = ?"
+    /// Página de `list_children` para readdir: `limit` hijos a partir de
+    /// `offset`, ordenados por nombre. Como `list_children_extended_page`,
+    /// en la raíz omite lo que no es propio
+    pub async fn list_children_page(&self, parent_inode: u64, offset: u64, limit: u64) -> Result<Vec<(u64, String, bool)>> {
+        let children = sqlx::query_as::<_, (i64, String, bool)>(
+            r#"
+            SELECT d.child_inode, d.name, a.is_dir
+            FROM dentry d
+            JOIN attrs a ON d.child_inode = a.inode
+            WHERE d.parent_inode = ? AND (d.parent_inode != 1 OR a.owned_by_me = 1)
+            ORDER BY d.name
+            LIMIT ? OFFSET ?
+            "#
         )
-        .bind(shared_inode as i64)
-        .fetch_optional(&self.pool)
+        .bind(parent_inode as i64)
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(&self.read_pool)
         .await?;
 
-        match row {
-            Some((dirty, synced)) => {
-                let total = dirty + synced;
-                Ok((dirty > 0, synced > 0, total))
-            }
-            None => Ok((false, false, 0)),
-        }
+        Ok(children.into_iter()
+            .map(|(inode, name, is_dir)| (inode as u64, name, is_dir))
+            .collect())
     }
 
-    // ============================================================
-    // Métodos para Soft Delete (Tombstones)
-    // ============================================================
-
-    /// Obtiene el inode asociado a un gdrive_id
-    pub async fn get_inode_by_gdrive_id(&self, gdrive_id: &str) -> Result<Option<u64>> {
-        let row = sqlx::query_scalar::<_, i64>(
-            "SELECT inode FROM inodes WHERE gdrive_id = ?"
+    /// Cuenta el número de hijos de un directorio (para verificación rápida de paginación)
+    /// Esta operación es O(1) con el índice de parent_inode
+    pub async fn count_children(&self, parent_inode: u64) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM dentry WHERE parent_inode = ?"
         )
-        .bind(gdrive_id)
-        .fetch_optional(&self.pool)
+        .bind(parent_inode as i64)
+        .fetch_one(&self.read_pool)
         .await?;
-
-        Ok(row.map(|i| i as u64))
+        
+        Ok(count as u64)
     }
 
-    /// Marca un archivo o directorio y todo su contenido (recursivamente) como eliminado
-    pub async fn soft_delete_by_gdrive_id(&self, gdrive_id: &str) -> Result<bool> {
-        let root_inode = match self.get_inode_by_gdrive_id(gdrive_id).await? {
-            Some(i) => i,
-            None => return Ok(false),
-        };
-
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64;
-
-        // 0. Burbujeo: contar archivos descendientes que estaban synced (no dirty)
-        // antes de marcarlos como dirty. Estos son los que cambian de estado.
-        let synced_becoming_dirty: i64 = sqlx::query_scalar(
+    /// Cuenta el número de hijos de un directorio que NO son propiedad del usuario
+    /// y que están en el root (usado para la carpeta compartida virtual)
+    pub async fn count_non_owned_root_children(&self) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar(
             r#"
-            WITH RECURSIVE subordinates AS (
-                SELECT child_inode FROM dentry WHERE child_inode = ?
-                UNION ALL
-                SELECT d.child_inode FROM dentry d
-                JOIN subordinates s ON d.parent_inode = s.child_inode
-            )
-            SELECT COUNT(*) FROM subordinates sub
-            JOIN attrs a ON sub.child_inode = a.inode
-            LEFT JOIN sync_state ss ON sub.child_inode = ss.inode
-            WHERE a.is_dir = 0
-              AND (COALESCE(ss.availability, 'online_only') = 'local_online')
-              AND (COALESCE(ss.dirty, 0) = 0)
-              AND (ss.deleted_at IS NULL OR ss.deleted_at = 0)
+            SELECT COUNT(*) 
+            FROM dentry d
+            JOIN attrs a ON d.child_inode = a.inode
+            WHERE d.parent_inode = 1 AND a.owned_by_me = 0
             "#
         )
-        .bind(root_inode as i64)
-        .fetch_one(&self.pool)
+        .fetch_one(&self.read_pool)
         .await?;
+        
+        Ok(count as u64)
+    }
 
+    /// Verifica si la tabla de inodos está vacía (excepto el root si existe)
+    pub async fn is_empty(&self) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM inodes")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count <= 1) // 1 si solo existe el root, 0 si está totalmente vacía
+    }
 
+    /// Obtiene o desarrolla un inodo para un gdrive_id dado
+    pub async fn get_or_create_inode(&self, gdrive_id: &str) -> Result<u64> {
+        // Intentar obtener existente
+        let existing = sqlx::query_scalar::<_, i64>("SELECT inode FROM inodes WHERE gdrive_id = ?")
+            .bind(gdrive_id)
+            .fetch_optional(&self.pool)
+            .await?;
 
-        // Archivos nuevos sin sync_state
-        let new_files: i64 = sqlx::query_scalar(
-            r#"
-            WITH RECURSIVE subordinates AS (
-                SELECT child_inode FROM dentry WHERE child_inode = ?
-                UNION ALL
-                SELECT d.child_inode FROM dentry d
-                JOIN subordinates s ON d.parent_inode = s.child_inode
-            )
-            SELECT COUNT(*) FROM subordinates sub
-            JOIN attrs a ON sub.child_inode = a.inode
-            WHERE a.is_dir = 0
-              AND sub.child_inode NOT IN (SELECT inode FROM sync_state)
-            "#
-        )
-        .bind(root_inode as i64)
-        .fetch_one(&self.pool)
-        .await?;
+        if let Some(inode) = existing {
+            return Ok(inode as u64);
+        }
 
-        // 1. Identificar recursivamente todos los inodos hijos (incluyendo el raíz)
-        let sql_deleted_dentries = r#"
-            WITH RECURSIVE subordinates AS (
-                SELECT child_inode, parent_inode, name FROM dentry WHERE child_inode = ?
-                UNION ALL
-                SELECT d.child_inode, d.parent_inode, d.name
-                FROM dentry d
-                JOIN subordinates s ON d.parent_inode = s.child_inode
-            )
-            INSERT OR REPLACE INTO dentry_deleted (parent_inode, child_inode, name, deleted_at)
-            SELECT parent_inode, child_inode, name, ? FROM subordinates
-        "#;
+        // Crear nuevo
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
 
-        sqlx::query(sql_deleted_dentries)
-            .bind(root_inode as i64)
+        let insert_result = sqlx::query(INSERT_INODE_SQL)
+            .bind(gdrive_id)
             .bind(now)
+            .bind(INODE_GENERATION_KEY)
             .execute(&self.pool)
-            .await?;
+            .await;
 
-        // 2. Marcar sync_state para todos los inodos afectados
-        let sql_update_sync = r#"
-            WITH RECURSIVE subordinates AS (
-                SELECT child_inode FROM dentry WHERE child_inode = ?
-                UNION ALL
-                SELECT d.child_inode
-                FROM dentry d
-                JOIN subordinates s ON d.parent_inode = s.child_inode
-            )
-            UPDATE sync_state
-            SET deleted_at = ?, dirty = 1
-            WHERE inode IN (SELECT child_inode FROM subordinates)
-        "#;
+        match insert_result {
+            Ok(result) => Ok(result.last_insert_rowid() as u64),
+            Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+                // Si hubo una colisión durante la inserción simultánea, simplemente lo leemos
+                let existing = sqlx::query_scalar::<_, i64>("SELECT inode FROM inodes WHERE gdrive_id = ?")
+                    .bind(gdrive_id)
+                    .fetch_one(&self.pool)
+                    .await?;
+                Ok(existing as u64)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
 
-        sqlx::query(sql_update_sync)
-            .bind(root_inode as i64)
-            .bind(now)
-            .execute(&self.pool)
-            .await?;
+    /// Obtiene o crea inodos para una lista de gdrive_ids de forma masiva
+    pub async fn get_or_create_inodes_bulk(&self, gdrive_ids: &[String]) -> Result<std::collections::HashMap<String, u64>> {
+        if gdrive_ids.is_empty() {
+            return Ok(std::collections::HashMap::new());
+        }
 
-        // Insertar los que falten
-        let sql_insert_sync = r#"
-            WITH RECURSIVE subordinates AS (
-                SELECT child_inode FROM dentry WHERE child_inode = ?
-                UNION ALL
-                SELECT d.child_inode
-                FROM dentry d
-                JOIN subordinates s ON d.parent_inode = s.child_inode
-            )
-            INSERT INTO sync_state (inode, dirty, version, deleted_at)
-            SELECT child_inode, 1, 0, ?
-            FROM subordinates
-            WHERE child_inode NOT IN (SELECT inode FROM sync_state)
-        "#;
-
-        sqlx::query(sql_insert_sync)
-            .bind(root_inode as i64)
-            .bind(now)
-            .execute(&self.pool)
-            .await?;
+        let mut results = std::collections::HashMap::new();
 
-        // 2.5. Burbujear el cambio de estado ANTES de eliminar dentries
-        // (bubble_state_change necesita las dentries para caminar hacia los ancestros)
-        let delta_dirty = synced_becoming_dirty + new_files; // Nuevos dirty
-        let delta_synced = -synced_becoming_dirty; // Dejaron de ser synced
-        if delta_dirty != 0 || delta_synced != 0 {
-            self.bubble_state_change(root_inode, delta_dirty as i32, delta_synced as i32).await?;
-        }
+        for batch in gdrive_ids.chunks(BULK_BATCH_SIZE) {
+            let mut tx = self.pool.begin().await?;
 
-        // 3. Limpiar dentry original para todos los inodos afectados
-        // IMPORTANTE: Esto debe ser lo ÚLTIMO porque las CTEs anteriores dependen de dentry.
-        let sql_cleanup_dentry = r#"
-            WITH RECURSIVE subordinates AS (
-                SELECT child_inode FROM dentry WHERE child_inode = ?
-                UNION ALL
-                SELECT d.child_inode
-                FROM dentry d
-                JOIN subordinates s ON d.parent_inode = s.child_inode
-            )
-            DELETE FROM dentry WHERE child_inode IN (SELECT child_inode FROM subordinates)
-        "#;
+            for id in batch {
+                // Intentar obtener existente
+                let existing: Option<i64> = sqlx::query_scalar("SELECT inode FROM inodes WHERE gdrive_id = ?")
+                    .bind(id)
+                    .fetch_optional(&mut *tx)
+                    .await?;
 
-        sqlx::query(sql_cleanup_dentry)
-            .bind(root_inode as i64)
-            .execute(&self.pool)
-            .await?;
+                if let Some(inode) = existing {
+                    results.insert(id.clone(), inode as u64);
+                    continue;
+                }
 
-        tracing::info!("Recursive soft delete applied for gdrive_id={}, root_inode={}", gdrive_id, root_inode);
-        Ok(true)
-    }
+                // Crear nuevo
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)?
+                    .as_secs() as i64;
 
-    /// Soft delete para eliminaciones REMOTAS (no marca dirty).
-    /// Usa soft_delete_by_gdrive_id internamente, pero luego limpia dirty=0
-    /// porque la eliminación ya ocurrió en GDrive y no necesita re-subirse.
-    pub async fn soft_delete_remote(&self, gdrive_id: &str) -> Result<bool> {
-        let inode = self.get_inode_by_gdrive_id(gdrive_id).await?;
-        let result = self.soft_delete_by_gdrive_id(gdrive_id).await?;
-        if result {
-            if let Some(root_inode) = inode {
-                // Limpiar dirty para este inode y todos sus descendientes
-                // (ya fueron movidos a dentry_deleted por soft_delete_by_gdrive_id)
-                sqlx::query(r#"
-                    WITH RECURSIVE subordinates AS (
-                        SELECT child_inode FROM dentry_deleted WHERE child_inode = ?
-                        UNION ALL
-                        SELECT d.child_inode FROM dentry_deleted d
-                        JOIN subordinates s ON d.parent_inode = s.child_inode
-                    )
-                    UPDATE sync_state SET dirty = 0
-                    WHERE inode IN (SELECT child_inode FROM subordinates)
-                      AND deleted_at IS NOT NULL
-                "#)
-                .bind(root_inode as i64)
-                .execute(&self.pool)
-                .await?;
+                let insert_result = sqlx::query(INSERT_INODE_SQL)
+                    .bind(id)
+                    .bind(now)
+                    .bind(INODE_GENERATION_KEY)
+                    .execute(&mut *tx)
+                    .await;
 
-                tracing::debug!("Remote soft delete: dirty cleared for gdrive_id={}", gdrive_id);
+                match insert_result {
+                    Ok(res) => {
+                        results.insert(id.clone(), res.last_insert_rowid() as u64);
+                    }
+                    Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+                        let inode: i64 = sqlx::query_scalar("SELECT inode FROM inodes WHERE gdrive_id = ?")
+                            .bind(id)
+                            .fetch_one(&mut *tx)
+                            .await?;
+                        results.insert(id.clone(), inode as u64);
+                    }
+                    Err(e) => return Err(e.into()),
+                }
             }
-        }
-        Ok(result)
-    }
 
-    /// Restaura un archivo eliminado (quita tombstone)
-    /// Mueve el dentry de vuelta, elimina deleted_at
-    pub async fn restore_by_gdrive_id(&self, gdrive_id: &str) -> Result<bool> {
-        let inode = match self.get_inode_by_gdrive_id(gdrive_id).await? {
-            Some(i) => i,
-            None => return Ok(false),
-        };
-
-        // Estado previo
-        let prev = sqlx::query_as::<_, (Option<String>, Option<bool>, Option<i64>)>(
-            "SELECT s.availability, s.dirty, s.deleted_at FROM sync_state s WHERE s.inode = ?"
-        )
-        .bind(inode as i64)
-        .fetch_optional(&self.pool)
-        .await?;
-
-        let was_dirty = prev.as_ref().map(|(_, d, del)| {
-            d.unwrap_or(false) || del.map(|v| v > 0).unwrap_or(false)
-        }).unwrap_or(false);
+            tx.commit().await?;
+        }
 
-        let was_synced = prev.as_ref().map(|(av, d, del)| {
-            let is_local_online = av.as_deref().unwrap_or("online_only") == "local_online";
-            let not_dirty = !d.unwrap_or(false);
-            let not_deleted = del.unwrap_or(0) == 0;
-            is_local_online && not_dirty && not_deleted
-        }).unwrap_or(false);
+        Ok(results)
+    }
 
-        // 1. Restaurar dentry desde dentry_deleted
+    /// Inserta o actualiza metadatos de un archivo
+    pub async fn upsert_file_metadata(
+        &self,
+        inode: u64,
+        size: i64,
+        mtime: i64,
+        mode: u32,
+        is_dir: bool,
+        mime_type: Option<&str>,
+        can_move: bool,
+        shared: bool,
+        owned_by_me: bool,
+    ) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT OR REPLACE INTO dentry (parent_inode, child_inode, name)
-            SELECT parent_inode, child_inode, name
-            FROM dentry_deleted WHERE child_inode = ?
+            INSERT INTO attrs (inode, size, mtime, ctime, mode, is_dir, mime_type, can_move, shared, owned_by_me)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(inode) DO UPDATE SET
+                size = excluded.size,
+                mtime = excluded.mtime,
+                mtime_nsec = 0,
+                mode = excluded.mode,
+                is_dir = excluded.is_dir,
+                mime_type = excluded.mime_type,
+                can_move = excluded.can_move,
+                shared = excluded.shared,
+                owned_by_me = excluded.owned_by_me
             "#
         )
         .bind(inode as i64)
+        .bind(size)
+        .bind(mtime)
+        .bind(mtime) // Usamos mtime como ctime por simplicidad inicial
+        .bind(mode as i32)
+        .bind(is_dir)
+        .bind(mime_type)
+        .bind(can_move)
+        .bind(shared)
+        .bind(owned_by_me)
         .execute(&self.pool)
         .await?;
 
-        // 2. Eliminar de dentry_deleted
-        sqlx::query("DELETE FROM dentry_deleted WHERE child_inode = ?")
-            .bind(inode as i64)
-            .execute(&self.pool)
-            .await?;
+        Ok(())
+    }
 
-        // 3. Limpiar deleted_at en sync_state
-        sqlx::query("UPDATE sync_state SET deleted_at = NULL WHERE inode = ?")
+    /// Actualiza específicamente el campo de propiedad (para correcciones masivas)
+    pub async fn update_ownership(&self, inode: u64, owned_by_me: bool) -> Result<()> {
+        sqlx::query("UPDATE attrs SET owned_by_me = ? WHERE inode = ?")
+            .bind(owned_by_me)
             .bind(inode as i64)
             .execute(&self.pool)
             .await?;
+        Ok(())
+    }
 
-        // 4. Burbujear
-        let is_dir: Option<bool> = sqlx::query_scalar(
-            "SELECT is_dir FROM attrs WHERE inode = ?"
-        )
-        .bind(inode as i64)
-        .fetch_optional(&self.pool)
-        .await?;
-
-        if is_dir == Some(false) {
-            let curr = sqlx::query_as::<_, (Option<String>, Option<bool>, Option<i64>)>(
-                "SELECT s.availability, s.dirty, s.deleted_at FROM sync_state s WHERE s.inode = ?"
-            )
-            .bind(inode as i64)
-            .fetch_one(&self.pool)
-            .await?;
+    /// Actualiza la propiedad de múltiples archivos en una sola transacción
+    pub async fn update_bulk_ownership(&self, items: &[(u64, bool)]) -> Result<()> {
+        if items.is_empty() { return Ok(()); }
+        let mut tx = self.pool.begin().await?;
+        for (inode, owned) in items {
+            sqlx::query("UPDATE attrs SET owned_by_me = ? WHERE inode = ?")
+                .bind(owned)
+                .bind(*inode as i64)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
 
-            let is_dirty = curr.1.unwrap_or(false) || curr.2.unwrap_or(0) > 0;
-            let is_synced = {
-                let is_local_online = curr.0.as_deref().unwrap_or("online_only") == "local_online";
-                let not_dirty = !curr.1.unwrap_or(false);
-                let not_deleted = curr.2.unwrap_or(0) == 0;
-                is_local_online && not_dirty && not_deleted
-            };
+    /// Inserta o actualiza metadatos de múltiples archivos en una sola transacción
+    pub async fn upsert_bulk_file_metadata(&self, items: &[BulkFileMetadata]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
 
-            let delta_dirty = if was_dirty && !is_dirty { -1 } else if !was_dirty && is_dirty { 1 } else { 0 };
-            let delta_synced = if was_synced && !is_synced { -1 } else if !was_synced && is_synced { 1 } else { 0 };
+        // Transacciones de BULK_BATCH_SIZE filas; sqlx reutiliza la sentencia
+        // preparada dentro de cada conexión
+        for batch in items.chunks(BULK_BATCH_SIZE) {
+            let mut tx = self.pool.begin().await?;
 
-            if delta_dirty != 0 || delta_synced != 0 {
-                self.bubble_state_change(inode, delta_dirty, delta_synced).await?;
+            for item in batch {
+                sqlx::query(
+                    r#"
+                    INSERT INTO attrs (inode, size, mtime, mtime_nsec, ctime, mode, is_dir, mime_type, can_move, shared, owned_by_me, starred, created_time, origin_client, pin_hint, description, folder_color)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    ON CONFLICT(inode) DO UPDATE SET
+                        size = excluded.size,
+                        mtime = excluded.mtime,
+                        mtime_nsec = excluded.mtime_nsec,
+                        mode = excluded.mode,
+                        is_dir = excluded.is_dir,
+                        mime_type = excluded.mime_type,
+                        can_move = excluded.can_move,
+                        shared = excluded.shared,
+                        owned_by_me = excluded.owned_by_me,
+                        starred = excluded.starred,
+                        created_time = COALESCE(excluded.created_time, attrs.created_time),
+                        origin_client = excluded.origin_client,
+                        pin_hint = CASE WHEN attrs.pin_hint_pending THEN attrs.pin_hint ELSE excluded.pin_hint END,
+                        description = excluded.description,
+                        folder_color = excluded.folder_color
+                    "#
+                )
+                .bind(item.inode as i64)
+                .bind(item.size)
+                .bind(item.mtime)
+                .bind(item.mtime_nsec as i64)
+                .bind(item.mtime)
+                .bind(item.mode as i32)
+                .bind(item.is_dir)
+                .bind(item.mime_type.as_deref())
+                .bind(item.can_move)
+                .bind(item.shared)
+                .bind(item.owned_by_me)
+                .bind(item.starred)
+                .bind(item.created_time)
+                .bind(item.origin_client.as_deref())
+                .bind(item.pin_hint)
+                .bind(item.description.as_deref())
+                .bind(item.folder_color.as_deref())
+                .execute(&mut *tx)
+                .await?;
             }
-        } else if is_dir == Some(true) {
-            // Restaurar directorio: asegurar que tiene fila en dir_counters
-            self.ensure_dir_counter(inode).await?;
-        }
 
-        tracing::debug!("Archivo restaurado: gdrive_id={}, inode={}", gdrive_id, inode);
-        Ok(true)
+            tx.commit().await?;
+        }
+        Ok(())
     }
 
-    /// Verifica si un gdrive_id tiene un tombstone activo
-    pub async fn has_tombstone(&self, gdrive_id: &str) -> Result<bool> {
-        let inode = match self.get_inode_by_gdrive_id(gdrive_id).await? {
-            Some(i) => i,
-            None => return Ok(false),
-        };
+    /// Inserta o actualiza una entrada de directorio con un nombre elegido localmente
+    /// (FUSE, mirror): el nombre local pasa a ser también el nombre en Drive,
+    /// deshaciendo la sustitución de `/` (`utils::names::remote_name`).
+    /// IMPORTANTE: Un archivo solo puede tener UN parent. Antes de insertar,
+    /// eliminamos cualquier dentry existente para este child_inode.
+    /// Para nombres que vienen de Drive usar `upsert_remote_dentry`.
+    pub async fn upsert_dentry(&self, parent_inode: u64, child_inode: u64, name: &str) -> Result<()> {
+        // 1. Eliminar cualquier dentry anterior para este child_inode
+        //    (un archivo solo puede estar en un directorio a la vez)
+        let mut tx = self.pool.begin().await?;
+        let old_parent = sqlx::query_scalar::<_, i64>("SELECT parent_inode FROM dentry WHERE child_inode = ? LIMIT 1")
+            .bind(child_inode as i64)
+            .fetch_optional(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM dentry WHERE child_inode = ?")
+            .bind(child_inode as i64)
+            .execute(&mut *tx)
+            .await?;
 
-        let count: i64 = sqlx::query_scalar(
-            "SELECT COUNT(*) FROM dentry_deleted WHERE child_inode = ?"
+        // 2. Insertar el nuevo dentry
+        let remote_name = crate::utils::names::remote_name(name);
+        sqlx::query(
+            r#"
+            INSERT INTO dentry (parent_inode, child_inode, name, remote_name)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT(parent_inode, name) DO UPDATE SET
+                child_inode = excluded.child_inode,
+                remote_name = excluded.remote_name
+            "#
         )
-        .bind(inode as i64)
-        .fetch_one(&self.pool)
+        .bind(parent_inode as i64)
+        .bind(child_inode as i64)
+        .bind(name)
+        .bind((remote_name != name).then_some(&remote_name))
+        .execute(&mut *tx)
         .await?;
 
-        Ok(count > 0)
+        touch_dirs(&mut tx, old_parent.map(|p| p as u64).into_iter().chain([parent_inode])).await?;
+        tx.commit().await?;
+        Ok(())
     }
 
-    /// Hard delete: elimina permanentemente registros con deleted_at > grace_period
-    /// Retorna el número de registros eliminados
-    pub async fn purge_expired_tombstones(&self, grace_days: i64) -> Result<u64> {
+    /// Vincula un archivo con el nombre que tiene en Drive (bootstrap, syncer).
+    /// Los duplicados dentro de la misma carpeta reciben un sufijo `(n)`.
+    /// Retorna el nombre local asignado.
+    /// Si la entrada es nueva o cambió de carpeta o nombre, los directorios
+    /// afectados pasan a estar modificados ahora (como al hacerlo en local).
+    pub async fn upsert_remote_dentry(&self, parent_inode: u64, child_inode: u64, name: &str) -> Result<String> {
+        let mut tx = self.pool.begin().await?;
+        let before = sqlx::query_as::<_, (i64, String)>(
+            "SELECT parent_inode, name FROM dentry WHERE child_inode = ? LIMIT 1"
+        )
+        .bind(child_inode as i64)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let local_name = link_remote_dentry(&mut tx, parent_inode, child_inode, name).await?;
+        if before.as_ref() != Some(&(parent_inode as i64, local_name.clone())) {
+            let old_parent = before.map(|(parent, _)| parent as u64);
+            touch_dirs(&mut tx, old_parent.into_iter().chain([parent_inode])).await?;
+        }
+        tx.commit().await?;
+        Ok(local_name)
+    }
+
+    /// Inode del directorio virtual de "Ordenadores", creado en el primer uso
+    /// (solo lectura: los equipos se añaden desde Drive, no desde el montaje)
+    pub async fn computers_inode(&self) -> Result<u64> {
+        let inode = self.get_or_create_inode(COMPUTERS_GDRIVE_ID).await?;
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)?
             .as_secs() as i64;
-        
-        let cutoff = now - (grace_days * 24 * 60 * 60);
-
-        // Obtener inodos a purgar
-        let inodes_to_purge: Vec<i64> = sqlx::query_scalar(
-            "SELECT child_inode FROM dentry_deleted WHERE deleted_at < ?"
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO attrs (inode, size, mtime, ctime, mode, is_dir, mime_type)
+            VALUES (?, 4096, ?, ?, 365, 1, 'application/vnd.google-apps.folder')
+            "#
         )
-        .bind(cutoff)
-        .fetch_all(&self.pool)
+        .bind(inode as i64)
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
         .await?;
+        self.ensure_dir_counter(inode).await?;
+        Ok(inode)
+    }
 
-        if inodes_to_purge.is_empty() {
-            return Ok(0);
+    /// Muestra u oculta el directorio virtual de "Ordenadores" en la raíz.
+    /// Las carpetas de los equipos siguen colgando de él: oculto, todo su
+    /// contenido queda fuera del árbol. Retorna `true` si cambió.
+    pub async fn set_computers_visible(&self, visible: bool) -> Result<bool> {
+        let inode = self.computers_inode().await?;
+        if self.has_dentry(inode).await? == visible {
+            return Ok(false);
         }
-
-        let count = inodes_to_purge.len() as u64;
-
-        for inode in &inodes_to_purge {
-            self.hard_delete_inode(*inode as u64).await?;
+        if visible {
+            self.upsert_remote_dentry(1, inode, COMPUTERS_DIR_NAME).await?;
+        } else {
+            sqlx::query("DELETE FROM dentry WHERE child_inode = ?")
+                .bind(inode as i64)
+                .execute(&self.pool)
+                .await?;
         }
-
-        tracing::info!("Purgados {} tombstones expirados (grace_days={})", count, grace_days);
-        Ok(count)
+        // Los contadores de la raíz incluyen (o dejan de incluir) el subárbol
+        self.rebuild_all_dir_counters().await?;
+        Ok(true)
     }
 
-    /// Elimina permanentemente un inode y todos sus registros asociados
-    async fn hard_delete_inode(&self, inode: u64) -> Result<()> {
-        let inode_i64 = inode as i64;
-
-        // Burbujear: decrementar contadores de ancestros según estado previo del archivo
-        // (solo para archivos, no directorios — los directorios eliminados ya tuvieron
-        // sus descendientes procesados en soft_delete)
-        let is_dir: Option<bool> = sqlx::query_scalar(
-            "SELECT is_dir FROM attrs WHERE inode = ?"
-        )
-        .bind(inode_i64)
-        .fetch_optional(&self.pool)
-        .await?;
+    // ============================================================
+    // Métodos para Sync Meta (persistencia de page tokens)
+    // ============================================================
 
-        if is_dir == Some(false) {
-            // Verificar estado actual del archivo para calcular delta
-            let state = sqlx::query_as::<_, (Option<bool>, Option<i64>)>(
-                "SELECT dirty, deleted_at FROM sync_state WHERE inode = ?"
-            )
-            .bind(inode_i64)
-            .fetch_optional(&self.pool)
-            .await?;
+    /// Guarda o actualiza un valor en sync_meta
+    pub async fn set_sync_meta(&self, key: &str, value: &str) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
 
-            if let Some((dirty, deleted_at)) = state {
-                let was_dirty = dirty.unwrap_or(false) || deleted_at.map(|v| v > 0).unwrap_or(false);
-                if was_dirty {
-                    self.bubble_state_change(inode, -1, 0).await?;
-                } else {
-                    self.bubble_state_change(inode, 0, -1).await?;
-                }
-            }
+        sqlx::query(
+            r#"
+            INSERT INTO sync_meta (key, value, updated_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(key) DO UPDATE SET
+                value = excluded.value,
+                updated_at = excluded.updated_at
+            "#
+        )
+        .bind(key)
+        .bind(value)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Inserta o actualiza múltiples entradas de directorio remotas en una sola transacción.
+    /// Igual que `upsert_remote_dentry`, los nombres duplicados reciben sufijo.
+    pub async fn upsert_bulk_dentries(&self, items: &[BulkDentry]) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
         }
 
-        // Eliminar de todas las tablas relacionadas
-        sqlx::query("DELETE FROM dentry WHERE child_inode = ?")
-            .bind(inode_i64)
-            .execute(&self.pool)
-            .await?;
+        for batch in items.chunks(BULK_BATCH_SIZE) {
+            let mut tx = self.pool.begin().await?;
 
-        sqlx::query("DELETE FROM dentry_deleted WHERE child_inode = ?")
-            .bind(inode_i64)
-            .execute(&self.pool)
-            .await?;
+            for item in batch {
+                link_remote_dentry(&mut tx, item.parent_inode, item.child_inode, &item.name).await?;
+            }
 
-        sqlx::query("DELETE FROM sync_state WHERE inode = ?")
-            .bind(inode_i64)
-            .execute(&self.pool)
-            .await?;
+            tx.commit().await?;
+        }
+        Ok(())
+    }
 
-        sqlx::query("DELETE FROM attrs WHERE inode = ?")
-            .bind(inode_i64)
-            .execute(&self.pool)
-            .await?;
+    /// ID de esta instalación, con el que se marcan en Drive los archivos que
+    /// crea (`gdrive::app_properties`). Se genera la primera vez
+    pub async fn client_id(&self) -> Result<String> {
+        if let Some(id) = self.get_sync_meta(CLIENT_ID_KEY).await? {
+            return Ok(id);
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        self.set_sync_meta(CLIENT_ID_KEY, &id).await?;
+        Ok(id)
+    }
 
-        sqlx::query("DELETE FROM file_cache_chunks WHERE inode = ?")
-            .bind(inode_i64)
+    /// Copia consistente de la base de datos en `dest` (`VACUUM INTO`), válida
+    /// aunque el daemon esté escribiendo. `dest` no debe existir
+    pub async fn export_snapshot(&self, dest: &Path) -> Result<()> {
+        sqlx::query("VACUUM INTO ?")
+            .bind(dest.to_string_lossy().as_ref())
             .execute(&self.pool)
-            .await?;
+            .await
+            .with_context(|| format!("No se pudo copiar la base de datos a {:?}", dest))?;
+        Ok(())
+    }
 
-        // Limpiar dir_counters si era directorio
-        sqlx::query("DELETE FROM dir_counters WHERE inode = ?")
-            .bind(inode_i64)
-            .execute(&self.pool)
+    /// Adapta una base de datos importada de otro equipo: la caché de contenido
+    /// no viajó (sin chunks ni fechas de miniaturas), el ID de instalación es
+    /// del otro equipo y las carpetas de Local Sync apuntan a rutas de su disco
+    pub async fn forget_device_state(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for sql in [
+            "DELETE FROM file_cache_chunks",
+            "DELETE FROM fs_intents",
+            "DELETE FROM local_sync_files",
+            "DELETE FROM local_sync_dirs",
+            "UPDATE sync_state SET uploading = 0",
+            "UPDATE attrs SET thumbnail_mtime = NULL",
+        ] {
+            sqlx::query(sql).execute(&mut *tx).await?;
+        }
+        sqlx::query("DELETE FROM sync_meta WHERE key IN (?, 'session_active')")
+            .bind(CLIENT_ID_KEY)
+            .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Obtiene un valor de sync_meta
+    pub async fn get_sync_meta(&self, key: &str) -> Result<Option<String>> {
+        let row = sqlx::query_scalar::<_, String>(
+            "SELECT value FROM sync_meta WHERE key = ?"
+        )
+        .bind(key)
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        Ok(row)
+    }
 
-        sqlx::query("DELETE FROM inodes WHERE inode = ?")
-            .bind(inode_i64)
+    /// Elimina una clave de sync_meta
+    pub async fn delete_sync_meta(&self, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM sync_meta WHERE key = ?")
+            .bind(key)
             .execute(&self.pool)
             .await?;
-
-        tracing::debug!("Hard delete completado para inode={}", inode);
         Ok(())
     }
 
-    /// Hard delete por gdrive_id: elimina permanentemente un archivo de la DB
-    /// Usado cuando un archivo es eliminado permanentemente de Google Drive
-    pub async fn hard_delete_by_gdrive_id(&self, gdrive_id: &str) -> Result<bool> {
-        let inode = match self.get_inode_by_gdrive_id(gdrive_id).await? {
-            Some(i) => i,
-            None => return Ok(false), // No existe, nada que eliminar
-        };
+    /// Verifica si existen chunks cacheados para un inodo
+    pub async fn has_any_chunks(&self, inode: u64) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM file_cache_chunks WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_one(&self.read_pool)
+        .await?;
 
-        self.hard_delete_inode(inode).await?;
-        tracing::info!("Hard delete aplicado: gdrive_id={}, inode={}", gdrive_id, inode);
-        Ok(true)
+        Ok(count > 0)
     }
 
     // ============================================================
-    // Métodos para File Cache Chunks (On-Demand Caching)
+    // Métodos para Conflict Detection (Remote MD5 Tracking)
     // ============================================================
 
-    /// Registra un rango descargado en la caché
-    pub async fn add_cached_chunk(&self, inode: u64, start: u64, end: u64) -> Result<()> {
+    /// Obtiene el MD5 remoto conocido para un archivo (`None` sin fila o con la columna vacía)
+    pub async fn get_remote_md5(&self, inode: u64) -> Result<Option<String>> {
+        let row = sqlx::query_scalar::<_, Option<String>>(
+            "SELECT remote_md5 FROM sync_state WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        Ok(row.flatten())
+    }
+
+    /// Actualiza el MD5 remoto conocido para un archivo
+    pub async fn set_remote_md5(&self, inode: u64, md5: &str) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT OR REPLACE INTO file_cache_chunks (inode, start_offset, end_offset)
-            VALUES (?, ?, ?)
+            INSERT INTO sync_state (inode, dirty, version, remote_md5)
+            VALUES (?, 0, 0, ?)
+            ON CONFLICT(inode) DO UPDATE SET remote_md5 = excluded.remote_md5
             "#
         )
         .bind(inode as i64)
-        .bind(start as i64)
-        .bind(end as i64)
+        .bind(md5)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    /// Limpia todos los chunks cacheados de un inodo (usado en caso de corrupción detectada)
-    pub async fn clear_chunks(&self, inode: u64) -> Result<()> {
-        sqlx::query("DELETE FROM file_cache_chunks WHERE inode = ?")
-            .bind(inode as i64)
-            .execute(&self.pool)
-            .await?;
+    // ============================================================
+    // Protocolo "Burbujeo de Estados" — Contadores pre-calculados
+    // ============================================================
 
-        tracing::warn!("🧹 Chunks limpiados para inode: {}", inode);
-        Ok(())
+    /// Burbujea un cambio de estado desde un archivo hacia todos sus directorios ancestros.
+    /// `delta_dirty` y `delta_synced` son incrementos (pueden ser negativos).
+    /// Ejemplo: archivo pasa de synced→dirty → delta_dirty=+1, delta_synced=-1
+    pub async fn bubble_state_change(
+        &self,
+        child_inode: u64,
+        delta_dirty: i32,
+        delta_synced: i32,
+    ) -> Result<()> {
+        bubble_state_change_in(&mut *self.pool.acquire().await?, child_inode, delta_dirty, delta_synced).await
     }
 
-    /// Limpia TODOS los chunks de caché (todas las filas de file_cache_chunks).
-    /// Usado en recuperación post-crash para eliminar estado obsoleto.
-    pub async fn clear_all_chunks(&self) -> Result<u64> {
-        let result = sqlx::query("DELETE FROM file_cache_chunks")
+    /// Asegura que el inode raíz (1) exista: Drive no tiene un "archivo" para el
+    /// root, pero FUSE siempre consulta inode=1 como punto de entrada y las
+    /// dentries del root lo referencian
+    pub async fn ensure_root(&self) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        // El root tiene gdrive_id = "root"
+        sqlx::query("INSERT OR IGNORE INTO inodes (inode, gdrive_id, generation, created_at) VALUES (1, 'root', 0, ?)")
+            .bind(now)
             .execute(&self.pool)
             .await?;
-        Ok(result.rows_affected())
-    }
-
-    /// Obtiene el offset máximo registrado en los chunks (para validar consistencia de tamaño)
-    pub async fn get_max_cached_offset(&self, inode: u64) -> Result<u64> {
-        let max_offset: Option<i64> = sqlx::query_scalar(
-            "SELECT MAX(end_offset) FROM file_cache_chunks WHERE inode = ?"
+        sqlx::query(
+            "INSERT OR IGNORE INTO attrs (inode, size, mtime, ctime, mode, is_dir, mime_type)
+             VALUES (1, 4096, ?, ?, 493, 1, 'application/vnd.google-apps.folder')"
         )
-        .bind(inode as i64)
-        .fetch_optional(&self.pool)
+        .bind(now)
+        .bind(now)
+        .execute(&self.pool)
         .await?;
-        
-        Ok(max_offset.unwrap_or(0) as u64)
+        self.ensure_dir_counter(1).await
     }
 
-    /// Obtiene el total de bytes cacheados sumando todos los chunks
-    pub async fn get_cached_bytes_count(&self, inode: u64) -> Result<u64> {
-        let total: Option<i64> = sqlx::query_scalar(
-            "SELECT SUM(end_offset - start_offset + 1) FROM file_cache_chunks WHERE inode = ?"
+    /// Inicializa una fila en dir_counters para un directorio si no existe.
+    pub async fn ensure_dir_counter(&self, inode: u64) -> Result<()> {
+        sqlx::query(
+            "INSERT OR IGNORE INTO dir_counters (inode, dirty_desc_count, synced_desc_count) VALUES (?, 0, 0)"
         )
         .bind(inode as i64)
-        .fetch_optional(&self.pool)
+        .execute(&self.pool)
         .await?;
-        
-        Ok(total.unwrap_or(0) as u64)
+        Ok(())
     }
 
-    /// Obtiene los rangos faltantes para un archivo en un intervalo dado
-    /// Retorna una lista de (start, end) que necesitan descargarse
-    pub async fn get_missing_ranges(&self, inode: u64, requested_start: u64, requested_end: u64) -> Result<Vec<(u64, u64)>> {
-        // Obtener todos los chunks cacheados para este inode que se solapan con el rango solicitado
-        let cached_chunks: Vec<(i64, i64)> = sqlx::query_as(
-            r#"
-            SELECT start_offset, end_offset
-            FROM file_cache_chunks
-            WHERE inode = ?
-              AND end_offset >= ?
-              AND start_offset <= ?
-            ORDER BY start_offset
-            "#
+    /// Marca un inode como dirty y burbujea el cambio a sus ancestros.
+    /// Detecta automáticamente el estado previo para calcular el delta correcto.
+    /// Solo burbujea para archivos (is_dir=0).
+    pub async fn mark_dirty(&self, inode: u64) -> Result<()> {
+        mark_dirty_in(&mut *self.pool.acquire().await?, inode).await
+    }
+
+    /// Marca un inode como ignorado (`Config.ignore_patterns`): deja de estar
+    /// pendiente de subida y `mark_dirty` no vuelve a marcarlo. No es dirty, pero
+    /// su caché no se descarta ni se verifica contra Drive: es la única copia.
+    pub async fn mark_ignored(&self, inode: u64) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO sync_state (inode, dirty, version, md5_checksum, status) VALUES (?, 0, 0, NULL, 'synced')
+             ON CONFLICT(inode) DO NOTHING"
         )
         .bind(inode as i64)
-        .bind(requested_start as i64)
-        .bind(requested_end as i64)
-        .fetch_all(&self.pool)
+        .execute(&self.pool)
         .await?;
+        // Si ya estaba pendiente, descontarlo de los contadores de sus ancestros
+        self.clear_dirty(inode).await?;
+        sqlx::query("UPDATE sync_state SET status = 'ignored' WHERE inode = ?")
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
-        // Si no hay chunks, el rango completo falta
-        if cached_chunks.is_empty() {
-            return Ok(vec![(requested_start, requested_end)]);
-        }
-
-        let mut missing = Vec::new();
-        let mut current_pos = requested_start;
-
-        for (start, end) in cached_chunks {
-            let start = start as u64;
-            let end = end as u64;
-
-            // Si hay un gap antes de este chunk
-            if current_pos < start {
-                missing.push((current_pos, start - 1));
-            }
+    /// Si el inode está ignorado
+    pub async fn is_ignored(&self, inode: u64) -> Result<bool> {
+        let status: Option<String> = sqlx::query_scalar("SELECT status FROM sync_state WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(status.as_deref() == Some(UploadStatus::Ignored.as_str()))
+    }
 
-            // Avanzar más allá del chunk actual
-            current_pos = current_pos.max(end + 1);
+    /// Deja de ignorar un inode y lo marca dirty para subirlo. Devuelve `false`
+    /// si no estaba ignorado.
+    pub async fn unignore(&self, inode: u64) -> Result<bool> {
+        let updated = sqlx::query("UPDATE sync_state SET status = 'synced' WHERE inode = ? AND status = 'ignored'")
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        if updated == 0 {
+            return Ok(false);
         }
+        self.mark_dirty(inode).await?;
+        Ok(true)
+    }
 
-        // Si queda espacio después del último chunk
-        if current_pos <= requested_end {
-            missing.push((current_pos, requested_end));
-        }
+    /// Limpia el flag dirty y burbujea el cambio a los ancestros.
+    /// Solo burbujea para archivos (is_dir=0).
+    pub async fn clear_dirty(&self, inode: u64) -> Result<()> {
+        // Verificar estado previo
+        let prev = sqlx::query_as::<_, (Option<String>, bool, Option<i64>)>(
+            "SELECT availability, dirty, deleted_at FROM sync_state WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.pool)
+        .await?;
 
-        Ok(missing)
-    }
+        let was_dirty = prev.as_ref().map(|(_, d, del)| {
+            *d || del.map(|v| v > 0).unwrap_or(false)
+        }).unwrap_or(false);
 
+        let was_synced = prev.as_ref().map(|(av, d, del)| {
+            let is_local_online = av.as_deref().unwrap_or("online_only") == "local_online";
+            let not_dirty = !d;
+            let not_deleted = del.unwrap_or(0) == 0;
+            is_local_online && not_dirty && not_deleted
+        }).unwrap_or(false);
 
-    /// Limpia todos los chunks cacheados para un inode (útil al invalidar caché)
-    #[allow(dead_code)]
-    pub async fn clear_cached_chunks(&self, inode: u64) -> Result<()> {
-        sqlx::query("DELETE FROM file_cache_chunks WHERE inode = ?")
+        // Limpiar dirty
+        sqlx::query(
+            "UPDATE sync_state SET dirty = 0, status = 'synced', last_error = NULL,
+                retry_count = 0, next_retry_at = NULL, error_permanent = 0
+             WHERE inode = ?"
+        )
             .bind(inode as i64)
             .execute(&self.pool)
             .await?;
 
-        Ok(())
-    }
+        // Solo burbujear para archivos
+        let is_dir: Option<bool> = sqlx::query_scalar(
+            "SELECT is_dir FROM attrs WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.pool)
+        .await?;
 
-    // ============================================================
-    // Métodos para Local Sync Directories
-    // ============================================================
+        if is_dir == Some(false) {
+            let curr = sqlx::query_as::<_, (Option<String>, bool, Option<i64>)>(
+                "SELECT availability, dirty, deleted_at FROM sync_state WHERE inode = ?"
+            )
+            .bind(inode as i64)
+            .fetch_one(&self.pool)
+            .await?;
 
-    /// Añade un directorio local a la lista de sincronización
-    pub async fn add_local_sync_dir(&self, local_path: &Path) -> Result<i64> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64;
+            let is_dirty = curr.1 || curr.2.unwrap_or(0) > 0;
+            let is_synced = {
+                let is_local_online = curr.0.as_deref().unwrap_or("online_only") == "local_online";
+                let not_dirty = !curr.1;
+                let not_deleted = curr.2.unwrap_or(0) == 0;
+                is_local_online && not_dirty && not_deleted
+            };
 
-        let path_str = local_path.to_string_lossy().to_string();
+            let delta_dirty = if was_dirty && !is_dirty { -1 } else if !was_dirty && is_dirty { 1 } else { 0 };
+            let delta_synced = if was_synced && !is_synced { -1 } else if !was_synced && is_synced { 1 } else { 0 };
 
-        let id = sqlx::query(
-            r#"
-            INSERT INTO local_sync_dirs (local_path, enabled, created_at)
-            VALUES (?, 1, ?)
-            "#
-        )
-        .bind(&path_str)
-        .bind(now)
-        .execute(&self.pool)
-        .await?
-        .last_insert_rowid();
+            if delta_dirty != 0 || delta_synced != 0 {
+                self.bubble_state_change(inode, delta_dirty, delta_synced).await?;
+            }
+        }
 
-        tracing::info!("Directorio local añadido: {} (id={})", path_str, id);
-        Ok(id)
+        Ok(())
     }
 
-    /// Elimina un directorio local de la sincronización
-    pub async fn remove_local_sync_dir(&self, id: i64) -> Result<()> {
-        sqlx::query("DELETE FROM local_sync_dirs WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
-
-        tracing::info!("Directorio local eliminado: id={}", id);
-        Ok(())
+    /// Limpia entradas dirty-delete que quedaron pendientes de sesiones anteriores.
+    /// Esto previene que el uploader envíe a papelera archivos que no fueron
+    /// realmente eliminados por el usuario (e.g., limpieza de huérfanos del mirror).
+    pub async fn clear_stale_dirty_deletes(&self) -> Result<usize> {
+        let result = sqlx::query(
+            "UPDATE sync_state SET dirty = 0, deleted_at = NULL, status = 'synced',
+                retry_count = 0, next_retry_at = NULL, error_permanent = 0
+             WHERE dirty = 1 AND deleted_at IS NOT NULL"
+        )
+        .execute(&self.pool)
+        .await?;
+        let count = result.rows_affected() as usize;
+        if count > 0 {
+            tracing::info!("🧹 Limpiadas {} entradas dirty-delete stale de sesión anterior", count);
+        }
+        Ok(count)
     }
 
-    /// Activa/desactiva la sincronización de un directorio local
-    pub async fn toggle_local_sync_dir(&self, id: i64, enabled: bool) -> Result<()> {
-        sqlx::query("UPDATE local_sync_dirs SET enabled = ? WHERE id = ?")
-            .bind(enabled)
-            .bind(id)
-            .execute(&self.pool)
+    /// Recalcula todos los contadores de directorio desde cero.
+    /// Optimizado: Calcula de abajo hacia arriba (bottom-up) para evitar subconsultas recursivas lentas.
+    pub async fn rebuild_all_dir_counters(&self) -> Result<()> {
+        tracing::info!("Iniciando rebuild de contadores de directorio (optimizado)...");
+
+        // Fase 1 (TX corta): Limpiar tabla y rellenar con contadores a 0
+        {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query("DELETE FROM dir_counters").execute(&mut *tx).await?;
+            sqlx::query(
+                "INSERT INTO dir_counters (inode, dirty_desc_count, synced_desc_count) SELECT inode, 0, 0 FROM attrs WHERE is_dir = 1"
+            )
+            .execute(&mut *tx)
             .await?;
+            tx.commit().await?;
+        }
 
-        tracing::debug!("Directorio local {} (id={})", if enabled { "activado" } else { "desactivado" }, id);
-        Ok(())
-    }
+        // Fase 2 (solo lectura, sin TX de escritura): Obtener datos para cálculo en memoria
+        let dentry_rows: Vec<(i64, i64)> = sqlx::query_as("SELECT parent_inode, child_inode FROM dentry")
+            .fetch_all(&self.pool)
+            .await?;
 
-    /// Obtiene todos los directorios locales configurados
-    pub async fn get_local_sync_dirs(&self) -> Result<Vec<LocalSyncDir>> {
-        let dirs = sqlx::query_as::<_, LocalSyncDir>(
-            "SELECT id, local_path, gdrive_folder_id, enabled, last_sync, created_at FROM local_sync_dirs ORDER BY created_at"
+        let file_states: Vec<(i64, bool, bool)> = sqlx::query_as(
+            r#"
+            SELECT
+                a.inode,
+                (s.dirty = 1 OR (s.deleted_at IS NOT NULL AND s.deleted_at > 0)) as is_dirty,
+                (COALESCE(s.availability, 'online_only') = 'local_online' AND COALESCE(s.dirty, 0) = 0 AND (s.deleted_at IS NULL OR s.deleted_at = 0)) as is_synced
+            FROM attrs a
+            LEFT JOIN sync_state s ON a.inode = s.inode
+            WHERE a.is_dir = 0
+            "#
         )
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(dirs)
-    }
+        // Fase 3 (cálculo en memoria, sin DB): Construir contadores
+        let mut child_to_parent = std::collections::HashMap::new();
+        for (parent, child) in dentry_rows {
+            child_to_parent.insert(child, parent);
+        }
 
-    /// Obtiene solo los directorios locales habilitados
-    pub async fn get_enabled_local_sync_dirs(&self) -> Result<Vec<LocalSyncDir>> {
-        let dirs = sqlx::query_as::<_, LocalSyncDir>(
-            "SELECT id, local_path, gdrive_folder_id, enabled, last_sync, created_at FROM local_sync_dirs WHERE enabled = 1 ORDER BY created_at"
-        )
-        .fetch_all(&self.pool)
-        .await?;
+        let mut dirty_counts = std::collections::HashMap::new();
+        let mut synced_counts = std::collections::HashMap::new();
 
-        Ok(dirs)
-    }
+        for (inode, is_dirty, is_synced) in file_states {
+            if !is_dirty && !is_synced { continue; }
 
-    /// Establece el gdrive_folder_id para un directorio local
-    pub async fn set_gdrive_folder_id(&self, id: i64, gdrive_id: &str) -> Result<()> {
-        sqlx::query("UPDATE local_sync_dirs SET gdrive_folder_id = ? WHERE id = ?")
-            .bind(gdrive_id)
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
+            let mut curr = inode;
+            while let Some(&parent) = child_to_parent.get(&curr) {
+                if is_dirty {
+                    *dirty_counts.entry(parent).or_insert(0) += 1;
+                }
+                if is_synced {
+                    *synced_counts.entry(parent).or_insert(0) += 1;
+                }
+                if parent == 1 { break; }
+                curr = parent;
+            }
+        }
 
-        tracing::debug!("GDrive folder ID {} asociado a local_sync_dir id={}", gdrive_id, id);
+        // Fase 4 (escritura en batches): Volcar resultados a la DB
+        let all_updates: Vec<(i64, i64, i64)> = {
+            let mut updates = std::collections::HashMap::new();
+            for (inode, count) in dirty_counts {
+                updates.entry(inode).or_insert((0i64, 0i64)).0 = count;
+            }
+            for (inode, count) in synced_counts {
+                updates.entry(inode).or_insert((0i64, 0i64)).1 = count;
+            }
+            updates.into_iter().map(|(inode, (d, s))| (inode, d, s)).collect()
+        };
+
+        for batch in all_updates.chunks(500) {
+            let mut tx = self.pool.begin().await?;
+            for &(inode, dirty, synced) in batch {
+                sqlx::query("UPDATE dir_counters SET dirty_desc_count = ?, synced_desc_count = ? WHERE inode = ?")
+                    .bind(dirty)
+                    .bind(synced)
+                    .bind(inode)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+        }
+
+        tracing::info!("Contadores de directorio recalculados (operación optimizada)");
         Ok(())
     }
 
-    /// Actualiza el timestamp de última sincronización
-    pub async fn update_last_sync(&self, id: i64) -> Result<()> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64;
+    /// Verifica si un inode tiene cambios locales pendientes de subir
+    pub async fn is_dirty(&self, inode: u64) -> Result<bool> {
+        let dirty = sqlx::query_scalar::<_, bool>(
+            "SELECT dirty FROM sync_state WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?
+        .unwrap_or(false);
 
-        sqlx::query("UPDATE local_sync_dirs SET last_sync = ? WHERE id = ?")
-            .bind(now)
-            .bind(id)
+        Ok(dirty)
+    }
+
+    /// Marca/desmarca un inode como "subiendo" (upload en curso)
+    pub async fn set_uploading(&self, inode: u64, uploading: bool) -> Result<()> {
+        // Al empezar: queued/error → uploading. Al terminar: synced si ya no quedan
+        // cambios, queued si hubo modificaciones concurrentes (el error lo fija el uploader).
+        sqlx::query(
+            r#"
+            UPDATE sync_state SET
+                uploading = ?1,
+                status = CASE
+                    WHEN ?1 THEN 'uploading'
+                    WHEN status != 'uploading' THEN status
+                    WHEN dirty = 1 THEN 'queued'
+                    ELSE 'synced'
+                END
+            WHERE inode = ?2
+            "#
+        )
+            .bind(uploading)
+            .bind(inode as i64)
             .execute(&self.pool)
             .await?;
-
         Ok(())
     }
 
-    // ============================================================
-    // Métodos para Local Sync Files (Hybrid Local Sync)
-    // ============================================================
+    /// Verifica si un inode tiene un upload en curso
+    pub async fn is_uploading(&self, inode: u64) -> Result<bool> {
+        let uploading = sqlx::query_scalar::<_, bool>(
+            "SELECT uploading FROM sync_state WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?
+        .unwrap_or(false);
 
-    /// Inserta o actualiza un archivo en local_sync_files
-    pub async fn upsert_local_sync_file(
-        &self,
-        sync_dir_id: i64,
-        relative_path: &str,
-        is_dir: bool,
-        availability: &str,
-        local_mtime: Option<i64>,
-        local_size: Option<i64>,
-        local_md5: Option<&str>,
-    ) -> Result<i64> {
-        let id = sqlx::query(
+        Ok(uploading)
+    }
+
+    /// Verifica (via CTE recursivo) si algún descendiente de un directorio tiene un upload en curso.
+    /// Los uploads en curso son pocos, así que se recorre hacia arriba desde ellos.
+    pub async fn has_uploading_descendant(&self, dir_inode: u64) -> Result<bool> {
+        let found: Option<i64> = sqlx::query_scalar(
             r#"
-            INSERT INTO local_sync_files 
-                (sync_dir_id, relative_path, is_dir, availability, local_mtime, local_size, local_md5, dirty)
-            VALUES (?, ?, ?, ?, ?, ?, ?, 1)
-            ON CONFLICT(sync_dir_id, relative_path) DO UPDATE SET
-                is_dir = excluded.is_dir,
-                availability = excluded.availability,
-                local_mtime = excluded.local_mtime,
-                local_size = excluded.local_size,
-                local_md5 = excluded.local_md5,
-                dirty = 1
+            WITH RECURSIVE ancestors(inode) AS (
+                SELECT d.parent_inode FROM dentry d
+                JOIN sync_state s ON s.inode = d.child_inode
+                WHERE s.uploading = 1
+                UNION
+                SELECT d.parent_inode FROM dentry d
+                JOIN ancestors a ON d.child_inode = a.inode
+                WHERE a.inode > 1
+            )
+            SELECT 1 FROM ancestors WHERE inode = ? LIMIT 1
             "#
         )
-        .bind(sync_dir_id)
-        .bind(relative_path)
-        .bind(is_dir)
-        .bind(availability)
-        .bind(local_mtime)
-        .bind(local_size)
-        .bind(local_md5)
-        .execute(&self.pool)
-        .await?
-        .last_insert_rowid();
+        .bind(dir_inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
 
-        Ok(id)
+        Ok(found.is_some())
     }
 
-    /// Obtiene archivos dirty para subir
-    pub async fn get_dirty_local_sync_files(&self) -> Result<Vec<LocalSyncFile>> {
-        let files = sqlx::query_as::<_, LocalSyncFile>(
-            "SELECT * FROM local_sync_files WHERE dirty = 1 ORDER BY id"
+    /// Verifica si algún descendiente de un directorio quedó con la subida en error
+    pub async fn has_error_descendant(&self, dir_inode: u64) -> Result<bool> {
+        let found: Option<i64> = sqlx::query_scalar(
+            r#"
+            WITH RECURSIVE ancestors(inode) AS (
+                SELECT d.parent_inode FROM dentry d
+                JOIN sync_state s ON s.inode = d.child_inode
+                WHERE s.status = 'error'
+                UNION
+                SELECT d.parent_inode FROM dentry d
+                JOIN ancestors a ON d.child_inode = a.inode
+                WHERE a.inode > 1
+            )
+            SELECT 1 FROM ancestors WHERE inode = ? LIMIT 1
+            "#
         )
-        .fetch_all(&self.pool)
+        .bind(dir_inode as i64)
+        .fetch_optional(&self.read_pool)
         .await?;
 
-        Ok(files)
+        Ok(found.is_some())
     }
 
-    /// Obtiene un archivo local por sync_dir_id y relative_path
-    pub async fn get_local_sync_file(&self, sync_dir_id: i64, relative_path: &str) -> Result<Option<LocalSyncFile>> {
-        let file = sqlx::query_as::<_, LocalSyncFile>(
-            "SELECT * FROM local_sync_files WHERE sync_dir_id = ? AND relative_path = ?"
+    /// Archivos y carpetas del árbol con ID de Drive (sin virtuales) que la
+    /// reconstrucción de metadatos puede dar por eliminados si ya no están en
+    /// Drive. Excluye los pendientes de crear en Drive (`pending_create`: IDs
+    /// `temp_` y reservados), los dirty y las carpetas con descendientes dirty:
+    /// sus cambios locales aún no se han subido.
+    pub async fn remote_ids_in_tree(&self) -> Result<Vec<String>> {
+        let ids = sqlx::query_scalar::<_, String>(
+            r#"
+            WITH RECURSIVE dirty_ancestors(inode) AS (
+                SELECT d.child_inode FROM dentry d
+                JOIN sync_state s ON s.inode = d.child_inode
+                WHERE s.dirty = 1
+                UNION
+                SELECT d.parent_inode FROM dentry d
+                JOIN dirty_ancestors a ON d.child_inode = a.inode
+                WHERE a.inode > 1
+            )
+            SELECT DISTINCT i.gdrive_id FROM inodes i
+            JOIN dentry d ON d.child_inode = i.inode
+            WHERE i.inode > 1
+              AND i.pending_create = 0
+              AND i.gdrive_id NOT LIKE 'virtual\_%' ESCAPE '\'
+              AND i.inode NOT IN (SELECT inode FROM dirty_ancestors)
+            "#
         )
-        .bind(sync_dir_id)
-        .bind(relative_path)
-        .fetch_optional(&self.pool)
+        .fetch_all(&self.read_pool)
         .await?;
-
-        Ok(file)
+        Ok(ids)
     }
 
-    /// Busca un archivo local por gdrive_id
-    pub async fn find_local_sync_file_by_gdrive_id(&self, gdrive_id: &str) -> Result<Option<LocalSyncFile>> {
-        let file = sqlx::query_as::<_, LocalSyncFile>(
-            "SELECT * FROM local_sync_files WHERE gdrive_id = ?"
+    /// IDs de Drive de las subcarpetas ya conocidas bajo `inode` (sin incluirlo)
+    pub async fn folder_ids_in_subtree(&self, inode: u64) -> Result<Vec<String>> {
+        let ids = sqlx::query_scalar::<_, String>(
+            r#"
+            WITH RECURSIVE subfolders(inode) AS (
+                SELECT d.child_inode FROM dentry d
+                JOIN attrs a ON a.inode = d.child_inode
+                WHERE d.parent_inode = ? AND a.is_dir = 1
+                UNION
+                SELECT d.child_inode FROM dentry d
+                JOIN attrs a ON a.inode = d.child_inode
+                JOIN subfolders s ON d.parent_inode = s.inode
+                WHERE a.is_dir = 1
+            )
+            SELECT i.gdrive_id FROM inodes i JOIN subfolders s ON s.inode = i.inode
+            "#
         )
-        .bind(gdrive_id)
-        .fetch_optional(&self.pool)
+        .bind(inode as i64)
+        .fetch_all(&self.read_pool)
         .await?;
-
-        Ok(file)
+        Ok(ids)
     }
 
-    /// Obtiene un directorio local por ID
-    pub async fn get_local_sync_dir(&self, id: i64) -> Result<LocalSyncDir> {
-        let dir = sqlx::query_as::<_, LocalSyncDir>(
-            "SELECT * FROM local_sync_dirs WHERE id = ?"
+    /// El inode y todo lo que cuelga de él (archivos y carpetas)
+    pub async fn subtree_inodes(&self, inode: u64) -> Result<Vec<u64>> {
+        let inodes = sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH RECURSIVE subtree(inode) AS (
+                SELECT ?
+                UNION
+                SELECT d.child_inode FROM dentry d
+                JOIN subtree s ON d.parent_inode = s.inode
+            )
+            SELECT inode FROM subtree
+            "#
         )
-        .bind(id)
-        .fetch_one(&self.pool)
+        .bind(inode as i64)
+        .fetch_all(&self.read_pool)
         .await?;
-
-        Ok(dir)
+        Ok(inodes.into_iter().map(|i| i as u64).collect())
     }
 
-    /// Cambia el modo de disponibilidad de un archivo
-    pub async fn set_file_availability(&self, sync_dir_id: i64, relative_path: &str, availability: &str) -> Result<()> {
+    /// Marca una carpeta "Mantener siempre en este dispositivo" (`local_online`)
+    /// o "Liberar espacio" (`online_only`). A diferencia de `set_availability`,
+    /// escribe la fila aunque sea `online_only`: una subcarpeta liberada dentro
+    /// de una carpeta mantenida debe distinguirse de una sin elección.
+    pub async fn set_folder_availability(&self, inode: u64, availability: &str) -> Result<()> {
         sqlx::query(
-            "UPDATE local_sync_files SET availability = ? WHERE sync_dir_id = ? AND relative_path = ?"
+            r#"
+            INSERT INTO sync_state (inode, availability, dirty, version)
+            VALUES (?, ?, 0, 0)
+            ON CONFLICT(inode) DO UPDATE SET availability = excluded.availability
+            "#
         )
+        .bind(inode as i64)
         .bind(availability)
-        .bind(sync_dir_id)
-        .bind(relative_path)
         .execute(&self.pool)
         .await?;
+        Ok(())
+    }
 
+    /// "Mantener siempre en este dispositivo" para un archivo o, en una carpeta,
+    /// para ella (lo que llegue después lo decide `folder_keeps_content`) y para
+    /// todos los archivos que ya cuelgan de ella
+    pub async fn keep_on_device(&self, inode: u64, is_dir: bool) -> Result<()> {
+        if !is_dir {
+            return self.set_availability(inode, "local_online", true).await;
+        }
+        self.set_folder_availability(inode, "local_online").await?;
+        for child in self.subtree_inodes(inode).await? {
+            if self.is_dir(child).await? == Some(false) {
+                self.set_availability(child, "local_online", true).await?;
+            }
+        }
         Ok(())
     }
 
-    /// Actualiza metadatos locales de un archivo
-    pub async fn update_local_file_metadata(
-        &self,
-        sync_dir_id: i64,
-        relative_path: &str,
-        availability: &str,
-        local_size: i64,
-        local_mtime: i64,
-        local_md5: &str,
-    ) -> Result<()> {
-        sqlx::query(
+    /// `gdrive_id` de lo marcado "Mantener siempre en este dispositivo" y si es
+    /// carpeta. Los archivos de una carpeta mantenida no se repiten
+    pub async fn list_kept_on_device(&self) -> Result<Vec<(String, bool)>> {
+        let rows = sqlx::query_as::<_, (String, bool)>(
             r#"
-            UPDATE local_sync_files 
-            SET availability = ?, local_size = ?, local_mtime = ?, local_md5 = ?
-            WHERE sync_dir_id = ? AND relative_path = ?
+            SELECT i.gdrive_id, a.is_dir FROM sync_state s
+            JOIN inodes i ON i.inode = s.inode
+            JOIN attrs a ON a.inode = s.inode
+            WHERE s.availability = 'local_online'
+              AND COALESCE(s.deleted_at, 0) = 0
+              AND i.pending_create = 0
+              AND NOT (a.is_dir = 0 AND EXISTS (
+                  SELECT 1 FROM dentry d
+                  JOIN sync_state ps ON ps.inode = d.parent_inode
+                  WHERE d.child_inode = s.inode AND ps.availability = 'local_online'
+              ))
+            ORDER BY i.gdrive_id
             "#
         )
-        .bind(availability)
-        .bind(local_size)
-        .bind(local_mtime)
-        .bind(local_md5)
-        .bind(sync_dir_id)
-        .bind(relative_path)
-        .execute(&self.pool)
+        .fetch_all(&self.read_pool)
         .await?;
+        Ok(rows)
+    }
 
-        Ok(())
+    /// Si lo que entra en la carpeta debe quedarse en el dispositivo: decide la
+    /// carpeta más cercana (ella misma o un ancestro) con una elección guardada
+    pub async fn folder_keeps_content(&self, inode: u64) -> Result<bool> {
+        let availability = sqlx::query_scalar::<_, String>(
+            r#"
+            WITH RECURSIVE up(inode, depth) AS (
+                SELECT ?, 0
+                UNION
+                SELECT d.parent_inode, up.depth + 1 FROM dentry d
+                JOIN up ON d.child_inode = up.inode
+                WHERE up.inode > 1 AND up.depth < 256
+            )
+            SELECT s.availability FROM up
+            JOIN attrs a ON a.inode = up.inode AND a.is_dir = 1
+            JOIN sync_state s ON s.inode = up.inode
+            WHERE s.availability IS NOT NULL
+            ORDER BY up.depth
+            LIMIT 1
+            "#
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(availability.as_deref() == Some("local_online"))
     }
 
-    /// Actualiza metadatos remotos desde un cambio de Drive
-    pub async fn update_local_file_from_remote(
-        &self,
-        file_id: i64,
-        remote_md5: Option<&str>,
-        remote_mtime: Option<i64>,
-    ) -> Result<()> {
-        sqlx::query(
-            "UPDATE local_sync_files SET remote_md5 = ?, remote_mtime = ?, dirty = 0 WHERE id = ?"
+    /// Verifica si algún descendiente de la carpeta virtual SHARED tiene un upload en curso
+    pub async fn has_uploading_shared_descendant(&self) -> Result<bool> {
+        let found: Option<i64> = sqlx::query_scalar(
+            r#"
+            WITH RECURSIVE ancestors(inode) AS (
+                SELECT d.child_inode FROM dentry d
+                JOIN sync_state s ON s.inode = d.child_inode
+                WHERE s.uploading = 1
+                UNION
+                SELECT d.parent_inode FROM dentry d
+                JOIN ancestors a ON d.child_inode = a.inode
+                WHERE a.inode > 1
+            )
+            SELECT 1 FROM ancestors a
+            JOIN dentry d ON d.child_inode = a.inode
+            JOIN attrs at ON at.inode = a.inode
+            WHERE d.parent_inode = 1 AND at.owned_by_me = 0
+            LIMIT 1
+            "#
         )
-        .bind(remote_md5)
-        .bind(remote_mtime)
-        .bind(file_id)
-        .execute(&self.pool)
+        .fetch_optional(&self.read_pool)
         .await?;
 
-        Ok(())
+        Ok(found.is_some())
     }
 
-    /// Actualiza solo metadatos remotos (para archivos online_only)
-    pub async fn update_local_file_remote_metadata(&self, file_id: i64, remote_md5: Option<&str>) -> Result<()> {
-        sqlx::query(
-            "UPDATE local_sync_files SET remote_md5 = ? WHERE id = ?"
+    /// Calcula el estado de sincronización agregado de todos los archivos
+    /// descendientes de un directorio, de forma recursiva via CTE.
+    /// Retorna (has_local_only, has_synced, total_files).
+    pub async fn get_directory_aggregate_status(&self, parent_inode: u64) -> Result<(bool, bool, i64)> {
+        // O(1): lectura directa de contadores pre-calculados
+        let row = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT dirty_desc_count, synced_desc_count FROM dir_counters WHERE inode = ?"
         )
-        .bind(remote_md5)
-        .bind(file_id)
-        .execute(&self.pool)
+        .bind(parent_inode as i64)
+        .fetch_optional(&self.read_pool)
         .await?;
 
-        Ok(())
+        match row {
+            Some((dirty, synced)) => {
+                let total = dirty + synced;
+                Ok((dirty > 0, synced > 0, total))
+            }
+            None => Ok((false, false, 0)),
+        }
     }
 
-    /// Limpia el flag dirty de un archivo después de una subida exitosa
-    pub async fn clear_local_file_dirty(&self, file_id: i64) -> Result<()> {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64;
-
-        sqlx::query(
-            "UPDATE local_sync_files SET dirty = 0, last_synced = ? WHERE id = ?"
+    /// Estado agregado para la carpeta virtual SHARED.
+    /// Usa contadores pre-calculados con SHARED_INODE como clave.
+    pub async fn get_shared_directory_aggregate_status(&self) -> Result<(bool, bool, i64)> {
+        // SHARED_INODE = 0xFFFFFFFFFFFFFFFE
+        let shared_inode = 0xFFFFFFFFFFFFFFFEu64;
+        let row = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT dirty_desc_count, synced_desc_count FROM dir_counters WHERE inode = ?"
         )
-        .bind(now)
-        .bind(file_id)
-        .execute(&self.pool)
+        .bind(shared_inode as i64)
+        .fetch_optional(&self.read_pool)
         .await?;
 
-        Ok(())
+        match row {
+            Some((dirty, synced)) => {
+                let total = dirty + synced;
+                Ok((dirty > 0, synced > 0, total))
+            }
+            None => Ok((false, false, 0)),
+        }
     }
 
-    /// Establece el gdrive_id para un archivo local después de crearlo en Drive
-    pub async fn set_local_file_gdrive_id(&self, file_id: i64, gdrive_id: &str) -> Result<()> {
-        sqlx::query(
-            "UPDATE local_sync_files SET gdrive_id = ? WHERE id = ?"
+    // ============================================================
+    // Métodos para Soft Delete (Tombstones)
+    // ============================================================
+
+    /// Obtiene el inode asociado a un gdrive_id
+    pub async fn get_inode_by_gdrive_id(&self, gdrive_id: &str) -> Result<Option<u64>> {
+        let row = sqlx::query_scalar::<_, i64>(
+            "SELECT inode FROM inodes WHERE gdrive_id = ?"
         )
         .bind(gdrive_id)
-        .bind(file_id)
-        .execute(&self.pool)
+        .fetch_optional(&self.read_pool)
         .await?;
 
-        Ok(())
+        Ok(row.map(|i| i as u64))
     }
 
-    /// Busca un archivo de local sync por su path absoluto del filesystem
-    /// Útil para comandos IPC que reciben file:// URIs de Nautilus
-    pub async fn find_local_sync_file_by_absolute_path(
-        &self,
-        absolute_path: &str,
-    ) -> Result<Option<LocalSyncFile>> {
-        // Obtener todas las carpetas locales habilitadas
-        let sync_dirs = self.get_enabled_local_sync_dirs().await?;
-        
-        // Intentar encontrar qué carpeta contiene este path
-        for dir in sync_dirs {
-            let base_path = &dir.local_path;
-            
-            // Si el absolute_path empieza con este base_path
-            if absolute_path.starts_with(base_path) {
-                // Calcular relative_path
-                let relative_path = absolute_path
-                    .strip_prefix(base_path)
-                    .unwrap_or("")
-                    .trim_start_matches('/');
-                
-                // Buscar el archivo en la DB
-                if let Some(file) = self.get_local_sync_file(dir.id, relative_path).await? {
-                    return Ok(Some(file));
-                }
-            }
-        }
-        
-        Ok(None)
+    /// Marca un archivo o directorio y todo su contenido (recursivamente) como eliminado
+    pub async fn soft_delete_by_gdrive_id(&self, gdrive_id: &str) -> Result<bool> {
+        soft_delete_in(&mut *self.pool.acquire().await?, gdrive_id).await
     }
 
-    /// Resuelve un path absoluto a (sync_dir_id, relative_path)
-    /// Lanza error si el path no pertenece a ninguna carpeta Local Sync
-    pub async fn resolve_local_sync_path(
-        &self,
-        absolute_path: &str,
-    ) -> Result<(i64, String)> {
-        let sync_dirs = self.get_enabled_local_sync_dirs().await?;
-        
-        for dir in sync_dirs {
-            let base_path = &dir.local_path;
-            
-            if absolute_path.starts_with(base_path) {
-                let relative_path = absolute_path
-                    .strip_prefix(base_path)
-                    .unwrap_or("")
-                    .trim_start_matches('/')
-                    .to_string();
-                
-                return Ok((dir.id, relative_path));
+    /// Soft delete para eliminaciones REMOTAS (no marca dirty).
+    /// Usa soft_delete_by_gdrive_id internamente, pero luego limpia dirty=0
+    /// porque la eliminación ya ocurrió en GDrive y no necesita re-subirse.
+    pub async fn soft_delete_remote(&self, gdrive_id: &str) -> Result<bool> {
+        let inode = self.get_inode_by_gdrive_id(gdrive_id).await?;
+        let result = self.soft_delete_by_gdrive_id(gdrive_id).await?;
+        if result {
+            if let Some(root_inode) = inode {
+                // Limpiar dirty para este inode y todos sus descendientes
+                // (ya fueron movidos a dentry_deleted por soft_delete_by_gdrive_id)
+                self.clear_deleted_subtree_dirty(root_inode).await?;
+
+                tracing::debug!("Remote soft delete: dirty cleared for gdrive_id={}", gdrive_id);
             }
         }
-        
-        Err(anyhow::anyhow!("Path no pertenece a ninguna carpeta Local Sync: {}", absolute_path))
+        Ok(result)
     }
 
-    pub async fn set_shortcut_target_id(&self, inode: u64, target_id: &str) -> Result<()> {
-        sqlx::query("UPDATE attrs SET shortcut_target_id = ? WHERE inode = ?")
-            .bind(target_id)
-            .bind(inode as i64)
-            .execute(&self.pool)
-            .await?;
-        Ok(())
+    /// Limpia dirty de un inode eliminado y de todos sus descendientes en tombstone.
+    /// Mover una carpeta a la papelera de Drive arrastra todo su contenido, así que
+    /// los descendientes no necesitan su propia llamada a la API.
+    /// Retorna cuántos inodes dejaron de estar dirty.
+    pub async fn clear_deleted_subtree_dirty(&self, inode: u64) -> Result<u64> {
+        let result = sqlx::query(r#"
+            WITH RECURSIVE subordinates AS (
+                SELECT child_inode FROM dentry_deleted WHERE child_inode = ?
+                UNION ALL
+                SELECT d.child_inode FROM dentry_deleted d
+                JOIN subordinates s ON d.parent_inode = s.child_inode
+            )
+            UPDATE sync_state SET dirty = 0, status = 'synced', last_error = NULL,
+                retry_count = 0, next_retry_at = NULL, error_permanent = 0
+            WHERE inode IN (SELECT child_inode FROM subordinates)
+              AND deleted_at IS NOT NULL
+              AND dirty = 1
+        "#)
+        .bind(inode as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
     }
 
-    pub async fn set_bulk_shortcut_targets(&self, items: &[(u64, String)]) -> Result<()> {
-        if items.is_empty() { return Ok(()); }
-        let mut tx = self.pool.begin().await?;
-        for (inode, target_id) in items {
-            sqlx::query("UPDATE attrs SET shortcut_target_id = ? WHERE inode = ?")
-                .bind(target_id)
-                .bind(*inode as i64)
-                .execute(&mut *tx)
-                .await?;
+    /// Estado del padre en tombstone de un inode eliminado:
+    /// - `None`: el padre no fue eliminado (el inode se borró por sí mismo)
+    /// - `Some(true)`: el padre también está pendiente de enviarse a la papelera
+    /// - `Some(false)`: el padre ya está en la papelera de Drive
+    pub async fn deleted_parent_pending(&self, inode: u64) -> Result<Option<bool>> {
+        let row = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT COALESCE(s.dirty, 0)
+            FROM dentry_deleted d
+            JOIN sync_state s ON s.inode = d.parent_inode
+            WHERE d.child_inode = ? AND s.deleted_at IS NOT NULL
+            "#
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        Ok(row)
+    }
+
+    /// Restaura un archivo eliminado (quita tombstone)
+    /// Mueve el dentry de vuelta, elimina deleted_at
+    pub async fn restore_by_gdrive_id(&self, gdrive_id: &str) -> Result<bool> {
+        let inode = match self.get_inode_by_gdrive_id(gdrive_id).await? {
+            Some(i) => i,
+            None => return Ok(false),
+        };
+
+        // Estado previo
+        let prev = sqlx::query_as::<_, (Option<String>, Option<bool>, Option<i64>)>(
+            "SELECT s.availability, s.dirty, s.deleted_at FROM sync_state s WHERE s.inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let was_dirty = prev.as_ref().map(|(_, d, del)| {
+            d.unwrap_or(false) || del.map(|v| v > 0).unwrap_or(false)
+        }).unwrap_or(false);
+
+        let was_synced = prev.as_ref().map(|(av, d, del)| {
+            let is_local_online = av.as_deref().unwrap_or("online_only") == "local_online";
+            let not_dirty = !d.unwrap_or(false);
+            let not_deleted = del.unwrap_or(0) == 0;
+            is_local_online && not_dirty && not_deleted
+        }).unwrap_or(false);
+
+        // 1. Restaurar dentry desde dentry_deleted (si el nombre se ocupó mientras
+        //    estaba en la papelera, el restaurado recibe sufijo en vez de ocultar al otro)
+        let tombstone = sqlx::query_as::<_, (i64, String)>(
+            "SELECT parent_inode, name FROM dentry_deleted WHERE child_inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some((parent_inode, name)) = tombstone {
+            let mut conn = self.pool.acquire().await?;
+            link_remote_dentry(&mut conn, parent_inode as u64, inode, &name).await?;
+            touch_dirs(&mut conn, [parent_inode as u64]).await?;
         }
-        tx.commit().await?;
-        Ok(())
+
+        // 2. Eliminar de dentry_deleted
+        sqlx::query("DELETE FROM dentry_deleted WHERE child_inode = ?")
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+
+        // 3. Limpiar deleted_at en sync_state (y una eliminación definitiva pendiente)
+        sqlx::query("UPDATE sync_state SET deleted_at = NULL, purge_requested = 0 WHERE inode = ?")
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+
+        // 4. Burbujear
+        let is_dir: Option<bool> = sqlx::query_scalar(
+            "SELECT is_dir FROM attrs WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if is_dir == Some(false) {
+            let curr = sqlx::query_as::<_, (Option<String>, Option<bool>, Option<i64>)>(
+                "SELECT s.availability, s.dirty, s.deleted_at FROM sync_state s WHERE s.inode = ?"
+            )
+            .bind(inode as i64)
+            .fetch_one(&self.pool)
+            .await?;
+
+            let is_dirty = curr.1.unwrap_or(false) || curr.2.unwrap_or(0) > 0;
+            let is_synced = {
+                let is_local_online = curr.0.as_deref().unwrap_or("online_only") == "local_online";
+                let not_dirty = !curr.1.unwrap_or(false);
+                let not_deleted = curr.2.unwrap_or(0) == 0;
+                is_local_online && not_dirty && not_deleted
+            };
+
+            let delta_dirty = if was_dirty && !is_dirty { -1 } else if !was_dirty && is_dirty { 1 } else { 0 };
+            let delta_synced = if was_synced && !is_synced { -1 } else if !was_synced && is_synced { 1 } else { 0 };
+
+            if delta_dirty != 0 || delta_synced != 0 {
+                self.bubble_state_change(inode, delta_dirty, delta_synced).await?;
+            }
+        } else if is_dir == Some(true) {
+            // Restaurar directorio: asegurar que tiene fila en dir_counters
+            self.ensure_dir_counter(inode).await?;
+        }
+
+        tracing::debug!("Archivo restaurado: gdrive_id={}, inode={}", gdrive_id, inode);
+        Ok(true)
     }
 
-    pub async fn resolve_shortcut_sizes(&self) -> Result<usize> {
+    /// Lista los elementos eliminados en local (carpeta virtual `.Trash`): los
+    /// tombstones cuyo padre no fue eliminado (el contenido de una carpeta
+    /// eliminada va con ella), salvo los ya pendientes de eliminación
+    /// definitiva. Mismo formato que `list_starred`.
+    pub async fn list_trashed(&self) -> Result<Vec<(u64, String, bool, Option<String>, String)>> {
+        let children = sqlx::query_as::<_, (i64, String, bool, Option<String>, String)>(
+            r#"
+            SELECT
+                d.child_inode,
+                d.name,
+                a.is_dir,
+                a.mime_type,
+                i.gdrive_id
+            FROM dentry_deleted d
+            JOIN attrs a ON d.child_inode = a.inode
+            JOIN inodes i ON d.child_inode = i.inode
+            JOIN sync_state s ON d.child_inode = s.inode
+            WHERE s.purge_requested = 0
+              AND NOT EXISTS (SELECT 1 FROM dentry_deleted p WHERE p.child_inode = d.parent_inode)
+            ORDER BY d.name
+            "#
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(children.into_iter()
+            .map(|(inode, name, is_dir, mime, gdrive_id)| (inode as u64, name, is_dir, mime, gdrive_id))
+            .collect())
+    }
+
+    /// Busca por nombre un elemento de `.Trash` (ver `list_trashed`). Con
+    /// nombres repetidos gana el inode más antiguo.
+    pub async fn lookup_trashed(&self, name: &str) -> Result<Option<u64>> {
+        let inode: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT d.child_inode
+            FROM dentry_deleted d
+            JOIN sync_state s ON d.child_inode = s.inode
+            WHERE d.name = ? AND s.purge_requested = 0
+              AND NOT EXISTS (SELECT 1 FROM dentry_deleted p WHERE p.child_inode = d.parent_inode)
+            ORDER BY d.child_inode
+            LIMIT 1
+            "#
+        )
+        .bind(name)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(inode.map(|i| i as u64))
+    }
+
+    /// Nombre con el que un elemento eliminado aparece en `.Trash`
+    pub async fn get_trashed_name(&self, inode: u64) -> Result<Option<String>> {
+        let name = sqlx::query_scalar::<_, String>(
+            "SELECT name FROM dentry_deleted WHERE child_inode = ? ORDER BY deleted_at DESC LIMIT 1"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        Ok(name)
+    }
+
+    /// Pide eliminar definitivamente un elemento de `.Trash`: queda dirty con
+    /// una operación `Delete` y el uploader llama a `files.delete` en vez de
+    /// enviarlo a la papelera. Retorna false si el inode no está en `.Trash`.
+    pub async fn request_permanent_delete(&self, inode: u64) -> Result<bool> {
         let result = sqlx::query(
-            r#"UPDATE attrs SET size = (
-                SELECT a2.size FROM inodes i2 JOIN attrs a2 ON i2.inode = a2.inode
-                WHERE i2.gdrive_id = attrs.shortcut_target_id AND a2.size > 0
-            )
-            WHERE shortcut_target_id IS NOT NULL AND size = 0
-            AND EXISTS (
-                SELECT 1 FROM inodes i2 JOIN attrs a2 ON i2.inode = a2.inode
-                WHERE i2.gdrive_id = attrs.shortcut_target_id AND a2.size > 0
-            )"#
+            r#"
+            UPDATE sync_state SET purge_requested = 1
+            WHERE inode = ?1 AND deleted_at IS NOT NULL AND purge_requested = 0
+              AND EXISTS (
+                  SELECT 1 FROM dentry_deleted d
+                  WHERE d.child_inode = ?1
+                    AND NOT EXISTS (SELECT 1 FROM dentry_deleted p WHERE p.child_inode = d.parent_inode)
+              )
+            "#
         )
+        .bind(inode as i64)
         .execute(&self.pool)
         .await?;
-        Ok(result.rows_affected() as usize)
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        self.mark_dirty(inode).await?;
+        self.record_upload_op(inode, UploadOp::Delete).await?;
+        Ok(true)
     }
-}
-/// Struct que representa un directorio local sincronizado
-#[derive(Debug, Clone, sqlx::FromRow)]
-pub struct LocalSyncDir {
-    pub id: i64,
-    pub local_path: String,
-    pub gdrive_folder_id: Option<String>,
-    pub enabled: bool,
-    pub last_sync: i64,
-    pub created_at: i64,
-}
 
-/// Struct que representa un archivo individual en Local Sync
-#[derive(Debug, Clone, sqlx::FromRow)]
-pub struct LocalSyncFile {
-    pub id: i64,
-    pub sync_dir_id: i64,
-    pub relative_path: String,
-    pub is_dir: bool,
-    
-    pub availability: String,  // 'local_online' | 'online_only'
-    
-    pub local_mtime: Option<i64>,
-    pub local_size: Option<i64>,
-    pub local_md5: Option<String>,
-    
-    pub gdrive_id: Option<String>,
-    pub remote_md5: Option<String>,
-    pub remote_mtime: Option<i64>,
-    
-    pub dirty: bool,
-    pub last_synced: Option<i64>,
-}
+    /// Indica si el usuario pidió eliminar el inode definitivamente
+    pub async fn is_purge_requested(&self, inode: u64) -> Result<bool> {
+        let purge: Option<bool> = sqlx::query_scalar("SELECT purge_requested FROM sync_state WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(purge.unwrap_or(false))
+    }
 
-/// Struct para inserción masiva de metadatos
-#[derive(Debug, Clone)]
-pub struct BulkFileMetadata {
-    pub inode: u64,
-    pub size: i64,
-    pub mtime: i64,
-    pub mode: u32,
-    pub is_dir: bool,
-    pub mime_type: Option<String>,
-    pub can_move: bool,
-    pub shared: bool,
-    pub owned_by_me: bool,
-}
+    /// Verifica si un gdrive_id tiene un tombstone activo
+    pub async fn has_tombstone(&self, gdrive_id: &str) -> Result<bool> {
+        let inode = match self.get_inode_by_gdrive_id(gdrive_id).await? {
+            Some(i) => i,
+            None => return Ok(false),
+        };
 
-/// Struct para inserción masiva de dentries
-#[derive(Debug, Clone)]
-pub struct BulkDentry {
-    pub parent_inode: u64,
-    pub child_inode: u64,
-    pub name: String,
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM dentry_deleted WHERE child_inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// Hard delete: elimina permanentemente registros con deleted_at > grace_period
+    /// Retorna los gdrive_id purgados para que el llamador elimine su contenido en caché
+    /// (los registros de `file_cache_chunks` se eliminan aquí).
+    pub async fn purge_expired_tombstones(&self, grace_days: i64) -> Result<Vec<String>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        
+        let cutoff = now - (grace_days * 24 * 60 * 60);
+
+        // Obtener inodos a purgar
+        let to_purge: Vec<(i64, String)> = sqlx::query_as(
+            r#"
+            SELECT d.child_inode, i.gdrive_id
+            FROM dentry_deleted d
+            JOIN inodes i ON i.inode = d.child_inode
+            WHERE d.deleted_at < ?
+            "#
+        )
+        .bind(cutoff)
+        .fetch_all(&self.pool)
+        .await?;
+
+        if to_purge.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut purged = Vec::with_capacity(to_purge.len());
+        for (inode, gdrive_id) in to_purge {
+            self.hard_delete_inode(inode as u64).await?;
+            purged.push(gdrive_id);
+        }
+
+        tracing::info!("Purgados {} tombstones expirados (grace_days={})", purged.len(), grace_days);
+        Ok(purged)
+    }
+
+    /// Elimina permanentemente un inode y todos sus registros asociados
+    async fn hard_delete_inode(&self, inode: u64) -> Result<()> {
+        hard_delete_in(&mut *self.pool.acquire().await?, inode).await
+    }
+
+    /// Hard delete por gdrive_id: elimina permanentemente un archivo de la DB
+    /// Usado cuando un archivo es eliminado permanentemente de Google Drive
+    pub async fn hard_delete_by_gdrive_id(&self, gdrive_id: &str) -> Result<bool> {
+        let inode = match self.get_inode_by_gdrive_id(gdrive_id).await? {
+            Some(i) => i,
+            None => return Ok(false), // No existe, nada que eliminar
+        };
+
+        self.hard_delete_inode(inode).await?;
+        tracing::info!("Hard delete aplicado: gdrive_id={}, inode={}", gdrive_id, inode);
+        Ok(true)
+    }
+
+    /// Hard delete de un archivo o carpeta y de todo su contenido (activo o en tombstone).
+    /// Usado cuando Drive reporta `removed=true`: ya no hay nada que restaurar.
+    /// Retorna los gdrive_id eliminados para que el llamador borre su caché en disco.
+    pub async fn hard_delete_subtree_by_gdrive_id(&self, gdrive_id: &str) -> Result<Vec<String>> {
+        let Some(root_inode) = self.get_inode_by_gdrive_id(gdrive_id).await? else {
+            return Ok(Vec::new());
+        };
+        // Solo si seguía visible: en tombstone ya salió de su carpeta
+        let parent = self.get_parent_inode(root_inode).await?;
+
+        // Hijos antes que padres: el burbujeo de cada archivo necesita las dentries de sus ancestros.
+        // El tope de profundidad protege contra ciclos en datos corruptos.
+        let subtree = sqlx::query_as::<_, (i64, String)>(
+            r#"
+            WITH RECURSIVE
+                links(parent_inode, child_inode) AS (
+                    SELECT parent_inode, child_inode FROM dentry
+                    UNION ALL
+                    SELECT parent_inode, child_inode FROM dentry_deleted
+                ),
+                subtree(inode, depth) AS (
+                    SELECT ?, 0
+                    UNION
+                    SELECT l.child_inode, s.depth + 1
+                    FROM links l
+                    JOIN subtree s ON l.parent_inode = s.inode
+                    WHERE s.depth < 256
+                )
+            SELECT s.inode, i.gdrive_id
+            FROM subtree s
+            JOIN inodes i ON i.inode = s.inode
+            GROUP BY s.inode
+            ORDER BY MAX(s.depth) DESC
+            "#
+        )
+        .bind(root_inode as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut removed = Vec::with_capacity(subtree.len());
+        for (inode, id) in subtree {
+            self.hard_delete_inode(inode as u64).await?;
+            removed.push(id);
+        }
+        touch_dirs(&mut *self.pool.acquire().await?, parent).await?;
+
+        tracing::info!("Hard delete recursivo: gdrive_id={}, {} inodes eliminados", gdrive_id, removed.len());
+        Ok(removed)
+    }
+
+    // ============================================================
+    // Intent log de mutaciones FUSE (ver fuse::journal)
+    // ============================================================
+
+    /// Registra una intención pendiente (payload JSON) y retorna su id
+    pub async fn log_intent(&self, payload: &str) -> Result<i64> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        let result = sqlx::query("INSERT INTO fs_intents (payload, created_at) VALUES (?, ?)")
+            .bind(payload)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Marca una intención como completada (la elimina del log)
+    pub async fn complete_intent(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM fs_intents WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Intenciones que quedaron sin completar, en orden de registro
+    pub async fn pending_intents(&self) -> Result<Vec<(i64, String)>> {
+        let rows = sqlx::query_as::<_, (i64, String)>(
+            "SELECT id, payload FROM fs_intents ORDER BY id"
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+        Ok(rows)
+    }
+
+    // ============================================================
+    // Métodos para File Cache Chunks (On-Demand Caching)
+    // ============================================================
+
+    /// Registra un rango descargado en la caché.
+    ///
+    /// Los rangos son inclusivos. El nuevo rango se fusiona con los que se
+    /// solapan o son contiguos, de modo que la tabla guarda un solo intervalo
+    /// por zona descargada en lugar de una fila por cada lectura de 128KB.
+    pub async fn add_cached_chunk(&self, inode: u64, start: u64, end: u64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let neighbours: Vec<(i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT start_offset, end_offset
+            FROM file_cache_chunks
+            WHERE inode = ?
+              AND end_offset >= ? - 1
+              AND start_offset <= ? + 1
+            "#
+        )
+        .bind(inode as i64)
+        .bind(start as i64)
+        .bind(end as i64)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let merged_start = neighbours.iter().map(|(s, _)| *s as u64).fold(start, u64::min);
+        let merged_end = neighbours.iter().map(|(_, e)| *e as u64).fold(end, u64::max);
+
+        if !neighbours.is_empty() {
+            sqlx::query(
+                r#"
+                DELETE FROM file_cache_chunks
+                WHERE inode = ?
+                  AND start_offset >= ?
+                  AND end_offset <= ?
+                "#
+            )
+            .bind(inode as i64)
+            .bind(merged_start as i64)
+            .bind(merged_end as i64)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO file_cache_chunks (inode, start_offset, end_offset)
+            VALUES (?, ?, ?)
+            "#
+        )
+        .bind(inode as i64)
+        .bind(merged_start as i64)
+        .bind(merged_end as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Compacta la tabla de chunks fusionando rangos solapados o contiguos.
+    /// Necesario para bases de datos creadas antes de que `add_cached_chunk`
+    /// fusionara al insertar. Retorna cuántas filas se eliminaron.
+    pub async fn compact_cached_chunks(&self) -> Result<u64> {
+        let rows: Vec<(i64, i64, i64)> = sqlx::query_as(
+            "SELECT inode, start_offset, end_offset FROM file_cache_chunks ORDER BY inode, start_offset"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut by_inode: std::collections::BTreeMap<i64, Vec<(u64, u64)>> = std::collections::BTreeMap::new();
+        for (inode, start, end) in rows {
+            by_inode.entry(inode).or_default().push((start as u64, end as u64));
+        }
+
+        let mut removed = 0u64;
+        let mut tx = self.pool.begin().await?;
+        for (inode, ranges) in by_inode {
+            let before = ranges.len();
+            let merged = merge_ranges(ranges);
+            if merged.len() == before {
+                continue;
+            }
+            removed += (before - merged.len()) as u64;
+
+            sqlx::query("DELETE FROM file_cache_chunks WHERE inode = ?")
+                .bind(inode)
+                .execute(&mut *tx)
+                .await?;
+            for (start, end) in merged {
+                sqlx::query("INSERT INTO file_cache_chunks (inode, start_offset, end_offset) VALUES (?, ?, ?)")
+                    .bind(inode)
+                    .bind(start as i64)
+                    .bind(end as i64)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+
+        Ok(removed)
+    }
+
+    /// Muestra aleatoria de archivos con contenido en caché para el verificador de integridad.
+    /// Excluye archivos dirty, en subida, ignorados (no existen en Drive), eliminados
+    /// y shortcuts (su caché es la del destino).
+    pub async fn sample_cached_files(&self, limit: u32) -> Result<Vec<CachedFileSample>> {
+        let rows = sqlx::query_as::<_, (i64, String, i64, Option<String>, i64, i64)>(
+            r#"
+            SELECT c.inode, i.gdrive_id, a.size, s.remote_md5,
+                   MAX(c.end_offset), SUM(c.end_offset - c.start_offset + 1)
+            FROM file_cache_chunks c
+            JOIN inodes i ON i.inode = c.inode
+            JOIN attrs a ON a.inode = c.inode
+            LEFT JOIN sync_state s ON s.inode = c.inode
+            WHERE a.is_dir = 0
+              AND a.shortcut_target_id IS NULL
+              AND COALESCE(s.dirty, 0) = 0
+              AND COALESCE(s.uploading, 0) = 0
+              AND COALESCE(s.status, 'synced') != 'ignored'
+              AND s.deleted_at IS NULL
+            GROUP BY c.inode
+            ORDER BY RANDOM()
+            LIMIT ?
+            "#
+        )
+        .bind(limit as i64)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|(inode, gdrive_id, size, remote_md5, max_end, cached_bytes)| CachedFileSample {
+                inode: inode as u64,
+                gdrive_id,
+                size: size.max(0) as u64,
+                remote_md5,
+                max_cached_end: max_end as u64,
+                cached_bytes: cached_bytes as u64,
+            })
+            .collect())
+    }
+
+    /// Archivos con contenido en caché, con su carpeta de primer nivel (para el
+    /// panel de uso de caché). Con varios padres se sigue el de menor inode; los
+    /// archivos del root o que no cuelgan de él quedan sin carpeta.
+    pub async fn cache_entries(&self) -> Result<Vec<CacheEntry>> {
+        let rows = sqlx::query_as::<_, (i64, String, i64, bool, bool, Option<String>)>(
+            r#"
+            WITH RECURSIVE
+            cached(inode, bytes) AS (
+                SELECT inode, SUM(end_offset - start_offset + 1)
+                FROM file_cache_chunks
+                GROUP BY inode
+            ),
+            up(inode, node, parent, depth) AS (
+                SELECT c.inode, c.inode,
+                       (SELECT MIN(d.parent_inode) FROM dentry d WHERE d.child_inode = c.inode), 0
+                FROM cached c
+                UNION ALL
+                SELECT up.inode, up.parent,
+                       (SELECT MIN(d.parent_inode) FROM dentry d WHERE d.child_inode = up.parent), up.depth + 1
+                FROM up
+                WHERE up.parent IS NOT NULL AND up.parent != 1 AND up.depth < 256
+            )
+            SELECT c.inode, i.gdrive_id, c.bytes,
+                   COALESCE(s.availability, 'online_only') = 'local_online',
+                   COALESCE(s.dirty, 0) = 0 AND COALESCE(s.uploading, 0) = 0
+                       AND COALESCE(s.status, 'synced') != 'ignored',
+                   (SELECT d.name FROM dentry d
+                    WHERE d.parent_inode = 1 AND d.child_inode = up.node AND up.node != c.inode
+                    LIMIT 1)
+            FROM cached c
+            JOIN inodes i ON i.inode = c.inode
+            LEFT JOIN sync_state s ON s.inode = c.inode
+            LEFT JOIN up ON up.inode = c.inode AND up.parent = 1
+            "#
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|(inode, gdrive_id, bytes, pinned, evictable, top_folder)| CacheEntry {
+                inode: inode as u64,
+                gdrive_id,
+                cached_bytes: bytes.max(0) as u64,
+                pinned,
+                evictable,
+                top_folder,
+            })
+            .collect())
+    }
+
+    /// Registra que el usuario abrió un directorio (o un archivo dentro de él)
+    pub async fn record_dir_access(&self, inode: u64) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        sqlx::query(
+            r#"
+            INSERT INTO dir_access_log (inode, last_access, access_count) VALUES (?, ?, 1)
+            ON CONFLICT(inode) DO UPDATE SET
+                last_access = excluded.last_access,
+                access_count = access_count + 1
+            "#
+        )
+        .bind(inode as i64)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Archivos de los directorios más usados, candidatos a precargar en caché.
+    /// Los directorios se ordenan por frecuencia con decaimiento diario
+    /// (`access_count / (1 + días desde el último acceso)`), y dentro de cada uno
+    /// por fecha de modificación. Solo directorios accedidos desde `since` (epoch).
+    /// Excluye shortcuts, documentos de Google (sin contenido binario), dirty y eliminados.
+    pub async fn list_prefetch_candidates(&self, since: i64, max_dirs: u32) -> Result<Vec<PrefetchCandidate>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        let rows = sqlx::query_as::<_, (i64, String, i64, i64)>(
+            r#"
+            WITH hot AS (
+                SELECT inode, access_count * 1.0 / (1 + (? - last_access) / 86400.0) AS score
+                FROM dir_access_log
+                WHERE last_access >= ?
+                ORDER BY score DESC
+                LIMIT ?
+            )
+            SELECT d.child_inode, i.gdrive_id, a.size,
+                   COALESCE((SELECT SUM(c.end_offset - c.start_offset + 1)
+                             FROM file_cache_chunks c WHERE c.inode = d.child_inode), 0)
+            FROM hot h
+            JOIN dentry d ON d.parent_inode = h.inode
+            JOIN inodes i ON i.inode = d.child_inode
+            JOIN attrs a ON a.inode = d.child_inode
+            LEFT JOIN sync_state s ON s.inode = d.child_inode
+            WHERE a.is_dir = 0
+              AND a.size > 0
+              AND a.shortcut_target_id IS NULL
+              AND COALESCE(a.mime_type, '') NOT LIKE 'application/vnd.google-apps.%'
+              AND COALESCE(s.dirty, 0) = 0
+              AND s.deleted_at IS NULL
+            ORDER BY h.score DESC, a.mtime DESC
+            "#
+        )
+        .bind(now)
+        .bind(since)
+        .bind(max_dirs as i64)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|(inode, gdrive_id, size, cached_bytes)| PrefetchCandidate {
+                inode: inode as u64,
+                gdrive_id,
+                size: size.max(0) as u64,
+                cached_bytes: cached_bytes.max(0) as u64,
+            })
+            .collect())
+    }
+
+    /// Limpia todos los chunks cacheados de un inodo (usado en caso de corrupción detectada)
+    pub async fn clear_chunks(&self, inode: u64) -> Result<()> {
+        sqlx::query("DELETE FROM file_cache_chunks WHERE inode = ?")
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+
+        tracing::warn!("🧹 Chunks limpiados para inode: {}", inode);
+        Ok(())
+    }
+
+    /// Limpia TODOS los chunks de caché (todas las filas de file_cache_chunks).
+    /// Usado en recuperación post-crash para eliminar estado obsoleto.
+    pub async fn clear_all_chunks(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM file_cache_chunks")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Obtiene el offset máximo registrado en los chunks (para validar consistencia de tamaño)
+    pub async fn get_max_cached_offset(&self, inode: u64) -> Result<u64> {
+        let max_offset: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(end_offset) FROM file_cache_chunks WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        
+        Ok(max_offset.unwrap_or(0) as u64)
+    }
+
+    /// Obtiene el total de bytes cacheados sumando todos los chunks
+    pub async fn get_cached_bytes_count(&self, inode: u64) -> Result<u64> {
+        let total: Option<i64> = sqlx::query_scalar(
+            "SELECT SUM(end_offset - start_offset + 1) FROM file_cache_chunks WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
+        
+        Ok(total.unwrap_or(0) as u64)
+    }
+
+    /// Obtiene los rangos faltantes para un archivo en un intervalo dado
+    /// Retorna una lista de (start, end) que necesitan descargarse
+    pub async fn get_missing_ranges(&self, inode: u64, requested_start: u64, requested_end: u64) -> Result<Vec<(u64, u64)>> {
+        // Obtener todos los chunks cacheados para este inode que se solapan con el rango solicitado
+        let cached_chunks: Vec<(i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT start_offset, end_offset
+            FROM file_cache_chunks
+            WHERE inode = ?
+              AND end_offset >= ?
+              AND start_offset <= ?
+            ORDER BY start_offset
+            "#
+        )
+        .bind(inode as i64)
+        .bind(requested_start as i64)
+        .bind(requested_end as i64)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        // Si no hay chunks, el rango completo falta
+        if cached_chunks.is_empty() {
+            return Ok(vec![(requested_start, requested_end)]);
+        }
+
+        let mut missing = Vec::new();
+        let mut current_pos = requested_start;
+
+        for (start, end) in cached_chunks {
+            let start = start as u64;
+            let end = end as u64;
+
+            // Si hay un gap antes de este chunk
+            if current_pos < start {
+                missing.push((current_pos, start - 1));
+            }
+
+            // Avanzar más allá del chunk actual
+            current_pos = current_pos.max(end + 1);
+        }
+
+        // Si queda espacio después del último chunk
+        if current_pos <= requested_end {
+            missing.push((current_pos, requested_end));
+        }
+
+        Ok(missing)
+    }
+
+
+    /// Limpia todos los chunks cacheados para un inode (útil al invalidar caché)
+    pub async fn clear_cached_chunks(&self, inode: u64) -> Result<()> {
+        sqlx::query("DELETE FROM file_cache_chunks WHERE inode = ?")
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Métodos para Local Sync Directories
+    // ============================================================
+
+    /// Añade un directorio local a la lista de sincronización
+    pub async fn add_local_sync_dir(&self, local_path: &Path) -> Result<i64> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        let path_str = local_path.to_string_lossy().to_string();
+
+        let id = sqlx::query(
+            r#"
+            INSERT INTO local_sync_dirs (local_path, enabled, created_at)
+            VALUES (?, 1, ?)
+            "#
+        )
+        .bind(&path_str)
+        .bind(now)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        tracing::info!("Directorio local añadido: {} (id={})", path_str, id);
+        Ok(id)
+    }
+
+    /// Elimina un directorio local de la sincronización
+    pub async fn remove_local_sync_dir(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM local_sync_dirs WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        tracing::info!("Directorio local eliminado: id={}", id);
+        Ok(())
+    }
+
+    /// Activa/desactiva la sincronización de un directorio local
+    pub async fn toggle_local_sync_dir(&self, id: i64, enabled: bool) -> Result<()> {
+        sqlx::query("UPDATE local_sync_dirs SET enabled = ? WHERE id = ?")
+            .bind(enabled)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        tracing::debug!("Directorio local {} (id={})", if enabled { "activado" } else { "desactivado" }, id);
+        Ok(())
+    }
+
+    /// Obtiene todos los directorios locales configurados
+    pub async fn get_local_sync_dirs(&self) -> Result<Vec<LocalSyncDir>> {
+        let dirs = sqlx::query_as::<_, LocalSyncDir>(
+            "SELECT id, local_path, gdrive_folder_id, enabled, last_sync, created_at FROM local_sync_dirs ORDER BY created_at"
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(dirs)
+    }
+
+    /// Obtiene solo los directorios locales habilitados
+    pub async fn get_enabled_local_sync_dirs(&self) -> Result<Vec<LocalSyncDir>> {
+        let dirs = sqlx::query_as::<_, LocalSyncDir>(
+            "SELECT id, local_path, gdrive_folder_id, enabled, last_sync, created_at FROM local_sync_dirs WHERE enabled = 1 ORDER BY created_at"
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(dirs)
+    }
+
+    /// Establece el gdrive_folder_id para un directorio local
+    pub async fn set_gdrive_folder_id(&self, id: i64, gdrive_id: &str) -> Result<()> {
+        sqlx::query("UPDATE local_sync_dirs SET gdrive_folder_id = ? WHERE id = ?")
+            .bind(gdrive_id)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        tracing::debug!("GDrive folder ID {} asociado a local_sync_dir id={}", gdrive_id, id);
+        Ok(())
+    }
+
+    /// Actualiza el timestamp de última sincronización
+    pub async fn update_last_sync(&self, id: i64) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        sqlx::query("UPDATE local_sync_dirs SET last_sync = ? WHERE id = ?")
+            .bind(now)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // ============================================================
+    // Métodos para Local Sync Files (Hybrid Local Sync)
+    // ============================================================
+
+    /// Inserta o actualiza un archivo en local_sync_files
+    pub async fn upsert_local_sync_file(
+        &self,
+        sync_dir_id: i64,
+        relative_path: &str,
+        is_dir: bool,
+        availability: &str,
+        local_mtime: Option<i64>,
+        local_size: Option<i64>,
+        local_md5: Option<&str>,
+    ) -> Result<i64> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO local_sync_files 
+                (sync_dir_id, relative_path, is_dir, availability, local_mtime, local_size, local_md5, dirty)
+            VALUES (?, ?, ?, ?, ?, ?, ?, 1)
+            ON CONFLICT(sync_dir_id, relative_path) DO UPDATE SET
+                is_dir = excluded.is_dir,
+                availability = excluded.availability,
+                local_mtime = excluded.local_mtime,
+                local_size = excluded.local_size,
+                local_md5 = excluded.local_md5,
+                dirty = 1
+            "#
+        )
+        .bind(sync_dir_id)
+        .bind(relative_path)
+        .bind(is_dir)
+        .bind(availability)
+        .bind(local_mtime)
+        .bind(local_size)
+        .bind(local_md5)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        Ok(id)
+    }
+
+    /// Obtiene archivos dirty para subir
+    pub async fn get_dirty_local_sync_files(&self) -> Result<Vec<LocalSyncFile>> {
+        let files = sqlx::query_as::<_, LocalSyncFile>(
+            "SELECT * FROM local_sync_files WHERE dirty = 1 ORDER BY id"
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(files)
+    }
+
+    /// Obtiene un archivo local por sync_dir_id y relative_path
+    pub async fn get_local_sync_file(&self, sync_dir_id: i64, relative_path: &str) -> Result<Option<LocalSyncFile>> {
+        let file = sqlx::query_as::<_, LocalSyncFile>(
+            "SELECT * FROM local_sync_files WHERE sync_dir_id = ? AND relative_path = ?"
+        )
+        .bind(sync_dir_id)
+        .bind(relative_path)
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        Ok(file)
+    }
+
+    /// Busca un archivo local por gdrive_id
+    pub async fn find_local_sync_file_by_gdrive_id(&self, gdrive_id: &str) -> Result<Option<LocalSyncFile>> {
+        let file = sqlx::query_as::<_, LocalSyncFile>(
+            "SELECT * FROM local_sync_files WHERE gdrive_id = ?"
+        )
+        .bind(gdrive_id)
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        Ok(file)
+    }
+
+    /// Obtiene un directorio local por ID
+    pub async fn get_local_sync_dir(&self, id: i64) -> Result<LocalSyncDir> {
+        let dir = sqlx::query_as::<_, LocalSyncDir>(
+            "SELECT * FROM local_sync_dirs WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_one(&self.read_pool)
+        .await?;
+
+        Ok(dir)
+    }
+
+    /// Cambia el modo de disponibilidad de un archivo
+    pub async fn set_file_availability(&self, sync_dir_id: i64, relative_path: &str, availability: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE local_sync_files SET availability = ? WHERE sync_dir_id = ? AND relative_path = ?"
+        )
+        .bind(availability)
+        .bind(sync_dir_id)
+        .bind(relative_path)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Actualiza metadatos locales de un archivo
+    pub async fn update_local_file_metadata(
+        &self,
+        sync_dir_id: i64,
+        relative_path: &str,
+        availability: &str,
+        local_size: i64,
+        local_mtime: i64,
+        local_md5: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE local_sync_files 
+            SET availability = ?, local_size = ?, local_mtime = ?, local_md5 = ?
+            WHERE sync_dir_id = ? AND relative_path = ?
+            "#
+        )
+        .bind(availability)
+        .bind(local_size)
+        .bind(local_mtime)
+        .bind(local_md5)
+        .bind(sync_dir_id)
+        .bind(relative_path)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Actualiza metadatos remotos desde un cambio de Drive
+    pub async fn update_local_file_from_remote(
+        &self,
+        file_id: i64,
+        remote_md5: Option<&str>,
+        remote_mtime: Option<i64>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE local_sync_files SET remote_md5 = ?, remote_mtime = ?, dirty = 0 WHERE id = ?"
+        )
+        .bind(remote_md5)
+        .bind(remote_mtime)
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Actualiza solo metadatos remotos (para archivos online_only)
+    pub async fn update_local_file_remote_metadata(&self, file_id: i64, remote_md5: Option<&str>) -> Result<()> {
+        sqlx::query(
+            "UPDATE local_sync_files SET remote_md5 = ? WHERE id = ?"
+        )
+        .bind(remote_md5)
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Limpia el flag dirty de un archivo después de una subida exitosa
+    pub async fn clear_local_file_dirty(&self, file_id: i64) -> Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        sqlx::query(
+            "UPDATE local_sync_files SET dirty = 0, last_synced = ? WHERE id = ?"
+        )
+        .bind(now)
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Establece el gdrive_id para un archivo local después de crearlo en Drive
+    pub async fn set_local_file_gdrive_id(&self, file_id: i64, gdrive_id: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE local_sync_files SET gdrive_id = ? WHERE id = ?"
+        )
+        .bind(gdrive_id)
+        .bind(file_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Busca un archivo de local sync por su path absoluto del filesystem
+    /// Útil para comandos IPC que reciben file:// URIs de Nautilus
+    pub async fn find_local_sync_file_by_absolute_path(
+        &self,
+        absolute_path: &str,
+    ) -> Result<Option<LocalSyncFile>> {
+        // Obtener todas las carpetas locales habilitadas
+        let sync_dirs = self.get_enabled_local_sync_dirs().await?;
+        
+        // Intentar encontrar qué carpeta contiene este path
+        for dir in sync_dirs {
+            let base_path = &dir.local_path;
+            
+            // Si el absolute_path empieza con este base_path
+            if absolute_path.starts_with(base_path) {
+                // Calcular relative_path
+                let relative_path = absolute_path
+                    .strip_prefix(base_path)
+                    .unwrap_or("")
+                    .trim_start_matches('/');
+                
+                // Buscar el archivo en la DB
+                if let Some(file) = self.get_local_sync_file(dir.id, relative_path).await? {
+                    return Ok(Some(file));
+                }
+            }
+        }
+        
+        Ok(None)
+    }
+
+    /// Resuelve un path absoluto a (sync_dir_id, relative_path)
+    /// Lanza error si el path no pertenece a ninguna carpeta Local Sync
+    pub async fn resolve_local_sync_path(
+        &self,
+        absolute_path: &str,
+    ) -> Result<(i64, String)> {
+        let sync_dirs = self.get_enabled_local_sync_dirs().await?;
+        
+        for dir in sync_dirs {
+            let base_path = &dir.local_path;
+            
+            if absolute_path.starts_with(base_path) {
+                let relative_path = absolute_path
+                    .strip_prefix(base_path)
+                    .unwrap_or("")
+                    .trim_start_matches('/')
+                    .to_string();
+                
+                return Ok((dir.id, relative_path));
+            }
+        }
+        
+        Err(anyhow::anyhow!("Path no pertenece a ninguna carpeta Local Sync: {}", absolute_path))
+    }
+
+    pub async fn set_shortcut_target_id(&self, inode: u64, target_id: &str) -> Result<()> {
+        sqlx::query("UPDATE attrs SET shortcut_target_id = ? WHERE inode = ?")
+            .bind(target_id)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_bulk_shortcut_targets(&self, items: &[(u64, String)]) -> Result<()> {
+        if items.is_empty() { return Ok(()); }
+        let mut tx = self.pool.begin().await?;
+        for (inode, target_id) in items {
+            sqlx::query("UPDATE attrs SET shortcut_target_id = ? WHERE inode = ?")
+                .bind(target_id)
+                .bind(*inode as i64)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Guarda el `webViewLink` (URL en drive.google.com) de un inode
+    pub async fn set_web_view_link(&self, inode: u64, link: &str) -> Result<()> {
+        sqlx::query("UPDATE attrs SET web_view_link = ? WHERE inode = ?")
+            .bind(link)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_bulk_web_view_links(&self, items: &[(u64, String)]) -> Result<()> {
+        if items.is_empty() { return Ok(()); }
+        let mut tx = self.pool.begin().await?;
+        for (inode, link) in items {
+            sqlx::query("UPDATE attrs SET web_view_link = ? WHERE inode = ?")
+                .bind(link)
+                .bind(*inode as i64)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Guarda el `thumbnailLink` de Drive de un inode
+    pub async fn set_thumbnail_link(&self, inode: u64, link: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE attrs SET thumbnail_link = ? WHERE inode = ?")
+            .bind(link)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_bulk_thumbnail_links(&self, items: &[(u64, String)]) -> Result<()> {
+        if items.is_empty() { return Ok(()); }
+        let mut tx = self.pool.begin().await?;
+        for (inode, link) in items {
+            sqlx::query("UPDATE attrs SET thumbnail_link = ? WHERE inode = ?")
+                .bind(link)
+                .bind(*inode as i64)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Registra que la miniatura de un inode se escribió para su `mtime` actual
+    pub async fn set_thumbnail_mtime(&self, inode: u64, mtime: i64) -> Result<()> {
+        sqlx::query("UPDATE attrs SET thumbnail_mtime = ? WHERE inode = ?")
+            .bind(mtime)
+            .bind(inode as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Archivos con miniatura en Drive dentro de los directorios más usados
+    /// (mismo orden que `list_prefetch_candidates`). Incluye los que ya tienen
+    /// miniatura escrita: el llamador comprueba si sigue en su ruta actual.
+    /// Excluye directorios, dirty (la miniatura remota no refleja el contenido
+    /// local) y eliminados.
+    pub async fn list_thumbnail_candidates(&self, since: i64, max_dirs: u32) -> Result<Vec<ThumbnailCandidate>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+
+        let rows = sqlx::query_as::<_, (i64, String, String, i64, Option<i64>)>(
+            r#"
+            WITH hot AS (
+                SELECT inode, access_count * 1.0 / (1 + (? - last_access) / 86400.0) AS score
+                FROM dir_access_log
+                WHERE last_access >= ?
+                ORDER BY score DESC
+                LIMIT ?
+            )
+            SELECT d.child_inode, i.gdrive_id, a.thumbnail_link, a.mtime, a.thumbnail_mtime
+            FROM hot h
+            JOIN dentry d ON d.parent_inode = h.inode
+            JOIN inodes i ON i.inode = d.child_inode
+            JOIN attrs a ON a.inode = d.child_inode
+            LEFT JOIN sync_state s ON s.inode = d.child_inode
+            WHERE a.is_dir = 0
+              AND a.thumbnail_link IS NOT NULL
+              AND COALESCE(s.dirty, 0) = 0
+              AND s.deleted_at IS NULL
+            ORDER BY h.score DESC, a.mtime DESC
+            "#
+        )
+        .bind(now)
+        .bind(since)
+        .bind(max_dirs as i64)
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|(inode, gdrive_id, link, mtime, thumbnail_mtime)| ThumbnailCandidate {
+                inode: inode as u64,
+                gdrive_id,
+                link,
+                mtime,
+                thumbnail_mtime,
+            })
+            .collect())
+    }
+
+    /// Guarda permisos y propietarios de un inode
+    pub async fn upsert_capabilities(&self, inode: u64, caps: &FileCapabilities) -> Result<()> {
+        self.upsert_bulk_capabilities(&[(inode, caps.clone())]).await
+    }
+
+    /// Guarda permisos y propietarios de varios inodes en una transacción
+    pub async fn upsert_bulk_capabilities(&self, items: &[(u64, FileCapabilities)]) -> Result<()> {
+        if items.is_empty() { return Ok(()); }
+        let mut tx = self.pool.begin().await?;
+        for (inode, caps) in items {
+            sqlx::query(
+                r#"
+                INSERT INTO file_capabilities (inode, can_edit, can_trash, owners)
+                VALUES (?, ?, ?, ?)
+                ON CONFLICT(inode) DO UPDATE SET
+                    can_edit = excluded.can_edit,
+                    can_trash = excluded.can_trash,
+                    owners = excluded.owners
+                "#
+            )
+            .bind(*inode as i64)
+            .bind(caps.can_edit)
+            .bind(caps.can_trash)
+            .bind(serde_json::to_string(&caps.owners)?)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Permisos y propietarios de un inode (None si Drive aún no los informó,
+    /// p. ej. archivos creados localmente)
+    pub async fn get_capabilities(&self, inode: u64) -> Result<Option<FileCapabilities>> {
+        let row = sqlx::query_as::<_, (bool, bool, String)>(
+            "SELECT can_edit, can_trash, owners FROM file_capabilities WHERE inode = ?"
+        )
+        .bind(inode as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        Ok(row.map(|(can_edit, can_trash, owners)| FileCapabilities {
+            can_edit,
+            can_trash,
+            owners: serde_json::from_str(&owners).unwrap_or_default(),
+        }))
+    }
+
+    /// Indica si Drive permite mover el inode a la papelera (sin dato = sí)
+    pub async fn can_trash(&self, inode: u64) -> Result<bool> {
+        Ok(self.get_capabilities(inode).await?.is_none_or(|caps| caps.can_trash))
+    }
+
+    /// Registra una operación local en el diario del uploader. Las escrituras
+    /// solo se anotan si el inode no tiene ya un `create` o `write` pendiente
+    /// (`write` llega por bloques: basta con la primera); tras un renombrado
+    /// sí, para que `dirty_backlog_bytes` cuente el contenido nuevo.
+    pub async fn record_upload_op(&self, inode: u64, op: UploadOp) -> Result<()> {
+        let sql = if op == UploadOp::Write {
+            "INSERT INTO upload_ops (inode, op) SELECT ?1, ?2
+             WHERE NOT EXISTS (SELECT 1 FROM upload_ops WHERE inode = ?1 AND op IN ('create', 'write'))"
+        } else {
+            "INSERT INTO upload_ops (inode, op) VALUES (?1, ?2)"
+        };
+        sqlx::query(sql)
+            .bind(inode as i64)
+            .bind(op.as_str())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Operaciones pendientes por inode: última posición en el diario y si
+    /// incluye un renombrado o una eliminación
+    pub async fn pending_upload_ops(&self) -> Result<std::collections::HashMap<u64, PendingOps>> {
+        let rows = sqlx::query_as::<_, (i64, i64, bool)>(
+            "SELECT inode, MAX(seq), MAX(op IN ('rename', 'delete'))
+             FROM upload_ops
+             GROUP BY inode"
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(rows.into_iter()
+            .map(|(inode, last_seq, structural)| (inode as u64, PendingOps { last_seq, structural }))
+            .collect())
+    }
+
+    /// Da por subidas las operaciones de un inode hasta `up_to_seq` (las
+    /// registradas durante la subida se conservan para el próximo ciclo)
+    pub async fn consume_upload_ops(&self, inode: u64, up_to_seq: i64) -> Result<()> {
+        sqlx::query("DELETE FROM upload_ops WHERE inode = ? AND seq <= ?")
+            .bind(inode as i64)
+            .bind(up_to_seq)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Descarta operaciones de inodes que ya no están dirty (limpiados por
+    /// otra vía: archivos de control, contenido perdido, recuperación).
+    /// Retorna cuántas se borraron.
+    pub async fn prune_upload_ops(&self) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM upload_ops WHERE inode NOT IN (SELECT inode FROM sync_state WHERE dirty = 1)"
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Obtiene el `webViewLink` almacenado de un inode (None si aún no se conoce)
+    pub async fn get_web_view_link(&self, inode: u64) -> Result<Option<String>> {
+        let link: Option<Option<String>> = sqlx::query_scalar("SELECT web_view_link FROM attrs WHERE inode = ?")
+            .bind(inode as i64)
+            .fetch_optional(&self.read_pool)
+            .await?;
+        Ok(link.flatten())
+    }
+
+    pub async fn resolve_shortcut_sizes(&self) -> Result<usize> {
+        let result = sqlx::query(
+            r#"UPDATE attrs SET size = (
+                SELECT a2.size FROM inodes i2 JOIN attrs a2 ON i2.inode = a2.inode
+                WHERE i2.gdrive_id = attrs.shortcut_target_id AND a2.size > 0
+            )
+            WHERE shortcut_target_id IS NOT NULL AND size = 0
+            AND EXISTS (
+                SELECT 1 FROM inodes i2 JOIN attrs a2 ON i2.inode = a2.inode
+                WHERE i2.gdrive_id = attrs.shortcut_target_id AND a2.size > 0
+            )"#
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() as usize)
+    }
+}
+/// Hijo de un directorio con su fila de `attrs` (`list_children_extended_page`)
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ChildEntry {
+    pub name: String,
+    pub gdrive_id: String,
+    #[sqlx(flatten)]
+    pub attrs: crate::fuse::attr::FileAttributes,
+}
+
+/// Struct que representa un directorio local sincronizado
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LocalSyncDir {
+    pub id: i64,
+    pub local_path: String,
+    pub gdrive_folder_id: Option<String>,
+    pub enabled: bool,
+    pub last_sync: i64,
+    pub created_at: i64,
+}
+
+/// Struct que representa un archivo individual en Local Sync
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LocalSyncFile {
+    pub id: i64,
+    pub sync_dir_id: i64,
+    pub relative_path: String,
+    pub is_dir: bool,
+    
+    pub availability: String,  // 'local_online' | 'online_only'
+    
+    pub local_mtime: Option<i64>,
+    pub local_size: Option<i64>,
+    pub local_md5: Option<String>,
+    
+    pub gdrive_id: Option<String>,
+    pub remote_md5: Option<String>,
+    pub remote_mtime: Option<i64>,
+    
+    pub dirty: bool,
+    pub last_synced: Option<i64>,
+}
+
+/// Fila de `sync_state` de un inode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncStateRow {
+    pub dirty: bool,
+    pub deleted_at: Option<i64>,
+    pub uploading: bool,
+    pub status: UploadStatus,
+    /// Intentos de subida fallidos consecutivos
+    pub retry_count: u32,
+}
+
+impl SyncStateRow {
+    /// Tiene cambios locales pendientes (dirty o eliminación sin confirmar)
+    pub fn is_pending(&self) -> bool {
+        self.dirty || self.deleted_at.is_some_and(|v| v > 0)
+    }
+}
+
+/// Archivo cacheado según la DB, tal como lo revisa el verificador de integridad
+#[derive(Debug, Clone)]
+pub struct CachedFileSample {
+    pub inode: u64,
+    pub gdrive_id: String,
+    /// Tamaño remoto conocido (`attrs.size`)
+    pub size: u64,
+    pub remote_md5: Option<String>,
+    /// Último byte registrado en `file_cache_chunks` (inclusivo)
+    pub max_cached_end: u64,
+    /// Bytes cubiertos por los chunks
+    pub cached_bytes: u64,
+}
+
+/// Archivo con contenido en caché (`cache_entries`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub inode: u64,
+    pub gdrive_id: String,
+    /// Bytes cubiertos por `file_cache_chunks`
+    pub cached_bytes: u64,
+    /// Marcado "Local & Online" (`sync_state.availability`)
+    pub pinned: bool,
+    /// Sin cambios locales pendientes ni subida en curso: se puede descartar
+    pub evictable: bool,
+    /// Carpeta del root que lo contiene (`None` en el root o fuera del árbol)
+    pub top_folder: Option<String>,
+}
+
+/// Archivo de un directorio usado con frecuencia, candidato del prefetcher
+#[derive(Debug, Clone)]
+pub struct PrefetchCandidate {
+    pub inode: u64,
+    pub gdrive_id: String,
+    /// Tamaño remoto conocido (`attrs.size`)
+    pub size: u64,
+    /// Bytes ya cubiertos por `file_cache_chunks`
+    pub cached_bytes: u64,
+}
+
+/// Archivo con miniatura en Drive, candidato a la caché de miniaturas del escritorio
+#[derive(Debug, Clone)]
+pub struct ThumbnailCandidate {
+    pub inode: u64,
+    pub gdrive_id: String,
+    /// `thumbnailLink` guardado (puede haber caducado)
+    pub link: String,
+    /// mtime del archivo (el que ve `stat`)
+    pub mtime: i64,
+    /// mtime para el que se escribió la miniatura (None = nunca)
+    pub thumbnail_mtime: Option<i64>,
+}
+
+/// Archivo con la subida en error, tal como lo muestra la lista de problemas
+#[derive(Debug, Clone)]
+pub struct UploadProblem {
+    pub inode: u64,
+    /// Ruta relativa a la raíz del montaje (o solo el nombre si ya no está en el árbol)
+    pub path: String,
+    pub error: String,
+    pub retry_count: u32,
+    /// Próximo reintento automático (epoch en segundos)
+    pub next_retry_at: Option<i64>,
+    /// No se reintenta hasta que el archivo cambie o el usuario lo pida
+    pub permanent: bool,
+}
+
+/// Conflicto de sincronización pendiente (`conflicts`)
+#[derive(Debug, Clone)]
+pub struct SyncConflict {
+    pub id: i64,
+    /// Inode del archivo original
+    pub inode: u64,
+    /// Ruta relativa del original (o solo el nombre si ya no está en el árbol)
+    pub path: String,
+    pub original_gdrive_id: String,
+    /// Copia con la edición local
+    pub conflict_gdrive_id: String,
+    pub conflict_name: String,
+    /// Fecha de la edición local (epoch en segundos)
+    pub local_mtime: i64,
+    /// Fecha de la versión de Drive que causó el conflicto
+    pub remote_mtime: Option<i64>,
+    pub created_at: i64,
+}
+
+/// Estado de un inode en el ciclo del uploader (`sync_state.status`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UploadStatus {
+    /// Cambios locales esperando al próximo ciclo
+    Queued,
+    /// Subida en curso
+    Uploading,
+    /// Sin cambios pendientes
+    #[default]
+    Synced,
+    /// El último intento falló (mensaje en `sync_state.last_error`)
+    Error,
+    /// Coincide con `Config.ignore_patterns`: queda solo en local y nunca se sube
+    Ignored,
+}
+
+impl UploadStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadStatus::Queued => "queued",
+            UploadStatus::Uploading => "uploading",
+            UploadStatus::Synced => "synced",
+            UploadStatus::Error => "error",
+            UploadStatus::Ignored => "ignored",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "queued" => UploadStatus::Queued,
+            "uploading" => UploadStatus::Uploading,
+            "error" => UploadStatus::Error,
+            "ignored" => UploadStatus::Ignored,
+            _ => UploadStatus::Synced,
+        }
+    }
+}
+
+/// Struct para inserción masiva de metadatos
+#[derive(Debug, Clone)]
+pub struct BulkFileMetadata {
+    pub inode: u64,
+    pub size: i64,
+    pub mtime: i64,
+    pub mtime_nsec: u32,
+    pub mode: u32,
+    pub is_dir: bool,
+    pub mime_type: Option<String>,
+    pub can_move: bool,
+    pub shared: bool,
+    pub owned_by_me: bool,
+    pub starred: bool,
+    /// `createdTime` de Drive (epoch en segundos)
+    pub created_time: Option<i64>,
+    /// Instalación de G-DriveXP que lo creó (`app_properties::CLIENT_KEY`)
+    pub origin_client: Option<String>,
+    /// Algún dispositivo lo mantiene descargado (`app_properties::PIN_KEY`)
+    pub pin_hint: bool,
+    /// `description` de Drive
+    pub description: Option<String>,
+    /// `folderColorRgb` de Drive ("#rrggbb"), solo en carpetas
+    pub folder_color: Option<String>,
+}
+
+/// Permisos y propietarios de un archivo según Drive (`file_capabilities`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCapabilities {
+    /// `capabilities.canEdit`: sin él el archivo se expone de solo lectura
+    pub can_edit: bool,
+    /// `capabilities.canTrash`: sin él no se intenta enviar a la papelera
+    pub can_trash: bool,
+    /// Propietarios como "Nombre <correo>"
+    pub owners: Vec<String>,
+}
+
+impl Default for FileCapabilities {
+    fn default() -> Self {
+        Self { can_edit: true, can_trash: true, owners: Vec::new() }
+    }
+}
+
+/// Operación local anotada en el diario del uploader (`upload_ops`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadOp {
+    Create,
+    Write,
+    Rename,
+    Delete,
+}
+
+impl UploadOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadOp::Create => "create",
+            UploadOp::Write => "write",
+            UploadOp::Rename => "rename",
+            UploadOp::Delete => "delete",
+        }
+    }
+}
+
+/// Operaciones pendientes de un inode en `upload_ops`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingOps {
+    /// Posición de la última operación (orden de subida)
+    pub last_seq: i64,
+    /// Incluye un renombrado o una eliminación: se sube sin concurrencia
+    pub structural: bool,
+}
+
+/// Struct para inserción masiva de dentries
+#[derive(Debug, Clone)]
+pub struct BulkDentry {
+    pub parent_inode: u64,
+    pub child_inode: u64,
+    pub name: String,
+}
+
+/// Convierte texto libre del usuario en una consulta FTS5 segura: cada palabra
+/// se entrecomilla (escapando comillas) y se marca como prefijo.
+fn fts_prefix_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Marca como modificados ahora (mtime y ctime) los directorios `dirs`:
+/// crear, renombrar, mover o eliminar una entrada cambia su carpeta, y
+/// `make` o los exploradores que ordenan por fecha dependen de ello.
+/// La carga masiva (bootstrap, reconstrucción) no la usa: conserva la fecha de Drive.
+async fn touch_dirs(
+    conn: &mut sqlx::SqliteConnection,
+    dirs: impl IntoIterator<Item = u64>,
+) -> Result<()> {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+    for dir in dirs {
+        sqlx::query("UPDATE attrs SET mtime = ?, mtime_nsec = ?, ctime = ? WHERE inode = ? AND is_dir = 1")
+            .bind(now.as_secs() as i64)
+            .bind(now.subsec_nanos() as i64)
+            .bind(now.as_secs() as i64)
+            .bind(dir as i64)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Vincula un archivo remoto a su carpeta con un nombre local válido
+/// (`utils::names::local_name`) y resolviendo colisiones de nombre.
+/// Si `(parent, name)` ya pertenece a otro archivo, el recién llegado recibe el
+/// primer sufijo libre. Siempre que el nombre local difiera del de Drive, el
+/// original queda en `remote_name`. Un archivo que ya está vinculado con ese
+/// nombre remoto conserva su nombre local, de modo que los sufijos son estables
+/// entre sincronizaciones.
+/// Retorna el nombre local asignado.
+async fn link_remote_dentry(
+    conn: &mut sqlx::SqliteConnection,
+    parent_inode: u64,
+    child_inode: u64,
+    name: &str,
+) -> Result<String> {
+    let current = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM dentry WHERE child_inode = ? AND parent_inode = ? AND COALESCE(remote_name, name) = ?"
+    )
+    .bind(child_inode as i64)
+    .bind(parent_inode as i64)
+    .bind(name)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let reserved = |local_name: &str| parent_inode == 1 && crate::fuse::filesystem::is_reserved_root_name(local_name);
+    if let Some(local_name) = current
+        && !reserved(&local_name)
+    {
+        return Ok(local_name);
+    }
+
+    // Un archivo solo puede estar en un directorio a la vez
+    sqlx::query("DELETE FROM dentry WHERE child_inode = ?")
+        .bind(child_inode as i64)
+        .execute(&mut *conn)
+        .await?;
+
+    let is_dir = sqlx::query_scalar::<_, bool>("SELECT is_dir FROM attrs WHERE inode = ?")
+        .bind(child_inode as i64)
+        .fetch_optional(&mut *conn)
+        .await?
+        .unwrap_or(false);
+
+    let base_name = crate::utils::names::local_name(name);
+    let mut local_name = base_name.clone();
+    let mut n = 1;
+    loop {
+        let taken = sqlx::query_scalar::<_, i64>(
+            "SELECT 1 FROM dentry WHERE parent_inode = ? AND name = ?"
+        )
+        .bind(parent_inode as i64)
+        .bind(&local_name)
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_some();
+        if !taken && !reserved(&local_name) {
+            break;
+        }
+        n += 1;
+        local_name = crate::utils::names::disambiguated_name(&base_name, n, is_dir);
+    }
+
+    sqlx::query("INSERT INTO dentry (parent_inode, child_inode, name, remote_name) VALUES (?, ?, ?, ?)")
+        .bind(parent_inode as i64)
+        .bind(child_inode as i64)
+        .bind(&local_name)
+        .bind((local_name != name).then_some(name))
+        .execute(&mut *conn)
+        .await?;
+
+    if n > 1 {
+        tracing::info!("📛 Nombre duplicado en Drive: '{}' expuesto como '{}' (inode={})", name, local_name, child_inode);
+    }
+    Ok(local_name)
+}
+
+/// `MetadataRepository::bubble_state_change` sobre `conn`: puede formar parte de una transacción
+async fn bubble_state_change_in(
+    conn: &mut sqlx::SqliteConnection,
+    child_inode: u64,
+    delta_dirty: i32,
+    delta_synced: i32,
+) -> Result<()> {
+    if delta_dirty == 0 && delta_synced == 0 {
+        return Ok(());
+    }
+
+    sqlx::query(
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT parent_inode FROM dentry WHERE child_inode = ?1
+            UNION ALL
+            SELECT d.parent_inode FROM dentry d
+            JOIN ancestors a ON d.child_inode = a.parent_inode
+            WHERE a.parent_inode > 1
+        )
+        UPDATE dir_counters
+        SET dirty_desc_count = MAX(0, dirty_desc_count + ?2),
+            synced_desc_count = MAX(0, synced_desc_count + ?3)
+        WHERE inode IN (SELECT parent_inode FROM ancestors)
+        "#
+    )
+    .bind(child_inode as i64)
+    .bind(delta_dirty)
+    .bind(delta_synced)
+    .execute(&mut *conn)
+    .await?;
+
+    // También actualizar root (inode 1) si el archivo cuelga de él
+    sqlx::query(
+        r#"
+        UPDATE dir_counters
+        SET dirty_desc_count = MAX(0, dirty_desc_count + ?2),
+            synced_desc_count = MAX(0, synced_desc_count + ?3)
+        WHERE inode = 1 AND EXISTS (
+            WITH RECURSIVE ancestors AS (
+                SELECT parent_inode FROM dentry WHERE child_inode = ?1
+                UNION ALL
+                SELECT d.parent_inode FROM dentry d
+                JOIN ancestors a ON d.child_inode = a.parent_inode
+                WHERE a.parent_inode > 1
+            )
+            SELECT 1 FROM ancestors WHERE parent_inode = 1
+        )
+        "#
+    )
+    .bind(child_inode as i64)
+    .bind(delta_dirty)
+    .bind(delta_synced)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// `MetadataRepository::mark_dirty` sobre `conn`: puede formar parte de una transacción
+async fn mark_dirty_in(
+    conn: &mut sqlx::SqliteConnection,
+    inode: u64) -> Result<()> {
+    // Obtener estado previo y si es directorio
+    let prev = sqlx::query_as::<_, (Option<String>, Option<bool>, Option<i64>, String)>(
+        "SELECT s.availability, s.dirty, s.deleted_at, s.status FROM sync_state s WHERE s.inode = ?"
+    )
+    .bind(inode as i64)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    // Un archivo ignorado nunca queda pendiente de subir (ver `unignore`)
+    if prev.as_ref().is_some_and(|(_, _, _, status)| status == UploadStatus::Ignored.as_str()) {
+        return Ok(());
+    }
+
+    let was_dirty = prev.as_ref().map(|(_, d, del, _)| {
+        d.unwrap_or(false) || del.map(|v| v > 0).unwrap_or(false)
+    }).unwrap_or(false);
+
+    let was_synced = prev.as_ref().map(|(av, d, del, _)| {
+        let is_local_online = av.as_deref().unwrap_or("online_only") == "local_online";
+        let not_dirty = !d.unwrap_or(false);
+        let not_deleted = del.unwrap_or(0) == 0;
+        is_local_online && not_dirty && not_deleted
+    }).unwrap_or(false);
+
+    // Marcar como dirty
+    sqlx::query(
+        "INSERT INTO sync_state (inode, dirty, version, md5_checksum, status) VALUES (?, 1, 0, NULL, 'queued')
+         ON CONFLICT(inode) DO UPDATE SET dirty = 1,
+            status = CASE WHEN status IN ('synced', 'error') THEN 'queued' ELSE status END,
+            retry_count = 0, next_retry_at = NULL, error_permanent = 0"
+    )
+    .bind(inode as i64)
+    .execute(&mut *conn)
+    .await?;
+
+    // Solo burbujear para archivos
+    let is_dir: Option<bool> = sqlx::query_scalar(
+        "SELECT is_dir FROM attrs WHERE inode = ?"
+    )
+    .bind(inode as i64)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if is_dir == Some(false) {
+        // El archivo ahora es dirty seguro
+        if was_dirty {
+            // Ya era dirty, no hay cambio en contadores
+        } else if was_synced {
+            // Era synced, ahora es dirty
+            bubble_state_change_in(&mut *conn, inode, 1, -1).await?;
+        } else {
+            // Archivo no era dirty ni synced (ej: online_only), ahora es dirty
+            bubble_state_change_in(&mut *conn, inode, 1, 0).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// `MetadataRepository::soft_delete_by_gdrive_id` sobre `conn`: puede formar parte de una transacción
+async fn soft_delete_in(
+    conn: &mut sqlx::SqliteConnection,
+    gdrive_id: &str) -> Result<bool> {
+    let inode_by_gdrive_id = sqlx::query_scalar::<_, i64>("SELECT inode FROM inodes WHERE gdrive_id = ?")
+        .bind(gdrive_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(|i| i as u64);
+    let root_inode = match inode_by_gdrive_id {
+        Some(i) => i,
+        None => return Ok(false),
+    };
+    let parent = sqlx::query_scalar::<_, i64>("SELECT parent_inode FROM dentry WHERE child_inode = ? LIMIT 1")
+        .bind(root_inode as i64)
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(|p| p as u64);
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+
+    // 0. Burbujeo: contar archivos descendientes que estaban synced (no dirty)
+    // antes de marcarlos como dirty. Estos son los que cambian de estado.
+    let synced_becoming_dirty: i64 = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE subordinates AS (
+            SELECT child_inode FROM dentry WHERE child_inode = ?
+            UNION ALL
+            SELECT d.child_inode FROM dentry d
+            JOIN subordinates s ON d.parent_inode = s.child_inode
+        )
+        SELECT COUNT(*) FROM subordinates sub
+        JOIN attrs a ON sub.child_inode = a.inode
+        LEFT JOIN sync_state ss ON sub.child_inode = ss.inode
+        WHERE a.is_dir = 0
+          AND (COALESCE(ss.availability, 'online_only') = 'local_online')
+          AND (COALESCE(ss.dirty, 0) = 0)
+          AND (ss.deleted_at IS NULL OR ss.deleted_at = 0)
+        "#
+    )
+    .bind(root_inode as i64)
+    .fetch_one(&mut *conn)
+    .await?;
+
+
+
+    // Archivos nuevos sin sync_state
+    let new_files: i64 = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE subordinates AS (
+            SELECT child_inode FROM dentry WHERE child_inode = ?
+            UNION ALL
+            SELECT d.child_inode FROM dentry d
+            JOIN subordinates s ON d.parent_inode = s.child_inode
+        )
+        SELECT COUNT(*) FROM subordinates sub
+        JOIN attrs a ON sub.child_inode = a.inode
+        WHERE a.is_dir = 0
+          AND sub.child_inode NOT IN (SELECT inode FROM sync_state)
+        "#
+    )
+    .bind(root_inode as i64)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // 1. Identificar recursivamente todos los inodos hijos (incluyendo el raíz)
+    let sql_deleted_dentries = r#"
+        WITH RECURSIVE subordinates AS (
+            SELECT child_inode, parent_inode, name FROM dentry WHERE child_inode = ?
+            UNION ALL
+            SELECT d.child_inode, d.parent_inode, d.name
+            FROM dentry d
+            JOIN subordinates s ON d.parent_inode = s.child_inode
+        )
+        INSERT OR REPLACE INTO dentry_deleted (parent_inode, child_inode, name, deleted_at)
+        SELECT parent_inode, child_inode, name, ? FROM subordinates
+    "#;
+
+    sqlx::query(sql_deleted_dentries)
+        .bind(root_inode as i64)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+    // 2. Marcar sync_state para todos los inodos afectados
+    let sql_update_sync = r#"
+        WITH RECURSIVE subordinates AS (
+            SELECT child_inode FROM dentry WHERE child_inode = ?
+            UNION ALL
+            SELECT d.child_inode
+            FROM dentry d
+            JOIN subordinates s ON d.parent_inode = s.child_inode
+        )
+        UPDATE sync_state
+        SET deleted_at = ?, dirty = 1, status = 'queued',
+            retry_count = 0, next_retry_at = NULL, error_permanent = 0
+        WHERE inode IN (SELECT child_inode FROM subordinates)
+    "#;
+
+    sqlx::query(sql_update_sync)
+        .bind(root_inode as i64)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+    // Insertar los que falten
+    let sql_insert_sync = r#"
+        WITH RECURSIVE subordinates AS (
+            SELECT child_inode FROM dentry WHERE child_inode = ?
+            UNION ALL
+            SELECT d.child_inode
+            FROM dentry d
+            JOIN subordinates s ON d.parent_inode = s.child_inode
+        )
+        INSERT INTO sync_state (inode, dirty, version, deleted_at, status)
+        SELECT child_inode, 1, 0, ?, 'queued'
+        FROM subordinates
+        WHERE child_inode NOT IN (SELECT inode FROM sync_state)
+    "#;
+
+    sqlx::query(sql_insert_sync)
+        .bind(root_inode as i64)
+        .bind(now)
+        .execute(&mut *conn)
+        .await?;
+
+    // 2.5. Burbujear el cambio de estado ANTES de eliminar dentries
+    // (bubble_state_change necesita las dentries para caminar hacia los ancestros)
+    let delta_dirty = synced_becoming_dirty + new_files; // Nuevos dirty
+    let delta_synced = -synced_becoming_dirty; // Dejaron de ser synced
+    if delta_dirty != 0 || delta_synced != 0 {
+        bubble_state_change_in(&mut *conn, root_inode, delta_dirty as i32, delta_synced as i32).await?;
+    }
+
+    // 3. Limpiar dentry original para todos los inodos afectados
+    // IMPORTANTE: Esto debe ser lo ÚLTIMO porque las CTEs anteriores dependen de dentry.
+    let sql_cleanup_dentry = r#"
+        WITH RECURSIVE subordinates AS (
+            SELECT child_inode FROM dentry WHERE child_inode = ?
+            UNION ALL
+            SELECT d.child_inode
+            FROM dentry d
+            JOIN subordinates s ON d.parent_inode = s.child_inode
+        )
+        DELETE FROM dentry WHERE child_inode IN (SELECT child_inode FROM subordinates)
+    "#;
+
+    sqlx::query(sql_cleanup_dentry)
+        .bind(root_inode as i64)
+        .execute(&mut *conn)
+        .await?;
+    touch_dirs(conn, parent).await?;
+
+    tracing::info!("Recursive soft delete applied for gdrive_id={}, root_inode={}", gdrive_id, root_inode);
+    Ok(true)
+}
+
+/// `MetadataRepository::hard_delete_inode` sobre `conn`: puede formar parte de una transacción
+async fn hard_delete_in(
+    conn: &mut sqlx::SqliteConnection,
+    inode: u64) -> Result<()> {
+    let inode_i64 = inode as i64;
+
+    // Burbujear: decrementar contadores de ancestros según estado previo del archivo
+    // (solo para archivos, no directorios — los directorios eliminados ya tuvieron
+    // sus descendientes procesados en soft_delete)
+    let is_dir: Option<bool> = sqlx::query_scalar(
+        "SELECT is_dir FROM attrs WHERE inode = ?"
+    )
+    .bind(inode_i64)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if is_dir == Some(false) {
+        // Verificar estado actual del archivo para calcular delta
+        let state = sqlx::query_as::<_, (Option<bool>, Option<i64>)>(
+            "SELECT dirty, deleted_at FROM sync_state WHERE inode = ?"
+        )
+        .bind(inode_i64)
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if let Some((dirty, deleted_at)) = state {
+            let was_dirty = dirty.unwrap_or(false) || deleted_at.map(|v| v > 0).unwrap_or(false);
+            if was_dirty {
+                bubble_state_change_in(&mut *conn, inode, -1, 0).await?;
+            } else {
+                bubble_state_change_in(&mut *conn, inode, 0, -1).await?;
+            }
+        }
+    }
+
+    // Eliminar de todas las tablas relacionadas
+    sqlx::query("DELETE FROM dentry WHERE child_inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM dentry_deleted WHERE child_inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM sync_state WHERE inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM attrs WHERE inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM file_cache_chunks WHERE inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    // Limpiar dir_counters si era directorio
+    sqlx::query("DELETE FROM dir_counters WHERE inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM dir_access_log WHERE inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM file_capabilities WHERE inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM upload_ops WHERE inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query("DELETE FROM inodes WHERE inode = ?")
+        .bind(inode_i64)
+        .execute(&mut *conn)
+        .await?;
+
+    // Nueva generación: si el número de inode se reutiliza, el kernel lo distingue
+    sqlx::query(
+        r#"
+        INSERT INTO sync_meta (key, value, updated_at) VALUES (?, '1', strftime('%s', 'now'))
+        ON CONFLICT(key) DO UPDATE SET
+            value = CAST(value AS INTEGER) + 1,
+            updated_at = excluded.updated_at
+        "#
+    )
+    .bind(INODE_GENERATION_KEY)
+    .execute(&mut *conn)
+    .await?;
+
+    tracing::debug!("Hard delete completado para inode={}", inode);
+    Ok(())
+}
+
+/// `MetadataRepository::mark_created` sobre `conn`: puede formar parte de una transacción
+async fn mark_created_in(conn: &mut sqlx::SqliteConnection, inode: u64, gdrive_id: &str) -> Result<()> {
+    sqlx::query("UPDATE inodes SET gdrive_id = ?, pending_create = 0 WHERE inode = ?")
+        .bind(gdrive_id)
+        .bind(inode as i64)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Fusiona rangos inclusivos solapados o contiguos (entrada en cualquier orden)
+fn merge_ranges(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_fixtures;
+    use rstest::rstest;
+
+    /// Esquema tal como lo creaban las versiones anteriores al migrador
+    const LEGACY_SCHEMA: &str = indoc::indoc! {"
+        CREATE TABLE inodes (
+            inode INTEGER PRIMARY KEY AUTOINCREMENT,
+            gdrive_id TEXT UNIQUE NOT NULL,
+            generation INTEGER DEFAULT 0,
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE dentry (
+            parent_inode INTEGER NOT NULL,
+            child_inode INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            PRIMARY KEY (parent_inode, name)
+        );
+        CREATE TABLE attrs (
+            inode INTEGER PRIMARY KEY,
+            size INTEGER NOT NULL,
+            mtime INTEGER NOT NULL,
+            ctime INTEGER NOT NULL,
+            mode INTEGER NOT NULL,
+            is_dir BOOLEAN NOT NULL,
+            mime_type TEXT
+        );
+        CREATE TABLE sync_state (
+            inode INTEGER PRIMARY KEY,
+            dirty BOOLEAN DEFAULT 0,
+            version INTEGER NOT NULL,
+            md5_checksum TEXT
+        );
+        CREATE TABLE sync_meta (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+        CREATE TABLE dentry_deleted (
+            parent_inode INTEGER NOT NULL,
+            child_inode INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            deleted_at INTEGER NOT NULL,
+            PRIMARY KEY (parent_inode, name)
+        );
+        CREATE INDEX idx_tombstone_deleted_at ON dentry_deleted(deleted_at);
+        INSERT INTO inodes (inode, gdrive_id, created_at) VALUES (1, 'root', 0), (2, 'folder_a', 0);
+        INSERT INTO dentry VALUES (1, 2, 'Carpeta');
+        INSERT INTO attrs VALUES (1, 0, 0, 0, 493, 1, NULL), (2, 0, 0, 0, 493, 1, NULL);
+        INSERT INTO sync_state (inode, version) VALUES (2, 1);
+        INSERT INTO dentry_deleted VALUES (1, 3, 'viejo.txt', 10), (2, 3, 'viejo.txt', 20);
+    "};
+
+    async fn open_legacy_db(dir: &tempfile::TempDir) -> MetadataRepository {
+        let db_path = dir.path().join("metadata.db");
+        let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", db_path.display()))
+            .unwrap()
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().connect_with(options).await.unwrap();
+        sqlx::raw_sql(LEGACY_SCHEMA).execute(&pool).await.unwrap();
+        pool.close().await;
+
+        MetadataRepository::new(&db_path).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_new_database_applies_all_migrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap();
+
+        let latest = MIGRATOR.iter().map(|m| m.version).max().unwrap();
+        assert_eq!(repo.schema_version().await.unwrap(), latest);
+        assert!(repo.is_empty().await.unwrap());
+    }
+
+    /// Crea un repositorio vacío con un archivo `doc.txt` (inode 2) bajo root
+    async fn repo_with_file(dir: &tempfile::TempDir) -> MetadataRepository {
+        let repo = test_fixtures::repo(dir).await;
+        let inode = repo.get_or_create_inode("file_doc").await.unwrap();
+        repo.upsert_file_metadata(inode, 10, 100, 0o644, false, Some("text/plain"), true, false, true)
+            .await
+            .unwrap();
+        repo.upsert_dentry(1, inode, "doc.txt").await.unwrap();
+        repo
+    }
+
+    #[tokio::test]
+    async fn test_mark_and_clear_dirty() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let inode = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+
+        repo.mark_dirty(inode).await.unwrap();
+        let state = repo.sync_state(inode).await.unwrap().unwrap();
+        assert!(state.dirty && state.is_pending());
+        assert_eq!(repo.list_dirty_inodes().await.unwrap(), vec![(inode, "file_doc".to_string(), false)]);
+
+        repo.clear_dirty(inode).await.unwrap();
+        let state = repo.sync_state(inode).await.unwrap().unwrap();
+        assert!(!state.is_pending());
+        assert!(repo.list_dirty_inodes().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_starred_listing_and_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let inode = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+        assert!(repo.list_starred().await.unwrap().is_empty());
+
+        repo.set_starred(inode, true).await.unwrap();
+        assert!(repo.is_starred(inode).await.unwrap());
+        let starred = repo.list_starred().await.unwrap();
+        assert_eq!(starred.len(), 1);
+        assert_eq!((starred[0].0, starred[0].1.as_str()), (inode, "doc.txt"));
+        assert_eq!(repo.lookup_starred("doc.txt").await.unwrap(), Some(inode));
+
+        repo.set_starred(inode, false).await.unwrap();
+        assert_eq!(repo.lookup_starred("doc.txt").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_roundtrip_and_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let inode = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+        assert_eq!(repo.get_capabilities(inode).await.unwrap(), None);
+        assert!(repo.can_trash(inode).await.unwrap(), "sin dato se permite");
+
+        let caps = FileCapabilities {
+            can_edit: false,
+            can_trash: false,
+            owners: vec!["Ana <ana@example.com>".to_string()],
+        };
+        repo.upsert_capabilities(inode, &caps).await.unwrap();
+
+        assert_eq!(repo.get_capabilities(inode).await.unwrap(), Some(caps));
+        assert!(!repo.can_trash(inode).await.unwrap());
+    }
+
+    #[rstest]
+    #[case::local_create("create", (true, false))]
+    #[case::local_rename("rename", (true, false))]
+    #[case::local_move("move", (true, true))]
+    #[case::soft_delete("soft_delete", (true, false))]
+    #[case::remote_move("remote_move", (true, true))]
+    #[case::remote_unchanged("remote_unchanged", (false, false))]
+    #[case::remote_removed("hard_delete", (true, false))]
+    #[tokio::test]
+    async fn test_namespace_changes_touch_parent_dirs(#[case] op: &str, #[case] touched: (bool, bool)) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        let mut folders = Vec::new();
+        for name in ["A", "B"] {
+            let folder = repo.get_or_create_inode(&format!("folder-{}", name)).await.unwrap();
+            repo.upsert_file_metadata(folder, 0, 100, 0o755, true, None, true, false, true).await.unwrap();
+            repo.upsert_remote_dentry(1, folder, name).await.unwrap();
+            folders.push(folder);
+        }
+        let (a, b) = (folders[0], folders[1]);
+        let doc = repo.get_or_create_inode("file-doc").await.unwrap();
+        repo.upsert_file_metadata(doc, 10, 100, 0o644, false, None, true, false, true).await.unwrap();
+        repo.upsert_remote_dentry(a, doc, "doc.txt").await.unwrap();
+        // El alta de las entradas ya tocó las carpetas: partir de una fecha conocida
+        sqlx::query("UPDATE attrs SET mtime = 100, mtime_nsec = 0, ctime = 100 WHERE inode IN (?, ?)")
+            .bind(a as i64)
+            .bind(b as i64)
+            .execute(repo.pool())
+            .await
+            .unwrap();
+
+        match op {
+            "create" => {
+                let new = repo.create_local_inode("temp_nuevo").await.unwrap();
+                repo.upsert_dentry(a, new, "nuevo.txt").await.unwrap();
+            }
+            "rename" => repo.rename_dentry(doc, "otro.txt").await.unwrap(),
+            "move" => repo.move_dentry(doc, b, "doc.txt").await.unwrap(),
+            "soft_delete" => assert!(repo.soft_delete_by_gdrive_id("file-doc").await.unwrap()),
+            "remote_move" => { repo.upsert_remote_dentry(b, doc, "doc.txt").await.unwrap(); }
+            "remote_unchanged" => { repo.upsert_remote_dentry(a, doc, "doc.txt").await.unwrap(); }
+            "hard_delete" => { repo.hard_delete_subtree_by_gdrive_id("file-doc").await.unwrap(); }
+            _ => unreachable!(),
+        }
+
+        let a_attrs = repo.get_attrs(a).await.unwrap();
+        let b_attrs = repo.get_attrs(b).await.unwrap();
+        assert_eq!((a_attrs.mtime > 100, b_attrs.mtime > 100), touched);
+        assert_eq!(a_attrs.ctime > 100, touched.0, "ctime acompaña al mtime");
+    }
+
+    #[rstest]
+    #[case::no_choice(&[], false)]
+    #[case::kept_parent(&[("A", "local_online")], true)]
+    #[case::freed_inside_kept(&[("A", "local_online"), ("B", "online_only")], false)]
+    #[case::kept_inside_freed(&[("A", "online_only"), ("B", "local_online")], true)]
+    #[tokio::test]
+    async fn test_folder_keeps_content_nearest_choice(#[case] choices: &[(&str, &str)], #[case] keeps: bool) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        let mut parent = 1;
+        let mut folders = std::collections::HashMap::new();
+        for name in ["A", "B"] {
+            let folder = repo.get_or_create_inode(&format!("folder-{}", name)).await.unwrap();
+            repo.upsert_file_metadata(folder, 0, 100, 0o755, true, None, true, false, true).await.unwrap();
+            repo.upsert_remote_dentry(parent, folder, name).await.unwrap();
+            folders.insert(name, folder);
+            parent = folder;
+        }
+        for (name, availability) in choices {
+            repo.set_folder_availability(folders[name], availability).await.unwrap();
+        }
+
+        assert_eq!(repo.folder_keeps_content(folders["B"]).await.unwrap(), keeps);
+        let subtree = repo.subtree_inodes(folders["A"]).await.unwrap();
+        assert_eq!(subtree.len(), 2, "A y su subcarpeta B: {:?}", subtree);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_dentries_keep_drive_folder_mtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        let folder = repo.get_or_create_inode("folder-A").await.unwrap();
+        let doc = repo.get_or_create_inode("file-doc").await.unwrap();
+        repo.upsert_file_metadata(folder, 0, 100, 0o755, true, None, true, false, true).await.unwrap();
+
+        repo.upsert_bulk_dentries(&[
+            BulkDentry { parent_inode: 1, child_inode: folder, name: "A".to_string() },
+            BulkDentry { parent_inode: folder, child_inode: doc, name: "doc.txt".to_string() },
+        ]).await.unwrap();
+
+        assert_eq!(repo.get_attrs(folder).await.unwrap().mtime, 100, "el bootstrap conserva la fecha de Drive");
+    }
+
+    #[tokio::test]
+    async fn test_pending_create_until_marked_created() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let folder = repo.create_local_inode("id-reservado").await.unwrap();
+        repo.upsert_file_metadata(folder, 0, 100, 0o755, true, None, true, false, true).await.unwrap();
+        repo.upsert_dentry(1, folder, "Nueva").await.unwrap();
+        let child = repo.create_local_inode("temp_hijo").await.unwrap();
+        repo.upsert_dentry(folder, child, "hijo.txt").await.unwrap();
+
+        assert!(repo.is_pending_create(folder).await.unwrap());
+        assert!(repo.parent_pending_create(child).await.unwrap());
+        let existing = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+        assert!(!repo.is_pending_create(existing).await.unwrap());
+
+        repo.mark_created(folder, "id-reservado").await.unwrap();
+        repo.mark_created(child, "id-drive").await.unwrap();
+
+        assert!(!repo.parent_pending_create(child).await.unwrap());
+        assert!(!repo.is_pending_create(child).await.unwrap());
+        assert_eq!(repo.get_gdrive_id(child).await.unwrap().as_deref(), Some("id-drive"));
+    }
+
+    #[tokio::test]
+    async fn test_upload_ops_order_and_consume() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let existing = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+        let created = repo.create_local_inode("temp_ops").await.unwrap();
+        repo.mark_dirty(created).await.unwrap();
+        repo.mark_dirty(existing).await.unwrap();
+
+        repo.record_upload_op(created, UploadOp::Create).await.unwrap();
+        repo.record_upload_op(created, UploadOp::Write).await.unwrap();
+        repo.record_upload_op(existing, UploadOp::Write).await.unwrap();
+        repo.record_upload_op(existing, UploadOp::Write).await.unwrap();
+        repo.record_upload_op(created, UploadOp::Rename).await.unwrap();
+
+        let pending = repo.pending_upload_ops().await.unwrap();
+        assert!(pending[&created].structural);
+        assert!(!pending[&existing].structural);
+        assert!(pending[&existing].last_seq < pending[&created].last_seq, "una escritura por inode basta");
+
+        repo.consume_upload_ops(created, pending[&created].last_seq - 1).await.unwrap();
+        assert_eq!(repo.pending_upload_ops().await.unwrap()[&created], pending[&created], "la última sigue pendiente");
+
+        repo.clear_dirty(existing).await.unwrap();
+        assert_eq!(repo.prune_upload_ops().await.unwrap(), 1);
+        assert!(!repo.pending_upload_ops().await.unwrap().contains_key(&existing));
+    }
+
+    #[tokio::test]
+    async fn test_dirty_backlog_counts_content_changes_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let inode = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+        repo.mark_dirty(inode).await.unwrap();
+        repo.record_upload_op(inode, UploadOp::Rename).await.unwrap();
+        assert_eq!(repo.dirty_backlog_bytes().await.unwrap(), 0, "un renombrado no ocupa caché nueva");
+
+        repo.record_upload_op(inode, UploadOp::Write).await.unwrap();
+        repo.set_size(inode, 4096).await.unwrap();
+        assert_eq!(repo.dirty_backlog_bytes().await.unwrap(), 4096);
+
+        repo.clear_dirty(inode).await.unwrap();
+        assert_eq!(repo.dirty_backlog_bytes().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_conflicts_listed_until_resolved() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let inode = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+
+        let first = repo.record_conflict(inode, "file_doc", "copia-1", "doc (Conflicto local 1).txt", 100, Some(200)).await.unwrap();
+        let second = repo.record_conflict(inode, "file_doc", "copia-2", "doc (Conflicto local 2).txt", 300, None).await.unwrap();
+
+        let conflicts = repo.list_conflicts().await.unwrap();
+        assert_eq!(conflicts.iter().map(|c| c.id).collect::<Vec<_>>(), vec![second, first]);
+        assert_eq!(conflicts[1].path, "doc.txt");
+        assert_eq!(conflicts[1].remote_mtime, Some(200));
+
+        repo.resolve_conflict(second, "dismissed").await.unwrap();
+        let pending = repo.list_conflicts().await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].conflict_gdrive_id, "copia-1");
+        assert!(repo.get_conflict(second).await.unwrap().is_some(), "el resuelto se conserva");
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_move_dentry() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let inode = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+
+        repo.rename_dentry(inode, "nuevo.txt").await.unwrap();
+        assert_eq!(repo.get_file_name(inode).await.unwrap().as_deref(), Some("nuevo.txt"));
+        assert_eq!(repo.get_parent_inode(inode).await.unwrap(), Some(1));
+
+        let folder = repo.get_or_create_inode("folder").await.unwrap();
+        repo.move_dentry(inode, folder, "movido.txt").await.unwrap();
+        assert_eq!(repo.lookup(folder, "movido.txt").await.unwrap(), Some(inode));
+        assert_eq!(repo.lookup(1, "nuevo.txt").await.unwrap(), None);
+
+        repo.remove_dentry(folder, "movido.txt").await.unwrap();
+        assert_eq!(repo.get_file_name(inode).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_gdrive_id_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let inode = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+
+        repo.set_gdrive_id(inode, "real_id").await.unwrap();
+        assert_eq!(repo.get_gdrive_id(inode).await.unwrap().as_deref(), Some("real_id"));
+        assert_eq!(repo.get_gdrive_id(9999).await.unwrap(), None);
+        assert_eq!(repo.sync_state(9999).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_read_pool_sees_committed_writes_and_cannot_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+
+        let inode = repo.lookup(1, "doc.txt").await.unwrap().expect("escrito por el pool de escritura");
+        repo.set_description(inode, Some("nueva")).await.unwrap();
+        assert_eq!(repo.get_description(inode).await.unwrap().as_deref(), Some("nueva"));
+        assert!(sqlx::query("DELETE FROM dentry").execute(&repo.read_pool).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reopen_is_idempotent() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("metadata.db");
+        let first = MetadataRepository::new(&db_path).await.unwrap();
+        let version = first.schema_version().await.unwrap();
+        first.close().await;
+
+        let second = MetadataRepository::new(&db_path).await.unwrap();
+        assert_eq!(second.schema_version().await.unwrap(), version);
+    }
+
+    #[rstest]
+    #[case::deleted_at("sync_state", "deleted_at")]
+    #[case::remote_md5("sync_state", "remote_md5")]
+    #[case::availability("sync_state", "availability")]
+    #[case::uploading("sync_state", "uploading")]
+    #[case::can_move("attrs", "can_move")]
+    #[case::shared("attrs", "shared")]
+    #[case::owned_by_me("attrs", "owned_by_me")]
+    #[case::shortcut_target_id("attrs", "shortcut_target_id")]
+    #[case::web_view_link("attrs", "web_view_link")]
+    #[tokio::test]
+    async fn test_legacy_database_gains_columns(#[case] table: &str, #[case] column: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = open_legacy_db(&dir).await;
+
+        assert!(repo.has_column(table, column).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_database_preserves_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = open_legacy_db(&dir).await;
+
+        let latest = MIGRATOR.iter().map(|m| m.version).max().unwrap();
+        assert_eq!(repo.schema_version().await.unwrap(), latest);
+        assert_eq!(repo.lookup(1, "Carpeta").await.unwrap(), Some(2));
+        // Las entradas previas a la migración FTS quedan indexadas
+        assert_eq!(repo.search_names("carpeta", 10).await.unwrap().len(), 1);
+        assert_eq!(repo.get_availability(2).await.unwrap(), "online_only");
+        assert!(repo.has_table("file_cache_chunks").await.unwrap());
+        assert!(repo.has_table("local_sync_files").await.unwrap());
+
+        // PK de dentry_deleted corregida: un solo tombstone por child_inode
+        let tombstones: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM dentry_deleted WHERE child_inode = 3")
+            .fetch_one(repo.pool())
+            .await
+            .unwrap();
+        assert_eq!(tombstones, 1);
+
+        // dir_counters recalculados para los directorios existentes
+        let counters: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM dir_counters")
+            .fetch_one(repo.pool())
+            .await
+            .unwrap();
+        assert!(counters > 0);
+    }
+
+    #[rstest]
+    #[case::adjacent(vec![(0, 99), (100, 199)], vec![(0, 199)])]
+    #[case::overlapping(vec![(0, 150), (100, 199)], vec![(0, 199)])]
+    #[case::contained(vec![(0, 500), (100, 199)], vec![(0, 500)])]
+    #[case::gap(vec![(0, 99), (101, 199)], vec![(0, 99), (101, 199)])]
+    #[case::unsorted(vec![(200, 299), (0, 99), (100, 199)], vec![(0, 299)])]
+    fn test_merge_ranges(#[case] input: Vec<(u64, u64)>, #[case] expected: Vec<(u64, u64)>) {
+        assert_eq!(merge_ranges(input), expected);
+    }
+
+    #[tokio::test]
+    async fn test_add_cached_chunk_coalesces_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+
+        repo.add_cached_chunk(2, 0, 131_071).await.unwrap();
+        repo.add_cached_chunk(2, 262_144, 393_215).await.unwrap();
+        repo.add_cached_chunk(2, 131_072, 262_143).await.unwrap();
+
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM file_cache_chunks WHERE inode = 2")
+            .fetch_one(repo.pool())
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+        assert_eq!(repo.get_cached_bytes_count(2).await.unwrap(), 393_216);
+        assert!(repo.get_missing_ranges(2, 0, 393_215).await.unwrap().is_empty());
+    }
+
+    #[rstest]
+    #[case::inside_chunk(150, vec![(0, 99), (120, 149)])]
+    #[case::between_chunks(110, vec![(0, 99)])]
+    #[case::at_chunk_start(120, vec![(0, 99)])]
+    #[case::empty(0, vec![])]
+    #[case::grows(300, vec![(0, 99), (120, 199)])]
+    #[tokio::test]
+    async fn test_shrinking_size_trims_cached_chunks(#[case] size: i64, #[case] expected: Vec<(u64, u64)>) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        repo.set_size(2, 200).await.unwrap();
+        repo.add_cached_chunk(2, 0, 99).await.unwrap();
+        repo.add_cached_chunk(2, 120, 199).await.unwrap();
+
+        repo.set_size(2, size).await.unwrap();
+
+        let rows: Vec<(i64, i64)> = sqlx::query_as("SELECT start_offset, end_offset FROM file_cache_chunks WHERE inode = 2 ORDER BY start_offset")
+            .fetch_all(repo.pool())
+            .await
+            .unwrap();
+        let rows: Vec<(u64, u64)> = rows.into_iter().map(|(s, e)| (s as u64, e as u64)).collect();
+        assert_eq!(rows, expected);
+    }
+
+    #[tokio::test]
+    async fn test_remote_scan_with_smaller_size_trims_cached_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        repo.add_cached_chunk(2, 0, 9).await.unwrap();
+
+        repo.upsert_bulk_file_metadata(&[BulkFileMetadata {
+            inode: 2,
+            size: 4,
+            mtime: 200,
+            mtime_nsec: 0,
+            mode: 0o644,
+            is_dir: false,
+            mime_type: Some("text/plain".into()),
+            can_move: true,
+            shared: false,
+            owned_by_me: true,
+            starred: false,
+            created_time: None,
+            origin_client: None,
+            pin_hint: false,
+            description: None,
+            folder_color: None,
+        }]).await.unwrap();
+
+        assert_eq!(repo.get_cached_bytes_count(2).await.unwrap(), 4);
+        assert!(repo.get_missing_ranges(2, 0, 3).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_description_survives_until_cleared() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        assert_eq!(repo.get_description(2).await.unwrap(), None);
+
+        repo.set_description(2, Some("Borrador del informe")).await.unwrap();
+        assert_eq!(repo.get_description(2).await.unwrap().as_deref(), Some("Borrador del informe"));
+        repo.set_description(2, None).await.unwrap();
+        assert_eq!(repo.get_description(2).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_app_marks_and_pending_pin_hints() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let doc = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+        assert!(repo.pending_pin_hints().await.unwrap().is_empty());
+
+        let marks = AppMarks { origin_client: Some("otra-instalacion".into()), pinned: true };
+        repo.set_app_marks(doc, &marks).await.unwrap();
+        assert_eq!(repo.get_origin_client(doc).await.unwrap().as_deref(), Some("otra-instalacion"));
+        assert_eq!(repo.pending_pin_hints().await.unwrap(), vec![(doc, false)]);
+
+        repo.set_availability(doc, "local_online", false).await.unwrap();
+        assert!(repo.pending_pin_hints().await.unwrap().is_empty(), "ya se mantiene aquí");
+        repo.set_pin_hint(doc, false).await.unwrap();
+        repo.set_availability(doc, "online_only", false).await.unwrap();
+        assert!(repo.pending_pin_hints().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unpublished_pin_hint_survives_remote_marks() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let doc = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+        let gdrive_id = repo.get_gdrive_id(doc).await.unwrap().unwrap();
+        let remote = AppMarks { origin_client: None, pinned: true };
+        repo.set_app_marks(doc, &remote).await.unwrap();
+
+        repo.set_pin_hint(doc, false).await.unwrap();
+        repo.set_app_marks(doc, &remote).await.unwrap();
+        assert!(repo.pending_pin_hints().await.unwrap().is_empty(), "la marca remota aún es la vieja");
+        assert_eq!(repo.unpublished_pin_hints().await.unwrap(), vec![(doc, gdrive_id, false)]);
+
+        repo.pin_hint_published(doc, true).await.unwrap();
+        assert_eq!(repo.unpublished_pin_hints().await.unwrap().len(), 1, "se publicó otro valor");
+        repo.pin_hint_published(doc, false).await.unwrap();
+        assert!(repo.unpublished_pin_hints().await.unwrap().is_empty());
+        repo.set_app_marks(doc, &remote).await.unwrap();
+        assert_eq!(repo.pending_pin_hints().await.unwrap(), vec![(doc, false)]);
+    }
+
+    #[tokio::test]
+    async fn test_client_id_is_generated_once() {
+        let repo = MetadataRepository::in_memory().await.unwrap();
+        let id = repo.client_id().await.unwrap();
+        assert!(!id.is_empty());
+        assert_eq!(repo.client_id().await.unwrap(), id);
+    }
+
+    /// Carpeta/ (f_carpeta) con a.txt y b.txt, y suelto.txt en la raíz
+    async fn repo_with_folder(dir: &tempfile::TempDir) -> MetadataRepository {
+        let repo = test_fixtures::repo(dir).await;
+        for (gdrive_id, name, is_dir, parent) in [
+            ("f_carpeta", "Carpeta", true, 1),
+            ("f_a", "a.txt", false, 2),
+            ("f_b", "b.txt", false, 2),
+            ("f_suelto", "suelto.txt", false, 1),
+        ] {
+            let inode = repo.get_or_create_inode(gdrive_id).await.unwrap();
+            repo.upsert_file_metadata(inode, 4, 0, 0o644, is_dir, None, true, false, true).await.unwrap();
+            repo.upsert_dentry(parent, inode, name).await.unwrap();
+        }
+        repo
+    }
+
+    #[rstest]
+    #[case::first_page(0, 2, &["a.txt", "b.txt"])]
+    #[case::last_page(1, 5, &["b.txt"])]
+    #[case::past_the_end(2, 2, &[])]
+    #[tokio::test]
+    async fn test_list_children_page(#[case] offset: u64, #[case] limit: u64, #[case] expected: &[&str]) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_folder(&dir).await;
+
+        let names: Vec<String> = repo.list_children_page(2, offset, limit).await.unwrap()
+            .into_iter().map(|(_, name, _)| name).collect();
+        assert_eq!(names, expected);
+        let extended: Vec<String> = repo.list_children_extended_page(2, offset, limit).await.unwrap()
+            .into_iter().map(|child| child.name).collect();
+        assert_eq!(extended, expected);
+    }
+
+    #[tokio::test]
+    async fn test_list_children_extended_page_carries_full_attrs() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_folder(&dir).await;
+        repo.set_created_time(3, 1_700_000_000).await.unwrap();
+
+        let children = repo.list_children_extended_page(2, 0, 10).await.unwrap();
+
+        assert_eq!(children.len(), 2);
+        for child in &children {
+            let attrs = repo.get_attrs(child.attrs.inode as u64).await.unwrap();
+            assert_eq!((child.attrs.size, child.attrs.mode, child.attrs.generation), (attrs.size, attrs.mode, attrs.generation));
+            assert_eq!(child.attrs.created_time, attrs.created_time);
+        }
+        assert_eq!(children[0].gdrive_id, "f_a");
+        assert_eq!(children[0].attrs.created_time, Some(1_700_000_000));
+    }
+
+    #[tokio::test]
+    async fn test_list_children_page_skips_shared_items_in_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_folder(&dir).await;
+        let shared = repo.get_or_create_inode("f_ajeno").await.unwrap();
+        repo.upsert_file_metadata(shared, 4, 0, 0o644, false, None, true, true, false).await.unwrap();
+        repo.upsert_dentry(1, shared, "Ajeno.txt").await.unwrap();
+
+        let names: Vec<String> = repo.list_children_page(1, 0, 10).await.unwrap()
+            .into_iter().map(|(_, name, _)| name).collect();
+        assert_eq!(names, ["Carpeta", "suelto.txt"]);
+        assert_eq!(repo.list_children_extended_page(1, 1, 10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_keep_on_device_lists_folders_without_their_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_folder(&dir).await;
+        assert!(repo.list_kept_on_device().await.unwrap().is_empty());
+
+        repo.keep_on_device(2, true).await.unwrap();
+        repo.keep_on_device(5, false).await.unwrap();
+        for inode in [3, 4, 5] {
+            assert_eq!(repo.get_availability(inode).await.unwrap(), "local_online");
+        }
+        assert_eq!(
+            repo.list_kept_on_device().await.unwrap(),
+            vec![("f_carpeta".to_string(), true), ("f_suelto".to_string(), false)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_forget_device_state_keeps_metadata_and_pins() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_folder(&dir).await;
+        repo.keep_on_device(5, false).await.unwrap();
+        repo.add_cached_chunk(5, 0, 3).await.unwrap();
+        let client_id = repo.client_id().await.unwrap();
+
+        repo.forget_device_state().await.unwrap();
+
+        assert!(!repo.has_any_chunks(5).await.unwrap(), "la caché no viajó");
+        assert_ne!(repo.client_id().await.unwrap(), client_id, "otra instalación");
+        assert_eq!(repo.get_availability(5).await.unwrap(), "local_online");
+        assert_eq!(repo.resolve_relative_path_to_inode("Carpeta/b.txt").await.unwrap(), Some(4));
+    }
+
+    #[rstest]
+    #[case::root("", Some(1))]
+    #[case::top_level("Carpeta", Some(2))]
+    #[case::nested("Carpeta/Sub dir/doc.txt", Some(4))]
+    #[case::shared_prefix("SHARED/Carpeta", Some(2))]
+    #[case::missing("Carpeta/nada.txt", None)]
+    #[tokio::test]
+    async fn test_resolve_relative_path_to_inode(#[case] path: &str, #[case] expected: Option<u64>) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        test_fixtures::add_chain(&repo, &[("f_carpeta", "Carpeta", true), ("f_sub", "Sub dir", true), ("f_doc", "doc.txt", false)]).await;
+
+        assert_eq!(repo.resolve_relative_path_to_inode(path).await.unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::rename("rename", "Carpeta/Otro/doc.txt")]
+    #[case::remote_move("remote_move", "Sub dir/doc.txt")]
+    #[case::raw_sql("raw_sql", "Carpeta/Sub/doc.txt")]
+    #[tokio::test]
+    async fn test_resolved_paths_cached_until_namespace_changes(#[case] op: &str, #[case] new_path: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        let chain = test_fixtures::add_chain(&repo, &[("f_carpeta", "Carpeta", true), ("f_sub", "Sub dir", true), ("f_doc", "doc.txt", false)]).await;
+        let (sub, doc) = (chain[1], chain[2]);
+        // Las conexiones vuelven al pool en otra tarea
+        while repo.paths.snapshot().is_none() {
+            tokio::task::yield_now().await;
+        }
+
+        let parts = ["Carpeta", "Sub dir", "doc.txt"];
+        let resolved = Some((doc, "f_doc".to_string()));
+        assert_eq!(repo.resolve_path_components(&parts).await.unwrap(), resolved);
+        assert_eq!(repo.paths.get(&PathCache::key(&parts)), Some(resolved.clone()), "queda en caché");
+
+        match op {
+            "rename" => repo.rename_dentry(sub, "Otro").await.unwrap(),
+            "remote_move" => { repo.upsert_remote_dentry(1, sub, "Sub dir").await.unwrap(); }
+            "raw_sql" => {
+                sqlx::query("UPDATE dentry SET name = 'Sub' WHERE child_inode = ?")
+                    .bind(sub as i64)
+                    .execute(repo.pool())
+                    .await
+                    .unwrap();
+            }
+            _ => unreachable!(),
+        }
+
+        assert_eq!(repo.resolve_path_components(&parts).await.unwrap(), None);
+        assert_eq!(repo.resolve_relative_path_to_inode(new_path).await.unwrap(), Some(doc));
+    }
+
+
+    #[rstest]
+    #[case::single("informe", Some(r#""informe"*"#))]
+    #[case::multiple("  informe   2024 ", Some(r#""informe"* "2024"*"#))]
+    #[case::quotes(r#"dice "hola""#, Some(r#""dice"* """hola"""*"#))]
+    #[case::operators("a OR b", Some(r#""a"* "OR"* "b"*"#))]
+    #[case::empty("   ", None)]
+    fn test_fts_prefix_query(#[case] input: &str, #[case] expected: Option<&str>) {
+        assert_eq!(fts_prefix_query(input).as_deref(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_remote_duplicates_get_stable_suffixes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        let mut inodes = Vec::new();
+        for gdrive_id in ["dup_a", "dup_b", "dup_c"] {
+            let inode = repo.get_or_create_inode(gdrive_id).await.unwrap();
+            repo.upsert_file_metadata(inode, 0, 0, 0o644, false, None, true, false, true).await.unwrap();
+            inodes.push(inode);
+        }
+        let (a, b, c) = (inodes[0], inodes[1], inodes[2]);
+
+        assert_eq!(repo.upsert_remote_dentry(1, a, "informe.pdf").await.unwrap(), "informe.pdf");
+        assert_eq!(repo.upsert_remote_dentry(1, b, "informe.pdf").await.unwrap(), "informe (2).pdf");
+        assert_eq!(repo.upsert_remote_dentry(1, c, "informe.pdf").await.unwrap(), "informe (3).pdf");
+
+        // Volver a sincronizar no reordena los sufijos
+        assert_eq!(repo.upsert_remote_dentry(1, b, "informe.pdf").await.unwrap(), "informe (2).pdf");
+        assert_eq!(repo.upsert_remote_dentry(1, a, "informe.pdf").await.unwrap(), "informe.pdf");
+
+        assert_eq!(repo.lookup(1, "informe (3).pdf").await.unwrap(), Some(c));
+        assert_eq!(repo.get_remote_name(c).await.unwrap().as_deref(), Some("informe.pdf"));
+
+        // Un rename local fija el nombre nuevo también como nombre remoto
+        repo.upsert_dentry(1, c, "informe final.pdf").await.unwrap();
+        assert_eq!(repo.get_remote_name(c).await.unwrap().as_deref(), Some("informe final.pdf"));
+    }
+
+    #[rstest]
+    #[case::trash(".Trash", ".Trash (2)")]
+    #[case::starred("Starred", "Starred (2)")]
+    #[tokio::test]
+    async fn test_remote_child_named_like_virtual_dir_is_not_shadowed(#[case] name: &str, #[case] expected: &str) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        let inode = repo.get_or_create_inode("f_reservado").await.unwrap();
+        repo.upsert_file_metadata(inode, 0, 0, 0o755, true, None, true, false, true).await.unwrap();
+
+        assert_eq!(repo.upsert_remote_dentry(1, inode, name).await.unwrap(), expected);
+        assert_eq!(repo.get_remote_name(inode).await.unwrap().as_deref(), Some(name), "sin renombrar en Drive");
+
+        // Fuera del root el nombre está libre
+        let sub = repo.get_or_create_inode("f_sub").await.unwrap();
+        repo.upsert_file_metadata(sub, 0, 0, 0o755, true, None, true, false, true).await.unwrap();
+        repo.upsert_remote_dentry(1, sub, "Carpeta").await.unwrap();
+        assert_eq!(repo.upsert_remote_dentry(sub, inode, name).await.unwrap(), name);
+    }
+
+    #[tokio::test]
+    async fn test_remote_names_are_sanitized_and_mapped_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        let inode = repo.get_or_create_inode("f_nfd").await.unwrap();
+        repo.upsert_file_metadata(inode, 0, 0, 0o644, false, None, true, false, true).await.unwrap();
+
+        let local = repo.upsert_remote_dentry(1, inode, "Q1/Q2 Cancio\u{301}n.txt").await.unwrap();
+
+        assert_eq!(local, "Q1／Q2 Canción.txt");
+        assert_eq!(repo.lookup(1, "Q1／Q2 Canción.txt").await.unwrap(), Some(inode));
+        assert_eq!(repo.get_remote_name(inode).await.unwrap().as_deref(), Some("Q1/Q2 Cancio\u{301}n.txt"));
+
+        // Rename local: el sustituto de `/` vuelve a ser `/` en Drive
+        repo.upsert_dentry(1, inode, "Q3／Q4.txt").await.unwrap();
+        assert_eq!(repo.get_remote_name(inode).await.unwrap().as_deref(), Some("Q3/Q4.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_search_names_follows_renames_and_deletes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+
+        assert_eq!(repo.search_names("DOC", 10).await.unwrap(), vec![(2, "doc.txt".to_string(), false)]);
+
+        repo.rename_dentry(2, "Canción añeja.txt").await.unwrap();
+        assert!(repo.search_names("doc", 10).await.unwrap().is_empty());
+        assert_eq!(repo.search_names("cancion anej", 10).await.unwrap().len(), 1);
+
+        repo.remove_dentry(1, "Canción añeja.txt").await.unwrap();
+        assert!(repo.search_names("cancion", 10).await.unwrap().is_empty());
+    }
+
+
+    #[tokio::test]
+    async fn test_purge_expired_tombstones_clears_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        repo.add_cached_chunk(2, 0, 1023).await.unwrap();
+        repo.soft_delete_by_gdrive_id("file_doc").await.unwrap();
+
+        // Período de gracia negativo: todo tombstone existente está expirado
+        let purged = repo.purge_expired_tombstones(-1).await.unwrap();
+
+        assert_eq!(purged, vec!["file_doc".to_string()]);
+        assert!(!repo.has_any_chunks(2).await.unwrap());
+        assert_eq!(repo.get_inode_by_gdrive_id("file_doc").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_folder_delete_covers_descendants() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        let inodes = test_fixtures::add_chain(&repo, &[("f_carpeta", "Carpeta", true), ("f_sub", "Sub", true), ("f_doc", "doc.txt", false)]).await;
+        let (carpeta, sub, doc) = (inodes[0], inodes[1], inodes[2]);
+
+        repo.soft_delete_by_gdrive_id("f_carpeta").await.unwrap();
+
+        assert!(!repo.has_dentry(doc).await.unwrap());
+        assert_eq!(repo.deleted_parent_pending(carpeta).await.unwrap(), None);
+        assert_eq!(repo.deleted_parent_pending(doc).await.unwrap(), Some(true));
+
+        // La papelera de la carpeta raíz limpia todo el subárbol
+        repo.clear_dirty(carpeta).await.unwrap();
+        assert_eq!(repo.clear_deleted_subtree_dirty(carpeta).await.unwrap(), 2);
+        assert_eq!(repo.deleted_parent_pending(sub).await.unwrap(), Some(false));
+        assert!(repo.list_dirty_inodes().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hard_delete_subtree_removes_active_and_tombstoned_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = test_fixtures::repo(&dir).await;
+        test_fixtures::add_chain(&repo, &[("f_carpeta", "Carpeta", true), ("f_sub", "Sub", true), ("f_doc", "doc.txt", false)]).await;
+        // Un hijo ya estaba en la papelera antes de la eliminación permanente
+        repo.soft_delete_remote("f_doc").await.unwrap();
+
+        let mut removed = repo.hard_delete_subtree_by_gdrive_id("f_carpeta").await.unwrap();
+        removed.sort();
+
+        assert_eq!(removed, vec!["f_carpeta", "f_doc", "f_sub"]);
+        for gdrive_id in ["f_carpeta", "f_sub", "f_doc"] {
+            assert_eq!(repo.get_inode_by_gdrive_id(gdrive_id).await.unwrap(), None);
+            assert!(!repo.has_tombstone(gdrive_id).await.unwrap());
+        }
+        assert_eq!(repo.count_children(1).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_ignored_file_is_never_queued_nor_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        repo.add_cached_chunk(2, 0, 9).await.unwrap();
+        repo.mark_dirty(2).await.unwrap();
+
+        repo.mark_ignored(2).await.unwrap();
+        // Escrituras posteriores no lo devuelven a la cola
+        repo.mark_dirty(2).await.unwrap();
+
+        assert!(repo.is_ignored(2).await.unwrap());
+        assert_eq!(repo.sync_state(2).await.unwrap().unwrap().status, UploadStatus::Ignored);
+        assert!(repo.list_dirty_inodes().await.unwrap().is_empty());
+        assert!(!repo.get_directory_aggregate_status(1).await.unwrap().0);
+        assert!(!repo.cache_entries().await.unwrap()[0].evictable);
+        assert!(repo.sample_cached_files(10).await.unwrap().is_empty());
+
+        assert!(repo.unignore(2).await.unwrap());
+        assert!(!repo.unignore(2).await.unwrap());
+        assert_eq!(repo.sync_state(2).await.unwrap().unwrap().status, UploadStatus::Queued);
+        assert_eq!(repo.list_dirty_inodes().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_adopt_replaced_file_keeps_drive_identity() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let original = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+        repo.set_availability(original, "local_online", false).await.unwrap();
+        repo.set_remote_md5(original, "md5-original").await.unwrap();
+        repo.add_cached_chunk(original, 0, 9).await.unwrap();
+
+        let temp = repo.create_local_inode("temp_guardado").await.unwrap();
+        repo.upsert_file_metadata(temp, 4, 200, 0o600, false, Some("application/octet-stream"), true, false, true)
+            .await
+            .unwrap();
+        repo.upsert_dentry(1, temp, "lu1234.tmp").await.unwrap();
+        repo.add_cached_chunk(temp, 0, 3).await.unwrap();
+        repo.mark_ignored(temp).await.unwrap();
+
+        assert!(repo.can_replace_in_place(temp, original).await.unwrap());
+        assert!(!repo.can_replace_in_place(original, temp).await.unwrap(), "el destino debe existir en Drive");
+        assert_eq!(repo.adopt_replaced_file(temp, original).await.unwrap(), "file_doc");
+
+        assert_eq!(repo.get_gdrive_id(temp).await.unwrap().as_deref(), Some("file_doc"));
+        assert_eq!(repo.get_inode_by_gdrive_id("file_doc").await.unwrap(), Some(temp));
+        assert!(!repo.is_pending_create(temp).await.unwrap());
+        assert!(repo.lookup(1, "doc.txt").await.unwrap().is_none(), "el original desaparece");
+
+        let attrs = repo.get_attrs(temp).await.unwrap();
+        assert_eq!((attrs.size, attrs.mtime), (4, 200), "el contenido es el nuevo");
+        assert_eq!(attrs.mime_type.as_deref(), Some("text/plain"));
+        assert_eq!(attrs.mode & 0o777, 0o644);
+        assert_eq!(repo.get_cached_bytes_count(temp).await.unwrap(), 4);
+
+        let state = repo.sync_state(temp).await.unwrap().unwrap();
+        assert!(state.dirty);
+        assert_eq!(state.status, UploadStatus::Queued);
+        assert_eq!(repo.get_availability(temp).await.unwrap(), "local_online");
+        assert_eq!(repo.get_remote_md5(temp).await.unwrap().as_deref(), Some("md5-original"));
+        assert!(repo.pending_upload_ops().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_adopt_replaced_file_trashes_orphaned_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let original = repo.lookup(1, "doc.txt").await.unwrap().unwrap();
+        let source = repo.get_or_create_inode("file_otro").await.unwrap();
+        repo.upsert_file_metadata(source, 4, 200, 0o644, false, Some("text/plain"), true, false, true)
+            .await
+            .unwrap();
+        repo.upsert_dentry(1, source, "otro.txt").await.unwrap();
+        repo.add_cached_chunk(original, 0, 9).await.unwrap();
+        repo.add_cached_chunk(source, 0, 3).await.unwrap();
+        let caps = |owner: &str| FileCapabilities { can_edit: true, can_trash: true, owners: vec![owner.to_string()] };
+        repo.upsert_capabilities(original, &caps("Ana <ana@example.com>")).await.unwrap();
+        repo.upsert_capabilities(source, &caps("Beto <beto@example.com>")).await.unwrap();
+
+        assert!(repo.can_replace_in_place(source, original).await.unwrap());
+        assert_eq!(repo.adopt_replaced_file(source, original).await.unwrap(), "file_doc");
+        assert_eq!(repo.get_capabilities(source).await.unwrap(), Some(caps("Ana <ana@example.com>")), "siguen a file_doc");
+        assert_eq!(repo.get_capabilities(original).await.unwrap(), Some(caps("Beto <beto@example.com>")), "siguen a file_otro");
+
+        assert_eq!(repo.get_inode_by_gdrive_id("file_doc").await.unwrap(), Some(source));
+        assert_eq!(repo.get_cached_bytes_count(source).await.unwrap(), 4);
+        assert_eq!(repo.sync_state(source).await.unwrap().unwrap().deleted_at, None);
+
+        // El ID del origen queda en un tombstone que el uploader envía a la papelera
+        assert_eq!(repo.get_inode_by_gdrive_id("file_otro").await.unwrap(), Some(original));
+        assert!(repo.lookup(1, "doc.txt").await.unwrap().is_none());
+        assert!(!repo.has_any_chunks(original).await.unwrap());
+        assert!(repo.list_dirty_inodes().await.unwrap().contains(&(original, "file_otro".to_string(), true)));
+        let ops = repo.pending_upload_ops().await.unwrap();
+        assert!(ops[&original].structural);
+        assert!(!ops.contains_key(&source), "el llamador anota la escritura");
+    }
+
+    #[tokio::test]
+    async fn test_upload_status_transitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let status = |row: Option<SyncStateRow>| row.unwrap().status;
+
+        repo.mark_dirty(2).await.unwrap();
+        assert_eq!(status(repo.sync_state(2).await.unwrap()), UploadStatus::Queued);
+
+        repo.set_uploading(2, true).await.unwrap();
+        assert_eq!(status(repo.sync_state(2).await.unwrap()), UploadStatus::Uploading);
+
+        // Sigue dirty al terminar el intento: vuelve a la cola
+        repo.set_uploading(2, false).await.unwrap();
+        assert_eq!(status(repo.sync_state(2).await.unwrap()), UploadStatus::Queued);
+
+        // Solo el primer error con un mensaje dado cuenta como nuevo
+        assert!(repo.set_upload_error(2, "403 Forbidden", None).await.unwrap());
+        assert!(!repo.set_upload_error(2, "403 Forbidden", None).await.unwrap());
+        assert_eq!(status(repo.sync_state(2).await.unwrap()), UploadStatus::Error);
+        assert_eq!(repo.upload_error(2).await.unwrap().as_deref(), Some("403 Forbidden"));
+        assert!(repo.has_error_descendant(1).await.unwrap());
+
+        repo.clear_dirty(2).await.unwrap();
+        assert_eq!(status(repo.sync_state(2).await.unwrap()), UploadStatus::Synced);
+        assert_eq!(repo.upload_error(2).await.unwrap(), None);
+        assert!(!repo.has_error_descendant(1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_failed_uploads_wait_for_backoff_or_user() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        repo.mark_dirty(2).await.unwrap();
+        let ready = vec![(2, "file_doc".to_string(), false)];
+
+        // Reintento programado en el futuro: fuera del ciclo
+        repo.set_upload_error(2, "503 Service Unavailable", Some(i64::MAX)).await.unwrap();
+        assert!(repo.list_dirty_inodes().await.unwrap().is_empty());
+        assert_eq!(repo.sync_state(2).await.unwrap().unwrap().retry_count, 1);
+
+        // Reintento vencido: vuelve a la cola
+        repo.set_upload_error(2, "503 Service Unavailable", Some(0)).await.unwrap();
+        assert_eq!(repo.list_dirty_inodes().await.unwrap(), ready);
+
+        // Error permanente: no se reintenta y aparece como problema
+        repo.set_upload_error(2, "insufficientFilePermissions", None).await.unwrap();
+        assert!(repo.list_dirty_inodes().await.unwrap().is_empty());
+        let problems = repo.list_upload_problems().await.unwrap();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].path, "doc.txt");
+        assert_eq!(problems[0].retry_count, 3);
+        assert!(problems[0].permanent);
+
+        // "Reintentar ahora" desde la GUI
+        repo.retry_upload_now(2).await.unwrap();
+        assert_eq!(repo.list_dirty_inodes().await.unwrap(), ready);
+
+        // Un nuevo cambio local también reinicia el backoff
+        repo.set_upload_error(2, "insufficientFilePermissions", None).await.unwrap();
+        repo.mark_dirty(2).await.unwrap();
+        assert_eq!(repo.list_dirty_inodes().await.unwrap(), ready);
+        assert_eq!(repo.sync_state(2).await.unwrap().unwrap().status, UploadStatus::Queued);
+        assert!(repo.list_upload_problems().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sample_cached_files_skips_dirty() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        repo.add_cached_chunk(2, 0, 4).await.unwrap();
+        repo.add_cached_chunk(2, 5, 9).await.unwrap();
+
+        let samples = repo.sample_cached_files(10).await.unwrap();
+        assert_eq!(samples.len(), 1);
+        assert_eq!((samples[0].size, samples[0].max_cached_end, samples[0].cached_bytes), (10, 9, 10));
+
+        // Contenido local pendiente de subir: la caché no debe coincidir con el remoto
+        repo.mark_dirty(2).await.unwrap();
+        assert!(repo.sample_cached_files(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_entries_group_by_top_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        // Trabajo/2024/informe.pdf
+        let mut parent = 1;
+        for (gdrive_id, name, is_dir) in [("dir_trabajo", "Trabajo", true), ("dir_2024", "2024", true), ("file_informe", "informe.pdf", false)] {
+            let inode = repo.get_or_create_inode(gdrive_id).await.unwrap();
+            repo.upsert_file_metadata(inode, if is_dir { 0 } else { 20 }, 100, 0o644, is_dir, None, true, false, true)
+                .await
+                .unwrap();
+            repo.upsert_dentry(parent, inode, name).await.unwrap();
+            parent = inode;
+        }
+        let informe = parent;
+
+        repo.add_cached_chunk(2, 0, 9).await.unwrap();
+        repo.add_cached_chunk(informe, 0, 19).await.unwrap();
+        repo.set_availability(informe, "local_online", false).await.unwrap();
+        repo.mark_dirty(2).await.unwrap();
+
+        let mut entries = repo.cache_entries().await.unwrap();
+        entries.sort_by_key(|e| e.inode);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].cached_bytes, entries[0].pinned, entries[0].evictable), (10, false, false));
+        assert_eq!(entries[0].top_folder, None, "archivo del root");
+        assert_eq!((entries[1].cached_bytes, entries[1].pinned, entries[1].evictable), (20, true, true));
+        assert_eq!(entries[1].top_folder.as_deref(), Some("Trabajo"));
+
+        repo.clear_cached_chunks(informe).await.unwrap();
+        assert_eq!(repo.cache_entries().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_set_computers_visible_attaches_virtual_dir_to_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let computers = repo.computers_inode().await.unwrap();
+        let laptop = repo.get_or_create_inode("folder_laptop").await.unwrap();
+        repo.upsert_file_metadata(laptop, 0, 100, 0o755, true, Some("application/vnd.google-apps.folder"), true, false, true).await.unwrap();
+        repo.upsert_remote_dentry(computers, laptop, "Mi portátil").await.unwrap();
+        assert_eq!(repo.resolve_inode_to_relative_path(laptop).await.unwrap(), None, "oculto por defecto");
+
+        assert!(repo.set_computers_visible(true).await.unwrap());
+        assert!(!repo.set_computers_visible(true).await.unwrap(), "ya visible");
+        assert_eq!(repo.lookup(1, COMPUTERS_DIR_NAME).await.unwrap(), Some(computers));
+        assert_eq!(repo.get_attrs(computers).await.unwrap().mode, 0o555);
+        assert_eq!(repo.resolve_inode_to_relative_path(laptop).await.unwrap().as_deref(), Some("Computers/Mi portátil"));
+
+        assert!(repo.set_computers_visible(false).await.unwrap());
+        assert_eq!(repo.lookup(1, COMPUTERS_DIR_NAME).await.unwrap(), None);
+        assert_eq!(repo.lookup(computers, "Mi portátil").await.unwrap(), Some(laptop), "los equipos siguen agrupados");
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_candidates_follow_dir_access() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let gdoc = repo.get_or_create_inode("file_gdoc").await.unwrap();
+        repo.upsert_file_metadata(gdoc, 100, 100, 0o644, false, Some("application/vnd.google-apps.document"), true, false, true)
+            .await
+            .unwrap();
+        repo.upsert_dentry(1, gdoc, "notas").await.unwrap();
+
+        // Sin accesos registrados no hay nada que precargar
+        assert!(repo.list_prefetch_candidates(0, 10).await.unwrap().is_empty());
+
+        repo.record_dir_access(1).await.unwrap();
+        repo.add_cached_chunk(2, 0, 4).await.unwrap();
+        let candidates = repo.list_prefetch_candidates(0, 10).await.unwrap();
+        assert_eq!(candidates.len(), 1, "el documento de Google no tiene contenido que precargar");
+        assert_eq!((candidates[0].inode, candidates[0].size, candidates[0].cached_bytes), (2, 10, 5));
+
+        // Accesos antiguos quedan fuera de la ventana
+        assert!(repo.list_prefetch_candidates(i64::MAX, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_candidates_need_link_and_clean_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        let foto = repo.get_or_create_inode("file_foto").await.unwrap();
+        repo.upsert_file_metadata(foto, 100, 200, 0o644, false, Some("image/jpeg"), true, false, true).await.unwrap();
+        repo.upsert_dentry(1, foto, "foto.jpg").await.unwrap();
+        repo.set_bulk_thumbnail_links(&[(foto, "https://lh3.example/thumb=s220".to_string())]).await.unwrap();
+        repo.record_dir_access(1).await.unwrap();
+
+        let candidates = repo.list_thumbnail_candidates(0, 10).await.unwrap();
+        assert_eq!(candidates.len(), 1, "el archivo sin thumbnailLink no es candidato");
+        assert_eq!((candidates[0].inode, candidates[0].mtime, candidates[0].thumbnail_mtime), (foto, 200, None));
+
+        repo.set_thumbnail_mtime(foto, 200).await.unwrap();
+        assert_eq!(repo.list_thumbnail_candidates(0, 10).await.unwrap()[0].thumbnail_mtime, Some(200));
+
+        repo.mark_dirty(foto).await.unwrap();
+        assert!(repo.list_thumbnail_candidates(0, 10).await.unwrap().is_empty(), "contenido local distinto del remoto");
+        repo.clear_dirty(foto).await.unwrap();
+        repo.set_thumbnail_link(foto, None).await.unwrap();
+        assert!(repo.list_thumbnail_candidates(0, 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_inodes_created_after_hard_delete_get_newer_generation() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = repo_with_file(&dir).await;
+        assert_eq!(repo.get_attrs(2).await.unwrap().generation, 0);
+
+        repo.hard_delete_by_gdrive_id("file_doc").await.unwrap();
+
+        let inode = repo.get_or_create_inode("file_nuevo").await.unwrap();
+        repo.upsert_file_metadata(inode, 0, 0, 0o644, false, None, true, false, true).await.unwrap();
+        assert_eq!(repo.get_attrs(inode).await.unwrap().generation, 1);
+    }
+
+
+    /// Latencias ordenadas: p50, p99 y máximo en milisegundos
+    fn latency_summary(latencies: &mut [std::time::Duration]) -> String {
+        latencies.sort();
+        let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+        let at = |q: f64| ms(latencies[((latencies.len() - 1) as f64 * q) as usize]);
+        format!("p50 {:.2} ms, p99 {:.2} ms, máx {:.2} ms ({} consultas)", at(0.5), at(0.99), at(1.0), latencies.len())
+    }
+
+    /// Prueba de carga manual: latencia de `get_attrs` (FUSE) y de `count_dirty`
+    /// (consulta del pool de escritura, como el estado de IPC/GUI) mientras un
+    /// escritor inserta lotes como el bootstrap, con la disposición anterior (un
+    /// pool de 5 conexiones) y la actual. Solo imprime los tiempos:
+    /// `cargo test load_getattr -- --ignored --nocapture`
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    #[ignore = "prueba de carga: imprime latencias, no comprueba tiempos"]
+    async fn load_getattr_latency_under_bootstrap() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+        const FILES: usize = 20_000;
+        const READERS: usize = 8;
+        const READS: usize = 2_000;
+
+        for (write_connections, read_connections) in [(5, None), (WRITE_CONNECTIONS, Some(READ_CONNECTIONS))] {
+            let layout = match read_connections {
+                None => format!("un pool de {}", write_connections),
+                Some(read) => format!("escritura {} + lectura {}", write_connections, read),
+            };
+            let dir = tempfile::tempdir().unwrap();
+            let repo = MetadataRepository::open(&dir.path().join("metadata.db"), write_connections, read_connections)
+                .await
+                .unwrap();
+            let repo = Arc::new(repo);
+            let ids: Vec<String> = (0..FILES).map(|i| format!("file_{}", i)).collect();
+            let inodes: Arc<Vec<u64>> = Arc::new(repo.get_or_create_inodes_bulk(&ids).await.unwrap().into_values().collect());
+            let page = |mtime: i64| -> Vec<BulkFileMetadata> {
+                inodes.iter().map(|&inode| BulkFileMetadata {
+                    inode, size: 4096, mtime, mtime_nsec: 0, mode: 0o644, is_dir: false,
+                    mime_type: Some("text/plain".to_string()), can_move: true, shared: false, owned_by_me: true,
+                    starred: false, created_time: None, origin_client: None, pin_hint: false,
+                    description: None, folder_color: None,
+                }).collect()
+            };
+            repo.upsert_bulk_file_metadata(&page(0)).await.unwrap();
+
+            // Bootstrap: páginas de Drive escritas sin pausa
+            let stop = Arc::new(AtomicBool::new(false));
+            let pages = Arc::new(AtomicU64::new(0));
+            let writer = {
+                let (repo, stop, pages, rows) = (repo.clone(), stop.clone(), pages.clone(), page(1));
+                tokio::spawn(async move {
+                    while !stop.load(Ordering::Relaxed) {
+                        for chunk in rows.chunks(1000) {
+                            repo.upsert_bulk_file_metadata(chunk).await.unwrap();
+                            pages.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                })
+            };
+            let status = {
+                let (repo, stop) = (repo.clone(), stop.clone());
+                tokio::spawn(async move {
+                    let mut latencies = Vec::new();
+                    while !stop.load(Ordering::Relaxed) {
+                        let start = std::time::Instant::now();
+                        repo.count_dirty().await.unwrap();
+                        latencies.push(start.elapsed());
+                        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                    }
+                    latencies
+                })
+            };
+            let readers: Vec<_> = (0..READERS)
+                .map(|reader| {
+                    let (repo, inodes) = (repo.clone(), inodes.clone());
+                    tokio::spawn(async move {
+                        let mut latencies = Vec::with_capacity(READS);
+                        for i in 0..READS {
+                            let inode = inodes[(reader * 7_919 + i * 104_729) % inodes.len()];
+                            let start = std::time::Instant::now();
+                            repo.get_attrs(inode).await.unwrap();
+                            latencies.push(start.elapsed());
+                        }
+                        latencies
+                    })
+                })
+                .collect();
+
+            let mut getattr = Vec::new();
+            for reader in readers {
+                getattr.extend(reader.await.unwrap());
+            }
+            stop.store(true, Ordering::Relaxed);
+            writer.await.unwrap();
+            let mut status = status.await.unwrap();
+            println!(
+                "{}: get_attrs {}; count_dirty {}; {} lotes escritos",
+                layout,
+                latency_summary(&mut getattr),
+                latency_summary(&mut status),
+                pages.load(Ordering::Relaxed),
+            );
+        }
+    }
 }
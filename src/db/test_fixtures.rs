@@ -0,0 +1,58 @@
+//! Fábricas de repositorios compartidas por los tests de todos los módulos
+//!
+//! Sustituyen los bloques `tempdir` + `MetadataRepository::new` + alta de
+//! inode, atributos y dentry que cada test repetía. El repositorio ya tiene el
+//! inode raíz (1), como tras el bootstrap: sin él, las dentries del root violan
+//! la clave foránea y el primer archivo recibiría el inode 1.
+
+use std::sync::Arc;
+
+use super::MetadataRepository;
+use crate::gdrive::DriveApi;
+use crate::gdrive::mock::{MockDriveClient, MOCK_ROOT_ID};
+
+/// Repositorio en `dir/metadata.db` con el inode raíz creado
+pub async fn repo(dir: &tempfile::TempDir) -> MetadataRepository {
+    let repo = MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap();
+    repo.ensure_root().await.unwrap();
+    repo
+}
+
+/// Da de alta un archivo o carpeta de Drive (tamaño y mtime 0, propio) en
+/// `parent` con un nombre elegido localmente. Retorna su inode.
+pub async fn add_node(repo: &MetadataRepository, gdrive_id: &str, name: &str, is_dir: bool, parent: u64) -> u64 {
+    let inode = repo.get_or_create_inode(gdrive_id).await.unwrap();
+    let mode = if is_dir { 0o755 } else { 0o644 };
+    repo.upsert_file_metadata(inode, 0, 0, mode, is_dir, None, true, false, true).await.unwrap();
+    repo.upsert_dentry(parent, inode, name).await.unwrap();
+    inode
+}
+
+/// Da de alta una cadena anidada `(gdrive_id, nombre, is_dir)`: el primero
+/// cuelga del root y cada uno del anterior. Retorna los inodes en orden.
+pub async fn add_chain(repo: &MetadataRepository, nodes: &[(&str, &str, bool)]) -> Vec<u64> {
+    let mut parent = 1;
+    let mut inodes = Vec::with_capacity(nodes.len());
+    for &(gdrive_id, name, is_dir) in nodes {
+        parent = add_node(repo, gdrive_id, name, is_dir, parent).await;
+        inodes.push(parent);
+    }
+    inodes
+}
+
+/// Repositorio en `dir` con el root de `mock` cargado (`bootstrap_level1`)
+pub async fn synced_repo(dir: &tempfile::TempDir, mock: &Arc<MockDriveClient>) -> Arc<MetadataRepository> {
+    bootstrapped(MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap(), mock).await
+}
+
+/// Como `synced_repo`, en memoria
+pub async fn synced_in_memory(mock: &Arc<MockDriveClient>) -> Arc<MetadataRepository> {
+    bootstrapped(MetadataRepository::in_memory().await.unwrap(), mock).await
+}
+
+async fn bootstrapped(repo: MetadataRepository, mock: &Arc<MockDriveClient>) -> Arc<MetadataRepository> {
+    let db = Arc::new(repo);
+    let client: Arc<dyn DriveApi> = mock.clone();
+    crate::sync::bootstrap::bootstrap_level1(&db, &client, MOCK_ROOT_ID).await.unwrap();
+    db
+}
@@ -4,11 +4,14 @@ use fuse3::FileType;
 use sqlx::FromRow;
 use std::time::UNIX_EPOCH;
 
-#[derive(Debug, FromRow)]
+#[derive(Debug, Clone, FromRow)]
 pub struct FileAttributes {
     pub inode: i64,
     pub size: i64,
     pub mtime: i64,
+    /// Fracción de segundo del mtime (`modifiedTime` de Drive tiene milisegundos)
+    #[sqlx(default)]
+    pub mtime_nsec: i64,
     pub ctime: i64,
     pub mode: i64,
     pub is_dir: bool,
@@ -19,6 +22,12 @@ pub struct FileAttributes {
     pub shared: bool,
     #[sqlx(default)]
     pub owned_by_me: bool,
+    /// Generación del inode (`inodes.generation`), distingue números de inode reutilizados
+    #[sqlx(default)]
+    pub generation: i64,
+    /// `createdTime` de Drive (o la hora de `create`/`mkdir` local); `None` si no se conoce
+    #[sqlx(default)]
+    pub created_time: Option<i64>,
 }
 
 impl FileAttributes {
@@ -27,15 +36,21 @@ impl FileAttributes {
             ino: self.inode as u64,
             size: self.size as u64,
             blocks: (self.size as u64 + 511) / 512,
-            atime: Timestamp::new(self.mtime as i64, 0),
-            mtime: Timestamp::new(self.mtime as i64, 0),
+            atime: Timestamp::new(self.mtime, self.mtime_nsec as u32),
+            mtime: Timestamp::new(self.mtime, self.mtime_nsec as u32),
             ctime: Timestamp::new(self.ctime as i64, 0),
+            // El protocolo FUSE de Linux no transporta la fecha de creación
+            // (fuse3 no implementa FUSE_STATX): ahí se expone como xattr
+            #[cfg(target_os = "macos")]
+            crtime: Timestamp::new(self.created_time.unwrap_or(self.ctime), 0),
             kind: if self.is_dir { FileType::Directory } else { FileType::RegularFile },
             perm: (self.mode & 0o7777) as u16,
             nlink: 1,
             uid: unsafe { libc::getuid() }, 
             gid: unsafe { libc::getgid() },
             rdev: 0,
+            #[cfg(target_os = "macos")]
+            flags: 0,
             blksize: 512,
         }
     }
@@ -51,6 +66,7 @@ impl FileAttributes {
             inode: 1,
             size: 4096,
             mtime: now,
+            mtime_nsec: 0,
             ctime: now,
             mode: 0o755, // 0o755 is correct, but let's check why stat showed 0644
             is_dir: true,
@@ -58,6 +74,8 @@ impl FileAttributes {
             can_move: true,
             shared: false,
             owned_by_me: true,
+            generation: 0,
+            created_time: None,
         }
     }
 }
@@ -0,0 +1,348 @@
+//! Uso y liberación de la caché de contenido
+//!
+//! Cada archivo leído a través de FUSE se guarda en `cache_dir/<gdrive_id>` y
+//! sus rangos presentes en `file_cache_chunks`. `CacheManager` resume el uso
+//! (total, anclado y por carpeta de primer nivel) para el panel de la GUI y
+//! libera espacio: `evict_unpinned` descarta lo que no está marcado
+//! "Local & Online", `evict_subtree` lo que hay bajo una carpeta y `clear`
+//! todo lo demás también. Un archivo con cambios
+//! sin subir (`dirty`/`uploading`) nunca se descarta: su caché es la única
+//! copia de ese contenido. Tampoco uno abierto en FUSE: un lector o escritor
+//! activo perdería el contenido bajo sus pies. Cada archivo se descarta con
+//! su lock de `FileLocks` tomado (el mismo que FUSE, el prefetcher y el
+//! uploader usan para escribir la caché), comprobando de nuevo ambas
+//! condiciones ya con el lock. Descartar es lo mismo que hace el verificador
+//! con una caché inválida: se borran los chunks y el archivo, y la próxima
+//! lectura vuelve a descargar.
+//!
+//! Los archivos de caché son dispersos (solo se asignan los rangos
+//! descargados o escritos), así que el tamaño de los chunks no es lo que
+//! ocupan: `usage` suma además los bloques asignados de cada archivo, una sola
+//! vez por inodo porque las copias idénticas comparten contenido
+//! (`content_store`). Tras liberar se borran del almacén las entradas que ya
+//! no enlaza ninguna caché.
+
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::db::{CacheEntry, MetadataRepository, UploadStatus};
+use crate::fuse::content_store::ContentStore;
+use crate::fuse::file_locks::FileLocks;
+
+/// Uso de la caché según `file_cache_chunks`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheUsage {
+    pub total_bytes: u64,
+    /// Espacio asignado en disco (bloques reales de los archivos dispersos)
+    pub disk_bytes: u64,
+    /// Bytes de archivos marcados "Local & Online"
+    pub pinned_bytes: u64,
+    /// Desglose por carpeta de primer nivel, de mayor a menor
+    pub folders: Vec<FolderCacheUsage>,
+}
+
+/// Uso de la caché bajo una carpeta del root
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderCacheUsage {
+    /// `None`: archivos sueltos del root (o fuera del árbol, ej. compartidos)
+    pub name: Option<String>,
+    pub bytes: u64,
+    pub pinned_bytes: u64,
+}
+
+/// Resultado de una liberación
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvictionReport {
+    pub files: u64,
+    pub bytes: u64,
+    /// Archivos conservados por tener cambios pendientes de subir o estar abiertos
+    pub skipped: u64,
+}
+
+/// Consulta y libera la caché de contenido
+#[derive(Debug, Clone)]
+pub struct CacheManager {
+    db: Arc<MetadataRepository>,
+    cache_dir: PathBuf,
+    /// Locks y handles abiertos de FUSE (`GDriveFS::with_file_locks`)
+    file_locks: FileLocks,
+}
+
+impl CacheManager {
+    pub fn new(db: Arc<MetadataRepository>, cache_dir: PathBuf) -> Self {
+        Self { db, cache_dir, file_locks: FileLocks::default() }
+    }
+
+    /// Comparte los locks de caché y los handles abiertos con FUSE
+    pub fn with_file_locks(mut self, file_locks: FileLocks) -> Self {
+        self.file_locks = file_locks;
+        self
+    }
+
+    /// Uso actual de la caché
+    pub async fn usage(&self) -> Result<CacheUsage> {
+        use std::os::unix::fs::MetadataExt;
+        let entries = self.db.cache_entries().await?;
+        let mut usage = summarize(&entries);
+        let mut seen = HashSet::new();
+        for entry in &entries {
+            // Bytes asignados en disco; los archivos enlazados comparten bloques
+            if let Ok(meta) = tokio::fs::metadata(self.cache_dir.join(&entry.gdrive_id)).await
+                && seen.insert((meta.dev(), meta.ino()))
+            {
+                usage.disk_bytes += meta.blocks() * 512;
+            }
+        }
+        Ok(usage)
+    }
+
+    /// Descarta la caché de los archivos que no están anclados
+    pub async fn evict_unpinned(&self) -> Result<EvictionReport> {
+        self.evict(|entry| !entry.pinned).await
+    }
+
+    /// Descarta la caché de un archivo o de todo lo que hay bajo una carpeta
+    /// ("Liberar espacio"), anclados incluidos. Los metadatos se conservan
+    pub async fn evict_subtree(&self, inode: u64) -> Result<EvictionReport> {
+        let subtree: HashSet<u64> = self.db.subtree_inodes(inode).await?.into_iter().collect();
+        self.evict(|entry| subtree.contains(&entry.inode)).await
+    }
+
+    /// Descarta toda la caché descartable (anclados incluidos)
+    pub async fn clear(&self) -> Result<EvictionReport> {
+        self.evict(|_| true).await
+    }
+
+    async fn evict(&self, select: impl Fn(&CacheEntry) -> bool) -> Result<EvictionReport> {
+        let mut report = EvictionReport::default();
+        for entry in self.db.cache_entries().await?.iter().filter(|entry| select(entry)) {
+            if !entry.evictable || self.file_locks.is_open(entry.inode) {
+                report.skipped += 1;
+                continue;
+            }
+            // Con el lock nadie escribe la caché; lo leído antes puede haber cambiado
+            let _guard = self.file_locks.lock(entry.inode).await;
+            if self.file_locks.is_open(entry.inode) || !self.still_evictable(entry.inode).await? {
+                report.skipped += 1;
+                continue;
+            }
+            // Primero la DB: un archivo sin chunks es "zombie" y FUSE lo borra al leer
+            self.db.clear_cached_chunks(entry.inode).await?;
+            let path = self.cache_dir.join(&entry.gdrive_id);
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => tracing::warn!("⚠️ No se pudo eliminar caché {:?}: {}", path, e),
+            }
+            report.files += 1;
+            report.bytes += entry.cached_bytes;
+        }
+        ContentStore::new(&self.cache_dir).collect_garbage().await?;
+        tracing::info!(
+            "🧹 Caché liberada: {} archivos, {} bytes ({} abiertos o con cambios pendientes conservados)",
+            report.files, report.bytes, report.skipped
+        );
+        Ok(report)
+    }
+
+    /// Sin cambios sin subir, como `CacheEntry::evictable` pero leído ahora
+    async fn still_evictable(&self, inode: u64) -> Result<bool> {
+        Ok(match self.db.sync_state(inode).await? {
+            Some(state) => !state.dirty && !state.uploading && state.status != UploadStatus::Ignored,
+            None => true,
+        })
+    }
+}
+
+/// Totales y desglose por carpeta de primer nivel
+fn summarize(entries: &[CacheEntry]) -> CacheUsage {
+    let mut usage = CacheUsage::default();
+    let mut folders: HashMap<Option<&str>, (u64, u64)> = HashMap::new();
+    for entry in entries {
+        let pinned = if entry.pinned { entry.cached_bytes } else { 0 };
+        usage.total_bytes += entry.cached_bytes;
+        usage.pinned_bytes += pinned;
+        let folder = folders.entry(entry.top_folder.as_deref()).or_default();
+        folder.0 += entry.cached_bytes;
+        folder.1 += pinned;
+    }
+    usage.folders = folders
+        .into_iter()
+        .map(|(name, (bytes, pinned_bytes))| FolderCacheUsage { name: name.map(str::to_string), bytes, pinned_bytes })
+        .collect();
+    usage.folders.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.name.cmp(&b.name)));
+    usage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(inode: u64, bytes: u64, pinned: bool, evictable: bool, folder: Option<&str>) -> CacheEntry {
+        CacheEntry {
+            inode,
+            gdrive_id: format!("file_{}", inode),
+            cached_bytes: bytes,
+            pinned,
+            evictable,
+            top_folder: folder.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_summarize_groups_by_top_folder() {
+        let usage = summarize(&[
+            entry(2, 10, false, true, None),
+            entry(3, 30, true, true, Some("Fotos")),
+            entry(4, 50, false, true, Some("Trabajo")),
+            entry(5, 5, true, true, Some("Fotos")),
+        ]);
+
+        assert_eq!((usage.total_bytes, usage.pinned_bytes), (95, 35));
+        assert_eq!(
+            usage.folders,
+            vec![
+                FolderCacheUsage { name: Some("Trabajo".into()), bytes: 50, pinned_bytes: 0 },
+                FolderCacheUsage { name: Some("Fotos".into()), bytes: 35, pinned_bytes: 35 },
+                FolderCacheUsage { name: None, bytes: 10, pinned_bytes: 0 },
+            ]
+        );
+    }
+
+    /// Caché con un archivo suelto, otro anclado y otro con cambios sin subir
+    async fn fixture() -> (tempfile::TempDir, Arc<MetadataRepository>, CacheManager) {
+        let cache = tempfile::tempdir().unwrap();
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        for (gdrive_id, name) in [("file_suelto", "suelto.txt"), ("file_anclado", "anclado.txt"), ("file_sucio", "sucio.txt")] {
+            let inode = db.get_or_create_inode(gdrive_id).await.unwrap();
+            db.upsert_file_metadata(inode, 4, 100, 0o644, false, Some("text/plain"), true, false, true).await.unwrap();
+            db.upsert_dentry(1, inode, name).await.unwrap();
+            db.add_cached_chunk(inode, 0, 3).await.unwrap();
+            std::fs::write(cache.path().join(gdrive_id), b"1234").unwrap();
+            match gdrive_id {
+                "file_anclado" => db.set_availability(inode, "local_online", false).await.unwrap(),
+                "file_sucio" => db.mark_dirty(inode).await.unwrap(),
+                _ => {}
+            }
+        }
+        let manager = CacheManager::new(db.clone(), cache.path().to_path_buf());
+        (cache, db, manager)
+    }
+
+    #[tokio::test]
+    async fn test_evict_skips_open_files() {
+        let (cache, db, manager) = fixture().await;
+        let file_locks = FileLocks::default();
+        let manager = manager.with_file_locks(file_locks.clone());
+        let suelto = db.get_inode_by_gdrive_id("file_suelto").await.unwrap().unwrap();
+        file_locks.opened(suelto);
+
+        let report = manager.evict_unpinned().await.unwrap();
+
+        assert_eq!(report, EvictionReport { files: 0, bytes: 0, skipped: 2 });
+        assert!(cache.path().join("file_suelto").exists(), "un lector activo conserva su caché");
+
+        file_locks.closed(suelto);
+        assert_eq!(manager.evict_unpinned().await.unwrap().files, 1);
+        assert!(!cache.path().join("file_suelto").exists());
+    }
+
+    #[tokio::test]
+    async fn test_evict_waits_for_writer_and_rechecks_dirty() {
+        let (cache, db, manager) = fixture().await;
+        let file_locks = FileLocks::default();
+        let manager = manager.with_file_locks(file_locks.clone());
+        let suelto = db.get_inode_by_gdrive_id("file_suelto").await.unwrap().unwrap();
+
+        // Un escritor con el lock deja el archivo dirty antes de soltarlo
+        let guard = file_locks.lock(suelto).await;
+        let evicting = tokio::spawn(async move { manager.evict_unpinned().await.unwrap() });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!evicting.is_finished(), "espera al lock del escritor");
+        db.mark_dirty(suelto).await.unwrap();
+        drop(guard);
+
+        let report = evicting.await.unwrap();
+        assert_eq!(report, EvictionReport { files: 0, bytes: 0, skipped: 2 });
+        assert!(cache.path().join("file_suelto").exists());
+    }
+
+    #[tokio::test]
+    async fn test_evict_unpinned_keeps_pinned_and_dirty() {
+        let (cache, _db, manager) = fixture().await;
+
+        let report = manager.evict_unpinned().await.unwrap();
+
+        assert_eq!(report, EvictionReport { files: 1, bytes: 4, skipped: 1 });
+        assert!(!cache.path().join("file_suelto").exists());
+        assert!(cache.path().join("file_anclado").exists());
+        assert!(cache.path().join("file_sucio").exists());
+        assert_eq!(manager.usage().await.unwrap().total_bytes, 8);
+    }
+
+    #[tokio::test]
+    async fn test_clear_keeps_only_dirty() {
+        let (cache, _db, manager) = fixture().await;
+
+        let report = manager.clear().await.unwrap();
+
+        assert_eq!(report, EvictionReport { files: 2, bytes: 8, skipped: 1 });
+        assert!(!cache.path().join("file_anclado").exists());
+        let usage = manager.usage().await.unwrap();
+        assert_eq!((usage.total_bytes, usage.pinned_bytes), (4, 0));
+    }
+
+    #[tokio::test]
+    async fn test_evict_subtree_frees_pinned_under_folder_only() {
+        let (cache, db, manager) = fixture().await;
+        let folder = db.get_or_create_inode("folder_fotos").await.unwrap();
+        db.upsert_file_metadata(folder, 0, 100, 0o755, true, None, true, false, true).await.unwrap();
+        db.upsert_dentry(1, folder, "Fotos").await.unwrap();
+        for name in ["anclado.txt", "sucio.txt"] {
+            let inode = db.lookup(1, name).await.unwrap().unwrap();
+            db.move_dentry(inode, folder, name).await.unwrap();
+        }
+
+        let report = manager.evict_subtree(folder).await.unwrap();
+
+        assert_eq!(report, EvictionReport { files: 1, bytes: 4, skipped: 1 });
+        assert!(!cache.path().join("file_anclado").exists(), "liberar espacio incluye lo anclado");
+        assert!(cache.path().join("file_sucio").exists());
+        assert!(cache.path().join("file_suelto").exists(), "fuera de la carpeta no se toca");
+    }
+
+    #[tokio::test]
+    async fn test_usage_counts_allocated_blocks_of_sparse_files() {
+        let (cache, db, manager) = fixture().await;
+        let inode = db.get_or_create_inode("file_disperso").await.unwrap();
+        db.upsert_file_metadata(inode, 64 * 1024 * 1024, 100, 0o644, false, None, true, false, true).await.unwrap();
+        db.add_cached_chunk(inode, 0, 3).await.unwrap();
+        let file = std::fs::File::create(cache.path().join("file_disperso")).unwrap();
+        file.set_len(64 * 1024 * 1024).unwrap();
+
+        let usage = manager.usage().await.unwrap();
+
+        assert_eq!(usage.total_bytes, 16);
+        assert!(usage.disk_bytes < 1024 * 1024, "el hueco no ocupa disco: {}", usage.disk_bytes);
+    }
+
+    #[tokio::test]
+    async fn test_linked_copies_count_once_and_eviction_frees_store() {
+        let (cache, db, manager) = fixture().await;
+        let inode = db.get_or_create_inode("file_copia").await.unwrap();
+        db.upsert_file_metadata(inode, 4, 100, 0o644, false, Some("text/plain"), true, false, true).await.unwrap();
+        db.add_cached_chunk(inode, 0, 3).await.unwrap();
+        let store_entry = cache.path().join(crate::fuse::content_store::STORE_DIR).join("81dc9bdb52d04dc20036dbd8313ed055");
+        let before = manager.usage().await.unwrap().disk_bytes;
+
+        std::fs::create_dir_all(store_entry.parent().unwrap()).unwrap();
+        std::fs::hard_link(cache.path().join("file_suelto"), &store_entry).unwrap();
+        std::fs::hard_link(&store_entry, cache.path().join("file_copia")).unwrap();
+        assert_eq!(manager.usage().await.unwrap().disk_bytes, before, "la copia no ocupa más");
+
+        manager.evict_unpinned().await.unwrap();
+        assert!(!store_entry.exists(), "sin cachés que la enlacen");
+    }
+}
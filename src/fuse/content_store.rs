@@ -0,0 +1,366 @@
+//! Almacén de contenido por MD5 (`cache_dir/by-md5/<md5>`)
+//!
+//! Muchas cuentas tienen copias idénticas de archivos grandes. Cuando una
+//! caché queda completa y su MD5 coincide con el remoto se publica aquí como
+//! enlace duro; cualquier otro archivo con el mismo `remote_md5` y tamaño
+//! adopta ese contenido enlazando su `cache_dir/<gdrive_id>` en lugar de
+//! descargarlo. Todas las copias comparten los bloques del disco (y la clave
+//! de cifrado, que está en el xattr del inodo).
+//!
+//! Un archivo compartido (`nlink > 1`) nunca se modifica en su sitio:
+//! - Antes de una escritura o truncado local, `make_private` lo separa
+//!   (copia o, si el otro enlace es solo el del almacén, retira ese enlace).
+//! - Antes de rellenar rangos que faltan, `release_shared` lo descarta: si a
+//!   un archivo compartido le faltan rangos es que su contenido remoto cambió.
+//!
+//! Las entradas del almacén que ya no enlaza ninguna caché (`nlink == 1`)
+//! solo ocupan disco: `collect_garbage` las borra tras cada liberación de
+//! caché y en cada ronda del verificador.
+
+use anyhow::Result;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::db::MetadataRepository;
+use crate::fuse::file_locks::FileLocks;
+
+/// Subdirectorio de `cache_dir` con el contenido por MD5
+pub const STORE_DIR: &str = "by-md5";
+
+/// Resultado de una recolección de entradas huérfanas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GarbageReport {
+    pub files: u64,
+    pub bytes: u64,
+}
+
+/// Contenido compartido entre archivos idénticos de la caché
+#[derive(Debug, Clone)]
+pub struct ContentStore {
+    dir: PathBuf,
+}
+
+impl ContentStore {
+    pub fn new(cache_dir: impl AsRef<Path>) -> Self {
+        Self { dir: cache_dir.as_ref().join(STORE_DIR) }
+    }
+
+    /// Ruta de la entrada de `md5` (None si no parece un MD5: no se usa como ruta)
+    fn entry_path(&self, md5: &str) -> Option<PathBuf> {
+        is_md5(md5).then(|| self.dir.join(md5.to_ascii_lowercase()))
+    }
+
+    /// Temporal en el mismo sistema de archivos, para enlazar y renombrar
+    async fn temp_path(&self) -> io::Result<PathBuf> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        Ok(self.dir.join(format!(".tmp-{}", uuid::Uuid::new_v4())))
+    }
+
+    /// Si otro archivo idéntico ya está en el almacén, lo enlaza como caché
+    /// completa de `inode` y retorna `true`: no hace falta descargar nada.
+    pub async fn adopt(&self, db: &MetadataRepository, inode: u64, size: u64, cache_path: &Path) -> Result<bool> {
+        // Con cambios locales la caché es la única copia de ese contenido
+        if size == 0 || db.is_dirty(inode).await? {
+            return Ok(false);
+        }
+        let Some(entry) = db.get_remote_md5(inode).await?.and_then(|md5| self.entry_path(&md5)) else {
+            return Ok(false);
+        };
+        match tokio::fs::metadata(&entry).await {
+            Ok(meta) if meta.len() == size => {}
+            _ => return Ok(false),
+        }
+
+        // Enlace temporal + rename: quien lea la caché parcial ve la vieja o la completa
+        let tmp = self.temp_path().await?;
+        match tokio::fs::hard_link(&entry, &tmp).await {
+            Ok(()) => {}
+            // La recolección la borró entre medias: se descarga como siempre
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        }
+        if let Err(e) = tokio::fs::rename(&tmp, cache_path).await {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e.into());
+        }
+        db.clear_cached_chunks(inode).await?;
+        db.add_cached_chunk(inode, 0, size - 1).await?;
+
+        tracing::info!("🔗 Contenido idéntico ya en caché: inode {} enlazado sin descargar ({} bytes)", inode, size);
+        Ok(true)
+    }
+
+    /// Publica la caché de `inode` si está completa y su MD5 coincide con el
+    /// remoto. Retorna `true` si se añadió una entrada al almacén.
+    pub async fn publish(&self, db: &MetadataRepository, file_locks: &FileLocks, inode: u64, cache_path: &Path) -> Result<bool> {
+        // Sin escrituras entre el MD5 y el enlace: se publicaría otro contenido bajo ese MD5
+        let _guard = file_locks.lock(inode).await;
+        if db.is_dirty(inode).await? {
+            return Ok(false);
+        }
+        let Some(md5) = db.get_remote_md5(inode).await? else {
+            return Ok(false);
+        };
+        let Some(entry) = self.entry_path(&md5) else {
+            return Ok(false);
+        };
+        if tokio::fs::try_exists(&entry).await.unwrap_or(false) {
+            return Ok(false);
+        }
+        let size = db.get_size(inode).await?.unwrap_or(0).max(0) as u64;
+        if size == 0 || !db.get_missing_ranges(inode, 0, size - 1).await?.is_empty() {
+            return Ok(false);
+        }
+
+        // Solo contenido verificado: una entrada dañada se serviría a todas las copias
+        let actual = crate::utils::hash::compute_file_md5(cache_path).await?;
+        if !actual.eq_ignore_ascii_case(&md5) {
+            tracing::debug!("MD5 de la caché de inode {} no coincide con el remoto: no se publica", inode);
+            return Ok(false);
+        }
+        Ok(self.link_entry(cache_path, &md5).await?)
+    }
+
+    /// Publica una caché cuyo MD5 ya se comprobó (ej. el verificador). Quien
+    /// llama tiene el lock de `inode` desde antes de calcular el MD5.
+    pub async fn publish_verified(&self, db: &MetadataRepository, inode: u64, cache_path: &Path, md5: &str) -> Result<bool> {
+        if db.is_dirty(inode).await? {
+            return Ok(false);
+        }
+        Ok(self.link_entry(cache_path, md5).await?)
+    }
+
+    /// Enlaza `cache_path` como entrada de `md5` en el almacén
+    async fn link_entry(&self, cache_path: &Path, md5: &str) -> io::Result<bool> {
+        let Some(entry) = self.entry_path(md5) else {
+            return Ok(false);
+        };
+        tokio::fs::create_dir_all(&self.dir).await?;
+        match tokio::fs::hard_link(cache_path, &entry).await {
+            Ok(()) => {
+                tracing::debug!("🔗 Contenido publicado en el almacén: {}", md5);
+                Ok(true)
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Separa la caché de `inode` de las demás copias antes de modificarla en
+    /// su sitio. Retorna `true` si estaba compartida.
+    pub async fn make_private(&self, db: &MetadataRepository, inode: u64, cache_path: &Path) -> Result<bool> {
+        let meta = match tokio::fs::metadata(cache_path).await {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        if meta.nlink() <= 1 {
+            return Ok(false);
+        }
+
+        // Si el único otro enlace es el del almacén, basta con retirarlo
+        if meta.nlink() == 2
+            && let Some(entry) = db.get_remote_md5(inode).await?.and_then(|md5| self.entry_path(&md5))
+            && let Ok(entry_meta) = tokio::fs::metadata(&entry).await
+            && (entry_meta.dev(), entry_meta.ino()) == (meta.dev(), meta.ino())
+        {
+            tokio::fs::remove_file(&entry).await?;
+            // Otra copia pudo adoptarlo entre medias: entonces hay que copiar igualmente
+            if tokio::fs::metadata(cache_path).await?.nlink() == 1 {
+                return Ok(true);
+            }
+        }
+
+        // Copia con clave propia: compartir clave y nonce reutilizaría el flujo al divergir
+        let tmp = self.temp_path().await?;
+        let copied = crate::utils::cache_crypto::copy_file(cache_path, &tmp).await;
+        let result = match copied {
+            Ok(()) => tokio::fs::rename(&tmp, cache_path).await,
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+        }
+        result?;
+        tracing::debug!("✂️ Caché compartida separada antes de modificarla: {:?}", cache_path);
+        Ok(true)
+    }
+
+    /// Borra las entradas que ya no enlaza ninguna caché
+    pub async fn collect_garbage(&self) -> io::Result<GarbageReport> {
+        let mut report = GarbageReport::default();
+        let mut entries = match tokio::fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(report),
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let Ok(meta) = entry.metadata().await else {
+                continue;
+            };
+            let name = entry.file_name();
+            let orphan = if is_md5(&name.to_string_lossy()) {
+                meta.nlink() == 1
+            } else {
+                // Temporales de una operación interrumpida (ej. un cierre brusco)
+                meta.modified().ok().and_then(|m| m.elapsed().ok()).is_some_and(|age| age.as_secs() > 3600)
+            };
+            if orphan && tokio::fs::remove_file(entry.path()).await.is_ok() {
+                report.files += 1;
+                report.bytes += meta.blocks() * 512;
+            }
+        }
+        if report.files > 0 {
+            tracing::info!("🧹 Almacén de contenido: {} entradas huérfanas borradas ({} bytes)", report.files, report.bytes);
+        }
+        Ok(report)
+    }
+}
+
+/// Descarta una caché compartida antes de rellenar los rangos que le faltan
+/// (su contenido remoto cambió: escribir en ella dañaría las demás copias).
+/// Retorna `true` si la descartó.
+pub async fn release_shared(db: &MetadataRepository, inode: u64, cache_path: &Path) -> Result<bool> {
+    match tokio::fs::metadata(cache_path).await {
+        Ok(meta) if meta.nlink() > 1 => {}
+        _ => return Ok(false),
+    }
+    db.clear_cached_chunks(inode).await?;
+    tokio::fs::remove_file(cache_path).await?;
+    tracing::debug!("✂️ Caché compartida de inode {} descartada antes de volver a descargar", inode);
+    Ok(true)
+}
+
+fn is_md5(name: &str) -> bool {
+    name.len() == 32 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const CONTENT: &[u8] = b"mismo contenido";
+
+    fn md5_of(data: &[u8]) -> String {
+        use md5::{Digest, Md5};
+        format!("{:x}", Md5::digest(data))
+    }
+
+    /// Dos archivos remotos idénticos; solo el primero está en caché
+    async fn fixture() -> (tempfile::TempDir, Arc<MetadataRepository>, ContentStore, u64, u64) {
+        let cache = tempfile::tempdir().unwrap();
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        let md5 = md5_of(CONTENT);
+        let mut inodes = Vec::new();
+        for gdrive_id in ["file_a", "file_b"] {
+            let inode = db.get_or_create_inode(gdrive_id).await.unwrap();
+            db.upsert_file_metadata(inode, CONTENT.len() as i64, 100, 0o644, false, None, true, false, true).await.unwrap();
+            db.set_remote_md5(inode, &md5).await.unwrap();
+            inodes.push(inode);
+        }
+        std::fs::write(cache.path().join("file_a"), CONTENT).unwrap();
+        db.add_cached_chunk(inodes[0], 0, CONTENT.len() as u64 - 1).await.unwrap();
+        let store = ContentStore::new(cache.path());
+        (cache, db, store, inodes[0], inodes[1])
+    }
+
+    #[test]
+    fn test_entry_path_rejects_non_md5() {
+        let store = ContentStore::new("/tmp/cache");
+        let md5 = md5_of(CONTENT);
+        assert_eq!(store.entry_path(&md5), Some(PathBuf::from("/tmp/cache/by-md5").join(&md5)));
+        assert_eq!(store.entry_path("../../etc/passwd"), None);
+        assert_eq!(store.entry_path(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_identical_file_adopts_published_content() {
+        let (cache, db, store, a, b) = fixture().await;
+        let size = CONTENT.len() as u64;
+
+        assert!(!store.adopt(&db, b, size, &cache.path().join("file_b")).await.unwrap(), "almacén vacío");
+        assert!(store.publish(&db, &FileLocks::default(), a, &cache.path().join("file_a")).await.unwrap());
+        assert!(!store.publish(&db, &FileLocks::default(), a, &cache.path().join("file_a")).await.unwrap(), "ya publicado");
+
+        assert!(store.adopt(&db, b, size, &cache.path().join("file_b")).await.unwrap());
+        assert_eq!(std::fs::read(cache.path().join("file_b")).unwrap(), CONTENT);
+        assert!(db.get_missing_ranges(b, 0, size - 1).await.unwrap().is_empty());
+        assert_eq!(std::fs::metadata(cache.path().join("file_a")).unwrap().nlink(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_publish_requires_matching_checksum() {
+        let (cache, db, store, a, _) = fixture().await;
+        std::fs::write(cache.path().join("file_a"), b"contenido corrupto").unwrap();
+
+        assert!(!store.publish(&db, &FileLocks::default(), a, &cache.path().join("file_a")).await.unwrap());
+        assert_eq!(store.collect_garbage().await.unwrap(), GarbageReport::default());
+    }
+
+    #[tokio::test]
+    async fn test_dirty_file_neither_publishes_nor_adopts() {
+        let (cache, db, store, a, b) = fixture().await;
+        db.mark_dirty(a).await.unwrap();
+        assert!(!store.publish(&db, &FileLocks::default(), a, &cache.path().join("file_a")).await.unwrap());
+
+        db.clear_dirty(a).await.unwrap();
+        store.publish(&db, &FileLocks::default(), a, &cache.path().join("file_a")).await.unwrap();
+        db.mark_dirty(b).await.unwrap();
+        assert!(!store.adopt(&db, b, CONTENT.len() as u64, &cache.path().join("file_b")).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_make_private_leaves_other_copies_intact() {
+        let (cache, db, store, a, b) = fixture().await;
+        let (path_a, path_b) = (cache.path().join("file_a"), cache.path().join("file_b"));
+        store.publish(&db, &FileLocks::default(), a, &path_a).await.unwrap();
+        store.adopt(&db, b, CONTENT.len() as u64, &path_b).await.unwrap();
+
+        assert!(store.make_private(&db, b, &path_b).await.unwrap());
+        std::fs::write(&path_b, b"editado").unwrap();
+
+        assert_eq!(std::fs::read(&path_a).unwrap(), CONTENT);
+        assert_eq!(std::fs::metadata(&path_b).unwrap().nlink(), 1);
+        assert!(!store.make_private(&db, b, &path_b).await.unwrap(), "ya era privado");
+    }
+
+    #[tokio::test]
+    async fn test_make_private_drops_store_link_instead_of_copying() {
+        let (cache, db, store, a, _) = fixture().await;
+        let path_a = cache.path().join("file_a");
+        store.publish(&db, &FileLocks::default(), a, &path_a).await.unwrap();
+        let ino = std::fs::metadata(&path_a).unwrap().ino();
+
+        assert!(store.make_private(&db, a, &path_a).await.unwrap());
+
+        assert_eq!(std::fs::metadata(&path_a).unwrap().ino(), ino, "sin copia");
+        assert!(!store.entry_path(&md5_of(CONTENT)).unwrap().exists());
+    }
+
+    #[tokio::test]
+    async fn test_collect_garbage_removes_unreferenced_entries() {
+        let (cache, db, store, a, _) = fixture().await;
+        let path_a = cache.path().join("file_a");
+        store.publish(&db, &FileLocks::default(), a, &path_a).await.unwrap();
+        assert_eq!(store.collect_garbage().await.unwrap().files, 0, "aún enlazada");
+
+        std::fs::remove_file(&path_a).unwrap();
+
+        assert_eq!(store.collect_garbage().await.unwrap().files, 1);
+        assert!(!store.entry_path(&md5_of(CONTENT)).unwrap().exists());
+    }
+
+    #[tokio::test]
+    async fn test_release_shared_discards_linked_cache() {
+        let (cache, db, store, a, _) = fixture().await;
+        let path_a = cache.path().join("file_a");
+        assert!(!release_shared(&db, a, &path_a).await.unwrap(), "no compartida");
+
+        store.publish(&db, &FileLocks::default(), a, &path_a).await.unwrap();
+        assert!(release_shared(&db, a, &path_a).await.unwrap());
+        assert!(!path_a.exists());
+        assert!(!db.has_any_chunks(a).await.unwrap());
+    }
+}
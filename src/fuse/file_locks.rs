@@ -0,0 +1,106 @@
+//! Locks por inode para los que escriben en el archivo de caché
+//!
+//! La lectura FUSE (chunks descargados), el Smart Streamer, el prefetcher y
+//! `write`/`setattr` abren el mismo `cache_dir/<gdrive_id>` por separado. Cada
+//! uno toma el lock del inode antes de tocarlo y no lo suelta hasta haber
+//! escrito y hecho `flush`, así que sus escrituras no se intercalan y un
+//! `flush`/`fsync` que toma el mismo lock ve todo lo escrito antes que él.
+//!
+//! También cuenta los handles FUSE abiertos por inode (`opened`/`closed`): la
+//! liberación de caché (`CacheManager`) no borra el contenido de un archivo
+//! que alguien tiene abierto.
+
+use dashmap::DashMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+/// Mapa inode → mutex, compartido entre FUSE y el prefetcher
+#[derive(Debug, Clone, Default)]
+pub struct FileLocks {
+    locks: Arc<DashMap<u64, Arc<Mutex<()>>>>,
+    /// Handles FUSE abiertos por inode (`open`/`create` hasta `release`)
+    open_handles: Arc<DashMap<u64, usize>>,
+}
+
+impl FileLocks {
+    /// Espera al lock de `inode`; se libera al soltar el guard
+    pub async fn lock(&self, inode: u64) -> OwnedMutexGuard<()> {
+        let lock = self.locks.entry(inode).or_default().clone();
+        lock.lock_owned().await
+    }
+
+    /// Olvida el lock de un inode sin escritores (al cerrar el archivo);
+    /// si alguien lo tiene o lo espera se conserva
+    pub fn forget(&self, inode: u64) {
+        self.locks.remove_if(&inode, |_, lock| Arc::strong_count(lock) == 1);
+    }
+
+    /// FUSE abrió un handle de `inode`
+    pub fn opened(&self, inode: u64) {
+        *self.open_handles.entry(inode).or_default() += 1;
+    }
+
+    /// FUSE cerró un handle de `inode`
+    pub fn closed(&self, inode: u64) {
+        self.open_handles.remove_if_mut(&inode, |_, count| {
+            *count = count.saturating_sub(1);
+            *count == 0
+        });
+    }
+
+    /// Hay algún handle FUSE abierto sobre `inode`
+    pub fn is_open(&self, inode: u64) -> bool {
+        self.open_handles.contains_key(&inode)
+    }
+
+    /// Olvida los handles de una sesión FUSE perdida (nunca recibirán `release`)
+    pub fn forget_open_handles(&self) {
+        self.open_handles.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_writers_of_same_inode_are_serialized() {
+        let locks = FileLocks::default();
+        let guard = locks.lock(1).await;
+
+        assert!(tokio::time::timeout(Duration::from_millis(50), locks.lock(1)).await.is_err());
+        let _other = tokio::time::timeout(Duration::from_millis(50), locks.lock(2)).await
+            .expect("otro inode no espera");
+
+        drop(guard);
+        let _again = tokio::time::timeout(Duration::from_secs(1), locks.lock(1)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_forget_keeps_held_locks() {
+        let locks = FileLocks::default();
+        let guard = locks.lock(1).await;
+
+        locks.forget(1);
+        assert!(tokio::time::timeout(Duration::from_millis(50), locks.lock(1)).await.is_err(), "sigue bloqueado");
+
+        drop(guard);
+        locks.forget(1);
+        assert!(locks.locks.is_empty());
+    }
+
+    #[test]
+    fn test_open_handles_are_counted() {
+        let locks = FileLocks::default();
+        locks.opened(1);
+        locks.opened(1);
+
+        locks.closed(1);
+        assert!(locks.clone().is_open(1), "queda un handle");
+        locks.closed(1);
+        assert!(!locks.is_open(1));
+        locks.closed(1);
+        assert!(!locks.is_open(1), "un release de más no deja la cuenta negativa");
+    }
+}
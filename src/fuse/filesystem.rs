@@ -3,51 +3,419 @@ use fuse3::{Errno, Result, Timestamp};
 use std::ffi::OsStr;
 use std::num::NonZeroU32;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tracing::{debug, error};
 use futures_util::stream::{self, BoxStream, StreamExt};
 use std::collections::HashMap;
 use dashmap::{DashMap, DashSet};
 
-use crate::db::MetadataRepository;
-use crate::gdrive::client::DriveClient;
+use crate::db::{MetadataRepository, UploadOp};
+use crate::gdrive::{DriveApi, DriveError};
+use crate::gdrive::id_pool::DriveIdPool;
+use crate::fuse::attr::FileAttributes;
+use crate::fuse::content_store::{self, ContentStore};
+use crate::fuse::file_locks::FileLocks;
+use crate::utils::quota::StorageQuota;
+use crate::fuse::journal::FsIntent;
+use crate::fuse::inflight::{Claim, InflightDownloads};
+use crate::fuse::interrupts::Interrupts;
+use crate::fuse::meta_cache::MetadataCache;
 use crate::fuse::shortcuts;
 use crate::gui::history::{ActionHistory, TransferOp};
+use crate::config::BackpressureMode;
+use crate::utils::backpressure;
+use crate::utils::ignore::IgnoreRules;
+use crate::sync::exclusions::Exclusions;
 
 
 /// Implementación del sistema de archivos FUSE para Google Drive
 pub const SHARED_INODE: u64 = 0xFFFF_FFFF_FFFF_FFFE; // Un inodo virtual muy alto
+/// Carpeta virtual `Starred/`: lista plana de los destacados de todo el árbol
+pub const STARRED_INODE: u64 = 0xFFFF_FFFF_FFFF_FFFD;
+/// Nombre de la carpeta virtual de destacados en el root
+pub const STARRED_NAME: &str = "Starred";
+/// Carpeta virtual `.Trash/`: elementos eliminados en local; borrarlos de
+/// aquí los elimina definitivamente de Drive
+pub const TRASH_INODE: u64 = 0xFFFF_FFFF_FFFF_FFFC;
+/// Nombre de la carpeta virtual de la papelera en el root
+pub const TRASH_NAME: &str = ".Trash";
+
+/// Nombres del root que ocupan las carpetas virtuales: un hijo real de Drive
+/// con el mismo nombre se expone desambiguado (`.Trash (2)`) para no quedar oculto
+pub fn is_reserved_root_name(name: &str) -> bool {
+    name == STARRED_NAME || name == TRASH_NAME
+}
+
+/// Carpetas virtuales del root (de solo lectura, sin fila en la DB)
+pub fn is_virtual_dir(inode: u64) -> bool {
+    inode == SHARED_INODE || inode == STARRED_INODE || inode == TRASH_INODE
+}
+
+/// Entradas que readdir antepone a los hijos: `.` y `..`, y en root las
+/// carpetas virtuales SHARED, Starred y .Trash
+fn fixed_dir_entries(parent: u64) -> Vec<(u64, String, bool)> {
+    let mut entries = vec![
+        (parent, ".".to_string(), true),
+        (if is_virtual_dir(parent) { 1 } else { 1.max(parent) }, "..".to_string(), true),
+    ];
+    if parent == 1 {
+        entries.push((SHARED_INODE, "SHARED".to_string(), true));
+        entries.push((STARRED_INODE, STARRED_NAME.to_string(), true));
+        entries.push((TRASH_INODE, TRASH_NAME.to_string(), true));
+    }
+    entries
+}
+
+/// Atributos de una carpeta virtual
+fn virtual_dir_attr(ino: u64) -> FileAttr {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    FileAttr {
+        ino,
+        size: 4096,
+        blocks: 8,
+        atime: Timestamp::new(now, 0),
+        mtime: Timestamp::new(now, 0),
+        ctime: Timestamp::new(now, 0),
+        kind: FileType::Directory,
+        perm: 0o755,
+        nlink: 2,
+        uid: unsafe { libc::getuid() },
+        gid: unsafe { libc::getgid() },
+        rdev: 0,
+        blksize: 4096,
+    }
+}
+
+/// Hijos por llamada a readdir/readdirplus en carpetas reales: el kernel lee
+/// por páginas y vuelve con el offset siguiente, así que una carpeta de 50k
+/// entradas no se carga entera en memoria en cada llamada
+const READDIR_PAGE_SIZE: u64 = 512;
+
+/// SMART BURST: una lectura que falla trae al menos el bloque alineado de 2MB
+/// que la contiene, para evitar micro-descargas asfixiantes
+const BURST_SIZE: u64 = 2 * 1024 * 1024;
+/// Ventana alineada que trae un fallo en lectura secuencial (cp, reproductor...)
+const SEQUENTIAL_WINDOW: u64 = 8 * 1024 * 1024;
+/// Tamaño máximo de cada petición de rango: la ventana se descarga en paralelo
+const PARALLEL_PART_SIZE: u64 = 2 * 1024 * 1024;
+/// Espera máxima de una escritura frenada por contrapresión antes de `ENOSPC`
+pub const THROTTLE_MAX_WAIT: Duration = Duration::from_secs(60);
+/// Cada cuánto `fsync`/`flush` consultan si el uploader terminó (`Config.fsync_upload_timeout_secs`)
+const UPLOAD_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Límites de `max_write` negociado en `init` (`Config.fuse_max_write_kb`)
+const MIN_MAX_WRITE: u32 = 4 * 1024;
+const MAX_MAX_WRITE: u32 = 16 * 1024 * 1024;
+
+/// Estado de subida del archivo (`queued`, `uploading`, `synced`, `error`, `ignored`)
+const XATTR_STATUS: &str = "user.gdrivexp.status";
+/// Mensaje del último error de subida (solo presente en estado `error`)
+const XATTR_ERROR: &str = "user.gdrivexp.error";
+/// Fecha de creación en Drive (RFC 3339, UTC): FUSE en Linux no transporta birthtime
+const XATTR_CREATED: &str = "user.gdrivexp.created";
+/// Descripción del archivo o carpeta en Drive (se edita desde el diálogo de propiedades)
+const XATTR_DESCRIPTION: &str = "user.gdrivexp.description";
+/// Color de la carpeta en Drive (`#rrggbb`)
+const XATTR_FOLDER_COLOR: &str = "user.gdrivexp.folder_color";
+/// Las copias comparten todo el estado (ver `new_session`)
+#[derive(Clone)]
 pub struct GDriveFS {
     db: Arc<MetadataRepository>,
-    drive_client: Arc<DriveClient>,
+    drive_client: Arc<dyn DriveApi>,
     cache_dir: std::path::PathBuf,
+    /// Contenido compartido entre archivos idénticos (`cache_dir/by-md5`)
+    content_store: ContentStore,
     history: Arc<ActionHistory>,
     /// Inodes que tienen un descargo activo en FUSE (Map de Inode -> (Option<Transfer ID>, Open Count, Session Bytes Read))
     fuse_downloads: Arc<tokio::sync::Mutex<HashMap<u64, (Option<u64>, usize, u64)>>>,
-    file_locks: Arc<DashMap<u64, Arc<tokio::sync::Mutex<()>>>>,
+    /// Serializa las escrituras en el archivo de caché de cada inode
+    file_locks: FileLocks,
+    /// Almacenamiento de Drive agotado (lo marca el uploader)
+    storage_quota: StorageQuota,
+    /// Rangos que se están descargando (lectura y Smart Streamer los comparten)
+    inflight: Arc<InflightDownloads>,
     /// Inodes que recibieron 403 permanente de Drive API (no reintentar)
     failed_downloads: Arc<DashSet<u64>>,
     /// Seguimiento de la última posición de lectura por inodo (para Smart Streamer)
     read_offsets: Arc<DashMap<u64, u64>>,
+    /// Caché en memoria de atributos y lookups (evita SQLite en getattr/lookup)
+    metadata_cache: Arc<MetadataCache>,
+    /// IDs de Drive reservados para `create`/`mkdir`
+    id_pool: Arc<DriveIdPool>,
+    /// `Config.ignore_patterns`: entradas nuevas que quedan solo en local
+    ignore_rules: Arc<IgnoreRules>,
+    /// `Config.excluded_folders`: no admiten entradas nuevas
+    exclusions: Arc<Exclusions>,
+    /// `Config.backpressure_mode`: esperar o fallar mientras haya contrapresión
+    backpressure_mode: BackpressureMode,
+    /// `Config.fsync_upload_timeout_secs`: `fsync`/`flush` esperan la subida (`None` = no esperan)
+    fsync_upload_timeout: Option<Duration>,
+    /// Próximo handle (`fh`) a devolver en `open`/`create`
+    next_fh: Arc<AtomicU64>,
+    /// Handles abiertos con `O_APPEND`: cada `write` escribe al final actual
+    append_handles: Arc<DashSet<u64>>,
+    /// Handles abiertos para escribir: solo su `flush` espera la subida
+    write_handles: Arc<DashSet<u64>>,
+    /// `Config.fuse_max_write_kb`: tamaño máximo de cada `write` que envía el kernel
+    max_write: NonZeroU32,
+    /// `Config.fuse_writeback_cache`: el kernel agrupa escrituras y resuelve `O_APPEND`
+    writeback_cache: bool,
+    /// `Config.read_timeout_secs`: espera máxima de `read` por un rango de Drive (`None` = sin límite)
+    read_timeout: Option<Duration>,
+    /// Lecturas esperando a Drive que `FUSE_INTERRUPT` puede cancelar
+    interrupts: Arc<Interrupts>,
 }
 
 impl GDriveFS {
     pub fn new(
         db: Arc<MetadataRepository>,
-        drive_client: Arc<DriveClient>,
+        drive_client: Arc<dyn DriveApi>,
         cache_dir: impl AsRef<std::path::Path>,
         history: Arc<ActionHistory>,
     ) -> Self {
         Self {
+            id_pool: Arc::new(DriveIdPool::new(drive_client.clone())),
             db,
             drive_client,
             cache_dir: cache_dir.as_ref().to_path_buf(),
+            content_store: ContentStore::new(cache_dir),
             history,
             fuse_downloads: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
-            file_locks: Arc::new(DashMap::new()),
+            file_locks: FileLocks::default(),
+            storage_quota: StorageQuota::default(),
+            inflight: Arc::new(InflightDownloads::default()),
             failed_downloads: Arc::new(DashSet::new()),
             read_offsets: Arc::new(DashMap::new()),
+            metadata_cache: Arc::new(MetadataCache::default()),
+            ignore_rules: Arc::new(IgnoreRules::default()),
+            exclusions: Arc::new(Exclusions::default()),
+            backpressure_mode: BackpressureMode::default(),
+            fsync_upload_timeout: None,
+            next_fh: Arc::new(AtomicU64::new(1)),
+            append_handles: Arc::new(DashSet::new()),
+            write_handles: Arc::new(DashSet::new()),
+            max_write: NonZeroU32::new(1024 * 1024).unwrap(), // 1MB
+            writeback_cache: false,
+            read_timeout: None,
+            interrupts: Arc::new(Interrupts::default()),
+        }
+    }
+
+    /// Comparte la caché de metadatos con otros componentes (syncer)
+    /// para que puedan invalidarla ante cambios remotos
+    pub fn with_metadata_cache(mut self, cache: Arc<MetadataCache>) -> Self {
+        self.metadata_cache = cache;
+        self
+    }
+
+    /// Patrones de archivos que se crean en local pero nunca se suben
+    pub fn with_ignore_rules(mut self, rules: Arc<IgnoreRules>) -> Self {
+        self.ignore_rules = rules;
+        self
+    }
+
+    /// Carpetas excluidas: crear o mover algo dentro responde `EACCES`
+    pub fn with_exclusions(mut self, exclusions: Arc<Exclusions>) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    /// Respuesta de las escrituras mientras `utils::backpressure` pida frenar
+    pub fn with_backpressure_mode(mut self, mode: BackpressureMode) -> Self {
+        self.backpressure_mode = mode;
+        self
+    }
+
+    /// `fsync`/`flush` de archivos con cambios esperan (como mucho `timeout`)
+    /// a que el uploader los suba y Drive confirme el MD5
+    pub fn with_fsync_upload_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.fsync_upload_timeout = timeout;
+        self
+    }
+
+    /// Tamaño máximo de escritura negociado con el kernel, acotado a [4 KB, 16 MB]
+    pub fn with_max_write(mut self, bytes: u32) -> Self {
+        self.max_write = NonZeroU32::new(bytes.clamp(MIN_MAX_WRITE, MAX_MAX_WRITE)).unwrap();
+        self
+    }
+
+    /// Debe coincidir con `MountOptions::write_back`: con la caché de escritura
+    /// el kernel ya traduce `O_APPEND` a offsets absolutos
+    pub fn with_writeback_cache(mut self, enabled: bool) -> Self {
+        self.writeback_cache = enabled;
+        self
+    }
+
+    /// Una lectura que espera a Drive más de `timeout` falla con `EIO`
+    pub fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Instancia para una sesión FUSE nueva (remontaje tras perder la
+    /// anterior). Comparte cachés y registros, pero los handles de la sesión
+    /// perdida nunca recibirán `release`: sus descargas se cierran aquí
+    pub async fn new_session(&self) -> Self {
+        let stale: Vec<_> = self.fuse_downloads.lock().await.drain().collect();
+        for (inode, (transfer_id, _, _)) in stale {
+            self.file_locks.forget(inode);
+            if let Some(t_id) = transfer_id {
+                self.history.complete_transfer(t_id);
+            }
+        }
+        self.append_handles.clear();
+        self.write_handles.clear();
+        self.file_locks.forget_open_handles();
+        self.clone()
+    }
+
+    /// Comparte el registro de descargas con el prefetcher y el uploader
+    pub fn with_inflight(mut self, inflight: Arc<InflightDownloads>) -> Self {
+        self.inflight = inflight;
+        self
+    }
+
+    /// Comparte los locks de caché con otros escritores (prefetcher)
+    pub fn with_file_locks(mut self, file_locks: FileLocks) -> Self {
+        self.file_locks = file_locks;
+        self
+    }
+
+    /// Rechaza con `ENOSPC` los datos nuevos mientras el uploader vea Drive lleno
+    pub fn with_storage_quota(mut self, storage_quota: StorageQuota) -> Self {
+        self.storage_quota = storage_quota;
+        self
+    }
+
+    /// `ENOSPC` mientras el almacenamiento de Drive se sepa lleno: los bytes
+    /// nuevos nunca llegarían a subirse
+    fn ensure_storage_available(&self, op: &str, inode: u64) -> Result<()> {
+        if self.storage_quota.is_full() {
+            debug!("💾 {} rechazado (ENOSPC): almacenamiento de Drive lleno, inode={}", op, inode);
+            return Err(Errno::from(libc::ENOSPC));
+        }
+        Ok(())
+    }
+
+    /// Deja pasar una escritura que añade datos si no hay contrapresión. En
+    /// modo `Throttle` espera a que el uploader o la limpieza de caché la
+    /// alivien (hasta `THROTTLE_MAX_WAIT`); en modo `Fail`, o si la espera se
+    /// agota, responde `ENOSPC`.
+    async fn admit_write(&self, op: &str, inode: u64) -> Result<()> {
+        let Some(pressure) = backpressure::current() else {
+            return Ok(());
+        };
+        if self.backpressure_mode == BackpressureMode::Throttle {
+            debug!("🚦 {} en espera por contrapresión ({:?}), inode={}", op, pressure, inode);
+            if backpressure::wait_for_relief(THROTTLE_MAX_WAIT).await {
+                return Ok(());
+            }
+        }
+        debug!("🚦 {} rechazado (ENOSPC) por contrapresión ({:?}), inode={}", op, pressure, inode);
+        Err(Errno::from(libc::ENOSPC))
+    }
+
+    /// Resuelve (parent, name) → inode pasando por la caché
+    async fn cached_lookup(&self, parent: u64, name: &str) -> anyhow::Result<Option<u64>> {
+        if let Some(inode) = self.metadata_cache.get_entry(parent, name) {
+            return Ok(Some(inode));
+        }
+        if self.metadata_cache.is_missing(parent, name) {
+            return Ok(None);
+        }
+        let inode = self.db.lookup(parent, name).await?;
+        match inode {
+            Some(inode) => self.metadata_cache.put_entry(parent, name, inode),
+            None => self.metadata_cache.put_missing(parent, name),
+        }
+        Ok(inode)
+    }
+
+    /// Atributos de un inode pasando por la caché
+    async fn cached_attrs(&self, inode: u64) -> anyhow::Result<FileAttributes> {
+        if let Some(attrs) = self.metadata_cache.get_attrs(inode) {
+            return Ok(attrs);
+        }
+        let attrs = self.db.get_attrs(inode).await?;
+        self.metadata_cache.put_attrs(inode, attrs.clone());
+        Ok(attrs)
+    }
+
+    /// Registra en `dir_access_log` el directorio que usa el usuario (el propio
+    /// `inode` si es directorio, si no su padre) para el prefetcher. Corre en
+    /// background y omite los accesos de este mismo proceso (mirror, verificador).
+    fn note_dir_access(&self, req: &Request, inode: u64, is_dir: bool) {
+        if is_virtual_dir(inode) || std::path::Path::new(&format!("/proc/self/task/{}", req.pid)).exists() {
+            return;
+        }
+
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            let dir = if is_dir {
+                Some(inode)
+            } else {
+                db.get_parent_inode(inode).await.ok().flatten()
+            };
+            if let Some(dir) = dir
+                && let Err(e) = db.record_dir_access(dir).await
+            {
+                tracing::debug!("No se pudo registrar acceso a directorio {}: {:?}", dir, e);
+            }
+        });
+    }
+
+    /// `unlink`/`rmdir` dentro de `.Trash`: pide la eliminación definitiva del
+    /// elemento (ver `MetadataRepository::request_permanent_delete`). El
+    /// uploader llama a Drive; aquí solo desaparece de la papelera local.
+    async fn purge_trashed(&self, name: &str, is_dir: bool) -> Result<()> {
+        let eio = |_| Errno::from(libc::EIO);
+        let inode = self.db.lookup_trashed(name).await.map_err(eio)?
+            .ok_or(Errno::from(libc::ENOENT))?;
+        match self.db.is_dir(inode).await.map_err(eio)? {
+            Some(dir) if dir == is_dir => {}
+            Some(true) => return Err(Errno::from(libc::EISDIR)),
+            Some(false) => return Err(Errno::from(libc::ENOTDIR)),
+            None => return Err(Errno::from(libc::ENOENT)),
+        }
+        if !self.db.request_permanent_delete(inode).await.map_err(eio)? {
+            return Err(Errno::from(libc::ENOENT));
+        }
+        self.metadata_cache.invalidate_inode(inode);
+        tracing::info!("🔥 {} pendiente de eliminación definitiva (inode {})", name, inode);
+        Ok(())
+    }
+
+    /// `EACCES` si `parent` no admite entradas nuevas: una carpeta excluida
+    /// (`Config.excluded_folders`), donde el escaneo volvería a retirar lo
+    /// subido, o el directorio virtual de "Ordenadores", que no existe en Drive
+    async fn ensure_accepts_entries(&self, parent: u64) -> Result<()> {
+        let gdrive_id = self.db.get_gdrive_id(parent).await.map_err(|_| Errno::from(libc::EIO))?;
+        let Some(gdrive_id) = gdrive_id else {
+            return Ok(());
+        };
+        if gdrive_id == crate::db::COMPUTERS_GDRIVE_ID {
+            tracing::warn!("🚫 Los equipos de Ordenadores se añaden desde Drive, no desde el montaje");
+            return Err(Errno::from(libc::EACCES));
+        }
+        if self.exclusions.covers(&gdrive_id) {
+            tracing::warn!("🚫 Carpeta excluida (inode {}): no admite contenido nuevo", parent);
+            return Err(Errno::from(libc::EACCES));
+        }
+        Ok(())
+    }
+
+    /// `EACCES` si Drive no permite enviar el inode a la papelera (`canTrash`),
+    /// antes de borrarlo localmente: evita que el uploader falle y lo restaure
+    async fn ensure_trashable(&self, inode: u64, name: &str) -> Result<()> {
+        let can_trash = self.db.can_trash(inode).await.map_err(|_| Errno::from(libc::EIO))?;
+        if !can_trash {
+            tracing::warn!("🔒 Sin permiso para eliminar '{}' en Drive (inode {})", name, inode);
+            return Err(Errno::from(libc::EACCES));
         }
+        Ok(())
     }
 }
 
@@ -58,9 +426,15 @@ impl Filesystem for GDriveFS {
 
     // Inicialización del sistema de archivos
     async fn init(&self, _req: Request) -> Result<ReplyInit> {
-        tracing::info!("Sistema de archivos inicializado");
+        // fuse3 ya acepta FUSE_ASYNC_READ y devuelve el max_readahead que ofrece el kernel
+        tracing::info!(
+            "Sistema de archivos inicializado (max_write={} KB, writeback={})",
+            self.max_write.get() / 1024,
+            self.writeback_cache
+        );
+        self.id_pool.refill_in_background();
         Ok(ReplyInit {
-            max_write: NonZeroU32::new(1024 * 1024).unwrap(), // 1MB
+            max_write: self.max_write,
         })
     }
 
@@ -68,6 +442,14 @@ impl Filesystem for GDriveFS {
         debug!("Sistema de archivos desmontado");
     }
 
+    // FUSE_INTERRUPT: solo `read` se puede cancelar (las demás operaciones son locales)
+    async fn interrupt(&self, _req: Request, unique: u64) -> Result<()> {
+        if self.interrupts.interrupt(unique) {
+            debug!("✋ FUSE_INTERRUPT para la petición {}", unique);
+        }
+        Ok(())
+    }
+
     // Listar directorio (readdir)
     async fn readdir(
         &self,
@@ -76,80 +458,54 @@ impl Filesystem for GDriveFS {
         _fh: u64,
         offset: i64,
     ) -> Result<ReplyDirectory<Self::DirEntryStream<'_>>> {
+        let _timer = crate::metrics::fuse_op("readdir");
         tracing::trace!("👁️ readdir: parent={} offset={}", parent, offset);
+        let offset = offset.max(0);
 
-        // 1. Verificación temprana y carga de datos
-        // Caso especial: SHARED_INODE
-        let (children, child_count) = if parent == SHARED_INODE {
+        // 1. Entradas fijas: . y .. (+ SHARED, Starred y .Trash en root)
+        let fixed = fixed_dir_entries(parent);
+        let child_offset = (offset as u64).saturating_sub(fixed.len() as u64);
+
+        // 2. Hijos desde `child_offset`: las carpetas virtuales se listan enteras;
+        //    las reales, una página de la DB (readdir vuelve con el offset siguiente)
+        let children: Vec<(u64, String, bool)> = if parent == SHARED_INODE {
             let items = self.db.list_non_owned_root_children().await
                 .map_err(|e| {
                     error!("❌ Error listando compartidos: {}", e);
                     Errno::from(libc::EIO)
                 })?;
-            let count = items.len() as u64;
-            let simplified = items.into_iter().map(|(inode, name, is_dir, _, _)| (inode, name, is_dir)).collect::<Vec<_>>();
-            (simplified, count)
+            items.into_iter().skip(child_offset as usize).map(|(inode, name, is_dir, _, _)| (inode, name, is_dir)).collect()
+        } else if parent == STARRED_INODE {
+            let items = self.db.list_starred().await
+                .map_err(|e| {
+                    error!("❌ Error listando destacados: {}", e);
+                    Errno::from(libc::EIO)
+                })?;
+            items.into_iter().skip(child_offset as usize).map(|(inode, name, is_dir, _, _)| (inode, name, is_dir)).collect()
+        } else if parent == TRASH_INODE {
+            let items = self.db.list_trashed().await
+                .map_err(|e| {
+                    error!("❌ Error listando la papelera: {}", e);
+                    Errno::from(libc::EIO)
+                })?;
+            items.into_iter().skip(child_offset as usize).map(|(inode, name, is_dir, _, _)| (inode, name, is_dir)).collect()
         } else {
-            let _count = match self.db.count_children(parent).await {
-                Ok(c) => c,
-                Err(e) => {
-                    error!("❌ Error contando hijos de {}: {}", parent, e);
-                    return Err(Errno::from(libc::EIO));
-                }
-            };
-            
-            let mut items = match self.db.list_children(parent).await {
-                Ok(c) => c,
-                Err(e) => {
+            // En root la DB ya omite lo que no es propio (va en SHARED)
+            self.db.list_children_page(parent, child_offset, READDIR_PAGE_SIZE).await
+                .map_err(|e| {
                     error!("❌ Error listando hijos de {}: {}", parent, e);
-                    return Err(Errno::from(libc::EIO));
-                }
-            };
-
-            // Si es root, filtrar los que NO son propios
-            if parent == 1 {
-                let mut filtered = Vec::new();
-                for (inode, name, is_dir) in items {
-                    let attrs = self.db.get_attrs(inode).await.map_err(|_| Errno::from(libc::EIO))?;
-                    if attrs.owned_by_me {
-                        filtered.push((inode, name, is_dir));
-                    }
-                }
-                items = filtered;
-            }
-
-            let real_count = items.len() as u64;
-            (items, real_count)
+                    Errno::from(libc::EIO)
+                })?
         };
-        
-        // Total = hijos + 2 (por . y ..) + (1 si es root por el SHARED)
-        let mut total_entries = child_count + 2;
-        if parent == 1 {
-            total_entries += 1;
-        }
-        
-        // Short-circuit: si ya consumieron todo, retornar vacío sin consultar DB
-        if offset as u64 >= total_entries {
-            tracing::trace!("📊 readdir short-circuit: offset={} >= total={}", offset, total_entries);
-            return Ok(ReplyDirectory {
-                entries: Box::pin(stream::empty())
-            });
-        }
 
-        // 3. Construir lista completa SIEMPRE (. y .. + hijos + SHARED)
-        let mut entries: Vec<(u64, String, bool)> = Vec::with_capacity(children.len() + 3);
-        entries.push((parent, ".".to_string(), true));
-        entries.push((if parent == SHARED_INODE { 1 } else { 1.max(parent) }, "..".to_string(), true));
-        
-        if parent == 1 {
-            entries.push((SHARED_INODE, "SHARED".to_string(), true));
-        }
-
-        entries.extend(children);
+        // 3. Las fijas que quedan tras el offset, seguidas de la página de hijos
+        let entries: Vec<(u64, String, bool)> = fixed.into_iter()
+            .skip(offset as usize)
+            .chain(children)
+            .collect();
 
-        // 4. Aplicar offset y generar stream
+        // 4. Generar stream: el offset de cada entrada es su posición absoluta + 1
         let stream = stream::iter(entries)
-            .skip(offset as usize)
             .enumerate()
             .map(move |(index, (inode, name, is_dir))| {
                 Ok(DirectoryEntry {
@@ -167,32 +523,20 @@ impl Filesystem for GDriveFS {
 
     // Buscar un archivo en un directorio (ls)
     async fn lookup(&self, _req: Request, parent: u64, name: &OsStr) -> Result<ReplyEntry> {
+        let _timer = crate::metrics::fuse_op("lookup");
         let name_str = name.to_str().ok_or(Errno::from(libc::EINVAL))?;
         
-        // Caso especial: Lookup de SHARED en el root
-        if parent == 1 && name_str == "SHARED" {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as i64;
-            
+        // Caso especial: Lookup de las carpetas virtuales en el root
+        let virtual_inode = match name_str {
+            "SHARED" => Some(SHARED_INODE),
+            STARRED_NAME => Some(STARRED_INODE),
+            TRASH_NAME => Some(TRASH_INODE),
+            _ => None,
+        };
+        if parent == 1 && let Some(ino) = virtual_inode {
             return Ok(ReplyEntry {
                 ttl: Duration::from_secs(3600),
-                attr: FileAttr {
-                    ino: SHARED_INODE,
-                    size: 4096,
-                    blocks: 8,
-                    atime: Timestamp::new(now, 0),
-                    mtime: Timestamp::new(now, 0),
-                    ctime: Timestamp::new(now, 0),
-                    kind: FileType::Directory,
-                    perm: 0o755,
-                    nlink: 2,
-                    uid: unsafe { libc::getuid() },
-                    gid: unsafe { libc::getgid() },
-                    rdev: 0,
-                    blksize: 4096,
-                },
+                attr: virtual_dir_attr(ino),
                 generation: 0,
             });
         }
@@ -208,8 +552,16 @@ impl Filesystem for GDriveFS {
         // Si el padre es SHARED_INODE, buscamos en el root (1) pero verificamos que sea SHARED
         let search_parent = if parent == SHARED_INODE { 1 } else { parent };
 
-        let inode = self.db.lookup(search_parent, lookup_name)
-            .await
+        // Starred/ y .Trash/ son listas planas: se busca por nombre entre sus elementos
+        let found = if parent == STARRED_INODE {
+            self.db.lookup_starred(lookup_name).await
+        } else if parent == TRASH_INODE {
+            self.db.lookup_trashed(lookup_name).await
+        } else {
+            self.cached_lookup(search_parent, lookup_name).await
+        };
+
+        let inode = found
             .map_err(|e| {
                 error!("Error en lookup: {}", e);
                 Errno::from(libc::EIO)
@@ -217,7 +569,7 @@ impl Filesystem for GDriveFS {
             .ok_or(Errno::from(libc::ENOENT))?;
 
         // Obtener atributos del archivo
-        let attrs = self.db.get_attrs(inode)
+        let attrs = self.cached_attrs(inode)
             .await
             .map_err(|e| {
                 error!("Error obteniendo atributos para inode {}: {}", inode, e);
@@ -262,44 +614,24 @@ impl Filesystem for GDriveFS {
         Ok(ReplyEntry {
             ttl: Duration::from_secs(1),
             attr: file_attr,
-            generation: 0,
+            generation: attrs.generation as u64,
         })
     }
 
     // Obtener atributos de un archivo (stat)
     async fn getattr(&self, _req: Request, inode: u64, _fh: Option<u64>, _flags: u32) -> Result<ReplyAttr> {
+        let _timer = crate::metrics::fuse_op("getattr");
         // tracing::info!("📋 GETATTR called: inode={}", inode);
 
-        // Caso especial: Inodo virtual SHARED
-        if inode == SHARED_INODE {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as i64;
-            
-            let attr = FileAttr {
-                ino: SHARED_INODE,
-                size: 4096,
-                blocks: 8,
-                atime: Timestamp::new(now, 0),
-                mtime: Timestamp::new(now, 0),
-                ctime: Timestamp::new(now, 0),
-                kind: FileType::Directory,
-                perm: 0o755,
-                nlink: 2,
-                uid: unsafe { libc::getuid() },
-                gid: unsafe { libc::getgid() },
-                rdev: 0,
-                blksize: 4096,
-            };
-
+        // Caso especial: Inodos virtuales SHARED y Starred
+        if is_virtual_dir(inode) {
             return Ok(ReplyAttr {
                 ttl: Duration::from_secs(3600), // Directorio virtual estable
-                attr,
+                attr: virtual_dir_attr(inode),
             });
         }
 
-        let attrs = self.db.get_attrs(inode)
+        let attrs = self.cached_attrs(inode)
             .await
             .map_err(|e| {
                 // Si el inodo es 1 (root) y no está en DB, devolver valores por defecto
@@ -360,28 +692,76 @@ impl Filesystem for GDriveFS {
         _req: Request,
         inode: u64,
         name: &OsStr,
-        _size: u32,
+        size: u32,
     ) -> Result<ReplyXAttr> {
+        let _timer = crate::metrics::fuse_op("getxattr");
         let name_str = name.to_str().unwrap_or("???");
         tracing::debug!("🏷️ getxattr called: inode={} name={}", inode, name_str);
+
+        let value = match name_str {
+            XATTR_STATUS => {
+                let state = self.db.sync_state(inode).await
+                    .map_err(|_| Errno::from(libc::EIO))?
+                    .unwrap_or_default();
+                Some(state.status.as_str().to_string())
+            }
+            XATTR_ERROR => self.db.upload_error(inode).await
+                .map_err(|_| Errno::from(libc::EIO))?,
+            XATTR_CREATED => self.cached_attrs(inode).await
+                .ok()
+                .and_then(|attrs| attrs.created_time)
+                .and_then(format_created_time),
+            XATTR_DESCRIPTION => self.db.get_description(inode).await
+                .map_err(|_| Errno::from(libc::EIO))?,
+            XATTR_FOLDER_COLOR => self.db.get_folder_color(inode).await
+                .map_err(|_| Errno::from(libc::EIO))?,
+            _ => None,
+        };
+
         // Retornar ENODATA (No attribute) en lugar de ENOSYS (Not implemented)
         // Muchas apps fallan si reciben ENOSYS.
-        Err(Errno::from(libc::ENODATA))
+        let value = value.ok_or(Errno::from(libc::ENODATA))?;
+        xattr_reply(value.into_bytes(), size)
     }
 
     async fn listxattr(
         &self,
         _req: Request,
         inode: u64,
-        _size: u32,
+        size: u32,
     ) -> Result<ReplyXAttr> {
         tracing::debug!("🏷️ listxattr called: inode={}", inode);
-        // Retornar lista vacía (0 bytes) - ReplyXAttr es un Enum
-        if _size == 0 {
-             Ok(ReplyXAttr::Size(0))
-        } else {
-             Ok(ReplyXAttr::Data(vec![].into()))
+        if is_virtual_dir(inode) {
+            return xattr_reply(Vec::new(), size);
+        }
+        let has_error = self.db.upload_error(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?
+            .is_some();
+        let has_created = self.cached_attrs(inode).await
+            .is_ok_and(|attrs| attrs.created_time.is_some());
+        let has_description = self.db.get_description(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?
+            .is_some();
+        let has_folder_color = self.db.get_folder_color(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?
+            .is_some();
+
+        // Lista de nombres terminados en NUL
+        let mut names = Vec::new();
+        for (name, present) in [
+            (XATTR_STATUS, true),
+            (XATTR_ERROR, has_error),
+            (XATTR_CREATED, has_created),
+            (XATTR_DESCRIPTION, has_description),
+            (XATTR_FOLDER_COLOR, has_folder_color),
+        ] {
+            if !present {
+                continue;
+            }
+            names.extend_from_slice(name.as_bytes());
+            names.push(0);
         }
+        xattr_reply(names, size)
     }
 
     async fn setxattr(
@@ -393,6 +773,7 @@ impl Filesystem for GDriveFS {
         _flags: u32,
         _position: u32,
     ) -> Result<()> {
+        let _timer = crate::metrics::fuse_op("setxattr");
         let name_str = name.to_str().unwrap_or("???");
         tracing::warn!("🏷️ setxattr called (IGNORED): inode={} name={}", inode, name_str);
         // Ignorar silenciosamente o dar error de permiso?
@@ -413,20 +794,22 @@ impl Filesystem for GDriveFS {
 
 
     // Abrir directorio (requerido antes de readdir)
-    async fn opendir(&self, _req: Request, inode: u64, _flags: u32) -> Result<ReplyOpen> {
+    async fn opendir(&self, req: Request, inode: u64, _flags: u32) -> Result<ReplyOpen> {
+        let _timer = crate::metrics::fuse_op("opendir");
         tracing::trace!("📂 opendir: inode={}", inode);
         
-        // Caso especial: SHARED
-        if inode == SHARED_INODE {
+        // Caso especial: carpetas virtuales
+        if is_virtual_dir(inode) {
             return Ok(ReplyOpen { fh: 0, flags: 0 });
         }
 
         // Verificar que el inode existe y es un directorio
-        match self.db.get_attrs(inode).await {
+        match self.cached_attrs(inode).await {
             Ok(attrs) => {
                 if !attrs.is_dir {
                     return Err(Errno::from(libc::ENOTDIR));
                 }
+                self.note_dir_access(&req, inode, true);
                 Ok(ReplyOpen { fh: 0, flags: 0 })
             }
             Err(_) => Err(Errno::from(libc::ENOENT)),
@@ -441,7 +824,8 @@ impl Filesystem for GDriveFS {
 
 
     // Abrir archivo (open)
-    async fn open(&self, _req: Request, inode: u64, _flags: u32) -> Result<ReplyOpen> {
+    async fn open(&self, req: Request, inode: u64, flags: u32) -> Result<ReplyOpen> {
+        let _timer = crate::metrics::fuse_op("open");
         
         // tracing::warn!("🔓 OPEN request: inode={} flags={}", inode, flags);
 
@@ -454,6 +838,9 @@ impl Filesystem for GDriveFS {
             }
         };
 
+        self.note_dir_access(&req, inode, attrs.is_dir);
+        let fh = self.open_handle(inode, flags);
+
         // Filtered detail logging
         let mime_lower = attrs.mime_type.as_deref().unwrap_or("").to_lowercase();
         let is_media = mime_lower.starts_with("video/") || mime_lower.starts_with("audio/");
@@ -472,7 +859,7 @@ impl Filesystem for GDriveFS {
             // Guard: No reintentar descargas que ya fallaron con 403
             if self.failed_downloads.contains(&inode) {
                 tracing::debug!("🚫 open() ignorado para inode={} (descarga 403 permanente)", inode);
-                return Ok(ReplyOpen { fh: 0, flags: 0 });
+                return Ok(ReplyOpen { fh, flags: 0 });
             }
 
             // Sync FD tracking
@@ -484,7 +871,7 @@ impl Filesystem for GDriveFS {
             }
         }
         
-        Ok(ReplyOpen { fh: 0, flags: 0 }) 
+        Ok(ReplyOpen { fh, flags: 0 })
     }
 
     // Cerrar archivo (release)
@@ -492,13 +879,17 @@ impl Filesystem for GDriveFS {
         &self,
         _req: Request,
         inode: u64,
-        _fh: u64,
+        fh: u64,
         _flags: u32,
         _lock_owner: u64,
         _flush: bool,
     ) -> Result<()> {
+        let _timer = crate::metrics::fuse_op("release");
         tracing::trace!("release: inode={}", inode);
-        
+        self.append_handles.remove(&fh);
+        self.write_handles.remove(&fh);
+        self.file_locks.closed(inode);
+
         let mut fuse_downloads = self.fuse_downloads.lock().await;
         let mut should_remove = false;
         let mut completed_transfer_id = None;
@@ -511,6 +902,7 @@ impl Filesystem for GDriveFS {
         }
         if should_remove {
             fuse_downloads.remove(&inode);
+            self.file_locks.forget(inode);
             if let Some(t_id) = completed_transfer_id {
                 self.history.complete_transfer(t_id);
                 tracing::debug!("✅ Transfer FUSE completado y removido de la interfaz: inode={}", inode);
@@ -527,13 +919,19 @@ impl Filesystem for GDriveFS {
         &self,
         _req: Request,
         inode: u64,
-        _fh: u64,
+        fh: u64,
         _lock_owner: u64,
     ) -> Result<()> {
+        let _timer = crate::metrics::fuse_op("flush");
         tracing::trace!("flush: inode={}", inode);
-        // Los datos ya se persisten sincrónicamente en write(),
-        // el upload a GDrive es asíncrono vía uploader
-        Ok(())
+        // Cada write() ya hizo flush antes de soltar el lock del inode: tomarlo
+        // espera a las escrituras en curso. El upload a GDrive es asíncrono vía
+        // uploader, salvo con `fsync_upload_timeout` al cerrar un handle de escritura
+        drop(self.file_locks.lock(inode).await);
+        if !self.write_handles.contains(&fh) {
+            return Ok(());
+        }
+        self.wait_for_upload("flush", inode).await
     }
 
     // Sincronizar datos a disco
@@ -542,35 +940,40 @@ impl Filesystem for GDriveFS {
         _req: Request,
         inode: u64,
         _fh: u64,
-        _datasync: bool,
+        datasync: bool,
     ) -> Result<()> {
+        let _timer = crate::metrics::fuse_op("fsync");
         tracing::trace!("fsync: inode={}", inode);
-        // Los datos ya se persisten sincrónicamente en write(),
-        // el upload a GDrive es asíncrono vía uploader
-        Ok(())
+        // Tras las escrituras en curso, llevar la caché al disco.
+        // El upload a GDrive es asíncrono vía uploader, salvo con `fsync_upload_timeout`
+        let cache_guard = self.file_locks.lock(inode).await;
+        self.sync_cache_file(inode, datasync).await
+            .map_err(|e| {
+                error!("Error en fsync de la caché de inode {}: {}", inode, e);
+                Errno::from(libc::EIO)
+            })?;
+        // El uploader toma el mismo lock para completar la caché
+        drop(cache_guard);
+        self.wait_for_upload("fsync", inode).await
     }
 
     // Leer contenido (read) - CON CACHÉ LOCAL
     async fn read(
         &self,
-        _req: Request,
+        req: Request,
         inode: u64,
         _fh: u64,
         offset: u64,
         size: u32,
     ) -> Result<ReplyData> {
+        let _timer = crate::metrics::fuse_op("read");
         // 1. Obtener el gdrive_id del archivo, mime_type, tamaño y shortcut_target_id
-        let (raw_gdrive_id, mime_type, file_size, shortcut_target_id) = match sqlx::query_as::<_, (String, Option<String>, i64, Option<String>)>(
-            "SELECT i.gdrive_id, a.mime_type, a.size, a.shortcut_target_id
-             FROM inodes i
-             LEFT JOIN attrs a ON i.inode = a.inode
-             WHERE i.inode = ?"
-        )
-            .bind(inode as i64)
-            .fetch_one(self.db.pool())
-            .await
-        {
-            Ok(row) => row,
+        let (raw_gdrive_id, mime_type, file_size, shortcut_target_id) = match self.db.get_content_source(inode).await {
+            Ok(Some(row)) => row,
+            Ok(None) => {
+                error!("Error buscando info para inode {}: no existe", inode);
+                return Err(Errno::from(libc::ENOENT));
+            }
             Err(e) => {
                 error!("Error buscando info para inode {}: {}", inode, e);
                 return Err(Errno::from(libc::ENOENT));
@@ -585,8 +988,10 @@ impl Filesystem for GDriveFS {
              // tracing::info!("📖 READ called: inode={} offset={} size={}", inode, offset, size);
         }
 
-        // GUARDAR OFFSET DE LECTURA para el Smart Streamer
-        self.read_offsets.insert(inode, offset + size as u64);
+        // GUARDAR OFFSET DE LECTURA para el Smart Streamer. Si la lectura
+        // continúa justo donde terminó la anterior, es secuencial.
+        let previous_end = self.read_offsets.insert(inode, offset + size as u64);
+        let sequential = offset > 0 && previous_end == Some(offset);
 
         // 2. Si es archivo de Google Workspace, generar .desktop file on-the-fly
         if let Some(ref mime) = mime_type {
@@ -646,8 +1051,7 @@ impl Filesystem for GDriveFS {
                         
                         // Oficializamos la transferencia
                         let db = self.db.clone();
-                        let file_name = sqlx::query_scalar::<_, String>("SELECT name FROM dentry WHERE child_inode = ? LIMIT 1")
-                            .bind(inode as i64).fetch_optional(db.pool()).await.unwrap_or_default().unwrap_or_else(|| format!("file_{}", inode));
+                        let file_name = db.get_file_name(inode).await.unwrap_or_default().unwrap_or_else(|| format!("file_{}", inode));
                         
                         let op = if is_media { TransferOp::Stream } else { TransferOp::Download };
                         let new_t_id = self.history.start_transfer(&file_name, op, file_size as u64);
@@ -661,63 +1065,52 @@ impl Filesystem for GDriveFS {
                 let drive_client = self.drive_client.clone();
                 let cache_path_bg = cache_path.clone();
                 let file_locks = self.file_locks.clone();
+                let inflight = self.inflight.clone();
                 let history = self.history.clone();
                 let fuse_downloads_clone = self.fuse_downloads.clone();
                 let failed_downloads = self.failed_downloads.clone();
                 let read_offsets = self.read_offsets.clone();
                 let gd_id_bg = gdrive_id.clone();
                 let db_clone = self.db.clone();
+                let content_store = self.content_store.clone();
 
                 tokio::spawn(async move {
                     let result = Self::start_background_download_stream(
                         db_clone, drive_client, inode, gd_id_bg, cache_path_bg, file_size as u64,
-                        file_locks, history, fuse_downloads_clone, read_offsets, is_media
+                        file_locks, inflight, history, fuse_downloads_clone, read_offsets, is_media, content_store
                     ).await;
 
-                    if let Err(ref e) = result {
-                        let err_msg = format!("{}", e);
-                        if err_msg.contains("403") && err_msg.contains("cannot") {
-                            failed_downloads.insert(inode);
-                        }
+                    if let Err(ref e) = result
+                        && is_forbidden_download(e)
+                    {
+                        failed_downloads.insert(inode);
                     }
                 });
                 tracing::info!("🚀 Heurística de volumen disparada (>1MB reales leídos). Smart Streamer iniciado para inode={}", inode);
             }
 
-            // Descargar solo lo necesario (con reintento tras corrección 416)
-            let mut effective_file_size = file_size as u64;
-            let mut attempt = 0u8;
-            loop {
-                match self.ensure_range_cached(inode, &gdrive_id, offset, size, effective_file_size).await {
-                    Ok(()) => break,
-                    Err(e) => {
-                        let err_msg = format!("{}", e);
-                        if err_msg.contains("416") && attempt == 0 {
-                            // ensure_range_cached ya corrigió attrs.size en DB. Re-leer y reintentar.
-                            if let Ok(new_size) = sqlx::query_scalar::<_, i64>(
-                                "SELECT size FROM attrs WHERE inode = ?"
-                            )
-                            .bind(inode as i64)
-                            .fetch_one(self.db.pool())
-                            .await
-                            {
-                                effective_file_size = new_size as u64;
-                                if effective_file_size == 0 || offset >= effective_file_size {
-                                    return Ok(ReplyData { data: vec![].into() });
-                                }
-                                tracing::info!("🔄 Reintentando descarga para inode {} con tamaño corregido: {}", inode, effective_file_size);
-                                attempt += 1;
-                                continue;
-                            }
-                        }
-                        if err_msg.contains("403") && err_msg.contains("cannot") {
-                            self.failed_downloads.insert(inode);
-                            tracing::warn!("🚫 Inode {} marcado como descarga prohibida (403 en read)", inode);
-                        }
-                        error!("Error descargando chunk para inode {}: {}", inode, e);
-                        return Err(Errno::from(libc::EIO));
-                    }
+            // Descargar solo lo necesario: abandonar si Drive no responde a
+            // tiempo o el kernel interrumpe la petición (Ctrl+C, vista previa cerrada)
+            let interrupt = self.interrupts.register(req.unique);
+            let fetch = self.cache_range_for_read(inode, &gdrive_id, offset, size, file_size as u64, sequential);
+            let fetch = async {
+                match self.read_timeout {
+                    Some(timeout) => tokio::time::timeout(timeout, fetch).await.unwrap_or_else(|_| {
+                        error!("⏱️ read() de inode {} sin respuesta de Drive en {:?}", inode, timeout);
+                        Err(Errno::from(libc::EIO))
+                    }),
+                    None => fetch.await,
+                }
+            };
+            let in_range = tokio::select! {
+                result = fetch => result?,
+                _ = interrupt.interrupted() => {
+                    debug!("✋ read() interrumpido para inode {}: descargas canceladas", inode);
+                    return Err(Errno::from(libc::EINTR));
                 }
+            };
+            if !in_range {
+                return Ok(ReplyData { data: vec![].into() });
             }
 
             // Leer desde caché
@@ -759,100 +1152,85 @@ impl Filesystem for GDriveFS {
         offset: u64,
         _lock_owner: u64,
     ) -> Result<ReplyDirectoryPlus<Self::DirEntryPlusStream<'_>>> {
+        let _timer = crate::metrics::fuse_op("readdirplus");
         tracing::trace!("👁️ readdirplus: parent={} offset={}", parent, offset);
 
         let db = self.db.clone();
-        
-        // 1. Carga de datos
-        let (children, child_count) = if parent == SHARED_INODE {
+
+        // 1. Entradas fijas: . y .. (+ SHARED, Starred y .Trash en root)
+        let fixed = fixed_dir_entries(parent);
+        let child_offset = offset.saturating_sub(fixed.len() as u64);
+
+        // 2. Hijos desde `child_offset` (virtuales enteras, reales por páginas)
+        let children: Vec<_> = if parent == SHARED_INODE {
              let items = db.list_non_owned_root_children().await
                 .map_err(|e| {
                     error!("❌ Error listando compartidos (plus): {}", e);
                     Errno::from(libc::EIO)
                 })?;
-            let count = items.len() as u64;
-            (items, count)
+            items.into_iter()
+                .skip(child_offset as usize)
+                .map(|(inode, name, is_dir, mime, gdrive_id)| (inode, name, is_dir, mime, Some(gdrive_id), None))
+                .collect()
+        } else if parent == STARRED_INODE {
+            let items = db.list_starred().await
+                .map_err(|e| {
+                    error!("❌ Error listando destacados (plus): {}", e);
+                    Errno::from(libc::EIO)
+                })?;
+            items.into_iter()
+                .skip(child_offset as usize)
+                .map(|(inode, name, is_dir, mime, gdrive_id)| (inode, name, is_dir, mime, Some(gdrive_id), None))
+                .collect()
+        } else if parent == TRASH_INODE {
+            let items = db.list_trashed().await
+                .map_err(|e| {
+                    error!("❌ Error listando la papelera (plus): {}", e);
+                    Errno::from(libc::EIO)
+                })?;
+            items.into_iter()
+                .skip(child_offset as usize)
+                .map(|(inode, name, is_dir, mime, gdrive_id)| (inode, name, is_dir, mime, Some(gdrive_id), None))
+                .collect()
         } else {
-            let mut items = match db.list_children_extended(parent).await {
-                Ok(c) => c,
-                Err(e) => {
+            // En root la DB ya omite lo que no es propio (va en SHARED)
+            db.list_children_extended_page(parent, child_offset, READDIR_PAGE_SIZE).await
+                .map_err(|e| {
                     error!("❌ Error listando hijos de {}: {}", parent, e);
-                    return Err(Errno::from(libc::EIO));
-                }
-            };
-
-            // Filtrar si es root
-            if parent == 1 {
-                let mut filtered = Vec::new();
-                for item in items {
-                    let attrs = db.get_attrs(item.0).await.map_err(|_| Errno::from(libc::EIO))?;
-                    if attrs.owned_by_me {
-                        filtered.push(item);
-                    }
-                }
-                items = filtered;
-            }
-
-            let real_count = items.len() as u64;
-            (items, real_count)
+                    Errno::from(libc::EIO)
+                })?
+                .into_iter()
+                .map(|child| {
+                    let attrs = child.attrs;
+                    (attrs.inode as u64, child.name, attrs.is_dir, attrs.mime_type.clone(), Some(child.gdrive_id), Some(attrs))
+                })
+                .collect()
         };
-        
-        // Total = hijos + 2 (por . y ..) + (1 si es root por el SHARED)
-        let mut total_entries = child_count + 2;
-        if parent == 1 {
-            total_entries += 1;
-        }
-        
-        // Short-circuit: si ya consumieron todo, retornar vacío sin consultar DB
-        if offset >= total_entries {
-            tracing::trace!("📊 readdirplus short-circuit: offset={} >= total={}", offset, total_entries);
-            return Ok(ReplyDirectoryPlus {
-                entries: Box::pin(stream::empty())
-            });
-        }
-
-        // 3. Construir lista completa SIEMPRE (. y .. + hijos + SHARED)
-        let mut final_entries: Vec<(u64, String, bool, Option<String>, Option<String>)> = 
-            Vec::with_capacity(children.len() + 3);
-        final_entries.push((parent, ".".to_string(), true, None, None));
-        final_entries.push((if parent == SHARED_INODE { 1 } else { 1.max(parent) }, "..".to_string(), true, None, None));
-
-        if parent == 1 {
-            final_entries.push((SHARED_INODE, "SHARED".to_string(), true, None, None));
-        }
 
-        for (inode, name, is_dir, mime, gdrive_id) in children {
-            final_entries.push((inode, name, is_dir, mime, Some(gdrive_id)));
-        }
+        // 3. Las fijas que quedan tras el offset, seguidas de la página de hijos
+        let final_entries: Vec<(u64, String, bool, Option<String>, Option<String>, Option<crate::fuse::attr::FileAttributes>)> = fixed.into_iter()
+            .skip(offset as usize)
+            .map(|(inode, name, is_dir)| (inode, name, is_dir, None, None, None))
+            .chain(children)
+            .collect();
 
-        // 4. Construir stream con atributos completos usando los datos ya cargados
+        // 4. Construir stream con atributos completos: los hijos de carpetas
+        //    reales ya traen su fila de `attrs`; el resto se consulta por entrada
         let stream = stream::iter(final_entries)
-            .skip(offset as usize)
             .enumerate()
-            .then(move |(index, (inode, name, is_dir, mime, gdrive_id))| {
+            .then(move |(index, (inode, name, is_dir, mime, gdrive_id, attrs))| {
                 let db_clone = db.clone();
                 async move {
-                    let mut attr = if inode == SHARED_INODE {
-                        let now = std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs() as i64;
-                        FileAttr {
-                            ino: SHARED_INODE,
-                            size: 4096,
-                            blocks: 8,
-                            atime: Timestamp::new(now, 0),
-                            mtime: Timestamp::new(now, 0),
-                            ctime: Timestamp::new(now, 0),
-                            kind: FileType::Directory,
-                            perm: 0o755,
-                            nlink: 2,
-                            uid: unsafe { libc::getuid() },
-                            gid: unsafe { libc::getgid() },
-                            rdev: 0,
-                            blksize: 4096,
-                        }
-                    } else if let Ok(a) = db_clone.get_attrs(inode).await {
+                    let mut generation = 0;
+                    let attrs = match attrs {
+                        Some(attrs) => Some(attrs),
+                        None if is_virtual_dir(inode) => None,
+                        None => db_clone.get_attrs(inode).await.ok(),
+                    };
+                    let mut attr = if is_virtual_dir(inode) {
+                        virtual_dir_attr(inode)
+                    } else if let Some(a) = attrs {
+                        generation = a.generation as u64;
                         a.to_file_attr()
                     } else {
                         // Si no hay atributos, crear unos por defecto
@@ -864,6 +1242,7 @@ impl Filesystem for GDriveFS {
                             inode: inode as i64,
                             size: if is_dir { 4096 } else { 0 },
                             mtime: now,
+                            mtime_nsec: 0,
                             ctime: now,
                             mode: if is_dir { 0o755 } else { 0o644 },
                             is_dir,
@@ -871,13 +1250,15 @@ impl Filesystem for GDriveFS {
                             can_move: true,
                             shared: false,
                             owned_by_me: true,
+                            generation: 0,
+                            created_time: None,
                         }.to_file_attr()
                     };
 
                     // Ajustar nombre y tamaño para archivos Workspace - SOLO para ARCHIVOS, no carpetas
                     // Añadimos .html porque Nautilus 3.30+ abre .desktop desde FUSE como texto
                     let mut display_name = name.clone();
-                    if !is_dir && inode != SHARED_INODE {
+                    if !is_dir && !is_virtual_dir(inode) {
                         if let (Some(m), Some(gid)) = (&mime, &gdrive_id) {
                             if shortcuts::is_workspace_file(m) {
                                 display_name = format!("{}.html", name);
@@ -891,8 +1272,8 @@ impl Filesystem for GDriveFS {
 
                     Ok(DirectoryEntryPlus {
                         inode,
-                        generation: 0,
-                        kind: if is_dir || inode == SHARED_INODE { FileType::Directory } else { FileType::RegularFile },
+                        generation,
+                        kind: if is_dir || is_virtual_dir(inode) { FileType::Directory } else { FileType::RegularFile },
                         name: display_name.into(),
                         offset: (offset as i64 + index as i64 + 1),
                         attr,
@@ -918,21 +1299,29 @@ impl Filesystem for GDriveFS {
         parent: u64,
         name: &OsStr,
         mode: u32,
-        _flags: u32,
+        flags: u32,
     ) -> Result<ReplyCreated> {
+        let _timer = crate::metrics::fuse_op("create");
         let name_str = name.to_str().ok_or(Errno::from(libc::EINVAL))?;
         tracing::info!("📝 CREATE request: parent={} name={} mode={:o}", parent, name_str, mode);
 
-        // Caso especial: SHARED es de solo lectura
-        if parent == SHARED_INODE {
+        // Caso especial: SHARED y Starred son de solo lectura
+        if is_virtual_dir(parent) {
             return Err(Errno::from(libc::EROFS));
         }
-
-        // Generar un gdrive_id temporal para el nuevo archivo (será reemplazado al subir)
-        let temp_gdrive_id = format!("temp_{}", uuid::Uuid::new_v4());
+        self.ensure_accepts_entries(parent).await?;
+        self.ensure_storage_available("create", parent)?;
+
+        // ID reservado en Drive (definitivo) o `temp_<uuid>` si no hay reserva
+        let gdrive_id = self.id_pool.take();
+        let intent = self.begin_intent(FsIntent::Create {
+            parent,
+            name: name_str.to_string(),
+            gdrive_id: gdrive_id.clone(),
+        }).await?;
         
-        // Crear inode en la DB
-        let inode = self.db.get_or_create_inode(&temp_gdrive_id).await
+        // Crear inode en la DB (pendiente de crear en Drive)
+        let inode = self.db.create_local_inode(&gdrive_id).await
             .map_err(|e| {
                 error!("Error creando inode: {}", e);
                 Errno::from(libc::EIO)
@@ -959,6 +1348,9 @@ impl Filesystem for GDriveFS {
             error!("Error insertando metadatos: {}", e);
             Errno::from(libc::EIO)
         })?;
+        // Drive confirma después su `createdTime` con el eco del cambio
+        self.db.set_created_time(inode, now).await
+            .map_err(|_| Errno::from(libc::EIO))?;
 
         // Agregar al dentry
         self.db.upsert_dentry(parent, inode, name_str).await
@@ -966,24 +1358,24 @@ impl Filesystem for GDriveFS {
                 error!("Error insertando dentry: {}", e);
                 Errno::from(libc::EIO)
             })?;
+        self.metadata_cache.invalidate_entry(parent, name_str);
+        self.metadata_cache.invalidate_missing(parent);
+        self.metadata_cache.invalidate_attrs(parent); // mtime del directorio
 
-        // Marcar como dirty y burbujear estado a ancestros
-        self.db.set_dirty_and_bubble(inode).await
-            .map_err(|e| {
-                error!("Error marcando archivo como dirty: {}", e);
-                Errno::from(libc::EIO)
-            })?;
+        // Marcar como dirty y burbujear estado a ancestros (o dejarlo solo en local)
+        self.queue_created(parent, inode, name_str).await?;
 
         let attrs = self.db.get_attrs(inode).await
             .map_err(|_| Errno::from(libc::EIO))?;
 
+        self.end_intent(intent).await;
         debug!("✅ Archivo creado: inode={} nombre={}", inode, name_str);
 
         Ok(ReplyCreated {
             ttl: Duration::from_secs(1),
             attr: attrs.to_file_attr(),
-            generation: 0,
-            fh: 0,
+            generation: attrs.generation as u64,
+            fh: self.open_handle(inode, flags),
             flags: 0,
         })
     }
@@ -997,19 +1389,26 @@ impl Filesystem for GDriveFS {
         mode: u32,
         _umask: u32,
     ) -> Result<ReplyEntry> {
+        let _timer = crate::metrics::fuse_op("mkdir");
         let name_str = name.to_str().ok_or(Errno::from(libc::EINVAL))?;
         debug!("📂 mkdir: parent={} name={} mode={:o}", parent, name_str, mode);
 
-        // Caso especial: SHARED es de solo lectura
-        if parent == SHARED_INODE {
+        // Caso especial: SHARED y Starred son de solo lectura
+        if is_virtual_dir(parent) {
             return Err(Errno::from(libc::EROFS));
         }
-
-        // Generar un gdrive_id temporal
-        let temp_gdrive_id = format!("temp_{}", uuid::Uuid::new_v4());
+        self.ensure_accepts_entries(parent).await?;
+
+        // ID reservado en Drive o `temp_<uuid>` (ver `create`)
+        let gdrive_id = self.id_pool.take();
+        let intent = self.begin_intent(FsIntent::Create {
+            parent,
+            name: name_str.to_string(),
+            gdrive_id: gdrive_id.clone(),
+        }).await?;
         
-        // Crear inode en la DB
-        let inode = self.db.get_or_create_inode(&temp_gdrive_id).await
+        // Crear inode en la DB (pendiente de crear en Drive)
+        let inode = self.db.create_local_inode(&gdrive_id).await
             .map_err(|e| {
                 error!("Error creando inode para directorio: {}", e);
                 Errno::from(libc::EIO)
@@ -1039,6 +1438,8 @@ impl Filesystem for GDriveFS {
             error!("Error insertando metadatos de directorio: {}", e);
             Errno::from(libc::EIO)
         })?;
+        self.db.set_created_time(inode, now).await
+            .map_err(|_| Errno::from(libc::EIO))?;
 
         // Agregar al dentry
         self.db.upsert_dentry(parent, inode, name_str).await
@@ -1046,14 +1447,13 @@ impl Filesystem for GDriveFS {
                 error!("Error insertando dentry de directorio: {}", e);
                 Errno::from(libc::EIO)
             })?;
+        self.metadata_cache.invalidate_entry(parent, name_str);
+        self.metadata_cache.invalidate_missing(parent);
+        self.metadata_cache.invalidate_attrs(parent); // mtime del directorio
 
         // Marcar como dirty (pendiente de creación en GDrive)
-        // Directorios: set_dirty_and_bubble no burbujea para is_dir=true (correcto)
-        self.db.set_dirty_and_bubble(inode).await
-            .map_err(|e| {
-                error!("Error marcando directorio como dirty: {}", e);
-                Errno::from(libc::EIO)
-            })?;
+        // Directorios: mark_dirty no burbujea para is_dir=true (correcto)
+        self.queue_created(parent, inode, name_str).await?;
         // Asegurar que el nuevo directorio tiene fila en dir_counters
         self.db.ensure_dir_counter(inode).await
             .map_err(|e| {
@@ -1064,12 +1464,13 @@ impl Filesystem for GDriveFS {
         let attrs = self.db.get_attrs(inode).await
             .map_err(|_| Errno::from(libc::EIO))?;
 
+        self.end_intent(intent).await;
         debug!("✅ Directorio creado: inode={} nombre={}", inode, name_str);
 
         Ok(ReplyEntry {
             ttl: Duration::from_secs(1),
             attr: attrs.to_file_attr(),
-            generation: 0,
+            generation: attrs.generation as u64,
         })
     }
 
@@ -1078,26 +1479,29 @@ impl Filesystem for GDriveFS {
         &self,
         _req: Request,
         inode: u64,
-        _fh: u64,
+        fh: u64,
         offset: u64,
         data: &[u8],
         _write_flags: u32,
-        _flags: u32,
+        flags: u32,
     ) -> Result<ReplyWrite> {
+        let _timer = crate::metrics::fuse_op("write");
         tracing::trace!("✏️ write: inode={} offset={} size={}", inode, offset, data.len());
+        self.ensure_storage_available("write", inode)?;
+        self.admit_write("write", inode).await?;
 
         // Obtener el gdrive_id del archivo
-        let gdrive_id = sqlx::query_scalar::<_, String>("SELECT gdrive_id FROM inodes WHERE inode = ?")
-            .bind(inode as i64)
-            .fetch_one(self.db.pool())
-            .await
+        let gdrive_id = self.db.get_gdrive_id(inode).await
             .map_err(|e| {
                 error!("Error obteniendo gdrive_id: {}", e);
-                Errno::from(libc::ENOENT)
-            })?;
+                Errno::from(libc::EIO)
+            })?
+            .ok_or(Errno::from(libc::ENOENT))?;
 
         // Ruta local de caché
         let cache_path = self.get_cache_path(&gdrive_id);
+        let intent = self.begin_intent(FsIntent::Write { inode }).await?;
+        let _cache_guard = self.file_locks.lock(inode).await;
         
         // Crear directorio de caché si no existe
         if let Some(parent_dir) = cache_path.parent() {
@@ -1108,7 +1512,26 @@ impl Filesystem for GDriveFS {
                 })?;
         }
 
+        // Una caché compartida con copias idénticas no se modifica en su sitio
+        self.content_store.make_private(&self.db, inode, &cache_path).await
+            .map_err(|e| {
+                error!("Error separando caché compartida: {}", e);
+                Errno::from(libc::EIO)
+            })?;
+
+        // Tamaño antes de escribir: lo que se añada por encima es contenido local.
+        // Se lee con el lock del inode tomado, así que ningún otro escritor lo
+        // cambia hasta que esta escritura registre el suyo
+        let old_size = self.cached_attrs(inode).await
+            .map(|attrs| attrs.size.max(0) as u64)
+            .unwrap_or(0);
+        // Con writeback el kernel envía offsets ya resueltos (y abre con O_RDWR)
+        let append = !self.writeback_cache
+            && (self.append_handles.contains(&fh) || flags as i32 & libc::O_APPEND != 0);
+        let (offset, new_size) = write_extent(old_size, offset, data.len() as u64, append);
+
         // Escribir datos en el archivo de caché
+        let is_new = !cache_path.exists();
         let mut file = tokio::fs::OpenOptions::new()
             .create(true)
             .write(true)
@@ -1118,6 +1541,27 @@ impl Filesystem for GDriveFS {
                 error!("Error abriendo archivo de caché: {}", e);
                 Errno::from(libc::EIO)
             })?;
+        if is_new {
+            crate::utils::cache_crypto::init_file(&file)
+                .map_err(|e| {
+                    error!("Error asignando clave de caché: {}", e);
+                    Errno::from(libc::EIO)
+                })?;
+        }
+
+        // Escribir más allá del final (o dentro de un hueco) deja zonas que deben leerse como ceros
+        crate::utils::cache_crypto::prepare_write(&mut file, offset, data.len() as u64).await
+            .map_err(|e| {
+                error!("Error rellenando hueco en caché: {}", e);
+                Errno::from(libc::EIO)
+            })?;
+
+        let mut data = data.to_vec();
+        crate::utils::cache_crypto::encrypt_at(&file, offset, &mut data)
+            .map_err(|e| {
+                error!("Error cifrando datos: {}", e);
+                Errno::from(libc::EIO)
+            })?;
 
         use tokio::io::{AsyncSeekExt, AsyncWriteExt};
         file.seek(std::io::SeekFrom::Start(offset)).await
@@ -1126,7 +1570,7 @@ impl Filesystem for GDriveFS {
                 Errno::from(libc::EIO)
             })?;
 
-        file.write_all(data).await
+        file.write_all(&data).await
             .map_err(|e| {
                 error!("Error escribiendo datos: {}", e);
                 Errno::from(libc::EIO)
@@ -1138,38 +1582,39 @@ impl Filesystem for GDriveFS {
                 Errno::from(libc::EIO)
             })?;
 
-        // Obtener el nuevo tamaño del archivo
-        let metadata = file.metadata().await
-            .map_err(|e| {
-                error!("Error obteniendo metadata: {}", e);
-                Errno::from(libc::EIO)
-            })?;
-        let new_size = metadata.len() as i64;
+        // El rango escrito (y el hueco de ceros si se escribió más allá del final)
+        // ya no se descarga de Drive: ni una lectura ni el uploader deben pisarlo
+        if !data.is_empty() {
+            self.db.add_cached_chunk(inode, offset.min(old_size), offset + data.len() as u64 - 1).await
+                .map_err(|e| {
+                    error!("Error registrando rango escrito: {}", e);
+                    Errno::from(libc::EIO)
+                })?;
+        }
 
-        // Actualizar tamaño en la base de datos
+        // Actualizar tamaño en la base de datos (la caché de un archivo a medio
+        // descargar puede ser más corta que el archivo: no sirve de referencia)
         let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
 
-        sqlx::query("UPDATE attrs SET size = ?, mtime = ? WHERE inode = ?")
-            .bind(new_size)
-            .bind(now)
-            .bind(inode as i64)
-            .execute(self.db.pool())
-            .await
+        self.db.set_size_and_mtime(inode, new_size as i64, now).await
             .map_err(|e| {
                 error!("Error actualizando attrs: {}", e);
                 Errno::from(libc::EIO)
             })?;
+        self.metadata_cache.invalidate_attrs(inode);
 
         // Marcar como dirty y burbujear estado
-        self.db.set_dirty_and_bubble(inode).await
+        self.db.mark_dirty(inode).await
             .map_err(|e| {
                 error!("Error marcando como dirty: {}", e);
                 Errno::from(libc::EIO)
             })?;
+        self.record_upload_op(inode, UploadOp::Write).await;
 
+        self.end_intent(intent).await;
         debug!("✅ Escritura completada: {} bytes", data.len());
 
         Ok(ReplyWrite {
@@ -1185,65 +1630,79 @@ impl Filesystem for GDriveFS {
         _fh: Option<u64>,
         set_attr: SetAttr,
     ) -> Result<ReplyAttr> {
+        let _timer = crate::metrics::fuse_op("setattr");
         debug!("✏️ setattr: inode={} set_attr={:?}", inode, set_attr);
 
         // Actualizar solo los campos especificados
         if let Some(size) = set_attr.size {
-            // Truncar archivo
-            let gdrive_id = sqlx::query_scalar::<_, String>("SELECT gdrive_id FROM inodes WHERE inode = ?")
-                .bind(inode as i64)
-                .fetch_one(self.db.pool())
-                .await
-                .map_err(|_| Errno::from(libc::ENOENT))?;
+            // Recortar libera espacio; extender, con Drive lleno o con contrapresión, no
+            if self.storage_quota.is_full() || backpressure::current().is_some() {
+                let current = self.db.get_attrs(inode).await
+                    .map_err(|_| Errno::from(libc::ENOENT))?
+                    .size;
+                if size > current.max(0) as u64 {
+                    self.ensure_storage_available("truncate", inode)?;
+                    self.admit_write("truncate", inode).await?;
+                }
+            }
+
+            // Truncar archivo
+            let intent = self.begin_intent(FsIntent::Truncate { inode, size }).await?;
+            let gdrive_id = self.db.get_gdrive_id(inode).await
+                .map_err(|_| Errno::from(libc::EIO))?
+                .ok_or(Errno::from(libc::ENOENT))?;
 
             let cache_path = self.get_cache_path(&gdrive_id);
+            let cache_guard = self.file_locks.lock(inode).await;
+            let old_size = self.db.get_attrs(inode).await
+                .map_err(|_| Errno::from(libc::EIO))?
+                .size
+                .max(0) as u64;
+            self.content_store.make_private(&self.db, inode, &cache_path).await
+                .map_err(|_| Errno::from(libc::EIO))?;
             
-            if cache_path.exists() {
-                let file = std::fs::OpenOptions::new()
-                    .write(true)
-                    .open(&cache_path)
-                    .map_err(|_| Errno::from(libc::EIO))?;
-                    
-                file.set_len(size)
-                    .map_err(|_| Errno::from(libc::EIO))?;
-            } else {
-                // Crear archivo vacío del tamaño especificado
-                std::fs::write(&cache_path, vec![0u8; size as usize])
+            // Trunca o extiende (creando el archivo si no existe); la extensión se lee como ceros
+            crate::utils::cache_crypto::set_len(&cache_path, size, true).await
+                .map_err(|_| Errno::from(libc::EIO))?;
+            // La extensión son ceros locales; al recortar, la DB elimina los
+            // chunks por encima del nuevo final (trigger de 0016)
+            if size > old_size {
+                self.db.add_cached_chunk(inode, old_size, size - 1).await
                     .map_err(|_| Errno::from(libc::EIO))?;
             }
-
-            sqlx::query("UPDATE attrs SET size = ? WHERE inode = ?")
-                .bind(size as i64)
-                .bind(inode as i64)
-                .execute(self.db.pool())
-                .await
+            // Antes de soltar el lock: un `write` concurrente calcula su offset y
+            // tamaño a partir de este
+            self.db.set_size(inode, size as i64).await
                 .map_err(|_| Errno::from(libc::EIO))?;
+            self.metadata_cache.invalidate_attrs(inode);
+            drop(cache_guard);
 
             // Marcar como dirty y burbujear estado
-            self.db.set_dirty_and_bubble(inode).await
+            self.db.mark_dirty(inode).await
                 .map_err(|_| Errno::from(libc::EIO))?;
+            self.record_upload_op(inode, UploadOp::Write).await;
+            self.end_intent(intent).await;
         }
 
         if let Some(mtime) = set_attr.mtime {
-            let mtime_secs = mtime.sec;
-
-            sqlx::query("UPDATE attrs SET mtime = ? WHERE inode = ?")
-                .bind(mtime_secs)
-                .bind(inode as i64)
-                .execute(self.db.pool())
-                .await
+            self.db.set_mtime(inode, mtime.sec, mtime.nsec).await
                 .map_err(|_| Errno::from(libc::EIO))?;
+
+            // `touch`, `cp -p`, rsync: la fecha también debe llegar a Drive
+            if self.db.is_dir(inode).await.ok().flatten() == Some(false) {
+                self.db.mark_dirty(inode).await
+                    .map_err(|_| Errno::from(libc::EIO))?;
+                self.record_upload_op(inode, UploadOp::Write).await;
+            }
         }
 
         if let Some(mode) = set_attr.mode {
-            sqlx::query("UPDATE attrs SET mode = ? WHERE inode = ?")
-                .bind(mode)
-                .bind(inode as i64)
-                .execute(self.db.pool())
-                .await
+            self.db.set_mode(inode, mode).await
                 .map_err(|_| Errno::from(libc::EIO))?;
         }
 
+        self.metadata_cache.invalidate_attrs(inode);
+
         let attrs = self.db.get_attrs(inode).await
             .map_err(|_| Errno::from(libc::ENOENT))?;
 
@@ -1260,11 +1719,17 @@ impl Filesystem for GDriveFS {
         parent: u64,
         name: &OsStr,
     ) -> Result<()> {
+        let _timer = crate::metrics::fuse_op("unlink");
         let name_str = name.to_str().ok_or(Errno::from(libc::EINVAL))?;
         tracing::info!("🗑️ UNLINK: parent={} name={}", parent, name_str);
 
-        // Caso especial: SHARED es de solo lectura
-        if parent == SHARED_INODE {
+        // Caso especial: borrar de .Trash es eliminar definitivamente
+        if parent == TRASH_INODE {
+            return self.purge_trashed(name_str, false).await;
+        }
+
+        // Caso especial: SHARED y Starred son de solo lectura
+        if is_virtual_dir(parent) {
             return Err(Errno::from(libc::EROFS));
         }
 
@@ -1272,13 +1737,18 @@ impl Filesystem for GDriveFS {
         let inode = self.db.lookup(parent, name_str).await
             .map_err(|_| Errno::from(libc::EIO))?
             .ok_or(Errno::from(libc::ENOENT))?;
+        self.ensure_trashable(inode, name_str).await?;
 
         // Obtener gdrive_id
-        let gdrive_id = sqlx::query_scalar::<_, String>("SELECT gdrive_id FROM inodes WHERE inode = ?")
-            .bind(inode as i64)
-            .fetch_one(self.db.pool())
-            .await
-            .map_err(|_| Errno::from(libc::ENOENT))?;
+        let gdrive_id = self.db.get_gdrive_id(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?
+            .ok_or(Errno::from(libc::ENOENT))?;
+
+        let intent = self.begin_intent(FsIntent::Unlink {
+            parent,
+            name: name_str.to_string(),
+            gdrive_id: gdrive_id.clone(),
+        }).await?;
 
         // Soft delete
         self.db.soft_delete_by_gdrive_id(&gdrive_id).await
@@ -1286,17 +1756,91 @@ impl Filesystem for GDriveFS {
                 error!("Error en soft delete: {}", e);
                 Errno::from(libc::EIO)
             })?;
+        self.metadata_cache.invalidate_inode(inode);
+        self.metadata_cache.invalidate_attrs(parent); // mtime del directorio
 
         // Marcar como dirty y burbujear (soft_delete_by_gdrive_id ya burbujea internamente,
-        // pero el set_dirty aquí es para el caso donde no hubo soft_delete recursivo)
-        self.db.set_dirty_and_bubble(inode).await
+        // pero el set_dirty aquí es para el caso donde no hubo soft_delete recursivo).
+        // Un ignorado vuelve a la cola solo para que el uploader lo limpie: nunca llegó a Drive
+        self.db.unignore(inode).await
             .map_err(|_| Errno::from(libc::EIO))?;
+        self.db.mark_dirty(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?;
+        self.record_upload_op(inode, UploadOp::Delete).await;
 
+        self.end_intent(intent).await;
         debug!("✅ Archivo marcado para eliminación: {}", name_str);
 
         Ok(())
     }
 
+    // Eliminar un directorio vacío (rm -r vacía primero su contenido con unlink/rmdir)
+    async fn rmdir(
+        &self,
+        _req: Request,
+        parent: u64,
+        name: &OsStr,
+    ) -> Result<()> {
+        let _timer = crate::metrics::fuse_op("rmdir");
+        let name_str = name.to_str().ok_or(Errno::from(libc::EINVAL))?;
+        tracing::info!("🗑️ RMDIR: parent={} name={}", parent, name_str);
+
+        if parent == TRASH_INODE {
+            return self.purge_trashed(name_str, true).await;
+        }
+
+        // Caso especial: SHARED y Starred son de solo lectura
+        if is_virtual_dir(parent) {
+            return Err(Errno::from(libc::EROFS));
+        }
+
+        let inode = self.db.lookup(parent, name_str).await
+            .map_err(|_| Errno::from(libc::EIO))?
+            .ok_or(Errno::from(libc::ENOENT))?;
+
+        match self.db.is_dir(inode).await.map_err(|_| Errno::from(libc::EIO))? {
+            Some(true) => {}
+            Some(false) => return Err(Errno::from(libc::ENOTDIR)),
+            None => return Err(Errno::from(libc::ENOENT)),
+        }
+        self.ensure_trashable(inode, name_str).await?;
+
+        if self.db.count_children(inode).await.map_err(|_| Errno::from(libc::EIO))? > 0 {
+            return Err(Errno::from(libc::ENOTEMPTY));
+        }
+
+        let gdrive_id = self.db.get_gdrive_id(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?
+            .ok_or(Errno::from(libc::ENOENT))?;
+
+        let intent = self.begin_intent(FsIntent::Unlink {
+            parent,
+            name: name_str.to_string(),
+            gdrive_id: gdrive_id.clone(),
+        }).await?;
+
+        // Soft delete recursivo: la carpeta y cualquier descendiente pasan a tombstone
+        // y el uploader envía solo la carpeta a la papelera de Drive
+        self.db.soft_delete_by_gdrive_id(&gdrive_id).await
+            .map_err(|e| {
+                error!("Error en soft delete: {}", e);
+                Errno::from(libc::EIO)
+            })?;
+        self.metadata_cache.invalidate_inode(inode);
+        self.metadata_cache.invalidate_attrs(parent); // mtime del directorio
+
+        self.db.unignore(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?;
+        self.db.mark_dirty(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?;
+        self.record_upload_op(inode, UploadOp::Delete).await;
+
+        self.end_intent(intent).await;
+        debug!("✅ Directorio marcado para eliminación: {}", name_str);
+
+        Ok(())
+    }
+
     // Renombrar/mover un archivo
     async fn rename(
         &self,
@@ -1306,15 +1850,19 @@ impl Filesystem for GDriveFS {
         new_parent: u64,
         new_name: &OsStr,
     ) -> Result<()> {
+        let _timer = crate::metrics::fuse_op("rename");
         let name_str = name.to_str().unwrap_or("???");
         let new_name_str = new_name.to_str().unwrap_or("???");
         tracing::info!("🔄 RENAME: parent={} name={} -> new_parent={} new_name={}", 
                       parent, name_str, new_parent, new_name_str);
 
-        // Caso especial: SHARED es de solo lectura
-        if parent == SHARED_INODE || new_parent == SHARED_INODE {
+        // Caso especial: SHARED y Starred son de solo lectura
+        if is_virtual_dir(parent) || is_virtual_dir(new_parent) {
             return Err(Errno::from(libc::EROFS));
         }
+        if new_parent != parent {
+            self.ensure_accepts_entries(new_parent).await?;
+        }
 
         // 1. Obtener inode origen
         let inode = self.db.lookup(parent, name_str).await
@@ -1331,25 +1879,45 @@ impl Filesystem for GDriveFS {
             return Err(Errno::from(libc::EACCES));
         }
 
-        // Si existe un archivo destino, eliminarlo primero (overwite)
+        // Destino ignorado: lo que aún no existe en Drive pasa a ser solo local;
+        // lo que ya existe no se puede ocultar de Drive con un rename, así que
+        // EXDEV hace que `mv` copie y borre (la copia queda ignorada)
+        let was_ignored = self.db.is_ignored(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?;
+        let now_ignored = self.is_ignored_entry(new_parent, new_name_str).await;
+        if now_ignored && !was_ignored
+            && !self.db.is_pending_create(inode).await.map_err(|_| Errno::from(libc::EIO))?
+        {
+            tracing::info!("🙈 RENAME hacia nombre ignorado de un archivo ya en Drive: {} -> {}", name_str, new_name_str);
+            return Err(Errno::from(libc::EXDEV));
+        }
+
+        let intent = self.begin_intent(FsIntent::Rename {
+            inode,
+            parent,
+            name: name_str.to_string(),
+            new_parent,
+            new_name: new_name_str.to_string(),
+        }).await?;
+
+        // Si existe un archivo destino, eliminarlo primero (overwite). Si es un
+        // archivo de Drive, el origen pasa a ser el destino (mismo ID y enlaces)
+        // y solo se sube su contenido: guardados atómicos y `mv -f` sobre otro
+        let mut in_place = false;
         if let Ok(Some(existing_inode)) = self.db.lookup(new_parent, new_name_str).await {
-            // Obtener gdrive_id del existente
-            if let Ok(gdrive_id) = sqlx::query_scalar::<_, String>("SELECT gdrive_id FROM inodes WHERE inode = ?")
-                .bind(existing_inode as i64)
-                .fetch_one(self.db.pool())
-                .await
-            {
+            in_place = self.can_replace_in_place(inode, existing_inode).await?;
+            if in_place {
+                self.adopt_replaced(inode, existing_inode).await?;
+            } else if let Ok(Some(gdrive_id)) = self.db.get_gdrive_id(existing_inode).await {
                 self.db.soft_delete_by_gdrive_id(&gdrive_id).await
                     .map_err(|_| Errno::from(libc::EIO))?;
+                self.record_upload_op(existing_inode, UploadOp::Delete).await;
             }
+            self.metadata_cache.invalidate_inode(existing_inode);
         }
 
         // Eliminar la entrada dentry antigua
-        sqlx::query("DELETE FROM dentry WHERE parent_inode = ? AND name = ?")
-            .bind(parent as i64)
-            .bind(name_str)
-            .execute(self.db.pool())
-            .await
+        self.db.remove_dentry(parent, name_str).await
             .map_err(|e| {
                 error!("Error eliminando dentry antiguo: {}", e);
                 Errno::from(libc::EIO)
@@ -1362,26 +1930,24 @@ impl Filesystem for GDriveFS {
                 Errno::from(libc::EIO)
             })?;
 
+        // Invalidar la caché: el mapeo viejo, el nuevo y el propio inode
+        self.metadata_cache.invalidate_entry(parent, name_str);
+        self.metadata_cache.invalidate_entry(new_parent, new_name_str);
+        self.metadata_cache.invalidate_missing(new_parent);
+        self.metadata_cache.invalidate_inode(inode);
+        self.metadata_cache.invalidate_attrs(parent);
+        self.metadata_cache.invalidate_attrs(new_parent);
+
         // Burbujeo para rename/move
-        let is_dir: Option<bool> = sqlx::query_scalar::<_, bool>(
-            "SELECT is_dir FROM attrs WHERE inode = ?"
-        )
-        .bind(inode as i64)
-        .fetch_optional(self.db.pool())
-        .await
-        .map_err(|_| Errno::from(libc::EIO))?;
+        let is_dir = self.db.is_dir(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?;
 
         if parent != new_parent {
             // Mover entre directorios: transferir contadores
             if is_dir == Some(true) {
                 // Mover un directorio: transferir sus contadores de descendientes
-                let counters = sqlx::query_as::<_, (i64, i64)>(
-                    "SELECT dirty_desc_count, synced_desc_count FROM dir_counters WHERE inode = ?"
-                )
-                .bind(inode as i64)
-                .fetch_optional(self.db.pool())
-                .await
-                .map_err(|_| Errno::from(libc::EIO))?;
+                let counters = self.db.get_dir_counters(inode).await
+                    .map_err(|_| Errno::from(libc::EIO))?;
 
                 if let Some((dirty, synced)) = counters {
                     // Decrementar ancestros del viejo padre
@@ -1391,78 +1957,53 @@ impl Filesystem for GDriveFS {
                 }
             } else if is_dir == Some(false) {
                 // Mover un archivo: determinar su estado y transferir
-                let state = sqlx::query_as::<_, (Option<bool>, Option<i64>)>(
-                    "SELECT dirty, deleted_at FROM sync_state WHERE inode = ?"
-                )
-                .bind(inode as i64)
-                .fetch_optional(self.db.pool())
-                .await
-                .map_err(|_| Errno::from(libc::EIO))?;
+                let state = self.db.sync_state(inode).await
+                    .map_err(|_| Errno::from(libc::EIO))?;
 
                 let (d_dirty, d_synced) = match state {
-                    Some((dirty, deleted_at)) => {
-                        let was_dirty = dirty.unwrap_or(false) || deleted_at.map(|v| v > 0).unwrap_or(false);
-                        if was_dirty { (1i32, 0i32) } else { (0, 1) }
-                    }
+                    Some(state) if state.is_pending() => (1i32, 0i32),
+                    Some(_) => (0, 1),
                     None => (0, 0),
                 };
                 // La dentry ya apunta al nuevo padre, así que bubble_state_change
                 // actuará sobre los nuevos ancestros. Necesitamos corregir los viejos manualmente.
-                // Usamos un UPDATE directo sobre los ancestros del viejo parent.
+                // Corregimos los ancestros de ambos padres partiendo del propio directorio.
                 if d_dirty != 0 || d_synced != 0 {
                     // Decrementar viejo padre y sus ancestros
-                    sqlx::query(
-                        r#"
-                        WITH RECURSIVE ancestors AS (
-                            SELECT ?1 as anc_inode
-                            UNION ALL
-                            SELECT d.parent_inode FROM dentry d
-                            JOIN ancestors a ON d.child_inode = a.anc_inode
-                            WHERE a.anc_inode > 0
-                        )
-                        UPDATE dir_counters
-                        SET dirty_desc_count = MAX(0, dirty_desc_count - ?2),
-                            synced_desc_count = MAX(0, synced_desc_count - ?3)
-                        WHERE inode IN (SELECT anc_inode FROM ancestors)
-                        "#
-                    )
-                    .bind(parent as i64)
-                    .bind(d_dirty)
-                    .bind(d_synced)
-                    .execute(self.db.pool())
-                    .await
-                    .map_err(|_| Errno::from(libc::EIO))?;
+                    self.db.adjust_dir_counters_from(parent, -d_dirty, -d_synced).await
+                        .map_err(|_| Errno::from(libc::EIO))?;
 
                     // Incrementar nuevo padre y sus ancestros
-                    sqlx::query(
-                        r#"
-                        WITH RECURSIVE ancestors AS (
-                            SELECT ?1 as anc_inode
-                            UNION ALL
-                            SELECT d.parent_inode FROM dentry d
-                            JOIN ancestors a ON d.child_inode = a.anc_inode
-                            WHERE a.anc_inode > 0
-                        )
-                        UPDATE dir_counters
-                        SET dirty_desc_count = dirty_desc_count + ?2,
-                            synced_desc_count = synced_desc_count + ?3
-                        WHERE inode IN (SELECT anc_inode FROM ancestors)
-                        "#
-                    )
-                    .bind(new_parent as i64)
-                    .bind(d_dirty)
-                    .bind(d_synced)
-                    .execute(self.db.pool())
-                    .await
-                    .map_err(|_| Errno::from(libc::EIO))?;
+                    self.db.adjust_dir_counters_from(new_parent, d_dirty, d_synced).await
+                        .map_err(|_| Errno::from(libc::EIO))?;
                 }
             }
         }
 
-        // Marcar como dirty para sincronizar el cambio de nombre
-        self.db.set_dirty_and_bubble(inode).await
-            .map_err(|_| Errno::from(libc::EIO))?;
+        match (was_ignored, now_ignored) {
+            // Nueva versión del original: mismo ID en Drive, solo cambia el contenido
+            _ if in_place => {
+                self.db.mark_dirty(inode).await
+                    .map_err(|_| Errno::from(libc::EIO))?;
+                self.record_upload_op(inode, UploadOp::Write).await;
+            }
+            (false, false) => {
+                // Marcar como dirty para sincronizar el cambio de nombre
+                self.db.mark_dirty(inode).await
+                    .map_err(|_| Errno::from(libc::EIO))?;
+                self.record_upload_op(inode, UploadOp::Rename).await;
+            }
+            (false, true) => {
+                // Aún pendiente de crear: deja de subirse
+                self.db.mark_ignored(inode).await
+                    .map_err(|_| Errno::from(libc::EIO))?;
+                tracing::info!("🙈 {} queda solo en local", new_name_str);
+            }
+            (true, false) => self.unignore_tree(inode).await?,
+            (true, true) => {}
+        }
 
+        self.end_intent(intent).await;
         debug!("✅ Archivo renombrado: {} -> {}", name_str, new_name_str);
 
         Ok(())
@@ -1470,6 +2011,126 @@ impl Filesystem for GDriveFS {
 }
 
 impl GDriveFS {
+    /// Si una entrada `name` bajo `parent` queda solo en local: coincide con
+    /// `ignore_patterns` o está dentro de una carpeta ignorada
+    async fn is_ignored_entry(&self, parent: u64, name: &str) -> bool {
+        self.ignore_rules.is_ignored_name(name)
+            || self.db.is_ignored(parent).await.unwrap_or(false)
+    }
+
+    /// Encola la creación en Drive de un inode recién creado, salvo que esté
+    /// ignorado: entonces queda solo en local (ver `is_ignored_entry`)
+    async fn queue_created(&self, parent: u64, inode: u64, name: &str) -> Result<()> {
+        if self.is_ignored_entry(parent, name).await {
+            self.db.mark_ignored(inode).await
+                .map_err(|e| {
+                    error!("Error marcando {} como ignorado: {}", name, e);
+                    Errno::from(libc::EIO)
+                })?;
+            debug!("🙈 {} coincide con ignore_patterns: queda solo en local", name);
+            return Ok(());
+        }
+        self.db.mark_dirty(inode).await
+            .map_err(|e| {
+                error!("Error marcando {} como dirty: {}", name, e);
+                Errno::from(libc::EIO)
+            })?;
+        self.record_upload_op(inode, UploadOp::Create).await;
+        Ok(())
+    }
+
+    /// Si `inode` puede reemplazar a `replaced` conservando su archivo de Drive
+    /// (ver `MetadataRepository::can_replace_in_place`). Un origen que ya está
+    /// en Drive además debe tener todo su contenido en caché: es lo que se sube.
+    async fn can_replace_in_place(&self, inode: u64, replaced: u64) -> Result<bool> {
+        let eio = |_| Errno::from(libc::EIO);
+        if !self.db.can_replace_in_place(inode, replaced).await.map_err(eio)? {
+            return Ok(false);
+        }
+        if self.db.is_pending_create(inode).await.map_err(eio)? {
+            return Ok(true);
+        }
+        let size = self.db.get_attrs(inode).await.map_err(eio)?.size.max(0) as u64;
+        Ok(size == 0 || self.db.get_missing_ranges(inode, 0, size - 1).await.map_err(eio)?.is_empty())
+    }
+
+    /// Rename encima de un archivo de Drive: `inode` pasa a ser el archivo
+    /// `replaced` (ver `MetadataRepository::adopt_replaced_file`) y su caché toma
+    /// el nombre del ID adoptado. El número de inode que el kernel asoció al
+    /// nombre en el rename sigue siendo válido.
+    async fn adopt_replaced(&self, inode: u64, replaced: u64) -> Result<()> {
+        let own_id = self.db.get_gdrive_id(inode).await
+            .map_err(|_| Errno::from(libc::EIO))?
+            .ok_or(Errno::from(libc::ENOENT))?;
+        let gdrive_id = self.db.adopt_replaced_file(inode, replaced).await
+            .map_err(|e| {
+                error!("Error adoptando inode {} como nueva versión de {}: {}", inode, replaced, e);
+                Errno::from(libc::EIO)
+            })?;
+        self.failed_downloads.remove(&replaced);
+        self.read_offsets.remove(&replaced);
+
+        let cache_path = self.cache_dir.join(&gdrive_id);
+        match tokio::fs::rename(self.cache_dir.join(&own_id), &cache_path).await {
+            Ok(()) => {}
+            // Origen vacío sin caché: no debe quedar el contenido del original
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let _ = tokio::fs::remove_file(&cache_path).await;
+            }
+            Err(e) => {
+                error!("Error moviendo caché de {} a {}: {}", own_id, gdrive_id, e);
+                return Err(Errno::from(libc::EIO));
+            }
+        }
+        tracing::info!("💾 Inode {} reemplaza a {} como nueva versión de {}", inode, replaced, gdrive_id);
+        Ok(())
+    }
+
+    /// Encola un subárbol ignorado que se movió fuera de los patrones. Los
+    /// hijos que coinciden por su propio nombre siguen ignorados.
+    async fn unignore_tree(&self, inode: u64) -> Result<()> {
+        let mut pending = vec![inode];
+        while let Some(inode) = pending.pop() {
+            if self.db.unignore(inode).await.map_err(|_| Errno::from(libc::EIO))? {
+                self.record_upload_op(inode, UploadOp::Create).await;
+            }
+            let children = self.db.list_children(inode).await
+                .map_err(|_| Errno::from(libc::EIO))?;
+            pending.extend(
+                children.into_iter()
+                    .filter(|(_, name, _)| !self.ignore_rules.is_ignored_name(name))
+                    .map(|(child, _, _)| child),
+            );
+        }
+        Ok(())
+    }
+
+    /// Anota la operación en el diario del uploader (`upload_ops`), que fija
+    /// el orden de subida. Un fallo no deshace la operación: el inode ya está
+    /// dirty y se subirá igual, solo que sin orden garantizado.
+    async fn record_upload_op(&self, inode: u64, op: UploadOp) {
+        if let Err(e) = self.db.record_upload_op(inode, op).await {
+            tracing::warn!("⚠️ No se pudo anotar {:?} de inode {} en el diario: {:?}", op, inode, e);
+        }
+    }
+
+    /// Registra la intención de una mutación multi-paso antes de ejecutarla.
+    /// Si falla algún paso, la intención queda en el log y se reaplica al arrancar.
+    async fn begin_intent(&self, intent: FsIntent) -> Result<i64> {
+        let payload = intent.to_json().map_err(|_| Errno::from(libc::EIO))?;
+        self.db.log_intent(&payload).await.map_err(|e| {
+            error!("Error registrando intent {:?}: {}", intent, e);
+            Errno::from(libc::EIO)
+        })
+    }
+
+    /// Cierra una intención completada
+    async fn end_intent(&self, id: i64) {
+        if let Err(e) = self.db.complete_intent(id).await {
+            tracing::warn!("⚠️ Error cerrando intent {}: {}", id, e);
+        }
+    }
+
     /// Construye la ruta local de caché para un archivo de GDrive
     fn get_cache_path(&self, gdrive_id: &str) -> std::path::PathBuf {
         self.cache_dir.join(gdrive_id)
@@ -1477,27 +2138,16 @@ impl GDriveFS {
 
     /// Obtiene el nombre de un archivo dado su inode
     async fn get_file_name(&self, inode: u64) -> anyhow::Result<String> {
-        let name = sqlx::query_scalar::<_, String>(
-            "SELECT name FROM dentry WHERE child_inode = ? LIMIT 1"
-        )
-        .bind(inode as i64)
-        .fetch_optional(self.db.pool())
-        .await?
-        .unwrap_or_else(|| format!("file_{}", inode));
-        
+        let name = self.db.get_file_name(inode).await?
+            .unwrap_or_else(|| format!("file_{}", inode));
+
         Ok(name)
     }
 
     /// Obtiene el gdrive_id de un archivo dado su inode
     async fn get_gdrive_id(&self, inode: u64) -> anyhow::Result<String> {
-        let gdrive_id = sqlx::query_scalar::<_, String>(
-            "SELECT gdrive_id FROM inodes WHERE inode = ?"
-        )
-        .bind(inode as i64)
-        .fetch_one(self.db.pool())
-        .await?;
-        
-        Ok(gdrive_id)
+        self.db.get_gdrive_id(inode).await?
+            .ok_or_else(|| anyhow::anyhow!("Inode {} no encontrado", inode))
     }
 
     /// Lee datos desde un archivo de caché local
@@ -1515,13 +2165,63 @@ impl GDriveFS {
         let mut buffer = vec![0u8; size as usize];
         let bytes_read = file.read(&mut buffer).await?;
         buffer.truncate(bytes_read);
+        crate::utils::cache_crypto::decrypt_at(&file, offset, &mut buffer)?;
         
         Ok(buffer)
     }
 
 
+    /// `ensure_range_cached` para `read`, con reintento tras corrección 416 y
+    /// el error que ve el kernel. `false` si el rango quedó fuera del archivo
+    async fn cache_range_for_read(
+        &self,
+        inode: u64,
+        gdrive_id: &str,
+        offset: u64,
+        size: u32,
+        file_size: u64,
+        sequential: bool,
+    ) -> Result<bool> {
+        let mut effective_file_size = file_size;
+        let mut attempt = 0u8;
+        loop {
+            match self.ensure_range_cached(inode, gdrive_id, offset, size, effective_file_size, sequential).await {
+                Ok(()) => return Ok(true),
+                Err(e) => {
+                    let err_msg = format!("{}", e);
+                    if err_msg.contains("416") && attempt == 0 {
+                        // ensure_range_cached ya corrigió attrs.size en DB. Re-leer y reintentar.
+                        if let Ok(Some(new_size)) = self.db.get_size(inode).await {
+                            effective_file_size = new_size as u64;
+                            if effective_file_size == 0 || offset >= effective_file_size {
+                                return Ok(false);
+                            }
+                            tracing::info!("🔄 Reintentando descarga para inode {} con tamaño corregido: {}", inode, effective_file_size);
+                            attempt += 1;
+                            continue;
+                        }
+                    }
+                    if is_forbidden_download(&e) {
+                        self.failed_downloads.insert(inode);
+                        tracing::warn!("🚫 Inode {} marcado como descarga prohibida (403 en read)", inode);
+                    }
+                    // Sin conexión y contenido no cacheado: error distinguible de un fallo de E/S
+                    if crate::utils::network::report_error(&e) {
+                        debug!("📴 read() sin conexión para inode {}: rango no cacheado", inode);
+                        return Err(Errno::from(libc::ENETDOWN));
+                    }
+                    error!("Error descargando chunk para inode {}: {}", inode, e);
+                    return Err(Errno::from(libc::EIO));
+                }
+            }
+        }
+    }
+
     /// Asegura que un rango específico esté disponible en caché
-    /// Descarga solo los chunks faltantes EN PARALELO para mejor performance
+    /// Descarga solo los chunks faltantes EN PARALELO para mejor performance.
+    /// Con `sequential` el fallo trae una ventana mayor (`SEQUENTIAL_WINDOW`)
+    /// repartida en peticiones de `PARALLEL_PART_SIZE`. Si se abandona el
+    /// future (timeout o interrupción de `read`), aborta sus descargas.
     async fn ensure_range_cached(
         &self,
         inode: u64,
@@ -1529,21 +2229,13 @@ impl GDriveFS {
         offset: u64,
         size: u32,
         file_size: u64,
+        sequential: bool,
     ) -> anyhow::Result<()> {
         use tokio::io::{AsyncSeekExt, AsyncWriteExt};
         
-        // SMART BURST: Alinear el rango solicitado a bloques de 2MB para evitar micro-descargas asfixiantes
-        const BURST_SIZE: u64 = 2 * 1024 * 1024; // 2MB
-        let aligned_start = (offset / BURST_SIZE) * BURST_SIZE;
-        let aligned_end_raw = ((offset + size as u64 + BURST_SIZE - 1) / BURST_SIZE) * BURST_SIZE - 1;
-        let aligned_end = aligned_end_raw.min(file_size.saturating_sub(1));
-
-        let requested_start = aligned_start;
-        let requested_end = aligned_end;
-        
-        if requested_start >= file_size {
+        let Some((requested_start, requested_end)) = download_window(offset, size, file_size, sequential) else {
             return Ok(()); // Fuera de rango, nada que hacer
-        }
+        };
 
         let cache_path = self.get_cache_path(gdrive_id);
         
@@ -1577,27 +2269,58 @@ impl GDriveFS {
          }
 
         // Solo si el archivo no está completo, consultar la DB para rangos faltantes
-        let missing_ranges = self.db.get_missing_ranges(inode, requested_start, requested_end).await?;
+        let missing_ranges = split_ranges(
+            self.db.get_missing_ranges(inode, requested_start, requested_end).await?,
+            PARALLEL_PART_SIZE,
+        );
 
 
         if missing_ranges.is_empty() {
             tracing::debug!("✅ Rango ya cacheado: inode={} offset={} size={}", inode, offset, size);
+            crate::metrics::record_cache_hit();
             return Ok(());
         }
 
+        // Otro archivo idéntico ya descargado: enlazarlo (funciona también offline)
+        match self.content_store.adopt(&self.db, inode, file_size, &cache_path).await {
+            Ok(true) => {
+                crate::metrics::record_cache_hit();
+                return Ok(());
+            }
+            Ok(false) => {}
+            Err(e) => tracing::warn!("⚠️ No se pudo enlazar contenido idéntico para inode {}: {}", inode, e),
+        }
+
+        // Offline: servir lo que ya esté en caché (el rango exacto, no el bloque alineado)
+        if !crate::utils::network::is_online() {
+            let exact_end = (offset + size as u64).min(file_size).saturating_sub(1);
+            if self.db.get_missing_ranges(inode, offset, exact_end).await?.is_empty() {
+                crate::metrics::record_cache_hit();
+                return Ok(());
+            }
+            crate::metrics::record_cache_miss();
+            return Err(crate::utils::network::Offline.into());
+        }
+        crate::metrics::record_cache_miss();
+
         // Crear directorio de caché si no existe
         if let Some(parent) = cache_path.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
 
+        // Rellenar rangos de una caché compartida dañaría las demás copias
+        content_store::release_shared(&self.db, inode, &cache_path).await?;
+
         // Asegurar que el archivo existe (usando OpenOptions para NO truncar si ganó la carrera el prefetch)
         let cache_was_created = !cache_path.exists();
-        if cache_was_created {
-             let _ = tokio::fs::OpenOptions::new()
+        if cache_was_created
+            && let Ok(file) = tokio::fs::OpenOptions::new()
                 .write(true)
                 .create(true)
                 .open(&cache_path)
-                .await;
+                .await
+        {
+            crate::utils::cache_crypto::init_file(&file)?;
         }
 
         // OPTIMIZACIÓN: Descargar todos los rangos EN PARALELO
@@ -1639,8 +2362,18 @@ impl GDriveFS {
         let cache_path_owned = cache_path.clone();
         let history = self.history.clone();
 
+        // Los rangos que otra lectura o el Smart Streamer ya están bajando se esperan
+        let mut owned = Vec::new();
+        let mut waiters = Vec::new();
+        for (start, end) in missing_ranges {
+            match self.inflight.claim(inode, start, end) {
+                Claim::Owned(guard) => owned.push((start, end, guard)),
+                Claim::Busy(rx) => waiters.extend(rx),
+            }
+        }
+
         // Spawn tasks para descargar cada rango en paralelo
-        let download_tasks: Vec<_> = missing_ranges.into_iter().map(|(start, end)| {
+        let download_tasks: Vec<_> = owned.into_iter().map(|(start, end, guard)| {
             let drive_client = drive_client.clone();
             let db = db.clone();
             let gdrive_id = gdrive_id_owned.clone();
@@ -1650,21 +2383,17 @@ impl GDriveFS {
             let file_locks_clone = self.file_locks.clone();
 
             tokio::spawn(async move {
+                let _inflight = guard;
                 let chunk_size = (end - start + 1) as u32;
                 
                 tracing::debug!("📥 Descargando chunk: inode={} range={}-{} ({} bytes)", 
                                inode, start, end, chunk_size);
                 
                 // Descargar chunk
-                let data = drive_client.download_chunk(&gdrive_id, start, chunk_size).await?;
+                let mut data = drive_client.download_chunk(&gdrive_id, start, chunk_size).await?;
                 
                 // OBTENER LOCK ANTES DE MUTAR EL ARCHIVO CONJUNTO
-                let inode_lock = file_locks_clone
-                    .entry(inode)
-                    .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
-                    .clone();
-                    
-                let _guard = inode_lock.lock().await;
+                let _guard = file_locks_clone.lock(inode).await;
 
                 // Escribir en el archivo de caché en la posición correcta (con lock)
                 let mut file = tokio::fs::OpenOptions::new()
@@ -1672,6 +2401,8 @@ impl GDriveFS {
                     .open(&cache_path)
                     .await?;
                 
+                crate::utils::cache_crypto::encrypt_at(&file, start, &mut data)?;
+                
                 file.seek(std::io::SeekFrom::Start(start)).await?;
                 file.write_all(&data).await?;
                 file.flush().await?;
@@ -1690,6 +2421,7 @@ impl GDriveFS {
                 Ok::<_, anyhow::Error>((start, end))
             })
         }).collect();
+        let _abort = AbortOnDrop(download_tasks.iter().map(|task| task.abort_handle()).collect());
 
         // Esperar a que todas las descargas completen
         let results = futures_util::future::join_all(download_tasks).await;
@@ -1706,11 +2438,8 @@ impl GDriveFS {
                         tracing::warn!("🔄 416 detectado para inode {}: refrescando tamaño desde Drive", inode);
                         if let Ok(remote_file) = self.drive_client.get_file_metadata(gdrive_id).await {
                             let real_size = remote_file.size.unwrap_or(0);
-                            let _ = sqlx::query("UPDATE attrs SET size = ? WHERE inode = ?")
-                                .bind(real_size)
-                                .bind(inode as i64)
-                                .execute(self.db.pool())
-                                .await;
+                            let _ = self.db.set_size(inode, real_size).await;
+                            self.metadata_cache.invalidate_attrs(inode);
 
                             // Invalidar chunks y caché obsoletos para que el reintento descargue limpio
                             let _ = self.db.clear_chunks(inode).await;
@@ -1747,21 +2476,113 @@ impl GDriveFS {
             }
         }
 
+        if !waiters.is_empty() {
+            tracing::debug!("⏳ Esperando {} descargas en curso de inode {}", waiters.len(), inode);
+            InflightDownloads::wait(waiters).await;
+            // Si la otra descarga falló, queda algo por bajar: volver a intentarlo aquí
+            if !self.db.get_missing_ranges(inode, requested_start, requested_end).await?.is_empty() {
+                return Box::pin(self.ensure_range_cached(inode, gdrive_id, offset, size, file_size, sequential)).await;
+            }
+        }
+
         tracing::info!("✅ Todos los chunks descargados para inode {}", inode);
+        self.spawn_publish(inode, cache_path_owned);
         Ok(())
     }
 
+    /// `fsync` del archivo de caché de `inode`, si existe
+    async fn sync_cache_file(&self, inode: u64, datasync: bool) -> anyhow::Result<()> {
+        let Some(gdrive_id) = self.db.get_gdrive_id(inode).await? else {
+            return Ok(());
+        };
+        let file = match tokio::fs::File::open(self.get_cache_path(&gdrive_id)).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        if datasync {
+            file.sync_data().await?;
+        } else {
+            file.sync_all().await?;
+        }
+        Ok(())
+    }
+
+    /// Con `fsync_upload_timeout`, pide un ciclo inmediato del uploader si
+    /// `inode` tiene cambios y espera a que Drive los confirme. Un intento de
+    /// subida fallido, la espera agotada o un MD5 distinto responden `EIO`.
+    async fn wait_for_upload(&self, op: &str, inode: u64) -> Result<()> {
+        let Some(timeout) = self.fsync_upload_timeout else {
+            return Ok(());
+        };
+        let retries = match self.db.sync_state(inode).await {
+            Ok(Some(state)) if state.dirty => state.retry_count,
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                error!("Error consultando sync_state de inode {} en {}: {}", inode, op, e);
+                return Err(Errno::from(libc::EIO));
+            }
+        };
+
+        tracing::info!("⏳ {} de inode {}: esperando a que se suba a Drive", op, inode);
+        crate::sync::trigger::request_sync_now();
+        let confirmed = confirm_upload(&self.db, self.drive_client.as_ref(), &self.cache_dir, inode, retries);
+        match tokio::time::timeout(timeout, confirmed).await {
+            Ok(Ok(())) => {
+                tracing::info!("✅ {} de inode {}: subida confirmada", op, inode);
+                Ok(())
+            }
+            Ok(Err(e)) => {
+                error!("❌ {} de inode {}: {:#}", op, inode, e);
+                Err(Errno::from(libc::EIO))
+            }
+            Err(_) => {
+                tracing::warn!("⏱️ {} de inode {}: la subida no terminó en {:?}", op, inode, timeout);
+                Err(Errno::from(libc::EIO))
+            }
+        }
+    }
+
+    /// Reserva un `fh` para `open`/`create`, cuenta el handle abierto de
+    /// `inode` (la liberación de caché lo respeta) y recuerda si se abrió para
+    /// escribir o con `O_APPEND`
+    fn open_handle(&self, inode: u64, flags: u32) -> u64 {
+        let fh = self.next_fh.fetch_add(1, Ordering::Relaxed);
+        self.file_locks.opened(inode);
+        if flags as i32 & libc::O_ACCMODE != libc::O_RDONLY {
+            self.write_handles.insert(fh);
+        }
+        if flags as i32 & libc::O_APPEND != 0 {
+            self.append_handles.insert(fh);
+        }
+        fh
+    }
+
+    /// Publica en background la caché de `inode` si quedó completa (calcular
+    /// el MD5 de un archivo grande no debe frenar la lectura)
+    fn spawn_publish(&self, inode: u64, cache_path: std::path::PathBuf) {
+        let store = self.content_store.clone();
+        let db = self.db.clone();
+        let file_locks = self.file_locks.clone();
+        tokio::spawn(async move {
+            if let Err(e) = store.publish(&db, &file_locks, inode, &cache_path).await {
+                tracing::debug!("No se pudo publicar la caché de inode {}: {:?}", inode, e);
+            }
+        });
+    }
+
 
 
     /// Pre-descarga un archivo completo en background (para archivos pequeños)
     #[allow(dead_code)]
     async fn prefetch_entire_file(
         db: &Arc<MetadataRepository>,
-        drive_client: &Arc<DriveClient>,
+        drive_client: &Arc<dyn DriveApi>,
         inode: u64,
         gdrive_id: &str,
         cache_path: &std::path::Path,
         file_size: u64,
+        file_locks: &FileLocks,
     ) -> anyhow::Result<()> {
         use tokio::io::{AsyncSeekExt, AsyncWriteExt};
         
@@ -1779,9 +2600,8 @@ impl GDriveFS {
             let data = drive_client.download_chunk(gdrive_id, 0, file_size as u32).await?;
             
             // Escribir a caché
-            let mut file = tokio::fs::File::create(cache_path).await?;
-            file.write_all(&data).await?;
-            file.flush().await?;
+            let _guard = file_locks.lock(inode).await;
+            crate::utils::cache_crypto::write_file(cache_path, data).await?;
             
             // Registrar en DB como completamente cacheado
             db.add_cached_chunk(inode, 0, file_size - 1).await?;
@@ -1796,12 +2616,19 @@ impl GDriveFS {
         
         tracing::info!("📥 Descargando archivo grande en chunks paralelos: {} bytes", file_size);
         
-        // Crear el archivo de caché (sin truncar si ya existe)
-        let _ = tokio::fs::OpenOptions::new()
+        // Crear el archivo de caché (sin truncar si ya existe). Una caché con
+        // rangos ya escritos sigue en claro: con clave se descifrarían como basura
+        let file = tokio::fs::OpenOptions::new()
             .write(true)
             .create(true)
             .open(cache_path)
             .await?;
+        {
+            let _guard = file_locks.lock(inode).await;
+            if file.metadata().await?.len() == 0 {
+                crate::utils::cache_crypto::init_file(&file)?;
+            }
+        }
         
         // Calcular rangos de chunks
         let mut chunks: Vec<(u64, u64)> = Vec::new();
@@ -1819,16 +2646,19 @@ impl GDriveFS {
                 let gdrive_id = gdrive_id.to_string();
                 let db = db.clone();
                 let cache_path = cache_path.to_path_buf();
+                let file_locks = file_locks.clone();
                 
                 tokio::spawn(async move {
                     let chunk_size = (end - start + 1) as u32;
-                    let data = drive_client.download_chunk(&gdrive_id, start, chunk_size).await?;
+                    let mut data = drive_client.download_chunk(&gdrive_id, start, chunk_size).await?;
                     
                     // Escribir en la posición correcta del archivo
+                    let _guard = file_locks.lock(inode).await;
                     let mut file = tokio::fs::OpenOptions::new()
                         .write(true)
                         .open(&cache_path)
                         .await?;
+                    crate::utils::cache_crypto::encrypt_at(&file, start, &mut data)?;
                     file.seek(std::io::SeekFrom::Start(start)).await?;
                     file.write_all(&data).await?;
                     file.flush().await?;
@@ -1857,19 +2687,24 @@ impl GDriveFS {
     /// Descarga continua y agresiva de un archivo en background (Para maximizar el ancho de banda)
     async fn start_background_download_stream(
         db: Arc<MetadataRepository>,
-        drive_client: Arc<DriveClient>,
+        drive_client: Arc<dyn DriveApi>,
         inode: u64,
         gdrive_id: String,
         cache_path: std::path::PathBuf,
         file_size: u64,
-        file_locks: Arc<DashMap<u64, Arc<tokio::sync::Mutex<()>>>>,
+        file_locks: FileLocks,
+        inflight: Arc<InflightDownloads>,
         history: Arc<ActionHistory>,
         fuse_downloads_map: Arc<tokio::sync::Mutex<HashMap<u64, (Option<u64>, usize, u64)>>>,
         read_offsets: Arc<DashMap<u64, u64>>,
         is_media: bool,
+        content_store: ContentStore,
     ) -> anyhow::Result<()> {
         use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 
+        // Mientras dure el streaming el uploader no sube este archivo
+        let _hydration = inflight.hydrate(inode);
+
         // --- QUICK CACHE CHECK ---
         // Verificar instantáneamente si el archivo ya está 100% descargado
         if file_size > 0 {
@@ -1879,6 +2714,9 @@ impl GDriveFS {
                 tracing::debug!("✅ Archivo ({}) previamente cacheado. Streaming abortado.", file_size);
                 return Ok(());
             }
+            if content_store.adopt(&db, inode, file_size, &cache_path).await? {
+                return Ok(());
+            }
         }
 
         // Crear directorio de caché si no existe
@@ -1886,24 +2724,24 @@ impl GDriveFS {
             tokio::fs::create_dir_all(parent).await?;
         }
 
+        // Rellenar rangos de una caché compartida dañaría las demás copias
+        content_store::release_shared(&db, inode, &cache_path).await?;
+
         // Asegurar que el archivo existe
         let cache_was_created = !cache_path.exists();
-        if cache_was_created {
-             let _ = tokio::fs::OpenOptions::new()
+        if cache_was_created
+            && let Ok(file) = tokio::fs::OpenOptions::new()
                 .write(true)
                 .create(true)
                 .open(&cache_path)
-                .await;
+                .await
+        {
+            crate::utils::cache_crypto::init_file(&file)?;
         }
 
         // Obtener nombre del archivo para el transfer
-        let file_name = sqlx::query_scalar::<_, String>(
-            "SELECT name FROM dentry WHERE child_inode = ? LIMIT 1"
-        )
-        .bind(inode as i64)
-        .fetch_optional(db.pool())
-        .await?
-        .unwrap_or_else(|| format!("file_{}", inode));
+        let file_name = db.get_file_name(inode).await?
+            .unwrap_or_else(|| format!("file_{}", inode));
 
         // --- FUSE DOWNLOAD PROGRESS TRACKING ---
         // La transferencia ya fue inicializada oficialmente por la heurística de read()
@@ -2009,7 +2847,16 @@ impl GDriveFS {
             // 4. Descargar el lote actual
             for batch in pending_chunks.chunks(MAX_CONCURRENT) {
                 let mut download_tasks = Vec::new();
+                let mut waiters = Vec::new();
                 for &(start, end) in batch {
+                    // Ya lo está bajando una lectura: esperarla en lugar de repetirlo
+                    let guard = match inflight.claim(inode, start, end) {
+                        Claim::Owned(guard) => guard,
+                        Claim::Busy(rx) => {
+                            waiters.extend(rx);
+                            continue;
+                        }
+                    };
                     let db_clone = db.clone();
                     let client_clone = drive_client.clone();
                     let gdrive_id_clone = gdrive_id.clone();
@@ -2019,16 +2866,14 @@ impl GDriveFS {
                     let tid_clone = transfer_id;
 
                     download_tasks.push(tokio::spawn(async move {
+                        let _inflight = guard;
                         let m_size = (end - start + 1) as u32;
-                        let data = client_clone.download_chunk(&gdrive_id_clone, start, m_size).await?;
+                        let mut data = client_clone.download_chunk(&gdrive_id_clone, start, m_size).await?;
                         
-                        let inode_lock = file_locks_clone
-                            .entry(inode)
-                            .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
-                            .clone();
-                        let _guard = inode_lock.lock().await;
+                        let _guard = file_locks_clone.lock(inode).await;
 
                         let mut file = tokio::fs::OpenOptions::new().write(true).open(&cache_path_clone).await?;
+                        crate::utils::cache_crypto::encrypt_at(&file, start, &mut data)?;
                         file.seek(std::io::SeekFrom::Start(start)).await?;
                         file.write_all(&data).await?;
                         file.flush().await?;
@@ -2046,6 +2891,7 @@ impl GDriveFS {
                 for res in futures_util::future::join_all(download_tasks).await {
                     if let Err(e) = res { tracing::error!("Task panic: {}", e); }
                 }
+                InflightDownloads::wait(waiters).await;
             }
             
             _iteration += 1;
@@ -2068,7 +2914,300 @@ impl GDriveFS {
 
         tracing::info!("✅ {} inteligente completado para: {}",
                       if is_media { "Streaming" } else { "Descarga" }, file_name);
+        if let Err(e) = content_store.publish(&db, &file_locks, inode, &cache_path).await {
+            tracing::debug!("No se pudo publicar la caché de inode {}: {:?}", inode, e);
+        }
         Ok(())
     }
 }
 
+
+/// Valor de `XATTR_CREATED`: `2024-03-01T09:30:00Z`
+fn format_created_time(secs: i64) -> Option<String> {
+    google_drive3::chrono::DateTime::from_timestamp(secs, 0)
+        .map(|dt| dt.to_rfc3339_opts(google_drive3::chrono::SecondsFormat::Secs, true))
+}
+
+/// Respuesta de getxattr/listxattr: con `size == 0` el kernel solo pide la
+/// longitud; si el buffer no alcanza corresponde ERANGE.
+fn xattr_reply(value: Vec<u8>, size: u32) -> Result<ReplyXAttr> {
+    if size == 0 {
+        Ok(ReplyXAttr::Size(value.len() as u32))
+    } else if (size as usize) < value.len() {
+        Err(Errno::from(libc::ERANGE))
+    } else {
+        Ok(ReplyXAttr::Data(value.into()))
+    }
+}
+
+/// Drive rechazó la descarga de forma permanente (sin permiso de descarga,
+/// archivo marcado como abusivo). La cuota de descargas agotada no cuenta:
+/// se recupera sola al cabo de unas horas
+fn is_forbidden_download(error: &anyhow::Error) -> bool {
+    matches!(DriveError::find(error), Some(DriveError::InsufficientPermissions(_)))
+}
+
+/// Rango `[start, end]` (inclusivo) a tener en caché para servir una lectura:
+/// la lectura alineada a `BURST_SIZE`, o a `SEQUENTIAL_WINDOW` si es secuencial,
+/// recortada al tamaño del archivo. `None` si la lectura empieza fuera del archivo.
+fn download_window(offset: u64, size: u32, file_size: u64, sequential: bool) -> Option<(u64, u64)> {
+    if offset >= file_size {
+        return None;
+    }
+    let block = if sequential { SEQUENTIAL_WINDOW } else { BURST_SIZE };
+    let start = (offset / block) * block;
+    let end = (offset + size as u64).div_ceil(block) * block - 1;
+    Some((start, end.min(file_size - 1)))
+}
+
+/// Parte los rangos `[start, end]` en trozos de como mucho `part_size` bytes
+fn split_ranges(ranges: Vec<(u64, u64)>, part_size: u64) -> Vec<(u64, u64)> {
+    ranges
+        .into_iter()
+        .flat_map(|(start, end)| {
+            (start..=end)
+                .step_by(part_size as usize)
+                .map(move |part_start| (part_start, (part_start + part_size - 1).min(end)))
+        })
+        .collect()
+}
+
+/// Aborta las descargas de un `ensure_range_cached` abandonado: soltar sus
+/// `InflightGuard` despierta a las demás lecturas, que vuelven a reclamar el rango
+struct AbortOnDrop(Vec<tokio::task::AbortHandle>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+/// Offset real y tamaño resultante de escribir `len` bytes en un archivo de
+/// `size` bytes. En `O_APPEND` el offset del kernel puede venir de un tamaño
+/// obsoleto (otro handle escribió después): se escribe al final actual.
+fn write_extent(size: u64, offset: u64, len: u64, append: bool) -> (u64, u64) {
+    let offset = if append { size } else { offset };
+    (offset, size.max(offset + len))
+}
+
+/// Espera a que el uploader deje `inode` sin cambios pendientes y comprueba
+/// que el MD5 de Drive es el de la caché. Falla si mientras tanto un intento
+/// de subida falla (`retry_count` supera `retries`).
+async fn confirm_upload(
+    db: &MetadataRepository,
+    drive_client: &dyn DriveApi,
+    cache_dir: &std::path::Path,
+    inode: u64,
+    retries: u32,
+) -> anyhow::Result<()> {
+    loop {
+        match db.sync_state(inode).await? {
+            Some(state) if state.dirty => {
+                if state.retry_count > retries {
+                    let message = db.upload_error(inode).await?.unwrap_or_default();
+                    anyhow::bail!("la subida falló: {}", message);
+                }
+            }
+            _ => break,
+        }
+        tokio::time::sleep(UPLOAD_POLL_INTERVAL).await;
+    }
+
+    // Tras subir un archivo nuevo su gdrive_id cambia: leerlo ahora
+    let gdrive_id = db.get_gdrive_id(inode).await?
+        .ok_or_else(|| anyhow::anyhow!("inode {} ya no existe", inode))?;
+    let local_md5 = crate::utils::hash::compute_file_md5(cache_dir.join(&gdrive_id)).await?;
+    let remote_md5 = drive_client.get_file_md5(&gdrive_id).await?;
+    if remote_md5.as_deref() != Some(local_md5.as_str()) {
+        anyhow::bail!("el MD5 de Drive ({:?}) no coincide con la caché ({})", remote_md5, local_md5);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[rstest]
+    #[case::no_permission(DriveError::InsufficientPermissions("403 cannotDownloadAbusiveFile".into()), true)]
+    #[case::download_quota(DriveError::QuotaExceeded("403 downloadQuotaExceeded".into()), false)]
+    #[case::rate_limited(DriveError::RateLimited { retry_after: None, message: "429".into() }, false)]
+    fn test_is_forbidden_download(#[case] error: DriveError, #[case] expected: bool) {
+        assert_eq!(is_forbidden_download(&error.into()), expected);
+    }
+
+    #[rstest]
+    #[case::random_read(3 * MB, 128 * 1024, 100 * MB, false, Some((2 * MB, 4 * MB - 1)))]
+    #[case::sequential_read(3 * MB, 128 * 1024, 100 * MB, true, Some((0, 8 * MB - 1)))]
+    #[case::sequential_next_window(9 * MB, 128 * 1024, 100 * MB, true, Some((8 * MB, 16 * MB - 1)))]
+    #[case::straddles_window(8 * MB - 10, 20, 100 * MB, true, Some((0, 16 * MB - 1)))]
+    #[case::clamped_to_file(3 * MB, 128 * 1024, 5 * MB, true, Some((0, 5 * MB - 1)))]
+    #[case::past_end(5 * MB, 10, 5 * MB, true, None)]
+    fn test_download_window(
+        #[case] offset: u64,
+        #[case] size: u32,
+        #[case] file_size: u64,
+        #[case] sequential: bool,
+        #[case] expected: Option<(u64, u64)>,
+    ) {
+        assert_eq!(download_window(offset, size, file_size, sequential), expected);
+    }
+
+    #[rstest]
+    #[case::small_range(vec![(0, 99)], vec![(0, 99)])]
+    #[case::exact_parts(vec![(0, 2 * MB - 1)], vec![(0, MB - 1), (MB, 2 * MB - 1)])]
+    #[case::remainder(vec![(MB, 3 * MB + 9)], vec![(MB, 2 * MB - 1), (2 * MB, 3 * MB - 1), (3 * MB, 3 * MB + 9)])]
+    #[case::several_gaps(vec![(0, 9), (5 * MB, 5 * MB)], vec![(0, 9), (5 * MB, 5 * MB)])]
+    fn test_split_ranges(#[case] ranges: Vec<(u64, u64)>, #[case] expected: Vec<(u64, u64)>) {
+        assert_eq!(split_ranges(ranges, MB), expected);
+    }
+
+    #[rstest]
+    #[case::root(1, 5)]
+    #[case::folder(42, 2)]
+    #[case::starred(STARRED_INODE, 2)]
+    fn test_fixed_dir_entries_precede_children(#[case] parent: u64, #[case] expected: usize) {
+        let entries = fixed_dir_entries(parent);
+        assert_eq!(entries.len(), expected);
+        assert_eq!(entries[0], (parent, ".".to_string(), true));
+        assert_eq!(entries[1].1, "..");
+    }
+
+    #[rstest]
+    #[case::epoch(0, Some("1970-01-01T00:00:00Z"))]
+    #[case::drive_date(1_709_285_400, Some("2024-03-01T09:30:00Z"))]
+    #[case::out_of_range(i64::MAX, None)]
+    fn test_format_created_time(#[case] secs: i64, #[case] expected: Option<&str>) {
+        assert_eq!(format_created_time(secs).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case::overwrite_inside(100, 10, 5, false, (10, 100))]
+    #[case::extend(100, 98, 5, false, (98, 103))]
+    #[case::past_end(100, 200, 5, false, (200, 205))]
+    #[case::append_stale_offset(100, 60, 5, true, (100, 105))]
+    #[case::append_empty(0, 0, 5, true, (0, 5))]
+    fn test_write_extent(
+        #[case] size: u64,
+        #[case] offset: u64,
+        #[case] len: u64,
+        #[case] append: bool,
+        #[case] expected: (u64, u64),
+    ) {
+        assert_eq!(write_extent(size, offset, len, append), expected);
+    }
+
+    #[tokio::test]
+    async fn test_excluded_folder_and_computers_refuse_new_entries() {
+        use crate::gdrive::mock::MockDriveClient;
+
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        let archivo = db.get_or_create_inode("folder-archivo").await.unwrap();
+        let docs = db.get_or_create_inode("folder-docs").await.unwrap();
+        let computers = db.computers_inode().await.unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let fs = GDriveFS::new(db, Arc::new(MockDriveClient::new()), cache.path(), Arc::new(ActionHistory::new()))
+            .with_exclusions(Arc::new(Exclusions::from_ids(&["folder-archivo"])));
+
+        assert_eq!(fs.ensure_accepts_entries(computers).await.unwrap_err(), Errno::from(libc::EACCES));
+
+        let err = fs.ensure_accepts_entries(archivo).await.unwrap_err();
+        assert_eq!(err, Errno::from(libc::EACCES));
+        assert!(fs.ensure_accepts_entries(docs).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_new_session_closes_stale_handles() {
+        use crate::gdrive::mock::MockDriveClient;
+
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        let history = Arc::new(ActionHistory::new());
+        let cache = tempfile::tempdir().unwrap();
+        let fs = GDriveFS::new(db, Arc::new(MockDriveClient::new()), cache.path(), history.clone());
+        let t_id = history.start_transfer("video.mp4", TransferOp::Stream, 100);
+        fs.fuse_downloads.lock().await.insert(7, (Some(t_id), 2, 50));
+        fs.append_handles.insert(3);
+        fs.write_handles.insert(3);
+
+        let session = fs.new_session().await;
+
+        assert!(history.active_transfers().is_empty(), "la descarga de la sesión perdida se cierra");
+        assert!(session.fuse_downloads.lock().await.is_empty());
+        assert!(fs.append_handles.is_empty());
+        assert!(fs.write_handles.is_empty());
+        assert!(Arc::ptr_eq(&fs.metadata_cache, &session.metadata_cache), "la caché se comparte");
+    }
+
+    /// Solo el `flush` de los handles de escritura espera la subida
+    #[rstest]
+    #[case::read_only(libc::O_RDONLY, false, false)]
+    #[case::write_only(libc::O_WRONLY, true, false)]
+    #[case::read_write_append(libc::O_RDWR | libc::O_APPEND, true, true)]
+    #[tokio::test]
+    async fn test_open_handle_tracks_write_handles(#[case] flags: i32, #[case] write: bool, #[case] append: bool) {
+        use crate::gdrive::mock::MockDriveClient;
+
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        let cache = tempfile::tempdir().unwrap();
+        let fs = GDriveFS::new(db, Arc::new(MockDriveClient::new()), cache.path(), Arc::new(ActionHistory::new()));
+
+        let fh = fs.open_handle(7, flags as u32);
+
+        assert_eq!(fs.write_handles.contains(&fh), write);
+        assert_eq!(fs.append_handles.contains(&fh), append);
+        assert!(fs.file_locks.is_open(7), "la liberación de caché no debe tocarlo");
+    }
+
+    /// Uploader simulado: tras un momento deja el archivo en Drive y lo marca
+    /// subido, o registra un intento fallido
+    #[rstest]
+    #[case::uploaded(Some(b"editado".as_slice()), None)]
+    #[case::upload_failed(None, Some("la subida falló"))]
+    #[case::remote_mismatch(Some(b"otra cosa".as_slice()), Some("no coincide"))]
+    #[tokio::test]
+    async fn test_confirm_upload(#[case] uploaded: Option<&'static [u8]>, #[case] error: Option<&str>) {
+        use crate::gdrive::mock::{MOCK_ROOT_ID, MockDriveClient};
+
+        let mock = Arc::new(MockDriveClient::new());
+        mock.add_file("file-nota", "nota.txt", MOCK_ROOT_ID, b"original");
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        let client: Arc<dyn DriveApi> = mock.clone();
+        crate::sync::bootstrap::bootstrap_level1(&db, &client, MOCK_ROOT_ID).await.unwrap();
+        let inode = db.get_inode_by_gdrive_id("file-nota").await.unwrap().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        std::fs::write(cache.path().join("file-nota"), b"editado").unwrap();
+        db.mark_dirty(inode).await.unwrap();
+
+        let uploader = {
+            let (db, mock) = (db.clone(), mock.clone());
+            tokio::spawn(async move {
+                tokio::time::sleep(UPLOAD_POLL_INTERVAL).await;
+                match uploaded {
+                    Some(content) => {
+                        mock.modify_remote("file-nota", content);
+                        db.clear_dirty(inode).await.unwrap();
+                    }
+                    None => {
+                        db.set_upload_error(inode, "sin conexión", Some(0)).await.unwrap();
+                    }
+                }
+            })
+        };
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            confirm_upload(&db, client.as_ref(), cache.path(), inode, 0),
+        ).await.expect("la espera termina");
+        uploader.await.unwrap();
+
+        match error {
+            None => result.unwrap(),
+            Some(message) => assert!(format!("{:#}", result.unwrap_err()).contains(message)),
+        }
+    }
+}
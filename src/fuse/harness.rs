@@ -0,0 +1,374 @@
+//! Arnés de tests end-to-end de FUSE
+//!
+//! Monta `GDriveFS` en un tempdir sobre una DB en memoria y un
+//! `MockDriveClient`, y ejecuta comandos reales (`cp`, `mv`, `rm`, `cat`)
+//! contra el punto de montaje: las peticiones pasan por el kernel igual que
+//! las de Nautilus. Los tests verifican después el estado de la DB, la caché
+//! y las llamadas al mock.
+//!
+//! Requiere `/dev/fuse` y `fusermount3` (o `fusermount`). Donde no se puede
+//! montar (contenedores, CI sin FUSE) `FuseHarness::mount` devuelve `None`
+//! y el test termina sin comprobar nada, con un aviso en stderr.
+
+use fuse3::MountOptions;
+use fuse3::raw::{MountHandle, Session};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::db::MetadataRepository;
+use crate::fuse::GDriveFS;
+use crate::gdrive::DriveApi;
+use crate::gdrive::mock::{MOCK_ROOT_ID, MockDriveClient};
+use crate::gui::history::ActionHistory;
+
+/// Tiempo máximo para montar (fusermount colgado = FUSE no usable)
+const MOUNT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// `GDriveFS` montado sobre un Drive falso
+pub struct FuseHarness {
+    dir: tempfile::TempDir,
+    pub mock: Arc<MockDriveClient>,
+    pub db: Arc<MetadataRepository>,
+    handle: Option<MountHandle>,
+}
+
+impl FuseHarness {
+    /// Vuelca el root de `mock` a una DB en memoria y monta el sistema de
+    /// archivos. `None` si este sistema no permite montar FUSE sin privilegios.
+    pub async fn mount(mock: MockDriveClient) -> Option<Self> {
+        Self::mount_with(mock, false, |fs| fs).await
+    }
+
+    /// Igual que `mount`, con la caché de escritura del kernel
+    /// (`FUSE_WRITEBACK_CACHE`) según `writeback` y los builders de `configure`
+    pub async fn mount_with(
+        mock: MockDriveClient,
+        writeback: bool,
+        configure: impl FnOnce(GDriveFS) -> GDriveFS,
+    ) -> Option<Self> {
+        if !Path::new("/dev/fuse").exists() {
+            eprintln!("⏭️ /dev/fuse no existe: se omite el test FUSE");
+            return None;
+        }
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        for sub in ["mnt", "cache", "local"] {
+            std::fs::create_dir_all(dir.path().join(sub)).expect("directorios del arnés");
+        }
+
+        let db = Arc::new(MetadataRepository::in_memory().await.expect("DB en memoria"));
+        let mock = Arc::new(mock);
+        let client: Arc<dyn DriveApi> = mock.clone();
+        crate::sync::bootstrap::bootstrap_level1(&db, &client, MOCK_ROOT_ID)
+            .await
+            .expect("bootstrap del Drive falso");
+
+        let fs = configure(
+            GDriveFS::new(db.clone(), client, dir.path().join("cache"), Arc::new(ActionHistory::new()))
+                .with_writeback_cache(writeback),
+        );
+
+        let mut options = MountOptions::default();
+        options
+            .uid(unsafe { libc::getuid() })
+            .gid(unsafe { libc::getgid() })
+            .fs_name("gdrivexp-test")
+            .default_permissions(true)
+            .write_back(writeback);
+
+        let mount_path = dir.path().join("mnt");
+        let mounted = tokio::time::timeout(
+            MOUNT_TIMEOUT,
+            Session::new(options).mount_with_unprivileged(fs, &mount_path),
+        )
+        .await;
+        let handle = match mounted {
+            Ok(Ok(handle)) => handle,
+            Ok(Err(e)) => {
+                eprintln!("⏭️ No se pudo montar FUSE ({}): se omite el test", e);
+                return None;
+            }
+            Err(_) => {
+                eprintln!("⏭️ El montaje FUSE no respondió: se omite el test");
+                return None;
+            }
+        };
+
+        Some(Self { dir, mock, db, handle: Some(handle) })
+    }
+
+    /// Ruta dentro del punto de montaje
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.dir.path().join("mnt").join(relative)
+    }
+
+    /// Crea un archivo fuera del montaje (origen para `cp`/`mv`)
+    pub fn local_file(&self, name: &str, content: &[u8]) -> PathBuf {
+        let path = self.dir.path().join("local").join(name);
+        std::fs::write(&path, content).expect("archivo local");
+        path
+    }
+
+    /// Ejecuta `script` con `sh -c` dentro del punto de montaje y devuelve su
+    /// stdout. Falla el test si el comando falla.
+    pub async fn sh(&self, script: &str) -> String {
+        let cwd = self.path("");
+        let script_owned = script.to_string();
+        // El comando bloquea hasta que FUSE responde: fuera de los workers de Tokio
+        let output = tokio::task::spawn_blocking(move || {
+            std::process::Command::new("sh")
+                .arg("-c")
+                .arg(&script_owned)
+                .current_dir(cwd)
+                .output()
+        })
+        .await
+        .expect("hilo del comando")
+        .expect("no se pudo ejecutar sh");
+
+        assert!(
+            output.status.success(),
+            "`{}` falló ({}): {}",
+            script,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    }
+
+    /// Resuelve una ruta relativa al root (`"Docs/nota.txt"`) en la DB
+    pub async fn lookup(&self, relative: &str) -> Option<u64> {
+        let mut inode = 1;
+        for component in relative.split('/').filter(|c| !c.is_empty()) {
+            inode = self.db.lookup(inode, component).await.expect("lookup en DB")?;
+        }
+        Some(inode)
+    }
+
+    /// Contenido en caché (descifrado) de un archivo
+    pub async fn cached_content(&self, inode: u64) -> Vec<u8> {
+        let gdrive_id = self.db.get_gdrive_id(inode).await.unwrap().expect("inode sin gdrive_id");
+        let path = self.dir.path().join("cache").join(gdrive_id);
+        crate::utils::cache_crypto::read_file(&path).await.expect("archivo en caché")
+    }
+
+    /// Desmonta y espera a que termine la sesión FUSE
+    pub async fn unmount(mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.unmount().await.expect("desmontaje FUSE");
+        }
+    }
+}
+
+impl Drop for FuseHarness {
+    fn drop(&mut self) {
+        // Test que falló antes de `unmount()`: no dejar un montaje colgado en /tmp
+        if self.handle.is_some() {
+            let _ = crate::utils::mount::unmount(self.dir.path().join("mnt"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drive con un único archivo `nota.txt` en el root
+    fn drive_with_note() -> MockDriveClient {
+        let mock = MockDriveClient::new();
+        mock.add_file("file-nota", "nota.txt", MOCK_ROOT_ID, b"hola desde Drive");
+        mock
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_cat_downloads_remote_file() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        let Some(fx) = FuseHarness::mount(drive_with_note()).await else { return };
+
+        assert_eq!(fx.sh("cat nota.txt").await, "hola desde Drive");
+
+        let inode = fx.lookup("nota.txt").await.unwrap();
+        assert!(fx.mock.calls("download_chunk") >= 1);
+        assert!(fx.db.has_any_chunks(inode).await.unwrap());
+        assert_eq!(fx.cached_content(inode).await, b"hola desde Drive");
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_cp_creates_dirty_file() {
+        let Some(fx) = FuseHarness::mount(drive_with_note()).await else { return };
+        let source = fx.local_file("origen.txt", b"contenido nuevo");
+
+        fx.sh(&format!("cp '{}' nuevo.txt", source.display())).await;
+
+        let inode = fx.lookup("nuevo.txt").await.expect("nuevo.txt en la DB");
+        assert!(fx.db.is_pending_create(inode).await.unwrap(), "aún sin subir");
+        assert!(fx.db.is_dirty(inode).await.unwrap());
+        assert_eq!(fx.db.get_attrs(inode).await.unwrap().size, 15);
+        assert_eq!(fx.cached_content(inode).await, b"contenido nuevo");
+        assert_eq!(fx.mock.calls("upload_file"), 0, "FUSE no sube: lo hace el uploader");
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_mv_moves_into_new_folder() {
+        let Some(fx) = FuseHarness::mount(drive_with_note()).await else { return };
+        let inode = fx.lookup("nota.txt").await.unwrap();
+
+        fx.sh("mkdir Docs && mv nota.txt Docs/renombrada.txt").await;
+
+        assert_eq!(fx.lookup("nota.txt").await, None);
+        assert_eq!(fx.lookup("Docs/renombrada.txt").await, Some(inode), "mismo inode tras mover");
+        assert!(fx.db.is_dirty(inode).await.unwrap(), "el movimiento queda pendiente de subir");
+        assert_eq!(fx.sh("ls Docs").await, "renombrada.txt\n");
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_atomic_save_becomes_new_version() {
+        let Some(fx) = FuseHarness::mount(drive_with_note()).await else { return };
+
+        fx.sh("printf 'versión nueva' > .nota.txt.tmp-guardado && mv .nota.txt.tmp-guardado nota.txt").await;
+
+        let inode = fx.lookup("nota.txt").await.expect("nota.txt sigue en la DB");
+        assert_eq!(fx.db.get_gdrive_id(inode).await.unwrap().as_deref(), Some("file-nota"), "mismo archivo de Drive");
+        assert!(!fx.db.is_pending_create(inode).await.unwrap(), "no se crea un archivo nuevo");
+        assert!(fx.db.is_dirty(inode).await.unwrap(), "el contenido queda pendiente de subir");
+        assert_eq!(fx.cached_content(inode).await, "versión nueva".as_bytes());
+        assert_eq!(fx.sh("cat nota.txt").await, "versión nueva");
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_mv_over_drive_file_updates_destination() {
+        let mock = drive_with_note();
+        mock.add_file("file-otra", "otra.txt", MOCK_ROOT_ID, b"otra versi\xc3\xb3n");
+        let Some(fx) = FuseHarness::mount(mock).await else { return };
+        let source = fx.lookup("otra.txt").await.unwrap();
+        let replaced = fx.lookup("nota.txt").await.unwrap();
+
+        fx.sh("cat otra.txt > /dev/null && mv -f otra.txt nota.txt").await;
+
+        assert_eq!(fx.lookup("nota.txt").await, Some(source));
+        assert_eq!(fx.db.get_gdrive_id(source).await.unwrap().as_deref(), Some("file-nota"), "se actualiza el destino");
+        assert!(fx.db.is_dirty(source).await.unwrap(), "el contenido queda pendiente de subir");
+        assert_eq!(fx.cached_content(source).await, "otra versión".as_bytes());
+
+        // El archivo de Drive del origen queda huérfano: se envía a la papelera
+        assert_eq!(fx.db.get_inode_by_gdrive_id("file-otra").await.unwrap(), Some(replaced));
+        assert!(fx.db.is_dirty(replaced).await.unwrap());
+        assert!(fx.db.pending_upload_ops().await.unwrap()[&replaced].structural);
+        assert_eq!(fx.sh("ls").await, "nota.txt\n");
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_concurrent_appends_do_not_overwrite_each_other() {
+        let Some(fx) = FuseHarness::mount(drive_with_note()).await else { return };
+
+        // Dos procesos con su propio descriptor O_APPEND sobre el mismo archivo
+        fx.sh("touch log.txt && \
+               (exec 3>>log.txt; for i in $(seq 1 40); do echo aaaa >&3; done) & \
+               (exec 4>>log.txt; for i in $(seq 1 40); do echo bbbb >&4; done) & \
+               wait").await;
+
+        let inode = fx.lookup("log.txt").await.unwrap();
+        let content = String::from_utf8(fx.cached_content(inode).await).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 80, "ninguna línea se pierde");
+        assert_eq!(lines.iter().filter(|line| **line == "aaaa").count(), 40);
+        assert_eq!(lines.iter().filter(|line| **line == "bbbb").count(), 40);
+        assert_eq!(fx.db.get_attrs(inode).await.unwrap().size, 400);
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_writeback_cache_keeps_appends_and_large_copies() {
+        let Some(fx) = FuseHarness::mount_with(drive_with_note(), true, |fs| fs).await else { return };
+        let source: Vec<u8> = (0..3 * 1024 * 1024 + 17).map(|i| (i % 251) as u8).collect();
+        let local = fx.local_file("grande.bin", &source);
+
+        // El kernel resuelve O_APPEND y agrupa las escrituras antes de enviarlas
+        fx.sh(&format!(
+            "cp {} grande.bin && \
+             (exec 3>>log.txt; for i in $(seq 1 40); do echo aaaa >&3; done) & \
+             (exec 4>>log.txt; for i in $(seq 1 40); do echo bbbb >&4; done) & \
+             wait",
+            local.display()
+        )).await;
+
+        let big = fx.lookup("grande.bin").await.unwrap();
+        assert_eq!(fx.cached_content(big).await, source);
+        assert_eq!(fx.db.get_attrs(big).await.unwrap().size, source.len() as i64);
+        let log = fx.lookup("log.txt").await.unwrap();
+        let content = String::from_utf8(fx.cached_content(log).await).unwrap();
+        assert_eq!(content.lines().filter(|line| *line == "aaaa").count(), 40);
+        assert_eq!(content.lines().filter(|line| *line == "bbbb").count(), 40);
+        assert_eq!(fx.db.get_attrs(log).await.unwrap().size, 400);
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_read_from_hung_drive_times_out() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        let mock = drive_with_note();
+        mock.stall("download_chunk");
+        let timeout = Duration::from_secs(1);
+        let Some(fx) = FuseHarness::mount_with(mock, false, |fs| fs.with_read_timeout(Some(timeout))).await else { return };
+
+        let started = std::time::Instant::now();
+        let output = fx.sh("cat nota.txt 2>&1 || echo FALLO").await;
+
+        assert!(output.contains("FALLO"), "el read colgado falla: {}", output);
+        assert!(output.contains("Input/output error"), "{}", output);
+        assert!(started.elapsed() < 5 * timeout, "no espera a Drive indefinidamente");
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_writes_to_uncached_file_keep_its_size() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        let Some(fx) = FuseHarness::mount(drive_with_note()).await else { return };
+        let inode = fx.lookup("nota.txt").await.unwrap();
+
+        // Ni sobrescribir el principio ni añadir al final descargan el archivo
+        fx.sh("printf 'HOLA' | dd of=nota.txt conv=notrunc status=none && echo '!' >> nota.txt").await;
+
+        assert_eq!(fx.db.get_attrs(inode).await.unwrap().size, 18, "la caché a medias no acorta el archivo");
+        assert_eq!(fx.sh("cat nota.txt").await, "HOLA desde Drive!\n");
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_rm_soft_deletes_file() {
+        let Some(fx) = FuseHarness::mount(drive_with_note()).await else { return };
+        let inode = fx.lookup("nota.txt").await.unwrap();
+
+        fx.sh("rm nota.txt").await;
+
+        assert_eq!(fx.lookup("nota.txt").await, None);
+        assert!(fx.db.is_dirty(inode).await.unwrap(), "la papelera remota queda pendiente");
+        assert!(!fx.path("nota.txt").exists());
+        assert_eq!(fx.mock.calls("trash_file"), 0, "FUSE no llama a Drive: lo hace el uploader");
+        fx.unmount().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_rm_from_trash_requests_permanent_delete() {
+        let Some(fx) = FuseHarness::mount(drive_with_note()).await else { return };
+        let inode = fx.lookup("nota.txt").await.unwrap();
+
+        fx.sh("rm nota.txt").await;
+        assert_eq!(fx.sh("ls .Trash").await, "nota.txt\n");
+
+        fx.sh("rm .Trash/nota.txt").await;
+        assert!(fx.db.is_purge_requested(inode).await.unwrap());
+        assert_eq!(fx.sh("ls .Trash").await, "");
+        assert_eq!(fx.mock.calls("delete_file"), 0, "FUSE no llama a Drive: lo hace el uploader");
+        fx.unmount().await;
+    }
+}
@@ -0,0 +1,219 @@
+//! Registro de descargas en curso por (inode, rango)
+//!
+//! Dos aplicaciones que leen el mismo archivo sin caché (o el Smart Streamer
+//! lanzado en `open` y la lectura que lo sigue) pedirían los mismos bytes a
+//! Drive a la vez. Antes de descargar un rango se reclama aquí: si otro rango
+//! en curso del mismo inode lo solapa, se espera a que termine y se vuelven a
+//! consultar los rangos faltantes en la DB; si no, el guard devuelto lo
+//! mantiene registrado hasta que la descarga acaba (con éxito o no).
+//!
+//! Además lleva la cuenta de los inodes en hidratación (Smart Streamer,
+//! prefetcher): el uploader no sube un archivo mientras se está descargando,
+//! porque su caché aún está a medias. Con `with_status_events` avisa del
+//! inicio y fin de cada hidratación y de cada chunk que termina durante ella,
+//! para que D-Bus emita `StatusChanged` con el estado `Downloading`.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::{broadcast, watch};
+
+type Ranges = HashMap<u64, Vec<(u64, u64, watch::Receiver<()>)>>;
+
+/// Descargas de chunks en curso, compartidas por lectura y streaming
+#[derive(Debug, Default)]
+pub struct InflightDownloads {
+    ranges: Arc<Mutex<Ranges>>,
+    hydrating: Arc<Mutex<HashMap<u64, usize>>>,
+    status_tx: Option<broadcast::Sender<u64>>,
+}
+
+/// Resultado de reclamar un rango
+pub enum Claim {
+    /// Nadie lo está descargando: descargarlo y soltar el guard al terminar
+    Owned(InflightGuard),
+    /// Otra descarga lo solapa: esperar con `wait` y revisar la DB
+    Busy(Vec<watch::Receiver<()>>),
+}
+
+/// Mantiene el inode en hidratación mientras vive
+pub struct HydrationGuard {
+    hydrating: Arc<Mutex<HashMap<u64, usize>>>,
+    status_tx: Option<broadcast::Sender<u64>>,
+    inode: u64,
+}
+
+/// Mantiene registrado un rango mientras se descarga
+pub struct InflightGuard {
+    ranges: Arc<Mutex<Ranges>>,
+    hydrating: Arc<Mutex<HashMap<u64, usize>>>,
+    status_tx: Option<broadcast::Sender<u64>>,
+    inode: u64,
+    start: u64,
+    end: u64,
+    // Al soltarse cierra el canal y despierta a quienes esperan
+    _done: watch::Sender<()>,
+}
+
+impl InflightDownloads {
+    /// Notifica por `tx` (inodes) los cambios de progreso de las hidrataciones
+    pub fn with_status_events(mut self, tx: broadcast::Sender<u64>) -> Self {
+        self.status_tx = Some(tx);
+        self
+    }
+
+    /// Reclama `[start, end]` (inclusivo) de `inode`
+    pub fn claim(&self, inode: u64, start: u64, end: u64) -> Claim {
+        let mut ranges = lock(&self.ranges);
+        let in_flight = ranges.entry(inode).or_default();
+        let overlapping: Vec<_> = in_flight
+            .iter()
+            .filter(|(s, e, _)| *s <= end && start <= *e)
+            .map(|(_, _, rx)| rx.clone())
+            .collect();
+        if !overlapping.is_empty() {
+            return Claim::Busy(overlapping);
+        }
+        let (done, rx) = watch::channel(());
+        in_flight.push((start, end, rx));
+        Claim::Owned(InflightGuard {
+            ranges: self.ranges.clone(),
+            hydrating: self.hydrating.clone(),
+            status_tx: self.status_tx.clone(),
+            inode,
+            start,
+            end,
+            _done: done,
+        })
+    }
+
+    /// Marca `inode` en hidratación hasta soltar el guard (admite varios a la vez)
+    pub fn hydrate(&self, inode: u64) -> HydrationGuard {
+        *lock(&self.hydrating).entry(inode).or_default() += 1;
+        notify(&self.status_tx, inode);
+        HydrationGuard { hydrating: self.hydrating.clone(), status_tx: self.status_tx.clone(), inode }
+    }
+
+    /// `true` si algún rango de `inode` se está descargando o hay una
+    /// hidratación en curso
+    pub fn is_hydrating(&self, inode: u64) -> bool {
+        lock(&self.ranges).contains_key(&inode) || lock(&self.hydrating).contains_key(&inode)
+    }
+
+    /// Espera a que terminen las descargas de `waiters`
+    pub async fn wait(waiters: Vec<watch::Receiver<()>>) {
+        for mut rx in waiters {
+            // Solo se cierra (el guard nunca envía): `Err` significa terminado
+            while rx.changed().await.is_ok() {}
+        }
+    }
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        let mut ranges = lock(&self.ranges);
+        if let Some(in_flight) = ranges.get_mut(&self.inode) {
+            in_flight.retain(|(s, e, _)| (*s, *e) != (self.start, self.end));
+            if in_flight.is_empty() {
+                ranges.remove(&self.inode);
+            }
+        }
+        drop(ranges);
+        // Un chunk más en caché: solo interesa si el archivo se está hidratando
+        if lock(&self.hydrating).contains_key(&self.inode) {
+            notify(&self.status_tx, self.inode);
+        }
+    }
+}
+
+impl Drop for HydrationGuard {
+    fn drop(&mut self) {
+        let mut hydrating = lock(&self.hydrating);
+        if let Some(count) = hydrating.get_mut(&self.inode) {
+            *count -= 1;
+            if *count == 0 {
+                hydrating.remove(&self.inode);
+            }
+        }
+        drop(hydrating);
+        notify(&self.status_tx, self.inode);
+    }
+}
+
+fn notify(status_tx: &Option<broadcast::Sender<u64>>, inode: u64) {
+    if let Some(tx) = status_tx {
+        // Error = sin receptores activos, no es relevante
+        let _ = tx.send(inode);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[rstest]
+    #[case::same_range(0, 2 * MB - 1, true)]
+    #[case::partial_overlap(MB, 3 * MB - 1, true)]
+    #[case::adjacent(2 * MB, 4 * MB - 1, false)]
+    fn test_claim_overlapping_range(#[case] start: u64, #[case] end: u64, #[case] busy: bool) {
+        let inflight = InflightDownloads::default();
+        let _guard = inflight.claim(1, 0, 2 * MB - 1);
+
+        assert_eq!(matches!(inflight.claim(1, start, end), Claim::Busy(_)), busy);
+        assert!(matches!(inflight.claim(2, start, end), Claim::Owned(_)), "otro inode no se bloquea");
+    }
+
+    #[tokio::test]
+    async fn test_waiters_wake_when_download_ends() {
+        let inflight = InflightDownloads::default();
+        let Claim::Owned(guard) = inflight.claim(1, 0, MB - 1) else { panic!("rango libre") };
+        let Claim::Busy(waiters) = inflight.claim(1, 0, MB - 1) else { panic!("rango en curso") };
+
+        let waiting = tokio::spawn(InflightDownloads::wait(waiters));
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        drop(guard);
+        tokio::time::timeout(std::time::Duration::from_secs(1), waiting).await.unwrap().unwrap();
+        assert!(matches!(inflight.claim(1, 0, MB - 1), Claim::Owned(_)), "el rango queda libre");
+    }
+
+    #[test]
+    fn test_is_hydrating() {
+        let inflight = InflightDownloads::default();
+        assert!(!inflight.is_hydrating(1));
+
+        let stream = inflight.hydrate(1);
+        let prefetch = inflight.hydrate(1);
+        drop(stream);
+        assert!(inflight.is_hydrating(1), "queda la otra hidratación");
+        drop(prefetch);
+        assert!(!inflight.is_hydrating(1));
+
+        let chunk = inflight.claim(1, 0, MB - 1);
+        assert!(inflight.is_hydrating(1), "un chunk en descarga también cuenta");
+        drop(chunk);
+        assert!(!inflight.is_hydrating(1));
+    }
+
+    #[test]
+    fn test_hydration_progress_events() {
+        let (tx, mut rx) = broadcast::channel(16);
+        let inflight = InflightDownloads::default().with_status_events(tx);
+
+        drop(inflight.claim(1, 0, MB - 1));
+        assert!(rx.try_recv().is_err(), "una lectura suelta no notifica");
+
+        let hydration = inflight.hydrate(1);
+        drop(inflight.claim(1, 0, MB - 1));
+        drop(hydration);
+        let events: Vec<u64> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(events, [1, 1, 1], "inicio, chunk y fin");
+    }
+}
@@ -0,0 +1,86 @@
+//! Peticiones FUSE que se pueden interrumpir
+//!
+//! Cuando el usuario cancela una operación (Ctrl+C en `cp`, cerrar la vista
+//! previa de Nautilus), el kernel envía `FUSE_INTERRUPT` con el `unique` de
+//! la petición. `read` registra aquí su `unique` mientras espera a Drive y
+//! abandona la espera (y las descargas que lanzó) en cuanto llega la
+//! interrupción. Las que llegan antes del registro o después de responder se
+//! ignoran: el kernel vuelve a enviarlas si la petición sigue pendiente.
+
+use dashmap::DashMap;
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Peticiones en espera, por `unique` de FUSE
+#[derive(Debug, Default)]
+pub struct Interrupts {
+    pending: Arc<DashMap<u64, Arc<Notify>>>,
+}
+
+/// Mantiene registrada una petición mientras vive
+pub struct InterruptGuard {
+    pending: Arc<DashMap<u64, Arc<Notify>>>,
+    unique: u64,
+    notify: Arc<Notify>,
+}
+
+impl Interrupts {
+    /// Registra la petición `unique` hasta que se suelte el guard
+    pub fn register(&self, unique: u64) -> InterruptGuard {
+        let notify = Arc::new(Notify::new());
+        self.pending.insert(unique, notify.clone());
+        InterruptGuard { pending: self.pending.clone(), unique, notify }
+    }
+
+    /// Avisa a la petición `unique`. `false` si no está esperando
+    pub fn interrupt(&self, unique: u64) -> bool {
+        match self.pending.get(&unique) {
+            Some(notify) => {
+                // notify_one guarda el permiso si aún no se está esperando
+                notify.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl InterruptGuard {
+    /// Se completa cuando llega `FUSE_INTERRUPT` para esta petición
+    pub async fn interrupted(&self) {
+        self.notify.notified().await;
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        self.pending.remove(&self.unique);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_interrupt_wakes_registered_request() {
+        let interrupts = Interrupts::default();
+        let guard = interrupts.register(7);
+
+        assert!(!interrupts.interrupt(8), "otra petición no se ve afectada");
+        assert!(interrupts.interrupt(7));
+        tokio::time::timeout(Duration::from_secs(1), guard.interrupted())
+            .await
+            .expect("la interrupción previa a la espera no se pierde");
+    }
+
+    #[tokio::test]
+    async fn test_finished_request_ignores_interrupt() {
+        let interrupts = Interrupts::default();
+        drop(interrupts.register(7));
+
+        assert!(!interrupts.interrupt(7));
+        assert!(interrupts.pending.is_empty());
+    }
+}
@@ -0,0 +1,230 @@
+//! Intent log (write-ahead) de mutaciones FUSE
+//!
+//! Las operaciones de escritura tocan la caché en disco y varias tablas en pasos
+//! separados (p.ej. `write`: archivo de caché → tamaño → dirty). Si el proceso
+//! muere entre pasos, el estado diverge: contenido escrito que nunca se sube,
+//! dentries a medio mover, inodes sin dentry. Antes de empezar, cada operación
+//! registra su intención en `fs_intents`; al terminar la borra. Al arrancar,
+//! `replay_pending_intents` lleva cada intención sobreviviente a un estado
+//! consistente (completándola o deshaciéndola) antes de montar FUSE.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::db::MetadataRepository;
+
+/// Mutación FUSE en curso
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FsIntent {
+    /// `create`/`mkdir`: se identifica por el gdrive_id asignado (el inode aún no existe)
+    Create { parent: u64, name: String, gdrive_id: String },
+    /// `write`: contenido escrito en caché pendiente de reflejarse en attrs/dirty
+    Write { inode: u64 },
+    /// `setattr` con truncado
+    Truncate { inode: u64, size: u64 },
+    Unlink { parent: u64, name: String, gdrive_id: String },
+    Rename { inode: u64, parent: u64, name: String, new_parent: u64, new_name: String },
+}
+
+impl FsIntent {
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn from_json(payload: &str) -> Result<Self> {
+        Ok(serde_json::from_str(payload)?)
+    }
+}
+
+/// Reaplica las intenciones que quedaron pendientes tras un cierre abrupto.
+/// Retorna cuántas se procesaron.
+pub async fn replay_pending_intents(db: &MetadataRepository, cache_dir: &Path) -> Result<usize> {
+    let pending = db.pending_intents().await?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    tracing::warn!("📓 {} operaciones FUSE interrumpidas, recuperando...", pending.len());
+
+    let mut touched_tree = false;
+    for (id, payload) in &pending {
+        match FsIntent::from_json(payload) {
+            Ok(intent) => {
+                touched_tree |= !matches!(intent, FsIntent::Write { .. } | FsIntent::Truncate { .. });
+                if let Err(e) = replay(db, cache_dir, &intent).await {
+                    // Queda pendiente: se vuelve a intentar en el próximo arranque
+                    tracing::error!("❌ Error recuperando {:?}: {:?}", intent, e);
+                    continue;
+                }
+            }
+            Err(e) => tracing::error!("❌ Intent ilegible (id={}): {:?}", id, e),
+        }
+        db.complete_intent(*id).await?;
+    }
+
+    // Los pasos de burbujeo pueden haber quedado a medias
+    if touched_tree {
+        db.rebuild_all_dir_counters().await?;
+    }
+
+    Ok(pending.len())
+}
+
+async fn replay(db: &MetadataRepository, cache_dir: &Path, intent: &FsIntent) -> Result<()> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64;
+
+    match intent {
+        FsIntent::Create { parent, name, gdrive_id } => {
+            let Some(inode) = db.get_inode_by_gdrive_id(gdrive_id).await? else {
+                return Ok(()); // Nunca llegó a crearse
+            };
+            if db.lookup(*parent, name).await? == Some(inode) {
+                // Completar: solo podía faltar el dirty
+                db.mark_dirty(inode).await?;
+                tracing::info!("📓 Creación completada: {}", name);
+            } else {
+                // Deshacer: inode sin dentry, invisible e imposible de subir
+                db.hard_delete_by_gdrive_id(gdrive_id).await?;
+                let _ = tokio::fs::remove_file(cache_dir.join(gdrive_id)).await;
+                tracing::info!("📓 Creación deshecha: {}", name);
+            }
+        }
+        FsIntent::Write { inode } => {
+            let Some(gdrive_id) = db.get_gdrive_id(*inode).await? else {
+                return Ok(());
+            };
+            if let Ok(metadata) = tokio::fs::metadata(cache_dir.join(&gdrive_id)).await {
+                db.set_size_and_mtime(*inode, metadata.len() as i64, now).await?;
+                db.mark_dirty(*inode).await?;
+                tracing::info!("📓 Escritura recuperada: inode={} size={}", inode, metadata.len());
+            }
+        }
+        FsIntent::Truncate { inode, size } => {
+            let Some(gdrive_id) = db.get_gdrive_id(*inode).await? else {
+                return Ok(());
+            };
+            let cache_path = cache_dir.join(&gdrive_id);
+            if cache_path.exists() {
+                crate::fuse::content_store::ContentStore::new(cache_dir).make_private(db, *inode, &cache_path).await?;
+                crate::utils::cache_crypto::set_len(&cache_path, *size, false).await?;
+            }
+            // Al reducir el tamaño la DB recorta los chunks (trigger de 0016)
+            db.set_size(*inode, *size as i64).await?;
+            db.mark_dirty(*inode).await?;
+        }
+        FsIntent::Unlink { parent, name, gdrive_id } => {
+            let Some(inode) = db.get_inode_by_gdrive_id(gdrive_id).await? else {
+                return Ok(());
+            };
+            if db.lookup(*parent, name).await? == Some(inode) {
+                db.soft_delete_by_gdrive_id(gdrive_id).await?;
+            }
+            db.mark_dirty(inode).await?;
+            tracing::info!("📓 Eliminación completada: {}", name);
+        }
+        FsIntent::Rename { inode, parent, name, new_parent, new_name } => {
+            // Completar siempre: el usuario ya vio el rename confirmado o en curso
+            if db.lookup(*new_parent, new_name).await? != Some(*inode) {
+                // Sobrescritura pendiente: retirar el destino antes de ocupar su nombre
+                if let Some(existing) = db.lookup(*new_parent, new_name).await?
+                    && let Some(existing_id) = db.get_gdrive_id(existing).await?
+                {
+                    db.soft_delete_by_gdrive_id(&existing_id).await?;
+                }
+                db.move_dentry(*inode, *new_parent, new_name).await?;
+            }
+            db.mark_dirty(*inode).await?;
+            tracing::info!("📓 Renombrado completado: {}/{} -> {}/{}", parent, name, new_parent, new_name);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::create(FsIntent::Create { parent: 1, name: "a.txt".into(), gdrive_id: "temp_1".into() })]
+    #[case::write(FsIntent::Write { inode: 7 })]
+    #[case::truncate(FsIntent::Truncate { inode: 7, size: 0 })]
+    #[case::unlink(FsIntent::Unlink { parent: 1, name: "a.txt".into(), gdrive_id: "abc".into() })]
+    #[case::rename(FsIntent::Rename { inode: 7, parent: 1, name: "a".into(), new_parent: 2, new_name: "b".into() })]
+    fn test_intent_json_roundtrip(#[case] intent: FsIntent) {
+        let json = intent.to_json().unwrap();
+        assert_eq!(FsIntent::from_json(&json).unwrap(), intent);
+    }
+
+    #[tokio::test]
+    async fn test_replay_rolls_back_create_without_dentry() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap();
+        let inode = db.get_or_create_inode("temp_x").await.unwrap();
+        db.upsert_file_metadata(inode, 0, 0, 0o644, false, None, true, false, true).await.unwrap();
+        let intent = FsIntent::Create { parent: 1, name: "x.txt".into(), gdrive_id: "temp_x".into() };
+        db.log_intent(&intent.to_json().unwrap()).await.unwrap();
+
+        assert_eq!(replay_pending_intents(&db, dir.path()).await.unwrap(), 1);
+
+        assert_eq!(db.get_inode_by_gdrive_id("temp_x").await.unwrap(), None);
+        assert!(db.pending_intents().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_replay_completes_write_from_cache_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap();
+        let inode = db.get_or_create_inode("file_w").await.unwrap();
+        db.upsert_file_metadata(inode, 0, 0, 0o644, false, None, true, false, true).await.unwrap();
+        db.upsert_dentry(1, inode, "w.txt").await.unwrap();
+        std::fs::write(dir.path().join("file_w"), b"hola mundo").unwrap();
+        db.log_intent(&FsIntent::Write { inode }.to_json().unwrap()).await.unwrap();
+
+        replay_pending_intents(&db, dir.path()).await.unwrap();
+
+        assert_eq!(db.get_size(inode).await.unwrap(), Some(10));
+        assert!(db.sync_state(inode).await.unwrap().is_some_and(|s| s.is_pending()));
+    }
+
+    #[tokio::test]
+    async fn test_replay_rename_leaves_a_single_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap();
+        let folder = db.get_or_create_inode("folder_d").await.unwrap();
+        db.upsert_file_metadata(folder, 0, 0, 0o755, true, None, true, false, true).await.unwrap();
+        db.upsert_dentry(1, folder, "d").await.unwrap();
+        let inode = db.get_or_create_inode("file_r").await.unwrap();
+        db.upsert_file_metadata(inode, 0, 0, 0o644, false, None, true, false, true).await.unwrap();
+        db.upsert_dentry(1, inode, "a.txt").await.unwrap();
+        let intent = FsIntent::Rename { inode, parent: 1, name: "a.txt".into(), new_parent: folder, new_name: "b.txt".into() };
+        db.log_intent(&intent.to_json().unwrap()).await.unwrap();
+
+        replay_pending_intents(&db, dir.path()).await.unwrap();
+
+        assert_eq!(db.lookup(folder, "b.txt").await.unwrap(), Some(inode));
+        assert_eq!(db.lookup(1, "a.txt").await.unwrap(), None);
+        assert!(db.pending_intents().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_replay_keeps_failed_intent_pending() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap();
+        let inode = db.get_or_create_inode("file_t").await.unwrap();
+        db.upsert_file_metadata(inode, 4, 0, 0o644, false, None, true, false, true).await.unwrap();
+        db.upsert_dentry(1, inode, "t.txt").await.unwrap();
+        // Un directorio en lugar del archivo de caché: el truncado falla
+        std::fs::create_dir(dir.path().join("file_t")).unwrap();
+        db.log_intent(&FsIntent::Truncate { inode, size: 0 }.to_json().unwrap()).await.unwrap();
+
+        replay_pending_intents(&db, dir.path()).await.unwrap();
+
+        assert_eq!(db.pending_intents().await.unwrap().len(), 1);
+    }
+}
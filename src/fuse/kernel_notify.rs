@@ -0,0 +1,244 @@
+//! Avisos al kernel sobre cambios que no pasaron por FUSE
+//!
+//! Cuando el syncer aplica un cambio remoto, el kernel sigue sirviendo sus
+//! dentries y atributos cacheados: un `ls` ve el listado viejo hasta que
+//! expira el TTL y los watchers (inotify de Nautilus, `GFileMonitor`) nunca
+//! se enteran. Los mensajes `FUSE_NOTIFY_*` invalidan esas entradas y, en el
+//! caso de `FUSE_NOTIFY_DELETE`, el kernel además emite el evento inotify.
+//!
+//! fuse3 solo entrega su `Notify` dentro de `poll`, así que tras montar se
+//! duplica el descriptor de `/dev/fuse` de la sesión y los mensajes se
+//! escriben directamente en él (cada `write` es un mensaje completo y no se
+//! mezcla con las respuestas de fuse3).
+
+use std::ffi::OsStr;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::sync::{PoisonError, RwLock};
+
+/// Códigos de `fuse_notify_code` (include/uapi/linux/fuse.h)
+const FUSE_NOTIFY_INVAL_INODE: i32 = 2;
+const FUSE_NOTIFY_INVAL_ENTRY: i32 = 3;
+const FUSE_NOTIFY_DELETE: i32 = 6;
+/// El kernel rechaza nombres más largos (`FUSE_NAME_MAX`)
+const FUSE_NAME_MAX: usize = 1024;
+
+/// Un aviso para el kernel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KernelEvent {
+    /// Descarta atributos y páginas cacheadas del inode (un directorio
+    /// vuelve a listarse)
+    InvalidInode(u64),
+    /// Descarta la dentry `name` de `parent`, exista o sea negativa
+    InvalidEntry { parent: u64, name: String },
+    /// `name` (inode `child`) ya no está en `parent`: invalida y emite
+    /// `IN_DELETE` a quien vigile la carpeta
+    Delete { parent: u64, child: u64, name: String },
+}
+
+/// Descriptor de `/dev/fuse` sobre el que se escriben los avisos
+///
+/// Se crea antes de montar (el syncer lo recibe al arrancar) y se conecta
+/// con `attach` cuando la sesión ya existe; hasta entonces `send` no hace nada.
+/// Al remontar se suelta con `detach` y se conecta a la sesión nueva.
+#[derive(Debug, Default)]
+pub struct KernelNotifier {
+    device: RwLock<Option<OwnedFd>>,
+}
+
+impl KernelNotifier {
+    /// Conecta con la sesión FUSE del proceso (el `/dev/fuse` abierto más
+    /// reciente). `false` si no se encuentra
+    pub fn attach(&self) -> bool {
+        match find_fuse_device() {
+            Some(fd) => {
+                self.attach_fd(fd);
+                true
+            }
+            None => {
+                tracing::warn!("⚠️ No se encontró /dev/fuse: los cambios remotos no se avisarán al kernel");
+                false
+            }
+        }
+    }
+
+    /// Conecta con un descriptor ya abierto. Reemplaza al de una sesión anterior
+    pub fn attach_fd(&self, fd: OwnedFd) {
+        *self.device.write().unwrap_or_else(PoisonError::into_inner) = Some(fd);
+    }
+
+    /// Suelta el descriptor de una sesión terminada. Debe llamarse antes de
+    /// montar otra: `attach` elige el `/dev/fuse` más reciente y el duplicado
+    /// viejo podría confundirlo
+    pub fn detach(&self) {
+        self.device.write().unwrap_or_else(PoisonError::into_inner).take();
+    }
+
+    /// Escribe los avisos en orden. Los que el kernel rechaza (inode o
+    /// dentry que no tiene cacheados → `ENOENT`) se ignoran
+    pub fn send(&self, events: &[KernelEvent]) {
+        let device = self.device.read().unwrap_or_else(PoisonError::into_inner);
+        let Some(device) = device.as_ref() else {
+            return;
+        };
+        for event in events {
+            let Some(message) = encode(event) else {
+                continue;
+            };
+            let written = unsafe {
+                libc::write(device.as_raw_fd(), message.as_ptr().cast(), message.len())
+            };
+            if written < 0 {
+                let err = std::io::Error::last_os_error();
+                if err.raw_os_error() != Some(libc::ENOENT) {
+                    tracing::debug!("Aviso al kernel {:?} rechazado: {}", event, err);
+                }
+            }
+        }
+    }
+}
+
+/// Serializa un aviso: `fuse_out_header` con `unique = 0` y el código en
+/// `error`, seguido de la estructura del aviso y el nombre terminado en NUL
+fn encode(event: &KernelEvent) -> Option<Vec<u8>> {
+    let (code, mut body) = match event {
+        KernelEvent::InvalidInode(inode) => {
+            // fuse_notify_inval_inode_out { ino, off, len }: off 0 y len 0 = todo
+            let mut body = Vec::with_capacity(24);
+            body.extend_from_slice(&inode.to_ne_bytes());
+            body.extend_from_slice(&0i64.to_ne_bytes());
+            body.extend_from_slice(&0i64.to_ne_bytes());
+            (FUSE_NOTIFY_INVAL_INODE, body)
+        }
+        KernelEvent::InvalidEntry { parent, name } => {
+            // fuse_notify_inval_entry_out { parent, namelen, flags }
+            let name = checked_name(name)?;
+            let mut body = Vec::with_capacity(16 + name.len() + 1);
+            body.extend_from_slice(&parent.to_ne_bytes());
+            body.extend_from_slice(&(name.len() as u32).to_ne_bytes());
+            body.extend_from_slice(&0u32.to_ne_bytes());
+            body.extend_from_slice(name);
+            (FUSE_NOTIFY_INVAL_ENTRY, body)
+        }
+        KernelEvent::Delete { parent, child, name } => {
+            // fuse_notify_delete_out { parent, child, namelen, padding }
+            let name = checked_name(name)?;
+            let mut body = Vec::with_capacity(24 + name.len() + 1);
+            body.extend_from_slice(&parent.to_ne_bytes());
+            body.extend_from_slice(&child.to_ne_bytes());
+            body.extend_from_slice(&(name.len() as u32).to_ne_bytes());
+            body.extend_from_slice(&0u32.to_ne_bytes());
+            body.extend_from_slice(name);
+            (FUSE_NOTIFY_DELETE, body)
+        }
+    };
+    if !matches!(event, KernelEvent::InvalidInode(_)) {
+        body.push(0);
+    }
+
+    // fuse_out_header { len, error, unique }
+    let len = 16 + body.len();
+    let mut message = Vec::with_capacity(len);
+    message.extend_from_slice(&(len as u32).to_ne_bytes());
+    message.extend_from_slice(&code.to_ne_bytes());
+    message.extend_from_slice(&0u64.to_ne_bytes());
+    message.append(&mut body);
+    Some(message)
+}
+
+/// Nombre en bytes si el kernel lo acepta
+fn checked_name(name: &str) -> Option<&[u8]> {
+    let bytes = OsStr::new(name).as_bytes();
+    (!bytes.is_empty() && bytes.len() <= FUSE_NAME_MAX && !bytes.contains(&0)).then_some(bytes)
+}
+
+/// Duplica el `/dev/fuse` abierto más reciente del proceso (el de la sesión
+/// recién montada; con `fusermount3` llega por un socket, no con `open`)
+fn find_fuse_device() -> Option<OwnedFd> {
+    let fd = std::fs::read_dir("/proc/self/fd")
+        .ok()?
+        .flatten()
+        .filter(|entry| {
+            std::fs::read_link(entry.path()).is_ok_and(|target| target == std::path::Path::new("/dev/fuse"))
+        })
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .max()?;
+    let duplicated = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+    if duplicated < 0 {
+        return None;
+    }
+    Some(unsafe { std::os::fd::FromRawFd::from_raw_fd(duplicated) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::io::Read;
+
+    #[rstest]
+    #[case::inval_inode(KernelEvent::InvalidInode(7), FUSE_NOTIFY_INVAL_INODE, 40)]
+    #[case::inval_entry(KernelEvent::InvalidEntry { parent: 1, name: "nuevo.txt".into() }, FUSE_NOTIFY_INVAL_ENTRY, 16 + 16 + 10)]
+    #[case::delete(KernelEvent::Delete { parent: 1, child: 9, name: "nota.txt".into() }, FUSE_NOTIFY_DELETE, 16 + 24 + 9)]
+    fn test_encode_header(#[case] event: KernelEvent, #[case] code: i32, #[case] len: usize) {
+        let message = encode(&event).unwrap();
+
+        assert_eq!(message.len(), len);
+        assert_eq!(u32::from_ne_bytes(message[0..4].try_into().unwrap()) as usize, len);
+        assert_eq!(i32::from_ne_bytes(message[4..8].try_into().unwrap()), code);
+        assert_eq!(u64::from_ne_bytes(message[8..16].try_into().unwrap()), 0, "los avisos usan unique 0");
+    }
+
+    #[test]
+    fn test_encode_entry_name() {
+        let message = encode(&KernelEvent::InvalidEntry { parent: 5, name: "año.txt".into() }).unwrap();
+
+        assert_eq!(u64::from_ne_bytes(message[16..24].try_into().unwrap()), 5);
+        assert_eq!(u32::from_ne_bytes(message[24..28].try_into().unwrap()), "año.txt".len() as u32);
+        assert_eq!(&message[32..], b"a\xc3\xb1o.txt\0");
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::too_long(&"x".repeat(FUSE_NAME_MAX + 1))]
+    fn test_encode_rejects_invalid_names(#[case] name: &str) {
+        assert_eq!(encode(&KernelEvent::InvalidEntry { parent: 1, name: name.into() }), None);
+    }
+
+    #[test]
+    fn test_send_writes_one_message_per_event() {
+        let (mut reader, writer) = std::io::pipe().unwrap();
+        let notifier = KernelNotifier::default();
+        notifier.send(&[KernelEvent::InvalidInode(1)]);
+        notifier.attach_fd(writer.into());
+
+        notifier.send(&[KernelEvent::InvalidInode(1), KernelEvent::InvalidEntry { parent: 1, name: "a".into() }]);
+        drop(notifier);
+
+        let mut written = Vec::new();
+        reader.read_to_end(&mut written).unwrap();
+        assert_eq!(written.len(), 40 + 34, "antes de attach no se escribe nada");
+    }
+
+    #[test]
+    fn test_reattach_writes_to_new_session_only() {
+        let (mut old_reader, old_writer) = std::io::pipe().unwrap();
+        let (mut new_reader, new_writer) = std::io::pipe().unwrap();
+        let notifier = KernelNotifier::default();
+        notifier.attach_fd(old_writer.into());
+        notifier.send(&[KernelEvent::InvalidInode(1)]);
+
+        notifier.detach();
+        notifier.send(&[KernelEvent::InvalidInode(2)]);
+        notifier.attach_fd(new_writer.into());
+        notifier.send(&[KernelEvent::InvalidInode(3)]);
+        drop(notifier);
+
+        let (mut old, mut new) = (Vec::new(), Vec::new());
+        old_reader.read_to_end(&mut old).unwrap();
+        new_reader.read_to_end(&mut new).unwrap();
+        assert_eq!(old.len(), 40, "tras detach la sesión vieja no recibe nada");
+        assert_eq!(new.len(), 40, "entre sesiones no se escribe nada");
+        assert_eq!(u64::from_ne_bytes(new[16..24].try_into().unwrap()), 3);
+    }
+}
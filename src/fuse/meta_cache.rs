@@ -0,0 +1,342 @@
+//! Caché en memoria de atributos y lookups para FUSE
+//!
+//! `getattr`/`lookup` se invocan miles de veces al listar carpetas grandes;
+//! esta caché evita ir a SQLite en cada llamada. Es concurrente (DashMap) y
+//! acotada: al superar la capacidad se desalojan las entradas menos usadas
+//! recientemente (LRU aproximado por contador de accesos). Cada entrada expira
+//! tras un TTL corto para acotar la desactualización frente a escritores que
+//! no pasan por `GDriveFS` (uploader, mirror).
+//!
+//! También recuerda los lookups sin resultado (`.git`, `__pycache__`... que
+//! las shells y los editores prueban una y otra vez) con un TTL aún más corto,
+//! y los olvida en cuanto se crea algo en esa carpeta o llega un cambio remoto.
+//! Olvidarlos no recorre el mapa: cada entrada negativa guarda la generación
+//! de su carpeta (`missing_gens`), e invalidar la carpeta solo retira su
+//! generación, así que un `untar` o un `git checkout` no pagan O(n) por archivo.
+
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::fuse::attr::FileAttributes;
+
+/// Capacidad por defecto (entradas por mapa)
+const DEFAULT_CAPACITY: usize = 50_000;
+/// Tiempo de vida por defecto de una entrada
+const DEFAULT_TTL: Duration = Duration::from_secs(10);
+/// Tiempo de vida de un lookup sin resultado (nunca mayor que el TTL general)
+const NEGATIVE_TTL: Duration = Duration::from_secs(2);
+
+struct CacheEntry<V> {
+    value: V,
+    inserted_at: Instant,
+    last_access: AtomicU64,
+}
+
+/// Caché LRU concurrente de `FileAttributes` por inode, de (parent, name) → inode
+/// y de los (parent, name) que no existen
+pub struct MetadataCache {
+    attrs: DashMap<u64, CacheEntry<FileAttributes>>,
+    entries: DashMap<(u64, String), CacheEntry<u64>>,
+    /// Nombres inexistentes, con la generación de la carpeta al recordarlos
+    missing: DashMap<(u64, String), CacheEntry<u64>>,
+    /// Generación vigente de cada carpeta con nombres inexistentes recordados
+    missing_gens: DashMap<u64, u64>,
+    capacity: usize,
+    ttl: Duration,
+    negative_ttl: Duration,
+    clock: AtomicU64,
+}
+
+impl Default for MetadataCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_TTL)
+    }
+}
+
+impl std::fmt::Debug for MetadataCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetadataCache")
+            .field("attrs", &self.attrs.len())
+            .field("entries", &self.entries.len())
+            .field("missing", &self.missing.len())
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+impl MetadataCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            attrs: DashMap::new(),
+            entries: DashMap::new(),
+            missing: DashMap::new(),
+            missing_gens: DashMap::new(),
+            capacity: capacity.max(1),
+            ttl,
+            negative_ttl: NEGATIVE_TTL.min(ttl),
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Atributos cacheados de un inode (None si no están o expiraron)
+    pub fn get_attrs(&self, inode: u64) -> Option<FileAttributes> {
+        let hit = self.attrs.get(&inode).and_then(|entry| {
+            if entry.inserted_at.elapsed() > self.ttl {
+                return None;
+            }
+            entry.last_access.store(self.tick(), Ordering::Relaxed);
+            Some(entry.value.clone())
+        });
+        if hit.is_none() {
+            self.attrs.remove_if(&inode, |_, e| e.inserted_at.elapsed() > self.ttl);
+        }
+        hit
+    }
+
+    pub fn put_attrs(&self, inode: u64, attrs: FileAttributes) {
+        self.attrs.insert(inode, self.new_entry(attrs));
+        if self.attrs.len() > self.capacity {
+            evict_lru(&self.attrs, self.capacity);
+        }
+    }
+
+    /// Inode cacheado para (parent, name)
+    pub fn get_entry(&self, parent: u64, name: &str) -> Option<u64> {
+        let key = (parent, name.to_string());
+        let hit = self.entries.get(&key).and_then(|entry| {
+            if entry.inserted_at.elapsed() > self.ttl {
+                return None;
+            }
+            entry.last_access.store(self.tick(), Ordering::Relaxed);
+            Some(entry.value)
+        });
+        if hit.is_none() {
+            self.entries.remove_if(&key, |_, e| e.inserted_at.elapsed() > self.ttl);
+        }
+        hit
+    }
+
+    pub fn put_entry(&self, parent: u64, name: &str, inode: u64) {
+        let key = (parent, name.to_string());
+        self.missing.remove(&key);
+        self.entries.insert(key, self.new_entry(inode));
+        if self.entries.len() > self.capacity {
+            evict_lru(&self.entries, self.capacity);
+        }
+    }
+
+    /// Indica si un lookup reciente de (parent, name) no encontró nada
+    pub fn is_missing(&self, parent: u64, name: &str) -> bool {
+        let key = (parent, name.to_string());
+        let generation = self.missing_gens.get(&parent).map(|g| *g);
+        let is_stale = |entry: &CacheEntry<u64>| {
+            Some(entry.value) != generation || entry.inserted_at.elapsed() > self.negative_ttl
+        };
+        let hit = self.missing.get(&key).is_some_and(|entry| {
+            if is_stale(&entry) {
+                return false;
+            }
+            entry.last_access.store(self.tick(), Ordering::Relaxed);
+            true
+        });
+        if !hit {
+            self.missing.remove_if(&key, |_, e| is_stale(e));
+        }
+        hit
+    }
+
+    /// Recuerda que (parent, name) no existe
+    pub fn put_missing(&self, parent: u64, name: &str) {
+        // Sin tope, las generaciones de carpetas que nunca se invalidan se
+        // acumularían; vaciarlas solo olvida nombres inexistentes
+        if self.missing_gens.len() > self.capacity {
+            self.missing_gens.clear();
+        }
+        let generation = *self.missing_gens.entry(parent).or_insert_with(|| self.tick());
+        self.missing.insert((parent, name.to_string()), self.new_entry(generation));
+        if self.missing.len() > self.capacity {
+            evict_lru(&self.missing, self.capacity);
+        }
+    }
+
+    /// Olvida los nombres inexistentes de una carpeta: algo se creó, se movió
+    /// o llegó desde Drive en ella. O(1): las entradas de la generación retirada
+    /// dejan de valer y se limpian al consultarlas o por LRU.
+    pub fn invalidate_missing(&self, parent: u64) {
+        self.missing_gens.remove(&parent);
+    }
+
+    /// Invalida los atributos de un inode (escrituras locales: size, mtime, mode)
+    pub fn invalidate_attrs(&self, inode: u64) {
+        self.attrs.remove(&inode);
+    }
+
+    /// Invalida un mapeo (parent, name)
+    pub fn invalidate_entry(&self, parent: u64, name: &str) {
+        self.entries.remove(&(parent, name.to_string()));
+    }
+
+    /// Invalida todo lo relacionado con un inode: sus atributos, los mapeos que
+    /// apuntan a él y los de sus hijos (si es directorio).
+    /// Usado ante cambios remotos, renombrados y eliminaciones.
+    pub fn invalidate_inode(&self, inode: u64) {
+        self.attrs.remove(&inode);
+        self.entries.retain(|(parent, _), entry| entry.value != inode && *parent != inode);
+        self.invalidate_missing(inode);
+    }
+
+    fn new_entry<V>(&self, value: V) -> CacheEntry<V> {
+        CacheEntry {
+            value,
+            inserted_at: Instant::now(),
+            last_access: AtomicU64::new(self.tick()),
+        }
+    }
+}
+
+/// Desaloja las entradas menos usadas hasta dejar el mapa al 90% de su capacidad.
+/// Se desaloja por lotes para amortizar el recorrido completo del mapa.
+fn evict_lru<K, V>(map: &DashMap<K, CacheEntry<V>>, capacity: usize)
+where
+    K: Eq + std::hash::Hash + Clone,
+{
+    let target = capacity - capacity / 10;
+    let excess = map.len().saturating_sub(target);
+    if excess == 0 {
+        return;
+    }
+
+    let mut by_access: Vec<(u64, K)> = map
+        .iter()
+        .map(|e| (e.value().last_access.load(Ordering::Relaxed), e.key().clone()))
+        .collect();
+    by_access.sort_unstable_by_key(|(access, _)| *access);
+
+    for (_, key) in by_access.into_iter().take(excess) {
+        map.remove(&key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn attrs(inode: u64, size: i64) -> FileAttributes {
+        FileAttributes {
+            inode: inode as i64,
+            size,
+            mtime: 0,
+            mtime_nsec: 0,
+            ctime: 0,
+            mode: 0o644,
+            is_dir: false,
+            mime_type: None,
+            can_move: true,
+            shared: false,
+            owned_by_me: true,
+            generation: 0,
+            created_time: None,
+        }
+    }
+
+    #[rstest]
+    fn test_attrs_roundtrip_and_invalidate() {
+        let cache = MetadataCache::default();
+        cache.put_attrs(5, attrs(5, 42));
+        assert_eq!(cache.get_attrs(5).map(|a| a.size), Some(42));
+
+        cache.invalidate_attrs(5);
+        assert!(cache.get_attrs(5).is_none());
+    }
+
+    #[rstest]
+    fn test_invalidate_inode_removes_entries_and_children() {
+        let cache = MetadataCache::default();
+        cache.put_entry(1, "carpeta", 10);
+        cache.put_entry(10, "hijo.txt", 11);
+        cache.put_entry(1, "otro.txt", 12);
+
+        cache.invalidate_inode(10);
+
+        assert_eq!(cache.get_entry(1, "carpeta"), None);
+        assert_eq!(cache.get_entry(10, "hijo.txt"), None);
+        assert_eq!(cache.get_entry(1, "otro.txt"), Some(12));
+    }
+
+    #[rstest]
+    fn test_entries_expire_after_ttl() {
+        let cache = MetadataCache::new(10, Duration::ZERO);
+        cache.put_entry(1, "a", 2);
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(cache.get_entry(1, "a"), None);
+    }
+
+    #[rstest]
+    fn test_missing_names_until_parent_changes() {
+        let cache = MetadataCache::default();
+        cache.put_missing(10, ".git");
+        cache.put_missing(11, ".git");
+        assert!(cache.is_missing(10, ".git"));
+        assert!(!cache.is_missing(10, "__pycache__"));
+
+        cache.invalidate_missing(10);
+
+        assert!(!cache.is_missing(10, ".git"));
+        assert!(cache.is_missing(11, ".git"), "solo se olvida la carpeta que cambió");
+        cache.put_entry(11, ".git", 12);
+        assert!(!cache.is_missing(11, ".git"));
+
+        // Lo recordado tras invalidar vale de nuevo
+        cache.put_missing(10, ".git");
+        assert!(cache.is_missing(10, ".git"));
+    }
+
+    #[rstest]
+    fn test_invalidate_missing_does_not_scan_other_parents() {
+        let cache = MetadataCache::default();
+        for parent in 0..1_000 {
+            cache.put_missing(parent, ".git");
+        }
+
+        cache.invalidate_missing(7);
+
+        assert_eq!(cache.missing.len(), 1_000, "sin recorrer el mapa");
+        assert!(!cache.is_missing(7, ".git"));
+        assert_eq!(cache.missing.len(), 999, "la entrada obsoleta se limpia al consultarla");
+        assert!(cache.is_missing(8, ".git"));
+    }
+
+    #[rstest]
+    fn test_missing_names_expire_before_entries() {
+        let cache = MetadataCache::default();
+        assert!(cache.negative_ttl < cache.ttl);
+        let cache = MetadataCache::new(10, Duration::ZERO);
+        cache.put_missing(1, "a");
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(!cache.is_missing(1, "a"));
+    }
+
+    #[rstest]
+    fn test_evicts_least_recently_used() {
+        let cache = MetadataCache::new(10, DEFAULT_TTL);
+        for inode in 0..10 {
+            cache.put_attrs(inode, attrs(inode, 0));
+        }
+        // Tocar el inode 0 para que sea el más reciente
+        assert!(cache.get_attrs(0).is_some());
+
+        cache.put_attrs(10, attrs(10, 0));
+
+        assert!(cache.attrs.len() <= 10);
+        assert!(cache.get_attrs(0).is_some());
+        assert!(cache.get_attrs(10).is_some());
+        assert!(cache.get_attrs(1).is_none());
+    }
+}
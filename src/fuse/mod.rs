@@ -1,5 +1,15 @@
 pub mod attr;
+pub mod cache_manager;
+pub mod content_store;
+pub mod file_locks;
 pub mod filesystem;
+#[cfg(test)]
+pub mod harness;
+pub mod inflight;
+pub mod interrupts;
+pub mod journal;
+pub mod kernel_notify;
+pub mod meta_cache;
 pub mod shortcuts;
 
 pub use filesystem::GDriveFS;
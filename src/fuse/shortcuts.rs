@@ -62,11 +62,43 @@ pub fn is_workspace_file(mime_type: &str) -> bool {
     )
 }
 
+/// Extensiones de archivo (las de Drive para escritorio) que, en un archivo
+/// nuevo y vacío, crean un documento de Google en lugar de subirse
+const WORKSPACE_TEMPLATES: [(&str, &str); 3] = [
+    ("gdoc", "application/vnd.google-apps.document"),
+    ("gsheet", "application/vnd.google-apps.spreadsheet"),
+    ("gslides", "application/vnd.google-apps.presentation"),
+];
+
+/// Para `Informe.gdoc` retorna el nombre del documento (`Informe`) y su MIME type
+pub fn workspace_template(name: &str) -> Option<(&str, &'static str)> {
+    let (stem, extension) = name.rsplit_once('.')?;
+    if stem.is_empty() {
+        return None;
+    }
+    WORKSPACE_TEMPLATES
+        .iter()
+        .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+        .map(|&(_, mime)| (stem, mime))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rstest::*;
 
+    #[rstest]
+    #[case::doc("Informe.gdoc", Some(("Informe", "application/vnd.google-apps.document")))]
+    #[case::sheet("Cuentas 2024.gsheet", Some(("Cuentas 2024", "application/vnd.google-apps.spreadsheet")))]
+    #[case::slides("Charla.GSLIDES", Some(("Charla", "application/vnd.google-apps.presentation")))]
+    #[case::dots_in_name("v1.2.gdoc", Some(("v1.2", "application/vnd.google-apps.document")))]
+    #[case::only_extension(".gdoc", None)]
+    #[case::plain_file("notas.txt", None)]
+    #[case::no_extension("gdoc", None)]
+    fn test_workspace_template(#[case] name: &str, #[case] expected: Option<(&str, &'static str)>) {
+        assert_eq!(workspace_template(name), expected);
+    }
+
     #[rstest]
     #[case::document("application/vnd.google-apps.document", true)]
     #[case::spreadsheet("application/vnd.google-apps.spreadsheet", true)]
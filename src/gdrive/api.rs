@@ -0,0 +1,157 @@
+//! Interfaz de la API de Google Drive
+//!
+//! Los consumidores (sync, FUSE, IPC) dependen de `Arc<dyn DriveApi>` en lugar
+//! del cliente HTTP concreto, para poder ejercitar su lógica en tests con
+//! `mock::MockDriveClient` sin credenciales reales.
+
+use anyhow::Result;
+use google_drive3::api::{Change, File, Revision};
+use google_drive3::chrono::{DateTime, Utc};
+
+use super::DriveError;
+
+/// Tipo para callback de progreso de upload: (bytes enviados, total)
+pub type ProgressCallback = Box<dyn Fn(u64, u64) + Send>;
+
+/// Operaciones sobre Google Drive que usa la aplicación
+#[async_trait::async_trait]
+pub trait DriveApi: Send + Sync + std::fmt::Debug {
+    /// Obtiene el ID canónico de la carpeta 'root' (My Drive)
+    async fn get_root_file_id(&self) -> Result<String>;
+
+    /// Descarga un chunk específico de un archivo usando Range Header
+    async fn download_chunk(&self, file_id: &str, offset: u64, size: u32) -> Result<Vec<u8>>;
+
+    /// Lista solo los hijos inmediatos del root de Drive.
+    /// Usado para el primer nivel del bootstrap BFS (respuesta rápida ~1s).
+    async fn list_root_children(&self, root_id: &str) -> Result<Vec<File>>;
+
+    /// Lista todos los archivos de Google Drive con los campos necesarios para
+    /// el bootstrapping, sin los hijos directos de `excluded_parents`
+    async fn list_all_files(&self, excluded_parents: &[String]) -> Result<Vec<File>>;
+
+    /// Obtiene una página de archivos de Drive. Retorna (archivos, next_page_token).
+    /// Si next_page_token es None, no hay más páginas. Omite los hijos directos de
+    /// `excluded_parents` (carpetas excluidas); las páginas siguientes deben pedirse
+    /// con la misma lista.
+    async fn fetch_files_page(&self, page_token: Option<&str>, excluded_parents: &[String]) -> Result<(Vec<File>, Option<String>)>;
+
+    // ============================================================
+    // Changes API (sincronización incremental)
+    // ============================================================
+
+    /// Obtiene el token inicial para comenzar a escuchar cambios
+    async fn get_start_page_token(&self) -> Result<String>;
+
+    /// Lista cambios desde un page_token dado
+    /// Retorna: (cambios, nuevo_start_page_token si es la última página, has_more)
+    async fn list_changes(&self, page_token: &str) -> Result<(Vec<Change>, Option<String>, bool)>;
+
+    /// Obtiene el MD5 checksum de un archivo remoto (para detectar conflictos)
+    async fn get_file_md5(&self, file_id: &str) -> Result<Option<String>>;
+
+    // ============================================================
+    // Upload (escritura)
+    // ============================================================
+
+    /// Reserva `count` IDs de Drive (files.generateIds) para usarlos al crear
+    async fn generate_ids(&self, count: u32) -> Result<Vec<String>>;
+
+    /// Sube un nuevo archivo a Google Drive
+    /// `file_id` es un ID reservado con `generate_ids` (None = lo asigna Drive).
+    /// `modified_time` preserva la fecha local (sin él Drive usa la hora de subida).
+    /// Retorna el gdrive_id del archivo creado
+    #[allow(clippy::too_many_arguments)]
+    async fn upload_file(
+        &self,
+        file_path: &std::path::Path,
+        name: &str,
+        mime_type: Option<&str>,
+        parent_id: &str,
+        file_id: Option<&str>,
+        modified_time: Option<DateTime<Utc>>,
+        progress_cb: Option<ProgressCallback>,
+    ) -> Result<String>;
+
+    /// Crea una nueva carpeta en Google Drive (`file_id` como en `upload_file`)
+    async fn create_folder(
+        &self,
+        name: &str,
+        parent_id: &str,
+        file_id: Option<&str>,
+    ) -> Result<String>;
+
+    /// Crea un documento de Google Workspace vacío (`mime_type` de Docs,
+    /// Sheets o Slides; `file_id` como en `upload_file`)
+    async fn create_workspace_file(
+        &self,
+        name: &str,
+        mime_type: &str,
+        parent_id: &str,
+        file_id: Option<&str>,
+    ) -> Result<String>;
+
+    /// Actualiza el contenido de un archivo existente.
+    /// Drive fija modifiedTime a la hora de subida salvo que se envíe `modified_time`.
+    async fn update_file_content(
+        &self,
+        file_id: &str,
+        file_path: &std::path::Path,
+        modified_time: Option<DateTime<Utc>>,
+        progress_cb: Option<ProgressCallback>,
+    ) -> Result<()>;
+
+    /// Mueve un archivo a la papelera
+    async fn trash_file(&self, file_id: &str) -> Result<(), DriveError>;
+
+    /// Elimina un archivo definitivamente (`files.delete`, sin pasar por la
+    /// papelera). Una carpeta se lleva todo su contenido.
+    async fn delete_file(&self, file_id: &str) -> Result<(), DriveError>;
+
+    /// Obtiene metadatos completos de un archivo (para detectar cambios de nombre/padre y contenido)
+    async fn get_file_metadata(&self, file_id: &str) -> Result<File>;
+
+    /// Crea un permiso "cualquiera con el enlace" con el rol indicado
+    /// ("reader", "commenter" o "writer") y retorna el `webViewLink` del archivo
+    async fn create_share_link(&self, file_id: &str, role: &str) -> Result<String>;
+
+    /// Obtiene el `webViewLink` (URL en drive.google.com) de un archivo
+    async fn get_web_view_link(&self, file_id: &str) -> Result<String>;
+
+    /// Obtiene un `thumbnailLink` nuevo (los guardados caducan en horas).
+    /// None si Drive no tiene miniatura del archivo.
+    async fn get_thumbnail_link(&self, file_id: &str) -> Result<Option<String>>;
+
+    /// Descarga la imagen de un `thumbnailLink`
+    async fn download_thumbnail(&self, link: &str) -> Result<Vec<u8>>;
+
+    /// Lista las revisiones guardadas de un archivo (revisions.list), de la
+    /// más antigua a la actual. Los documentos de Google no tienen contenido
+    /// descargable por revisión.
+    async fn list_revisions(&self, file_id: &str) -> Result<Vec<Revision>>;
+
+    /// Descarga el contenido de una revisión (revisions.get con `alt=media`)
+    /// en `dest`, sin cifrar. Retorna los bytes escritos.
+    async fn download_revision(&self, file_id: &str, revision_id: &str, dest: &std::path::Path) -> Result<u64>;
+
+    /// Marca o desmarca un archivo como destacado (files.update con `starred`)
+    async fn set_starred(&self, file_id: &str, starred: bool) -> Result<()>;
+
+    /// Pone (`Some`) o borra (`None`) una `appProperty` del archivo
+    /// (`gdrive::app_properties`)
+    async fn set_app_property(&self, file_id: &str, key: &str, value: Option<&str>) -> Result<()>;
+
+    /// Pone (`Some`) o borra (`None`) la descripción del archivo
+    /// (files.update con `description`)
+    async fn set_description(&self, file_id: &str, description: Option<&str>) -> Result<()>;
+
+    /// Actualiza solo los metadatos de un archivo (nombre, padres, modifiedTime)
+    async fn update_file_metadata(
+        &self,
+        file_id: &str,
+        new_name: Option<&str>,
+        add_parent: Option<&str>,
+        remove_parent: Option<&str>,
+        new_mtime: Option<DateTime<Utc>>,
+    ) -> Result<()>;
+}
@@ -0,0 +1,84 @@
+//! Marcas `appProperties` que G-DriveXP deja en los archivos de Drive
+//!
+//! Las `appProperties` son pares clave/valor privados del cliente OAuth: las
+//! leen todas las instalaciones de G-DriveXP de la cuenta y ninguna otra
+//! aplicación. `CLIENT_KEY` identifica la instalación que creó el archivo
+//! (se pone al subirlo o crear la carpeta) y `PIN_KEY` indica que el usuario
+//! lo mantiene en algún dispositivo ("Mantener siempre en este dispositivo").
+
+use std::collections::HashMap;
+
+use google_drive3::api::File;
+
+/// Instalación (`MetadataRepository::client_id`) que creó el archivo
+pub const CLIENT_KEY: &str = "gdxpClient";
+
+/// `PIN_VALUE` mientras el usuario lo mantenga en un dispositivo
+pub const PIN_KEY: &str = "gdxpPin";
+
+/// Valor de `PIN_KEY` para un archivo mantenido (ausente = no mantenido)
+pub const PIN_VALUE: &str = "1";
+
+/// Marcas de G-DriveXP leídas de un archivo
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppMarks {
+    /// Instalación que lo creó; `None` si no lo creó G-DriveXP
+    pub origin_client: Option<String>,
+    /// Algún dispositivo lo mantiene descargado
+    pub pinned: bool,
+}
+
+impl AppMarks {
+    /// Lee las marcas de un archivo (`appProperties` debe estar en `fields`)
+    pub fn from_file(file: &File) -> Self {
+        let props = file.app_properties.as_ref();
+        Self {
+            origin_client: props
+                .and_then(|props| props.get(CLIENT_KEY))
+                .filter(|id| !id.is_empty())
+                .cloned(),
+            pinned: props.and_then(|props| props.get(PIN_KEY)).is_some_and(|v| v == PIN_VALUE),
+        }
+    }
+}
+
+/// `appProperties` de un archivo nuevo creado por la instalación `client_id`
+pub fn creation_properties(client_id: &str) -> HashMap<String, String> {
+    HashMap::from([(CLIENT_KEY.to_string(), client_id.to_string())])
+}
+
+/// Valor de `PIN_KEY` para `pinned` (`None` borra la propiedad)
+pub fn pin_value(pinned: bool) -> Option<&'static str> {
+    pinned.then_some(PIN_VALUE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn file_with(props: Option<&[(&str, &str)]>) -> File {
+        File {
+            app_properties: props.map(|props| {
+                props.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case::none(None, AppMarks::default())]
+    #[case::other_app(Some(&[("otra", "x")][..]), AppMarks::default())]
+    #[case::created(Some(&[(CLIENT_KEY, "abc")][..]), AppMarks { origin_client: Some("abc".into()), pinned: false })]
+    #[case::pinned(Some(&[(PIN_KEY, PIN_VALUE)][..]), AppMarks { origin_client: None, pinned: true })]
+    #[case::unknown_pin_value(Some(&[(PIN_KEY, "0"), (CLIENT_KEY, "")][..]), AppMarks::default())]
+    fn test_from_file(#[case] props: Option<&[(&str, &str)]>, #[case] expected: AppMarks) {
+        assert_eq!(AppMarks::from_file(&file_with(props)), expected);
+    }
+
+    #[test]
+    fn test_creation_properties_round_trip() {
+        let file = File { app_properties: Some(creation_properties("abc")), ..Default::default() };
+        assert_eq!(AppMarks::from_file(&file).origin_client.as_deref(), Some("abc"));
+    }
+}
@@ -1,25 +1,28 @@
 use anyhow::{Context, Result};
 use google_drive3::DriveHub;
-use hyper::client::HttpConnector;
-use hyper_rustls::HttpsConnector;
+use google_drive3::client::GetToken;
 use std::io::{Read, Seek, SeekFrom};
-use yup_oauth2::authenticator::Authenticator;
 
-/// Tipo para callback de progreso de upload
-pub type ProgressCallback = Box<dyn Fn(u64) + Send>;
+use crate::utils::proxy::{HttpSettings, ProxyHttpsConnector};
 
-/// Reader que envuelve otro Read y reporta progreso via callback
+use super::api::{DriveApi, ProgressCallback};
+use super::app_properties;
+use super::DriveError;
+
+/// Reader que envuelve otro Read y reporta progreso (enviado, total) via callback
 struct ProgressReader<R: Read + Seek> {
     inner: R,
     bytes_read: u64,
-    callback: ProgressCallback,
+    total: u64,
+    callback: Option<ProgressCallback>,
 }
 
 impl<R: Read + Seek> ProgressReader<R> {
-    fn new(inner: R, callback: ProgressCallback) -> Self {
+    fn new(inner: R, total: u64, callback: Option<ProgressCallback>) -> Self {
         Self {
             inner,
             bytes_read: 0,
+            total,
             callback,
         }
     }
@@ -30,7 +33,9 @@ impl<R: Read + Seek> Read for ProgressReader<R> {
         let n = self.inner.read(buf)?;
         if n > 0 {
             self.bytes_read += n as u64;
-            (self.callback)(self.bytes_read);
+            if let Some(callback) = &self.callback {
+                callback(self.bytes_read.min(self.total), self.total);
+            }
         }
         Ok(n)
     }
@@ -44,38 +49,184 @@ impl<R: Read + Seek> Seek for ProgressReader<R> {
     }
 }
 
+/// Abre un archivo para subirlo: se lee de disco por tramos (descifrado si es
+/// una caché cifrada) y cada lectura avanza el progreso
+fn upload_reader(
+    file_path: &std::path::Path,
+    progress_cb: Option<ProgressCallback>,
+) -> Result<(ProgressReader<crate::utils::cache_crypto::PlainReader>, u64)> {
+    let reader = crate::utils::cache_crypto::PlainReader::open(file_path)
+        .context("Error leyendo archivo local")?;
+    let size = reader.size();
+    Ok((ProgressReader::new(reader, size, progress_cb), size))
+}
+
+/// Raíz de la API de Drive (reemplazable con `with_base_url` para tests)
+const DRIVE_API_BASE: &str = "https://www.googleapis.com";
+
+/// Tiempo máximo para establecer la conexión TCP + TLS
+const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Tiempo máximo sin recibir datos en una respuesta (no limita la duración
+/// total: una descarga grande puede tardar minutos mientras progrese)
+const READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Conexiones ociosas que se mantienen abiertas para reutilizarlas
+const POOL_IDLE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(90);
+
 /// Cliente Wrapper para Google Drive API
 pub struct DriveClient {
-    hub: DriveHub<HttpsConnector<HttpConnector>>,
+    hub: DriveHub<ProxyHttpsConnector>,
+    /// Cliente HTTP compartido por todas las llamadas directas a la API: un
+    /// único pool de conexiones (HTTP/2 y sesiones TLS reutilizadas)
     http: reqwest::Client,
+    api_base: String,
+    /// Instalación que firma lo que se crea (`app_properties::CLIENT_KEY`)
+    client_id: Option<String>,
+}
+
+/// Cliente HTTP para las llamadas directas a la API. Usa el proxy y la CA de
+/// `http_settings` (o los proxies del entorno) y negocia HTTP/2 vía ALPN.
+fn build_http_client(http_settings: &HttpSettings) -> Result<reqwest::Client> {
+    let builder = reqwest::Client::builder()
+        .user_agent(concat!("g-drive-xp/", env!("CARGO_PKG_VERSION")))
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+        .tcp_keepalive(std::time::Duration::from_secs(60))
+        .http2_keep_alive_interval(std::time::Duration::from_secs(30))
+        .http2_keep_alive_while_idle(true);
+    http_settings
+        .apply_to_reqwest(builder)?
+        .build()
+        .context("No se pudo inicializar el cliente HTTP")
+}
+
+/// Carpetas excluidas que caben en el `q` de `files.list`; el resto lo filtra el llamador
+pub const MAX_EXCLUDED_PARENTS_IN_QUERY: usize = 100;
+
+/// Filtro `q` de `files.list`: sin papelera y sin los hijos de `excluded_parents`
+pub fn files_query(excluded_parents: &[String]) -> String {
+    let mut query = String::from("trashed = false");
+    for id in excluded_parents.iter().take(MAX_EXCLUDED_PARENTS_IN_QUERY) {
+        query.push_str(&format!(" and not '{}' in parents", id));
+    }
+    query
+}
+
+/// Error tipado a partir de una respuesta fallida de la API (consume el cuerpo)
+async fn api_error(response: reqwest::Response, operation: &str) -> DriveError {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await.unwrap_or_default();
+    tracing::error!("Error API Drive {}: {} - {}", operation, status, body);
+    DriveError::from_response(status.as_u16(), retry_after.as_deref(), &body)
+}
+
+/// Error tipado a partir de un error del `DriveHub` (subidas y carpetas): las
+/// respuestas de la API se clasifican igual que las de `api_error`
+fn hub_error(error: google_drive3::Error) -> anyhow::Error {
+    match error {
+        google_drive3::Error::BadRequest(value) => {
+            let status = value["error"]["code"].as_u64().unwrap_or(400) as u16;
+            DriveError::from_response(status, None, &value.to_string()).into()
+        }
+        google_drive3::Error::Failure(response) => {
+            let retry_after = response
+                .headers()
+                .get(hyper::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok());
+            DriveError::from_response(response.status().as_u16(), retry_after, "").into()
+        }
+        other => anyhow::Error::new(other),
+    }
+}
+
+impl std::fmt::Debug for DriveClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DriveClient").finish_non_exhaustive()
+    }
 }
 
 impl DriveClient {
     /// Inicializa el cliente de Google Drive
-    pub fn new(auth: Authenticator<yup_oauth2::hyper_rustls::HttpsConnector<hyper::client::HttpConnector>>) -> Self {
-        let https = hyper_rustls::HttpsConnectorBuilder::new()
-            .with_native_roots()
-            .expect("no se pudieron cargar los certificados nativos")
-            .https_or_http()
-            .enable_http1()
-            .build();
-
-        let client = hyper::Client::builder().build(https);
+    pub fn new(auth: impl GetToken + 'static, http_settings: &HttpSettings) -> Result<Self> {
+        let client = hyper::Client::builder().build(http_settings.https_connector()?);
 
         let hub = DriveHub::new(client, auth);
 
-        Self { hub, http: reqwest::Client::new() }
+        Ok(Self {
+            hub,
+            http: build_http_client(http_settings)?,
+            api_base: DRIVE_API_BASE.to_string(),
+            client_id: None,
+        })
+    }
+
+    /// Marca los archivos y carpetas que crea con el ID de esta instalación
+    pub fn with_client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = Some(client_id.into());
+        self
+    }
+
+    /// Crea un archivo sin contenido (carpeta o documento de Workspace): solo
+    /// metadatos, marcado con `gdxpClient`
+    async fn create_without_content(
+        &self,
+        name: &str,
+        mime_type: &str,
+        parent_id: &str,
+        file_id: Option<&str>,
+    ) -> Result<String> {
+        let mut file_metadata = google_drive3::api::File::default();
+        file_metadata.id = file_id.map(str::to_string);
+        file_metadata.name = Some(name.to_string());
+        file_metadata.mime_type = Some(mime_type.to_string());
+        file_metadata.app_properties = self.client_id.as_deref().map(app_properties::creation_properties);
+
+        if parent_id != "root" {
+            file_metadata.parents = Some(vec![parent_id.to_string()]);
+        }
+
+        let result = self.hub
+            .files()
+            .create(file_metadata)
+            .supports_all_drives(true)
+            .ignore_default_visibility(true)
+            .upload(std::io::Cursor::new(vec![]), mime_type.parse()?)
+            .await
+            .map_err(hub_error)?;
+
+        result.1.id.ok_or_else(|| anyhow::anyhow!("Drive no devolvió el ID de {}", name))
     }
 
-    /// Obtiene el ID canónico de la carpeta 'root' (My Drive)
-    pub async fn get_root_file_id(&self) -> Result<String> {
+    /// Dirige todas las peticiones a otro servidor (p. ej. un servidor HTTP falso en tests)
+    #[cfg(test)]
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        self.hub.base_url(format!("{}/drive/v3/", base_url));
+        self.hub.root_url(format!("{}/", base_url));
+        self.api_base = base_url.to_string();
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl DriveApi for DriveClient {
+
+    async fn get_root_file_id(&self) -> Result<String> {
+        let call = crate::metrics::ApiCall::start("get_root_file_id");
         let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
             .await
             .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
             .context("No se obtuvo ningún token válido")?;
 
         let client = &self.http;
-        let url = "https://www.googleapis.com/drive/v3/files/root?fields=id";
+        let url = format!("{}/drive/v3/files/root?fields=id", self.api_base);
 
         let response = client
             .get(url)
@@ -85,10 +236,7 @@ impl DriveClient {
             .context("Error de red al obtener root id")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            tracing::error!("Error API Drive get_root_id: {} - {}", status, body);
-            anyhow::bail!("Error API Drive get_root_id: {} - {}", status, body);
+            return Err(api_error(response, "get_root_file_id").await.into());
         }
 
         #[derive(serde::Deserialize)]
@@ -98,11 +246,12 @@ impl DriveClient {
 
         let file: FileId = response.json().await?;
         tracing::info!("Drive Root ID identificado como: {}", file.id);
+        call.succeeded();
         Ok(file.id)
     }
 
-    /// Descarga un chunk específico de un archivo usando Range Header
-    pub async fn download_chunk(&self, file_id: &str, offset: u64, size: u32) -> Result<Vec<u8>> {
+    async fn download_chunk(&self, file_id: &str, offset: u64, size: u32) -> Result<Vec<u8>> {
+        let call = crate::metrics::ApiCall::start("download_chunk");
         let end = offset + size as u64 - 1;
         let range_header = format!("bytes={}-{}", offset, end);
 
@@ -115,7 +264,7 @@ impl DriveClient {
             .context("No se obtuvo ningún token válido para la descarga")?;
 
         // 2. Construir URL de descarga (Incluyendo acknowledgeAbuse=true para evitar 403 en falsos positivos de malware)
-        let url = format!("https://www.googleapis.com/drive/v3/files/{}?alt=media&acknowledgeAbuse=true", file_id);
+        let url = format!("{}/drive/v3/files/{}?alt=media&acknowledgeAbuse=true", self.api_base, file_id);
 
         // 3. Realizar petición con reqwest
         let client = &self.http;
@@ -130,25 +279,25 @@ impl DriveClient {
 
         // 4. Verificar estado
         let status = response.status();
+        if status.as_u16() == 416 {
+            // 416 es recuperable: el caller corregirá attrs.size y reintentará
+            tracing::warn!("416 Range Not Satisfiable: file_id={} range={} (se corregirá automáticamente)", file_id, range_header);
+            let body = response.text().await.unwrap_or_default();
+            return Err(DriveError::from_response(416, None, &body).into());
+        }
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            if status.as_u16() == 416 {
-                // 416 es recuperable: el caller corregirá attrs.size y reintentará
-                tracing::warn!("416 Range Not Satisfiable: file_id={} range={} (se corregirá automáticamente)", file_id, range_header);
-            } else {
-                tracing::error!("Error API Drive: {} - {}", status, error_text);
-            }
-            anyhow::bail!("Error API Drive: {} - {}", status, error_text);
+            return Err(api_error(response, "download_chunk").await.into());
         }
 
         // 5. Devolver bytes
         let bytes = response.bytes().await.context("Error al leer cuerpo de respuesta")?;
+        crate::metrics::add_bytes_downloaded(bytes.len() as u64);
+        call.succeeded();
         Ok(bytes.to_vec())
     }
 
-    /// Lista solo los hijos inmediatos del root de Drive.
-    /// Usado para el primer nivel del bootstrap BFS (respuesta rápida ~1s).
-    pub async fn list_root_children(&self, root_id: &str) -> Result<Vec<google_drive3::api::File>> {
+    async fn list_root_children(&self, root_id: &str) -> Result<Vec<google_drive3::api::File>> {
+        let call = crate::metrics::ApiCall::start("list_root_children");
         let mut all_files = Vec::new();
         let mut page_token: Option<String> = None;
 
@@ -164,7 +313,8 @@ impl DriveClient {
 
         loop {
             let mut url = format!(
-                "https://www.googleapis.com/drive/v3/files?pageSize=1000&q={}&fields=nextPageToken,files(id,name,parents,mimeType,size,modifiedTime,md5Checksum,version,shared,ownedByMe,capabilities(canMoveItemWithinDrive),shortcutDetails(targetId,targetMimeType))",
+                "{}/drive/v3/files?pageSize=1000&q={}&fields=nextPageToken,files(id,name,parents,mimeType,size,modifiedTime,createdTime,md5Checksum,version,shared,starred,ownedByMe,owners(displayName,emailAddress),capabilities(canMoveItemWithinDrive,canEdit,canTrash),shortcutDetails(targetId,targetMimeType),webViewLink,thumbnailLink,appProperties,description,folderColorRgb)",
+                self.api_base,
                 urlencoding::encode(&query)
             );
 
@@ -180,10 +330,7 @@ impl DriveClient {
                 .context("Error de red al listar hijos del root")?;
 
             if !response.status().is_success() {
-                let status = response.status();
-                let body = response.text().await.unwrap_or_default();
-                tracing::error!("Error API Drive (list_root_children): {} - {}", status, body);
-                anyhow::bail!("Error API Drive: {} - {}", status, body);
+                return Err(api_error(response, "list_root_children").await.into());
             }
 
             let file_list: google_drive3::api::FileList = response.json()
@@ -202,12 +349,13 @@ impl DriveClient {
         }
 
         tracing::info!("📊 Bootstrap nivel 1: {} items en root", all_files.len());
+        call.succeeded();
         Ok(all_files)
     }
 
-    /// Lista todos los archivos de Google Drive con los campos necesarios para el bootstrapping
-    /// NOTA: Usamos reqwest directamente para evitar que google-drive3 añada scopes automáticos
-    pub async fn list_all_files(&self) -> Result<Vec<google_drive3::api::File>> {
+    async fn list_all_files(&self, excluded_parents: &[String]) -> Result<Vec<google_drive3::api::File>> {
+        // reqwest directo: google-drive3 añadiría scopes automáticos
+        let call = crate::metrics::ApiCall::start("list_all_files");
         let mut all_files = Vec::new();
         let mut page_token: Option<String> = None;
 
@@ -223,8 +371,9 @@ impl DriveClient {
 
         loop {
             let mut url = format!(
-                "https://www.googleapis.com/drive/v3/files?pageSize=1000&q={}&fields=nextPageToken,files(id,name,parents,mimeType,size,modifiedTime,md5Checksum,version,shared,ownedByMe,capabilities(canMoveItemWithinDrive),shortcutDetails(targetId,targetMimeType))",
-                urlencoding::encode("trashed = false")
+                "{}/drive/v3/files?pageSize=1000&q={}&fields=nextPageToken,files(id,name,parents,mimeType,size,modifiedTime,createdTime,md5Checksum,version,shared,starred,ownedByMe,owners(displayName,emailAddress),capabilities(canMoveItemWithinDrive,canEdit,canTrash),shortcutDetails(targetId,targetMimeType),webViewLink,thumbnailLink,appProperties,description,folderColorRgb)",
+                self.api_base,
+                urlencoding::encode(&files_query(excluded_parents))
             );
             
             if let Some(ref token_str) = page_token {
@@ -239,10 +388,7 @@ impl DriveClient {
                 .context("Error de red al listar archivos")?;
 
             if !response.status().is_success() {
-                let status = response.status();
-                let body = response.text().await.unwrap_or_default();
-                tracing::error!("Error API Drive: {} - {}", status, body);
-                anyhow::bail!("Error API Drive: {} - {}", status, body);
+                return Err(api_error(response, "list_all_files").await.into());
             }
 
             // Parsear respuesta como FileList
@@ -262,20 +408,21 @@ impl DriveClient {
         }
 
         tracing::info!("📊 Sincronización: Se recuperaron {} archivos en total", all_files.len());
+        call.succeeded();
         Ok(all_files)
     }
 
-    /// Obtiene una página de archivos de Drive. Retorna (archivos, next_page_token).
-    /// Si next_page_token es None, no hay más páginas.
-    pub async fn fetch_files_page(&self, page_token: Option<&str>) -> Result<(Vec<google_drive3::api::File>, Option<String>)> {
+    async fn fetch_files_page(&self, page_token: Option<&str>, excluded_parents: &[String]) -> Result<(Vec<google_drive3::api::File>, Option<String>)> {
+        let call = crate::metrics::ApiCall::start("fetch_files_page");
         let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
             .await
             .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
             .context("No se obtuvo ningún token válido")?;
 
         let mut url = format!(
-            "https://www.googleapis.com/drive/v3/files?pageSize=1000&q={}&fields=nextPageToken,files(id,name,parents,mimeType,size,modifiedTime,md5Checksum,version,shared,ownedByMe,capabilities(canMoveItemWithinDrive),shortcutDetails(targetId,targetMimeType))",
-            urlencoding::encode("trashed = false")
+            "{}/drive/v3/files?pageSize=1000&q={}&fields=nextPageToken,files(id,name,parents,mimeType,size,modifiedTime,createdTime,md5Checksum,version,shared,starred,ownedByMe,owners(displayName,emailAddress),capabilities(canMoveItemWithinDrive,canEdit,canTrash),shortcutDetails(targetId,targetMimeType),webViewLink,thumbnailLink,appProperties,description,folderColorRgb)",
+            self.api_base,
+            urlencoding::encode(&files_query(excluded_parents))
         );
 
         if let Some(pt) = page_token {
@@ -290,9 +437,7 @@ impl DriveClient {
             .context("Error de red al obtener página de archivos")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            anyhow::bail!("Error API Drive fetch_files_page: {} - {}", status, body);
+            return Err(api_error(response, "fetch_files_page").await.into());
         }
 
         let file_list: google_drive3::api::FileList = response.json()
@@ -300,6 +445,7 @@ impl DriveClient {
             .context("Error al parsear respuesta JSON de Drive")?;
 
         let files = file_list.files.unwrap_or_default();
+        call.succeeded();
         Ok((files, file_list.next_page_token))
     }
 
@@ -307,15 +453,15 @@ impl DriveClient {
     // Métodos para Changes API (sincronización incremental)
     // ============================================================
 
-    /// Obtiene el token inicial para comenzar a escuchar cambios
-    pub async fn get_start_page_token(&self) -> Result<String> {
+    async fn get_start_page_token(&self) -> Result<String> {
+        let call = crate::metrics::ApiCall::start("get_start_page_token");
         let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
             .await
             .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
             .context("No se obtuvo ningún token válido")?;
 
         let client = &self.http;
-        let url = "https://www.googleapis.com/drive/v3/changes/startPageToken";
+        let url = format!("{}/drive/v3/changes/startPageToken", self.api_base);
 
         let response = client
             .get(url)
@@ -325,10 +471,7 @@ impl DriveClient {
             .context("Error de red al obtener startPageToken")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            tracing::error!("Error API Drive: {} - {}", status, body);
-            anyhow::bail!("Error API Drive: {} - {}", status, body);
+            return Err(api_error(response, "get_start_page_token").await.into());
         }
 
         #[derive(serde::Deserialize)]
@@ -342,12 +485,12 @@ impl DriveClient {
             .context("Error al parsear startPageToken")?;
 
         tracing::debug!("Obtenido startPageToken: {}", parsed.start_page_token);
+        call.succeeded();
         Ok(parsed.start_page_token)
     }
 
-    /// Lista cambios desde un page_token dado
-    /// Retorna: (cambios, nuevo_start_page_token si es la última página, has_more)
-    pub async fn list_changes(&self, page_token: &str) -> Result<(Vec<google_drive3::api::Change>, Option<String>, bool)> {
+    async fn list_changes(&self, page_token: &str) -> Result<(Vec<google_drive3::api::Change>, Option<String>, bool)> {
+        let call = crate::metrics::ApiCall::start("list_changes");
         let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
             .await
             .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
@@ -357,7 +500,8 @@ impl DriveClient {
         
         // pageToken es requerido, fields especifica qué queremos recibir
         let url = format!(
-            "https://www.googleapis.com/drive/v3/changes?pageSize=1000&pageToken={}&fields=nextPageToken,newStartPageToken,changes(fileId,removed,file(id,name,parents,mimeType,size,modifiedTime,md5Checksum,trashed,shared,ownedByMe,capabilities(canMoveItemWithinDrive),shortcutDetails(targetId,targetMimeType)))",
+            "{}/drive/v3/changes?pageSize=1000&pageToken={}&fields=nextPageToken,newStartPageToken,changes(fileId,removed,file(id,name,parents,mimeType,size,modifiedTime,createdTime,md5Checksum,trashed,shared,starred,ownedByMe,owners(displayName,emailAddress),capabilities(canMoveItemWithinDrive,canEdit,canTrash),shortcutDetails(targetId,targetMimeType),webViewLink,thumbnailLink,appProperties,description,folderColorRgb))",
+            self.api_base,
             page_token
         );
 
@@ -369,10 +513,7 @@ impl DriveClient {
             .context("Error de red al listar cambios")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            tracing::error!("Error API Drive changes: {} - {}", status, body);
-            anyhow::bail!("Error API Drive changes: {} - {}", status, body);
+            return Err(api_error(response, "list_changes").await.into());
         }
 
         let change_list: google_drive3::api::ChangeList = response.json()
@@ -392,11 +533,12 @@ impl DriveClient {
 
         // Retornamos el siguiente token a usar (ya sea next_page_token para seguir iterando
         // o new_start_page_token si llegamos al final de los cambios actuales)
+        call.succeeded();
         Ok((changes, next_token, has_more))
     }
 
-    /// Obtiene el MD5 checksum de un archivo remoto (para detectar conflictos)
-    pub async fn get_file_md5(&self, file_id: &str) -> Result<Option<String>> {
+    async fn get_file_md5(&self, file_id: &str) -> Result<Option<String>> {
+        let call = crate::metrics::ApiCall::start("get_file_md5");
         let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
             .await
             .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
@@ -404,7 +546,8 @@ impl DriveClient {
 
         let client = &self.http;
         let url = format!(
-            "https://www.googleapis.com/drive/v3/files/{}?fields=md5Checksum",
+            "{}/drive/v3/files/{}?fields=md5Checksum",
+            self.api_base,
             file_id
         );
 
@@ -416,16 +559,14 @@ impl DriveClient {
             .context("Error de red al obtener md5Checksum")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            tracing::error!("Error API Drive get_file_md5: {} - {}", status, body);
-            anyhow::bail!("Error API Drive get_file_md5: {} - {}", status, body);
+            return Err(api_error(response, "get_file_md5").await.into());
         }
 
         let file: google_drive3::api::File = response.json()
             .await
             .context("Error al parsear respuesta de get_file_md5")?;
 
+        call.succeeded();
         Ok(file.md5_checksum)
     }
 
@@ -433,174 +574,180 @@ impl DriveClient {
     // Métodos para Upload (escritura)
     // ============================================================
 
-    /// Sube un nuevo archivo a Google Drive
-    /// Retorna el gdrive_id del archivo creado
-    pub async fn upload_file(
+    async fn generate_ids(&self, count: u32) -> Result<Vec<String>> {
+        let call = crate::metrics::ApiCall::start("generate_ids");
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        let url = format!(
+            "{}/drive/v3/files/generateIds?count={}&space=drive&type=files",
+            self.api_base,
+            count
+        );
+        let response = self.http
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .context("Error de red al reservar IDs")?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "generate_ids").await.into());
+        }
+
+        let body: google_drive3::api::GeneratedIds = response.json().await
+            .context("Respuesta inválida de generateIds")?;
+        let ids = body.ids.unwrap_or_default();
+        tracing::debug!("🆔 {} IDs de Drive reservados", ids.len());
+        call.succeeded();
+        Ok(ids)
+    }
+
+    async fn upload_file(
         &self,
         file_path: &std::path::Path,
         name: &str,
         mime_type: Option<&str>,
         parent_id: &str,
+        file_id: Option<&str>,
+        modified_time: Option<google_drive3::chrono::DateTime<google_drive3::chrono::Utc>>,
         progress_cb: Option<ProgressCallback>,
     ) -> Result<String> {
+        let call = crate::metrics::ApiCall::start("upload_file");
         tracing::info!("📤 Subiendo archivo: {}", name);
 
-        // Leer contenido del archivo
-        let content = tokio::fs::read(file_path).await
-            .context("Error leyendo archivo local")?;
+        // Contenido en streaming desde disco (descifrado si es una caché cifrada)
+        let (reader, content_len) = upload_reader(file_path, progress_cb)?;
 
         // Construir metadata
         let mut file_metadata = google_drive3::api::File::default();
+        file_metadata.id = file_id.map(str::to_string);
         file_metadata.name = Some(name.to_string());
         file_metadata.mime_type = Some(mime_type.unwrap_or("application/octet-stream").to_string());
+        file_metadata.modified_time = modified_time;
+        file_metadata.app_properties = self.client_id.as_deref().map(app_properties::creation_properties);
 
         if parent_id != "root" {
             file_metadata.parents = Some(vec![parent_id.to_string()]);
         }
 
         let mime = mime_type.unwrap_or("application/octet-stream").parse().unwrap();
-        let content_len = content.len();
 
         // Estrategia adaptativa:
         // - Archivos pequeños (< 5MB) o vacíos: Upload simple (evita panic en resumable con 0 bytes)
         // - Archivos grandes: Resumable upload
         let result = if content_len < 5 * 1024 * 1024 {
             tracing::debug!("Usando upload simple para archivo de {} bytes", content_len);
-            match progress_cb {
-                Some(cb) => {
-                    let reader = ProgressReader::new(std::io::Cursor::new(content), cb);
-                    self.hub.files().create(file_metadata)
-                        .upload(reader, mime).await
-                        .context("Error en upload simple")?
-                }
-                None => {
-                    self.hub.files().create(file_metadata)
-                        .upload(std::io::Cursor::new(content), mime).await
-                        .context("Error en upload simple")?
-                }
-            }
+            self.hub.files().create(file_metadata)
+                .upload(reader, mime).await
+                .map_err(hub_error)
+                .context("Error en upload simple")?
         } else {
             tracing::debug!("Usando upload resumable para archivo de {} bytes", content_len);
-            match progress_cb {
-                Some(cb) => {
-                    let reader = ProgressReader::new(std::io::Cursor::new(content), cb);
-                    self.hub.files().create(file_metadata)
-                        .upload_resumable(reader, mime).await
-                        .context("Error en upload resumable")?
-                }
-                None => {
-                    self.hub.files().create(file_metadata)
-                        .upload_resumable(std::io::Cursor::new(content), mime).await
-                        .context("Error en upload resumable")?
-                }
-            }
+            self.hub.files().create(file_metadata)
+                .upload_resumable(reader, mime).await
+                .map_err(hub_error)
+                .context("Error en upload resumable")?
         };
 
         let file_id = result.1.id.ok_or_else(|| anyhow::anyhow!("No se recibió file_id en respuesta"))?;
 
         tracing::info!("✅ Archivo subido: {}", file_id);
+        crate::metrics::add_bytes_uploaded(content_len);
+        call.succeeded();
         Ok(file_id)
     }
 
-    /// Crea una nueva carpeta en Google Drive
-    pub async fn create_folder(
+    async fn create_folder(
         &self,
         name: &str,
         parent_id: &str,
+        file_id: Option<&str>,
     ) -> Result<String> {
+        let call = crate::metrics::ApiCall::start("create_folder");
         tracing::info!("📂 Creando carpeta: {}", name);
 
-        let mut file_metadata = google_drive3::api::File::default();
-        file_metadata.name = Some(name.to_string());
-        file_metadata.mime_type = Some("application/vnd.google-apps.folder".to_string());
-        
-        if parent_id != "root" {
-            file_metadata.parents = Some(vec![parent_id.to_string()]);
-        }
-
-        let result = self.hub
-            .files()
-            .create(file_metadata)
-            .supports_all_drives(true)
-            .ignore_default_visibility(true)
-            .upload(
-                std::io::Cursor::new(vec![]),
-                "application/vnd.google-apps.folder".parse().unwrap(),
-            )
+        let file_id = self.create_without_content(name, "application/vnd.google-apps.folder", parent_id, file_id)
             .await
             .context("Error creando carpeta en API")?;
-
-        let file_id = result.1.id.ok_or_else(|| anyhow::anyhow!("No se recibió file_id para carpeta"))?;
         
         tracing::info!("✅ Carpeta creada: {}", file_id);
+        call.succeeded();
         Ok(file_id)
     }
 
-    /// Actualiza el contenido de un archivo existente
-    pub async fn update_file_content(
+    async fn create_workspace_file(
+        &self,
+        name: &str,
+        mime_type: &str,
+        parent_id: &str,
+        file_id: Option<&str>,
+    ) -> Result<String> {
+        let call = crate::metrics::ApiCall::start("create_workspace_file");
+        tracing::info!("📝 Creando documento de Google ({}): {}", mime_type, name);
+
+        let file_id = self.create_without_content(name, mime_type, parent_id, file_id)
+            .await
+            .context("Error creando documento de Google en API")?;
+
+        tracing::info!("✅ Documento de Google creado: {}", file_id);
+        call.succeeded();
+        Ok(file_id)
+    }
+
+    async fn update_file_content(
         &self,
         file_id: &str,
         file_path: &std::path::Path,
+        modified_time: Option<google_drive3::chrono::DateTime<google_drive3::chrono::Utc>>,
         progress_cb: Option<ProgressCallback>,
     ) -> Result<()> {
+        let call = crate::metrics::ApiCall::start("update_file_content");
         tracing::info!("📝 Actualizando contenido de archivo: {}", file_id);
 
-        // Leer contenido del archivo
-        let content = tokio::fs::read(file_path).await
-            .context("Error leyendo archivo local")?;
+        // Contenido en streaming desde disco (descifrado si es una caché cifrada)
+        let (reader, content_len) = upload_reader(file_path, progress_cb)?;
 
-        // Metadata vacío (no cambiamos nombre ni padres, solo contenido)
-        let file_metadata = google_drive3::api::File::default();
+        // Solo contenido y fecha (no cambiamos nombre ni padres)
+        let file_metadata = google_drive3::api::File {
+            modified_time,
+            ..Default::default()
+        };
         let mime = "application/octet-stream".parse().unwrap();
-        let content_len = content.len();
 
         // Estrategia adaptativa para updates
         if content_len < 5 * 1024 * 1024 {
             tracing::debug!("Usando update simple para archivo de {} bytes", content_len);
-            match progress_cb {
-                Some(cb) => {
-                    let reader = ProgressReader::new(std::io::Cursor::new(content), cb);
-                    self.hub.files().update(file_metadata, file_id)
-                        .upload(reader, mime).await
-                        .context("Error en update simple")?;
-                }
-                None => {
-                    self.hub.files().update(file_metadata, file_id)
-                        .upload(std::io::Cursor::new(content), mime).await
-                        .context("Error en update simple")?;
-                }
-            }
+            self.hub.files().update(file_metadata, file_id)
+                .upload(reader, mime).await
+                .map_err(hub_error)
+                .context("Error en update simple")?;
         } else {
             tracing::debug!("Usando update resumable para archivo de {} bytes", content_len);
-            match progress_cb {
-                Some(cb) => {
-                    let reader = ProgressReader::new(std::io::Cursor::new(content), cb);
-                    self.hub.files().update(file_metadata, file_id)
-                        .upload_resumable(reader, mime).await
-                        .context("Error en update resumable")?;
-                }
-                None => {
-                    self.hub.files().update(file_metadata, file_id)
-                        .upload_resumable(std::io::Cursor::new(content), mime).await
-                        .context("Error en update resumable")?;
-                }
-            }
+            self.hub.files().update(file_metadata, file_id)
+                .upload_resumable(reader, mime).await
+                .map_err(hub_error)
+                .context("Error en update resumable")?;
         }
 
         tracing::info!("✅ Archivo actualizado: {}", file_id);
+        crate::metrics::add_bytes_uploaded(content_len);
+        call.succeeded();
         Ok(())
     }
 
-    /// Mueve un archivo a la papelera
-    pub async fn trash_file(&self, file_id: &str) -> Result<(), super::DriveError> {
+    async fn trash_file(&self, file_id: &str) -> Result<(), DriveError> {
+        let call = crate::metrics::ApiCall::start("trash_file");
         tracing::info!("🗑️ Moviendo a papelera: {}", file_id);
 
         let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
             .await
-            .map_err(|e| super::DriveError::Auth(format!("{}", e)))?
-            .ok_or_else(|| super::DriveError::Auth("No token available".into()))?;
+            .map_err(|e| DriveError::Auth(format!("{}", e)))?
+            .ok_or_else(|| DriveError::Auth("No token available".into()))?;
 
-        let url = format!("https://www.googleapis.com/drive/v3/files/{}", file_id);
+        let url = format!("{}/drive/v3/files/{}", self.api_base, file_id);
         let client = &self.http;
 
         let response = client
@@ -610,33 +757,41 @@ impl DriveClient {
             .send()
             .await?;
 
-        let status = response.status();
-        if !status.is_success() {
-            let body = response.text().await.unwrap_or_default();
-            tracing::error!("Error API Drive trash: {} - {}", status, body);
-            
-            // Detectar error 403 de permisos insuficientes
-            if status == 403 && body.contains("insufficientFilePermissions") {
-                return Err(super::DriveError::InsufficientPermissions(
-                    format!("No se puede eliminar archivo compartido: {}", file_id)
-                ));
-            }
-            
-            // Detectar error 404 de archivo no encontrado
-            if status == 404 {
-                return Err(super::DriveError::NotFound(
-                    format!("Archivo no existe en Drive: {}", file_id)
-                ));
-            }
-            
-            return Err(super::DriveError::ApiError(format!("{} - {}", status, body)));
+        if !response.status().is_success() {
+            return Err(api_error(response, "trash_file").await);
         }
 
         tracing::info!("✅ Archivo movido a papelera: {}", file_id);
+        call.succeeded();
         Ok(())
     }
-    /// Obtiene metadatos completos de un archivo (para detectar cambios de nombre/padre y contenido)
-    pub async fn get_file_metadata(&self, file_id: &str) -> Result<google_drive3::api::File> {
+
+    async fn delete_file(&self, file_id: &str) -> Result<(), DriveError> {
+        let call = crate::metrics::ApiCall::start("delete_file");
+        tracing::info!("🔥 Eliminando definitivamente: {}", file_id);
+
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| DriveError::Auth(format!("{}", e)))?
+            .ok_or_else(|| DriveError::Auth("No token available".into()))?;
+
+        let url = format!("{}/drive/v3/files/{}", self.api_base, file_id);
+        let response = self.http
+            .delete(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "delete_file").await);
+        }
+
+        tracing::info!("✅ Archivo eliminado definitivamente: {}", file_id);
+        call.succeeded();
+        Ok(())
+    }
+    async fn get_file_metadata(&self, file_id: &str) -> Result<google_drive3::api::File> {
+        let call = crate::metrics::ApiCall::start("get_file_metadata");
         let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
             .await
             .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
@@ -645,7 +800,8 @@ impl DriveClient {
         let client = &self.http;
         // Solicitamos name, parents, md5Checksum, size y capabilities para verificar permisos
         let url = format!(
-            "https://www.googleapis.com/drive/v3/files/{}?fields=id,name,parents,md5Checksum,mimeType,size,shared,ownedByMe,capabilities&supportsAllDrives=true",
+            "{}/drive/v3/files/{}?fields=id,name,parents,md5Checksum,mimeType,size,createdTime,shared,starred,ownedByMe,owners(displayName,emailAddress),capabilities,appProperties,description,folderColorRgb&supportsAllDrives=true",
+            self.api_base,
             file_id
         );
 
@@ -657,10 +813,7 @@ impl DriveClient {
             .context("Error de red al obtener metadata")?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            tracing::error!("Error API Drive get_file_metadata: {} - {}", status, body);
-            anyhow::bail!("Error API Drive get_file_metadata: {} - {}", status, body);
+            return Err(api_error(response, "get_file_metadata").await.into());
         }
 
         let body = response.text().await.context("Error leyendo body")?;
@@ -669,11 +822,307 @@ impl DriveClient {
         let file: google_drive3::api::File = serde_json::from_str(&body)
             .context("Error al parsear respuesta de get_file_metadata")?;
 
+        call.succeeded();
         Ok(file)
     }
 
-    /// Actualiza solo los metadatos de un archivo (nombre, padres, modifiedTime)
-    pub async fn update_file_metadata(
+    async fn create_share_link(&self, file_id: &str, role: &str) -> Result<String> {
+        let call = crate::metrics::ApiCall::start("create_share_link");
+        tracing::info!("🔗 Creando enlace para compartir: {} (rol={})", file_id, role);
+
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        let client = &self.http;
+        let url = format!(
+            "{}/drive/v3/files/{}/permissions?supportsAllDrives=true",
+            self.api_base,
+            file_id
+        );
+
+        let response = client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&serde_json::json!({ "type": "anyone", "role": role }))
+            .send()
+            .await
+            .context("Error de red al crear permiso")?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "create_share_link").await.into());
+        }
+        // La petición del enlace la mide `get_web_view_link`: no contarla dos veces
+        call.succeeded();
+
+        let link = self.get_web_view_link(file_id).await?;
+
+        tracing::info!("✅ Enlace creado para {}", file_id);
+        Ok(link)
+    }
+
+    async fn get_web_view_link(&self, file_id: &str) -> Result<String> {
+        let call = crate::metrics::ApiCall::start("get_web_view_link");
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        let url = format!(
+            "{}/drive/v3/files/{}?fields=webViewLink&supportsAllDrives=true",
+            self.api_base,
+            file_id
+        );
+
+        let response = self.http
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .context("Error de red al obtener webViewLink")?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "get_web_view_link").await.into());
+        }
+
+        let file: google_drive3::api::File = response.json()
+            .await
+            .context("Error al parsear respuesta de webViewLink")?;
+
+        let link = file.web_view_link
+            .ok_or_else(|| anyhow::anyhow!("Drive no retornó webViewLink para {}", file_id))?;
+        call.succeeded();
+        Ok(link)
+    }
+
+    async fn get_thumbnail_link(&self, file_id: &str) -> Result<Option<String>> {
+        let call = crate::metrics::ApiCall::start("get_thumbnail_link");
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        let url = format!(
+            "{}/drive/v3/files/{}?fields=thumbnailLink&supportsAllDrives=true",
+            self.api_base,
+            file_id
+        );
+
+        let response = self.http
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .context("Error de red al obtener thumbnailLink")?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "get_thumbnail_link").await.into());
+        }
+
+        let file: google_drive3::api::File = response.json()
+            .await
+            .context("Error al parsear respuesta de thumbnailLink")?;
+        call.succeeded();
+        Ok(file.thumbnail_link)
+    }
+
+    async fn download_thumbnail(&self, link: &str) -> Result<Vec<u8>> {
+        let call = crate::metrics::ApiCall::start("download_thumbnail");
+        // Las miniaturas de archivos privados también exigen el token
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        let response = self.http
+            .get(link)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .context("Error de red al descargar miniatura")?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "download_thumbnail").await.into());
+        }
+
+        let bytes = response.bytes().await.context("Error al leer la miniatura")?;
+        crate::metrics::add_bytes_downloaded(bytes.len() as u64);
+        call.succeeded();
+        Ok(bytes.to_vec())
+    }
+
+    async fn list_revisions(&self, file_id: &str) -> Result<Vec<google_drive3::api::Revision>> {
+        let call = crate::metrics::ApiCall::start("list_revisions");
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        let mut revisions = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "{}/drive/v3/files/{}/revisions?pageSize=200&fields=nextPageToken,revisions(id,modifiedTime,size,md5Checksum,keepForever,lastModifyingUser(displayName))",
+                self.api_base,
+                file_id
+            );
+            if let Some(ref token_str) = page_token {
+                url.push_str(&format!("&pageToken={}", token_str));
+            }
+
+            let response = self.http
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", token))
+                .send()
+                .await
+                .context("Error de red al listar revisiones")?;
+
+            if !response.status().is_success() {
+                return Err(api_error(response, "list_revisions").await.into());
+            }
+
+            let list: google_drive3::api::RevisionList = response.json()
+                .await
+                .context("Error al parsear la lista de revisiones")?;
+            revisions.extend(list.revisions.unwrap_or_default());
+
+            page_token = list.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+
+        tracing::debug!("🕘 {} revisiones de {}", revisions.len(), file_id);
+        call.succeeded();
+        Ok(revisions)
+    }
+
+    async fn download_revision(&self, file_id: &str, revision_id: &str, dest: &std::path::Path) -> Result<u64> {
+        use tokio::io::AsyncWriteExt;
+
+        let call = crate::metrics::ApiCall::start("download_revision");
+        tracing::info!("🕘 Descargando revisión {} de {}", revision_id, file_id);
+
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        let url = format!(
+            "{}/drive/v3/files/{}/revisions/{}?alt=media&acknowledgeAbuse=true",
+            self.api_base,
+            file_id,
+            revision_id
+        );
+        let mut response = self.http
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .context("Error de red al descargar revisión")?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "download_revision").await.into());
+        }
+
+        // Por trozos: una revisión puede ser tan grande como el archivo
+        let mut file = tokio::fs::File::create(dest)
+            .await
+            .with_context(|| format!("No se pudo crear {:?}", dest))?;
+        let mut written = 0u64;
+        while let Some(chunk) = response.chunk().await.context("Error al leer la revisión")? {
+            file.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        file.flush().await?;
+
+        crate::metrics::add_bytes_downloaded(written);
+        call.succeeded();
+        Ok(written)
+    }
+
+    async fn set_starred(&self, file_id: &str, starred: bool) -> Result<()> {
+        let call = crate::metrics::ApiCall::start("set_starred");
+        tracing::info!("⭐ Actualizando destacado de {}: {}", file_id, starred);
+
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        let url = format!("{}/drive/v3/files/{}?supportsAllDrives=true", self.api_base, file_id);
+        let response = self.http
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&serde_json::json!({ "starred": starred }))
+            .send()
+            .await
+            .context("Error de red al actualizar destacado")?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "set_starred").await.into());
+        }
+
+        call.succeeded();
+        Ok(())
+    }
+
+    async fn set_app_property(&self, file_id: &str, key: &str, value: Option<&str>) -> Result<()> {
+        let call = crate::metrics::ApiCall::start("set_app_property");
+        tracing::debug!("🏷️ appProperty {}={:?} en {}", key, value, file_id);
+
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        // `null` borra la propiedad; las demás quedan como están
+        let url = format!("{}/drive/v3/files/{}?fields=id&supportsAllDrives=true", self.api_base, file_id);
+        let response = self.http
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&serde_json::json!({ "appProperties": { key: value } }))
+            .send()
+            .await
+            .context("Error de red al actualizar appProperties")?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "set_app_property").await.into());
+        }
+
+        call.succeeded();
+        Ok(())
+    }
+
+    async fn set_description(&self, file_id: &str, description: Option<&str>) -> Result<()> {
+        let call = crate::metrics::ApiCall::start("set_description");
+        tracing::info!("📝 Actualizando descripción de {}", file_id);
+
+        let token = self.hub.auth.get_token(&["https://www.googleapis.com/auth/drive"])
+            .await
+            .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
+            .context("No se obtuvo ningún token válido")?;
+
+        // `null` borra la descripción
+        let url = format!("{}/drive/v3/files/{}?fields=id&supportsAllDrives=true", self.api_base, file_id);
+        let response = self.http
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&serde_json::json!({ "description": description }))
+            .send()
+            .await
+            .context("Error de red al actualizar la descripción")?;
+
+        if !response.status().is_success() {
+            return Err(api_error(response, "set_description").await.into());
+        }
+
+        call.succeeded();
+        Ok(())
+    }
+
+    async fn update_file_metadata(
         &self,
         file_id: &str,
         new_name: Option<&str>,
@@ -681,6 +1130,7 @@ impl DriveClient {
         remove_parent: Option<&str>,
         new_mtime: Option<google_drive3::chrono::DateTime<google_drive3::chrono::Utc>>,
     ) -> Result<()> {
+        let call = crate::metrics::ApiCall::start("update_file_metadata");
         tracing::info!("📝 Actualizando metadatos de archivo: {} (name={:?}, mtime={:?})", 
                        file_id, new_name, new_mtime);
 
@@ -689,7 +1139,7 @@ impl DriveClient {
             .map_err(|e| anyhow::anyhow!("Error de autenticación: {}", e))?
             .context("No se obtuvo ningún token válido")?;
 
-        let mut url = format!("https://www.googleapis.com/drive/v3/files/{}", file_id);
+        let mut url = format!("{}/drive/v3/files/{}", self.api_base, file_id);
         
         // Query params
         let mut params = Vec::new();
@@ -714,9 +1164,9 @@ impl DriveClient {
             json_map.insert("name".to_string(), serde_json::Value::String(name.to_string()));
         }
         if let Some(mtime) = new_mtime {
-            // Google Drive espera RFC3339
+            // Google Drive espera RFC3339 (con milisegundos)
             use google_drive3::chrono::SecondsFormat;
-            json_map.insert("modifiedTime".to_string(), serde_json::Value::String(mtime.to_rfc3339_opts(SecondsFormat::Secs, true)));
+            json_map.insert("modifiedTime".to_string(), serde_json::Value::String(mtime.to_rfc3339_opts(SecondsFormat::Millis, true)));
         }
 
         let client = &self.http;
@@ -729,14 +1179,390 @@ impl DriveClient {
             .context("Error de red al actualizar metadatos")?;
 
         if !response.status().is_success() {
-             let status = response.status();
-             let body = response.text().await.unwrap_or_default();
-             tracing::error!("Error API Drive update_file_metadata: {} - {}", status, body);
-             anyhow::bail!("Error API Drive update_file_metadata: {} - {}", status, body);
+            return Err(api_error(response, "update_file_metadata").await.into());
         }
 
         tracing::info!("✅ Metadatos actualizados para: {}", file_id);
+        call.succeeded();
         Ok(())
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use crate::gdrive::DriveError;
+    use wiremock::matchers::{body_json, body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Servidor HTTP falso y un cliente que le envía todas las peticiones
+    async fn fake_drive() -> (MockServer, DriveClient) {
+        let server = MockServer::start().await;
+        let auth = yup_oauth2::AccessTokenAuthenticator::builder("token-de-prueba".into())
+            .build()
+            .await
+            .expect("autenticador de prueba");
+        let client = DriveClient::new(auth, &HttpSettings::default())
+            .expect("cliente de prueba")
+            .with_base_url(&server.uri());
+        (server, client)
+    }
+
+    #[tokio::test]
+    async fn test_get_root_file_id_sends_bearer_token() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/files/root"))
+            .and(header("Authorization", "Bearer token-de-prueba"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "root-123" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(client.get_root_file_id().await.unwrap(), "root-123");
+    }
+
+    #[tokio::test]
+    async fn test_download_chunk_requests_byte_range() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/files/f1"))
+            .and(query_param("alt", "media"))
+            .and(header("Range", "bytes=10-13"))
+            .respond_with(ResponseTemplate::new(206).set_body_bytes(b"hola".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(client.download_chunk("f1", 10, 4).await.unwrap(), b"hola");
+    }
+
+    #[rstest]
+    #[case::no_exclusions(&[], "trashed = false")]
+    #[case::excluded(&["f1", "f2"], "trashed = false and not 'f1' in parents and not 'f2' in parents")]
+    fn test_files_query(#[case] excluded: &[&str], #[case] expected: &str) {
+        let excluded: Vec<String> = excluded.iter().map(|id| id.to_string()).collect();
+        assert_eq!(files_query(&excluded), expected);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_files_page_filters_excluded_parents() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/files"))
+            .and(query_param("q", "trashed = false and not 'folder-x' in parents"))
+            .and(query_param("pageSize", "1000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "files": [{ "id": "a", "name": "a.txt" }],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let (files, next) = client.fetch_files_page(None, &["folder-x".to_string()]).await.unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(next, None);
+    }
+
+    #[rstest]
+    #[case::more_pages(Some("p2"), None, Some("p2"), true)]
+    #[case::last_page(None, Some("s9"), Some("s9"), false)]
+    #[tokio::test]
+    async fn test_list_changes_tokens(
+        #[case] next_page: Option<&str>,
+        #[case] new_start: Option<&str>,
+        #[case] expected_token: Option<&str>,
+        #[case] expected_more: bool,
+    ) {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/changes"))
+            .and(query_param("pageToken", "p1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "nextPageToken": next_page,
+                "newStartPageToken": new_start,
+                "changes": [
+                    { "fileId": "a", "removed": false, "file": { "id": "a", "name": "a.txt" } },
+                    { "fileId": "b", "removed": true },
+                ],
+            })))
+            .mount(&server)
+            .await;
+
+        let (changes, token, has_more) = client.list_changes("p1").await.unwrap();
+
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].file.as_ref().and_then(|f| f.name.as_deref()), Some("a.txt"));
+        assert_eq!(changes[1].removed, Some(true));
+        assert_eq!(token.as_deref(), expected_token);
+        assert_eq!(has_more, expected_more);
+    }
+
+    #[rstest]
+    #[case::not_found(404, "{}", "not_found")]
+    #[case::shared(403, r#"{"error":{"errors":[{"reason":"insufficientFilePermissions"}]}}"#, "permissions")]
+    #[case::rate_limit(403, r#"{"error":{"errors":[{"reason":"userRateLimitExceeded"}]}}"#, "rate_limited")]
+    #[case::server(500, "{}", "api")]
+    #[tokio::test]
+    async fn test_trash_file_maps_errors(#[case] status: u16, #[case] body: &str, #[case] expected: &str) {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("PATCH"))
+            .and(path("/drive/v3/files/f1"))
+            .respond_with(ResponseTemplate::new(status).set_body_string(body))
+            .mount(&server)
+            .await;
+
+        let kind = match client.trash_file("f1").await.unwrap_err() {
+            DriveError::NotFound(_) => "not_found",
+            DriveError::InsufficientPermissions(_) => "permissions",
+            DriveError::RateLimited { .. } => "rate_limited",
+            DriveError::ApiError(_) => "api",
+            other => panic!("error inesperado: {:?}", other),
+        };
+        assert_eq!(kind, expected);
+    }
+
+    #[rstest]
+    #[case::deleted(204, true)]
+    #[case::not_found(404, false)]
+    #[tokio::test]
+    async fn test_delete_file_sends_delete(#[case] status: u16, #[case] ok: bool) {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("DELETE"))
+            .and(path("/drive/v3/files/f1"))
+            .respond_with(ResponseTemplate::new(status))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        match client.delete_file("f1").await {
+            Ok(()) => assert!(ok),
+            Err(DriveError::NotFound(_)) => assert!(!ok),
+            Err(other) => panic!("error inesperado: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_posts_to_upload_endpoint() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("POST"))
+            .and(path("/upload/drive/v3/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "nuevo-1" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("nota.txt");
+        std::fs::write(&file_path, b"contenido").unwrap();
+
+        let id = client
+            .upload_file(&file_path, "nota.txt", Some("text/plain"), "parent-1", None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(id, "nuevo-1");
+    }
+
+    #[tokio::test]
+    async fn test_generate_ids_requests_file_ids() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/files/generateIds"))
+            .and(query_param("count", "2"))
+            .and(query_param("type", "files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "kind": "drive#generatedIds",
+                "space": "drive",
+                "ids": ["id-a", "id-b"]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(client.generate_ids(2).await.unwrap(), ["id-a", "id-b"]);
+    }
+
+    #[tokio::test]
+    async fn test_list_revisions_follows_pages() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/files/f1/revisions"))
+            .and(query_param("pageToken", "p2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "revisions": [{ "id": "r3", "size": "30" }],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/files/f1/revisions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "nextPageToken": "p2",
+                "revisions": [
+                    { "id": "r1", "size": "10", "lastModifyingUser": { "displayName": "Ana" } },
+                    { "id": "r2", "size": "20", "keepForever": true },
+                ],
+            })))
+            .mount(&server)
+            .await;
+
+        let revisions = client.list_revisions("f1").await.unwrap();
+
+        let ids: Vec<_> = revisions.iter().filter_map(|r| r.id.as_deref()).collect();
+        assert_eq!(ids, ["r1", "r2", "r3"]);
+        assert_eq!(revisions[0].size, Some(10));
+        assert_eq!(revisions[0].last_modifying_user.as_ref().and_then(|u| u.display_name.as_deref()), Some("Ana"));
+        assert_eq!(revisions[1].keep_forever, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_download_revision_writes_content() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/files/f1/revisions/r1"))
+            .and(query_param("alt", "media"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"version antigua".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("rev");
+        assert_eq!(client.download_revision("f1", "r1", &dest).await.unwrap(), 15);
+        assert_eq!(std::fs::read(&dest).unwrap(), b"version antigua");
+    }
+
+    #[tokio::test]
+    async fn test_set_starred_patches_flag() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("PATCH"))
+            .and(path("/drive/v3/files/f1"))
+            .and(body_json(serde_json::json!({ "starred": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id":"f1","starred":true}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client.set_starred("f1", true).await.unwrap();
+    }
+
+    #[rstest]
+    #[case::set(Some("1"), serde_json::json!({ "appProperties": { "gdxpPin": "1" } }))]
+    #[case::clear(None, serde_json::json!({ "appProperties": { "gdxpPin": null } }))]
+    #[tokio::test]
+    async fn test_set_app_property_patches_only_that_key(#[case] value: Option<&str>, #[case] body: serde_json::Value) {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("PATCH"))
+            .and(path("/drive/v3/files/f1"))
+            .and(body_json(body))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id":"f1"}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client.set_app_property("f1", "gdxpPin", value).await.unwrap();
+    }
+
+    #[rstest]
+    #[case::set(Some("Facturas de 2025"), serde_json::json!({ "description": "Facturas de 2025" }))]
+    #[case::clear(None, serde_json::json!({ "description": null }))]
+    #[tokio::test]
+    async fn test_set_description_patches_field(#[case] description: Option<&str>, #[case] body: serde_json::Value) {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("PATCH"))
+            .and(path("/drive/v3/files/f1"))
+            .and(body_json(body))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id":"f1"}"#))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        client.set_description("f1", description).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_create_folder_is_tagged_with_client_id() {
+        let (server, client) = fake_drive().await;
+        let client = client.with_client_id("instalacion-1");
+        Mock::given(method("POST"))
+            .and(path("/upload/drive/v3/files"))
+            .and(body_string_contains(r#""appProperties":{"gdxpClient":"instalacion-1"}"#))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "carpeta-1" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(client.create_folder("Fotos", "root", None).await.unwrap(), "carpeta-1");
+    }
+
+    #[tokio::test]
+    async fn test_create_workspace_file_sends_google_mime_type() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("POST"))
+            .and(path("/upload/drive/v3/files"))
+            .and(body_string_contains(r#""mimeType":"application/vnd.google-apps.spreadsheet""#))
+            .and(body_string_contains(r#""name":"Cuentas""#))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "hoja-1" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let id = client
+            .create_workspace_file("Cuentas", "application/vnd.google-apps.spreadsheet", "root", None)
+            .await
+            .unwrap();
+        assert_eq!(id, "hoja-1");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_typed_with_retry_after() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("GET"))
+            .and(path("/drive/v3/changes"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("Retry-After", "7")
+                    .set_body_string(r#"{"error":{"code":429,"message":"slow down","errors":[{"reason":"rateLimitExceeded"}]}}"#),
+            )
+            .mount(&server)
+            .await;
+
+        let error = client.list_changes("p1").await.unwrap_err();
+        match DriveError::find(&error) {
+            Some(DriveError::RateLimited { retry_after, .. }) => {
+                assert_eq!(*retry_after, Some(std::time::Duration::from_secs(7)));
+            }
+            other => panic!("se esperaba RateLimited: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_streams_from_disk_with_progress() {
+        let (server, client) = fake_drive().await;
+        Mock::given(method("POST"))
+            .and(path("/upload/drive/v3/files"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": "nuevo" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("nota.txt");
+        std::fs::write(&file, vec![b'x'; 300_000]).unwrap();
+        let progress = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = progress.clone();
+
+        let id = client
+            .upload_file(&file, "nota.txt", None, "root", None, None, Some(Box::new(move |sent, total| {
+                seen.lock().unwrap().push((sent, total));
+            })))
+            .await
+            .unwrap();
+
+        assert_eq!(id, "nuevo");
+        let progress = progress.lock().unwrap();
+        assert!(progress.iter().all(|&(sent, total)| total == 300_000 && sent <= total));
+        assert_eq!(progress.last(), Some(&(300_000, 300_000)));
+    }
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -7,6 +8,22 @@ pub enum DriveError {
     
     #[error("Archivo no encontrado: {0}")]
     NotFound(String),
+
+    /// 429 o `rateLimitExceeded`: reintentar tras `retry_after` (cabecera
+    /// `Retry-After`) o con backoff si Drive no la envía
+    #[error("Límite de peticiones de Google Drive alcanzado: {message}")]
+    RateLimited { retry_after: Option<Duration>, message: String },
+
+    /// Cuota de almacenamiento o límites de Drive (archivos por carpeta,
+    /// descargas diarias de un archivo): reintentar no cambia el resultado
+    #[error("Cuota de Google Drive excedida: {0}")]
+    QuotaExceeded(String),
+
+    /// `storageQuotaExceeded`: el almacenamiento de la cuenta está lleno.
+    /// Permanente como `QuotaExceeded`, pero además frena las escrituras
+    /// locales (`utils::quota`)
+    #[error("Almacenamiento de Google Drive lleno: {0}")]
+    StorageFull(String),
     
     #[error("Error de red: {0}")]
     Network(#[from] reqwest::Error),
@@ -21,11 +38,99 @@ pub enum DriveError {
     Other(#[from] anyhow::Error),
 }
 
+/// Cuerpo de error de la API v3: `{"error": {"code", "message", "errors": [{"domain", "reason", "message"}]}}`
+#[derive(serde::Deserialize)]
+struct ErrorBody {
+    error: ErrorDetail,
+}
+
+#[derive(serde::Deserialize)]
+struct ErrorDetail {
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    errors: Vec<ErrorItem>,
+}
+
+#[derive(serde::Deserialize)]
+struct ErrorItem {
+    #[serde(default)]
+    reason: String,
+}
+
 impl DriveError {
+    /// Clasifica una respuesta HTTP fallida de Drive según su código y el
+    /// `reason` del cuerpo JSON. `retry_after` es el valor de la cabecera
+    /// `Retry-After`, si vino. Lo que no se reconoce queda como `ApiError`
+    /// con el código y el cuerpo completos.
+    pub fn from_response(status: u16, retry_after: Option<&str>, body: &str) -> Self {
+        let (reason, message) = match serde_json::from_str::<ErrorBody>(body) {
+            Ok(parsed) => (
+                parsed.error.errors.into_iter().map(|e| e.reason).find(|r| !r.is_empty()).unwrap_or_default(),
+                parsed.error.message,
+            ),
+            Err(_) => (String::new(), String::new()),
+        };
+        let detail = if reason.is_empty() {
+            format!("{} - {}", status, body)
+        } else {
+            format!("{} {}: {}", status, reason, message)
+        };
+
+        match (status, reason.as_str()) {
+            (429, _) | (403, "rateLimitExceeded" | "userRateLimitExceeded" | "sharingRateLimitExceeded") => {
+                DriveError::RateLimited {
+                    retry_after: retry_after.and_then(|v| v.trim().parse().ok()).map(Duration::from_secs),
+                    message: detail,
+                }
+            }
+            (403, "storageQuotaExceeded") => DriveError::StorageFull(detail),
+            (403, "quotaExceeded" | "downloadQuotaExceeded"
+                | "numChildrenInNonRootLimitExceeded" | "teamDriveFileLimitExceeded") => {
+                DriveError::QuotaExceeded(detail)
+            }
+            (404, _) => DriveError::NotFound(detail),
+            (403, r) if r == "insufficientFilePermissions"
+                || r == "appNotAuthorizedToFile"
+                || r == "forbidden"
+                || r.starts_with("cannot") => DriveError::InsufficientPermissions(detail),
+            (401, _) => DriveError::Auth(detail),
+            _ => DriveError::ApiError(detail),
+        }
+    }
+
+    /// Primer `DriveError` en la cadena de causas de un error `anyhow`
+    pub fn find(error: &anyhow::Error) -> Option<&DriveError> {
+        error.chain().find_map(|cause| cause.downcast_ref::<DriveError>())
+    }
+
+    /// Espera pedida por Drive (`Retry-After`) si el error es un límite de peticiones
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            DriveError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, DriveError::RateLimited { .. })
+    }
+
+    /// Drive rechazó la escritura porque el almacenamiento de la cuenta está
+    /// lleno (`storageQuotaExceeded`), no por otro límite de cuota
+    pub fn is_storage_full(&self) -> bool {
+        matches!(self, DriveError::StorageFull(_))
+    }
+
     /// Retorna true si el error es permanente (no vale la pena reintentar)
-    #[cfg(test)]
     pub fn is_permanent(&self) -> bool {
-        matches!(self, DriveError::InsufficientPermissions(_) | DriveError::NotFound(_))
+        matches!(
+            self,
+            DriveError::InsufficientPermissions(_)
+                | DriveError::NotFound(_)
+                | DriveError::QuotaExceeded(_)
+                | DriveError::StorageFull(_)
+        )
     }
 }
 
@@ -40,10 +145,28 @@ mod tests {
     #[case::api_error(DriveError::ApiError("500 internal".into()), false)]
     #[case::auth(DriveError::Auth("token expired".into()), false)]
     #[case::other(DriveError::Other(anyhow::anyhow!("something")), false)]
+    #[case::quota(DriveError::QuotaExceeded("quotaExceeded".into()), true)]
+    #[case::storage_full(DriveError::StorageFull("storageQuotaExceeded".into()), true)]
+    #[case::rate_limited(DriveError::RateLimited { retry_after: None, message: "429".into() }, false)]
     fn test_is_permanent(#[case] error: DriveError, #[case] expected: bool) {
         assert_eq!(error.is_permanent(), expected);
     }
 
+    #[rstest]
+    #[case::storage(403, "storageQuotaExceeded", true)]
+    #[case::folder_limit(403, "numChildrenInNonRootLimitExceeded", false)]
+    #[case::download_limit(403, "downloadQuotaExceeded", false)]
+    #[case::rate_limited(429, "rateLimitExceeded", false)]
+    fn test_is_storage_full(#[case] status: u16, #[case] reason: &str, #[case] expected: bool) {
+        assert_eq!(DriveError::from_response(status, None, &api_body(reason)).is_storage_full(), expected);
+    }
+
+    #[test]
+    fn test_is_storage_full_ignores_message_text() {
+        let body = r#"{"error":{"code":403,"message":"storageQuotaExceeded","errors":[{"reason":"quotaExceeded"}]}}"#;
+        assert!(!DriveError::from_response(403, None, body).is_storage_full(), "solo cuenta el reason");
+    }
+
     #[rstest]
     #[case::permissions(DriveError::InsufficientPermissions("read".into()), "Permisos insuficientes: read")]
     #[case::not_found(DriveError::NotFound("abc".into()), "Archivo no encontrado: abc")]
@@ -52,4 +175,66 @@ mod tests {
     fn test_display_messages(#[case] error: DriveError, #[case] expected: &str) {
         assert_eq!(error.to_string(), expected);
     }
+
+    /// Cuerpo de error de Drive con un único `reason`
+    fn api_body(reason: &str) -> String {
+        format!(
+            r#"{{"error":{{"code":403,"message":"mensaje de Drive","errors":[{{"domain":"usageLimits","reason":"{}","message":"mensaje de Drive"}}]}}}}"#,
+            reason
+        )
+    }
+
+    #[rstest]
+    #[case::not_found(404, "notFound", "not_found")]
+    #[case::permissions(403, "insufficientFilePermissions", "permissions")]
+    #[case::abusive_file(403, "cannotDownloadAbusiveFile", "permissions")]
+    #[case::user_rate(403, "userRateLimitExceeded", "rate_limited")]
+    #[case::too_many_requests(429, "rateLimitExceeded", "rate_limited")]
+    #[case::storage(403, "storageQuotaExceeded", "storage_full")]
+    #[case::folder_limit(403, "numChildrenInNonRootLimitExceeded", "quota")]
+    #[case::auth(401, "authError", "auth")]
+    #[case::server(500, "backendError", "api")]
+    fn test_from_response_reason(#[case] status: u16, #[case] reason: &str, #[case] expected: &str) {
+        let kind = match DriveError::from_response(status, None, &api_body(reason)) {
+            DriveError::NotFound(_) => "not_found",
+            DriveError::InsufficientPermissions(_) => "permissions",
+            DriveError::RateLimited { .. } => "rate_limited",
+            DriveError::QuotaExceeded(_) => "quota",
+            DriveError::StorageFull(_) => "storage_full",
+            DriveError::Auth(_) => "auth",
+            DriveError::ApiError(_) => "api",
+            other => panic!("variante inesperada: {:?}", other),
+        };
+        assert_eq!(kind, expected);
+    }
+
+    #[rstest]
+    #[case::seconds(Some("30"), Some(Duration::from_secs(30)))]
+    #[case::missing(None, None)]
+    #[case::http_date(Some("Wed, 21 Oct 2026 07:28:00 GMT"), None)]
+    fn test_from_response_retry_after(#[case] header: Option<&str>, #[case] expected: Option<Duration>) {
+        match DriveError::from_response(429, header, "Too Many Requests") {
+            DriveError::RateLimited { retry_after, .. } => assert_eq!(retry_after, expected),
+            other => panic!("se esperaba RateLimited: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_response_keeps_status_and_body() {
+        // Los callers que aún miran el texto (416 en FUSE) siguen viendo el código
+        let error = DriveError::from_response(416, None, "Requested range not satisfiable");
+        assert_eq!(
+            error.to_string(),
+            "Error de la API de Google Drive: 416 - Requested range not satisfiable"
+        );
+        let error = DriveError::from_response(404, None, &api_body("notFound"));
+        assert_eq!(error.to_string(), "Archivo no encontrado: 404 notFound: mensaje de Drive");
+    }
+
+    #[test]
+    fn test_find_in_context_chain() {
+        let error = anyhow::Error::new(DriveError::NotFound("x".into())).context("Error subiendo archivo");
+        assert!(matches!(DriveError::find(&error), Some(DriveError::NotFound(_))));
+        assert!(DriveError::find(&anyhow::anyhow!("sin tipo")).is_none());
+    }
 }
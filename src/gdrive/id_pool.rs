@@ -0,0 +1,109 @@
+//! Reserva de IDs de Drive para archivos creados localmente
+//!
+//! `create`/`mkdir` de FUSE necesitan un `gdrive_id` al instante, sin esperar
+//! a la red. `DriveIdPool` guarda IDs reservados con `files.generateIds` y se
+//! rellena en background al bajar del mínimo: el archivo nace con su ID
+//! definitivo y el uploader lo crea en Drive con ese mismo ID (sin renombrar
+//! caché ni inode). Si la reserva está vacía (offline, primer uso) se usa un
+//! `temp_<uuid>` y Drive asigna el ID al subir.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::DriveApi;
+
+/// Prefijo de los IDs provisionales (no reservados en Drive)
+pub const TEMP_ID_PREFIX: &str = "temp_";
+/// IDs pedidos por llamada a `files.generateIds`
+const REFILL_BATCH: u32 = 50;
+/// Por debajo de este número de IDs se pide otro lote
+const LOW_WATERMARK: usize = 10;
+
+/// Indica si `gdrive_id` es provisional (Drive asignará el real al subir)
+pub fn is_temp_id(gdrive_id: &str) -> bool {
+    gdrive_id.starts_with(TEMP_ID_PREFIX)
+}
+
+pub struct DriveIdPool {
+    drive_client: Arc<dyn DriveApi>,
+    ids: Mutex<VecDeque<String>>,
+    refilling: AtomicBool,
+}
+
+impl DriveIdPool {
+    pub fn new(drive_client: Arc<dyn DriveApi>) -> Self {
+        Self {
+            drive_client,
+            ids: Mutex::new(VecDeque::new()),
+            refilling: AtomicBool::new(false),
+        }
+    }
+
+    /// ID para un archivo nuevo: uno reservado si hay, si no `temp_<uuid>`.
+    /// No bloquea; repone la reserva en background si hace falta.
+    pub fn take(self: &Arc<Self>) -> String {
+        let reserved = self.ids.lock().unwrap().pop_front();
+        self.refill_in_background();
+        reserved.unwrap_or_else(|| format!("{}{}", TEMP_ID_PREFIX, uuid::Uuid::new_v4()))
+    }
+
+    /// IDs reservados disponibles
+    pub fn available(&self) -> usize {
+        self.ids.lock().unwrap().len()
+    }
+
+    /// Lanza una reposición si la reserva está baja y hay red
+    pub fn refill_in_background(self: &Arc<Self>) {
+        if self.available() >= LOW_WATERMARK || !crate::utils::network::is_online() {
+            return;
+        }
+        if self.refilling.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let pool = Arc::clone(self);
+        tokio::spawn(async move {
+            pool.refill().await;
+            pool.refilling.store(false, Ordering::Release);
+        });
+    }
+
+    /// Pide un lote de IDs a Drive y lo añade a la reserva
+    pub async fn refill(&self) {
+        match self.drive_client.generate_ids(REFILL_BATCH).await {
+            Ok(ids) => {
+                tracing::debug!("🆔 {} IDs de Drive reservados", ids.len());
+                self.ids.lock().unwrap().extend(ids);
+            }
+            Err(e) => {
+                crate::utils::network::report_error(&e);
+                tracing::warn!("⚠️ No se pudieron reservar IDs de Drive: {:?}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gdrive::mock::MockDriveClient;
+
+    #[tokio::test]
+    async fn test_take_uses_reserved_ids_then_falls_back_to_temp() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_offline("test");
+        let mock = Arc::new(MockDriveClient::new());
+        let pool = Arc::new(DriveIdPool::new(mock.clone()));
+        assert!(is_temp_id(&pool.take()), "sin reserva ni red: ID provisional");
+        assert_eq!(mock.calls("generate_ids"), 0);
+        crate::utils::network::set_online();
+
+        pool.refill().await;
+        assert_eq!(pool.available(), REFILL_BATCH as usize);
+        assert_eq!(mock.calls("generate_ids"), 1);
+
+        let id = pool.take();
+        assert!(!is_temp_id(&id), "ID reservado: {}", id);
+        assert_eq!(pool.available(), REFILL_BATCH as usize - 1);
+    }
+}
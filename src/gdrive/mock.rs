@@ -0,0 +1,700 @@
+//! Drive en memoria para tests
+//!
+//! `MockDriveClient` implementa `DriveApi` sobre un mapa de archivos y una
+//! lista de cambios, sin red ni credenciales. Los tests siembran el estado
+//! remoto (`add_file`, `modify_remote`, `trash_remote`...) y verifican lo que
+//! hizo el código bajo prueba (`content`, `find_by_name_prefix`, `calls`). Cada
+//! mutación registra un cambio, como haría Drive, de modo que `list_changes`
+//! también devuelve el eco de las subidas propias.
+
+use anyhow::Result;
+use google_drive3::api::{Change, File, Revision};
+use google_drive3::chrono::{DateTime, Utc};
+use md5::{Digest, Md5};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+
+use super::api::{DriveApi, ProgressCallback};
+use super::DriveError;
+
+/// ID canónico del root ("My Drive") que reporta el mock
+pub const MOCK_ROOT_ID: &str = "mock-root";
+
+const FOLDER_MIME: &str = "application/vnd.google-apps.folder";
+
+/// Drive falso en memoria
+#[derive(Debug)]
+pub struct MockDriveClient {
+    state: Mutex<MockState>,
+    page_size: usize,
+    /// Como `DriveClient::with_client_id`: marca lo que se crea
+    client_id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    files: BTreeMap<String, File>,
+    contents: HashMap<String, Vec<u8>>,
+    /// Historial de contenido por archivo, de la revisión más antigua a la actual
+    revisions: HashMap<String, Vec<(Revision, Vec<u8>)>>,
+    next_revision: u64,
+    changes: Vec<Change>,
+    next_id: u64,
+    /// Imagen de cada `thumbnailLink` (sin el sufijo de tamaño `=sN`)
+    thumbnails: HashMap<String, Vec<u8>>,
+    calls: HashMap<&'static str, usize>,
+    /// Fallo programado por método: llamadas que aún deben tener éxito y error
+    failures: HashMap<&'static str, (usize, anyhow::Error)>,
+    /// Métodos que nunca responden (Drive colgado)
+    stalled: HashSet<&'static str>,
+}
+
+impl MockState {
+    /// Registra la llamada y consume el fallo programado para `method`, si lo hay
+    fn begin(&mut self, method: &'static str) -> Result<()> {
+        *self.calls.entry(method).or_default() += 1;
+        match self.failures.get_mut(method) {
+            Some((0, _)) => Err(self.failures.remove(method).expect("fallo programado").1),
+            Some((skip, _)) => {
+                *skip -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn new_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("mock-file-{}", self.next_id)
+    }
+
+    /// ID para un archivo nuevo: el reservado (`generate_ids`) o uno nuevo.
+    /// Reusar el ID de un archivo existente falla como en Drive (409).
+    fn assign_id(&mut self, file_id: Option<&str>) -> Result<String> {
+        match file_id {
+            Some(id) if self.files.contains_key(id) => {
+                Err(DriveError::ApiError(format!("409 Conflict: fileIdInUse ({})", id)).into())
+            }
+            Some(id) => Ok(id.to_string()),
+            None => Ok(self.new_id()),
+        }
+    }
+
+    /// Guarda el archivo y registra el cambio correspondiente
+    fn put(&mut self, file: File) {
+        let id = file.id.clone().expect("archivo sin id");
+        self.changes.push(Change {
+            file_id: Some(id.clone()),
+            removed: Some(false),
+            file: Some(file.clone()),
+            ..Default::default()
+        });
+        self.files.insert(id, file);
+    }
+
+    fn set_content(&mut self, id: &str, content: Vec<u8>) {
+        if let Some(file) = self.files.get_mut(id) {
+            file.size = Some(content.len() as i64);
+            file.md5_checksum = Some(md5_hex(&content));
+            file.version = Some(file.version.unwrap_or(0) + 1);
+        }
+        self.record_revision(id, &content);
+        self.contents.insert(id.to_string(), content);
+    }
+
+    /// Cada contenido nuevo es una revisión, como en Drive
+    fn record_revision(&mut self, id: &str, content: &[u8]) {
+        self.next_revision += 1;
+        let revision = Revision {
+            id: Some(format!("mock-rev-{}", self.next_revision)),
+            size: Some(content.len() as i64),
+            md5_checksum: Some(md5_hex(content)),
+            modified_time: Some(Utc::now()),
+            keep_forever: Some(false),
+            ..Default::default()
+        };
+        self.revisions.entry(id.to_string()).or_default().push((revision, content.to_vec()));
+    }
+
+    fn touch(&mut self, id: &str) {
+        if let Some(file) = self.files.get(id).cloned() {
+            self.put(file);
+        }
+    }
+
+    fn not_found(method: &str, id: &str) -> anyhow::Error {
+        DriveError::NotFound(format!("404 notFound: {} ({})", id, method)).into()
+    }
+}
+
+impl Default for MockDriveClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockDriveClient {
+    pub fn new() -> Self {
+        Self { state: Mutex::new(MockState::default()), page_size: 1000, client_id: None }
+    }
+
+    /// Tamaño de página de `fetch_files_page` y `list_changes` (para ejercitar la paginación)
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Marca los archivos y carpetas creados con `appProperties` de esta instalación
+    pub fn with_client_id(mut self, client_id: &str) -> Self {
+        self.client_id = Some(client_id.to_string());
+        self
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("estado del mock envenenado")
+    }
+
+    /// Crea un archivo remoto (como si lo hubiera subido otro cliente)
+    pub fn add_file(&self, id: &str, name: &str, parent_id: &str, content: &[u8]) -> File {
+        let mut state = self.state();
+        let file = File {
+            id: Some(id.to_string()),
+            name: Some(name.to_string()),
+            parents: Some(vec![parent_id.to_string()]),
+            mime_type: Some(mime_guess::from_path(name).first_or_octet_stream().to_string()),
+            size: Some(content.len() as i64),
+            md5_checksum: Some(md5_hex(content)),
+            modified_time: Some(Utc::now()),
+            created_time: Some(Utc::now()),
+            version: Some(1),
+            owned_by_me: Some(true),
+            trashed: Some(false),
+            ..Default::default()
+        };
+        state.contents.insert(id.to_string(), content.to_vec());
+        state.record_revision(id, content);
+        state.put(file.clone());
+        file
+    }
+
+    /// Crea una carpeta remota
+    pub fn add_folder(&self, id: &str, name: &str, parent_id: &str) -> File {
+        let mut state = self.state();
+        let file = File {
+            id: Some(id.to_string()),
+            name: Some(name.to_string()),
+            parents: Some(vec![parent_id.to_string()]),
+            mime_type: Some(FOLDER_MIME.to_string()),
+            modified_time: Some(Utc::now()),
+            created_time: Some(Utc::now()),
+            version: Some(1),
+            owned_by_me: Some(true),
+            trashed: Some(false),
+            ..Default::default()
+        };
+        state.put(file.clone());
+        file
+    }
+
+    /// Carpeta de un equipo en la sección "Ordenadores" (sin padres, fuera de "Mi unidad", no se puede mover)
+    pub fn add_computer(&self, id: &str, name: &str) -> File {
+        let mut state = self.state();
+        let file = File {
+            id: Some(id.to_string()),
+            name: Some(name.to_string()),
+            mime_type: Some(FOLDER_MIME.to_string()),
+            modified_time: Some(Utc::now()),
+            created_time: Some(Utc::now()),
+            version: Some(1),
+            owned_by_me: Some(true),
+            trashed: Some(false),
+            capabilities: Some(google_drive3::api::FileCapabilities {
+                can_move_item_within_drive: Some(false),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        state.put(file.clone());
+        file
+    }
+
+    /// Otro cliente modificó el contenido (nuevo md5 y versión)
+    pub fn modify_remote(&self, id: &str, content: &[u8]) {
+        let mut state = self.state();
+        state.set_content(id, content.to_vec());
+        if let Some(file) = state.files.get_mut(id) {
+            file.modified_time = Some(Utc::now());
+        }
+        state.touch(id);
+    }
+
+    /// El archivo pasa a ser de otro usuario, compartido solo para lectura
+    /// (sin `canEdit` ni `canTrash`)
+    pub fn share_read_only(&self, id: &str, owner_email: &str) {
+        let mut state = self.state();
+        if let Some(file) = state.files.get_mut(id) {
+            file.owned_by_me = Some(false);
+            file.shared = Some(true);
+            file.owners = Some(vec![google_drive3::api::User {
+                email_address: Some(owner_email.to_string()),
+                ..Default::default()
+            }]);
+            file.capabilities = Some(google_drive3::api::FileCapabilities {
+                can_edit: Some(false),
+                can_trash: Some(false),
+                ..Default::default()
+            });
+        }
+        state.touch(id);
+    }
+
+    /// Otro cliente cambió el color de una carpeta (`folderColorRgb`)
+    pub fn set_folder_color(&self, id: &str, color: &str) {
+        let mut state = self.state();
+        if let Some(file) = state.files.get_mut(id) {
+            file.folder_color_rgb = Some(color.to_string());
+        }
+        state.touch(id);
+    }
+
+    /// Otro cliente movió el archivo a otra carpeta
+    pub fn move_remote(&self, id: &str, new_parent_id: &str) {
+        let mut state = self.state();
+        if let Some(file) = state.files.get_mut(id) {
+            file.parents = Some(vec![new_parent_id.to_string()]);
+        }
+        state.touch(id);
+    }
+
+    /// Otro cliente movió el archivo a la papelera
+    pub fn trash_remote(&self, id: &str) {
+        let mut state = self.state();
+        if let Some(file) = state.files.get_mut(id) {
+            file.trashed = Some(true);
+        }
+        state.touch(id);
+    }
+
+    /// Eliminación permanente (papelera vaciada)
+    pub fn remove_remote(&self, id: &str) {
+        let mut state = self.state();
+        state.files.remove(id);
+        state.contents.remove(id);
+        state.changes.push(Change {
+            file_id: Some(id.to_string()),
+            removed: Some(true),
+            ..Default::default()
+        });
+    }
+
+    /// Drive generó una miniatura del archivo: le asigna un `thumbnailLink`
+    pub fn set_thumbnail(&self, id: &str, image: &[u8]) {
+        let mut state = self.state();
+        let base = format!("https://thumbnails.mock/{}/{}", id, state.next_id);
+        state.next_id += 1;
+        state.thumbnails.insert(base.clone(), image.to_vec());
+        if let Some(file) = state.files.get_mut(id) {
+            file.thumbnail_link = Some(format!("{}=s220", base));
+        }
+        state.touch(id);
+    }
+
+    /// Contenido remoto actual de un archivo
+    pub fn content(&self, id: &str) -> Option<Vec<u8>> {
+        self.state().contents.get(id).cloned()
+    }
+
+    /// IDs de las revisiones de un archivo, de la más antigua a la actual
+    pub fn revision_ids(&self, id: &str) -> Vec<String> {
+        self.state()
+            .revisions
+            .get(id)
+            .map(|revisions| revisions.iter().filter_map(|(r, _)| r.id.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Metadatos remotos actuales de un archivo
+    pub fn file(&self, id: &str) -> Option<File> {
+        self.state().files.get(id).cloned()
+    }
+
+    /// Primer archivo remoto cuyo nombre empieza por `prefix` (las copias de
+    /// conflicto llevan fecha, así que no se pueden buscar por nombre exacto)
+    pub fn find_by_name_prefix(&self, prefix: &str) -> Option<File> {
+        self.state()
+            .files
+            .values()
+            .find(|f| f.name.as_deref().is_some_and(|n| n.starts_with(prefix)))
+            .cloned()
+    }
+
+    /// Veces que se llamó a un método de `DriveApi`
+    pub fn calls(&self, method: &str) -> usize {
+        self.state().calls.get(method).copied().unwrap_or(0)
+    }
+
+    /// La próxima llamada a `method` falla con `message`
+    pub fn fail_next(&self, method: &'static str, message: &str) {
+        self.fail_after(method, 0, message);
+    }
+
+    /// Tras `ok_calls` llamadas exitosas, la siguiente a `method` falla con `message`
+    pub fn fail_after(&self, method: &'static str, ok_calls: usize, message: &str) {
+        self.state().failures.insert(method, (ok_calls, anyhow::anyhow!("{}", message)));
+    }
+
+    /// Las llamadas a `method` dejan de responder, como una petición colgada
+    pub fn stall(&self, method: &'static str) {
+        self.state().stalled.insert(method);
+    }
+
+    /// La próxima llamada a `method` falla con un error tipado de Drive
+    pub fn fail_next_with(&self, method: &'static str, error: DriveError) {
+        self.state().failures.insert(method, (0, error.into()));
+    }
+
+    fn live_files(&self, filter: impl Fn(&File) -> bool) -> Vec<File> {
+        self.state()
+            .files
+            .values()
+            .filter(|f| f.trashed != Some(true) && filter(f))
+            .cloned()
+            .collect()
+    }
+}
+
+fn md5_hex(content: &[u8]) -> String {
+    format!("{:x}", Md5::digest(content))
+}
+
+#[async_trait::async_trait]
+impl DriveApi for MockDriveClient {
+    async fn get_root_file_id(&self) -> Result<String> {
+        self.state().begin("get_root_file_id")?;
+        Ok(MOCK_ROOT_ID.to_string())
+    }
+
+    async fn download_chunk(&self, file_id: &str, offset: u64, size: u32) -> Result<Vec<u8>> {
+        let stalled = {
+            let mut state = self.state();
+            state.begin("download_chunk")?;
+            state.stalled.contains("download_chunk")
+        };
+        if stalled {
+            std::future::pending::<()>().await;
+        }
+        let state = self.state();
+        let content = state.contents.get(file_id).ok_or_else(|| MockState::not_found("download_chunk", file_id))?;
+        let start = (offset as usize).min(content.len());
+        let end = (start + size as usize).min(content.len());
+        Ok(content[start..end].to_vec())
+    }
+
+    async fn list_root_children(&self, root_id: &str) -> Result<Vec<File>> {
+        self.state().begin("list_root_children")?;
+        Ok(self.live_files(|f| {
+            f.parents.as_ref().is_some_and(|p| p.iter().any(|id| id == root_id || id == "root"))
+        }))
+    }
+
+    async fn list_all_files(&self, excluded_parents: &[String]) -> Result<Vec<File>> {
+        self.state().begin("list_all_files")?;
+        let excluded = &excluded_parents[..excluded_parents.len().min(super::client::MAX_EXCLUDED_PARENTS_IN_QUERY)];
+        Ok(self.live_files(|f| {
+            !f.parents.as_ref().is_some_and(|parents| parents.iter().any(|p| excluded.contains(p)))
+        }))
+    }
+
+    async fn fetch_files_page(&self, page_token: Option<&str>, excluded_parents: &[String]) -> Result<(Vec<File>, Option<String>)> {
+        self.state().begin("fetch_files_page")?;
+        // Como `q = ... and not 'id' in parents`: solo los hijos directos
+        let excluded = &excluded_parents[..excluded_parents.len().min(super::client::MAX_EXCLUDED_PARENTS_IN_QUERY)];
+        let files = self.live_files(|f| {
+            !f.parents.as_ref().is_some_and(|parents| parents.iter().any(|p| excluded.contains(p)))
+        });
+        let start: usize = page_token.and_then(|t| t.parse().ok()).unwrap_or(0);
+        let end = (start + self.page_size).min(files.len());
+        let next = (end < files.len()).then(|| end.to_string());
+        Ok((files[start.min(end)..end].to_vec(), next))
+    }
+
+    async fn get_start_page_token(&self) -> Result<String> {
+        let mut state = self.state();
+        state.begin("get_start_page_token")?;
+        Ok(state.changes.len().to_string())
+    }
+
+    async fn list_changes(&self, page_token: &str) -> Result<(Vec<Change>, Option<String>, bool)> {
+        let mut state = self.state();
+        state.begin("list_changes")?;
+        let start: usize = page_token.parse().unwrap_or(0).min(state.changes.len());
+        let end = (start + self.page_size).min(state.changes.len());
+        // Como Drive: nextPageToken mientras queden páginas, newStartPageToken en la última
+        let has_more = end < state.changes.len();
+        Ok((state.changes[start..end].to_vec(), Some(end.to_string()), has_more))
+    }
+
+    async fn get_file_md5(&self, file_id: &str) -> Result<Option<String>> {
+        let mut state = self.state();
+        state.begin("get_file_md5")?;
+        let file = state.files.get(file_id).ok_or_else(|| MockState::not_found("get_file_md5", file_id))?;
+        Ok(file.md5_checksum.clone())
+    }
+
+    async fn generate_ids(&self, count: u32) -> Result<Vec<String>> {
+        let mut state = self.state();
+        state.begin("generate_ids")?;
+        Ok((0..count).map(|_| state.new_id()).collect())
+    }
+
+    async fn upload_file(
+        &self,
+        file_path: &std::path::Path,
+        name: &str,
+        mime_type: Option<&str>,
+        parent_id: &str,
+        file_id: Option<&str>,
+        modified_time: Option<DateTime<Utc>>,
+        progress_cb: Option<ProgressCallback>,
+    ) -> Result<String> {
+        self.state().begin("upload_file")?;
+        let content = crate::utils::cache_crypto::read_file(file_path).await?;
+        if let Some(cb) = progress_cb {
+            cb(content.len() as u64, content.len() as u64);
+        }
+
+        let mut state = self.state();
+        let id = state.assign_id(file_id)?;
+        let file = File {
+            id: Some(id.clone()),
+            name: Some(name.to_string()),
+            parents: Some(vec![parent_id.to_string()]),
+            mime_type: Some(mime_type.unwrap_or("application/octet-stream").to_string()),
+            modified_time: modified_time.or_else(|| Some(Utc::now())),
+            created_time: Some(Utc::now()),
+            app_properties: self.client_id.as_deref().map(super::app_properties::creation_properties),
+            owned_by_me: Some(true),
+            trashed: Some(false),
+            ..Default::default()
+        };
+        state.files.insert(id.clone(), file);
+        state.set_content(&id, content);
+        state.touch(&id);
+        Ok(id)
+    }
+
+    async fn create_folder(&self, name: &str, parent_id: &str, file_id: Option<&str>) -> Result<String> {
+        let mut state = self.state();
+        state.begin("create_folder")?;
+        let id = state.assign_id(file_id)?;
+        state.put(File {
+            id: Some(id.clone()),
+            name: Some(name.to_string()),
+            parents: Some(vec![parent_id.to_string()]),
+            mime_type: Some(FOLDER_MIME.to_string()),
+            modified_time: Some(Utc::now()),
+            created_time: Some(Utc::now()),
+            app_properties: self.client_id.as_deref().map(super::app_properties::creation_properties),
+            owned_by_me: Some(true),
+            trashed: Some(false),
+            ..Default::default()
+        });
+        Ok(id)
+    }
+
+    async fn create_workspace_file(&self, name: &str, mime_type: &str, parent_id: &str, file_id: Option<&str>) -> Result<String> {
+        let mut state = self.state();
+        state.begin("create_workspace_file")?;
+        let id = state.assign_id(file_id)?;
+        state.put(File {
+            id: Some(id.clone()),
+            name: Some(name.to_string()),
+            parents: Some(vec![parent_id.to_string()]),
+            mime_type: Some(mime_type.to_string()),
+            modified_time: Some(Utc::now()),
+            created_time: Some(Utc::now()),
+            app_properties: self.client_id.as_deref().map(super::app_properties::creation_properties),
+            owned_by_me: Some(true),
+            trashed: Some(false),
+            ..Default::default()
+        });
+        Ok(id)
+    }
+
+    async fn update_file_content(
+        &self,
+        file_id: &str,
+        file_path: &std::path::Path,
+        modified_time: Option<DateTime<Utc>>,
+        progress_cb: Option<ProgressCallback>,
+    ) -> Result<()> {
+        self.state().begin("update_file_content")?;
+        let content = crate::utils::cache_crypto::read_file(file_path).await?;
+        if let Some(cb) = progress_cb {
+            cb(content.len() as u64, content.len() as u64);
+        }
+
+        let mut state = self.state();
+        if !state.files.contains_key(file_id) {
+            return Err(MockState::not_found("update_file_content", file_id));
+        }
+        state.set_content(file_id, content);
+        if let Some(file) = state.files.get_mut(file_id) {
+            file.modified_time = modified_time.or_else(|| Some(Utc::now()));
+        }
+        state.touch(file_id);
+        Ok(())
+    }
+
+    async fn trash_file(&self, file_id: &str) -> Result<(), DriveError> {
+        let mut state = self.state();
+        state.begin("trash_file").map_err(|e| e.downcast::<DriveError>().unwrap_or_else(|e| DriveError::ApiError(e.to_string())))?;
+        match state.files.get_mut(file_id) {
+            Some(file) => file.trashed = Some(true),
+            None => return Err(DriveError::NotFound(format!("Archivo no existe en Drive: {}", file_id))),
+        }
+        state.touch(file_id);
+        Ok(())
+    }
+
+    async fn delete_file(&self, file_id: &str) -> Result<(), DriveError> {
+        let mut state = self.state();
+        state.begin("delete_file").map_err(|e| e.downcast::<DriveError>().unwrap_or_else(|e| DriveError::ApiError(e.to_string())))?;
+        if state.files.remove(file_id).is_none() {
+            return Err(DriveError::NotFound(format!("Archivo no existe en Drive: {}", file_id)));
+        }
+        state.contents.remove(file_id);
+        state.changes.push(Change {
+            file_id: Some(file_id.to_string()),
+            removed: Some(true),
+            ..Default::default()
+        });
+        Ok(())
+    }
+
+    async fn get_file_metadata(&self, file_id: &str) -> Result<File> {
+        let mut state = self.state();
+        state.begin("get_file_metadata")?;
+        state.files.get(file_id).cloned().ok_or_else(|| MockState::not_found("get_file_metadata", file_id))
+    }
+
+    async fn create_share_link(&self, file_id: &str, _role: &str) -> Result<String> {
+        self.state().begin("create_share_link")?;
+        self.get_web_view_link(file_id).await
+    }
+
+    async fn get_web_view_link(&self, file_id: &str) -> Result<String> {
+        let mut state = self.state();
+        state.begin("get_web_view_link")?;
+        if !state.files.contains_key(file_id) {
+            return Err(MockState::not_found("get_web_view_link", file_id));
+        }
+        Ok(format!("https://drive.google.com/file/d/{}/view", file_id))
+    }
+
+    async fn get_thumbnail_link(&self, file_id: &str) -> Result<Option<String>> {
+        let mut state = self.state();
+        state.begin("get_thumbnail_link")?;
+        let file = state.files.get(file_id).ok_or_else(|| MockState::not_found("get_thumbnail_link", file_id))?;
+        Ok(file.thumbnail_link.clone())
+    }
+
+    async fn download_thumbnail(&self, link: &str) -> Result<Vec<u8>> {
+        let mut state = self.state();
+        state.begin("download_thumbnail")?;
+        let base = link.rsplit_once("=s").map_or(link, |(base, _)| base);
+        state.thumbnails.get(base).cloned().ok_or_else(|| MockState::not_found("download_thumbnail", link))
+    }
+
+    async fn list_revisions(&self, file_id: &str) -> Result<Vec<Revision>> {
+        let mut state = self.state();
+        state.begin("list_revisions")?;
+        if !state.files.contains_key(file_id) {
+            return Err(MockState::not_found("list_revisions", file_id));
+        }
+        Ok(state
+            .revisions
+            .get(file_id)
+            .map(|revisions| revisions.iter().map(|(r, _)| r.clone()).collect())
+            .unwrap_or_default())
+    }
+
+    async fn download_revision(&self, file_id: &str, revision_id: &str, dest: &std::path::Path) -> Result<u64> {
+        let content = {
+            let mut state = self.state();
+            state.begin("download_revision")?;
+            state
+                .revisions
+                .get(file_id)
+                .and_then(|revisions| revisions.iter().find(|(r, _)| r.id.as_deref() == Some(revision_id)))
+                .map(|(_, content)| content.clone())
+                .ok_or_else(|| MockState::not_found("download_revision", revision_id))?
+        };
+        tokio::fs::write(dest, &content).await?;
+        Ok(content.len() as u64)
+    }
+
+    async fn set_starred(&self, file_id: &str, starred: bool) -> Result<()> {
+        let mut state = self.state();
+        state.begin("set_starred")?;
+        let file = state.files.get_mut(file_id).ok_or_else(|| MockState::not_found("set_starred", file_id))?;
+        file.starred = Some(starred);
+        state.touch(file_id);
+        Ok(())
+    }
+
+    async fn set_description(&self, file_id: &str, description: Option<&str>) -> Result<()> {
+        let mut state = self.state();
+        state.begin("set_description")?;
+        let file = state.files.get_mut(file_id).ok_or_else(|| MockState::not_found("set_description", file_id))?;
+        file.description = description.map(str::to_string);
+        state.touch(file_id);
+        Ok(())
+    }
+
+    async fn set_app_property(&self, file_id: &str, key: &str, value: Option<&str>) -> Result<()> {
+        let mut state = self.state();
+        state.begin("set_app_property")?;
+        let file = state.files.get_mut(file_id).ok_or_else(|| MockState::not_found("set_app_property", file_id))?;
+        let props = file.app_properties.get_or_insert_with(Default::default);
+        match value {
+            Some(value) => {
+                props.insert(key.to_string(), value.to_string());
+            }
+            None => {
+                props.remove(key);
+            }
+        }
+        state.touch(file_id);
+        Ok(())
+    }
+
+    async fn update_file_metadata(
+        &self,
+        file_id: &str,
+        new_name: Option<&str>,
+        add_parent: Option<&str>,
+        remove_parent: Option<&str>,
+        new_mtime: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let mut state = self.state();
+        state.begin("update_file_metadata")?;
+        let file = state.files.get_mut(file_id).ok_or_else(|| MockState::not_found("update_file_metadata", file_id))?;
+
+        if let Some(name) = new_name {
+            file.name = Some(name.to_string());
+        }
+        let parents = file.parents.get_or_insert_with(Vec::new);
+        if let Some(parent) = remove_parent {
+            parents.retain(|p| p != parent);
+        }
+        if let Some(parent) = add_parent {
+            parents.push(parent.to_string());
+        }
+        if new_mtime.is_some() {
+            file.modified_time = new_mtime;
+        }
+        state.touch(file_id);
+        Ok(())
+    }
+}
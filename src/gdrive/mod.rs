@@ -1,4 +1,10 @@
+pub mod api;
+pub mod app_properties;
 pub mod client;
 pub mod error;
+pub mod id_pool;
+#[cfg(test)]
+pub mod mock;
 
+pub use api::DriveApi;
 pub use error::DriveError;
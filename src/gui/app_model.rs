@@ -6,6 +6,48 @@ use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 
 use super::history::{ActionHistory, ActionType, ActionEntry, ActiveTransfer, TransferOp};
 use super::tray::TrayIcon;
+use crate::config::ColorScheme;
+use crate::i18n::t;
+use crate::utils::supervisor::{BackendState, BackendSupervisor};
+
+/// Tamaño máximo del log que se carga en el visor
+const LOG_VIEWER_MAX_BYTES: u64 = 256 * 1024;
+
+/// Entradas del punto de montaje que se nombran en el aviso (el resto se cuenta)
+const MOUNT_POINT_LISTED_ENTRIES: usize = 5;
+
+/// Opciones de "Apariencia" (con el id de su texto), en el orden del desplegable
+const COLOR_SCHEMES: [(ColorScheme, &str); 3] = [
+    (ColorScheme::System, "appearance-system"),
+    (ColorScheme::Light, "appearance-light"),
+    (ColorScheme::Dark, "appearance-dark"),
+];
+
+/// Aplica el esquema de color a toda la aplicación
+fn apply_color_scheme(scheme: ColorScheme) {
+    adw::StyleManager::default().set_color_scheme(match scheme {
+        ColorScheme::System => adw::ColorScheme::Default,
+        ColorScheme::Light => adw::ColorScheme::ForceLight,
+        ColorScheme::Dark => adw::ColorScheme::ForceDark,
+    });
+}
+
+/// Subtítulo de "Sincronizar ahora": cuándo se sincroniza solo
+fn sync_now_subtitle(schedule: &crate::config::SyncSchedule) -> String {
+    use crate::config::SyncSchedule;
+    match schedule {
+        SyncSchedule::Always => t!("gui-sync-now-subtitle"),
+        SyncSchedule::Window { start, end } => t!("gui-sync-now-window", start = start.as_str(), end = end.as_str()),
+        SyncSchedule::Manual => t!("gui-sync-now-manual"),
+    }
+}
+
+/// Opciones de arranque de la ventana principal
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AppInit {
+    /// `--start-hidden`: arrancar solo en la bandeja, sin presentar la ventana
+    pub start_hidden: bool,
+}
 
 pub struct AppModel {
     pub status_message: String,
@@ -13,8 +55,23 @@ pub struct AppModel {
     pub mirror_path: Option<std::path::PathBuf>,
     pub fuse_mount_path: Option<std::path::PathBuf>,
     pub sync_paused: Arc<AtomicBool>,
+    /// Esquema de color elegido en "Apariencia" (`Config.color_scheme`)
+    pub color_scheme: ColorScheme,
+    /// Inicio automático al iniciar sesión (`utils::autostart`)
+    pub autostart: bool,
+    /// Mostrar la sección "Ordenadores" de Drive (`Config.computers_mode`)
+    pub show_computers: bool,
+    /// Franja de sincronización automática (`Config.sync_schedule`), para el texto de "Sincronizar ahora"
+    pub sync_schedule: crate::config::SyncSchedule,
     pub history: ActionHistory,
     pub db: Option<Arc<crate::db::MetadataRepository>>,
+    pub drive_client: Option<Arc<dyn crate::gdrive::DriveApi>>,
+    /// Almacenamiento de Drive agotado, compartido con el uploader y FUSE
+    pub storage_quota: crate::utils::quota::StorageQuota,
+    /// Contexto del servidor IPC: datos y acciones del diálogo de propiedades
+    pub ipc_context: Option<crate::ipc::server::IpcContext>,
+    /// Consulta y liberación de la caché de contenido (panel "Caché")
+    pub cache_manager: Option<crate::fuse::cache_manager::CacheManager>,
     pub login_url: Option<String>,
     // Actividad reciente
     pub activity_entries: Vec<ActionEntry>,
@@ -23,13 +80,42 @@ pub struct AppModel {
     pub sync_applied: usize,
     pub pending_uploads: usize,
     pub scanning_total: usize,
+    pub scanning_expected: usize,
+    pub scanning_pages: usize,
+    /// Estado del backend según el supervisor (banner de reconexión/reinicio)
+    pub backend_state: BackendState,
+    /// Adelanta el reintento del supervisor ("Reconectar")
+    pub backend_reconnect: Option<std::sync::mpsc::Sender<()>>,
+    /// Últimos problemas de configuración mostrados (evita repetir el diálogo en cada reintento)
+    pub config_problems: Vec<crate::config::ConfigProblem>,
+    /// Punto de montaje no vacío ya mostrado (evita repetir el diálogo en cada reintento)
+    pub mount_point_problem: Option<std::path::PathBuf>,
+    /// Motivo de pausa automática de las transferencias (`gui::network_pause_text`)
+    pub network_pause: Option<String>,
+    /// Motivo por el que se frenan las escrituras locales (`gui::backpressure_text`)
+    pub backpressure: Option<String>,
+    /// Drive rechazó una subida por almacenamiento lleno (`storage_quota`)
+    pub storage_full: bool,
+    pub upload_problems: Vec<crate::db::UploadProblem>,
+    /// Conflictos de sincronización sin resolver
+    pub conflicts: Vec<crate::db::SyncConflict>,
     // Directorios de sincronización
     pub local_sync_dirs: Vec<crate::db::repository::LocalSyncDir>,
     // Referencias a widgets dinámicos
     pub uploads_listbox: Option<gtk::ListBox>,
     pub downloads_listbox: Option<gtk::ListBox>,
     pub history_listbox: Option<gtk::ListBox>,
+    pub problems_listbox: Option<gtk::ListBox>,
+    pub conflicts_listbox: Option<gtk::ListBox>,
     pub sync_dirs_listbox: Option<gtk::ListBox>,
+    pub search_results_listbox: Option<gtk::ListBox>,
+    pub cache_listbox: Option<gtk::ListBox>,
+    pub trash_listbox: Option<gtk::ListBox>,
+    /// Elementos de la papelera local (eliminados, aún no definitivamente)
+    pub trash_items: Vec<crate::ipc::TrashItem>,
+    pub log_textview: Option<gtk::TextView>,
+    // Búsqueda
+    pub search_query: String,
     // Navegación
     pub current_view: ViewMode,
     // Shutdown
@@ -40,13 +126,31 @@ pub struct AppModel {
 pub enum ViewMode {
     Main,
     Activity,
+    Logs,
+    Conflicts,
 }
 
 impl AppModel {
     fn sync_hint_text(&self) -> String {
         // Escaneo en curso tiene prioridad visual
         if self.scanning_total > 0 {
-            return format!("Escaneados {} archivos", self.scanning_total);
+            if self.scanning_expected >= self.scanning_total {
+                return t!("gui-scanned-of", count = self.scanning_total, expected = self.scanning_expected, pages = self.scanning_pages);
+            }
+            return t!("gui-scanned", count = self.scanning_total, pages = self.scanning_pages);
+        }
+
+        // Escrituras frenadas: lo que se está copiando al montaje espera o falla
+        if let Some(reason) = self.backpressure.as_deref() {
+            return t!("backpressure-writes-held", reason = reason);
+        }
+
+        // Sin conexión o en conexión medida los cambios locales esperan en cola
+        if let Some(reason) = self.network_pause.as_deref() {
+            if self.pending_uploads > 0 {
+                return t!("gui-changes-queued", reason = reason, count = self.pending_uploads);
+            }
+            return t!("network-uploads-paused", reason = reason);
         }
 
         let has_pending_downloads = self.sync_detected != self.sync_applied;
@@ -55,17 +159,53 @@ impl AppModel {
 
         if has_active_real_transfers || has_pending_downloads || has_pending_uploads {
             if has_pending_downloads {
-                format!("{}/{} Cambios aplicados", self.sync_applied, self.sync_detected)
+                t!("gui-changes-applied", applied = self.sync_applied, detected = self.sync_detected)
             } else if has_pending_uploads {
-                format!("{} Cambios pendientes", self.pending_uploads)
+                t!("gui-changes-pending", count = self.pending_uploads)
             } else {
-                "Sincronizando...".to_string()
+                t!("gui-syncing")
             }
         } else {
-            "Sin Novedad, mi general".to_string()
+            t!("gui-all-synced")
+        }
+    }
+
+    /// Texto del banner del supervisor (`None` oculta el banner)
+    fn backend_banner_title(&self) -> Option<String> {
+        match &self.backend_state {
+            BackendState::Starting { attempt } if *attempt > 1 => {
+                Some(t!("gui-backend-reconnecting", attempt = *attempt))
+            }
+            BackendState::Retrying { retry_in, error, .. } => Some(t!(
+                "gui-backend-retrying",
+                error = error.as_str(),
+                seconds = retry_in.as_secs()
+            )),
+            BackendState::Stopped { error, .. } => Some(t!("gui-backend-needs-action", error = error.as_str())),
+            BackendState::Crashed(message) => Some(t!("gui-backend-crashed", error = message.as_str())),
+            _ => None,
         }
     }
 
+    /// Botón del banner: reintentar ya o, tras un panic, reiniciar la aplicación
+    fn backend_banner_button(&self) -> Option<String> {
+        match self.backend_state {
+            BackendState::Retrying { .. } | BackendState::Stopped { .. } => Some(t!("gui-reconnect")),
+            BackendState::Crashed(_) => Some(t!("gui-restart")),
+            _ => None,
+        }
+    }
+
+    /// En modo solo lectura avisa y devuelve `true`: la acción no debe
+    /// modificar nada en Drive (`IpcRequest::is_mutating`)
+    fn refuse_if_read_only(&mut self) -> bool {
+        let read_only = self.ipc_context.as_ref().is_some_and(|ctx| ctx.is_read_only());
+        if read_only {
+            self.status_message = t!("gui-read-only-refused");
+        }
+        read_only
+    }
+
     /// Reconstruye el contenido del listbox de transfers activos
     fn rebuild_transfers_box(transfers_box: &gtk::ListBox, transfers: &[&ActiveTransfer]) {
         // Limpiar
@@ -120,7 +260,7 @@ impl AppModel {
         }
 
         if entries.is_empty() {
-            let label = gtk::Label::new(Some("Sin actividad reciente"));
+            let label = gtk::Label::new(Some(&t!("gui-no-recent-activity")));
             label.set_css_classes(&["dim-label"]);
             label.set_margin_top(8);
             label.set_margin_bottom(8);
@@ -140,6 +280,510 @@ impl AppModel {
         }
     }
 
+    /// Reconstruye el listbox de archivos que no se pudieron subir
+    fn rebuild_problems_box(box_widget: &gtk::ListBox, problems: &[crate::db::UploadProblem], sender: &ComponentSender<Self>) {
+        while let Some(child) = box_widget.first_child() {
+            box_widget.remove(&child);
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        for problem in problems {
+            let retry_hint = match problem.next_retry_at {
+                _ if problem.permanent => t!("gui-no-retry"),
+                Some(at) if at > now => t!("gui-retry-in", attempt = problem.retry_count + 1, minutes = (at - now + 59) / 60),
+                _ => t!("gui-retry-pending", attempt = problem.retry_count + 1),
+            };
+
+            let row = adw::ActionRow::new();
+            row.set_title(&gtk::glib::markup_escape_text(&problem.path));
+            row.set_subtitle(&gtk::glib::markup_escape_text(&format!("{} · {}", retry_hint, problem.error)));
+            row.set_subtitle_lines(2);
+            row.add_prefix(&gtk::Image::from_icon_name(if problem.permanent { "dialog-error-symbolic" } else { "dialog-warning-symbolic" }));
+
+            let btn_retry = gtk::Button::builder()
+                .icon_name("view-refresh-symbolic")
+                .tooltip_text(t!("gui-retry-now"))
+                .css_classes(["flat"])
+                .valign(gtk::Align::Center)
+                .build();
+            let inode = problem.inode;
+            let sender_clone = sender.clone();
+            btn_retry.connect_clicked(move |_| {
+                sender_clone.input(AppMsg::RetryUpload(inode));
+            });
+            row.add_suffix(&btn_retry);
+
+            box_widget.append(&row);
+        }
+    }
+
+    /// Reconstruye el listbox del centro de conflictos
+    fn rebuild_conflicts_box(box_widget: &gtk::ListBox, conflicts: &[crate::db::SyncConflict], sender: &ComponentSender<Self>) {
+        use crate::sync::conflicts::ConflictResolution;
+
+        while let Some(child) = box_widget.first_child() {
+            box_widget.remove(&child);
+        }
+
+        let format_time = |secs: i64| {
+            gtk::glib::DateTime::from_unix_local(secs).ok()
+                .and_then(|dt| dt.format("%d/%m/%Y %H:%M").ok())
+                .map(|dt| dt.to_string())
+                .unwrap_or_else(|| "?".to_string())
+        };
+
+        for conflict in conflicts {
+            let remote = conflict.remote_mtime.map(format_time).unwrap_or_else(|| t!("gui-unknown-date"));
+            let row = adw::ActionRow::new();
+            row.set_title(&gtk::glib::markup_escape_text(&conflict.path));
+            row.set_subtitle(&gtk::glib::markup_escape_text(&format!(
+                "{}\n{}",
+                t!("gui-conflict-copy", name = conflict.conflict_name.as_str()),
+                t!("gui-conflict-edited", local = format_time(conflict.local_mtime), remote = remote),
+            )));
+            row.set_subtitle_lines(3);
+            row.add_prefix(&gtk::Image::from_icon_name("dialog-warning-symbolic"));
+
+            let btn_diff = gtk::Button::builder()
+                .icon_name("view-dual-symbolic")
+                .tooltip_text(t!("gui-show-diff"))
+                .css_classes(["flat"])
+                .valign(gtk::Align::Center)
+                .build();
+            let id = conflict.id;
+            let sender_clone = sender.clone();
+            btn_diff.connect_clicked(move |_| {
+                sender_clone.input(AppMsg::ShowConflictDiff(id));
+            });
+            row.add_suffix(&btn_diff);
+
+            for (icon, tooltip, resolution) in [
+                ("computer-symbolic", t!("gui-keep-local"), ConflictResolution::KeepLocal),
+                ("folder-remote-symbolic", t!("gui-keep-remote"), ConflictResolution::KeepRemote),
+                ("window-close-symbolic", t!("gui-dismiss-conflict"), ConflictResolution::Dismiss),
+            ] {
+                let button = gtk::Button::builder()
+                    .icon_name(icon)
+                    .tooltip_text(tooltip)
+                    .css_classes(["flat"])
+                    .valign(gtk::Align::Center)
+                    .build();
+                let sender_clone = sender.clone();
+                button.connect_clicked(move |_| {
+                    sender_clone.input(AppMsg::ResolveConflict { id, resolution });
+                });
+                row.add_suffix(&button);
+            }
+
+            box_widget.append(&row);
+        }
+    }
+
+    /// Diálogo con las diferencias entre la versión de Drive y la copia local
+    fn present_conflict_diff(root: &adw::ApplicationWindow, title: &str, text: &str) {
+        let textview = gtk::TextView::builder()
+            .editable(false)
+            .cursor_visible(false)
+            .monospace(true)
+            .top_margin(8)
+            .bottom_margin(8)
+            .left_margin(8)
+            .right_margin(8)
+            .build();
+        let buffer = textview.buffer();
+        let removed = buffer.create_tag(Some("removed"), &[("foreground", &"#e01b24")]);
+        let added = buffer.create_tag(Some("added"), &[("foreground", &"#2ec27e")]);
+        for line in text.split_inclusive('\n') {
+            let tag = match line.chars().next() {
+                Some('-') => removed.as_ref(),
+                Some('+') => added.as_ref(),
+                _ => None,
+            };
+            let mut end = buffer.end_iter();
+            match tag {
+                Some(tag) => buffer.insert_with_tags(&mut end, line, &[tag]),
+                None => buffer.insert(&mut end, line),
+            }
+        }
+
+        let scrolled = gtk::ScrolledWindow::builder().vexpand(true).child(&textview).build();
+        let header = adw::HeaderBar::new();
+        header.set_title_widget(Some(&adw::WindowTitle::new(title, "- Drive  + local")));
+        let toolbar = adw::ToolbarView::new();
+        toolbar.add_top_bar(&header);
+        toolbar.set_content(Some(&scrolled));
+
+        let dialog = adw::Dialog::builder().title(t!("gui-differences")).content_width(640).content_height(520).build();
+        dialog.set_child(Some(&toolbar));
+        dialog.present(Some(root));
+    }
+
+    /// Reconstruye el listbox de resultados de búsqueda
+    fn rebuild_search_results_box(box_widget: &gtk::ListBox, query: &str, results: &[crate::ipc::SearchResult], mirror: Option<&std::path::Path>, sender: &ComponentSender<Self>) {
+        while let Some(child) = box_widget.first_child() {
+            box_widget.remove(&child);
+        }
+
+        box_widget.set_visible(!query.trim().is_empty());
+
+        if results.is_empty() {
+            let label = gtk::Label::new(Some(&t!("gui-no-results")));
+            label.set_css_classes(&["dim-label"]);
+            label.set_margin_top(16);
+            label.set_margin_bottom(16);
+            box_widget.append(&label);
+            return;
+        }
+
+        for result in results {
+            let path = std::path::PathBuf::from(&result.path);
+            // Subtítulo: carpeta contenedora relativa al espejo
+            let location = path.parent()
+                .map(|parent| mirror.and_then(|m| parent.strip_prefix(m).ok()).unwrap_or(parent))
+                .map(|parent| parent.to_string_lossy().into_owned())
+                .filter(|parent| !parent.is_empty())
+                .unwrap_or_else(|| t!("gui-my-drive"));
+
+            let row = adw::ActionRow::new();
+            row.set_title(&gtk::glib::markup_escape_text(&result.name));
+            row.set_subtitle(&gtk::glib::markup_escape_text(&location));
+            row.set_activatable(true);
+            row.add_prefix(&gtk::Image::from_icon_name(if result.is_dir { "folder-symbolic" } else { "text-x-generic-symbolic" }));
+
+            let properties = gtk::Button::builder()
+                .icon_name("document-properties-symbolic")
+                .tooltip_text(t!("gui-properties"))
+                .css_classes(["flat"])
+                .valign(gtk::Align::Center)
+                .build();
+            let sender_clone = sender.clone();
+            let properties_path = path.clone();
+            properties.connect_clicked(move |_| {
+                sender_clone.input(AppMsg::ShowFileProperties(properties_path.clone()));
+            });
+            row.add_suffix(&properties);
+
+            let sender_clone = sender.clone();
+            row.connect_activated(move |_| {
+                sender_clone.input(AppMsg::OpenSearchResult(path.clone()));
+            });
+            box_widget.append(&row);
+        }
+    }
+
+    /// Diálogo de propiedades de un archivo del espejo con sus datos de Drive
+    /// (`FileDetails`), acciones y su historial de versiones (la más reciente
+    /// primero). Cada versión se puede guardar como copia `nombre (rev N)` o
+    /// restaurar sobre el original. El interruptor "Destacado" lo añade o quita
+    /// de la carpeta `Starred` y la descripción se edita en su propia fila.
+    fn present_file_properties(root: &adw::ApplicationWindow, path: &std::path::Path, details: Option<&crate::ipc::server::FileDetails>, revisions: &[crate::ipc::RevisionInfo], starred: bool, sender: &ComponentSender<Self>) {
+        let page = adw::PreferencesPage::new();
+
+        let info = adw::PreferencesGroup::builder().title(t!("gui-file")).build();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let location = path.parent().map(|p| p.display().to_string()).unwrap_or_default();
+        // El tamaño de Drive vale también para archivos solo online (symlinks al montaje)
+        let size = details
+            .map(|d| d.size)
+            .or_else(|| std::fs::metadata(path).ok().map(|m| m.len()))
+            .map(|size| gtk::glib::format_size(size).to_string())
+            .unwrap_or_default();
+        for (title, value) in [(t!("gui-name"), name), (t!("gui-location"), location), (t!("gui-size"), size)] {
+            Self::add_property_row(&info, &title, &value);
+        }
+        let starred_row = adw::SwitchRow::builder()
+            .title(t!("gui-starred"))
+            .subtitle(t!("gui-starred-subtitle"))
+            .active(starred)
+            .build();
+        let sender_clone = sender.clone();
+        let starred_path = path.to_path_buf();
+        starred_row.connect_active_notify(move |row| {
+            sender_clone.input(AppMsg::SetStarred { path: starred_path.clone(), starred: row.is_active() });
+        });
+        info.add(&starred_row);
+        if let Some(details) = details {
+            let description_row = adw::EntryRow::builder()
+                .title(t!("gui-description"))
+                .text(details.description.as_deref().unwrap_or_default())
+                .show_apply_button(true)
+                .build();
+            let sender_clone = sender.clone();
+            let description_path = path.to_path_buf();
+            description_row.connect_apply(move |row| {
+                sender_clone.input(AppMsg::SetDescription { path: description_path.clone(), description: row.text().to_string() });
+            });
+            info.add(&description_row);
+        }
+        page.add(&info);
+
+        if let Some(details) = details {
+            page.add(&Self::drive_details_group(path, details, sender));
+        }
+
+        let versions = adw::PreferencesGroup::builder()
+            .title(t!("gui-versions"))
+            .description(t!("gui-versions-description"))
+            .build();
+        let dialog = adw::Dialog::builder().title(t!("gui-properties")).content_width(440).content_height(520).build();
+
+        for revision in revisions.iter().rev() {
+            let date = revision.modified_time
+                .and_then(|secs| gtk::glib::DateTime::from_unix_local(secs).ok())
+                .and_then(|dt| dt.format("%d/%m/%Y %H:%M").ok())
+                .map(|dt| dt.to_string());
+            let details: Vec<String> = [
+                date,
+                revision.size.map(|size| gtk::glib::format_size(size).to_string()),
+                revision.author.clone(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+
+            let row = adw::ActionRow::new();
+            row.set_title(&if revision.is_current {
+                t!("gui-version-current", number = revision.number)
+            } else {
+                t!("gui-version", number = revision.number)
+            });
+            row.set_subtitle(&gtk::glib::markup_escape_text(&details.join(" · ")));
+            if revision.keep_forever {
+                row.add_prefix(&gtk::Image::from_icon_name("starred-symbolic"));
+            }
+
+            for (icon, tooltip, as_copy) in [("document-save-symbolic", t!("gui-save-as-copy"), true), ("edit-undo-symbolic", t!("gui-restore-version"), false)] {
+                if revision.is_current && !as_copy {
+                    continue;
+                }
+                let button = gtk::Button::builder()
+                    .icon_name(icon)
+                    .tooltip_text(tooltip)
+                    .css_classes(["flat"])
+                    .valign(gtk::Align::Center)
+                    .build();
+                let sender_clone = sender.clone();
+                let dialog_weak = dialog.downgrade();
+                let path = path.to_path_buf();
+                let revision_id = revision.id.clone();
+                button.connect_clicked(move |_| {
+                    sender_clone.input(AppMsg::RestoreRevision { path: path.clone(), revision_id: revision_id.clone(), as_copy });
+                    if let Some(dialog) = dialog_weak.upgrade() {
+                        dialog.close();
+                    }
+                });
+                row.add_suffix(&button);
+            }
+            versions.add(&row);
+        }
+        if revisions.is_empty() {
+            let row = adw::ActionRow::new();
+            row.set_title(&t!("gui-no-versions"));
+            versions.add(&row);
+        }
+        if !details.is_some_and(|d| d.is_dir) {
+            page.add(&versions);
+        }
+
+        let toolbar = adw::ToolbarView::new();
+        toolbar.add_top_bar(&adw::HeaderBar::new());
+        toolbar.set_content(Some(&page));
+        dialog.set_child(Some(&toolbar));
+        dialog.present(Some(root));
+    }
+
+    /// Fila de solo lectura `título: valor` de los diálogos de propiedades
+    fn add_property_row(group: &adw::PreferencesGroup, title: &str, value: &str) {
+        let row = adw::ActionRow::new();
+        row.set_title(title);
+        row.set_subtitle(&gtk::glib::markup_escape_text(value));
+        row.set_subtitle_selectable(true);
+        row.add_css_class("property");
+        group.add(&row);
+    }
+
+    /// Grupo "Google Drive" del diálogo de propiedades: MD5, propietario,
+    /// fecha de creación, color de carpeta, compartición, estado de sincronización, disponibilidad y caché,
+    /// más las acciones "Abrir en el navegador", "Copiar enlace", "Mantener siempre en
+    /// este dispositivo" y "Liberar espacio"
+    fn drive_details_group(path: &std::path::Path, details: &crate::ipc::server::FileDetails, sender: &ComponentSender<Self>) -> adw::PreferencesGroup {
+        use crate::ipc::{FileAvailability, SyncStatus};
+        use crate::ipc::server::FileOrigin;
+
+        let group = adw::PreferencesGroup::builder().title(t!("gui-drive-details")).build();
+        let owner = if details.owned_by_me {
+            t!("gui-owner-me")
+        } else if details.owners.is_empty() {
+            t!("gui-not-available")
+        } else {
+            details.owners.join(", ")
+        };
+        let sharing = if details.shared { t!("gui-sharing-shared") } else { t!("gui-sharing-private") };
+        let status = match details.status {
+            SyncStatus::Synced => t!("gui-sync-synced"),
+            SyncStatus::CloudOnly => t!("gui-sync-cloud-only"),
+            SyncStatus::LocalOnly => t!("gui-sync-local-only"),
+            SyncStatus::Syncing => t!("gui-sync-syncing"),
+            SyncStatus::Error => t!("gui-sync-error"),
+            SyncStatus::Downloading(percent) => t!("gui-sync-downloading", percent = percent),
+            SyncStatus::Uploading(percent) => t!("gui-sync-uploading", percent = percent),
+            SyncStatus::Unknown => t!("gui-not-available"),
+        };
+
+        let mut rows = Vec::new();
+        if !details.is_dir {
+            rows.push((t!("gui-md5"), details.md5.clone().unwrap_or_else(|| t!("gui-not-available"))));
+        }
+        rows.push((t!("gui-owner"), owner));
+        if let Some(created) = details.created_time
+            .and_then(|secs| gtk::glib::DateTime::from_unix_local(secs).ok())
+            .and_then(|dt| dt.format("%d/%m/%Y %H:%M").ok())
+        {
+            rows.push((t!("gui-created"), created.to_string()));
+        }
+        if let Some(origin) = details.origin {
+            let origin = match origin {
+                FileOrigin::ThisDevice => t!("gui-origin-this-device"),
+                FileOrigin::OtherDevice => t!("gui-origin-other-device"),
+            };
+            rows.push((t!("gui-created-by"), origin));
+        }
+        rows.push((t!("gui-sharing"), sharing));
+        rows.push((t!("gui-sync-state"), status));
+        let availability = match details.availability {
+            FileAvailability::LocalOnline => t!("gui-availability-local"),
+            _ => t!("gui-availability-online"),
+        };
+        rows.push((t!("gui-availability"), availability));
+        if let Some(percent) = details.cached_percent {
+            rows.push((t!("gui-cached"), t!("gui-cached-percent", percent = percent)));
+        }
+        for (title, value) in rows {
+            Self::add_property_row(&group, &title, &value);
+        }
+        if let Some(color) = &details.folder_color {
+            let row = adw::ActionRow::new();
+            row.set_title(&t!("gui-folder-color"));
+            row.set_subtitle(&gtk::glib::markup_escape_text(color));
+            row.add_css_class("property");
+            let swatch = gtk::Label::new(None);
+            swatch.set_markup(&format!("<span foreground=\"{}\">●</span>", gtk::glib::markup_escape_text(color)));
+            row.add_suffix(&swatch);
+            group.add(&row);
+        }
+
+        let actions = [
+            ("web-browser-symbolic", t!("gui-open-in-browser"), AppMsg::OpenInBrowser as fn(_) -> _, true),
+            ("edit-copy-symbolic", t!("gui-copy-share-link"), AppMsg::CreateShareLink, true),
+            ("folder-download-symbolic", t!("gui-pin-offline"), AppMsg::PinOffline, details.availability == FileAvailability::OnlineOnly),
+            // En una carpeta siempre puede haber contenido descargado que liberar
+            ("edit-clear-symbolic", t!("gui-free-up-space"), AppMsg::FreeUpSpace,
+                details.is_dir || details.availability == FileAvailability::LocalOnline || details.cached_percent.is_some_and(|p| p > 0)),
+        ];
+        for (icon, title, msg, sensitive) in actions {
+            let row = adw::ActionRow::new();
+            row.set_title(&title);
+            row.add_prefix(&gtk::Image::from_icon_name(icon));
+            row.set_activatable(true);
+            row.set_sensitive(sensitive);
+            let sender_clone = sender.clone();
+            let path = path.to_path_buf();
+            row.connect_activated(move |_| {
+                sender_clone.input(msg(path.clone()));
+            });
+            group.add(&row);
+        }
+        group
+    }
+
+    /// Reconstruye el panel de caché: totales, desglose por carpeta y acciones
+    fn rebuild_cache_box(box_widget: &gtk::ListBox, usage: &crate::fuse::cache_manager::CacheUsage, sender: &ComponentSender<Self>) {
+        while let Some(child) = box_widget.first_child() {
+            box_widget.remove(&child);
+        }
+
+        let rows = [
+            (t!("gui-cache-total"), usage.total_bytes),
+            (t!("gui-cache-on-disk"), usage.disk_bytes),
+            (t!("gui-cache-pinned"), usage.pinned_bytes),
+        ];
+        for (title, bytes) in rows {
+            let row = adw::ActionRow::new();
+            row.set_title(&title);
+            row.add_suffix(&gtk::Label::new(Some(&gtk::glib::format_size(bytes))));
+            box_widget.append(&row);
+        }
+
+        let folders = adw::ExpanderRow::new();
+        folders.set_title(&t!("gui-cache-by-folder"));
+        if usage.folders.is_empty() {
+            folders.set_subtitle(&t!("gui-cache-empty"));
+            folders.set_enable_expansion(false);
+        }
+        for folder in &usage.folders {
+            let row = adw::ActionRow::new();
+            let name = folder.name.clone().unwrap_or_else(|| t!("gui-cache-root-files"));
+            row.set_title(&gtk::glib::markup_escape_text(&name));
+            if folder.pinned_bytes > 0 {
+                row.set_subtitle(&t!("gui-cache-folder-pinned", size = gtk::glib::format_size(folder.pinned_bytes).to_string()));
+            }
+            row.add_prefix(&gtk::Image::from_icon_name(if folder.name.is_some() { "folder-symbolic" } else { "text-x-generic-symbolic" }));
+            row.add_suffix(&gtk::Label::new(Some(&gtk::glib::format_size(folder.bytes))));
+            folders.add_row(&row);
+        }
+        box_widget.append(&folders);
+
+        let actions = [
+            (t!("gui-cache-evict-unpinned"), t!("gui-cache-evict-unpinned-subtitle"), "edit-clear-symbolic", (|| AppMsg::EvictUnpinnedCache) as fn() -> AppMsg, usage.total_bytes > usage.pinned_bytes),
+            (t!("gui-cache-clear"), t!("gui-cache-clear-subtitle"), "user-trash-symbolic", || AppMsg::ConfirmClearCache, usage.total_bytes > 0),
+        ];
+        for (title, subtitle, icon, msg, sensitive) in actions {
+            let row = adw::ActionRow::new();
+            row.set_title(&title);
+            row.set_subtitle(&subtitle);
+            row.set_activatable(true);
+            row.set_sensitive(sensitive);
+            row.add_suffix(&gtk::Image::from_icon_name(icon));
+            let sender_clone = sender.clone();
+            row.connect_activated(move |_| {
+                sender_clone.input(msg());
+            });
+            box_widget.append(&row);
+        }
+    }
+
+    /// Reconstruye la lista de la papelera local
+    fn rebuild_trash_box(box_widget: &gtk::ListBox, items: &[crate::ipc::TrashItem], sender: &ComponentSender<Self>) {
+        while let Some(child) = box_widget.first_child() {
+            box_widget.remove(&child);
+        }
+
+        for item in items {
+            let row = adw::ActionRow::new();
+            row.set_title(&gtk::glib::markup_escape_text(&item.name));
+            row.add_prefix(&gtk::Image::from_icon_name(if item.is_dir { "folder-symbolic" } else { "text-x-generic-symbolic" }));
+
+            let btn_delete = gtk::Button::builder()
+                .icon_name("edit-delete-symbolic")
+                .tooltip_text(t!("gui-delete-permanently"))
+                .css_classes(["flat", "destructive-action"])
+                .valign(gtk::Align::Center)
+                .build();
+            let item = item.clone();
+            let sender_clone = sender.clone();
+            btn_delete.connect_clicked(move |_| {
+                sender_clone.input(AppMsg::ConfirmDeletePermanently(item.clone()));
+            });
+            row.add_suffix(&btn_delete);
+
+            box_widget.append(&row);
+        }
+    }
+
     /// Reconstruye el listbox de directorios locales
     fn rebuild_sync_dirs_box(box_widget: &gtk::ListBox, dirs: &[crate::db::repository::LocalSyncDir], sender: &ComponentSender<Self>) {
         while let Some(child) = box_widget.first_child() {
@@ -148,7 +792,7 @@ impl AppModel {
 
         // Fila para "Añadir Carpeta..." siempre visible
         let add_row = adw::ActionRow::new();
-        add_row.set_title("Añadir Carpeta...");
+        add_row.set_title(&t!("gui-add-folder"));
         add_row.set_activatable(true);
         let add_icon = gtk::Image::from_icon_name("list-add-symbolic");
         add_row.add_prefix(&add_icon);
@@ -159,7 +803,7 @@ impl AppModel {
         box_widget.append(&add_row);
 
         if dirs.is_empty() {
-            let label = gtk::Label::new(Some("Ninguna carpeta adicional configurada"));
+            let label = gtk::Label::new(Some(&t!("gui-no-extra-folders")));
             label.set_css_classes(&["dim-label"]);
             label.set_margin_top(16);
             label.set_margin_bottom(16);
@@ -170,7 +814,7 @@ impl AppModel {
         for dir in dirs {
             let row = adw::ActionRow::new();
             row.set_title(&dir.local_path);
-            row.set_subtitle(if dir.enabled { "Sincronizando activamente" } else { "Sincronización pausada para esta carpeta" });
+            row.set_subtitle(&if dir.enabled { t!("gui-dir-syncing") } else { t!("gui-dir-paused") });
             
             let id = dir.id;
             
@@ -205,8 +849,16 @@ pub enum AppMsg {
     SetConnected(bool),
     SetPaths { mirror: std::path::PathBuf, fuse: std::path::PathBuf },
     SetDatabase(Arc<crate::db::MetadataRepository>),
+    SetDriveClient(Arc<dyn crate::gdrive::DriveApi>),
+    SetStorageQuota(crate::utils::quota::StorageQuota),
     OpenInNautilus,
     SetPauseSync(bool),
+    SetColorScheme(ColorScheme),
+    SetAutostart(bool),
+    SetShowComputers(bool),
+    SyncNow,
+    /// Compara todo Drive con la DB local y repara las diferencias
+    RebuildMetadata,
     Logout,
     Hide,
     Quit,
@@ -226,15 +878,80 @@ pub enum AppMsg {
     ToggleSyncDir(i64, bool),
     // Refresco periódico de actividad
     RefreshActivity,
+    // Problemas de subida
+    LoadUploadProblems,
+    UploadProblemsLoaded(Vec<crate::db::UploadProblem>),
+    RetryUpload(u64),
+    /// El usuario liberó espacio en Drive: reintentar lo rechazado por cuota
+    RetryStorageFull,
+    // Centro de conflictos
+    LoadConflicts,
+    ConflictsLoaded(Vec<crate::db::SyncConflict>),
+    ResolveConflict { id: i64, resolution: crate::sync::conflicts::ConflictResolution },
+    ShowConflictDiff(i64),
+    ConflictDiffLoaded { title: String, text: String },
     // Navegación
     ShowActivityView,
+    ShowLogsView,
+    ShowConflictsView,
     ShowMainView,
+    // Supervisor del backend
+    SetBackendState(BackendState),
+    SetConfigProblems(Vec<crate::config::ConfigProblem>),
+    /// El punto de montaje tiene archivos que el montaje ocultaría
+    MountPointNotEmpty { path: std::path::PathBuf, entries: Vec<String> },
+    SelectMountPoint,
+    SetMountPoint(std::path::PathBuf),
+    MigrateMountPoint,
+    RecoverBackend,
+    // Diagnóstico
+    SelectDiagnosticsDest,
+    ExportDiagnostics(std::path::PathBuf),
+    // Enlaces para compartir
+    SelectFileToShare,
+    CreateShareLink(std::path::PathBuf),
+    ShareLinkCreated(String),
+    ShareLinkFailed(String),
+    // Propiedades e historial de versiones
+    SelectFileForProperties,
+    ShowFileProperties(std::path::PathBuf),
+    FilePropertiesLoaded {
+        path: std::path::PathBuf,
+        details: Option<crate::ipc::server::FileDetails>,
+        revisions: Vec<crate::ipc::RevisionInfo>,
+        starred: bool,
+    },
+    RestoreRevision { path: std::path::PathBuf, revision_id: String, as_copy: bool },
+    SetStarred { path: std::path::PathBuf, starred: bool },
+    /// Nueva descripción en Drive (vacía: la borra)
+    SetDescription { path: std::path::PathBuf, description: String },
+    SetIpcContext(crate::ipc::server::IpcContext),
+    OpenInBrowser(std::path::PathBuf),
+    PinOffline(std::path::PathBuf),
+    FreeUpSpace(std::path::PathBuf),
+    // Uso de la caché
+    SetCacheManager(crate::fuse::cache_manager::CacheManager),
+    LoadCacheUsage,
+    CacheUsageLoaded(crate::fuse::cache_manager::CacheUsage),
+    EvictUnpinnedCache,
+    ConfirmClearCache,
+    ClearCache,
+    CacheFreed(crate::fuse::cache_manager::EvictionReport),
+    // Papelera local
+    LoadTrash,
+    TrashLoaded(Vec<crate::ipc::TrashItem>),
+    ConfirmDeletePermanently(crate::ipc::TrashItem),
+    DeletePermanently(crate::ipc::TrashItem),
+    // Búsqueda por nombre
+    Search(String),
+    SearchResultsLoaded { query: String, results: Vec<crate::ipc::SearchResult> },
+    OpenSearchResult(std::path::PathBuf),
 }
 
 #[relm4::component(pub)]
 #[allow(unused_assignments)]
 impl Component for AppModel {
-    type Init = ();
+    type Init = AppInit;
     type Input = AppMsg;
     type Output = ();
     type CommandOutput = ();
@@ -253,7 +970,7 @@ impl Component for AppModel {
                     pack_start = &gtk::Button {
                         set_icon_name: "go-previous-symbolic",
                         #[watch]
-                        set_visible: model.current_view == ViewMode::Activity,
+                        set_visible: model.current_view != ViewMode::Main,
                         connect_clicked[sender] => move |_| {
                             sender.input(AppMsg::ShowMainView);
                         },
@@ -275,14 +992,40 @@ impl Component for AppModel {
                         append = &adw::WindowTitle {
                             set_title: "G-DriveXP",
                             #[watch]
-                            set_subtitle: match model.current_view {
-                                ViewMode::Main => "Cliente de Google Drive",
-                                ViewMode::Activity => "Actividad Reciente",
+                            set_subtitle: &match model.current_view {
+                                ViewMode::Main => t!("gui-subtitle-main"),
+                                ViewMode::Activity => t!("gui-recent-activity"),
+                                ViewMode::Logs => t!("gui-log"),
+                                ViewMode::Conflicts => t!("gui-conflicts"),
                             },
                         },
                     },
                 },
 
+                // Aviso de backend caído (el supervisor ya desmontó FUSE)
+                append = &adw::Banner {
+                    #[watch]
+                    set_button_label: model.backend_banner_button().as_deref(),
+                    #[watch]
+                    set_title: &model.backend_banner_title().unwrap_or_default(),
+                    #[watch]
+                    set_revealed: model.backend_banner_title().is_some(),
+                    connect_button_clicked[sender] => move |_| {
+                        sender.input(AppMsg::RecoverBackend);
+                    },
+                },
+
+                // Aviso de almacenamiento de Drive lleno: las escrituras fallan con ENOSPC
+                append = &adw::Banner {
+                    set_title: &t!("gui-storage-full"),
+                    set_button_label: Some(&t!("gui-storage-full-retry")),
+                    #[watch]
+                    set_revealed: model.storage_full,
+                    connect_button_clicked[sender] => move |_| {
+                        sender.input(AppMsg::RetryStorageFull);
+                    },
+                },
+
                 // Stack para alternar vistas
                 #[name = "main_stack"]
                 append = &gtk::Stack {
@@ -322,7 +1065,7 @@ impl Component for AppModel {
                                         },
 
                                         append = &gtk::Label {
-                                            set_label: "Abrir en Archivos",
+                                            set_label: &t!("open-in-files"),
                                         },
                                     },
 
@@ -335,12 +1078,12 @@ impl Component for AppModel {
                                 append = &adw::PreferencesGroup {
                                     #[watch]
                                     set_visible: model.is_connected,
-                                    set_title: "Estado",
+                                    set_title: &t!("gui-status"),
 
                                     add = &adw::ActionRow {
-                                        set_title: "Conexión",
+                                        set_title: &t!("gui-connection"),
                                         #[watch]
-                                        set_subtitle: if model.is_connected { "Conectado a Google Drive" } else { "Desconectado" },
+                                        set_subtitle: &if model.is_connected { t!("gui-connected") } else { t!("gui-disconnected") },
 
                                         add_suffix = &gtk::Image {
                                             #[watch]
@@ -351,9 +1094,9 @@ impl Component for AppModel {
                                     },
 
                                     add = &adw::ActionRow {
-                                        set_title: "Estado",
+                                        set_title: &t!("gui-status"),
                                         #[watch]
-                                        set_subtitle: if model.scanning_total > 0 { "Escaneando..." } else { &model.status_message },
+                                        set_subtitle: &if model.scanning_total > 0 { t!("gui-scanning") } else { model.status_message.clone() },
                                     },
                                 },
 
@@ -365,7 +1108,7 @@ impl Component for AppModel {
                                     add = &adw::ActionRow {
                                         #[watch]
                                         set_title: &model.sync_hint_text(),
-                                        set_subtitle: "Actividad Reciente",
+                                        set_subtitle: &t!("gui-recent-activity"),
                                         set_activatable: true,
 
                                         add_prefix = &gtk::Image {
@@ -393,101 +1136,313 @@ impl Component for AppModel {
                                             sender.input(AppMsg::ShowActivityView);
                                         },
                                     },
+
+                                    add = &adw::ActionRow {
+                                        set_title: &t!("gui-conflicts"),
+                                        #[watch]
+                                        set_subtitle: &if model.conflicts.is_empty() {
+                                            t!("gui-conflicts-subtitle")
+                                        } else {
+                                            t!("gui-conflicts-unresolved", count = model.conflicts.len())
+                                        },
+                                        set_activatable: true,
+
+                                        add_prefix = &gtk::Image {
+                                            #[watch]
+                                            set_icon_name: Some(if model.conflicts.is_empty() { "object-select-symbolic" } else { "dialog-warning-symbolic" }),
+                                            #[watch]
+                                            set_css_classes: if model.conflicts.is_empty() { &["success"] } else { &["warning"] },
+                                        },
+
+                                        add_suffix = &gtk::Image {
+                                            set_icon_name: Some("go-next-symbolic"),
+                                        },
+
+                                        connect_activated[sender] => move |_| {
+                                            sender.input(AppMsg::ShowConflictsView);
+                                        },
+                                    },
                                 },
 
-                                // Sección Configuración
+                                // Sección Búsqueda
                                 append = &adw::PreferencesGroup {
                                     #[watch]
                                     set_visible: model.is_connected,
-                                    set_title: "Configuración",
+                                    set_title: &t!("gui-search-drive"),
 
-                                    add = &adw::SwitchRow {
-                                        set_title: "Pausar sincronización",
-                                        set_subtitle: "Detiene temporalmente la sincronización",
-                                        #[watch]
-                                        set_active: model.sync_paused.load(Ordering::Relaxed),
+                                    add = &gtk::SearchEntry {
+                                        set_placeholder_text: Some(&t!("gui-search-placeholder")),
+                                        set_margin_bottom: 8,
 
-                                        connect_active_notify[sender] => move |switch| {
-                                            sender.input(AppMsg::SetPauseSync(switch.is_active()));
+                                        connect_search_changed[sender] => move |entry| {
+                                            sender.input(AppMsg::Search(entry.text().to_string()));
                                         },
                                     },
-                                },
-
-                                // Sección Directorios Adicionales
-                                append = &adw::PreferencesGroup {
-                                    set_visible: false, // Oculto hasta nuevo aviso
-                                    set_title: "Carpetas Locales Sincronizadas",
-                                    set_description: Some("Seleccione directorios fuera del espejo de Google Drive para sincronizar"),
 
-                                    #[name = "sync_dirs_box"]
+                                    #[name = "search_results_box"]
                                     add = &gtk::ListBox {
                                         set_css_classes: &["boxed-list"],
                                         set_selection_mode: gtk::SelectionMode::None,
+                                        set_visible: false,
                                     },
                                 },
 
-                                // Sección Cuenta
+                                // Sección Archivos
                                 append = &adw::PreferencesGroup {
                                     #[watch]
                                     set_visible: model.is_connected,
-                                    set_title: "Cuenta",
+                                    set_title: &t!("gui-files"),
 
                                     add = &adw::ActionRow {
-                                        set_title: "Cerrar sesión",
-                                        set_subtitle: "Desvincula esta cuenta de Google",
+                                        set_title: &t!("gui-share-link"),
+                                        set_subtitle: &t!("gui-share-link-subtitle"),
                                         set_activatable: true,
 
                                         add_suffix = &gtk::Image {
-                                            set_icon_name: Some("system-log-out-symbolic"),
+                                            set_icon_name: Some("emblem-shared-symbolic"),
                                         },
 
                                         connect_activated[sender] => move |_| {
-                                            sender.input(AppMsg::Logout);
+                                            sender.input(AppMsg::SelectFileToShare);
                                         },
                                     },
 
                                     add = &adw::ActionRow {
-                                        set_title: "Hard Reset",
-                                        set_subtitle: "BORRADO TOTAL: Reinicia DB, Cache y Archivos.",
+                                        set_title: &t!("gui-properties-and-versions"),
+                                        set_subtitle: &t!("gui-properties-and-versions-subtitle"),
                                         set_activatable: true,
-                                        set_css_classes: &["destructive-action"],
 
                                         add_suffix = &gtk::Image {
-                                            set_icon_name: Some("user-trash-symbolic"),
+                                            set_icon_name: Some("document-open-recent-symbolic"),
                                         },
 
                                         connect_activated[sender] => move |_| {
-                                            sender.input(AppMsg::HardReset);
+                                            sender.input(AppMsg::SelectFileForProperties);
                                         },
                                     },
                                 },
 
-                                // PANTALLA DE LOGIN (SOLO SI NO ESTÁ CONECTADO)
-                                append = &gtk::Box {
-                                    set_orientation: gtk::Orientation::Vertical,
-                                    set_valign: gtk::Align::Center,
-                                    set_halign: gtk::Align::Center,
-                                    set_spacing: 16,
-                                    set_margin_top: 40,
+                                // Sección Configuración
+                                append = &adw::PreferencesGroup {
                                     #[watch]
-                                    set_visible: !model.is_connected,
-
-                                    append = &gtk::Image {
-                                        set_pixel_size: 96,
-                                        set_icon_name: Some("avatar-default-symbolic"),
-                                        set_css_classes: &["dim-label"],
+                                    set_visible: model.is_connected,
+                                    set_title: &t!("gui-settings"),
+
+                                    add = &adw::ComboRow {
+                                        set_title: &t!("gui-appearance"),
+                                        set_subtitle: &t!("gui-appearance-subtitle"),
+                                        set_model: Some(&COLOR_SCHEMES.iter().map(|(_, id)| t!(id)).collect::<gtk::StringList>()),
+                                        set_selected: COLOR_SCHEMES.iter().position(|(scheme, _)| *scheme == model.color_scheme).unwrap_or(0) as u32,
+
+                                        connect_selected_notify[sender] => move |row| {
+                                            if let Some((scheme, _)) = COLOR_SCHEMES.get(row.selected() as usize) {
+                                                sender.input(AppMsg::SetColorScheme(*scheme));
+                                            }
+                                        },
                                     },
 
-                                    append = &gtk::Label {
-                                        set_label: "Inicie sesión para continuar",
-                                        set_css_classes: &["title-1"],
+                                    add = &adw::SwitchRow {
+                                        set_title: &t!("gui-autostart"),
+                                        set_subtitle: &t!("gui-autostart-subtitle"),
+                                        #[watch]
+                                        set_active: model.autostart,
+
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(AppMsg::SetAutostart(switch.is_active()));
+                                        },
                                     },
 
-                                    append = &gtk::Button {
+                                    add = &adw::SwitchRow {
+                                        set_title: &t!("gui-show-computers"),
+                                        set_subtitle: &t!("gui-show-computers-subtitle"),
                                         #[watch]
-                                        set_label: if model.login_url.is_some() { "Iniciar Sesión" } else { "Generando enlace..." },
+                                        set_active: model.show_computers,
+
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(AppMsg::SetShowComputers(switch.is_active()));
+                                        },
+                                    },
+
+                                    add = &adw::SwitchRow {
+                                        set_title: &t!("pause-sync"),
+                                        set_subtitle: &t!("gui-pause-sync-subtitle"),
                                         #[watch]
-                                        set_sensitive: model.login_url.is_some(),
+                                        set_active: model.sync_paused.load(Ordering::Relaxed),
+
+                                        connect_active_notify[sender] => move |switch| {
+                                            sender.input(AppMsg::SetPauseSync(switch.is_active()));
+                                        },
+                                    },
+
+                                    add = &adw::ActionRow {
+                                        set_title: &t!("tray-sync-now"),
+                                        set_subtitle: &sync_now_subtitle(&model.sync_schedule),
+                                        set_activatable: true,
+                                        #[watch]
+                                        set_sensitive: !model.sync_paused.load(Ordering::Relaxed),
+
+                                        add_suffix = &gtk::Image {
+                                            set_icon_name: Some("emblem-synchronizing-symbolic"),
+                                        },
+
+                                        connect_activated[sender] => move |_| {
+                                            sender.input(AppMsg::SyncNow);
+                                        },
+                                    },
+
+                                    add = &adw::ActionRow {
+                                        set_title: &t!("gui-rebuild-metadata"),
+                                        set_subtitle: &t!("gui-rebuild-metadata-subtitle"),
+                                        set_activatable: true,
+                                        #[watch]
+                                        set_sensitive: !model.sync_paused.load(Ordering::Relaxed),
+
+                                        add_suffix = &gtk::Image {
+                                            set_icon_name: Some("view-refresh-symbolic"),
+                                        },
+
+                                        connect_activated[sender] => move |_| {
+                                            sender.input(AppMsg::RebuildMetadata);
+                                        },
+                                    },
+                                },
+
+                                // Sección Caché (uso total, anclado y por carpeta)
+                                append = &adw::PreferencesGroup {
+                                    #[watch]
+                                    set_visible: model.is_connected,
+                                    set_title: &t!("gui-cache"),
+                                    set_description: Some(&t!("gui-cache-description")),
+
+                                    #[name = "cache_box"]
+                                    add = &gtk::ListBox {
+                                        set_css_classes: &["boxed-list"],
+                                        set_selection_mode: gtk::SelectionMode::None,
+                                    },
+                                },
+
+                                // Sección Diagnóstico (disponible también sin conexión)
+                                append = &adw::PreferencesGroup {
+                                    set_title: &t!("gui-diagnostics"),
+
+                                    add = &adw::ActionRow {
+                                        set_title: &t!("gui-view-log"),
+                                        set_subtitle: &t!("gui-view-log-subtitle"),
+                                        set_activatable: true,
+
+                                        add_suffix = &gtk::Image {
+                                            set_icon_name: Some("go-next-symbolic"),
+                                        },
+
+                                        connect_activated[sender] => move |_| {
+                                            sender.input(AppMsg::ShowLogsView);
+                                        },
+                                    },
+
+                                    add = &adw::ActionRow {
+                                        set_title: &t!("gui-export-diagnostics"),
+                                        set_subtitle: &t!("gui-export-diagnostics-subtitle"),
+                                        set_activatable: true,
+
+                                        add_suffix = &gtk::Image {
+                                            set_icon_name: Some("document-save-symbolic"),
+                                        },
+
+                                        connect_activated[sender] => move |_| {
+                                            sender.input(AppMsg::SelectDiagnosticsDest);
+                                        },
+                                    },
+                                },
+
+                                // Sección Papelera local (solo si hay elementos)
+                                append = &adw::PreferencesGroup {
+                                    #[watch]
+                                    set_visible: model.is_connected && !model.trash_items.is_empty(),
+                                    set_title: &t!("gui-trash"),
+                                    set_description: Some(&t!("gui-trash-description")),
+
+                                    #[name = "trash_box"]
+                                    add = &gtk::ListBox {
+                                        set_css_classes: &["boxed-list"],
+                                        set_selection_mode: gtk::SelectionMode::None,
+                                    },
+                                },
+
+                                // Sección Directorios Adicionales
+                                append = &adw::PreferencesGroup {
+                                    set_visible: false, // Oculto hasta nuevo aviso
+                                    set_title: &t!("gui-local-folders"),
+                                    set_description: Some(&t!("gui-local-folders-description")),
+
+                                    #[name = "sync_dirs_box"]
+                                    add = &gtk::ListBox {
+                                        set_css_classes: &["boxed-list"],
+                                        set_selection_mode: gtk::SelectionMode::None,
+                                    },
+                                },
+
+                                // Sección Cuenta
+                                append = &adw::PreferencesGroup {
+                                    #[watch]
+                                    set_visible: model.is_connected,
+                                    set_title: &t!("gui-account"),
+
+                                    add = &adw::ActionRow {
+                                        set_title: &t!("gui-logout"),
+                                        set_subtitle: &t!("gui-logout-subtitle"),
+                                        set_activatable: true,
+
+                                        add_suffix = &gtk::Image {
+                                            set_icon_name: Some("system-log-out-symbolic"),
+                                        },
+
+                                        connect_activated[sender] => move |_| {
+                                            sender.input(AppMsg::Logout);
+                                        },
+                                    },
+
+                                    add = &adw::ActionRow {
+                                        set_title: &t!("gui-hard-reset"),
+                                        set_subtitle: &t!("gui-hard-reset-subtitle"),
+                                        set_activatable: true,
+                                        set_css_classes: &["destructive-action"],
+
+                                        add_suffix = &gtk::Image {
+                                            set_icon_name: Some("user-trash-symbolic"),
+                                        },
+
+                                        connect_activated[sender] => move |_| {
+                                            sender.input(AppMsg::HardReset);
+                                        },
+                                    },
+                                },
+
+                                // PANTALLA DE LOGIN (SOLO SI NO ESTÁ CONECTADO)
+                                append = &gtk::Box {
+                                    set_orientation: gtk::Orientation::Vertical,
+                                    set_valign: gtk::Align::Center,
+                                    set_halign: gtk::Align::Center,
+                                    set_spacing: 16,
+                                    set_margin_top: 40,
+                                    #[watch]
+                                    set_visible: !model.is_connected,
+
+                                    append = &gtk::Image {
+                                        set_pixel_size: 96,
+                                        set_icon_name: Some("avatar-default-symbolic"),
+                                        set_css_classes: &["dim-label"],
+                                    },
+
+                                    append = &gtk::Label {
+                                        set_label: &t!("gui-login-prompt"),
+                                        set_css_classes: &["title-1"],
+                                    },
+
+                                    append = &gtk::Button {
+                                        #[watch]
+                                        set_label: &if model.login_url.is_some() { t!("gui-login") } else { t!("gui-login-generating") },
+                                        #[watch]
+                                        set_sensitive: model.login_url.is_some(),
                                         set_css_classes: &["suggested-action", "pill"],
                                         set_margin_top: 16,
                                         connect_clicked[sender] => move |_| {
@@ -515,7 +1470,7 @@ impl Component for AppModel {
                             append = &adw::ActionRow {
                                 #[watch]
                                 set_title: &model.sync_hint_text(),
-                                set_subtitle: "Estado General",
+                                set_subtitle: &t!("gui-overall-status"),
 
                                 add_prefix = &gtk::Image {
                                     #[watch]
@@ -538,7 +1493,7 @@ impl Component for AppModel {
 
                         // Label "Descargando de Google Drive"
                         append = &gtk::Label {
-                            set_label: "Descargando de Google Drive",
+                            set_label: &t!("gui-downloading-heading"),
                             set_halign: gtk::Align::Start,
                             set_css_classes: &["heading"],
                             #[watch]
@@ -556,7 +1511,7 @@ impl Component for AppModel {
 
                         // Label "Subiendo a Google Drive"
                         append = &gtk::Label {
-                            set_label: "Subiendo a Google Drive",
+                            set_label: &t!("gui-uploading-heading"),
                             set_halign: gtk::Align::Start,
                             set_css_classes: &["heading"],
                             #[watch]
@@ -572,9 +1527,27 @@ impl Component for AppModel {
                             set_visible: model.active_transfers.iter().any(|t| t.operation == TransferOp::Upload),
                         },
 
+                        // Label "Problemas"
+                        append = &gtk::Label {
+                            set_label: &t!("gui-problems"),
+                            set_halign: gtk::Align::Start,
+                            set_css_classes: &["heading"],
+                            #[watch]
+                            set_visible: !model.upload_problems.is_empty(),
+                        },
+
+                        // Archivos con la subida en error
+                        #[name = "problems_box"]
+                        append = &gtk::ListBox {
+                            set_css_classes: &["boxed-list"],
+                            set_selection_mode: gtk::SelectionMode::None,
+                            #[watch]
+                            set_visible: !model.upload_problems.is_empty(),
+                        },
+
                         // Label "Historial"
                         append = &gtk::Label {
-                            set_label: "Historial",
+                            set_label: &t!("gui-history"),
                             set_halign: gtk::Align::Start,
                             set_css_classes: &["heading"],
                             #[watch]
@@ -598,10 +1571,73 @@ impl Component for AppModel {
                         set_name: "activity",
                     },
 
+                    // ========== VISTA DE CONFLICTOS ==========
+                    add_named[Some("conflicts")] = &gtk::ScrolledWindow {
+                        set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                        #[wrap(Some)]
+                        set_child = &adw::Clamp {
+                            set_maximum_size: 600,
+                            set_margin_all: 16,
+
+                            #[wrap(Some)]
+                            set_child = &gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+                                set_spacing: 16,
+
+                                append = &adw::StatusPage {
+                                    set_icon_name: Some("object-select-symbolic"),
+                                    set_title: &t!("gui-no-conflicts"),
+                                    set_description: Some(&t!("gui-no-conflicts-description")),
+                                    #[watch]
+                                    set_visible: model.conflicts.is_empty(),
+                                },
+
+                                append = &gtk::Label {
+                                    set_label: &t!("gui-conflicts-explanation"),
+                                    set_wrap: true,
+                                    set_xalign: 0.0,
+                                    set_css_classes: &["dim-label"],
+                                    #[watch]
+                                    set_visible: !model.conflicts.is_empty(),
+                                },
+
+                                #[name = "conflicts_box"]
+                                append = &gtk::ListBox {
+                                    set_css_classes: &["boxed-list"],
+                                    set_selection_mode: gtk::SelectionMode::None,
+                                    #[watch]
+                                    set_visible: !model.conflicts.is_empty(),
+                                },
+                            },
+                        },
+                    } -> {
+                        set_name: "conflicts",
+                    },
+
+                    // ========== VISTA DE REGISTRO ==========
+                    add_named[Some("logs")] = &gtk::ScrolledWindow {
+                        set_vexpand: true,
+                        set_margin_all: 16,
+
+                        #[wrap(Some)]
+                        #[name = "log_textview"]
+                        set_child = &gtk::TextView {
+                            set_editable: false,
+                            set_cursor_visible: false,
+                            set_monospace: true,
+                            set_wrap_mode: gtk::WrapMode::WordChar,
+                        },
+                    } -> {
+                        set_name: "logs",
+                    },
+
                     #[watch]
                     set_visible_child_name: match model.current_view {
                         ViewMode::Main => "main",
                         ViewMode::Activity => "activity",
+                        ViewMode::Logs => "logs",
+                        ViewMode::Conflicts => "conflicts",
                     },
                 },
             }
@@ -609,12 +1645,17 @@ impl Component for AppModel {
     }
 
     fn init(
-        _init: Self::Init,
+        init: Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        // Forzar tema oscuro
-        adw::StyleManager::default().set_color_scheme(adw::ColorScheme::ForceDark);
+        // Configuración guardada: esquema de color y rutas de la bandeja
+        let config = crate::config::Config::load_effective();
+        let color_scheme = config.as_ref().map(|config| config.color_scheme).unwrap_or_default();
+        let show_computers = config.as_ref()
+            .is_ok_and(|config| config.computers_mode == crate::config::ComputersMode::Show);
+        let sync_schedule = config.as_ref().map(|config| config.sync_schedule.clone()).unwrap_or_default();
+        apply_color_scheme(color_scheme);
 
         // Icono de la app para taskbar/dock (independiente del .desktop lookup)
         gtk::Window::set_default_icon_name("org.gnome.FedoraDrive");
@@ -623,13 +1664,21 @@ impl Component for AppModel {
         let history = ActionHistory::new();
 
         let mut model = AppModel {
-            status_message: "Iniciando G-DriveXP...".to_string(),
+            status_message: t!("gui-starting"),
             is_connected: false,
             mirror_path: None,
             fuse_mount_path: None,
             sync_paused: sync_paused.clone(),
+            color_scheme,
+            autostart: crate::utils::autostart::is_enabled(),
+            show_computers,
+            sync_schedule,
             history: history.clone(),
             db: None,
+            drive_client: None,
+            storage_quota: crate::utils::quota::StorageQuota::default(),
+            ipc_context: None,
+            cache_manager: None,
             login_url: None,
             activity_entries: Vec::new(),
             active_transfers: Vec::new(),
@@ -637,17 +1686,40 @@ impl Component for AppModel {
             sync_applied: 0,
             pending_uploads: 0,
             scanning_total: 0,
+            scanning_expected: 0,
+            scanning_pages: 0,
+            network_pause: None,
+            backpressure: None,
+            storage_full: false,
+            backend_state: BackendState::Starting { attempt: 1 },
+            backend_reconnect: None,
+            config_problems: Vec::new(),
+            mount_point_problem: None,
+            upload_problems: Vec::new(),
+            conflicts: Vec::new(),
             local_sync_dirs: Vec::new(),
             uploads_listbox: None,
             downloads_listbox: None,
             history_listbox: None,
+            problems_listbox: None,
+            conflicts_listbox: None,
             sync_dirs_listbox: None,
+            search_results_listbox: None,
+            cache_listbox: None,
+            trash_listbox: None,
+            trash_items: Vec::new(),
+            log_textview: None,
+            search_query: String::new(),
             current_view: ViewMode::Main,
             shutdown_requested: false,
         };
 
         // Iniciar icono de bandeja
-        let tray = TrayIcon::new(history.clone(), sync_paused.clone());
+        let mut tray = TrayIcon::new(history.clone(), sync_paused.clone());
+        match &config {
+            Ok(config) => tray = tray.with_config(config),
+            Err(e) => tracing::warn!("Bandeja sin configuración, se usan las rutas por defecto: {:?}", e),
+        }
         let _tray_handle = tray.spawn();
 
         // Registrar acción para mostrar ventana desde el tray (D-Bus)
@@ -659,6 +1731,18 @@ impl Component for AppModel {
         });
         app.add_action(&show_action);
 
+        // Arranque oculto: la primera activación no muestra la ventana (relm4 ya no
+        // la presenta), pero relanzar la aplicación después sí debe mostrarla
+        if init.start_hidden {
+            let sender_activate = sender.clone();
+            let first_activation = std::cell::Cell::new(true);
+            app.connect_activate(move |_| {
+                if !first_activation.replace(false) {
+                    sender_activate.input(AppMsg::ShowWindow);
+                }
+            });
+        }
+
         // Timer de refresco de actividad cada 2 segundos
         let sender_timer = sender.clone();
         gtk::glib::timeout_add_local(std::time::Duration::from_secs(2), move || {
@@ -678,15 +1762,24 @@ impl Component for AppModel {
         controller.add_shortcut(shortcut);
         root.add_controller(controller);
 
-        // Spawnear el backend en un hilo separado
+        // Spawnear el backend bajo un supervisor: tras un error desmonta FUSE y
+        // lo relanza con backoff; tras un panic ofrece reiniciar la aplicación
         let sender_clone = sender.clone();
         let history_clone = history.clone();
         let sync_paused_clone = sync_paused.clone();
-        std::thread::spawn(move || {
-            if let Err(e) = crate::run_backend(sender_clone, history_clone, sync_paused_clone) {
-                tracing::error!("Error en el backend: {:?}", e);
+        let sender_supervisor = sender.clone();
+        let supervisor = BackendSupervisor::new(move || {
+            crate::run_backend(sender_clone.clone(), history_clone.clone(), sync_paused_clone.clone())
+        })
+        .with_state_callback(move |state| sender_supervisor.input(AppMsg::SetBackendState(state)))
+        .with_failure_cleanup(|| {
+            let fuse_mount_path = crate::config::Config::load_effective().map(|config| config.fuse_mount_path);
+            if let Ok(path) = fuse_mount_path {
+                crate::utils::crash::cleanup_after_crash(&path);
             }
         });
+        model.backend_reconnect = Some(supervisor.reconnect_sender());
+        let _supervisor_handle = supervisor.spawn();
 
         let widgets = view_output!();
 
@@ -694,7 +1787,13 @@ impl Component for AppModel {
         model.uploads_listbox = Some(widgets.uploads_box.clone());
         model.downloads_listbox = Some(widgets.downloads_box.clone());
         model.history_listbox = Some(widgets.history_listbox.clone());
+        model.problems_listbox = Some(widgets.problems_box.clone());
+        model.conflicts_listbox = Some(widgets.conflicts_box.clone());
         model.sync_dirs_listbox = Some(widgets.sync_dirs_box.clone());
+        model.search_results_listbox = Some(widgets.search_results_box.clone());
+        model.cache_listbox = Some(widgets.cache_box.clone());
+        model.trash_listbox = Some(widgets.trash_box.clone());
+        model.log_textview = Some(widgets.log_textview.clone());
 
         // Cargar logo embebido y asignarlo al widget
         let logo_bytes = include_bytes!("../../assets/logo.png");
@@ -743,16 +1842,79 @@ impl Component for AppModel {
                         .spawn();
                 }
             }
+            AppMsg::SetColorScheme(scheme) => {
+                if self.color_scheme == scheme {
+                    return;
+                }
+                self.color_scheme = scheme;
+                apply_color_scheme(scheme);
+                // Releer antes de guardar para no pisar cambios hechos a mano en config.json
+                let result = crate::config::Config::load().and_then(|mut config| {
+                    config.color_scheme = scheme;
+                    config.save()
+                });
+                if let Err(e) = result {
+                    tracing::warn!("No se pudo guardar el esquema de color: {:?}", e);
+                }
+            }
+            AppMsg::SetAutostart(enabled) => {
+                if self.autostart == enabled {
+                    return;
+                }
+                if let Err(e) = crate::utils::autostart::set_enabled(enabled) {
+                    tracing::warn!("No se pudo cambiar el inicio automático: {:?}", e);
+                    self.status_message = t!("gui-autostart-failed", error = e.to_string());
+                }
+                // El switch refleja lo que quedó en disco, también si falló
+                self.autostart = crate::utils::autostart::is_enabled();
+            }
+            AppMsg::SetShowComputers(show) => {
+                if self.show_computers == show {
+                    return;
+                }
+                self.show_computers = show;
+                let mode = if show { crate::config::ComputersMode::Show } else { crate::config::ComputersMode::Hide };
+                let result = crate::config::Config::load().and_then(|mut config| {
+                    config.computers_mode = mode;
+                    config.save()
+                });
+                if let Err(e) = result {
+                    tracing::warn!("No se pudo guardar la visibilidad de Computers: {:?}", e);
+                }
+                // Sin backend aún, `main.rs` lo aplica al arrancar
+                if let Some(ctx) = self.ipc_context.clone() {
+                    std::thread::spawn(move || {
+                        if let Ok(rt) = tokio::runtime::Runtime::new() {
+                            rt.block_on(async {
+                                match ctx.db().set_computers_visible(show).await {
+                                    // El espejo crea o borra `Computers/` según la DB
+                                    Ok(true) => ctx.refresh_mirror().await,
+                                    Ok(false) => {}
+                                    Err(e) => tracing::warn!("Error cambiando la visibilidad de Computers: {:?}", e),
+                                }
+                            });
+                        }
+                    });
+                }
+            }
+            AppMsg::SyncNow => {
+                crate::sync::trigger::request_sync_now();
+                self.status_message = t!("gui-sync-now-requested");
+            }
+            AppMsg::RebuildMetadata => {
+                crate::sync::trigger::request_rebuild_metadata();
+                self.status_message = t!("gui-rebuild-metadata-requested");
+            }
             AppMsg::SetPauseSync(paused) => {
                 let current = self.sync_paused.load(Ordering::Relaxed);
                 if current != paused {
                     self.sync_paused.store(paused, Ordering::Relaxed);
                     if paused {
                         tracing::info!("Sincronización pausada");
-                        self.history.log(ActionType::Sync, "Sincronización pausada");
+                        self.history.log(ActionType::Sync, t!("sync-paused"));
                     } else {
                         tracing::info!("Sincronización reanudada");
-                        self.history.log(ActionType::Sync, "Sincronización reanudada");
+                        self.history.log(ActionType::Sync, t!("sync-resumed"));
                     }
                 }
             }
@@ -780,10 +1942,14 @@ impl Component for AppModel {
             }
             AppMsg::ShowWindow => {
                 root.present();
+                _sender.input(AppMsg::LoadCacheUsage);
+                _sender.input(AppMsg::LoadTrash);
             }
             AppMsg::SetDatabase(db) => {
                 self.db = Some(db);
                 _sender.input(AppMsg::LoadSyncDirs);
+                _sender.input(AppMsg::LoadConflicts);
+                _sender.input(AppMsg::LoadTrash);
             }
             AppMsg::PrepareShutdown => {
                 self.shutdown_requested = true;
@@ -794,6 +1960,14 @@ impl Component for AppModel {
             }
             AppMsg::Quit => {
                 tracing::info!("Cerrando aplicación...");
+                // Sin backend vivo (esperando reintento o tras un panic) nadie
+                // atendería el shutdown, y el supervisor ya desmontó FUSE
+                if matches!(
+                    self.backend_state,
+                    BackendState::Retrying { .. } | BackendState::Stopped { .. } | BackendState::Crashed(_)
+                ) {
+                    std::process::exit(0);
+                }
                 // Solo señalizar — el backend en main.rs ejecuta: hide → unmount → exit
                 crate::utils::shutdown::request_shutdown();
             }
@@ -807,7 +1981,7 @@ impl Component for AppModel {
                 self.sync_paused.store(true, Ordering::Relaxed);
                 
                 // 2. Dar retroalimentación en UI
-                self.status_message = "Purgando sistema, por favor espere...".to_string();
+                self.status_message = t!("gui-hard-reset-running");
 
                 // 3. Clonar parámetros necesarios antes de mover al hilo
                 let fuse_path = self.fuse_mount_path.clone();
@@ -831,7 +2005,9 @@ impl Component for AppModel {
 
                     // Paso 2: Limpieza de datos locales (FUSE ya desmontado,
                     //         rm -rf no pasa por el filesystem virtual)
-                    if let Err(e) = crate::utils::cleanup::perform_hard_reset() {
+                    let result = crate::config::Config::load_effective()
+                        .and_then(|config| crate::utils::cleanup::perform_hard_reset(&config));
+                    if let Err(e) = result {
                         tracing::error!("Error durante limpieza profunda: {:?}", e);
                     }
 
@@ -882,7 +2058,7 @@ impl Component for AppModel {
             }
             AppMsg::SelectNewSyncDir => {
                 let dialog = gtk::FileDialog::builder()
-                    .title("Seleccionar carpeta para sincronizar")
+                    .title(t!("gui-select-sync-folder"))
                     .build();
                 let sender_clone = _sender.clone();
                 // Pass the root window explicitly since context requires it in GTK4
@@ -939,6 +2115,11 @@ impl Component for AppModel {
                 self.sync_applied = progress.changes_applied;
                 self.pending_uploads = progress.pending_uploads;
                 self.scanning_total = progress.scanning_total;
+                self.scanning_expected = progress.scanning_expected;
+                self.scanning_pages = progress.scanning_pages;
+                self.network_pause = super::network_pause_text();
+                self.backpressure = super::backpressure_text();
+                self.storage_full = self.storage_quota.is_full();
 
                 // Rebuild imperativo de los listbox dinámicos
                 if let Some(ref uploads_box) = self.uploads_listbox {
@@ -954,13 +2135,777 @@ impl Component for AppModel {
                 if let Some(ref history_box) = self.history_listbox {
                     Self::rebuild_history_listbox(history_box, &self.activity_entries);
                 }
+                // La lista de problemas sale de la DB: solo se consulta con la vista abierta
+                if self.current_view == ViewMode::Activity {
+                    _sender.input(AppMsg::LoadUploadProblems);
+                }
+                if self.current_view == ViewMode::Conflicts {
+                    _sender.input(AppMsg::LoadConflicts);
+                }
+                if self.current_view == ViewMode::Main {
+                    _sender.input(AppMsg::LoadTrash);
+                }
+            }
+            AppMsg::LoadUploadProblems => {
+                if let Some(db) = self.db.clone() {
+                    let sender_clone = _sender.clone();
+                    std::thread::spawn(move || {
+                        if let Ok(rt) = tokio::runtime::Runtime::new() {
+                            match rt.block_on(db.list_upload_problems()) {
+                                Ok(problems) => sender_clone.input(AppMsg::UploadProblemsLoaded(problems)),
+                                Err(e) => tracing::warn!("Error cargando problemas de subida: {:?}", e),
+                            }
+                        }
+                    });
+                }
+            }
+            AppMsg::UploadProblemsLoaded(problems) => {
+                self.upload_problems = problems;
+                if let Some(ref box_widget) = self.problems_listbox {
+                    Self::rebuild_problems_box(box_widget, &self.upload_problems, &_sender);
+                }
+            }
+            AppMsg::RetryUpload(inode) => {
+                if let Some(db) = self.db.clone() {
+                    let sender_clone = _sender.clone();
+                    std::thread::spawn(move || {
+                        if let Ok(rt) = tokio::runtime::Runtime::new() {
+                            if let Err(e) = rt.block_on(db.retry_upload_now(inode)) {
+                                tracing::warn!("Error reprogramando subida de inode {}: {:?}", inode, e);
+                            }
+                            sender_clone.input(AppMsg::LoadUploadProblems);
+                        }
+                    });
+                }
+            }
+            AppMsg::RetryStorageFull => {
+                let rejected = self.storage_quota.take_rejected();
+                self.storage_full = false;
+                if let Some(db) = self.db.clone() {
+                    let sender_clone = _sender.clone();
+                    std::thread::spawn(move || {
+                        if let Ok(rt) = tokio::runtime::Runtime::new() {
+                            rt.block_on(async {
+                                for inode in rejected {
+                                    if let Err(e) = db.retry_upload_now(inode).await {
+                                        tracing::warn!("Error reprogramando subida de inode {}: {:?}", inode, e);
+                                    }
+                                }
+                            });
+                            sender_clone.input(AppMsg::LoadUploadProblems);
+                        }
+                    });
+                }
+            }
+            AppMsg::LoadConflicts => {
+                if let Some(db) = self.db.clone() {
+                    let sender_clone = _sender.clone();
+                    std::thread::spawn(move || {
+                        if let Ok(rt) = tokio::runtime::Runtime::new() {
+                            match rt.block_on(db.list_conflicts()) {
+                                Ok(conflicts) => sender_clone.input(AppMsg::ConflictsLoaded(conflicts)),
+                                Err(e) => tracing::warn!("Error cargando conflictos: {:?}", e),
+                            }
+                        }
+                    });
+                }
+            }
+            AppMsg::ConflictsLoaded(conflicts) => {
+                self.conflicts = conflicts;
+                if let Some(ref box_widget) = self.conflicts_listbox {
+                    Self::rebuild_conflicts_box(box_widget, &self.conflicts, &_sender);
+                }
+            }
+            AppMsg::ResolveConflict { id, resolution } => {
+                if self.refuse_if_read_only() {
+                    return;
+                }
+                let (Some(db), Some(client)) = (self.db.clone(), self.drive_client.clone()) else {
+                    return;
+                };
+                let name = self.conflicts.iter()
+                    .find(|c| c.id == id)
+                    .map(|c| c.path.clone())
+                    .unwrap_or_default();
+                let history = self.history.clone();
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let result = crate::config::Config::load_effective().and_then(|config| {
+                            rt.block_on(crate::sync::conflicts::resolve(&db, client.as_ref(), &config.cache_dir, id, resolution))
+                        });
+                        let message = match result {
+                            Ok(()) => {
+                                use crate::sync::conflicts::ConflictResolution;
+                                let action = t!(match resolution {
+                                    ConflictResolution::KeepLocal => "gui-conflict-kept-local",
+                                    ConflictResolution::KeepRemote => "gui-conflict-kept-remote",
+                                    ConflictResolution::Dismiss => "gui-conflict-kept-both",
+                                });
+                                history.log(ActionType::Conflict, t!("activity-conflict-resolved", action = action.as_str(), name = name.as_str()));
+                                format!("{}: {}", name, action)
+                            }
+                            Err(e) => {
+                                tracing::warn!("Error resolviendo conflicto {}: {:?}", id, e);
+                                history.log(ActionType::Error, t!("activity-conflict-failed", name = name.as_str(), error = e.to_string()));
+                                t!("gui-conflict-failed", error = e.to_string())
+                            }
+                        };
+                        sender_clone.input(AppMsg::UpdateStatus(message));
+                        sender_clone.input(AppMsg::LoadConflicts);
+                    }
+                });
+            }
+            AppMsg::ShowConflictDiff(id) => {
+                let (Some(db), Some(client)) = (self.db.clone(), self.drive_client.clone()) else {
+                    return;
+                };
+                let title = self.conflicts.iter()
+                    .find(|c| c.id == id)
+                    .map(|c| c.path.clone())
+                    .unwrap_or_default();
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let text = rt.block_on(crate::sync::conflicts::diff(&db, client.as_ref(), id))
+                            .unwrap_or_else(|e| t!("gui-diff-failed", error = e.to_string()));
+                        sender_clone.input(AppMsg::ConflictDiffLoaded { title, text });
+                    }
+                });
+            }
+            AppMsg::ConflictDiffLoaded { title, text } => {
+                Self::present_conflict_diff(root, &title, &text);
+            }
+            AppMsg::ShowConflictsView => {
+                self.current_view = ViewMode::Conflicts;
+                _sender.input(AppMsg::LoadConflicts);
             }
             AppMsg::ShowActivityView => {
                 self.current_view = ViewMode::Activity;
+                _sender.input(AppMsg::LoadUploadProblems);
+            }
+            AppMsg::ShowLogsView => {
+                self.current_view = ViewMode::Logs;
+                let text = crate::utils::logging::read_recent(LOG_VIEWER_MAX_BYTES)
+                    .unwrap_or_else(|e| t!("gui-log-read-failed", error = e.to_string()));
+                if let Some(ref textview) = self.log_textview {
+                    let buffer = textview.buffer();
+                    buffer.set_text(&text);
+                    // Mostrar lo más reciente
+                    let mut end = buffer.end_iter();
+                    textview.scroll_to_iter(&mut end, 0.0, false, 0.0, 1.0);
+                }
             }
             AppMsg::ShowMainView => {
                 self.current_view = ViewMode::Main;
             }
+            AppMsg::SetBackendState(state) => {
+                match &state {
+                    BackendState::Starting { attempt } if *attempt > 1 => {
+                        self.status_message = t!("gui-reconnecting");
+                    }
+                    BackendState::Running if self.backend_state != (BackendState::Starting { attempt: 1 }) => {
+                        self.history.log(ActionType::Sync, t!("activity-backend-reconnected"));
+                    }
+                    BackendState::Retrying { attempt, retry_in, error } => {
+                        if *attempt == 1 {
+                            self.history.log(ActionType::Error, t!("activity-backend-stopped", error = error.as_str()));
+                        }
+                        self.status_message = t!("gui-backend-retry-status", seconds = retry_in.as_secs());
+                    }
+                    BackendState::Stopped { attempt, error } => {
+                        if *attempt == 1 {
+                            self.history.log(ActionType::Error, t!("activity-backend-stopped", error = error.as_str()));
+                        }
+                        self.status_message = t!("gui-backend-stopped");
+                    }
+                    BackendState::Crashed(message) => {
+                        tracing::error!("🧯 Backend detenido: {}", message);
+                        self.history.log(ActionType::Error, t!("activity-backend-stopped", error = message.as_str()));
+                        self.status_message = t!("gui-backend-stopped");
+                    }
+                    _ => {}
+                }
+                if state == BackendState::Running {
+                    self.config_problems.clear();
+                    self.mount_point_problem = None;
+                }
+                self.backend_state = state;
+            }
+            AppMsg::SetConfigProblems(problems) => {
+                if problems.is_empty() || problems == self.config_problems {
+                    return;
+                }
+                let config_path = crate::config::Config::config_path().unwrap_or_default();
+                let list = problems
+                    .iter()
+                    .map(|problem| format!("• {}", super::config_problem_text(problem)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let body = format!(
+                    "{}\n\n{}",
+                    t!("gui-config-invalid-body", path = config_path.display().to_string()),
+                    list
+                );
+                let dialog = adw::AlertDialog::new(Some(&t!("gui-config-invalid")), Some(&body));
+                dialog.add_responses(&[("close", &t!("gui-close")), ("open", &t!("gui-open-config"))]);
+                dialog.set_default_response(Some("open"));
+                dialog.set_close_response("close");
+                dialog.connect_response(Some("open"), move |_, _| {
+                    let _ = std::process::Command::new("xdg-open").arg(&config_path).spawn();
+                });
+                dialog.present(Some(root));
+                self.config_problems = problems;
+            }
+            AppMsg::MountPointNotEmpty { path, entries } => {
+                if self.mount_point_problem.as_ref() == Some(&path) {
+                    return;
+                }
+                let mut list = entries
+                    .iter()
+                    .take(MOUNT_POINT_LISTED_ENTRIES)
+                    .map(|name| format!("• {}", name))
+                    .collect::<Vec<_>>();
+                if entries.len() > MOUNT_POINT_LISTED_ENTRIES {
+                    list.push(t!("gui-mount-not-empty-more", count = entries.len() - MOUNT_POINT_LISTED_ENTRIES));
+                }
+                let body = format!(
+                    "{}\n\n{}",
+                    t!("gui-mount-not-empty-body", path = path.display().to_string(), count = entries.len()),
+                    list.join("\n")
+                );
+                let dialog = adw::AlertDialog::new(Some(&t!("gui-mount-not-empty")), Some(&body));
+                dialog.add_responses(&[
+                    ("close", &t!("gui-close")),
+                    ("choose", &t!("gui-mount-choose-other")),
+                    ("migrate", &t!("gui-mount-migrate")),
+                ]);
+                dialog.set_response_appearance("migrate", adw::ResponseAppearance::Suggested);
+                dialog.set_default_response(Some("choose"));
+                dialog.set_close_response("close");
+                let sender_choose = _sender.clone();
+                dialog.connect_response(Some("choose"), move |_, _| {
+                    sender_choose.input(AppMsg::SelectMountPoint);
+                });
+                let sender_migrate = _sender.clone();
+                dialog.connect_response(Some("migrate"), move |_, _| {
+                    sender_migrate.input(AppMsg::MigrateMountPoint);
+                });
+                dialog.present(Some(root));
+                self.mount_point_problem = Some(path);
+            }
+            AppMsg::SelectMountPoint => {
+                let dialog = gtk::FileDialog::builder()
+                    .title(t!("gui-mount-choose-other"))
+                    .build();
+                let sender_clone = _sender.clone();
+                dialog.select_folder(Some(root), gtk::gio::Cancellable::NONE, move |res| {
+                    if let Ok(folder) = res
+                        && let Some(path) = folder.path()
+                    {
+                        sender_clone.input(AppMsg::SetMountPoint(path));
+                    }
+                });
+            }
+            AppMsg::SetMountPoint(path) => {
+                // Releer antes de guardar para no pisar cambios hechos a mano en config.json
+                let result = crate::config::Config::load().and_then(|mut config| {
+                    config.fuse_mount_path = path.clone();
+                    config.save()
+                });
+                match result {
+                    Ok(()) => {
+                        tracing::info!("📁 Nuevo punto de montaje: {:?}", path);
+                        self.mount_point_problem = None;
+                        _sender.input(AppMsg::RecoverBackend);
+                    }
+                    Err(e) => {
+                        tracing::warn!("No se pudo guardar el punto de montaje: {:?}", e);
+                        self.status_message = t!("gui-mount-point-failed", error = e.to_string());
+                    }
+                }
+            }
+            AppMsg::MigrateMountPoint => {
+                crate::utils::mount::request_migration();
+                self.mount_point_problem = None;
+                _sender.input(AppMsg::RecoverBackend);
+            }
+            AppMsg::RecoverBackend => {
+                if matches!(self.backend_state, BackendState::Crashed(_)) {
+                    crate::utils::crash::restart_application();
+                }
+                if let Some(reconnect) = &self.backend_reconnect {
+                    let _ = reconnect.send(());
+                }
+            }
+            AppMsg::SelectDiagnosticsDest => {
+                let name = gtk::glib::DateTime::now_local()
+                    .and_then(|now| now.format("%Y%m%d-%H%M%S"))
+                    .map(|stamp| format!("gdrivexp-diagnostico-{}.tar.gz", stamp))
+                    .unwrap_or_else(|_| "gdrivexp-diagnostico.tar.gz".to_string());
+                let dialog = gtk::FileDialog::builder()
+                    .title(t!("gui-export-diagnostics"))
+                    .initial_name(name)
+                    .build();
+                let sender_clone = _sender.clone();
+                dialog.save(Some(root), gtk::gio::Cancellable::NONE, move |res| {
+                    if let Ok(file) = res
+                        && let Some(path) = file.path()
+                    {
+                        sender_clone.input(AppMsg::ExportDiagnostics(path));
+                    }
+                });
+            }
+            AppMsg::ExportDiagnostics(path) => {
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    let message = match crate::utils::logging::export_diagnostics(&path) {
+                        Ok(()) => t!("gui-diagnostics-saved", path = path.display().to_string()),
+                        Err(e) => {
+                            tracing::error!("Error exportando diagnóstico: {:?}", e);
+                            t!("gui-diagnostics-failed", error = e.to_string())
+                        }
+                    };
+                    sender_clone.input(AppMsg::UpdateStatus(message));
+                });
+            }
+            AppMsg::SetDriveClient(client) => {
+                self.drive_client = Some(client);
+            }
+            AppMsg::SetStorageQuota(quota) => {
+                self.storage_full = quota.is_full();
+                self.storage_quota = quota;
+            }
+            AppMsg::SelectFileToShare => {
+                let dialog = gtk::FileDialog::builder()
+                    .title(t!("gui-select-file-to-share"))
+                    .build();
+                if let Some(ref mirror) = self.mirror_path {
+                    dialog.set_initial_folder(Some(&gtk::gio::File::for_path(mirror)));
+                }
+                let sender_clone = _sender.clone();
+                dialog.open(Some(root), gtk::gio::Cancellable::NONE, move |res| {
+                    if let Ok(file) = res
+                        && let Some(path) = file.path()
+                    {
+                        sender_clone.input(AppMsg::CreateShareLink(path));
+                    }
+                });
+            }
+            AppMsg::CreateShareLink(path) => {
+                if self.refuse_if_read_only() {
+                    return;
+                }
+                let (Some(db), Some(client), Some(mirror)) =
+                    (self.db.clone(), self.drive_client.clone(), self.mirror_path.clone())
+                else {
+                    tracing::warn!("Backend no inicializado, no se puede crear enlace");
+                    return;
+                };
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let result = rt.block_on(crate::ipc::server::create_share_link(
+                            &db,
+                            Some(client.as_ref()),
+                            &mirror,
+                            &path.to_string_lossy(),
+                            "reader",
+                        ));
+                        match result {
+                            Ok(url) => sender_clone.input(AppMsg::ShareLinkCreated(url)),
+                            Err(e) => sender_clone.input(AppMsg::ShareLinkFailed(e.to_string())),
+                        }
+                    }
+                });
+            }
+            AppMsg::ShareLinkCreated(url) => {
+                root.clipboard().set_text(&url);
+                self.status_message = t!("gui-link-copied");
+                self.history.log(ActionType::Sync, t!("activity-link-shared", url = url.as_str()));
+            }
+            AppMsg::ShareLinkFailed(message) => {
+                tracing::warn!("Error creando enlace para compartir: {}", message);
+                self.status_message = t!("gui-link-failed", error = message.as_str());
+            }
+            AppMsg::SelectFileForProperties => {
+                let dialog = gtk::FileDialog::builder()
+                    .title(t!("gui-select-file"))
+                    .build();
+                if let Some(ref mirror) = self.mirror_path {
+                    dialog.set_initial_folder(Some(&gtk::gio::File::for_path(mirror)));
+                }
+                let sender_clone = _sender.clone();
+                dialog.open(Some(root), gtk::gio::Cancellable::NONE, move |res| {
+                    if let Ok(file) = res
+                        && let Some(path) = file.path()
+                    {
+                        sender_clone.input(AppMsg::ShowFileProperties(path));
+                    }
+                });
+            }
+            AppMsg::ShowFileProperties(path) => {
+                let (Some(db), Some(client), Some(mirror)) =
+                    (self.db.clone(), self.drive_client.clone(), self.mirror_path.clone())
+                else {
+                    tracing::warn!("Backend no inicializado, no se pueden consultar versiones");
+                    return;
+                };
+                let ipc_context = self.ipc_context.clone();
+                self.status_message = t!("gui-loading-versions");
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let file_path = path.to_string_lossy().into_owned();
+                        let details = ipc_context.and_then(|ctx| {
+                            rt.block_on(crate::ipc::server::file_details(&ctx, &file_path))
+                                .inspect_err(|e| tracing::warn!("Sin detalles de {:?}: {:?}", path, e))
+                                .ok()
+                        });
+                        let result = rt.block_on(crate::ipc::server::list_revisions(
+                            &db,
+                            Some(client.as_ref()),
+                            &mirror,
+                            &file_path,
+                        ));
+                        let starred = rt.block_on(crate::ipc::server::is_starred(&db, &mirror, &file_path))
+                            .unwrap_or(false);
+                        // Las carpetas no tienen versiones: basta con sus detalles
+                        let is_dir = details.as_ref().is_some_and(|d| d.is_dir);
+                        match result {
+                            Ok(revisions) => sender_clone.input(AppMsg::FilePropertiesLoaded { path, details, revisions, starred }),
+                            Err(_) if is_dir => sender_clone.input(AppMsg::FilePropertiesLoaded { path, details, revisions: Vec::new(), starred }),
+                            Err(e) => sender_clone.input(AppMsg::UpdateStatus(t!("gui-versions-failed", error = e.to_string()))),
+                        }
+                    }
+                });
+            }
+            AppMsg::FilePropertiesLoaded { path, details, revisions, starred } => {
+                self.status_message = t!("gui-versions-count", count = revisions.len());
+                Self::present_file_properties(root, &path, details.as_ref(), &revisions, starred, &_sender);
+            }
+            AppMsg::SetIpcContext(ctx) => {
+                self.ipc_context = Some(ctx);
+            }
+            AppMsg::SetCacheManager(manager) => {
+                self.cache_manager = Some(manager);
+                _sender.input(AppMsg::LoadCacheUsage);
+            }
+            AppMsg::LoadCacheUsage => {
+                let Some(manager) = self.cache_manager.clone() else {
+                    return;
+                };
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        match rt.block_on(manager.usage()) {
+                            Ok(usage) => sender_clone.input(AppMsg::CacheUsageLoaded(usage)),
+                            Err(e) => tracing::warn!("No se pudo consultar el uso de caché: {:?}", e),
+                        }
+                    }
+                });
+            }
+            AppMsg::CacheUsageLoaded(usage) => {
+                if let Some(ref box_widget) = self.cache_listbox {
+                    Self::rebuild_cache_box(box_widget, &usage, &_sender);
+                }
+            }
+            AppMsg::ConfirmClearCache => {
+                let dialog = adw::AlertDialog::new(Some(&t!("gui-cache-clear-confirm")), Some(&t!("gui-cache-clear-confirm-body")));
+                dialog.add_responses(&[("cancel", &t!("gui-cancel")), ("clear", &t!("gui-cache-clear"))]);
+                dialog.set_response_appearance("clear", adw::ResponseAppearance::Destructive);
+                dialog.set_default_response(Some("cancel"));
+                dialog.set_close_response("cancel");
+                let sender_clone = _sender.clone();
+                dialog.connect_response(Some("clear"), move |_, _| {
+                    sender_clone.input(AppMsg::ClearCache);
+                });
+                dialog.present(Some(root));
+            }
+            AppMsg::LoadTrash => {
+                if let Some(db) = self.db.clone() {
+                    let sender_clone = _sender.clone();
+                    std::thread::spawn(move || {
+                        if let Ok(rt) = tokio::runtime::Runtime::new() {
+                            match rt.block_on(crate::ipc::server::list_trash(&db)) {
+                                Ok(items) => sender_clone.input(AppMsg::TrashLoaded(items)),
+                                Err(e) => tracing::warn!("Error cargando la papelera local: {:?}", e),
+                            }
+                        }
+                    });
+                }
+            }
+            AppMsg::TrashLoaded(items) => {
+                // Se consulta en cada refresco: no reconstruir la lista si no cambió
+                if items == self.trash_items {
+                    return;
+                }
+                self.trash_items = items;
+                if let Some(ref box_widget) = self.trash_listbox {
+                    Self::rebuild_trash_box(box_widget, &self.trash_items, &_sender);
+                }
+            }
+            AppMsg::ConfirmDeletePermanently(item) => {
+                let dialog = adw::AlertDialog::new(
+                    Some(&t!("gui-delete-permanently-confirm", name = item.name.as_str())),
+                    Some(&t!("gui-delete-permanently-confirm-body")),
+                );
+                dialog.add_responses(&[("cancel", &t!("gui-cancel")), ("delete", &t!("gui-delete-permanently"))]);
+                dialog.set_response_appearance("delete", adw::ResponseAppearance::Destructive);
+                dialog.set_default_response(Some("cancel"));
+                dialog.set_close_response("cancel");
+                let sender_clone = _sender.clone();
+                dialog.connect_response(Some("delete"), move |_, _| {
+                    sender_clone.input(AppMsg::DeletePermanently(item.clone()));
+                });
+                dialog.present(Some(root));
+            }
+            AppMsg::DeletePermanently(item) => {
+                if self.refuse_if_read_only() {
+                    return;
+                }
+                let Some(db) = self.db.clone() else {
+                    return;
+                };
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let message = match rt.block_on(crate::ipc::server::delete_permanently(&db, item.inode)) {
+                            Ok(()) => t!("gui-delete-permanently-queued", name = item.name.as_str()),
+                            Err(e) => t!("gui-delete-permanently-failed", error = e.to_string()),
+                        };
+                        sender_clone.input(AppMsg::UpdateStatus(message));
+                        sender_clone.input(AppMsg::LoadTrash);
+                    }
+                });
+            }
+            AppMsg::EvictUnpinnedCache | AppMsg::ClearCache => {
+                let Some(manager) = self.cache_manager.clone() else {
+                    return;
+                };
+                let clear_all = matches!(msg, AppMsg::ClearCache);
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let result = if clear_all {
+                            rt.block_on(manager.clear())
+                        } else {
+                            rt.block_on(manager.evict_unpinned())
+                        };
+                        match result {
+                            Ok(report) => sender_clone.input(AppMsg::CacheFreed(report)),
+                            Err(e) => sender_clone.input(AppMsg::UpdateStatus(t!("gui-cache-failed", error = e.to_string()))),
+                        }
+                    }
+                });
+            }
+            AppMsg::CacheFreed(report) => {
+                self.status_message = t!(
+                    "gui-cache-freed",
+                    size = gtk::glib::format_size(report.bytes).to_string(),
+                    kept = report.skipped
+                );
+                _sender.input(AppMsg::LoadCacheUsage);
+            }
+            AppMsg::OpenInBrowser(path) => {
+                let Some(ctx) = self.ipc_context.clone() else {
+                    return;
+                };
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let request = crate::ipc::IpcRequest::GetWebLink { path: path.to_string_lossy().into_owned() };
+                        match rt.block_on(crate::ipc::server::process_request(&ctx, request)) {
+                            crate::ipc::IpcResponse::Link { url } => {
+                                let _ = std::process::Command::new("xdg-open").arg(&url).spawn();
+                            }
+                            crate::ipc::IpcResponse::Error { message } => {
+                                sender_clone.input(AppMsg::UpdateStatus(t!("gui-browser-failed", error = message.as_str())));
+                            }
+                            other => tracing::warn!("Respuesta inesperada a GetWebLink: {:?}", other),
+                        }
+                    }
+                });
+            }
+            AppMsg::PinOffline(path) => {
+                let Some(ctx) = self.ipc_context.clone() else {
+                    return;
+                };
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        // El MirrorManager registra la descarga en el historial
+                        let request = crate::ipc::IpcRequest::SetLocalOnline { path: path.to_string_lossy().into_owned() };
+                        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                        let message = match rt.block_on(crate::ipc::server::process_request(&ctx, request)) {
+                            crate::ipc::IpcResponse::Error { message } => t!("gui-pin-failed", error = message.as_str()),
+                            _ => t!("gui-pinned-offline", name = name.as_str()),
+                        };
+                        sender_clone.input(AppMsg::UpdateStatus(message));
+                    }
+                });
+            }
+            AppMsg::FreeUpSpace(path) => {
+                let Some(ctx) = self.ipc_context.clone() else {
+                    return;
+                };
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        // El espejo vuelve a symlinks y se descarta la caché de FUSE
+                        let request = crate::ipc::IpcRequest::SetOnlineOnly { path: path.to_string_lossy().into_owned() };
+                        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                        let message = match rt.block_on(crate::ipc::server::process_request(&ctx, request)) {
+                            crate::ipc::IpcResponse::Error { message } => t!("gui-free-failed", error = message.as_str()),
+                            _ => t!("gui-freed-space", name = name.as_str()),
+                        };
+                        sender_clone.input(AppMsg::UpdateStatus(message));
+                        sender_clone.input(AppMsg::LoadCacheUsage);
+                    }
+                });
+            }
+            AppMsg::SetStarred { path, starred } => {
+                if self.refuse_if_read_only() {
+                    return;
+                }
+                let (Some(db), Some(client), Some(mirror)) =
+                    (self.db.clone(), self.drive_client.clone(), self.mirror_path.clone())
+                else {
+                    return;
+                };
+                let history = self.history.clone();
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let result = rt.block_on(crate::ipc::server::set_starred(
+                            &db,
+                            Some(client.as_ref()),
+                            &mirror,
+                            &path.to_string_lossy(),
+                            starred,
+                        ));
+                        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                        let message = match result {
+                            Ok(()) if starred => {
+                                history.log(ActionType::Sync, t!("activity-starred", name = name.as_str()));
+                                t!("gui-starred-added", name = name.as_str())
+                            }
+                            Ok(()) => {
+                                history.log(ActionType::Sync, t!("activity-unstarred", name = name.as_str()));
+                                t!("gui-starred-removed", name = name.as_str())
+                            }
+                            Err(e) => {
+                                tracing::warn!("Error cambiando destacado de {:?}: {:?}", path, e);
+                                t!("gui-starred-failed", error = e.to_string())
+                            }
+                        };
+                        sender_clone.input(AppMsg::UpdateStatus(message));
+                    }
+                });
+            }
+            AppMsg::SetDescription { path, description } => {
+                if self.refuse_if_read_only() {
+                    return;
+                }
+                let (Some(db), Some(client), Some(mirror)) =
+                    (self.db.clone(), self.drive_client.clone(), self.mirror_path.clone())
+                else {
+                    return;
+                };
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let result = rt.block_on(crate::ipc::server::set_description(
+                            &db,
+                            Some(client.as_ref()),
+                            &mirror,
+                            &path.to_string_lossy(),
+                            Some(&description),
+                        ));
+                        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                        let message = match result {
+                            Ok(()) => t!("gui-description-saved", name = name.as_str()),
+                            Err(e) => {
+                                tracing::warn!("Error cambiando la descripción de {:?}: {:?}", path, e);
+                                t!("gui-description-failed", error = e.to_string())
+                            }
+                        };
+                        sender_clone.input(AppMsg::UpdateStatus(message));
+                    }
+                });
+            }
+            AppMsg::RestoreRevision { path, revision_id, as_copy } => {
+                if self.refuse_if_read_only() {
+                    return;
+                }
+                let (Some(db), Some(client), Some(mirror)) =
+                    (self.db.clone(), self.drive_client.clone(), self.mirror_path.clone())
+                else {
+                    return;
+                };
+                let history = self.history.clone();
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        let result = rt.block_on(crate::ipc::server::restore_revision(
+                            &db,
+                            Some(client.as_ref()),
+                            &mirror,
+                            &path.to_string_lossy(),
+                            &revision_id,
+                            as_copy,
+                        ));
+                        let message = match result {
+                            Ok(dest) => {
+                                let name = dest.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                                history.log(ActionType::Sync, t!("activity-version-restored", name = name.as_str()));
+                                t!("gui-version-restored", name = name.as_str())
+                            }
+                            Err(e) => {
+                                tracing::warn!("Error recuperando versión de {:?}: {:?}", path, e);
+                                t!("gui-version-restore-failed", error = e.to_string())
+                            }
+                        };
+                        sender_clone.input(AppMsg::UpdateStatus(message));
+                    }
+                });
+            }
+            AppMsg::Search(query) => {
+                self.search_query = query.clone();
+                if query.trim().is_empty() {
+                    if let Some(ref box_widget) = self.search_results_listbox {
+                        Self::rebuild_search_results_box(box_widget, &query, &[], None, &_sender);
+                    }
+                    return;
+                }
+                let (Some(db), Some(mirror)) = (self.db.clone(), self.mirror_path.clone()) else {
+                    return;
+                };
+                let sender_clone = _sender.clone();
+                std::thread::spawn(move || {
+                    if let Ok(rt) = tokio::runtime::Runtime::new() {
+                        match rt.block_on(crate::ipc::server::search_files(&db, &mirror, &query)) {
+                            Ok(results) => sender_clone.input(AppMsg::SearchResultsLoaded { query, results }),
+                            Err(e) => tracing::warn!("Error buscando '{}': {:?}", query, e),
+                        }
+                    }
+                });
+            }
+            AppMsg::SearchResultsLoaded { query, results } => {
+                // Descartar resultados de consultas ya reemplazadas por otra
+                if query != self.search_query {
+                    return;
+                }
+                if let Some(ref box_widget) = self.search_results_listbox {
+                    Self::rebuild_search_results_box(box_widget, &query, &results, self.mirror_path.as_deref(), &_sender);
+                }
+            }
+            AppMsg::OpenSearchResult(path) => {
+                // xdg-open abre carpetas en Archivos y archivos con su aplicación predeterminada
+                let _ = std::process::Command::new("xdg-open")
+                    .arg(&path)
+                    .spawn();
+            }
         }
     }
 }
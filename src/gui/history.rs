@@ -4,10 +4,13 @@
 //! También rastrea transfers activos (uploads/downloads) con progreso.
 
 use std::collections::{VecDeque, HashMap};
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock, mpsc};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::SystemTime;
 
+use crate::i18n::t;
+
 /// Número máximo de entradas en el historial
 const MAX_HISTORY_ENTRIES: usize = 50;
 
@@ -90,6 +93,12 @@ pub struct SyncProgress {
     pub pending_uploads: usize,
     /// Total de archivos escaneados (0 = no hay escaneo en curso)
     pub scanning_total: usize,
+    /// Total estimado de archivos del escaneo en curso (0 = desconocido)
+    pub scanning_expected: usize,
+    /// Páginas de `files.list` leídas en el escaneo en curso
+    pub scanning_pages: usize,
+    /// Archivos cuya última subida falló (`sync_state.status = 'error'`)
+    pub upload_errors: usize,
 }
 
 impl SyncProgress {
@@ -104,6 +113,8 @@ pub struct ActionEntry {
     pub timestamp: SystemTime,
     pub action_type: ActionType,
     pub description: String,
+    /// Archivo afectado en el espejo (la bandeja lo abre al pulsar la entrada)
+    pub path: Option<PathBuf>,
 }
 
 impl ActionEntry {
@@ -112,18 +123,25 @@ impl ActionEntry {
             timestamp: SystemTime::now(),
             action_type,
             description: description.into(),
+            path: None,
         }
     }
 
+    /// Asocia la entrada al archivo afectado
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
     /// Formatea la entrada para mostrar en el menú del tray
     pub fn format_for_menu(&self) -> String {
         let elapsed = self.timestamp.elapsed().unwrap_or_default();
         let time_str = if elapsed.as_secs() < 60 {
-            "ahora".to_string()
+            t!("time-now")
         } else if elapsed.as_secs() < 3600 {
-            format!("{}m", elapsed.as_secs() / 60)
+            t!("time-minutes-short", count = elapsed.as_secs() / 60)
         } else {
-            format!("{}h", elapsed.as_secs() / 3600)
+            t!("time-hours-short", count = elapsed.as_secs() / 3600)
         };
 
         format!(
@@ -191,6 +209,15 @@ impl ActionHistory {
         self.push(ActionEntry::new(action_type, description));
     }
 
+    /// Como `log`, indicando el archivo afectado (`None` = entrada sin archivo)
+    pub fn log_file(&self, action_type: ActionType, description: impl Into<String>, path: Option<PathBuf>) {
+        let entry = ActionEntry::new(action_type, description);
+        self.push(match path {
+            Some(path) => entry.with_path(path),
+            None => entry,
+        });
+    }
+
     /// Obtiene las N entradas más recientes
     pub fn recent(&self, count: usize) -> Vec<ActionEntry> {
         if let Ok(entries) = self.entries.read() {
@@ -361,6 +388,35 @@ impl ActionHistory {
         }
     }
 
+    /// Actualiza el conteo de archivos con la subida en error
+    pub fn set_upload_errors(&self, count: usize) {
+        let changed = if let Ok(mut progress) = self.sync_progress.write() {
+            let changed = progress.upload_errors != count;
+            progress.upload_errors = count;
+            changed
+        } else {
+            false
+        };
+
+        if changed {
+            self.notify_change();
+        }
+    }
+
+    /// Fija el total estimado de archivos del escaneo (0 = desconocido)
+    pub fn set_scanning_expected(&self, count: usize) {
+        if let Ok(mut progress) = self.sync_progress.write() {
+            progress.scanning_expected = count;
+        }
+    }
+
+    /// Fija las páginas leídas del escaneo (se notifica con `set_scanning_total`)
+    pub fn set_scanning_pages(&self, pages: usize) {
+        if let Ok(mut progress) = self.sync_progress.write() {
+            progress.scanning_pages = pages;
+        }
+    }
+
     /// Actualiza el total de archivos escaneados (0 = escaneo finalizado)
     pub fn set_scanning_total(&self, count: usize) {
         let changed = if let Ok(mut progress) = self.sync_progress.write() {
@@ -455,7 +511,7 @@ mod tests {
         #[case] applied: usize,
         #[case] expected: bool,
     ) {
-        let p = SyncProgress { changes_detected: detected, changes_applied: applied, pending_uploads: 0, scanning_total: 0 };
+        let p = SyncProgress { changes_detected: detected, changes_applied: applied, pending_uploads: 0, scanning_total: 0, scanning_expected: 0, scanning_pages: 0, upload_errors: 0 };
         assert_eq!(p.is_synced(), expected);
     }
 
@@ -467,7 +523,7 @@ mod tests {
         let formatted = entry.format_for_menu();
         assert!(formatted.contains("📤"));
         assert!(formatted.contains("archivo.txt subido"));
-        assert!(formatted.contains("ahora"));
+        assert!(formatted.contains("now"));
     }
 
     // --- ActionHistory: push y recent ---
@@ -484,6 +540,16 @@ mod tests {
         assert_eq!(recent[1].description, "upload 1");
     }
 
+    #[rstest]
+    fn test_log_file_keeps_path(history: ActionHistory) {
+        history.log_file(ActionType::Upload, "Subido: a.txt", Some(PathBuf::from("/espejo/a.txt")));
+        history.log(ActionType::Sync, "sin archivo");
+
+        let recent = history.recent(2);
+        assert_eq!(recent[0].path, None);
+        assert_eq!(recent[1].path.as_deref(), Some(std::path::Path::new("/espejo/a.txt")));
+    }
+
     #[rstest]
     fn test_history_max_capacity(history: ActionHistory) {
         for i in 0..60 {
@@ -557,6 +623,16 @@ mod tests {
         assert_eq!(p.pending_uploads, 5);
     }
 
+    #[rstest]
+    fn test_set_upload_errors_notifies_only_on_change(history: ActionHistory) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        history.set_notifier(tx);
+        history.set_upload_errors(2);
+        history.set_upload_errors(2);
+        assert_eq!(history.get_sync_progress().upload_errors, 2);
+        assert_eq!(rx.try_iter().count(), 1);
+    }
+
     // --- Notifier ---
 
     #[rstest]
@@ -1,3 +1,56 @@
 pub mod app_model;
 pub mod history;
 pub mod tray;
+
+/// Motivo de pausa automática por la red (`utils::network::pause_reason`), traducido
+pub fn network_pause_text() -> Option<String> {
+    crate::utils::network::pause_reason().map(|_| {
+        if crate::utils::network::is_online() {
+            crate::i18n::tr("network-metered")
+        } else {
+            crate::i18n::tr("network-offline")
+        }
+    })
+}
+
+/// Motivo por el que FUSE frena las escrituras (`utils::backpressure`), traducido
+pub fn backpressure_text() -> Option<String> {
+    use crate::utils::backpressure::Pressure;
+    crate::utils::backpressure::current().map(|pressure| match pressure {
+        Pressure::DirtyBacklog => crate::i18n::tr("backpressure-backlog"),
+        Pressure::LowDiskSpace => crate::i18n::tr("backpressure-disk"),
+    })
+}
+
+/// Texto traducido de un problema de configuración (`Config::validate`)
+pub fn config_problem_text(problem: &crate::config::ConfigProblem) -> String {
+    use crate::config::{ConfigProblem, MIN_CACHE_SIZE_MB};
+    use crate::i18n::t;
+    let display = |path: &std::path::Path| path.display().to_string();
+    match problem {
+        ConfigProblem::Unreadable { path, error } => {
+            t!("config-problem-unreadable", path = display(path), error = error.as_str())
+        }
+        ConfigProblem::NotADirectory { field, path } => {
+            t!("config-problem-not-a-directory", field = *field, path = display(path))
+        }
+        ConfigProblem::NotWritable { field, path } => {
+            t!("config-problem-not-writable", field = *field, path = display(path))
+        }
+        ConfigProblem::Nested { field, path, outer } => {
+            t!("config-problem-nested", field = *field, path = display(path), outer = *outer)
+        }
+        ConfigProblem::ZeroSyncInterval => t!("config-problem-zero-interval"),
+        ConfigProblem::CacheTooSmall { mb } => t!("config-problem-cache-too-small", mb = *mb, min = MIN_CACHE_SIZE_MB),
+        ConfigProblem::PrefetchExceedsCache { prefetch_mb, cache_mb } => {
+            t!("config-problem-prefetch-exceeds-cache", prefetch = *prefetch_mb, cache = *cache_mb)
+        }
+        ConfigProblem::InvalidIgnorePattern { pattern, .. } => {
+            t!("config-problem-invalid-ignore-pattern", pattern = pattern.as_str())
+        }
+        ConfigProblem::InvalidSyncSchedule { .. } => t!("config-problem-invalid-sync-schedule"),
+        ConfigProblem::MetricsNotLoopback { addr } => {
+            t!("config-problem-metrics-not-loopback", addr = addr.to_string())
+        }
+    }
+}
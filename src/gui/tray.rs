@@ -1,23 +1,117 @@
 //! Icono de bandeja del sistema usando StatusNotifierItem (ksni)
 //!
 //! Muestra el historial de acciones recientes y permite controlar la aplicación.
+//! El tooltip y la cabecera del menú resumen lo pendiente ("3 archivos
+//! pendientes · subiendo informe.pdf 45%", textos de `i18n`) y el icono superpuesto indica si
+//! está sincronizando o si hay subidas en error.
 
 use ksni::{menu::*, Tray, TrayService, ToolTip};
+use std::path::PathBuf;
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 
-use super::history::{ActionHistory, TransferOp};
+use super::history::{ActionHistory, ActiveTransfer, SyncProgress, TransferOp};
+use crate::i18n::t;
 
+/// Entradas del historial en el submenú "Actividad reciente"
+const RECENT_MENU_ENTRIES: usize = 8;
 
+/// Estado mostrado por el icono de la bandeja
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrayState {
+    Idle,
+    Syncing,
+    /// Hay archivos con la subida en error (prioridad sobre `Syncing`)
+    Error,
+}
+
+impl TrayState {
+    fn from_progress(progress: &SyncProgress, transfers: &[ActiveTransfer]) -> Self {
+        if progress.upload_errors > 0 {
+            TrayState::Error
+        } else if progress.scanning_total > 0
+            || progress.pending_uploads > 0
+            || !progress.is_synced()
+            || transfers.iter().any(|t| t.operation != TransferOp::Stream)
+        {
+            TrayState::Syncing
+        } else {
+            TrayState::Idle
+        }
+    }
+
+    /// Icono superpuesto al de la aplicación (vacío = ninguno)
+    fn overlay_icon_name(&self) -> &'static str {
+        match self {
+            TrayState::Idle => "",
+            TrayState::Syncing => "emblem-synchronizing-symbolic",
+            TrayState::Error => "dialog-warning-symbolic",
+        }
+    }
+}
+
+/// Resumen de una línea: pendientes, transferencia en curso y errores.
+/// `None` si no hay nada pendiente.
+fn sync_summary(progress: &SyncProgress, transfers: &[ActiveTransfer]) -> Option<String> {
+    let mut parts = Vec::new();
+
+    if progress.pending_uploads > 0 {
+        parts.push(t!("tray-pending-files", count = progress.pending_uploads));
+    }
+    if !progress.is_synced() {
+        parts.push(t!("tray-changes-applied", applied = progress.changes_applied, detected = progress.changes_detected));
+    }
+
+    // La transferencia más reciente representa la actividad en curso
+    let mut active: Vec<&ActiveTransfer> = transfers.iter().filter(|t| t.operation != TransferOp::Stream).collect();
+    active.sort_by_key(|t| t.id);
+    if let Some(current) = active.last() {
+        let id = if current.operation == TransferOp::Upload { "tray-uploading" } else { "tray-downloading" };
+        let percent = (current.progress_fraction() * 100.0).round() as u32;
+        let mut text = t!(id, name = current.file_name.as_str(), percent = percent);
+        if active.len() > 1 {
+            text.push_str(&format!(" (+{})", active.len() - 1));
+        }
+        parts.push(text);
+    }
+
+    if progress.upload_errors > 0 {
+        parts.push(t!("tray-upload-errors", count = progress.upload_errors));
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" · "))
+    }
+}
 
 /// Servicio del icono de bandeja
 pub struct TrayIcon {
     history: ActionHistory,
     sync_paused: Arc<AtomicBool>,
+    mirror_path: PathBuf,
+    fuse_mount_path: PathBuf,
 }
 
 impl TrayIcon {
     pub fn new(history: ActionHistory, sync_paused: Arc<AtomicBool>) -> Self {
-        Self { history, sync_paused }
+        // Rutas por defecto hasta que se indique la configuración real
+        let base = dirs::home_dir()
+            .map(|h| h.join("GoogleDrive"))
+            .unwrap_or_else(|| PathBuf::from("/tmp/GoogleDrive"));
+        Self {
+            history,
+            sync_paused,
+            fuse_mount_path: base.join("FUSE_Mount"),
+            mirror_path: base,
+        }
+    }
+
+    /// Usa el espejo y el punto de montaje configurados
+    pub fn with_config(mut self, config: &crate::config::Config) -> Self {
+        self.mirror_path = config.mirror_path.clone();
+        self.fuse_mount_path = config.fuse_mount_path.clone();
+        self
     }
 
     /// Inicia el servicio del icono de bandeja en un thread separado
@@ -27,6 +121,8 @@ impl TrayIcon {
             let service = TrayService::new(GDriveXPTray {
                 history: self.history,
                 sync_paused: self.sync_paused,
+                mirror_path: self.mirror_path,
+                fuse_mount_path: self.fuse_mount_path,
             });
 
             // Obtener handle para forzar actualizaciones del menú
@@ -55,6 +151,15 @@ impl TrayIcon {
 struct GDriveXPTray {
     history: ActionHistory,
     sync_paused: Arc<AtomicBool>,
+    mirror_path: PathBuf,
+    fuse_mount_path: PathBuf,
+}
+
+/// Abre un archivo o carpeta con la aplicación predeterminada
+fn open_path(path: &std::path::Path) {
+    if let Err(e) = std::process::Command::new("xdg-open").arg(path).spawn() {
+        tracing::warn!("No se pudo abrir {:?}: {}", path, e);
+    }
 }
 
 impl Tray for GDriveXPTray {
@@ -73,22 +178,36 @@ impl Tray for GDriveXPTray {
             .unwrap_or_default()
     }
 
+    fn overlay_icon_name(&self) -> String {
+        let progress = self.history.get_sync_progress();
+        TrayState::from_progress(&progress, &self.history.active_transfers())
+            .overlay_icon_name()
+            .to_string()
+    }
+
     fn title(&self) -> String {
         "G-DriveXP".to_string()
     }
 
     fn tool_tip(&self) -> ToolTip {
+        let progress = self.history.get_sync_progress();
+        let summary = sync_summary(&progress, &self.history.active_transfers())
+            .unwrap_or_else(|| t!("tray-all-good"));
         let status = if self.sync_paused.load(Ordering::Relaxed) {
-            "Sincronización pausada"
+            format!("{}\n{}", t!("sync-paused"), summary)
+        } else if let Some(reason) = super::backpressure_text() {
+            format!("{}\n{}", t!("backpressure-writes-held", reason = reason), summary)
+        } else if let Some(reason) = super::network_pause_text() {
+            format!("{}\n{}", t!("network-uploads-paused", reason = reason), summary)
         } else {
-            "Sincronizando"
+            summary
         };
 
         ToolTip {
             icon_name: self.icon_name(),
             icon_pixmap: Vec::new(),
             title: "G-DriveXP".to_string(),
-            description: status.to_string(),
+            description: status,
         }
     }
 
@@ -98,54 +217,70 @@ impl Tray for GDriveXPTray {
         let progress = self.history.get_sync_progress();
         let active_transfers = self.history.active_transfers();
 
-        let has_pending_downloads = progress.changes_detected != progress.changes_applied;
-        let has_pending_uploads = progress.pending_uploads > 0;
-
-        let has_active_real_transfers = active_transfers.iter().any(|t| t.operation != TransferOp::Stream);
-
-        // Determinar estado de sincronización
-        if has_active_real_transfers || has_pending_downloads || has_pending_uploads {
+        // Pausa automática por la red (sin conexión o conexión medida)
+        if let Some(reason) = super::network_pause_text() {
             items.push(StandardItem {
-                label: "🔄 Syncronizando".to_string(),
+                label: format!("⏸️ {}", t!("network-uploads-paused", reason = reason)),
                 enabled: false,
                 ..Default::default()
             }.into());
+        }
 
-            if has_pending_downloads {
-                items.push(StandardItem {
-                    label: format!("Descargas pendientes: {}/{}", progress.changes_applied, progress.changes_detected),
-                    enabled: false,
-                    ..Default::default()
-                }.into());
-            }
-            if has_pending_uploads {
-                items.push(StandardItem {
-                    label: format!("Subidas/Cambios pendientes: {}", progress.pending_uploads),
-                    enabled: false,
-                    ..Default::default()
-                }.into());
-            }
-            let non_stream_transfers: Vec<_> = active_transfers.iter().filter(|t| t.operation != TransferOp::Stream).collect();
-            if !non_stream_transfers.is_empty() {
-                items.push(StandardItem {
-                    label: format!("{} transferencias activas", non_stream_transfers.len()),
-                    enabled: false,
-                    ..Default::default()
-                }.into());
-            }
-        } else {
+        // Escrituras locales frenadas por contrapresión
+        if let Some(reason) = super::backpressure_text() {
             items.push(StandardItem {
-                label: "Todo en Orden".to_string(),
+                label: format!("🚦 {}", t!("backpressure-writes-held", reason = reason)),
                 enabled: false,
                 ..Default::default()
             }.into());
         }
 
+        // Cabecera: el mismo resumen que el tooltip
+        let label = match (TrayState::from_progress(&progress, &active_transfers), sync_summary(&progress, &active_transfers)) {
+            (TrayState::Error, Some(summary)) => format!("⚠️ {}", summary),
+            (_, Some(summary)) => format!("🔄 {}", summary),
+            (_, None) => t!("tray-all-good"),
+        };
+        items.push(StandardItem {
+            label,
+            enabled: false,
+            ..Default::default()
+        }.into());
+
+        // Actividad reciente: las entradas con archivo lo abren al pulsarlas
+        let recent = self.history.recent(RECENT_MENU_ENTRIES);
+        if !recent.is_empty() {
+            let submenu = recent
+                .into_iter()
+                .map(|entry| {
+                    let label = entry.format_for_menu();
+                    match entry.path.filter(|path| path.exists()) {
+                        Some(path) => StandardItem {
+                            label,
+                            activate: Box::new(move |_: &mut Self| open_path(&path)),
+                            ..Default::default()
+                        },
+                        None => StandardItem {
+                            label,
+                            enabled: false,
+                            ..Default::default()
+                        },
+                    }
+                    .into()
+                })
+                .collect();
+            items.push(SubMenu {
+                label: t!("tray-recent-activity"),
+                submenu,
+                ..Default::default()
+            }.into());
+        }
+
         items.push(MenuItem::Separator);
 
         // Abrir panel principal
         items.push(StandardItem {
-            label: "Abrir Panel".to_string(),
+            label: t!("tray-open-panel"),
             activate: Box::new(|_| {
                 // Enviar señal D-Bus para activar la ventana GTK
                 // Por ahora usamos xdg-open como fallback
@@ -162,26 +297,27 @@ impl Tray for GDriveXPTray {
             ..Default::default()
         }.into());
 
-        // Abrir en Archivos
+        // Abrir en Archivos (el espejo configurado, igual que la ventana principal)
         items.push(StandardItem {
-            label: "Abrir en Archivos".to_string(),
-            activate: Box::new(|_| {
-                let mount_point = dirs::home_dir()
-                    .map(|h| h.join("GoogleDrive"))
-                    .unwrap_or_else(|| std::path::PathBuf::from("/tmp/GoogleDrive"));
-                let _ = std::process::Command::new("xdg-open")
-                    .arg(mount_point)
-                    .spawn();
-            }),
+            label: t!("open-in-files"),
+            activate: Box::new(|this: &mut Self| open_path(&this.mirror_path)),
             ..Default::default()
         }.into());
 
         items.push(MenuItem::Separator);
 
+        // Ciclo inmediato de syncer y uploader
+        items.push(StandardItem {
+            label: t!("tray-sync-now"),
+            enabled: !self.sync_paused.load(Ordering::Relaxed),
+            activate: Box::new(|_| crate::sync::trigger::request_sync_now()),
+            ..Default::default()
+        }.into());
+
         // Pausar/Reanudar sincronización
         let is_paused = self.sync_paused.load(Ordering::Relaxed);
         items.push(CheckmarkItem {
-            label: "Pausar sincronización".to_string(),
+            label: t!("pause-sync"),
             checked: is_paused,
             activate: Box::new(|this: &mut Self| {
                 let current = this.sync_paused.load(Ordering::Relaxed);
@@ -199,14 +335,11 @@ impl Tray for GDriveXPTray {
 
         // Salir
         items.push(StandardItem {
-            label: "Salir".to_string(),
-            activate: Box::new(|_| {
+            label: t!("tray-quit"),
+            activate: Box::new(|this: &mut Self| {
                 tracing::info!("👋 Cerrando aplicación desde bandeja...");
                 // Desmontar FUSE antes de salir para evitar zombie del kernel
-                let fuse_path = dirs::home_dir()
-                    .map(|h| h.join("GoogleDrive/FUSE_Mount"))
-                    .unwrap_or_else(|| std::path::PathBuf::from("/tmp/GoogleDrive/FUSE_Mount"));
-                let _ = crate::utils::mount::unmount_and_wait(&fuse_path);
+                let _ = crate::utils::mount::unmount_and_wait(&this.fuse_mount_path);
                 std::process::exit(0);
             }),
             ..Default::default()
@@ -215,3 +348,53 @@ impl Tray for GDriveXPTray {
         items
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn transfer(id: u64, name: &str, operation: TransferOp, done: u64, total: u64) -> ActiveTransfer {
+        ActiveTransfer {
+            id,
+            file_name: name.to_string(),
+            operation,
+            bytes_transferred: done,
+            total_bytes: total,
+            speed_bps: 0,
+            last_update: None,
+        }
+    }
+
+    #[rstest]
+    #[case::idle(0, 0, vec![], None, TrayState::Idle)]
+    #[case::single_pending(1, 0, vec![], Some("1 file pending"), TrayState::Syncing)]
+    #[case::uploading(
+        3, 0,
+        vec![transfer(1, "informe.pdf", TransferOp::Upload, 45, 100)],
+        Some("3 files pending · uploading informe.pdf 45%"),
+        TrayState::Syncing,
+    )]
+    #[case::latest_transfer_wins(
+        0, 0,
+        vec![
+            transfer(2, "b.txt", TransferOp::Download, 1, 4),
+            transfer(1, "a.txt", TransferOp::Upload, 0, 4),
+            transfer(3, "video.mp4", TransferOp::Stream, 0, 4),
+        ],
+        Some("downloading b.txt 25% (+1)"),
+        TrayState::Syncing,
+    )]
+    #[case::errors(2, 2, vec![], Some("2 files pending · 2 with errors"), TrayState::Error)]
+    fn test_sync_summary_and_state(
+        #[case] pending_uploads: usize,
+        #[case] upload_errors: usize,
+        #[case] transfers: Vec<ActiveTransfer>,
+        #[case] expected: Option<&str>,
+        #[case] state: TrayState,
+    ) {
+        let progress = SyncProgress { pending_uploads, upload_errors, ..Default::default() };
+        assert_eq!(sync_summary(&progress, &transfers).as_deref(), expected);
+        assert_eq!(TrayState::from_progress(&progress, &transfers), state);
+    }
+}
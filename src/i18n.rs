@@ -0,0 +1,190 @@
+//! Traducciones de la interfaz (GUI, bandeja y actividad reciente)
+//!
+//! Catálogos Fluent embebidos en el binario: `i18n/en/main.ftl` es la base y
+//! `i18n/es/main.ftl` la traducción al español. El idioma sale de las
+//! variables de entorno de locale (`LANGUAGE`, `LC_ALL`, `LC_MESSAGES`,
+//! `LANG`) al primer uso. Un mensaje que falte en el catálogo elegido se toma
+//! del inglés y, si tampoco está, se muestra su id.
+//!
+//! Uso: `t!("tray-sync-now")` o `t!("gui-changes-pending", count = n)`.
+
+use std::sync::LazyLock;
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+const EN_FTL: &str = include_str!("../i18n/en/main.ftl");
+const ES_FTL: &str = include_str!("../i18n/es/main.ftl");
+
+/// Idiomas con catálogo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Es,
+}
+
+impl Lang {
+    /// Primer idioma con catálogo en las variables de locale (inglés si ninguno)
+    pub fn from_env() -> Self {
+        ["LANGUAGE", "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map(|value| Self::from_locale(&value))
+            .unwrap_or(Lang::En)
+    }
+
+    /// `es_ES.UTF-8`, `es`, `es:en` → español; cualquier otro → inglés.
+    /// `LANGUAGE` admite una lista separada por `:` y se usa la primera entrada.
+    pub fn from_locale(locale: &str) -> Self {
+        let first = locale.split(':').next().unwrap_or_default();
+        let language = first.split(['_', '.', '@', '-']).next().unwrap_or_default();
+        if language.eq_ignore_ascii_case("es") { Lang::Es } else { Lang::En }
+    }
+
+    fn source(&self) -> (&'static str, &'static str) {
+        match self {
+            Lang::En => ("en", EN_FTL),
+            Lang::Es => ("es", ES_FTL),
+        }
+    }
+}
+
+/// Catálogo elegido más el inglés como respaldo
+pub struct Localizer {
+    bundle: FluentBundle<FluentResource>,
+    fallback: Option<FluentBundle<FluentResource>>,
+}
+
+impl Localizer {
+    pub fn new(lang: Lang) -> Self {
+        Self {
+            bundle: build_bundle(lang),
+            fallback: (lang != Lang::En).then(|| build_bundle(Lang::En)),
+        }
+    }
+
+    /// Mensaje `id` con sus argumentos; el propio id si no existe en ningún catálogo
+    pub fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        std::iter::once(&self.bundle)
+            .chain(self.fallback.as_ref())
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = Vec::new();
+                let text = bundle.format_pattern(pattern, args, &mut errors).into_owned();
+                if !errors.is_empty() {
+                    tracing::warn!("⚠️ Traducción incompleta de {}: {:?}", id, errors);
+                }
+                Some(text)
+            })
+            .unwrap_or_else(|| {
+                tracing::warn!("⚠️ Mensaje sin traducción: {}", id);
+                id.to_string()
+            })
+    }
+}
+
+fn build_bundle(lang: Lang) -> FluentBundle<FluentResource> {
+    let (tag, source) = lang.source();
+    let langid: LanguageIdentifier = tag.parse().expect("identificador de idioma válido");
+    let resource = FluentResource::try_new(source.to_string())
+        .unwrap_or_else(|(resource, errors)| {
+            tracing::error!("❌ Errores en el catálogo {}: {:?}", tag, errors);
+            resource
+        });
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Sin marcas de aislamiento bidi: los textos van a GTK y al menú de la bandeja tal cual
+    bundle.set_use_isolating(false);
+    if let Err(errors) = bundle.add_resource(resource) {
+        tracing::error!("❌ Mensajes duplicados en el catálogo {}: {:?}", tag, errors);
+    }
+    bundle
+}
+
+/// Localizador global. En los tests se fija el inglés para no depender del entorno.
+static LOCALIZER: LazyLock<Localizer> = LazyLock::new(|| {
+    let lang = if cfg!(test) { Lang::En } else { Lang::from_env() };
+    Localizer::new(lang)
+});
+
+/// Texto traducido sin argumentos
+pub fn tr(id: &str) -> String {
+    LOCALIZER.format(id, None)
+}
+
+/// Texto traducido con argumentos `{ $nombre }`
+pub fn tr_args(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let mut fluent_args = FluentArgs::with_capacity(args.len());
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+    LOCALIZER.format(id, Some(&fluent_args))
+}
+
+/// `t!("id")` o `t!("id", nombre = valor, ...)` con valores convertibles a `FluentValue`
+macro_rules! t {
+    ($id:expr) => {
+        $crate::i18n::tr($id)
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::tr_args(
+            $id,
+            &[$((stringify!($name), ::fluent_bundle::FluentValue::from($value))),+],
+        )
+    };
+}
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::collections::BTreeSet;
+
+    /// Ids de los mensajes de un catálogo (líneas `id = ...` sin sangría)
+    fn message_ids(source: &str) -> BTreeSet<&str> {
+        source
+            .lines()
+            .filter(|line| !line.starts_with([' ', '#']))
+            .filter_map(|line| line.split_once(" =").map(|(id, _)| id))
+            .collect()
+    }
+
+    #[test]
+    fn test_catalogs_have_the_same_messages() {
+        let en = message_ids(EN_FTL);
+        let es = message_ids(ES_FTL);
+        assert!(!en.is_empty());
+        assert_eq!(en.difference(&es).collect::<Vec<_>>(), Vec::<&&str>::new(), "faltan en es");
+        assert_eq!(es.difference(&en).collect::<Vec<_>>(), Vec::<&&str>::new(), "sobran en es");
+    }
+
+    #[rstest]
+    #[case::spanish_locale("es_ES.UTF-8", Lang::Es)]
+    #[case::spanish_short("es", Lang::Es)]
+    #[case::language_list("es:en", Lang::Es)]
+    #[case::english("en_US.UTF-8", Lang::En)]
+    #[case::posix("C", Lang::En)]
+    #[case::other("fr_FR.UTF-8", Lang::En)]
+    fn test_lang_from_locale(#[case] locale: &str, #[case] expected: Lang) {
+        assert_eq!(Lang::from_locale(locale), expected);
+    }
+
+    #[rstest]
+    #[case::english(Lang::En, 1, "1 file pending")]
+    #[case::english_plural(Lang::En, 3, "3 files pending")]
+    #[case::spanish(Lang::Es, 1, "1 archivo pendiente")]
+    #[case::spanish_plural(Lang::Es, 3, "3 archivos pendientes")]
+    fn test_format_with_plural_args(#[case] lang: Lang, #[case] count: usize, #[case] expected: &str) {
+        let mut args = FluentArgs::new();
+        args.set("count", count);
+        assert_eq!(Localizer::new(lang).format("tray-pending-files", Some(&args)), expected);
+    }
+
+    #[test]
+    fn test_unknown_message_falls_back_to_id() {
+        assert_eq!(Localizer::new(Lang::Es).format("no-such-message", None), "no-such-message");
+        assert_eq!(t!("tray-sync-now"), "Sync now");
+    }
+}
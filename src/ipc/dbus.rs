@@ -0,0 +1,325 @@
+//! Servicio D-Bus `org.gnome.GDriveXP` (bus de sesión)
+//!
+//! Expone la misma API que el socket Unix (ver `server.rs`) como interfaz D-Bus,
+//! más la señal `StatusChanged` para que extensiones de GNOME Shell y otros
+//! componentes de escritorio reaccionen a cambios sin hacer polling. En la
+//! misma conexión se sirve el proveedor de búsqueda de GNOME Shell
+//! (`search_provider.rs`).
+
+use anyhow::{Context, Result};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use zbus::object_server::SignalEmitter;
+
+use super::search_provider::{SearchProvider, SEARCH_PROVIDER_PATH};
+use super::server::{process_request, IpcContext};
+use super::{FileStatusData, IpcRequest, IpcResponse, PathStatus, SyncStatus};
+
+/// Nombre del servicio en el bus de sesión
+pub const DBUS_SERVICE_NAME: &str = "org.gnome.GDriveXP";
+/// Ruta del objeto exportado
+pub const DBUS_OBJECT_PATH: &str = "/org/gnome/GDriveXP";
+
+/// Tupla D-Bus `(sssb)`: ruta, estado, disponibilidad, compartido
+type DbusPathStatus = (String, String, String, bool);
+
+/// Tupla D-Bus `(suxtsbb)`: id, número, fecha (Unix, 0 = desconocida),
+/// tamaño, autor, conservar siempre, actual
+type DbusRevision = (String, u32, i64, u64, String, bool, bool);
+
+/// Servicio D-Bus que refleja la API IPC
+pub struct DbusService {
+    ctx: IpcContext,
+    status_rx: Option<broadcast::Receiver<u64>>,
+}
+
+impl DbusService {
+    /// Crea un nuevo servicio D-Bus sobre el contexto IPC compartido
+    pub fn new(ctx: IpcContext) -> Self {
+        Self { ctx, status_rx: None }
+    }
+
+    /// Suscribe el servicio a notificaciones de cambio de estado (inodes)
+    /// para emitir la señal `StatusChanged`
+    pub fn with_status_events(mut self, rx: broadcast::Receiver<u64>) -> Self {
+        self.status_rx = Some(rx);
+        self
+    }
+
+    /// Inicia el servicio en un task de Tokio separado
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            if let Err(e) = self.run().await {
+                tracing::warn!("⚠️ Servicio D-Bus no disponible: {:?}", e);
+            }
+        })
+    }
+
+    /// Registra el servicio y reenvía eventos de estado como señales
+    async fn run(self) -> Result<()> {
+        let interface = GDriveXpInterface { ctx: self.ctx.clone() };
+        let search_provider = SearchProvider::new(self.ctx.clone());
+
+        let connection = zbus::connection::Builder::session()?
+            .name(DBUS_SERVICE_NAME)?
+            .serve_at(DBUS_OBJECT_PATH, interface)?
+            .serve_at(SEARCH_PROVIDER_PATH, search_provider)?
+            .build()
+            .await
+            .context("Error registrando servicio D-Bus")?;
+
+        tracing::info!("🔌 Servicio D-Bus registrado como {}", DBUS_SERVICE_NAME);
+
+        let Some(mut status_rx) = self.status_rx else {
+            // Sin eventos: mantener la conexión viva hasta el shutdown
+            crate::utils::shutdown::wait_for_shutdown().await;
+            return Ok(());
+        };
+
+        let iface_ref = connection
+            .object_server()
+            .interface::<_, GDriveXpInterface>(DBUS_OBJECT_PATH)
+            .await?;
+
+        loop {
+            let inode = tokio::select! {
+                event = status_rx.recv() => match event {
+                    Ok(inode) => inode,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::debug!("D-Bus: {} eventos de estado descartados (lag)", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = crate::utils::shutdown::wait_for_shutdown() => break,
+            };
+
+            let Ok(Some(rel)) = self.ctx.db().resolve_inode_to_relative_path(inode).await else {
+                continue;
+            };
+            let path = self.ctx.mirror_path().join(rel).to_string_lossy().into_owned();
+
+            let response = process_request(&self.ctx, IpcRequest::GetFileStatus { path: path.clone() }).await;
+            let IpcResponse::ExtendedStatus(data) = response else {
+                continue;
+            };
+            let emitter = iface_ref.signal_emitter();
+            if let Err(e) = GDriveXpInterface::status_changed(emitter, &path, data.status.as_str()).await {
+                tracing::debug!("Error emitiendo señal StatusChanged: {:?}", e);
+            }
+            let percent = transfer_percent(&data.status);
+            if percent >= 0
+                && let Err(e) = GDriveXpInterface::transfer_progress(emitter, &path, percent).await
+            {
+                tracing::debug!("Error emitiendo señal TransferProgress: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Implementación de la interfaz `org.gnome.GDriveXP`
+struct GDriveXpInterface {
+    ctx: IpcContext,
+}
+
+impl GDriveXpInterface {
+    /// Ejecuta una petición IPC y convierte `IpcResponse::Error` en error D-Bus
+    async fn call(&self, request: IpcRequest) -> zbus::fdo::Result<IpcResponse> {
+        match process_request(&self.ctx, request).await {
+            IpcResponse::Error { message } => Err(zbus::fdo::Error::Failed(message)),
+            response => Ok(response),
+        }
+    }
+}
+
+fn to_dbus_status(path: String, data: &FileStatusData) -> DbusPathStatus {
+    (
+        path,
+        data.status.as_str().to_string(),
+        data.availability.as_str().to_string(),
+        data.is_shared,
+    )
+}
+
+/// Porcentaje de la transferencia en curso (-1: ninguna)
+fn transfer_percent(status: &SyncStatus) -> i32 {
+    status.download_percent()
+        .or_else(|| status.upload_percent())
+        .map_or(-1, i32::from)
+}
+
+fn unexpected(response: IpcResponse) -> zbus::fdo::Error {
+    zbus::fdo::Error::Failed(format!("Respuesta IPC inesperada: {:?}", response))
+}
+
+#[zbus::interface(name = "org.gnome.GDriveXP")]
+impl GDriveXpInterface {
+    /// Verifica que el daemon responde
+    async fn ping(&self) -> String {
+        "pong".to_string()
+    }
+
+    /// Estado extendido de un archivo: (ruta, estado, disponibilidad, compartido)
+    async fn get_file_status(&self, path: String) -> zbus::fdo::Result<DbusPathStatus> {
+        match self.call(IpcRequest::GetFileStatus { path: path.clone() }).await? {
+            IpcResponse::ExtendedStatus(data) => Ok(to_dbus_status(path, &data)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Porcentaje de la descarga o subida en curso de un archivo (-1: ninguna)
+    async fn get_transfer_progress(&self, path: String) -> zbus::fdo::Result<i32> {
+        match self.call(IpcRequest::GetFileStatus { path }).await? {
+            IpcResponse::ExtendedStatus(data) => Ok(transfer_percent(&data.status)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Disponibilidad de un archivo ("local_online", "online_only", "not_tracked")
+    async fn get_file_availability(&self, path: String) -> zbus::fdo::Result<String> {
+        match self.call(IpcRequest::GetFileAvailability { path }).await? {
+            IpcResponse::Availability(avail) => Ok(avail.as_str().to_string()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Estados de varios archivos en una sola llamada
+    async fn get_status_batch(&self, paths: Vec<String>) -> zbus::fdo::Result<Vec<DbusPathStatus>> {
+        match self.call(IpcRequest::GetStatusBatch { paths }).await? {
+            IpcResponse::StatusBatch(statuses) => Ok(statuses
+                .into_iter()
+                .map(|PathStatus { path, data }| to_dbus_status(path, &data))
+                .collect()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Estados de todos los hijos de un directorio
+    async fn get_dir_status(&self, dir: String) -> zbus::fdo::Result<Vec<DbusPathStatus>> {
+        match self.call(IpcRequest::GetDirStatus { dir }).await? {
+            IpcResponse::StatusBatch(statuses) => Ok(statuses
+                .into_iter()
+                .map(|PathStatus { path, data }| to_dbus_status(path, &data))
+                .collect()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Crea un enlace "cualquiera con el enlace" y retorna su URL
+    async fn create_share_link(&self, path: String, role: String) -> zbus::fdo::Result<String> {
+        match self.call(IpcRequest::CreateShareLink { path, role }).await? {
+            IpcResponse::Link { url } => Ok(url),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// URL del archivo en drive.google.com
+    async fn get_web_link(&self, path: String) -> zbus::fdo::Result<String> {
+        match self.call(IpcRequest::GetWebLink { path }).await? {
+            IpcResponse::Link { url } => Ok(url),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Busca archivos por nombre: lista de (ruta, es_directorio)
+    async fn search(&self, query: String) -> zbus::fdo::Result<Vec<(String, bool)>> {
+        match self.call(IpcRequest::Search { query }).await? {
+            IpcResponse::SearchResults(results) => Ok(results
+                .into_iter()
+                .map(|r| (r.path, r.is_dir))
+                .collect()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Métricas del daemon en formato de texto de Prometheus
+    async fn get_daemon_stats(&self) -> zbus::fdo::Result<String> {
+        match self.call(IpcRequest::GetDaemonStats).await? {
+            IpcResponse::DaemonStats(stats) => Ok(crate::metrics::render_prometheus(&stats)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Historial de versiones de un archivo, de la más antigua a la actual
+    async fn list_revisions(&self, path: String) -> zbus::fdo::Result<Vec<DbusRevision>> {
+        match self.call(IpcRequest::ListRevisions { path }).await? {
+            IpcResponse::Revisions(revisions) => Ok(revisions
+                .into_iter()
+                .map(|r| (
+                    r.id,
+                    r.number,
+                    r.modified_time.unwrap_or(0),
+                    r.size.unwrap_or(0),
+                    r.author.unwrap_or_default(),
+                    r.keep_forever,
+                    r.is_current,
+                ))
+                .collect()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Recupera una versión (sobre el original o como copia) y retorna la ruta escrita
+    async fn restore_revision(&self, path: String, revision_id: String, as_copy: bool) -> zbus::fdo::Result<String> {
+        match self.call(IpcRequest::RestoreRevision { path, revision_id, as_copy }).await? {
+            IpcResponse::RevisionRestored { path } => Ok(path),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Cambia un archivo a modo "Just Online"
+    async fn set_online_only(&self, path: String) -> zbus::fdo::Result<()> {
+        self.call(IpcRequest::SetOnlineOnly { path }).await.map(|_| ())
+    }
+
+    /// Cambia un archivo a modo "Local & Online"
+    async fn set_local_online(&self, path: String) -> zbus::fdo::Result<()> {
+        self.call(IpcRequest::SetLocalOnline { path }).await.map(|_| ())
+    }
+
+    /// Marca o desmarca un archivo como destacado en Drive
+    async fn set_starred(&self, path: String, starred: bool) -> zbus::fdo::Result<()> {
+        self.call(IpcRequest::SetStarred { path, starred }).await.map(|_| ())
+    }
+
+    /// Cambia la descripción de un archivo en Drive (vacía: la borra)
+    async fn set_description(&self, path: String, description: String) -> zbus::fdo::Result<()> {
+        self.call(IpcRequest::SetDescription { path, description: Some(description) }).await.map(|_| ())
+    }
+
+    /// Sincroniza ahora sin esperar al próximo intervalo
+    async fn force_sync(&self) -> zbus::fdo::Result<()> {
+        self.call(IpcRequest::ForceSync).await.map(|_| ())
+    }
+
+    /// Muestra en la GUI las propiedades de un archivo
+    async fn show_properties(&self, path: String) -> zbus::fdo::Result<()> {
+        self.call(IpcRequest::ShowProperties { path }).await.map(|_| ())
+    }
+
+    /// Elementos eliminados en local: (inode, nombre, es_directorio)
+    async fn list_trash(&self) -> zbus::fdo::Result<Vec<(u64, String, bool)>> {
+        match self.call(IpcRequest::ListTrash).await? {
+            IpcResponse::TrashItems(items) => Ok(items
+                .into_iter()
+                .map(|item| (item.inode, item.name, item.is_dir))
+                .collect()),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    /// Elimina definitivamente de Drive un elemento de la papelera local
+    async fn delete_permanently(&self, inode: u64) -> zbus::fdo::Result<()> {
+        self.call(IpcRequest::DeletePermanently { inode }).await.map(|_| ())
+    }
+
+    /// Señal emitida cuando cambia el estado de sincronización de un archivo
+    #[zbus(signal)]
+    async fn status_changed(emitter: &SignalEmitter<'_>, path: &str, status: &str) -> zbus::Result<()>;
+
+    /// Señal emitida con cada avance de una descarga o subida (porcentaje 0-100)
+    #[zbus(signal)]
+    async fn transfer_progress(emitter: &SignalEmitter<'_>, path: &str, percent: i32) -> zbus::Result<()>;
+}
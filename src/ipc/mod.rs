@@ -1,7 +1,11 @@
 //! Comunicación IPC para extensiones externas (Nautilus, etc.)
 //!
-//! Protocolo binario sobre Unix Domain Sockets para consultar estado de sincronización.
+//! Mensajes JSON con prefijo de longitud sobre Unix Domain Sockets para
+//! consultar estado de sincronización. Cada conexión empieza con `Hello`, que
+//! fija la versión del protocolo (`PROTOCOL_VERSION`).
 
+pub mod dbus;
+pub mod search_provider;
 pub mod server;
 
 use serde::{Deserialize, Serialize};
@@ -19,6 +23,52 @@ pub enum IpcRequest {
     SetLocalOnline { path: String },
     /// Obtener disponibilidad actual de un archivo
     GetFileAvailability { path: String },
+    /// Consultar el estado de varios archivos en un solo round-trip
+    GetStatusBatch { paths: Vec<String> },
+    /// Consultar el estado de todos los hijos de un directorio
+    GetDirStatus { dir: String },
+    /// Crear un enlace "cualquiera con el enlace" (role: "reader", "commenter" o "writer")
+    CreateShareLink { path: String, role: String },
+    /// Obtener la URL del archivo en drive.google.com (webViewLink)
+    GetWebLink { path: String },
+    /// Buscar archivos y carpetas por nombre en todo el Drive
+    Search { query: String },
+    /// Métricas del daemon (API, transferencias, caché, latencia FUSE)
+    GetDaemonStats,
+    /// Historial de versiones de un archivo en Drive
+    ListRevisions { path: String },
+    /// Recupera una versión anterior: reemplaza el contenido actual o, con
+    /// `as_copy`, la guarda junto al original como `nombre (rev N)`
+    RestoreRevision { path: String, revision_id: String, as_copy: bool },
+    /// Marcar (`starred`) o desmarcar un archivo como destacado en Drive
+    SetStarred { path: String, starred: bool },
+    /// Sincronizar ahora: syncer y uploader ejecutan un ciclo sin esperar al intervalo
+    ForceSync,
+    /// Abrir en la GUI el diálogo de propiedades de un archivo del espejo
+    ShowProperties { path: String },
+    /// Elementos eliminados en local que aún se pueden eliminar definitivamente
+    ListTrash,
+    /// Eliminar definitivamente de Drive un elemento de `ListTrash` (sin papelera)
+    DeletePermanently { inode: u64 },
+    /// Cambiar (`Some`) o borrar (`None`) la descripción de un archivo en Drive
+    SetDescription { path: String, description: Option<String> },
+}
+
+impl IpcRequest {
+    /// Cambia algo en Drive o en lo que este dispositivo mantiene descargado;
+    /// en modo solo lectura (`Config.read_only`) se rechaza
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Self::SetOnlineOnly { .. }
+                | Self::SetLocalOnline { .. }
+                | Self::CreateShareLink { .. }
+                | Self::RestoreRevision { .. }
+                | Self::SetStarred { .. }
+                | Self::DeletePermanently { .. }
+                | Self::SetDescription { .. }
+        )
+    }
 }
 
 /// Respuesta del servidor IPC
@@ -36,6 +86,105 @@ pub enum IpcResponse {
     Success,
     /// Error en la operación
     Error { message: String },
+    /// Estados de múltiples archivos (respuesta a `GetStatusBatch` y `GetDirStatus`)
+    StatusBatch(Vec<PathStatus>),
+    /// Enlace web de Google Drive (respuesta a `CreateShareLink` y `GetWebLink`)
+    Link { url: String },
+    /// Resultados de `Search`, ordenados por relevancia
+    SearchResults(Vec<SearchResult>),
+    /// Respuesta a `GetDaemonStats`
+    DaemonStats(crate::metrics::DaemonStats),
+    /// Versiones de un archivo, de la más antigua a la actual
+    Revisions(Vec<RevisionInfo>),
+    /// Ruta donde quedó la versión recuperada (el original o la copia)
+    RevisionRestored { path: String },
+    /// Respuesta a `ListTrash`, por nombre
+    TrashItems(Vec<TrashItem>),
+}
+
+/// Roles aceptados por `CreateShareLink`
+pub const SHARE_ROLES: &[&str] = &["reader", "commenter", "writer"];
+
+/// Estado extendido asociado a una ruta (elemento de una respuesta batch)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathStatus {
+    pub path: String,
+    pub data: FileStatusData,
+}
+
+/// Archivo encontrado por `Search`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchResult {
+    /// Ruta absoluta en el espejo local
+    pub path: String,
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Elemento eliminado en local (respuesta a `ListTrash`). Ya no tiene ruta en
+/// el espejo: se identifica por inode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashItem {
+    pub inode: u64,
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Versión de un archivo (respuesta a `ListRevisions`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevisionInfo {
+    pub id: String,
+    /// Posición en el historial (1 = la más antigua); es la `N` de `nombre (rev N)`
+    pub number: u32,
+    /// Fecha de la versión (segundos Unix)
+    pub modified_time: Option<i64>,
+    pub size: Option<u64>,
+    /// Nombre de quien subió la versión
+    pub author: Option<String>,
+    /// Marcada como "conservar siempre" (Drive no la purga)
+    pub keep_forever: bool,
+    /// Es el contenido actual del archivo
+    pub is_current: bool,
+}
+
+/// Tamaño máximo de un mensaje IPC (payload sin el prefijo de longitud).
+/// Las peticiones batch pueden incluir miles de rutas.
+pub const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Versión del protocolo del socket. La 1 era bincode sin handshake, donde
+/// reordenar variantes desincronizaba a los clientes en silencio; desde la 2
+/// los mensajes son JSON con nombres de variante y de campo. Subirla solo al
+/// romper la compatibilidad (renombrar o quitar variantes o campos): añadir
+/// variantes no la cambia, un daemon antiguo responde `Error` a las que no
+/// conoce.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Primer mensaje de cada conexión al socket
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    /// `PROTOCOL_VERSION` del cliente
+    pub protocol: u32,
+    /// Nombre y versión del cliente, para los logs (ej: "nautilus-ext 1.4.0")
+    pub client: String,
+}
+
+/// Respuesta del daemon a `Hello`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HelloReply {
+    /// Misma versión: la conexión sigue con `IpcRequest`/`IpcResponse`
+    Accepted { protocol: u32, server: String },
+    /// Versión distinta (o falta el `Hello`): el daemon cierra la conexión
+    Rejected { protocol: u32, message: String },
+}
+
+/// Codifica un mensaje del socket (sin el prefijo de longitud)
+pub fn encode<T: Serialize>(message: &T) -> serde_json::Result<Vec<u8>> {
+    serde_json::to_vec(message)
+}
+
+/// Decodifica un mensaje del socket (sin el prefijo de longitud)
+pub fn decode<'a, T: Deserialize<'a>>(payload: &'a [u8]) -> serde_json::Result<T> {
+    serde_json::from_slice(payload)
 }
 
 /// Datos completos de estado del archivo para el InfoProvider
@@ -57,23 +206,77 @@ pub enum FileAvailability {
     NotTracked,
 }
 
+impl FileAvailability {
+    /// Representación textual estable (usada por D-Bus y la DB)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileAvailability::LocalOnline => "local_online",
+            FileAvailability::OnlineOnly => "online_only",
+            FileAvailability::NotTracked => "not_tracked",
+        }
+    }
+}
+
 /// Estado de sincronización de un archivo
 /// - Synced: Local + Drive (verde)
 /// - CloudOnly: Solo en Drive, no descargado (azul)
-/// - LocalOnly: Solo local, pendiente de subir (naranja)
+/// - LocalOnly: Solo local, pendiente de subir o ignorado (naranja)
 /// - Error: Error de sincronización (rojo)
+/// - Syncing: Subida en curso (él o algún descendiente)
+/// - Downloading: Descarga en curso hacia la caché, con el porcentaje ya presente
+/// - Uploading: Subida en curso de un archivo, con el porcentaje ya enviado
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SyncStatus {
     /// Sincronizado: existe en local y en Drive
     Synced,
     /// Solo en Drive: no descargado localmente
     CloudOnly,
-    /// Solo local: pendiente de subir a Drive
+    /// Solo local: pendiente de subir a Drive o excluido por `ignore_patterns`
     LocalOnly,
     /// Error de sincronización
     Error,
     /// No es un archivo de G-DriveXP
     Unknown,
+    /// Sincronizando: upload en curso (en directorios, algún descendiente subiendo)
+    Syncing,
+    /// Descargando: el Smart Streamer o el prefetcher hidratan la caché
+    /// (porcentaje del contenido ya descargado)
+    Downloading(u8),
+    /// Subiendo: como `Syncing`, pero de un archivo concreto cuyo progreso
+    /// se conoce (porcentaje ya enviado a Drive)
+    Uploading(u8),
+}
+
+impl SyncStatus {
+    /// Representación textual estable (usada por D-Bus)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncStatus::Synced => "synced",
+            SyncStatus::CloudOnly => "cloud_only",
+            SyncStatus::LocalOnly => "local_only",
+            SyncStatus::Error => "error",
+            SyncStatus::Unknown => "unknown",
+            SyncStatus::Syncing => "syncing",
+            SyncStatus::Downloading(_) => "downloading",
+            SyncStatus::Uploading(_) => "syncing",
+        }
+    }
+
+    /// Porcentaje descargado si hay una descarga en curso
+    pub fn download_percent(&self) -> Option<u8> {
+        match self {
+            SyncStatus::Downloading(percent) => Some(*percent),
+            _ => None,
+        }
+    }
+
+    /// Porcentaje enviado si hay una subida en curso
+    pub fn upload_percent(&self) -> Option<u8> {
+        match self {
+            SyncStatus::Uploading(percent) => Some(*percent),
+            _ => None,
+        }
+    }
 }
 
 /// Ruta del socket IPC (usando XDG_RUNTIME_DIR)
@@ -87,7 +290,7 @@ mod tests {
     use super::*;
     use rstest::*;
 
-    // --- Bincode roundtrip: contrato con nautilus-ext ---
+    // --- Roundtrip JSON: contrato con nautilus-ext ---
 
     #[rstest]
     #[case::ping(IpcRequest::Ping)]
@@ -95,9 +298,22 @@ mod tests {
     #[case::set_online(IpcRequest::SetOnlineOnly { path: "file:///home/user/GoogleDrive/foto.jpg".into() })]
     #[case::set_local(IpcRequest::SetLocalOnline { path: "/home/user/GoogleDrive/video.mp4".into() })]
     #[case::get_avail(IpcRequest::GetFileAvailability { path: "/home/user/GoogleDrive/notes.md".into() })]
-    fn test_request_bincode_roundtrip(#[case] request: IpcRequest) {
-        let bytes = bincode::serialize(&request).unwrap();
-        let decoded: IpcRequest = bincode::deserialize(&bytes).unwrap();
+    #[case::status_batch(IpcRequest::GetStatusBatch { paths: vec!["/home/user/GoogleDrive/a.txt".into(), "/home/user/GoogleDrive/b.txt".into()] })]
+    #[case::share_link(IpcRequest::CreateShareLink { path: "/home/user/GoogleDrive/informe.pdf".into(), role: "reader".into() })]
+    #[case::web_link(IpcRequest::GetWebLink { path: "/home/user/GoogleDrive/informe.pdf".into() })]
+    #[case::dir_status(IpcRequest::GetDirStatus { dir: "file:///home/user/GoogleDrive/Fotos".into() })]
+    #[case::search(IpcRequest::Search { query: "informe 2024".into() })]
+    #[case::daemon_stats(IpcRequest::GetDaemonStats)]
+    #[case::list_revisions(IpcRequest::ListRevisions { path: "/home/user/GoogleDrive/informe.pdf".into() })]
+    #[case::restore_revision(IpcRequest::RestoreRevision { path: "/home/user/GoogleDrive/informe.pdf".into(), revision_id: "r1".into(), as_copy: true })]
+    #[case::set_starred(IpcRequest::SetStarred { path: "/home/user/GoogleDrive/informe.pdf".into(), starred: true })]
+    #[case::show_properties(IpcRequest::ShowProperties { path: "file:///home/user/GoogleDrive/informe.pdf".into() })]
+    #[case::list_trash(IpcRequest::ListTrash)]
+    #[case::delete_permanently(IpcRequest::DeletePermanently { inode: 42 })]
+    #[case::set_description(IpcRequest::SetDescription { path: "/home/user/GoogleDrive/informe.pdf".into(), description: Some("Versión final".into()) })]
+    fn test_request_roundtrip(#[case] request: IpcRequest) {
+        let bytes = encode(&request).unwrap();
+        let decoded: IpcRequest = decode(&bytes).unwrap();
         // Verificar variante (Debug string match, ya que no tiene PartialEq)
         assert_eq!(format!("{:?}", request), format!("{:?}", decoded));
     }
@@ -107,15 +323,56 @@ mod tests {
     #[case::success(IpcResponse::Success)]
     #[case::error(IpcResponse::Error { message: "timeout".into() })]
     #[case::file_status(IpcResponse::FileStatus(SyncStatus::Synced))]
+    #[case::link(IpcResponse::Link { url: "https://drive.google.com/file/d/abc/view".into() })]
     #[case::availability(IpcResponse::Availability(FileAvailability::LocalOnline))]
     #[case::extended(IpcResponse::ExtendedStatus(FileStatusData {
         status: SyncStatus::CloudOnly,
         availability: FileAvailability::OnlineOnly,
         is_shared: true,
     }))]
-    fn test_response_bincode_roundtrip(#[case] response: IpcResponse) {
-        let bytes = bincode::serialize(&response).unwrap();
-        let decoded: IpcResponse = bincode::deserialize(&bytes).unwrap();
+    #[case::status_batch(IpcResponse::StatusBatch(vec![PathStatus {
+        path: "/home/user/GoogleDrive/a.txt".into(),
+        data: FileStatusData {
+            status: SyncStatus::Synced,
+            availability: FileAvailability::LocalOnline,
+            is_shared: false,
+        },
+    }]))]
+    #[case::search_results(IpcResponse::SearchResults(vec![SearchResult {
+        path: "/home/user/GoogleDrive/Trabajo/informe.pdf".into(),
+        name: "informe.pdf".into(),
+        is_dir: false,
+    }]))]
+    #[case::daemon_stats(IpcResponse::DaemonStats(crate::metrics::DaemonStats {
+        uptime_secs: 3600,
+        online: true,
+        bytes_uploaded: 1024,
+        bytes_downloaded: 4096,
+        cache_hits: 10,
+        cache_misses: 2,
+        api_calls: vec![crate::metrics::OpStats {
+            name: "download_chunk".into(),
+            count: 2,
+            errors: 0,
+            sum_us: 150_000,
+            buckets: vec![0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 2, 2],
+        }],
+        fuse_ops: Vec::new(),
+    }))]
+    #[case::revisions(IpcResponse::Revisions(vec![RevisionInfo {
+        id: "r1".into(),
+        number: 1,
+        modified_time: Some(1_700_000_000),
+        size: Some(2048),
+        author: Some("Ana".into()),
+        keep_forever: false,
+        is_current: true,
+    }]))]
+    #[case::revision_restored(IpcResponse::RevisionRestored { path: "/home/user/GoogleDrive/informe (rev 1).pdf".into() })]
+    #[case::trash_items(IpcResponse::TrashItems(vec![TrashItem { inode: 42, name: "borrador.txt".into(), is_dir: false }]))]
+    fn test_response_roundtrip(#[case] response: IpcResponse) {
+        let bytes = encode(&response).unwrap();
+        let decoded: IpcResponse = decode(&bytes).unwrap();
         assert_eq!(format!("{:?}", response), format!("{:?}", decoded));
     }
 
@@ -124,7 +381,7 @@ mod tests {
     #[rstest]
     fn test_length_prefix_framing() {
         let request = IpcRequest::GetFileStatus { path: "/test/path".into() };
-        let payload = bincode::serialize(&request).unwrap();
+        let payload = encode(&request).unwrap();
         let len_bytes = (payload.len() as u32).to_be_bytes();
 
         // Simular frame completo
@@ -136,7 +393,7 @@ mod tests {
         let decoded_len = u32::from_be_bytes([frame[0], frame[1], frame[2], frame[3]]) as usize;
         assert_eq!(decoded_len, payload.len());
 
-        let decoded: IpcRequest = bincode::deserialize(&frame[4..4 + decoded_len]).unwrap();
+        let decoded: IpcRequest = decode(&frame[4..4 + decoded_len]).unwrap();
         assert_eq!(format!("{:?}", request), format!("{:?}", decoded));
     }
 
@@ -148,9 +405,12 @@ mod tests {
     #[case::local(SyncStatus::LocalOnly)]
     #[case::error(SyncStatus::Error)]
     #[case::unknown(SyncStatus::Unknown)]
+    #[case::syncing(SyncStatus::Syncing)]
+    #[case::downloading(SyncStatus::Downloading(42))]
+    #[case::uploading(SyncStatus::Uploading(17))]
     fn test_sync_status_serialization(#[case] status: SyncStatus) {
-        let bytes = bincode::serialize(&status).unwrap();
-        let decoded: SyncStatus = bincode::deserialize(&bytes).unwrap();
+        let bytes = encode(&status).unwrap();
+        let decoded: SyncStatus = decode(&bytes).unwrap();
         assert_eq!(status, decoded);
     }
 
@@ -159,11 +419,21 @@ mod tests {
     #[case::online_only(FileAvailability::OnlineOnly)]
     #[case::not_tracked(FileAvailability::NotTracked)]
     fn test_file_availability_serialization(#[case] avail: FileAvailability) {
-        let bytes = bincode::serialize(&avail).unwrap();
-        let decoded: FileAvailability = bincode::deserialize(&bytes).unwrap();
+        let bytes = encode(&avail).unwrap();
+        let decoded: FileAvailability = decode(&bytes).unwrap();
         assert_eq!(avail, decoded);
     }
 
+    #[rstest]
+    #[case::synced(SyncStatus::Synced, "synced")]
+    #[case::cloud(SyncStatus::CloudOnly, "cloud_only")]
+    #[case::syncing(SyncStatus::Syncing, "syncing")]
+    #[case::downloading(SyncStatus::Downloading(42), "downloading")]
+    #[case::uploading(SyncStatus::Uploading(17), "syncing")]
+    fn test_sync_status_as_str(#[case] status: SyncStatus, #[case] expected: &str) {
+        assert_eq!(status.as_str(), expected);
+    }
+
     #[rstest]
     fn test_socket_path_format() {
         let path = get_socket_path();
@@ -180,8 +450,19 @@ mod tests {
     #[case::deep("/home/user/GoogleDrive/a/b/c/d/e/f/g/file.txt")]
     fn test_request_with_special_paths(#[case] path: &str) {
         let request = IpcRequest::GetFileStatus { path: path.into() };
-        let bytes = bincode::serialize(&request).unwrap();
-        let decoded: IpcRequest = bincode::deserialize(&bytes).unwrap();
+        let bytes = encode(&request).unwrap();
+        let decoded: IpcRequest = decode(&bytes).unwrap();
         assert_eq!(format!("{:?}", request), format!("{:?}", decoded));
     }
+
+    #[test]
+    fn test_wire_format_uses_names() {
+        let request = IpcRequest::GetFileStatus { path: "/a.txt".into() };
+        let json: serde_json::Value = serde_json::from_slice(&encode(&request).unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!({"GetFileStatus": {"path": "/a.txt"}}));
+
+        let hello: serde_json::Value =
+            serde_json::from_slice(&encode(&Hello { protocol: PROTOCOL_VERSION, client: "x".into() }).unwrap()).unwrap();
+        assert_eq!(hello, serde_json::json!({"protocol": PROTOCOL_VERSION, "client": "x"}));
+    }
 }
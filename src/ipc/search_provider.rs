@@ -0,0 +1,225 @@
+//! Proveedor de búsqueda de GNOME Shell (`org.gnome.Shell.SearchProvider2`)
+//!
+//! Se exporta en `/org/gnome/GDriveXP/SearchProvider` sobre la misma conexión
+//! que `dbus.rs`. GNOME Shell lo descubre por
+//! `data/org.gnome.FedoraDrive.search-provider.ini` y consulta el índice de
+//! nombres (`search_files`) mientras se escribe en la vista de Actividades.
+//! Los identificadores de resultado son rutas absolutas del espejo; activar
+//! uno lo abre con la aplicación predeterminada. Solo se aceptan los
+//! identificadores de la última tanda de resultados, y la URI `file://` que
+//! recibe `xdg-open` se construye aquí a partir de la ruta, nunca se pasa tal
+//! cual lo que envía el bus.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use zbus::zvariant::Value;
+
+use super::server::{search_files, IpcContext};
+
+/// Ruta del objeto exportado (la del `.ini` del proveedor)
+pub const SEARCH_PROVIDER_PATH: &str = "/org/gnome/GDriveXP/SearchProvider";
+
+/// Implementación de `org.gnome.Shell.SearchProvider2`
+pub struct SearchProvider {
+    ctx: IpcContext,
+    /// Identificadores de la última tanda de resultados (los únicos activables)
+    last_results: Mutex<Vec<String>>,
+}
+
+impl SearchProvider {
+    pub fn new(ctx: IpcContext) -> Self {
+        Self { ctx, last_results: Mutex::new(Vec::new()) }
+    }
+
+    /// Rutas que coinciden con los términos (todos, como prefijo de palabra)
+    async fn search(&self, terms: &[String]) -> Vec<String> {
+        let query = terms.join(" ");
+        let ids: Vec<String> = match search_files(self.ctx.db(), self.ctx.mirror_path(), &query).await {
+            Ok(results) => results.into_iter().map(|result| result.path).collect(),
+            Err(e) => {
+                tracing::warn!("⚠️ Búsqueda de GNOME Shell fallida para {:?}: {:?}", query, e);
+                Vec::new()
+            }
+        };
+        *self.last_results.lock().unwrap() = ids.clone();
+        ids
+    }
+
+    /// Ruta del resultado a abrir, si `identifier` salió en la última búsqueda
+    fn accepted_result(&self, identifier: &str) -> Option<PathBuf> {
+        self.last_results
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|id| id.as_str() == identifier)
+            .map(PathBuf::from)
+    }
+}
+
+/// Metadatos de un resultado: nombre, carpeta contenedora relativa al espejo e icono
+fn result_meta(mirror: &Path, id: &str) -> HashMap<String, Value<'static>> {
+    let path = Path::new(id);
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| id.to_string());
+    let description = path
+        .parent()
+        .and_then(|parent| parent.strip_prefix(mirror).ok())
+        .map(|parent| parent.to_string_lossy().into_owned())
+        .filter(|parent| !parent.is_empty())
+        .unwrap_or_else(|| crate::i18n::tr("gui-my-drive"));
+
+    let mut meta = HashMap::new();
+    meta.insert("id".to_string(), Value::from(id.to_string()));
+    meta.insert("name".to_string(), Value::from(name));
+    meta.insert("description".to_string(), Value::from(description));
+    meta.insert("gicon".to_string(), Value::from(gicon_for(path)));
+    meta
+}
+
+/// Icono serializado como `GThemedIcon`: el del tipo MIME y uno genérico de respaldo
+fn gicon_for(path: &Path) -> String {
+    if path.is_dir() {
+        return ". GThemedIcon folder-remote folder".to_string();
+    }
+    let generic = "text-x-generic";
+    match mime_guess::from_path(path).first() {
+        Some(mime) => format!(
+            ". GThemedIcon {}-{} {}-x-generic {}",
+            mime.type_(),
+            mime.subtype(),
+            mime.type_(),
+            generic
+        ),
+        None => format!(". GThemedIcon {}", generic),
+    }
+}
+
+/// Abre un archivo o carpeta con la aplicación predeterminada. Se pasa la URI
+/// `file://` y no la ruta: `xdg-open` no puede tomarla por otro esquema ni por
+/// una opción.
+fn open_path(path: &Path) {
+    let uri = match crate::sync::thumbnails::file_uri(path) {
+        Ok(uri) => uri,
+        Err(e) => {
+            tracing::warn!("No se pudo construir la URI de {:?}: {:?}", path, e);
+            return;
+        }
+    };
+    if let Err(e) = std::process::Command::new("xdg-open").arg(&uri).spawn() {
+        tracing::warn!("No se pudo abrir {:?}: {}", path, e);
+    }
+}
+
+#[zbus::interface(name = "org.gnome.Shell.SearchProvider2")]
+impl SearchProvider {
+    /// Primera búsqueda al empezar a escribir
+    async fn get_initial_result_set(&self, terms: Vec<String>) -> Vec<String> {
+        self.search(&terms).await
+    }
+
+    /// Búsqueda al refinar los términos. Se repite la consulta completa: el
+    /// índice limita el número de resultados y filtrar los anteriores perdería
+    /// coincidencias que no entraron en la primera tanda.
+    async fn get_subsearch_result_set(&self, _previous_results: Vec<String>, terms: Vec<String>) -> Vec<String> {
+        self.search(&terms).await
+    }
+
+    /// Nombre, descripción e icono de cada resultado
+    async fn get_result_metas(&self, identifiers: Vec<String>) -> Vec<HashMap<String, Value<'static>>> {
+        identifiers
+            .iter()
+            .map(|id| result_meta(self.ctx.mirror_path(), id))
+            .collect()
+    }
+
+    /// Abre el resultado elegido (los de solo online abren a través del montaje FUSE)
+    async fn activate_result(&self, identifier: String, _terms: Vec<String>, _timestamp: u32) {
+        let Some(path) = self.accepted_result(&identifier) else {
+            tracing::warn!("⚠️ Resultado de búsqueda desconocido ignorado: {:?}", identifier);
+            return;
+        };
+        tracing::info!("🔎 Abriendo resultado de búsqueda de GNOME Shell: {:?}", path);
+        open_path(&path);
+    }
+
+    /// "Más resultados": muestra la ventana principal
+    async fn launch_search(&self, _terms: Vec<String>, _timestamp: u32) {
+        let spawned = std::process::Command::new("gdbus")
+            .args([
+                "call", "--session",
+                "--dest", "org.gnome.FedoraDrive",
+                "--object-path", "/org/gnome/FedoraDrive",
+                "--method", "org.gtk.Actions.Activate",
+                "show-window", "[]", "{}",
+            ])
+            .spawn();
+        if let Err(e) = spawned {
+            tracing::warn!("No se pudo mostrar la ventana: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_fixtures;
+    use crate::gdrive::mock::{MOCK_ROOT_ID, MockDriveClient};
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    fn meta_str(meta: &HashMap<String, Value<'static>>, key: &str) -> String {
+        match &meta[key] {
+            Value::Str(s) => s.to_string(),
+            other => panic!("{} no es texto: {:?}", key, other),
+        }
+    }
+
+    #[rstest]
+    #[case::root("/espejo/informe.pdf", "informe.pdf", "My Drive")]
+    #[case::nested("/espejo/Trabajo/2024/informe.pdf", "informe.pdf", "Trabajo/2024")]
+    fn test_result_meta(#[case] id: &str, #[case] name: &str, #[case] description: &str) {
+        let meta = result_meta(Path::new("/espejo"), id);
+        assert_eq!(meta_str(&meta, "id"), id);
+        assert_eq!(meta_str(&meta, "name"), name);
+        assert_eq!(meta_str(&meta, "description"), description);
+    }
+
+    #[rstest]
+    #[case::pdf("/espejo/informe.pdf", ". GThemedIcon application-pdf application-x-generic text-x-generic")]
+    #[case::unknown("/espejo/LEEME", ". GThemedIcon text-x-generic")]
+    fn test_gicon_for(#[case] path: &str, #[case] expected: &str) {
+        assert_eq!(gicon_for(Path::new(path)), expected);
+    }
+
+    #[tokio::test]
+    async fn test_initial_result_set_uses_name_index() {
+        let mock = Arc::new(MockDriveClient::new());
+        mock.add_file("file-informe", "Informe anual.pdf", MOCK_ROOT_ID, b"pdf");
+        mock.add_file("file-otro", "notas.txt", MOCK_ROOT_ID, b"txt");
+        let db = test_fixtures::synced_in_memory(&mock).await;
+
+        let provider = SearchProvider::new(IpcContext::new(db, "/espejo".into(), "/cache".into()));
+        let results = provider.get_initial_result_set(vec!["inf".into(), "anu".into()]).await;
+        assert_eq!(results, vec!["/espejo/Informe anual.pdf".to_string()]);
+        assert!(provider.get_initial_result_set(vec!["zzz".into()]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_only_last_results_are_activatable() {
+        let mock = Arc::new(MockDriveClient::new());
+        mock.add_file("file-informe", "Informe anual.pdf", MOCK_ROOT_ID, b"pdf");
+        let db = test_fixtures::synced_in_memory(&mock).await;
+        let provider = SearchProvider::new(IpcContext::new(db, "/espejo".into(), "/cache".into()));
+
+        assert_eq!(provider.accepted_result("/espejo/Informe anual.pdf"), None, "sin búsqueda previa");
+        provider.get_initial_result_set(vec!["informe".into()]).await;
+        assert_eq!(provider.accepted_result("/espejo/Informe anual.pdf"), Some(PathBuf::from("/espejo/Informe anual.pdf")));
+        assert_eq!(provider.accepted_result("https://example.com/x"), None);
+        assert_eq!(provider.accepted_result("/etc/passwd"), None);
+
+        // Una búsqueda nueva reemplaza los activables
+        provider.get_subsearch_result_set(Vec::new(), vec!["zzz".into()]).await;
+        assert_eq!(provider.accepted_result("/espejo/Informe anual.pdf"), None);
+    }
+}
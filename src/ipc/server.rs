@@ -4,51 +4,171 @@
 
 use anyhow::{Context, Result};
 use percent_encoding::percent_decode_str;
+use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{UnixListener, UnixStream};
 use tokio::task::JoinHandle;
 
-use crate::db::MetadataRepository;
+use crate::db::{MetadataRepository, UploadStatus};
 use crate::fuse::filesystem::SHARED_INODE;
+use crate::fuse::file_locks::FileLocks;
+use crate::fuse::inflight::InflightDownloads;
+use crate::sync::upload_progress::UploadProgress;
+use crate::gdrive::DriveApi;
+use crate::gdrive::app_properties;
 use crate::mirror::MirrorCommand;
-use super::{IpcRequest, IpcResponse, SyncStatus, FileAvailability};
+use crate::utils::ignore::IgnoreRules;
+use super::{IpcRequest, IpcResponse, PathStatus, RevisionInfo, SearchResult, SyncStatus, FileAvailability, Hello, HelloReply, MAX_MESSAGE_SIZE, PROTOCOL_VERSION, SHARE_ROLES, decode, encode};
 use tokio::sync::mpsc;
 
-/// Servidor IPC para comunicación con extensiones externas
-/// Servidor IPC para comunicación con extensiones externas
-pub struct IpcServer {
-    socket_path: PathBuf,
+/// Máximo de resultados retornados por `Search`
+const MAX_SEARCH_RESULTS: u32 = 50;
+
+/// Estado compartido necesario para resolver peticiones IPC.
+/// Lo usan tanto el servidor Unix Socket como el servicio D-Bus (y la GUI
+/// para las acciones del diálogo de propiedades).
+#[derive(Clone, Debug)]
+pub struct IpcContext {
     db: Arc<MetadataRepository>,
     mirror_path: PathBuf,
     cache_dir: PathBuf,
     mirror_tx: Option<mpsc::Sender<MirrorCommand>>,
+    drive_client: Option<Arc<dyn DriveApi>>,
+    properties_tx: Option<mpsc::UnboundedSender<PathBuf>>,
+    ignore_rules: Arc<IgnoreRules>,
+    inflight: Arc<InflightDownloads>,
+    upload_progress: UploadProgress,
+    file_locks: FileLocks,
+    read_only: bool,
 }
 
-impl IpcServer {
-    /// Crea un nuevo servidor IPC
+impl IpcContext {
+    /// Crea un nuevo contexto IPC
     pub fn new(
-        socket_path: PathBuf,
         db: Arc<MetadataRepository>,
         mirror_path: PathBuf,
         cache_dir: PathBuf,
     ) -> Self {
         Self {
-            socket_path,
             db,
             mirror_path,
             cache_dir,
             mirror_tx: None,
+            drive_client: None,
+            properties_tx: None,
+            ignore_rules: Arc::new(IgnoreRules::default()),
+            inflight: Arc::new(InflightDownloads::default()),
+            upload_progress: UploadProgress::default(),
+            file_locks: FileLocks::default(),
+            read_only: false,
+        }
+    }
+
+    /// Modo solo lectura (`Config.read_only`): las peticiones que modifican
+    /// algo (`IpcRequest::is_mutating`) se rechazan
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Acceso al repositorio de metadatos
+    pub fn db(&self) -> &Arc<MetadataRepository> {
+        &self.db
+    }
+
+    /// Ruta del directorio espejo visible
+    pub fn mirror_path(&self) -> &std::path::Path {
+        &self.mirror_path
+    }
+
+    /// Pide al MirrorManager que rehaga el espejo a partir de la DB
+    pub async fn refresh_mirror(&self) {
+        if let Some(tx) = &self.mirror_tx {
+            let _ = tx.send(MirrorCommand::Refresh).await;
+        }
+    }
+}
+
+/// Servidor IPC para comunicación con extensiones externas
+pub struct IpcServer {
+    socket_path: PathBuf,
+    ctx: IpcContext,
+}
+
+impl IpcServer {
+    /// Crea un nuevo servidor IPC
+    pub fn new(
+        socket_path: PathBuf,
+        db: Arc<MetadataRepository>,
+        mirror_path: PathBuf,
+        cache_dir: PathBuf,
+    ) -> Self {
+        Self {
+            socket_path,
+            ctx: IpcContext::new(db, mirror_path, cache_dir),
         }
     }
 
     /// Establece el canal de comandos para MirrorManager
     pub fn with_mirror_manager(mut self, tx: mpsc::Sender<MirrorCommand>) -> Self {
-        self.mirror_tx = Some(tx);
+        self.ctx.mirror_tx = Some(tx);
+        self
+    }
+
+    /// Establece el cliente de Drive (necesario para peticiones que llaman a la API)
+    pub fn with_drive_client(mut self, client: Arc<dyn DriveApi>) -> Self {
+        self.ctx.drive_client = Some(client);
         self
     }
 
+    /// Establece el canal hacia la GUI por el que `ShowProperties` abre el
+    /// diálogo de propiedades
+    pub fn with_properties_requests(mut self, tx: mpsc::UnboundedSender<PathBuf>) -> Self {
+        self.ctx.properties_tx = Some(tx);
+        self
+    }
+
+    /// Patrones de `Config.ignore_patterns`: las rutas del espejo que coinciden
+    /// y no están en la DB se reportan como `LocalOnly`
+    pub fn with_ignore_rules(mut self, rules: Arc<IgnoreRules>) -> Self {
+        self.ctx.ignore_rules = rules;
+        self
+    }
+
+    /// Descargas en curso de FUSE y el prefetcher: los archivos en hidratación
+    /// se reportan como `Downloading`
+    pub fn with_inflight(mut self, inflight: Arc<InflightDownloads>) -> Self {
+        self.ctx.inflight = inflight;
+        self
+    }
+
+    /// Subidas en curso del uploader: los archivos que se suben se reportan
+    /// como `Uploading` con su porcentaje
+    pub fn with_upload_progress(mut self, upload_progress: UploadProgress) -> Self {
+        self.ctx.upload_progress = upload_progress;
+        self
+    }
+
+    /// Locks y handles abiertos de FUSE: "Liberar espacio" no descarta la
+    /// caché de un archivo abierto
+    pub fn with_file_locks(mut self, file_locks: FileLocks) -> Self {
+        self.ctx.file_locks = file_locks;
+        self
+    }
+
+    /// Modo solo lectura (`Config.read_only`): el socket, D-Bus y la GUI
+    /// rechazan las peticiones que modifican algo
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.ctx.read_only = read_only;
+        self
+    }
+
+    /// Contexto compartido (para reutilizarlo en otros transportes, ej: D-Bus)
+    pub fn context(&self) -> IpcContext {
+        self.ctx.clone()
+    }
+
     /// Inicia el servidor IPC en un task de Tokio separado
     pub fn spawn(self) -> JoinHandle<()> {
         tokio::spawn(async move {
@@ -58,8 +178,9 @@ impl IpcServer {
         })
     }
 
-    /// Loop principal del servidor
-    async fn run(&self) -> Result<()> {
+    /// Crea el socket con permisos `0600`: solo el usuario del daemon puede
+    /// conectar aunque el directorio padre sea más permisivo
+    fn bind(&self) -> Result<UnixListener> {
         // Eliminar socket existente si quedó de una ejecución anterior
         if self.socket_path.exists() {
             std::fs::remove_file(&self.socket_path)
@@ -68,19 +189,31 @@ impl IpcServer {
 
         let listener = UnixListener::bind(&self.socket_path)
             .context("Error al crear Unix Socket")?;
+        std::fs::set_permissions(&self.socket_path, std::fs::Permissions::from_mode(SOCKET_MODE))
+            .context("Error al fijar permisos del Unix Socket")?;
+        Ok(listener)
+    }
+
+    /// Loop principal del servidor
+    async fn run(&self) -> Result<()> {
+        let listener = self.bind()?;
+        let owner_uid = unsafe { libc::getuid() };
 
         tracing::info!("🔌 Servidor IPC escuchando en {:?}", self.socket_path);
 
         loop {
             match listener.accept().await {
                 Ok((stream, _addr)) => {
-                    let db = self.db.clone();
-                    let mirror_path = self.mirror_path.clone();
-                    let cache_dir = self.cache_dir.clone();
-                    let local_sync_tx = self.mirror_tx.clone();
-                    
+                    // Los permisos del socket no cubren la ventana entre bind y
+                    // chmod ni descriptores heredados: se comprueba cada peer
+                    if let Err(reason) = check_peer(&stream, owner_uid) {
+                        tracing::warn!("🚫 Conexión IPC rechazada: {}", reason);
+                        continue;
+                    }
+                    let ctx = self.ctx.clone();
+
                     tokio::spawn(async move {
-                        if let Err(e) = handle_client(stream, db, mirror_path, cache_dir, local_sync_tx).await {
+                        if let Err(e) = handle_client(stream, ctx).await {
                             tracing::debug!("Error manejando cliente IPC: {:?}", e);
                         }
                     });
@@ -93,102 +226,311 @@ impl IpcServer {
     }
 }
 
+/// Permisos del socket: lectura y escritura solo para el propietario
+const SOCKET_MODE: u32 = 0o600;
+
+/// Comprueba con `SO_PEERCRED` que el proceso del otro extremo pertenece al
+/// usuario del daemon. El error describe al peer para el log.
+fn check_peer(stream: &UnixStream, owner_uid: u32) -> std::result::Result<(), String> {
+    let cred = stream.peer_cred()
+        .map_err(|e| format!("no se pudieron leer las credenciales del peer: {}", e))?;
+    if cred.uid() == owner_uid {
+        return Ok(());
+    }
+
+    let process = cred.pid()
+        .map(|pid| {
+            let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap_or_default();
+            format!("pid {} ({})", pid, comm.trim())
+        })
+        .unwrap_or_else(|| "pid desconocido".into());
+    Err(format!("uid {} (gid {}, {}) no es el propietario uid {}", cred.uid(), cred.gid(), process, owner_uid))
+}
+
+/// Lee un mensaje con prefijo de longitud (4 bytes, big-endian).
+/// Devuelve `None` si el cliente cerró la conexión entre mensajes.
+async fn read_frame<'a>(stream: &mut UnixStream, buf: &'a mut Vec<u8>) -> Result<Option<&'a [u8]>> {
+    let mut len_bytes = [0u8; 4];
+    match stream.read_exact(&mut len_bytes).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+
+    let len = u32::from_be_bytes(len_bytes) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        anyhow::bail!("Mensaje IPC demasiado grande: {} bytes", len);
+    }
+    if len > buf.len() {
+        buf.resize(len, 0);
+    }
+
+    // Si falla la lectura del contenido pero leímos el header, es un error real
+    stream.read_exact(&mut buf[..len]).await
+        .context("Error leyendo cuerpo del mensaje IPC")?;
+    Ok(Some(&buf[..len]))
+}
+
+/// Escribe un mensaje con prefijo de longitud
+async fn write_frame(stream: &mut UnixStream, payload: &[u8]) -> Result<()> {
+    stream.write_all(&(payload.len() as u32).to_be_bytes()).await?;
+    stream.write_all(payload).await?;
+    Ok(())
+}
+
+/// Índice de `Error` en el `IpcResponse` publicado con el protocolo v1
+const V1_ERROR_VARIANT: u32 = 5;
+
+/// `Error { message }` tal como lo codificaba el protocolo v1 en bincode: el
+/// índice de la variante en el enum de entonces seguido del mensaje. Se
+/// construye a mano para no depender del orden actual de `IpcResponse`.
+fn v1_error(message: &str) -> Result<Vec<u8>> {
+    let mut bytes = V1_ERROR_VARIANT.to_le_bytes().to_vec();
+    bytes.extend(bincode::serialize(message).context("Error serializando respuesta IPC")?);
+    Ok(bytes)
+}
+
+/// Responde al primer mensaje de una conexión. Devuelve la respuesta ya
+/// codificada y si la conexión puede continuar.
+///
+/// Un payload que no es JSON viene de un cliente del protocolo v1 (bincode,
+/// sin handshake): se le contesta su `Error` en bincode, que es lo que sabe
+/// leer, para que muestre el motivo en vez de un fallo de decodificación.
+fn answer_hello(payload: &[u8]) -> Result<(Vec<u8>, bool)> {
+    let Ok(value) = serde_json::from_slice::<serde_json::Value>(payload) else {
+        tracing::warn!("🚫 Cliente IPC con protocolo v1 (bincode) rechazado");
+        let message = format!(
+            "Protocolo IPC v1 no soportado: el daemon usa la versión {}. Actualiza la extensión.",
+            PROTOCOL_VERSION
+        );
+        return Ok((v1_error(&message)?, false));
+    };
+
+    let reply = match serde_json::from_value::<Hello>(value) {
+        Ok(hello) if hello.protocol == PROTOCOL_VERSION => {
+            tracing::debug!("🤝 Cliente IPC conectado: {} (protocolo {})", hello.client, hello.protocol);
+            HelloReply::Accepted {
+                protocol: PROTOCOL_VERSION,
+                server: format!("g-drive-xp {}", env!("CARGO_PKG_VERSION")),
+            }
+        }
+        Ok(hello) => {
+            tracing::warn!(
+                "🚫 Cliente IPC rechazado: {} habla el protocolo {}, el daemon el {}",
+                hello.client, hello.protocol, PROTOCOL_VERSION
+            );
+            HelloReply::Rejected {
+                protocol: PROTOCOL_VERSION,
+                message: format!(
+                    "Versión de protocolo incompatible: el cliente usa la {} y el daemon la {}. \
+                     Actualiza {} o el daemon para que coincidan.",
+                    hello.protocol, PROTOCOL_VERSION, hello.client
+                ),
+            }
+        }
+        Err(e) => {
+            tracing::warn!("🚫 Cliente IPC sin Hello: {}", e);
+            HelloReply::Rejected {
+                protocol: PROTOCOL_VERSION,
+                message: format!("El primer mensaje debe ser Hello con la versión del protocolo: {}", e),
+            }
+        }
+    };
+
+    let accepted = matches!(reply, HelloReply::Accepted { .. });
+    Ok((encode(&reply).context("Error serializando respuesta IPC")?, accepted))
+}
+
 /// Maneja una conexión de cliente individual
-async fn handle_client(
-    mut stream: UnixStream,
-    db: Arc<MetadataRepository>,
-    mirror_path: PathBuf,
-    cache_dir: PathBuf,
-    mirror_tx: Option<mpsc::Sender<MirrorCommand>>,
-) -> Result<()> {
-    // Buffer para leer el request (max 4KB)
+async fn handle_client(mut stream: UnixStream, ctx: IpcContext) -> Result<()> {
+    // Buffer para leer el request (crece bajo demanda hasta MAX_MESSAGE_SIZE)
     let mut buf = vec![0u8; 4096];
-    
+
+    // Handshake: el primer mensaje fija la versión del protocolo
+    let Some(payload) = read_frame(&mut stream, &mut buf).await? else {
+        return Ok(());
+    };
+    let (reply, accepted) = answer_hello(payload)?;
+    write_frame(&mut stream, &reply).await?;
+    if !accepted {
+        return Ok(());
+    }
+
     // Loop principal para conexión persistente
-    loop {
-        // Leer longitud del mensaje (4 bytes, big-endian)
-        // Usamos read_exact pero manejamos EOF gracefulmente
-        match stream.read_exact(&mut buf[..4]).await {
-            Ok(_) => {}, // Continuamos
-            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                // El cliente cerró la conexión
-                return Ok(());
-            },
-            Err(e) => return Err(e.into()),
-        }
-
-        let len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
-        
-        if len > 4096 {
-            anyhow::bail!("Mensaje IPC demasiado grande: {} bytes", len);
-        }
-        
-        // Leer el mensaje
-        if let Err(e) = stream.read_exact(&mut buf[..len]).await {
-            // Si falla la lectura del contenido pero leímos el header, es un error real
-            anyhow::bail!("Error leyendo cuerpo del mensaje IPC: {}", e);
-        }
-        
-        // Deserializar request
-        let request: IpcRequest = bincode::deserialize(&buf[..len])
-            .context("Error deserializando request IPC")?;
-        
-        // Log de entrada (solo nivel trace para no saturar con el loop)
-        tracing::trace!("📥 IPC Request: {:?}", request);
-        
-        // Procesar request
-        let response = match request {
-            IpcRequest::Ping => IpcResponse::Pong,
-            IpcRequest::GetFileStatus { path } => {
-                let data = get_extended_file_status(&db, &mirror_path, &cache_dir, &path).await;
-                IpcResponse::ExtendedStatus(data)
+    while let Some(payload) = read_frame(&mut stream, &mut buf).await? {
+        // Una variante desconocida (cliente más nuevo) no rompe la conexión
+        let response = match decode::<IpcRequest>(payload) {
+            Ok(request) => {
+                // Log de entrada (solo nivel trace para no saturar con el loop)
+                tracing::trace!("📥 IPC Request: {:?}", request);
+                process_request(&ctx, request).await
             }
-            IpcRequest::GetFileAvailability { path } => {
-                let avail = get_file_availability(&db, &mirror_path, &path).await;
-                IpcResponse::Availability(avail)
+            Err(e) => {
+                tracing::debug!("⚠️ Request IPC no reconocido: {}", e);
+                IpcResponse::Error { message: format!("Request IPC no reconocido: {}", e) }
             }
-            IpcRequest::SetOnlineOnly { path } => {
-                // Validación para evitar borrar archivos no sincronizados
-                let rel = if path.starts_with(mirror_path.to_string_lossy().as_ref()) {
-                    path.strip_prefix(mirror_path.to_string_lossy().as_ref()).unwrap_or(&path).trim_start_matches('/')
-                } else {
-                    &path
-                };
-                
-                let can_free_space = if let Ok(Some((_, gdrive_id))) = resolve_path_to_inode_and_gdrive_id(&db, rel).await {
-                    !gdrive_id.starts_with("temp_")
-                } else {
-                    true // Si no encontramos inode, dejamos que el error se maneje más adelante
-                };
-
-                if !can_free_space {
-                    IpcResponse::Error { message: "El archivo aún no se ha sincronizado con Google Drive. No se puede liberar espacio.".to_string() }
-                } else {
-                    match set_availability(&mirror_tx, &path, "online_only").await {
-                        Ok(()) => IpcResponse::Success,
-                        Err(e) => IpcResponse::Error { message: e.to_string() },
+        };
+
+        // Log de salida (trace)
+        tracing::trace!("📤 IPC Response: {:?}", response);
+
+        let response_bytes = encode(&response)
+            .context("Error serializando respuesta IPC")?;
+        write_frame(&mut stream, &response_bytes).await?;
+    }
+    Ok(())
+}
+
+/// Respuesta a una petición que modifica algo en modo solo lectura
+const READ_ONLY_MESSAGE: &str = "G-DriveXP está en modo solo lectura: no se modifica nada en Drive ni en este dispositivo";
+
+/// Procesa una petición IPC independiente del transporte (Unix Socket o D-Bus)
+pub async fn process_request(ctx: &IpcContext, request: IpcRequest) -> IpcResponse {
+    let db = &ctx.db;
+    let mirror_path = &ctx.mirror_path;
+    let cache_dir = &ctx.cache_dir;
+    let mirror_tx = &ctx.mirror_tx;
+    let ignore_rules = &ctx.ignore_rules;
+    let inflight = ctx.inflight.as_ref();
+    let uploads = &ctx.upload_progress;
+
+    if ctx.read_only && request.is_mutating() {
+        return IpcResponse::Error { message: READ_ONLY_MESSAGE.to_string() };
+    }
+
+    match request {
+        IpcRequest::Ping => IpcResponse::Pong,
+        IpcRequest::GetFileStatus { path } => {
+            let data = get_extended_file_status(db, mirror_path, cache_dir, ignore_rules, inflight, uploads, &path).await;
+            IpcResponse::ExtendedStatus(data)
+        }
+        IpcRequest::GetFileAvailability { path } => {
+            let avail = get_file_availability(db, mirror_path, &path).await;
+            IpcResponse::Availability(avail)
+        }
+        IpcRequest::SetOnlineOnly { path } => {
+            // Validación para evitar borrar archivos no sincronizados
+            let rel = if path.starts_with(mirror_path.to_string_lossy().as_ref()) {
+                path.strip_prefix(mirror_path.to_string_lossy().as_ref()).unwrap_or(&path).trim_start_matches('/')
+            } else {
+                &path
+            };
+            
+            let inode = resolve_path_to_inode_and_gdrive_id(db, rel).await.ok().flatten().map(|(inode, _)| inode);
+            let can_free_space = match inode {
+                Some(inode) => !db.is_pending_create(inode).await.unwrap_or(false),
+                None => true, // Si no encontramos inode, dejamos que el error se maneje más adelante
+            };
+
+            if !can_free_space {
+                IpcResponse::Error { message: "El archivo aún no se ha sincronizado con Google Drive. No se puede liberar espacio.".to_string() }
+            } else {
+                match set_availability(mirror_tx, &path, "online_only").await {
+                    Ok(()) => {
+                        publish_pin_hint(ctx, &path, false);
+                        // El espejo solo cambia la copia visible por un symlink: el
+                        // contenido descargado en la caché de FUSE se libera aquí
+                        if let Some(inode) = inode {
+                            let manager = crate::fuse::cache_manager::CacheManager::new(db.clone(), cache_dir.clone())
+                                .with_file_locks(ctx.file_locks.clone());
+                            if let Err(e) = manager.evict_subtree(inode).await {
+                                tracing::warn!("⚠️ No se pudo liberar la caché de {}: {:?}", rel, e);
+                            }
+                        }
+                        IpcResponse::Success
                     }
+                    Err(e) => IpcResponse::Error { message: e.to_string() },
                 }
             }
-            IpcRequest::SetLocalOnline { path } => {
-                match set_availability(&mirror_tx, &path, "local_online").await {
-                    Ok(()) => IpcResponse::Success,
-                    Err(e) => IpcResponse::Error { message: e.to_string() },
+        }
+        IpcRequest::SetLocalOnline { path } => {
+            match set_availability(mirror_tx, &path, "local_online").await {
+                Ok(()) => {
+                    publish_pin_hint(ctx, &path, true);
+                    IpcResponse::Success
                 }
+                Err(e) => IpcResponse::Error { message: e.to_string() },
             }
-        };
-        
-        // Log de salida (trace)
-        tracing::trace!("📤 IPC Response: {:?}", response);
-        
-        // Serializar respuesta
-        let response_bytes = bincode::serialize(&response)
-            .context("Error serializando respuesta IPC")?;
-        
-        // Escribir longitud + respuesta
-        let len_bytes = (response_bytes.len() as u32).to_be_bytes();
-        stream.write_all(&len_bytes).await?;
-        stream.write_all(&response_bytes).await?;
+        }
+        IpcRequest::GetStatusBatch { paths } => {
+            let mut statuses = Vec::with_capacity(paths.len());
+            for path in paths {
+                let data = get_extended_file_status(db, mirror_path, cache_dir, ignore_rules, inflight, uploads, &path).await;
+                statuses.push(PathStatus { path, data });
+            }
+            IpcResponse::StatusBatch(statuses)
+        }
+        IpcRequest::GetDirStatus { dir } => {
+            match get_dir_children_status(db, mirror_path, cache_dir, ignore_rules, inflight, uploads, &dir).await {
+                Ok(statuses) => IpcResponse::StatusBatch(statuses),
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::CreateShareLink { path, role } => {
+            match create_share_link(db, ctx.drive_client.as_deref(), mirror_path, &path, &role).await {
+                Ok(url) => IpcResponse::Link { url },
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::GetWebLink { path } => {
+            match get_web_link(db, ctx.drive_client.as_deref(), mirror_path, &path).await {
+                Ok(url) => IpcResponse::Link { url },
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::Search { query } => {
+            match search_files(db, mirror_path, &query).await {
+                Ok(results) => IpcResponse::SearchResults(results),
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::GetDaemonStats => IpcResponse::DaemonStats(crate::metrics::snapshot()),
+        IpcRequest::ListRevisions { path } => {
+            match list_revisions(db, ctx.drive_client.as_deref(), mirror_path, &path).await {
+                Ok(revisions) => IpcResponse::Revisions(revisions),
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::RestoreRevision { path, revision_id, as_copy } => {
+            match restore_revision(db, ctx.drive_client.as_deref(), mirror_path, &path, &revision_id, as_copy).await {
+                Ok(dest) => IpcResponse::RevisionRestored { path: dest.to_string_lossy().into_owned() },
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::SetStarred { path, starred } => {
+            match set_starred(db, ctx.drive_client.as_deref(), mirror_path, &path, starred).await {
+                Ok(()) => IpcResponse::Success,
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::SetDescription { path, description } => {
+            match set_description(db, ctx.drive_client.as_deref(), mirror_path, &path, description.as_deref()).await {
+                Ok(()) => IpcResponse::Success,
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::ForceSync => {
+            crate::sync::trigger::request_sync_now();
+            IpcResponse::Success
+        }
+        IpcRequest::ShowProperties { path } => {
+            match show_properties(ctx.properties_tx.as_ref(), mirror_path, &path) {
+                Ok(()) => IpcResponse::Success,
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::ListTrash => {
+            match list_trash(db).await {
+                Ok(items) => IpcResponse::TrashItems(items),
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
+        IpcRequest::DeletePermanently { inode } => {
+            match delete_permanently(db, inode).await {
+                Ok(()) => IpcResponse::Success,
+                Err(e) => IpcResponse::Error { message: e.to_string() },
+            }
+        }
     }
 }
 
@@ -197,6 +539,9 @@ async fn get_extended_file_status(
     db: &MetadataRepository,
     mirror_path: &std::path::Path,
     cache_dir: &std::path::Path,
+    ignore_rules: &IgnoreRules,
+    inflight: &InflightDownloads,
+    uploads: &UploadProgress,
     file_path: &str,
 ) -> super::FileStatusData {
     // Decodificar URI
@@ -226,6 +571,19 @@ async fn get_extended_file_status(
                         match get_sync_state(db, cache_dir, inode, &gdrive_id, &path_str).await {
                             Ok(status) => {
                                 data.status = status;
+                                // Los cambios locales pendientes tienen prioridad sobre la descarga
+                                if matches!(status, SyncStatus::Synced | SyncStatus::CloudOnly)
+                                    && inflight.is_hydrating(inode)
+                                    && let Ok(percent) = hydration_percent(db, inode).await
+                                {
+                                    data.status = SyncStatus::Downloading(percent);
+                                }
+                                // Subida en curso de este archivo: con su porcentaje
+                                if status == SyncStatus::Syncing
+                                    && let Some(percent) = uploads.percent(inode)
+                                {
+                                    data.status = SyncStatus::Uploading(percent);
+                                }
                             }
                             Err(e) => {
                                 tracing::warn!("[IPC] path={} get_sync_state ERROR: {}", rel, e);
@@ -238,6 +596,10 @@ async fn get_extended_file_status(
                             data.is_shared = attrs.shared;
                         }
                     }
+                    Ok(None) if ignore_rules.is_ignored_path(std::path::Path::new(rel)) => {
+                        // Archivo real del espejo que nunca se registra (ignore_patterns)
+                        data.status = SyncStatus::LocalOnly;
+                    }
                     Ok(None) => {
                         tracing::info!("[IPC] path={} resolve returned None", rel);
                     }
@@ -256,6 +618,432 @@ async fn get_extended_file_status(
     data
 }
 
+/// Obtiene el estado extendido de todos los hijos de un directorio del mirror.
+/// Enumera las entradas físicas (lo mismo que muestra el gestor de archivos).
+async fn get_dir_children_status(
+    db: &MetadataRepository,
+    mirror_path: &std::path::Path,
+    cache_dir: &std::path::Path,
+    ignore_rules: &IgnoreRules,
+    inflight: &InflightDownloads,
+    uploads: &UploadProgress,
+    dir: &str,
+) -> Result<Vec<PathStatus>> {
+    let dir_path = PathBuf::from(decode_file_uri(dir));
+
+    let mut entries = tokio::fs::read_dir(&dir_path)
+        .await
+        .with_context(|| format!("Error leyendo directorio {:?}", dir_path))?;
+
+    let mut statuses = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let child_path = entry.path().to_string_lossy().into_owned();
+        let data = get_extended_file_status(db, mirror_path, cache_dir, ignore_rules, inflight, uploads, &child_path).await;
+        statuses.push(PathStatus { path: child_path, data });
+    }
+
+    Ok(statuses)
+}
+
+/// Resuelve una ruta absoluta (o URI file://) del mirror a su (inode, gdrive_id).
+/// Falla si la ruta está fuera del mirror o el archivo aún no se ha subido.
+pub(crate) async fn resolve_mirror_path_to_gdrive_id(
+    db: &MetadataRepository,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+) -> Result<(u64, String)> {
+    let path_str = decode_file_uri(file_path);
+    let relative = std::path::Path::new(&path_str)
+        .strip_prefix(mirror_path)
+        .map_err(|_| anyhow::anyhow!("La ruta no pertenece a Google Drive: {}", path_str))?;
+
+    let (inode, gdrive_id) = resolve_path_to_inode_and_gdrive_id(db, &relative.to_string_lossy())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Archivo no encontrado en Google Drive: {}", path_str))?;
+
+    if gdrive_id == "SHARED_VIRTUAL" || db.is_pending_create(inode).await? {
+        anyhow::bail!("El archivo aún no se ha sincronizado con Google Drive");
+    }
+
+    Ok((inode, gdrive_id))
+}
+
+/// Crea un enlace "cualquiera con el enlace" para una ruta del mirror
+pub(crate) async fn create_share_link(
+    db: &MetadataRepository,
+    drive_client: Option<&dyn DriveApi>,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+    role: &str,
+) -> Result<String> {
+    if !SHARE_ROLES.contains(&role) {
+        anyhow::bail!("Rol de compartición no válido: {} (esperado: {:?})", role, SHARE_ROLES);
+    }
+
+    let client = drive_client
+        .ok_or_else(|| anyhow::anyhow!("Cliente de Google Drive no disponible"))?;
+    let (_, gdrive_id) = resolve_mirror_path_to_gdrive_id(db, mirror_path, file_path).await?;
+
+    client.create_share_link(&gdrive_id, role).await
+}
+
+/// Obtiene el webViewLink de una ruta del mirror.
+/// Usa el valor guardado durante la sincronización; si aún no se conoce,
+/// lo consulta a la API y lo persiste.
+pub(crate) async fn get_web_link(
+    db: &MetadataRepository,
+    drive_client: Option<&dyn DriveApi>,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+) -> Result<String> {
+    let (inode, gdrive_id) = resolve_mirror_path_to_gdrive_id(db, mirror_path, file_path).await?;
+
+    if let Some(link) = db.get_web_view_link(inode).await? {
+        return Ok(link);
+    }
+
+    let client = drive_client
+        .ok_or_else(|| anyhow::anyhow!("Cliente de Google Drive no disponible"))?;
+    let link = client.get_web_view_link(&gdrive_id).await?;
+
+    if let Err(e) = db.set_web_view_link(inode, &link).await {
+        tracing::warn!("No se pudo guardar webViewLink de inode {}: {:?}", inode, e);
+    }
+
+    Ok(link)
+}
+
+/// Versiones de un archivo del mirror, de la más antigua a la actual
+pub(crate) async fn list_revisions(
+    db: &MetadataRepository,
+    drive_client: Option<&dyn DriveApi>,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+) -> Result<Vec<RevisionInfo>> {
+    let client = drive_client
+        .ok_or_else(|| anyhow::anyhow!("Cliente de Google Drive no disponible"))?;
+    let (_, revisions) = file_revisions(db, client, mirror_path, file_path).await?;
+    Ok(revisions)
+}
+
+/// Marca o desmarca como destacado un archivo del mirror (files.update en
+/// Drive) y guarda el flag en la DB para que aparezca o salga de `Starred/`
+/// sin esperar al siguiente ciclo de cambios
+pub(crate) async fn set_starred(
+    db: &MetadataRepository,
+    drive_client: Option<&dyn DriveApi>,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+    starred: bool,
+) -> Result<()> {
+    let client = drive_client
+        .ok_or_else(|| anyhow::anyhow!("Cliente de Google Drive no disponible"))?;
+    let (inode, gdrive_id) = resolve_mirror_path_to_gdrive_id(db, mirror_path, file_path).await?;
+
+    client.set_starred(&gdrive_id, starred).await?;
+    db.set_starred(inode, starred).await?;
+    tracing::info!("⭐ {} {}", if starred { "Destacado:" } else { "Ya no destacado:" }, file_path);
+    Ok(())
+}
+
+/// Cambia la descripción de un archivo del mirror en Drive y la guarda en la
+/// DB. Una descripción vacía o solo con espacios la borra
+pub(crate) async fn set_description(
+    db: &MetadataRepository,
+    drive_client: Option<&dyn DriveApi>,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+    description: Option<&str>,
+) -> Result<()> {
+    let client = drive_client
+        .ok_or_else(|| anyhow::anyhow!("Cliente de Google Drive no disponible"))?;
+    let (inode, gdrive_id) = resolve_mirror_path_to_gdrive_id(db, mirror_path, file_path).await?;
+    let description = description.map(str::trim).filter(|d| !d.is_empty());
+
+    client.set_description(&gdrive_id, description).await?;
+    db.set_description(inode, description).await?;
+    tracing::info!("📝 Descripción actualizada: {}", file_path);
+    Ok(())
+}
+
+/// Elementos eliminados en local (los mismos que la carpeta virtual `.Trash`
+/// de FUSE)
+pub(crate) async fn list_trash(db: &MetadataRepository) -> Result<Vec<super::TrashItem>> {
+    Ok(db.list_trashed().await?
+        .into_iter()
+        .map(|(inode, name, is_dir, _, _)| super::TrashItem { inode, name, is_dir })
+        .collect())
+}
+
+/// Pide eliminar definitivamente un elemento de la papelera local. El
+/// uploader llama a `files.delete` en el ciclo que se solicita aquí.
+pub(crate) async fn delete_permanently(db: &MetadataRepository, inode: u64) -> Result<()> {
+    if !db.request_permanent_delete(inode).await? {
+        anyhow::bail!("El elemento ya no está en la papelera");
+    }
+    tracing::info!("🔥 Eliminación definitiva solicitada (inode {})", inode);
+    crate::sync::trigger::request_sync_now();
+    Ok(())
+}
+
+/// Indica si un archivo del mirror está destacado (según la DB)
+pub(crate) async fn is_starred(
+    db: &MetadataRepository,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+) -> Result<bool> {
+    let (inode, _) = resolve_mirror_path_to_gdrive_id(db, mirror_path, file_path).await?;
+    db.is_starred(inode).await
+}
+
+/// Publica en segundo plano la marca `app_properties::PIN_KEY` de un archivo
+/// del mirror, para que otras instalaciones lo mantengan también. Sin red
+/// queda pendiente y el sincronizador la reintenta en cada ciclo
+fn publish_pin_hint(ctx: &IpcContext, file_path: &str, pinned: bool) {
+    let Some(client) = ctx.drive_client.clone() else {
+        return;
+    };
+    let (db, mirror_path, file_path) = (ctx.db.clone(), ctx.mirror_path.clone(), file_path.to_string());
+    tokio::spawn(async move {
+        if let Err(e) = set_pin_hint(&db, client.as_ref(), &mirror_path, &file_path, pinned).await {
+            tracing::debug!("No se publicó la marca de anclado de {}: {:?}", file_path, e);
+        }
+    });
+}
+
+/// Guarda y publica en Drive si el usuario mantiene un archivo del mirror en el dispositivo
+pub(crate) async fn set_pin_hint(
+    db: &MetadataRepository,
+    client: &dyn DriveApi,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+    pinned: bool,
+) -> Result<()> {
+    let (inode, gdrive_id) = resolve_mirror_path_to_gdrive_id(db, mirror_path, file_path).await?;
+    // Primero en local y pendiente: mientras no se publique, la marca vieja de
+    // Drive no debe volver a anclar aquí lo que el usuario acaba de liberar
+    db.set_pin_hint(inode, pinned).await?;
+    client.set_app_property(&gdrive_id, app_properties::PIN_KEY, app_properties::pin_value(pinned)).await?;
+    db.pin_hint_published(inode, pinned).await
+}
+
+/// Datos de Drive y de sincronización de un archivo del mirror (diálogo de propiedades)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDetails {
+    pub size: u64,
+    pub is_dir: bool,
+    /// MD5 de la versión remota conocida (Drive no lo calcula para carpetas ni documentos de Google)
+    pub md5: Option<String>,
+    /// Propietarios según Drive ("Nombre <correo>")
+    pub owners: Vec<String>,
+    pub owned_by_me: bool,
+    pub shared: bool,
+    /// `createdTime` de Drive (epoch en segundos; `None` si aún no se conoce)
+    pub created_time: Option<i64>,
+    /// Instalación que lo creó según sus `appProperties` (`None`: no fue G-DriveXP)
+    pub origin: Option<FileOrigin>,
+    /// `description` de Drive
+    pub description: Option<String>,
+    /// `folderColorRgb` de Drive ("#rrggbb"), solo en carpetas
+    pub folder_color: Option<String>,
+    pub status: SyncStatus,
+    pub availability: FileAvailability,
+    /// Porcentaje del contenido presente en la caché de FUSE (`None` en carpetas)
+    pub cached_percent: Option<u8>,
+}
+
+/// Instalación de G-DriveXP que creó un archivo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileOrigin {
+    ThisDevice,
+    OtherDevice,
+}
+
+/// Reúne los datos del diálogo de propiedades. A diferencia de las peticiones
+/// que llaman a la API, acepta archivos aún no subidos (solo lee la DB).
+pub(crate) async fn file_details(ctx: &IpcContext, file_path: &str) -> Result<FileDetails> {
+    let (db, mirror_path) = (ctx.db.as_ref(), ctx.mirror_path.as_path());
+    let path_str = decode_file_uri(file_path);
+    let relative = std::path::Path::new(&path_str)
+        .strip_prefix(mirror_path)
+        .map_err(|_| anyhow::anyhow!("La ruta no pertenece a Google Drive: {}", path_str))?;
+    let (inode, _) = resolve_path_to_inode_and_gdrive_id(db, &relative.to_string_lossy())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Archivo no encontrado en Google Drive: {}", path_str))?;
+
+    let attrs = db.get_attrs(inode).await?;
+    let size = attrs.size.max(0) as u64;
+    let cached_percent = if attrs.is_dir {
+        None
+    } else {
+        Some(cached_percent(db.get_cached_bytes_count(inode).await?, size))
+    };
+    let status = get_extended_file_status(db, mirror_path, &ctx.cache_dir, &ctx.ignore_rules, &ctx.inflight, &ctx.upload_progress, &path_str).await;
+
+    let origin = match db.get_origin_client(inode).await? {
+        Some(client) if client == db.client_id().await? => Some(FileOrigin::ThisDevice),
+        Some(_) => Some(FileOrigin::OtherDevice),
+        None => None,
+    };
+
+    Ok(FileDetails {
+        size,
+        is_dir: attrs.is_dir,
+        md5: db.get_remote_md5(inode).await?,
+        owners: db.get_capabilities(inode).await?.map(|caps| caps.owners).unwrap_or_default(),
+        owned_by_me: attrs.owned_by_me,
+        shared: attrs.shared,
+        created_time: attrs.created_time,
+        origin,
+        description: db.get_description(inode).await?,
+        folder_color: db.get_folder_color(inode).await?,
+        status: status.status,
+        availability: status.availability,
+        cached_percent,
+    })
+}
+
+/// Porcentaje en caché (los chunks pueden solaparse tras una reescritura: se acota a 100)
+fn cached_percent(cached_bytes: u64, size: u64) -> u8 {
+    if size == 0 {
+        return 100;
+    }
+    (cached_bytes.saturating_mul(100) / size).min(100) as u8
+}
+
+/// Porcentaje ya descargado de un archivo en hidratación
+async fn hydration_percent(db: &MetadataRepository, inode: u64) -> Result<u8> {
+    let size = db.get_size(inode).await?.unwrap_or(0).max(0) as u64;
+    Ok(cached_percent(db.get_cached_bytes_count(inode).await?, size))
+}
+
+/// Pide a la GUI que muestre las propiedades de una ruta del mirror
+fn show_properties(
+    properties_tx: Option<&mpsc::UnboundedSender<PathBuf>>,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+) -> Result<()> {
+    let tx = properties_tx.ok_or_else(|| anyhow::anyhow!("Interfaz gráfica no disponible"))?;
+    let path = PathBuf::from(decode_file_uri(file_path));
+    if !path.starts_with(mirror_path) {
+        anyhow::bail!("La ruta no pertenece a Google Drive: {}", path.display());
+    }
+    tx.send(path).map_err(|_| anyhow::anyhow!("La interfaz gráfica ya no está activa"))
+}
+
+/// Recupera una versión anterior de un archivo del mirror. La escribe en el
+/// propio mirror (sobre el original o como `nombre (rev N)` al lado), de modo
+/// que llega a Drive por el mismo camino que cualquier edición local: Drive
+/// no tiene una operación de "restaurar", la versión restaurada es una nueva.
+/// Retorna la ruta escrita.
+pub(crate) async fn restore_revision(
+    db: &MetadataRepository,
+    drive_client: Option<&dyn DriveApi>,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+    revision_id: &str,
+    as_copy: bool,
+) -> Result<PathBuf> {
+    let client = drive_client
+        .ok_or_else(|| anyhow::anyhow!("Cliente de Google Drive no disponible"))?;
+    let (gdrive_id, revisions) = file_revisions(db, client, mirror_path, file_path).await?;
+    let revision = revisions
+        .iter()
+        .find(|r| r.id == revision_id)
+        .ok_or_else(|| anyhow::anyhow!("La versión {} ya no existe en Drive", revision_id))?;
+
+    let original = PathBuf::from(decode_file_uri(file_path));
+    let dest = if as_copy {
+        let name = original.file_name().unwrap_or_default().to_string_lossy();
+        // Copia de una versión: `informe (rev 3).pdf`
+        let copy_name = crate::utils::names::suffixed_name(&name, &format!("rev {}", revision.number), false);
+        let dest = original.with_file_name(copy_name);
+        if tokio::fs::symlink_metadata(&dest).await.is_ok() {
+            anyhow::bail!("Ya existe {:?}", dest);
+        }
+        dest
+    } else {
+        if revision.is_current {
+            anyhow::bail!("Esa versión ya es el contenido actual del archivo");
+        }
+        original
+    };
+
+    // Se descarga fuera de la vista del watcher y se copia al destino de una
+    // vez: el watcher ve un único cambio completo (y si el original es un
+    // symlink Online Only, la copia escribe a través de él en FUSE)
+    let tmp_dir = mirror_path.join(".gdrive_tmp_ops");
+    tokio::fs::create_dir_all(&tmp_dir).await?;
+    let tmp = tmp_dir.join(format!("revision_{}", uuid::Uuid::new_v4()));
+    let result = async {
+        client.download_revision(&gdrive_id, revision_id, &tmp).await?;
+        tokio::fs::copy(&tmp, &dest)
+            .await
+            .with_context(|| format!("No se pudo escribir {:?}", dest))
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&tmp).await;
+    result?;
+
+    tracing::info!("🕘 Versión {} restaurada en {:?}", revision.number, dest);
+    Ok(dest)
+}
+
+/// gdrive_id y versiones de un archivo del mirror (las carpetas no tienen)
+async fn file_revisions(
+    db: &MetadataRepository,
+    client: &dyn DriveApi,
+    mirror_path: &std::path::Path,
+    file_path: &str,
+) -> Result<(String, Vec<RevisionInfo>)> {
+    let (inode, gdrive_id) = resolve_mirror_path_to_gdrive_id(db, mirror_path, file_path).await?;
+    if db.is_dir(inode).await? == Some(true) {
+        anyhow::bail!("Las carpetas no tienen historial de versiones");
+    }
+
+    let revisions = client.list_revisions(&gdrive_id).await?;
+    let current = revisions.len();
+    let infos = revisions
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, revision)| {
+            Some(RevisionInfo {
+                id: revision.id?,
+                number: index as u32 + 1,
+                modified_time: revision.modified_time.map(|t| t.timestamp()),
+                size: revision.size.map(|size| size.max(0) as u64),
+                author: revision.last_modifying_user.and_then(|user| user.display_name),
+                keep_forever: revision.keep_forever.unwrap_or(false),
+                is_current: index + 1 == current,
+            })
+        })
+        .collect();
+    Ok((gdrive_id, infos))
+}
+
+/// Busca archivos por nombre (índice FTS) y retorna sus rutas en el espejo
+pub(crate) async fn search_files(
+    db: &MetadataRepository,
+    mirror_path: &std::path::Path,
+    query: &str,
+) -> Result<Vec<SearchResult>> {
+    let hits = db.search_names(query, MAX_SEARCH_RESULTS).await?;
+
+    let mut results = Vec::with_capacity(hits.len());
+    for (inode, name, is_dir) in hits {
+        // Entradas huérfanas (padre eliminado) no tienen ruta visible
+        let Some(relative) = db.resolve_inode_to_relative_path(inode).await? else {
+            continue;
+        };
+        results.push(SearchResult {
+            path: mirror_path.join(relative).to_string_lossy().into_owned(),
+            name,
+            is_dir,
+        });
+    }
+    Ok(results)
+}
+
 /// Resuelve un path relativo a su inode y gdrive_id
 async fn resolve_path_to_inode_and_gdrive_id(
     db: &MetadataRepository,
@@ -271,46 +1059,10 @@ async fn resolve_path_to_inode_and_gdrive_id(
         }
         // Hijos de SHARED están bajo root (inode 1) con owned_by_me=0
         // Saltar "SHARED" y resolver normalmente desde root
-        let mut current_inode = 1u64;
-        for part in &parts[1..] {
-            match db.lookup(current_inode, part).await? {
-                Some(child_inode) => current_inode = child_inode,
-                None => return Ok(None),
-            }
-        }
-        let gdrive_id: Option<String> = sqlx::query_scalar(
-            "SELECT gdrive_id FROM inodes WHERE inode = ?"
-        )
-        .bind(current_inode as i64)
-        .fetch_optional(db.pool())
-        .await?;
-        return match gdrive_id {
-            Some(id) => Ok(Some((current_inode, id))),
-            None => Ok(None),
-        };
-    }
-
-    let mut current_inode = 1u64; // Root inode
-
-    for part in parts {
-        match db.lookup(current_inode, part).await? {
-            Some(child_inode) => current_inode = child_inode,
-            None => return Ok(None),
-        }
+        return db.resolve_path_components(&parts[1..]).await;
     }
 
-    // Obtener gdrive_id del inode final
-    let gdrive_id: Option<String> = sqlx::query_scalar(
-        "SELECT gdrive_id FROM inodes WHERE inode = ?"
-    )
-    .bind(current_inode as i64)
-    .fetch_optional(db.pool())
-    .await?;
-
-    match gdrive_id {
-        Some(id) => Ok(Some((current_inode, id))),
-        None => Ok(None),
-    }
+    db.resolve_path_components(&parts).await
 }
 
 /// Consulta el estado de sincronización en sync_state
@@ -325,8 +1077,11 @@ async fn get_sync_state(
     if inode == SHARED_INODE {
         let (has_local_only, has_synced, _total_files) =
             db.get_shared_directory_aggregate_status().await?;
+        let has_uploading = has_local_only && db.has_uploading_shared_descendant().await?;
 
-        return Ok(if has_local_only {
+        return Ok(if has_uploading {
+            SyncStatus::Syncing
+        } else if has_local_only {
             SyncStatus::LocalOnly
         } else if has_synced {
             SyncStatus::Synced
@@ -335,6 +1090,11 @@ async fn get_sync_state(
         });
     }
 
+    // Ignorados (ignore_patterns): existen solo en local y nunca se suben
+    if db.is_ignored(inode).await? {
+        return Ok(SyncStatus::LocalOnly);
+    }
+
     // 1. Verificación Física (Source of Truth para UI)
     // Si es un Symlink -> CloudOnly
     // Si es File -> Synced
@@ -360,19 +1120,21 @@ async fn get_sync_state(
     };
 
     // Obtener atributos para verificar si es directorio
-    let is_dir: Option<bool> = sqlx::query_scalar(
-        "SELECT is_dir FROM attrs WHERE inode = ?"
-    )
-    .bind(inode as i64)
-    .fetch_optional(db.pool())
-    .await?;
-    
+    let is_dir = db.is_dir(inode).await?;
+
     // Directorios: calcular estado agregado recursivo de todos sus descendientes
     if is_dir == Some(true) {
         let (has_local_only, has_synced, _total_files) =
             db.get_directory_aggregate_status(inode).await?;
+        // Solo puede haber uploads en curso o fallidos si hay descendientes dirty
+        let has_uploading = has_local_only && db.has_uploading_descendant(inode).await?;
+        let has_error = has_local_only && !has_uploading && db.has_error_descendant(inode).await?;
 
-        return Ok(if has_local_only {
+        return Ok(if has_uploading {
+            SyncStatus::Syncing // Al menos 1 descendiente subiendo
+        } else if has_error {
+            SyncStatus::Error // Rojo: al menos 1 descendiente cuya subida falló
+        } else if has_local_only {
             SyncStatus::LocalOnly // Naranja: al menos 1 hijo con cambios pendientes
         } else if has_synced {
             SyncStatus::Synced // Verde: al menos 1 hijo sincronizado
@@ -382,13 +1144,8 @@ async fn get_sync_state(
     }
     
     // Obtener el tamaño esperado del archivo desde la base de datos
-    let expected_size: Option<i64> = sqlx::query_scalar(
-        "SELECT size FROM attrs WHERE inode = ?"
-    )
-    .bind(inode as i64)
-    .fetch_optional(db.pool())
-    .await?;
-    
+    let expected_size = db.get_size(inode).await?;
+
     // Verificar si el archivo está COMPLETAMENTE cacheado usando file_cache_chunks
     // Esta es la forma correcta ya que usamos caché por chunks, no archivos completos
     let has_complete_cache = if let Some(expected) = expected_size {
@@ -405,20 +1162,19 @@ async fn get_sync_state(
         false
     };
     
-    // Consultar si está dirty
-    let result = sqlx::query_as::<_, (bool, Option<i64>)>(
-        "SELECT dirty, deleted_at FROM sync_state WHERE inode = ?"
-    )
-    .bind(inode as i64)
-    .fetch_optional(db.pool())
-    .await?;
-    
-    match result {
-        Some((dirty, deleted_at)) => {
-            if deleted_at.is_some() {
+    // Consultar si está dirty o subiendo
+    match db.sync_state(inode).await? {
+        Some(state) => {
+            if state.uploading {
+                // Upload en curso
+                Ok(SyncStatus::Syncing)
+            } else if state.status == UploadStatus::Error {
+                // El último intento de subida falló (se reintentará)
+                Ok(SyncStatus::Error)
+            } else if state.deleted_at.is_some() {
                 // Archivo marcado para eliminación
                 Ok(SyncStatus::LocalOnly)
-            } else if dirty {
+            } else if state.dirty {
                 // Cambios locales pendientes de subir
                 Ok(SyncStatus::LocalOnly)
             } else {
@@ -451,6 +1207,13 @@ async fn get_file_availability(
              let relative_str = relative.to_string_lossy();
              // Resolver inode
              if let Ok(Some((inode, _))) = resolve_path_to_inode_and_gdrive_id(db, &relative_str).await {
+                 // Carpetas: la elección guardada en ella o en el ancestro más cercano
+                 if let Ok(Some(true)) = db.is_dir(inode).await {
+                     return match db.folder_keeps_content(inode).await {
+                         Ok(true) => FileAvailability::LocalOnline,
+                         _ => FileAvailability::OnlineOnly,
+                     };
+                 }
                  // Consultar disponibilidad en DB
                  if let Ok(avail_str) = db.get_availability(inode).await {
                      return match avail_str.as_str() {
@@ -524,3 +1287,392 @@ impl Drop for IpcServer {
         let _ = std::fs::remove_file(&self.socket_path);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_fixtures;
+    use crate::gdrive::mock::{MOCK_ROOT_ID, MockDriveClient};
+    use rstest::*;
+    use std::path::Path;
+
+    /// Mirror con `nota.txt` (dos versiones en Drive) ya volcado a la DB
+    struct RevisionFixture {
+        mirror: tempfile::TempDir,
+        mock: Arc<MockDriveClient>,
+        db: Arc<MetadataRepository>,
+    }
+
+    impl RevisionFixture {
+        async fn new() -> Self {
+            let mock = Arc::new(MockDriveClient::new());
+            mock.add_file("file-nota", "nota.txt", MOCK_ROOT_ID, b"primera");
+            mock.modify_remote("file-nota", b"segunda");
+
+            let db = test_fixtures::synced_in_memory(&mock).await;
+
+            let mirror = tempfile::tempdir().unwrap();
+            std::fs::write(mirror.path().join("nota.txt"), b"segunda").unwrap();
+            Self { mirror, mock, db }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.mirror.path().join(name)
+        }
+
+        async fn restore(&self, revision_id: &str, as_copy: bool) -> Result<PathBuf> {
+            let note = self.path("nota.txt").to_string_lossy().into_owned();
+            restore_revision(&self.db, Some(self.mock.as_ref()), self.mirror.path(), &note, revision_id, as_copy).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_revisions_numbers_oldest_first() {
+        let fx = RevisionFixture::new().await;
+        let note = fx.path("nota.txt").to_string_lossy().into_owned();
+
+        let revisions = list_revisions(&fx.db, Some(fx.mock.as_ref()), fx.mirror.path(), &note).await.unwrap();
+
+        assert_eq!(revisions.iter().map(|r| r.number).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(revisions.iter().map(|r| r.is_current).collect::<Vec<_>>(), [false, true]);
+        assert_eq!(revisions[0].size, Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_restore_revision_overwrites_original() {
+        let fx = RevisionFixture::new().await;
+        let first = fx.mock.revision_ids("file-nota")[0].clone();
+
+        let dest = fx.restore(&first, false).await.unwrap();
+
+        assert_eq!(dest, fx.path("nota.txt"));
+        assert_eq!(std::fs::read(&dest).unwrap(), b"primera");
+        assert_eq!(std::fs::read_dir(fx.path(".gdrive_tmp_ops")).unwrap().count(), 0, "sin temporales");
+    }
+
+    #[tokio::test]
+    async fn test_restore_revision_as_copy_keeps_original() {
+        let fx = RevisionFixture::new().await;
+        let first = fx.mock.revision_ids("file-nota")[0].clone();
+
+        let dest = fx.restore(&first, true).await.unwrap();
+
+        assert_eq!(dest, fx.path("nota (rev 1).txt"));
+        assert_eq!(std::fs::read(&dest).unwrap(), b"primera");
+        assert_eq!(std::fs::read(fx.path("nota.txt")).unwrap(), b"segunda");
+        assert!(fx.restore(&first, true).await.is_err(), "no pisa una copia existente");
+    }
+
+    #[tokio::test]
+    async fn test_restore_current_revision_in_place_is_rejected() {
+        let fx = RevisionFixture::new().await;
+        let current = fx.mock.revision_ids("file-nota")[1].clone();
+
+        assert!(fx.restore(&current, false).await.is_err());
+        assert!(fx.restore("no-existe", true).await.is_err());
+        assert_eq!(fx.mock.calls("download_revision"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_set_starred_updates_drive_and_db() {
+        let fx = RevisionFixture::new().await;
+        let note = fx.path("nota.txt").to_string_lossy().into_owned();
+        assert!(!is_starred(&fx.db, fx.mirror.path(), &note).await.unwrap());
+
+        set_starred(&fx.db, Some(fx.mock.as_ref()), fx.mirror.path(), &note, true).await.unwrap();
+
+        assert_eq!(fx.mock.get_file_metadata("file-nota").await.unwrap().starred, Some(true));
+        assert!(is_starred(&fx.db, fx.mirror.path(), &note).await.unwrap());
+        assert_eq!(fx.db.list_starred().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_rejects_mutating_requests() {
+        let fx = RevisionFixture::new().await;
+        let note = fx.path("nota.txt").to_string_lossy().into_owned();
+        let mut ctx = IpcContext::new(fx.db.clone(), fx.mirror.path().to_path_buf(), "/cache".into());
+        ctx.drive_client = Some(fx.mock.clone());
+        ctx.read_only = true;
+
+        let request = IpcRequest::SetStarred { path: note.clone(), starred: true };
+        assert!(matches!(process_request(&ctx, request).await, IpcResponse::Error { .. }));
+        assert_eq!(fx.mock.calls("set_starred"), 0);
+        assert!(!is_starred(&fx.db, fx.mirror.path(), &note).await.unwrap());
+        let request = IpcRequest::GetFileAvailability { path: note };
+        assert!(matches!(process_request(&ctx, request).await, IpcResponse::Availability(_)), "las consultas siguen");
+    }
+
+    #[rstest]
+    #[case::set(Some("  Informe trimestral "), Some("Informe trimestral"))]
+    #[case::blank_clears(Some("   "), None)]
+    #[case::clear(None, None)]
+    #[tokio::test]
+    async fn test_set_description_updates_drive_and_db(#[case] input: Option<&str>, #[case] expected: Option<&str>) {
+        let fx = RevisionFixture::new().await;
+        let note = fx.path("nota.txt").to_string_lossy().into_owned();
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        fx.db.set_description(inode, Some("anterior")).await.unwrap();
+
+        set_description(&fx.db, Some(fx.mock.as_ref()), fx.mirror.path(), &note, input).await.unwrap();
+
+        assert_eq!(fx.mock.get_file_metadata("file-nota").await.unwrap().description.as_deref(), expected);
+        assert_eq!(fx.db.get_description(inode).await.unwrap().as_deref(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_delete_permanently_accepts_trash_items_only() {
+        let fx = RevisionFixture::new().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        assert!(delete_permanently(&fx.db, inode).await.is_err(), "no está eliminado");
+
+        fx.db.soft_delete_by_gdrive_id("file-nota").await.unwrap();
+        let items = list_trash(&fx.db).await.unwrap();
+        assert_eq!(items, vec![crate::ipc::TrashItem { inode, name: "nota.txt".into(), is_dir: false }]);
+
+        delete_permanently(&fx.db, inode).await.unwrap();
+        assert!(fx.db.is_purge_requested(inode).await.unwrap());
+        assert!(list_trash(&fx.db).await.unwrap().is_empty());
+        assert!(delete_permanently(&fx.db, inode).await.is_err(), "ya pedida");
+    }
+
+    #[rstest]
+    #[case::empty(0, 0, 100)]
+    #[case::partial(3, 7, 42)]
+    #[case::complete(7, 7, 100)]
+    #[case::overlapping_chunks(9, 7, 100)]
+    fn test_cached_percent(#[case] cached: u64, #[case] size: u64, #[case] expected: u8) {
+        assert_eq!(cached_percent(cached, size), expected);
+    }
+
+    #[tokio::test]
+    async fn test_file_details_reads_drive_metadata_and_cache() {
+        let fx = RevisionFixture::new().await;
+        let note = fx.path("nota.txt").to_string_lossy().into_owned();
+        let inode = fx.db.get_inode_by_gdrive_id("file-nota").await.unwrap().unwrap();
+        fx.db.set_remote_md5(inode, "0123abcd").await.unwrap();
+        fx.db.add_cached_chunk(inode, 0, 2).await.unwrap();
+
+        let ctx = IpcContext::new(fx.db.clone(), fx.mirror.path().to_path_buf(), "/cache".into());
+
+        let details = file_details(&ctx, &note).await.unwrap();
+
+        assert_eq!(details.size, 7);
+        assert!(!details.is_dir);
+        assert_eq!(details.md5.as_deref(), Some("0123abcd"));
+        assert_eq!(details.cached_percent, Some(42));
+        assert_eq!(details.origin, None, "no lo creó G-DriveXP");
+        assert_eq!(details.description, None);
+        assert_eq!(details.folder_color, None, "no es una carpeta");
+        assert!(file_details(&ctx, "/fuera/nota.txt").await.is_err());
+
+        for (client, origin) in [(fx.db.client_id().await.unwrap(), FileOrigin::ThisDevice), ("otra".into(), FileOrigin::OtherDevice)] {
+            let marks = app_properties::AppMarks { origin_client: Some(client), pinned: false };
+            fx.db.set_app_marks(inode, &marks).await.unwrap();
+            assert_eq!(file_details(&ctx, &note).await.unwrap().origin, Some(origin));
+        }
+    }
+
+    #[rstest]
+    #[case::pin(true, Some("1"))]
+    #[case::unpin(false, None)]
+    #[tokio::test]
+    async fn test_set_pin_hint_updates_drive_and_db(#[case] pinned: bool, #[case] remote: Option<&str>) {
+        let fx = RevisionFixture::new().await;
+        let note = fx.path("nota.txt").to_string_lossy().into_owned();
+        let inode = fx.db.get_inode_by_gdrive_id("file-nota").await.unwrap().unwrap();
+        fx.db.set_app_marks(inode, &app_properties::AppMarks { origin_client: None, pinned: !pinned }).await.unwrap();
+
+        set_pin_hint(&fx.db, fx.mock.as_ref(), fx.mirror.path(), &note, pinned).await.unwrap();
+
+        let file = fx.mock.get_file_metadata("file-nota").await.unwrap();
+        let value = file.app_properties.as_ref().and_then(|props| props.get(app_properties::PIN_KEY));
+        assert_eq!(value.map(String::as_str), remote);
+        assert_eq!(app_properties::AppMarks::from_file(&file).pinned, pinned);
+        assert_eq!(fx.db.pending_pin_hints().await.unwrap().is_empty(), !pinned);
+        assert!(fx.db.unpublished_pin_hints().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_file_status_reports_download_progress() {
+        let fx = RevisionFixture::new().await;
+        let note = fx.path("nota.txt").to_string_lossy().into_owned();
+        let inode = fx.db.get_inode_by_gdrive_id("file-nota").await.unwrap().unwrap();
+        fx.db.add_cached_chunk(inode, 0, 2).await.unwrap();
+        let inflight = Arc::new(InflightDownloads::default());
+        let mut ctx = IpcContext::new(fx.db.clone(), fx.mirror.path().to_path_buf(), "/cache".into());
+        ctx.inflight = inflight.clone();
+        let status = || async {
+            match process_request(&ctx, IpcRequest::GetFileStatus { path: note.clone() }).await {
+                IpcResponse::ExtendedStatus(data) => data.status,
+                other => panic!("respuesta inesperada: {:?}", other),
+            }
+        };
+
+        assert_eq!(status().await, SyncStatus::Synced);
+
+        let hydration = inflight.hydrate(inode);
+        assert_eq!(status().await, SyncStatus::Downloading(42));
+
+        fx.db.mark_dirty(inode).await.unwrap();
+        assert_eq!(status().await, SyncStatus::LocalOnly, "los cambios pendientes tienen prioridad");
+
+        drop(hydration);
+    }
+
+    #[tokio::test]
+    async fn test_file_status_reports_upload_progress() {
+        let fx = RevisionFixture::new().await;
+        let note = fx.path("nota.txt").to_string_lossy().into_owned();
+        let inode = fx.db.get_inode_by_gdrive_id("file-nota").await.unwrap().unwrap();
+        let uploads = UploadProgress::default();
+        let mut ctx = IpcContext::new(fx.db.clone(), fx.mirror.path().to_path_buf(), "/cache".into());
+        ctx.upload_progress = uploads.clone();
+        let status = || async {
+            match process_request(&ctx, IpcRequest::GetFileStatus { path: note.clone() }).await {
+                IpcResponse::ExtendedStatus(data) => data.status,
+                other => panic!("respuesta inesperada: {:?}", other),
+            }
+        };
+
+        fx.db.mark_dirty(inode).await.unwrap();
+        fx.db.set_uploading(inode, true).await.unwrap();
+        assert_eq!(status().await, SyncStatus::Syncing, "sin progreso registrado");
+
+        let upload = uploads.start(inode, 200);
+        uploads.update(inode, 50, 200);
+        assert_eq!(status().await, SyncStatus::Uploading(25));
+
+        drop(upload);
+        assert_eq!(status().await, SyncStatus::Syncing);
+    }
+
+    #[test]
+    fn test_show_properties_forwards_mirror_paths_only() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mirror = Path::new("/home/user/GoogleDrive");
+
+        show_properties(Some(&tx), mirror, "file:///home/user/GoogleDrive/Mi%20informe.pdf").unwrap();
+        assert_eq!(rx.try_recv().unwrap(), mirror.join("Mi informe.pdf"));
+
+        assert!(show_properties(Some(&tx), mirror, "/tmp/otro.pdf").is_err());
+        assert!(show_properties(None, mirror, "/home/user/GoogleDrive/a.txt").is_err());
+        assert!(rx.try_recv().is_err());
+    }
+
+    fn hello(protocol: u32) -> Vec<u8> {
+        encode(&Hello { protocol, client: "test-client 0.1".into() }).unwrap()
+    }
+
+    #[rstest]
+    #[case::same_version(hello(PROTOCOL_VERSION), true)]
+    #[case::older_client(hello(1), false)]
+    #[case::newer_client(hello(PROTOCOL_VERSION + 1), false)]
+    #[case::request_without_hello(encode(&IpcRequest::Ping).unwrap(), false)]
+    fn test_answer_hello(#[case] payload: Vec<u8>, #[case] accepted: bool) {
+        let (reply, ok) = answer_hello(&payload).unwrap();
+        assert_eq!(ok, accepted);
+        match decode::<HelloReply>(&reply).unwrap() {
+            HelloReply::Accepted { protocol, .. } => {
+                assert!(accepted);
+                assert_eq!(protocol, PROTOCOL_VERSION);
+            }
+            HelloReply::Rejected { protocol, message } => {
+                assert!(!accepted);
+                assert_eq!(protocol, PROTOCOL_VERSION);
+                assert!(!message.is_empty());
+            }
+        }
+    }
+
+    /// `IpcResponse` tal como se publicó con el protocolo v1
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    enum V1Response {
+        FileStatus(SyncStatus),
+        ExtendedStatus(crate::ipc::FileStatusData),
+        Pong,
+        Availability(FileAvailability),
+        Success,
+        Error { message: String },
+    }
+
+    #[test]
+    fn test_answer_hello_explains_v1_in_bincode() {
+        let legacy = bincode::serialize(&IpcRequest::Ping).unwrap();
+        let (reply, ok) = answer_hello(&legacy).unwrap();
+        assert!(!ok);
+        match bincode::deserialize::<V1Response>(&reply).unwrap() {
+            V1Response::Error { message } => assert!(message.contains("v1"), "{}", message),
+            other => panic!("respuesta inesperada: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handle_client_handshake_then_requests() {
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        let ctx = IpcContext::new(db, "/mirror".into(), "/cache".into());
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let task = tokio::spawn(handle_client(server, ctx));
+        let mut buf = Vec::new();
+
+        write_frame(&mut client, &hello(PROTOCOL_VERSION)).await.unwrap();
+        let reply = read_frame(&mut client, &mut buf).await.unwrap().unwrap();
+        assert!(matches!(decode(reply).unwrap(), HelloReply::Accepted { .. }));
+
+        write_frame(&mut client, &encode(&IpcRequest::Ping).unwrap()).await.unwrap();
+        let reply = read_frame(&mut client, &mut buf).await.unwrap().unwrap();
+        assert!(matches!(decode(reply).unwrap(), IpcResponse::Pong));
+
+        // Variante de un cliente más nuevo: error, pero la conexión sigue viva
+        write_frame(&mut client, br#"{"FutureRequest":{"x":1}}"#).await.unwrap();
+        let reply = read_frame(&mut client, &mut buf).await.unwrap().unwrap();
+        assert!(matches!(decode(reply).unwrap(), IpcResponse::Error { .. }));
+
+        write_frame(&mut client, &encode(&IpcRequest::Ping).unwrap()).await.unwrap();
+        let reply = read_frame(&mut client, &mut buf).await.unwrap().unwrap();
+        assert!(matches!(decode(reply).unwrap(), IpcResponse::Pong));
+
+        drop(client);
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_client_closes_on_version_mismatch() {
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        let ctx = IpcContext::new(db, "/mirror".into(), "/cache".into());
+        let (mut client, server) = UnixStream::pair().unwrap();
+        let task = tokio::spawn(handle_client(server, ctx));
+        let mut buf = Vec::new();
+
+        write_frame(&mut client, &hello(PROTOCOL_VERSION + 1)).await.unwrap();
+        let reply = read_frame(&mut client, &mut buf).await.unwrap().unwrap();
+        assert!(matches!(decode(reply).unwrap(), HelloReply::Rejected { .. }));
+
+        task.await.unwrap().unwrap();
+        assert!(read_frame(&mut client, &mut buf).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bind_restricts_socket_to_owner() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("gdrivexp.sock");
+        std::fs::write(&socket_path, b"stale").unwrap();
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        let server = IpcServer::new(socket_path.clone(), db, "/mirror".into(), "/cache".into());
+
+        let _listener = server.bind().unwrap();
+        let mode = std::fs::metadata(&socket_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, SOCKET_MODE);
+    }
+
+    #[tokio::test]
+    async fn test_check_peer_accepts_only_owner_uid() {
+        let (_client, server) = UnixStream::pair().unwrap();
+        let uid = unsafe { libc::getuid() };
+
+        assert!(check_peer(&server, uid).is_ok());
+        let reason = check_peer(&server, uid.wrapping_add(1)).unwrap_err();
+        assert!(reason.contains(&format!("uid {}", uid)), "{}", reason);
+        assert!(reason.contains(&format!("pid {}", std::process::id())), "{}", reason);
+    }
+}
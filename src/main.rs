@@ -1,5 +1,7 @@
 mod auth;
+mod cli;
 mod config;
+mod i18n;
 mod db;
 mod fuse;
 mod gdrive;
@@ -8,13 +10,14 @@ mod gui;
 mod ipc;
 mod utils;
 mod mirror;
+mod metrics;
 
 use anyhow::{Context, Result};
+use clap::Parser;
 use fuse3::MountOptions;
 use fuse3::raw::Session;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use relm4::{RelmApp, ComponentSender};
 
 use config::Config;
@@ -23,19 +26,43 @@ use fuse::GDriveFS;
 /// Flag global: cuando Hard Reset está en curso, main.rs NO debe hacer process::exit.
 pub static HARD_RESET_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
 
+/// Clave de `sync_meta` con el Root ID de Drive (para arrancar sin conexión)
+const ROOT_ID_META_KEY: &str = "root_gdrive_id";
+
 fn main() -> Result<()> {
+    // `--help`/`--version` y los errores de uso terminan aquí, sin tocar disco
+    let cli = cli::Cli::parse();
+
+    // Antes que nada: la configuración y los logs pueden estar aún en los directorios antiguos
+    let layout_changes = utils::paths::migrate_legacy_layout();
+    config::set_overrides(cli.overrides());
+
+    // Exportar o importar el estado no arranca la aplicación
+    if let Some(command) = cli.state_command() {
+        return utils::state_archive::run_cli(command);
+    }
+
     // Inicializar sistema de logging
     init_logging()?;
+    utils::crash::install_panic_hook();
+    metrics::init();
     
     tracing::info!("🚀 Iniciando FedoraDrive-rs v{}", env!("CARGO_PKG_VERSION"));
+    log_layout_changes(&layout_changes);
     
     // El registro de manejadores de señales se delega al runtime asíncrono
     // dentro de la función de backend para operar mediante primitivas exclusivas de Tokio.
 
+    // Los argumentos ya los consumió clap: GTK rechaza opciones que no conoce
+    let gtk_args: Vec<String> = std::env::args().take(1).collect();
+    let start_hidden = cli.start_hidden;
+
     // Iniciar la aplicación Relm4
-    tracing::info!("🖥️ Iniciando interfaz gráfica...");
-    let app = RelmApp::new("org.gnome.FedoraDrive");
-    app.run::<gui::app_model::AppModel>(());
+    tracing::info!("🖥️ Iniciando interfaz gráfica{}...", if start_hidden { " (solo bandeja)" } else { "" });
+    let app = RelmApp::new("org.gnome.FedoraDrive")
+        .with_args(gtk_args)
+        .visible_on_activate(!start_hidden);
+    app.run::<gui::app_model::AppModel>(gui::app_model::AppInit { start_hidden });
 
     Ok(())
 }
@@ -61,7 +88,7 @@ pub fn run_backend(
     history: gui::history::ActionHistory,
     sync_paused: std::sync::Arc<std::sync::atomic::AtomicBool>,
 ) -> Result<()> {
-    ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Inicializando backend...".to_string()));
+    ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-initializing")));
     // Crear runtime de Tokio
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -94,20 +121,40 @@ pub fn run_backend(
             }
         });
 
-        // Cargar o crear configuración
-        let config = Config::load().unwrap_or_else(|_| {
-            tracing::warn!("No se pudo cargar configuración, usando valores predeterminados");
-            Config::default().expect("Error al crear configuración predeterminada")
-        });
+        // Cargar o crear configuración. Un config.json ilegible no se pisa con
+        // los valores predeterminados: se reporta para que el usuario lo corrija
+        let config = match Config::load() {
+            Ok(config) => config,
+            Err(e) => {
+                let problem = config::ConfigProblem::Unreadable {
+                    path: Config::config_path()?,
+                    error: format!("{:#}", e),
+                };
+                return Err(report_config_problems(&ui_sender, vec![problem]));
+            }
+        };
+        
+        // Guardar configuración (sin los overrides de línea de comandos/entorno)
+        config.save().context("Error al guardar configuración")?;
+        let config = config.with_overrides();
+        let problems = config.validate();
+        if !problems.is_empty() {
+            return Err(report_config_problems(&ui_sender, problems));
+        }
+        if config.read_only {
+            tracing::info!("🔒 Modo solo lectura: FUSE sin escritura y sin uploader");
+        }
+        // Ya validados: archivos que se quedan solo en local
+        let ignore_rules = Arc::new(
+            utils::ignore::IgnoreRules::new(&config.ignore_patterns)
+                .context("Error compilando ignore_patterns")?,
+        );
         
         // Crear directorios necesarios
         config
             .ensure_directories()
             .context("Error al crear directorios de configuración")?;
         
-        // Guardar configuración
-        config.save().context("Error al guardar configuración")?;
-        
         // Mostrar ambas rutas para depuración
         tracing::info!("Ruta Espejo (Visible): {:?}", config.mirror_path);
         tracing::info!("Punto de Montaje FUSE (Oculto): {:?}", config.fuse_mount_path);
@@ -115,37 +162,76 @@ pub fn run_backend(
         tracing::info!("Base de datos: {:?}", config.db_path);
         
         // Fase 1: Autenticación OAuth2
-        ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Verificando autenticación...".to_string()));
+        ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-checking-auth")));
         
-        // Buscar archivo de credenciales: primero en ~/.config/fedoradrive/, luego relativo (desarrollo)
-        let home = std::env::var("HOME").unwrap_or_default();
-        let config_cred = format!("{}/.config/fedoradrive/credentials.json", home);
-        let cred_path = if std::path::Path::new(&config_cred).exists() {
-            config_cred
+        // Buscar archivo de credenciales: primero en ~/.config/gdrivexp/, luego relativo (desarrollo)
+        let config_cred = utils::paths::credentials_path();
+        let cred_path = if config_cred.exists() {
+            config_cred.to_string_lossy().into_owned()
         } else if std::path::Path::new("credentials.json").exists() {
             "credentials.json".to_string()
         } else {
-            tracing::error!("No se encontró credentials.json en ~/.config/fedoradrive/ ni en el directorio actual");
-            ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Error: credentials.json no encontrado".to_string()));
-            anyhow::bail!("Archivo de credenciales no encontrado. Colóquelo en ~/.config/fedoradrive/credentials.json");
+            tracing::error!("No se encontró credentials.json en {:?} ni en el directorio actual", config_cred);
+            ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-credentials-missing")));
+            return Err(utils::supervisor::needs_user_action(anyhow::anyhow!(
+                "Archivo de credenciales no encontrado. Colóquelo en {:?}",
+                config_cred
+            )));
         };
 
+        // Proxy y CA adicional para todas las conexiones a Google
+        let http_settings = utils::proxy::HttpSettings::from_config(&config)
+            .context("Configuración de proxy/CA inválida")?;
+
         let oauth_manager = auth::OAuth2Manager::new_from_file(&cred_path)
             .await
-            .context("Error al inicializar gestor OAuth2")?;
+            .context("Error al inicializar gestor OAuth2")?
+            .with_http_settings(http_settings.clone());
 
         tracing::info!("Verificando estado de autenticación (esto puede abrir su navegador)...");
-        or_shutdown!(oauth_manager.authenticate(Some(ui_sender.clone())))
-            .context("Fallo crítico en autenticación")?;
-            
-        tracing::info!("✅ Autenticación correcta");
-        ui_sender.input(gui::app_model::AppMsg::SetConnected(true));
-        ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Autenticación correcta".to_string()));
+        match or_shutdown!(oauth_manager.authenticate(Some(ui_sender.clone()))) {
+            Ok(()) => {
+                tracing::info!("✅ Autenticación correcta");
+                ui_sender.input(gui::app_model::AppMsg::SetConnected(true));
+                ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-auth-ok")));
+            }
+            // Sin red el token guardado se refresca al reconectar; se arranca en modo offline
+            Err(e) if utils::network::report_error(&e) => {
+                tracing::warn!("📴 Arrancando sin conexión: se usará la caché local");
+                ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-offline-mode")));
+            }
+            Err(e) => return Err(e.context("Fallo crítico en autenticación")),
+        }
         
         // Inicializar base de datos SQLite
-        ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Cargando base de datos...".to_string()));
+        ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-loading-db")));
         let db = Arc::new(db::MetadataRepository::new(&config.db_path).await?);
 
+        // Clave maestra de la caché cifrada: antes de tocar cualquier archivo de caché
+        match utils::cache_crypto::init(config.encrypt_cache) {
+            Ok(true) => tracing::info!("🔐 Cifrado de caché disponible (archivos nuevos cifrados: {})", config.encrypt_cache),
+            Ok(false) => {}
+            Err(e) => {
+                // Sin clave las cachés cifradas dan error de lectura; el verificador las descarta
+                tracing::error!("Error cargando clave de cifrado de caché: {:?}", e);
+            }
+        }
+
+        // Recuperar operaciones FUSE interrumpidas (intent log) antes de cualquier limpieza
+        match fuse::journal::replay_pending_intents(&db, &config.cache_dir).await {
+            Ok(n) if n > 0 => tracing::info!("📓 {} operaciones interrumpidas recuperadas", n),
+            Ok(_) => {}
+            Err(e) => tracing::error!("Error recuperando operaciones interrumpidas: {:?}", e),
+        }
+
+        // Sección "Ordenadores" de Drive: el espejo arranca ya con la visibilidad elegida
+        let show_computers = config.computers_mode == config::ComputersMode::Show;
+        match db.set_computers_visible(show_computers).await {
+            Ok(true) => tracing::info!("💻 Carpeta {} {}", db::COMPUTERS_DIR_NAME, if show_computers { "visible" } else { "oculta" }),
+            Ok(false) => {}
+            Err(e) => tracing::error!("Error aplicando la visibilidad de {}: {:?}", db::COMPUTERS_DIR_NAME, e),
+        }
+
         // --- Resiliencia post-crash: detectar cierre no limpio ---
         // Usamos un marcador físico en el espejo para mayor robustez.
         let shutdown_marker = config.mirror_path.join(".gdrivexp_clean_shutdown");
@@ -182,12 +268,53 @@ pub fn run_backend(
 
         // Inicializar cliente de Google Drive
         let authenticator = oauth_manager.get_authenticator(None).await?;
-        let drive_client = Arc::new(gdrive::client::DriveClient::new(authenticator));
+        // Lo que se crea queda marcado con esta instalación (`appProperties`)
+        let client_id = db.client_id().await?;
+        let drive_client: Arc<dyn gdrive::DriveApi> = Arc::new(
+            gdrive::client::DriveClient::new(authenticator, &http_settings)?.with_client_id(client_id),
+        );
+        ui_sender.input(gui::app_model::AppMsg::SetDriveClient(drive_client.clone()));
 
         // Obtener Root ID para optimizaciones del Uploader
-        ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Obteniendo ID de carpeta raíz...".to_string()));
-        let root_id = or_shutdown!(drive_client.get_root_file_id())
-            .context("Error crítico obteniendo Root ID de Google Drive")?;
+        ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-fetching-root")));
+        let root_id = match or_shutdown!(drive_client.get_root_file_id()) {
+            Ok(id) => {
+                let _ = db.set_sync_meta(ROOT_ID_META_KEY, &id).await;
+                id
+            }
+            // Offline: el Root ID no cambia, sirve el de la sesión anterior
+            Err(e) if utils::network::report_error(&e) => db.get_sync_meta(ROOT_ID_META_KEY).await?
+                .context("Sin conexión y sin Root ID guardado: se necesita red en el primer arranque")?,
+            Err(e) => return Err(e.context("Error crítico obteniendo Root ID de Google Drive")),
+        };
+
+        // Monitor de conectividad: detecta caídas/reconexión (sondas de API + NetworkManager)
+        // y conexiones medidas
+        let _connectivity_handle = sync::connectivity::ConnectivityMonitor::new(
+            drive_client.clone(),
+            history.clone(),
+        )
+        .with_pause_on_metered(config.pause_on_metered)
+        .spawn();
+
+        // Caché de atributos/lookups compartida entre FUSE y el syncer
+        let metadata_cache = Arc::new(fuse::meta_cache::MetadataCache::default());
+        // Locks de escritura en caché compartidos entre FUSE y el prefetcher
+        let file_locks = fuse::file_locks::FileLocks::default();
+        // Canal de eventos de cambio de estado (Uploader e hidratación → D-Bus StatusChanged)
+        let (status_tx, status_rx) = tokio::sync::broadcast::channel::<u64>(256);
+        // Descargas en curso: FUSE y prefetcher las registran, el uploader las respeta
+        let inflight = Arc::new(
+            fuse::inflight::InflightDownloads::default().with_status_events(status_tx.clone()),
+        );
+        // Progreso de las subidas: el uploader lo publica, IPC lo reporta por archivo
+        let upload_progress =
+            sync::upload_progress::UploadProgress::default().with_status_events(status_tx.clone());
+        // Carpetas excluidas: FUSE y el espejo las consultan; se cargan tras el nivel 1
+        let exclusions = Arc::new(sync::exclusions::Exclusions::default());
+        // Almacenamiento de Drive lleno: el uploader lo marca, FUSE rechaza datos nuevos
+        let storage_quota = utils::quota::StorageQuota::default();
+        ui_sender.input(gui::app_model::AppMsg::SetStorageQuota(storage_quota.clone()));
 
         // Inicializar sistema de archivos
         let fs = GDriveFS::new(
@@ -195,6 +322,24 @@ pub fn run_backend(
             drive_client.clone(),
             &config.cache_dir,
             Arc::new(history.clone()),
+        )
+        .with_metadata_cache(metadata_cache.clone())
+        .with_file_locks(file_locks.clone())
+        .with_storage_quota(storage_quota.clone())
+        .with_inflight(inflight.clone())
+        .with_ignore_rules(ignore_rules.clone())
+        .with_exclusions(exclusions.clone())
+        .with_backpressure_mode(config.backpressure_mode)
+        .with_fsync_upload_timeout(
+            // Sin uploader (`read_only`) no hay subida que esperar
+            (config.fsync_upload_timeout_secs > 0 && !config.read_only)
+                .then(|| std::time::Duration::from_secs(config.fsync_upload_timeout_secs)),
+        )
+        .with_max_write(config.fuse_max_write_kb.saturating_mul(1024))
+        .with_writeback_cache(config.fuse_writeback_cache)
+        .with_read_timeout(
+            (config.read_timeout_secs > 0)
+                .then(|| std::time::Duration::from_secs(config.read_timeout_secs)),
         );
 
         // Canal de coordinación: BFS bootstrap → MirrorManager
@@ -205,32 +350,41 @@ pub fn run_backend(
             db.clone(),
             config.mirror_path.clone(),
             config.fuse_mount_path.clone(),
+            config.cache_dir.clone(),
             history.clone(),
             bfs_ready_rx,
         );
+        let mirror_manager = mirror_manager
+            .with_ignore_rules(ignore_rules.clone())
+            .with_exclusions(exclusions.clone());
 
         // Fase 2.1: Bootstrap inicial + Escaneo progresivo
         let bootstrap_done = db.get_sync_meta("bootstrap_complete").await?;
 
         // Primera vez con DB vacía: nivel 1 rápido para mostrar root de inmediato
         if bootstrap_done.is_none() && db.is_empty().await? {
-            ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Cargando estructura inicial...".to_string()));
+            ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-loading-structure")));
             or_shutdown!(sync::bootstrap::bootstrap_level1(&db, &drive_client, &root_id))?;
             let _ = db.set_sync_meta("repair_ownership_done_v2", "true").await;
         }
 
+        // Carpetas excluidas: se resuelven contra lo ya indexado (nivel 1 incluido)
+        exclusions.load(&db, &config.excluded_folders).await?;
+
         // Señalar a MirrorManager que puede arrancar con los datos actuales
         let _ = bfs_ready_tx.send(true);
 
         // Escaneo progresivo: SIEMPRE se ejecuta al iniciar/reanudar sesión
         if is_crash_recovery {
             // Post-crash: escaneo SÍNCRONO antes de montar FUSE (evita 416 por sizes desactualizados)
-            ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Recuperando metadatos...".to_string()));
+            ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-repairing-metadata")));
             tracing::info!("Escaneo síncrono post-crash...");
             if let Err(e) = or_shutdown!(sync::bootstrap::bootstrap_remaining_bfs(
-                &db, &drive_client, &root_id, &history, &mirror_sender
+                &db, &drive_client, &root_id, &history, &mirror_sender, &exclusions
             )) {
                 tracing::error!("Error en escaneo post-crash: {:?}", e);
+            } else {
+                apply_imported_pins(&db, &history, &mirror_sender).await;
             }
             if bootstrap_done.is_none() {
                 let _ = db.set_sync_meta("bootstrap_complete", "true").await;
@@ -242,37 +396,56 @@ pub fn run_backend(
             let root_id_bg = root_id.clone();
             let mirror_tx_bg = mirror_sender.clone();
             let history_bg = history.clone();
+            let exclusions_bg = exclusions.clone();
             let needs_bootstrap_mark = bootstrap_done.is_none();
             let ui_bg = ui_sender.clone();
-            ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Escaneando...".to_string()));
+            ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("gui-scanning")));
             tokio::spawn(async move {
                 if let Err(e) = sync::bootstrap::bootstrap_remaining_bfs(
-                    &db_bg, &client_bg, &root_id_bg, &history_bg, &mirror_tx_bg
+                    &db_bg, &client_bg, &root_id_bg, &history_bg, &mirror_tx_bg, &exclusions_bg
                 ).await {
                     tracing::error!("Error en escaneo background: {:?}", e);
-                } else if needs_bootstrap_mark {
-                    let _ = db_bg.set_sync_meta("bootstrap_complete", "true").await;
+                } else {
+                    if needs_bootstrap_mark {
+                        let _ = db_bg.set_sync_meta("bootstrap_complete", "true").await;
+                    }
+                    apply_imported_pins(&db_bg, &history_bg, &mirror_tx_bg).await;
                 }
                 ui_bg.input(gui::app_model::AppMsg::UpdateStatus(
-                    "Sistema de archivos montado y activo".to_string()
+                    i18n::tr("status-mounted")
                 ));
             });
         }
 
+        // Franja de sincronización automática (`validate` ya rechazó las inválidas)
+        let schedule = sync::schedule::Schedule::from_config(&config.sync_schedule).unwrap_or_default();
+        if schedule != sync::schedule::Schedule::Always {
+            tracing::info!("🕗 Sincronización automática limitada: {:?}", config.sync_schedule);
+        }
+
         // Fase 2.2: Background Syncer (sincronización continua)
         tracing::info!("Iniciando sincronizador en background...");
+        // Avisos al kernel de los cambios remotos; se conecta al montar
+        let kernel_notifier = Arc::new(fuse::kernel_notify::KernelNotifier::default());
         let syncer = sync::syncer::BackgroundSyncer::new(
             db.clone(),
             drive_client.clone(),
-            60, // Intervalo base: 60 segundos
+            config.sync_interval_secs,
             history.clone(),
             sync_paused.clone(),
             mirror_sender.clone(),
-        );
+        )
+        .with_metadata_cache(metadata_cache.clone())
+        .with_kernel_notifier(kernel_notifier.clone())
+        .with_cache_dir(&config.cache_dir)
+        .with_file_locks(file_locks.clone())
+        .with_schedule(schedule)
+        .with_exclusions(exclusions.clone())
+        .with_pin_hints(config.sync_pins_across_devices);
 
         // Sync inicial ANTES de montar FUSE: actualizar metadatos (sizes) para evitar
         // 416 Range Not Satisfiable masivos cuando GNOME escanea el montaje.
-        ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Sincronizando cambios recientes...".to_string()));
+        ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-syncing-recent")));
         match or_shutdown!(syncer.sync_once()) {
             Ok(n) if n > 0 => tracing::info!("✅ Sync inicial pre-FUSE: {} cambios aplicados", n),
             Ok(_) => tracing::info!("✅ Sync inicial pre-FUSE: sin cambios pendientes"),
@@ -285,6 +458,13 @@ pub fn run_backend(
         // (previene que el uploader envíe a papelera archivos que no borró el usuario)
         let _ = db.clear_stale_dirty_deletes().await;
 
+        // Fusionar rangos de caché fragmentados (lecturas aleatorias de sesiones anteriores)
+        match db.compact_cached_chunks().await {
+            Ok(n) if n > 0 => tracing::info!("🧩 Chunks de caché compactados: {} filas eliminadas", n),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("⚠️ Error compactando chunks de caché: {:?}", e),
+        }
+
         // Fase 2.3: Uploader (subida de archivos dirty)
         tracing::info!("Iniciando uploader en background...");
         let uploader = sync::uploader::Uploader::new(
@@ -295,8 +475,69 @@ pub fn run_backend(
             &config.mirror_path,
             history.clone(),
             root_id.clone(),
-        );
-        let _uploader_handle = uploader.spawn();
+        )
+        .with_status_events(status_tx)
+        .with_ignore_rules(ignore_rules.clone())
+        .with_inflight(inflight.clone())
+        .with_file_locks(file_locks.clone())
+        .with_upload_progress(upload_progress.clone())
+        .with_storage_quota(storage_quota)
+        .with_schedule(schedule);
+        // Solo lectura: los cambios locales quedan pendientes, nunca se suben
+        let _uploader_handle = (!config.read_only).then(|| uploader.spawn());
+
+        // Contrapresión: FUSE frena las escrituras si el trabajo pendiente de
+        // subir o el disco de la caché superan los límites de la configuración
+        let backpressure_limits = utils::backpressure::Limits::from_config(&config);
+        let _backpressure_handle = (!config.read_only && !backpressure_limits.is_disabled()).then(|| {
+            sync::backpressure::BackpressureMonitor::new(
+                db.clone(),
+                config.cache_dir.clone(),
+                backpressure_limits,
+                history.clone(),
+            )
+            .spawn()
+        });
+
+        // Fase 2.3.1: Verificador de integridad de la caché (muestra aleatoria cada hora)
+        let cache_verifier = sync::verifier::CacheVerifier::new(
+            db.clone(),
+            &config.cache_dir,
+            3600,
+            history.clone(),
+        )
+        .with_drive_client(drive_client.clone())
+        .with_file_locks(file_locks.clone());
+        let _verifier_handle = cache_verifier.spawn();
+
+        // Fase 2.3.2: Prefetcher (precarga de directorios frecuentes al arrancar y en reposo)
+        if config.prefetch_budget_mb > 0 {
+            let prefetcher = sync::prefetcher::Prefetcher::new(
+                db.clone(),
+                drive_client.clone(),
+                &config.cache_dir,
+                900, // Intervalo: 15 minutos
+                config.prefetch_budget_mb,
+                history.clone(),
+                sync_paused.clone(),
+            )
+            .with_file_locks(file_locks.clone())
+            .with_inflight(inflight.clone());
+            let _prefetcher_handle = prefetcher.spawn();
+        }
+
+        // Miniaturas de Drive para Nautilus (sin descargar los archivos "Online Only")
+        if config.drive_thumbnails {
+            let thumbnail_fetcher = sync::thumbnails::ThumbnailFetcher::new(
+                db.clone(),
+                drive_client.clone(),
+                &config.mirror_path,
+                &config.fuse_mount_path,
+                120, // Intervalo: 2 minutos
+            )
+            .with_encrypted_cache(config.encrypt_cache);
+            let _thumbnails_handle = thumbnail_fetcher.spawn();
+        }
         
         // Fase 2.3.5: Progress Monitor (Monitor de Operaciones Pendientes)
         let db_monitor = db.clone();
@@ -309,22 +550,36 @@ pub fn run_backend(
                     break;
                 }
 
-                let dirty_fuse = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM sync_state WHERE dirty = 1")
-                    .fetch_one(db_monitor.pool())
-                    .await
-                    .unwrap_or(0);
-
-                let dirty_local = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM local_sync_files WHERE dirty = 1")
-                    .fetch_one(db_monitor.pool())
-                    .await
-                    .unwrap_or(0);
+                let (dirty_fuse, dirty_local) = db_monitor.count_dirty().await.unwrap_or((0, 0));
 
                 history_monitor.set_pending_uploads((dirty_fuse + dirty_local) as usize);
+                let upload_errors = db_monitor.count_upload_errors().await.unwrap_or(0);
+                history_monitor.set_upload_errors(upload_errors as usize);
 
                 tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
             }
         });
 
+        // Fase 2.3.6: Checkpoint periódico del WAL
+        // Evita que el archivo -wal crezca sin límite bajo carga continua de FUSE/syncer/uploader
+        let db_checkpoint = db.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(300));
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = utils::shutdown::wait_for_shutdown() => break,
+                }
+                match db_checkpoint.wal_checkpoint(false).await {
+                    Ok((log, checkpointed)) => tracing::debug!(
+                        "💾 Checkpoint WAL: {}/{} páginas transferidas", checkpointed, log
+                    ),
+                    Err(e) => tracing::warn!("⚠️ Error en checkpoint WAL: {:?}", e),
+                }
+            }
+        });
+
         // Fase 2.4: MirrorManager (Nuevo Sistema Híbrido)
         // Reemplaza a LocalSyncManager
         // Fase 2.4: MirrorManager & IPC DEFERRED
@@ -334,6 +589,32 @@ pub fn run_backend(
         // CRITICAL: Limpiar punto de montaje huérfano antes de intentar montar
         utils::mount::cleanup_if_needed(&config.fuse_mount_path)
             .context("Error al limpiar punto de montaje huérfano")?;
+
+        // Montar encima de archivos del usuario los ocultaría: se para hasta que
+        // elija otro directorio o pida migrarlos a Drive (desde la GUI)
+        let migrate_requested = utils::mount::take_migration_request();
+        let hidden = utils::mount::hidden_entries(&config.fuse_mount_path)
+            .context("Error leyendo el punto de montaje")?;
+        if !hidden.is_empty() {
+            if !migrate_requested || config.read_only {
+                tracing::error!(
+                    "❌ El punto de montaje {:?} contiene {} entradas que quedarían ocultas; no se monta",
+                    config.fuse_mount_path,
+                    hidden.len()
+                );
+                ui_sender.input(gui::app_model::AppMsg::MountPointNotEmpty {
+                    path: config.fuse_mount_path.clone(),
+                    entries: hidden,
+                });
+                return Err(utils::supervisor::needs_user_action(anyhow::anyhow!(
+                    "El punto de montaje {:?} no está vacío",
+                    config.fuse_mount_path
+                )));
+            }
+            let moved = utils::mount::stage_for_migration(&config.fuse_mount_path)
+                .context("Error apartando el contenido del punto de montaje")?;
+            tracing::info!("📦 {} entradas del punto de montaje apartadas para migrarlas a Drive", moved);
+        }
         
         // Informar a la GUI de las rutas (Mirror y FUSE)
         ui_sender.input(gui::app_model::AppMsg::SetPaths {
@@ -351,17 +632,44 @@ pub fn run_backend(
             .gid(gid)
             .fs_name("fedoradrive")
             .allow_other(true)
-            .custom_options("default_permissions") // Apply permissions locally
-            .custom_options("exec") // CRÍTICO: Permitir ejecución de binarios y .desktop
-            .custom_options("max_read=1048576"); // Rendimiento: Kernel debe solicitar hasta 1MB por read()
+            .default_permissions(true) // Apply permissions locally
+            .write_back(config.fuse_writeback_cache)
+            // custom_options reemplaza el valor anterior: todo va en una sola llamada.
+            // exec es CRÍTICO (binarios y .desktop); max_read acota cada read() del kernel
+            .custom_options(format!("exec,max_read={}", config.fuse_max_read_kb.max(4).saturating_mul(1024)));
+        if config.read_only {
+            mount_options.read_only(true);
+        }
             
         tracing::info!("Montando sistema de archivos en {:?}...", config.fuse_mount_path);
         ui_sender.input(gui::app_model::AppMsg::UpdateStatus(format!("Montando en {:?}...", config.mirror_path)));
         
-        let mut handle = Session::new(mount_options)
-            .mount_with_unprivileged(fs, &config.fuse_mount_path)
+        let mut handle = Session::new(mount_options.clone())
+            .mount_with_unprivileged(fs.new_session().await, &config.fuse_mount_path)
             .await
             .context("Error al montar sistema de archivos FUSE")?;
+        if kernel_notifier.attach() {
+            tracing::info!("🔔 Cambios remotos se avisarán al kernel (FUSE_NOTIFY)");
+        }
+
+        // Migración pendiente del punto de montaje (de este arranque o de uno interrumpido)
+        if !config.read_only {
+            let mount_path = config.fuse_mount_path.clone();
+            let history_migration = history.clone();
+            tokio::spawn(async move {
+                match utils::mount::migrate_staged(&mount_path).await {
+                    Ok(0) => {}
+                    Ok(count) => {
+                        tracing::info!("📦 {} archivos del punto de montaje migrados a Drive", count);
+                        history_migration.log(
+                            gui::history::ActionType::Create,
+                            i18n::t!("activity-mount-migrated", count = count),
+                        );
+                    }
+                    Err(e) => tracing::error!("❌ Error migrando el punto de montaje a Drive: {:?}", e),
+                }
+            });
+        }
         
         // Fase 2.4: MirrorManager (Nuevo Sistema Híbrido)
         // Reemplaza a LocalSyncManager
@@ -371,10 +679,11 @@ pub fn run_backend(
         // SINCRONIZAR propiedad ANTES del bootstrap del espejo para evitar race condition:
         let db_mirror = db.clone();
         let client_mirror = drive_client.clone();
+        let exclusions_mirror = exclusions.clone();
         tokio::spawn(async move {
             if let Ok(None) = db_mirror.get_sync_meta("repair_ownership_done_v2").await {
                 tracing::info!("⚙️ Verificando consistencia de propiedad para limpieza de redundancias...");
-                if let Err(e) = sync::bootstrap::repair_ownership_metadata(&db_mirror, &client_mirror).await {
+                if let Err(e) = sync::bootstrap::repair_ownership_metadata(&db_mirror, &client_mirror, &exclusions_mirror).await {
                     tracing::error!("❌ Error reparando propiedad: {:?}", e);
                 } else {
                     let _ = db_mirror.set_sync_meta("repair_ownership_done_v2", "true").await;
@@ -387,6 +696,15 @@ pub fn run_backend(
         
         // Fase 2.5: Servidor IPC para extensiones externas (Nautilus)
         tracing::info!("Iniciando servidor IPC...");
+        // `ShowProperties` (IPC/D-Bus) abre el diálogo de propiedades en la ventana
+        let (properties_tx, mut properties_rx) = tokio::sync::mpsc::unbounded_channel();
+        let ui_properties = ui_sender.clone();
+        tokio::spawn(async move {
+            while let Some(path) = properties_rx.recv().await {
+                ui_properties.input(gui::app_model::AppMsg::ShowWindow);
+                ui_properties.input(gui::app_model::AppMsg::ShowFileProperties(path));
+            }
+        });
         let socket_path = ipc::get_socket_path();
         let ipc_server = ipc::server::IpcServer::new(
             socket_path,
@@ -394,28 +712,123 @@ pub fn run_backend(
             config.mirror_path.clone(), // IPC usa rutas visibles del usuario
             config.cache_dir.clone(),
         )
-        .with_mirror_manager(mirror_sender.clone());
+        .with_mirror_manager(mirror_sender.clone())
+        .with_drive_client(drive_client.clone())
+        .with_properties_requests(properties_tx)
+        .with_ignore_rules(ignore_rules)
+        .with_inflight(inflight.clone())
+        .with_upload_progress(upload_progress)
+        .with_file_locks(file_locks.clone())
+        .with_read_only(config.read_only);
+        let ipc_server_context = ipc_server.context();
+        ui_sender.input(gui::app_model::AppMsg::SetIpcContext(ipc_server_context.clone()));
+        ui_sender.input(gui::app_model::AppMsg::SetCacheManager(
+            fuse::cache_manager::CacheManager::new(db.clone(), config.cache_dir.clone())
+                .with_file_locks(file_locks.clone()),
+        ));
         let _ipc_handle = ipc_server.spawn();
+
+        // Fase 2.6: Servicio D-Bus (misma API que el socket + señales de estado)
+        let dbus_service = ipc::dbus::DbusService::new(ipc_server_context)
+            .with_status_events(status_rx);
+        let _dbus_handle = dbus_service.spawn();
+
+        // Fase 2.7: Endpoint Prometheus (opcional, `metrics_listen`)
+        if let Some(addr) = config.metrics_listen {
+            let _metrics_handle = metrics::http::MetricsServer::new(addr).spawn();
+        }
         
         tracing::info!("✅ Sistema de archivos montado exitosamente");
-        ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Sistema de archivos montado y activo".to_string()));
+        ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-mounted")));
+        ui_sender.input(gui::app_model::AppMsg::SetBackendState(utils::supervisor::BackendState::Running));
 
         // TODO: Actualizar GUI para usar MirrorManager Sender
         // ui_sender.input(gui::app_model::AppMsg::SetLocalSyncSender(local_sync_sender));
 
         
-        // Esperar a que termine la sesión recursiva, o se notifique un shutdown coordinado
-        // (el cual unifica cierres provenientes vía GUI o del Systema Operativo vía Señal)
-        tokio::select! {
-            res = &mut handle => {
-                if let Err(e) = res {
-                    tracing::error!("Error en la sesión FUSE: {:?}", e);
+        // Esperar a que se notifique un shutdown coordinado (el cual unifica cierres
+        // provenientes vía GUI o del Systema Operativo vía Señal). Si antes se pierde
+        // la sesión (error de FUSE, vuelta de una suspensión) se remonta con backoff
+        let mut resume_events = utils::suspend::ResumeEvents::subscribe().await;
+        loop {
+            let session_ended = tokio::select! {
+                res = &mut handle => {
+                    match res {
+                        Ok(()) => tracing::warn!("⚠️ La sesión FUSE terminó inesperadamente"),
+                        Err(e) => tracing::error!("Error en la sesión FUSE: {:?}", e),
+                    }
+                    true
+                }
+                _ = resume_events.next_resume() => {
+                    let path = config.fuse_mount_path.clone();
+                    let check = tokio::task::spawn_blocking(move || utils::mount::is_healthy(&path));
+                    // Un stat que no responde a tiempo no es un endpoint roto: FUSE va lento
+                    let lost = matches!(
+                        tokio::time::timeout(std::time::Duration::from_secs(5), check).await,
+                        Ok(Ok(false))
+                    );
+                    if !lost {
+                        tracing::info!("☀️ Vuelta de suspensión: el montaje sigue activo");
+                        continue;
+                    }
+                    tracing::warn!("⚠️ Vuelta de suspensión con el montaje perdido");
+                    false
+                }
+                _ = utils::shutdown::wait_for_shutdown() => {
+                    tracing::info!("🛑 Desmontaje coordinado activado...");
+                    ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-shutting-down")));
+                    break;
                 }
+            };
+            // El Hard Reset y el cierre desmontan a propósito
+            if HARD_RESET_IN_PROGRESS.load(Ordering::SeqCst) || utils::shutdown::is_shutdown_requested() {
+                break;
             }
-            _ = utils::shutdown::wait_for_shutdown() => {
-                tracing::info!("🛑 Desmontaje coordinado activado...");
-                ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Cerrando subsistemas...".to_string()));
+
+            history.log(gui::history::ActionType::Error, i18n::tr("activity-mount-lost"));
+            kernel_notifier.detach();
+            if !session_ended {
+                // Con la tarea viva, el Drop del handle desmontaría en segundo plano
+                // y podría llevarse por delante la sesión nueva
+                let _ = tokio::time::timeout(std::time::Duration::from_secs(5), handle.unmount()).await;
             }
+
+            let mut attempt = 0;
+            let remounted = loop {
+                attempt += 1;
+                ui_sender.input(gui::app_model::AppMsg::UpdateStatus(
+                    i18n::t!("status-remounting", attempt = attempt),
+                ));
+                let path = config.fuse_mount_path.clone();
+                match tokio::task::spawn_blocking(move || utils::mount::cleanup_if_needed(&path)).await {
+                    Ok(Err(e)) => tracing::warn!("⚠️ Error limpiando el montaje perdido: {:?}", e),
+                    Err(e) => tracing::warn!("⚠️ Error limpiando el montaje perdido: {:?}", e),
+                    Ok(Ok(())) => {}
+                }
+                match Session::new(mount_options.clone())
+                    .mount_with_unprivileged(fs.new_session().await, &config.fuse_mount_path)
+                    .await
+                {
+                    Ok(new_handle) => break Some(new_handle),
+                    Err(e) => tracing::error!("❌ Remontaje fallido (intento {}): {:?}", attempt, e),
+                }
+                let retry_in = utils::mount::remount_delay(attempt);
+                tracing::warn!("🔁 Reintentando el montaje en {} s", retry_in.as_secs());
+                tokio::select! {
+                    _ = tokio::time::sleep(retry_in) => {}
+                    _ = utils::shutdown::wait_for_shutdown() => break None,
+                }
+            };
+            let Some(new_handle) = remounted else {
+                tracing::info!("🛑 Desmontaje coordinado activado durante el remontaje...");
+                ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-shutting-down")));
+                break;
+            };
+            handle = new_handle;
+            kernel_notifier.attach();
+            tracing::info!("✅ Sistema de archivos remontado tras {} intento(s)", attempt);
+            history.log(gui::history::ActionType::Sync, i18n::tr("activity-mount-restored"));
+            ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-mounted")));
         }
         
         // Marcar cierre limpio antes de cualquier ruta de salida
@@ -429,7 +842,7 @@ pub fn run_backend(
         }
 
         tracing::info!("🛑 Desmontando sistema de archivos y cerrando...");
-        ui_sender.input(gui::app_model::AppMsg::UpdateStatus("Desmontando...".to_string()));
+        ui_sender.input(gui::app_model::AppMsg::UpdateStatus(i18n::tr("status-unmounting")));
 
         // Detener el MirrorWatcher ANTES de escribir .hidden para evitar que
         // el watcher detecte los archivos y los registre como cambios del usuario.
@@ -446,6 +859,11 @@ pub fn run_backend(
         // El drop de 'handle' debería intentar desmontar, pero lo forzamos por seguridad
         let _ = utils::mount::unmount_and_wait(&config.fuse_mount_path);
 
+        // Volcar el WAL a la base de datos principal antes de salir
+        if let Err(e) = db.wal_checkpoint(true).await {
+            tracing::warn!("⚠️ Error en checkpoint WAL final: {:?}", e);
+        }
+
         // Crear marcador de cierre limpio FÍSICO tras desmontaje exitoso
         tracing::info!("💾 Escribiendo marcador de cierre limpio...");
         if let Err(e) = std::fs::File::create(&shutdown_marker) {
@@ -458,15 +876,59 @@ pub fn run_backend(
     })
 }
 
-/// Inicializa el sistema de logging con tracing
+/// Muestra los problemas de configuración en la GUI y arma el error con el que
+/// termina el intento del backend (el supervisor reintenta: basta corregir y
+/// pulsar "Reconectar")
+fn report_config_problems(
+    ui_sender: &ComponentSender<gui::app_model::AppModel>,
+    problems: Vec<config::ConfigProblem>,
+) -> anyhow::Error {
+    for problem in &problems {
+        tracing::error!("❌ Configuración inválida: {}", problem);
+    }
+    let summary = problems.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+    ui_sender.input(gui::app_model::AppMsg::SetConfigProblems(problems));
+    // Reintentar no arregla la configuración: el supervisor espera a "Reconectar"
+    utils::supervisor::needs_user_action(anyhow::anyhow!("Configuración inválida: {}", summary))
+}
+
+/// Registra lo que hizo `migrate_legacy_layout` (se ejecutó sin logging)
+fn log_layout_changes(changes: &[utils::paths::LayoutChange]) {
+    use utils::paths::LayoutChange;
+    for change in changes {
+        match change {
+            LayoutChange::Moved { from, to } => tracing::info!("📦 MIGRACIÓN: {:?} → {:?}", from, to),
+            LayoutChange::Kept { legacy, current } => {
+                tracing::warn!("⚠️ MIGRACIÓN: {:?} ya existe, se ignora {:?} (puede borrarse a mano)", current, legacy)
+            }
+            LayoutChange::Failed { from, error } => tracing::warn!("⚠️ MIGRACIÓN: no se pudo mover {:?}: {}", from, error),
+            LayoutChange::ConfigRewritten { path } => tracing::info!("📦 MIGRACIÓN: rutas actualizadas en {:?}", path),
+        }
+    }
+}
+
+/// Marca "Mantener siempre en este dispositivo" lo que trajo `--import-state`
+/// sin base de datos, ya con Drive indexado, y pide al espejo descargarlo
+async fn apply_imported_pins(
+    db: &db::MetadataRepository,
+    history: &gui::history::ActionHistory,
+    mirror_tx: &tokio::sync::mpsc::Sender<mirror::MirrorCommand>,
+) {
+    match utils::state_archive::apply_imported_pins(db).await {
+        Ok(0) => {}
+        Ok(count) => {
+            history.log(gui::history::ActionType::Download, i18n::t!("activity-imported-pins", count = count));
+            let _ = mirror_tx.send(mirror::MirrorCommand::Refresh).await;
+        }
+        Err(e) => tracing::warn!("No se pudieron aplicar los elementos mantenidos importados: {:?}", e),
+    }
+}
+
+/// Inicializa el sistema de logging con tracing (stderr + archivo rotativo)
 fn init_logging() -> Result<()> {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "g_drive_xp=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-    
-    Ok(())
+    // Los mensajes de esta carga previa se pierden; run_backend vuelve a cargarla
+    let level = Config::load()
+        .map(|config| config.log_level)
+        .unwrap_or_else(|_| config::default_log_level());
+    utils::logging::init(&level)
 }
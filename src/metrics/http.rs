@@ -0,0 +1,60 @@
+//! Endpoint HTTP `/metrics` para Prometheus (opcional)
+//!
+//! Solo se inicia si `Config.metrics_listen` tiene una dirección. Sin
+//! autenticación: solo escucha en loopback (`Config::validate` rechaza otras
+//! direcciones y `spawn` tampoco las abre).
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use tracing::{error, info};
+
+/// Servidor HTTP de métricas
+pub struct MetricsServer {
+    addr: SocketAddr,
+}
+
+impl MetricsServer {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr }
+    }
+
+    /// Inicia el servidor en un task de Tokio separado
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            if !self.addr.ip().is_loopback() {
+                error!("❌ Endpoint de métricas no iniciado: {} no es una dirección de loopback", self.addr);
+                return;
+            }
+            let make_service = make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(handle)) });
+
+            let server = match hyper::Server::try_bind(&self.addr) {
+                Ok(builder) => builder.serve(make_service),
+                Err(e) => {
+                    error!("❌ No se pudo abrir el endpoint de métricas en {}: {}", self.addr, e);
+                    return;
+                }
+            };
+            info!("📈 Métricas Prometheus en http://{}/metrics", self.addr);
+
+            let server = server.with_graceful_shutdown(crate::utils::shutdown::wait_for_shutdown());
+            if let Err(e) = server.await {
+                error!("Error en el endpoint de métricas: {:?}", e);
+            }
+        })
+    }
+}
+
+async fn handle(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(super::render_prometheus(&super::snapshot()))),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty()),
+    };
+    // Los builders solo fallan con cabeceras inválidas, que aquí son constantes
+    Ok(response.unwrap_or_default())
+}
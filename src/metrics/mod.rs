@@ -0,0 +1,360 @@
+//! Métricas del daemon: contadores e histogramas en memoria
+//!
+//! Todo es estático y sin locks en el camino caliente (atómicos, un `DashMap`
+//! por etiqueta). `snapshot()` produce `DaemonStats` para el IPC
+//! (`GetDaemonStats`) y `render_prometheus()` el formato de texto de
+//! Prometheus que sirve el endpoint HTTP opcional (`http::MetricsServer`).
+
+pub mod http;
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Límites superiores de los buckets de latencia, en microsegundos
+pub const LATENCY_BUCKETS_US: [u64; 12] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000, 5_000_000,
+];
+
+static STARTED_AT: OnceLock<Instant> = OnceLock::new();
+static BYTES_UPLOADED: AtomicU64 = AtomicU64::new(0);
+static BYTES_DOWNLOADED: AtomicU64 = AtomicU64::new(0);
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Latencia de llamadas a la API de Drive, por método de `DriveClient`
+static API_CALLS: LazyLock<DashMap<&'static str, Histogram>> = LazyLock::new(DashMap::new);
+
+/// Llamadas a la API que terminaron en error, por método
+static API_ERRORS: LazyLock<DashMap<&'static str, AtomicU64>> = LazyLock::new(DashMap::new);
+
+/// Latencia de operaciones FUSE, por operación
+static FUSE_OPS: LazyLock<DashMap<&'static str, Histogram>> = LazyLock::new(DashMap::new);
+
+/// Histograma de latencias con buckets fijos (`LATENCY_BUCKETS_US` + `+Inf`)
+struct Histogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS_US.len() + 1],
+    count: AtomicU64,
+    sum_us: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            count: AtomicU64::new(0),
+            sum_us: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, elapsed: Duration) {
+        let us = elapsed.as_micros() as u64;
+        let bucket = LATENCY_BUCKETS_US
+            .iter()
+            .position(|&limit| us <= limit)
+            .unwrap_or(LATENCY_BUCKETS_US.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(us, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self, name: &str, errors: u64) -> OpStats {
+        // Prometheus espera buckets acumulados
+        let mut cumulative = 0;
+        let buckets = self.buckets[..LATENCY_BUCKETS_US.len()]
+            .iter()
+            .map(|bucket| {
+                cumulative += bucket.load(Ordering::Relaxed);
+                cumulative
+            })
+            .collect();
+        OpStats {
+            name: name.to_string(),
+            count: self.count.load(Ordering::Relaxed),
+            errors,
+            sum_us: self.sum_us.load(Ordering::Relaxed),
+            buckets,
+        }
+    }
+}
+
+/// Estadísticas de una operación (método de la API u operación FUSE)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpStats {
+    pub name: String,
+    pub count: u64,
+    /// Solo llamadas a la API; siempre 0 en operaciones FUSE
+    pub errors: u64,
+    pub sum_us: u64,
+    /// Conteo acumulado por cada límite de `LATENCY_BUCKETS_US`
+    pub buckets: Vec<u64>,
+}
+
+/// Instantánea de las métricas del daemon (respuesta a `GetDaemonStats`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonStats {
+    pub uptime_secs: u64,
+    pub online: bool,
+    pub bytes_uploaded: u64,
+    pub bytes_downloaded: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub api_calls: Vec<OpStats>,
+    pub fuse_ops: Vec<OpStats>,
+}
+
+impl DaemonStats {
+    /// Fracción de lecturas servidas sin descargar (None si aún no hubo lecturas)
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        let total = self.cache_hits + self.cache_misses;
+        (total > 0).then(|| self.cache_hits as f64 / total as f64)
+    }
+}
+
+/// Marca el inicio del daemon (para `uptime_secs`)
+pub fn init() {
+    STARTED_AT.get_or_init(Instant::now);
+}
+
+pub fn add_bytes_uploaded(bytes: u64) {
+    BYTES_UPLOADED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+pub fn add_bytes_downloaded(bytes: u64) {
+    BYTES_DOWNLOADED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// Lectura FUSE servida íntegramente desde la caché
+pub fn record_cache_hit() {
+    CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Lectura FUSE que necesitó descargar de Drive
+pub fn record_cache_miss() {
+    CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Llamada a la API en curso. Al descartarse registra la latencia; si no se
+/// marcó con `succeeded()` (salida por `?` o `bail!`) cuenta como error.
+pub struct ApiCall {
+    method: &'static str,
+    started: Instant,
+    ok: bool,
+}
+
+impl ApiCall {
+    pub fn start(method: &'static str) -> Self {
+        Self { method, started: Instant::now(), ok: false }
+    }
+
+    pub fn succeeded(mut self) {
+        self.ok = true;
+    }
+}
+
+impl Drop for ApiCall {
+    fn drop(&mut self) {
+        API_CALLS
+            .entry(self.method)
+            .or_insert_with(Histogram::new)
+            .observe(self.started.elapsed());
+        if !self.ok {
+            API_ERRORS
+                .entry(self.method)
+                .or_insert_with(|| AtomicU64::new(0))
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Temporizador de una operación FUSE: registra la latencia al descartarse
+pub struct FuseOpTimer {
+    op: &'static str,
+    started: Instant,
+}
+
+/// Inicia la medición de una operación FUSE (`let _timer = metrics::fuse_op("read");`)
+pub fn fuse_op(op: &'static str) -> FuseOpTimer {
+    FuseOpTimer { op, started: Instant::now() }
+}
+
+impl Drop for FuseOpTimer {
+    fn drop(&mut self) {
+        FUSE_OPS
+            .entry(self.op)
+            .or_insert_with(Histogram::new)
+            .observe(self.started.elapsed());
+    }
+}
+
+/// Instantánea de todas las métricas, ordenadas por nombre
+pub fn snapshot() -> DaemonStats {
+    let mut api_calls: Vec<OpStats> = API_CALLS
+        .iter()
+        .map(|entry| {
+            let errors = API_ERRORS
+                .get(entry.key())
+                .map(|e| e.load(Ordering::Relaxed))
+                .unwrap_or(0);
+            entry.value().snapshot(entry.key(), errors)
+        })
+        .collect();
+    api_calls.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut fuse_ops: Vec<OpStats> = FUSE_OPS
+        .iter()
+        .map(|entry| entry.value().snapshot(entry.key(), 0))
+        .collect();
+    fuse_ops.sort_by(|a, b| a.name.cmp(&b.name));
+
+    DaemonStats {
+        uptime_secs: STARTED_AT.get().map(|t| t.elapsed().as_secs()).unwrap_or(0),
+        online: crate::utils::network::is_online(),
+        bytes_uploaded: BYTES_UPLOADED.load(Ordering::Relaxed),
+        bytes_downloaded: BYTES_DOWNLOADED.load(Ordering::Relaxed),
+        cache_hits: CACHE_HITS.load(Ordering::Relaxed),
+        cache_misses: CACHE_MISSES.load(Ordering::Relaxed),
+        api_calls,
+        fuse_ops,
+    }
+}
+
+/// Formato de texto de Prometheus (exposition format 0.0.4)
+pub fn render_prometheus(stats: &DaemonStats) -> String {
+    let mut out = String::new();
+
+    let counters = [
+        ("gdrivexp_bytes_uploaded_total", "Bytes subidos a Drive", stats.bytes_uploaded),
+        ("gdrivexp_bytes_downloaded_total", "Bytes descargados de Drive", stats.bytes_downloaded),
+        ("gdrivexp_cache_hits_total", "Lecturas servidas desde la caché", stats.cache_hits),
+        ("gdrivexp_cache_misses_total", "Lecturas que necesitaron descargar", stats.cache_misses),
+    ];
+    for (name, help, value) in counters {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+    }
+
+    let _ = writeln!(out, "# HELP gdrivexp_uptime_seconds Segundos desde el arranque");
+    let _ = writeln!(out, "# TYPE gdrivexp_uptime_seconds gauge\ngdrivexp_uptime_seconds {}", stats.uptime_secs);
+    let _ = writeln!(out, "# HELP gdrivexp_online Conexión con Google Drive (1 = online)");
+    let _ = writeln!(out, "# TYPE gdrivexp_online gauge\ngdrivexp_online {}", stats.online as u8);
+    if let Some(ratio) = stats.cache_hit_ratio() {
+        let _ = writeln!(out, "# HELP gdrivexp_cache_hit_ratio Fracción de lecturas servidas desde la caché");
+        let _ = writeln!(out, "# TYPE gdrivexp_cache_hit_ratio gauge\ngdrivexp_cache_hit_ratio {}", ratio);
+    }
+
+    let _ = writeln!(out, "# HELP gdrivexp_api_errors_total Llamadas a la API de Drive con error");
+    let _ = writeln!(out, "# TYPE gdrivexp_api_errors_total counter");
+    for op in &stats.api_calls {
+        let _ = writeln!(out, "gdrivexp_api_errors_total{{method=\"{}\"}} {}", op.name, op.errors);
+    }
+
+    render_histogram(
+        &mut out,
+        "gdrivexp_api_call_duration_seconds",
+        "Latencia de las llamadas a la API de Drive",
+        "method",
+        &stats.api_calls,
+    );
+    render_histogram(
+        &mut out,
+        "gdrivexp_fuse_op_duration_seconds",
+        "Latencia de las operaciones FUSE",
+        "op",
+        &stats.fuse_ops,
+    );
+
+    out
+}
+
+fn render_histogram(out: &mut String, name: &str, help: &str, label: &str, ops: &[OpStats]) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} histogram", name, help, name);
+    for op in ops {
+        for (limit_us, count) in LATENCY_BUCKETS_US.iter().zip(&op.buckets) {
+            let le = *limit_us as f64 / 1_000_000.0;
+            let _ = writeln!(out, "{}_bucket{{{}=\"{}\",le=\"{}\"}} {}", name, label, op.name, le, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{{}=\"{}\",le=\"+Inf\"}} {}", name, label, op.name, op.count);
+        let _ = writeln!(out, "{}_sum{{{}=\"{}\"}} {}", name, label, op.name, op.sum_us as f64 / 1_000_000.0);
+        let _ = writeln!(out, "{}_count{{{}=\"{}\"}} {}", name, label, op.name, op.count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::fast(Duration::from_micros(80), 0)]
+    #[case::exact_limit(Duration::from_micros(500), 2)]
+    #[case::slow(Duration::from_millis(30), 7)]
+    #[case::overflow(Duration::from_secs(10), LATENCY_BUCKETS_US.len())]
+    fn test_histogram_bucket(#[case] elapsed: Duration, #[case] expected_bucket: usize) {
+        let histogram = Histogram::new();
+        histogram.observe(elapsed);
+
+        for (i, bucket) in histogram.buckets.iter().enumerate() {
+            let expected = u64::from(i == expected_bucket);
+            assert_eq!(bucket.load(Ordering::Relaxed), expected, "bucket {}", i);
+        }
+    }
+
+    #[test]
+    fn test_histogram_snapshot_is_cumulative() {
+        let histogram = Histogram::new();
+        histogram.observe(Duration::from_micros(50));
+        histogram.observe(Duration::from_micros(300));
+        histogram.observe(Duration::from_secs(10));
+
+        let stats = histogram.snapshot("read", 0);
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.buckets[0], 1);
+        assert_eq!(stats.buckets[2], 2);
+        assert_eq!(*stats.buckets.last().unwrap(), 2, "el +Inf solo aparece en count");
+    }
+
+    #[rstest]
+    #[case::no_reads(0, 0, None)]
+    #[case::all_hits(4, 0, Some(1.0))]
+    #[case::mixed(3, 1, Some(0.75))]
+    fn test_cache_hit_ratio(#[case] hits: u64, #[case] misses: u64, #[case] expected: Option<f64>) {
+        let stats = DaemonStats {
+            uptime_secs: 0,
+            online: true,
+            bytes_uploaded: 0,
+            bytes_downloaded: 0,
+            cache_hits: hits,
+            cache_misses: misses,
+            api_calls: Vec::new(),
+            fuse_ops: Vec::new(),
+        };
+        assert_eq!(stats.cache_hit_ratio(), expected);
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let histogram = Histogram::new();
+        histogram.observe(Duration::from_millis(2));
+        let stats = DaemonStats {
+            uptime_secs: 60,
+            online: true,
+            bytes_uploaded: 1024,
+            bytes_downloaded: 2048,
+            cache_hits: 1,
+            cache_misses: 1,
+            api_calls: vec![histogram.snapshot("download_chunk", 1)],
+            fuse_ops: Vec::new(),
+        };
+
+        let text = render_prometheus(&stats);
+        assert!(text.contains("gdrivexp_bytes_downloaded_total 2048\n"));
+        assert!(text.contains("gdrivexp_cache_hit_ratio 0.5\n"));
+        assert!(text.contains("gdrivexp_api_errors_total{method=\"download_chunk\"} 1\n"));
+        assert!(text.contains("gdrivexp_api_call_duration_seconds_bucket{method=\"download_chunk\",le=\"0.001\"} 0\n"));
+        assert!(text.contains("gdrivexp_api_call_duration_seconds_bucket{method=\"download_chunk\",le=\"0.0025\"} 1\n"));
+        assert!(text.contains("gdrivexp_api_call_duration_seconds_count{method=\"download_chunk\"} 1\n"));
+    }
+}
@@ -4,8 +4,11 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 use tracing::{info, error, warn};
 
-use crate::db::MetadataRepository;
+use crate::db::{MetadataRepository, UploadOp};
+use crate::i18n::t;
 use crate::gui::history::{ActionHistory, ActionType, TransferOp};
+use crate::utils::ignore::IgnoreRules;
+use crate::sync::exclusions::Exclusions;
 
 const HIDDEN_MANIFEST: &str = ".gdrivexp_hidden_manifest";
 
@@ -36,7 +39,13 @@ struct MirrorContext {
     db: Arc<MetadataRepository>,
     mirror_path: PathBuf,
     fuse_mount_path: PathBuf,
+    /// Caché de contenido de FUSE (`cache_dir/<gdrive_id>`)
+    cache_dir: PathBuf,
     history: ActionHistory,
+    /// Rutas locales que nunca se registran (`Config.ignore_patterns`)
+    ignore_rules: Arc<IgnoreRules>,
+    /// Carpetas excluidas (`Config.excluded_folders`): no admiten contenido nuevo
+    exclusions: Arc<Exclusions>,
 }
 
 /// Gestor principal de la arquitectura Espejo
@@ -55,6 +64,7 @@ impl MirrorManager {
         db: Arc<MetadataRepository>,
         mirror_path: PathBuf,
         fuse_mount_path: PathBuf,
+        cache_dir: PathBuf,
         history: ActionHistory,
         bfs_ready_rx: tokio::sync::watch::Receiver<bool>,
     ) -> (Self, mpsc::Sender<MirrorCommand>) {
@@ -65,7 +75,10 @@ impl MirrorManager {
             db,
             mirror_path,
             fuse_mount_path,
+            cache_dir,
             history,
+            ignore_rules: Arc::new(IgnoreRules::default()),
+            exclusions: Arc::new(Exclusions::default()),
         });
 
         let manager = Self {
@@ -80,6 +93,18 @@ impl MirrorManager {
         (manager, tx)
     }
 
+    /// Archivos del espejo que quedan solo en local, sin registrarse en la DB
+    pub fn with_ignore_rules(mut self, rules: Arc<IgnoreRules>) -> Self {
+        Arc::make_mut(&mut self.ctx).ignore_rules = rules;
+        self
+    }
+
+    /// Carpetas excluidas: lo creado dentro se queda solo en local
+    pub fn with_exclusions(mut self, exclusions: Arc<Exclusions>) -> Self {
+        Arc::make_mut(&mut self.ctx).exclusions = exclusions;
+        self
+    }
+
     /// Inicia el gestor en segundo plano
     pub fn spawn(mut self) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
@@ -467,6 +492,18 @@ impl MirrorManager {
 
     // Funciones estáticas que reciben contexto en lugar de &self
     
+    /// Guarda la elección de la carpeta: decide qué pasa con lo que entre después
+    async fn mark_folder(ctx: &MirrorContext, path: &std::path::Path, availability: &str) {
+        let Ok(relative) = path.strip_prefix(&ctx.mirror_path) else {
+            return;
+        };
+        if let Ok(Some(inode)) = ctx.db.resolve_relative_path_to_inode(relative.to_str().unwrap_or("")).await
+            && let Err(e) = ctx.db.set_folder_availability(inode, availability).await
+        {
+            warn!("Error guardando disponibilidad de carpeta {:?}: {:?}", relative, e);
+        }
+    }
+
     async fn static_handle_set_online_only(ctx: &MirrorContext, path_str: &str) {
         Self::static_handle_set_online_only_opt(ctx, path_str, true).await;
     }
@@ -489,7 +526,8 @@ impl MirrorManager {
                     let name_display = path.file_name()
                         .map(|n| n.to_string_lossy().to_string())
                         .unwrap_or_else(|| path_str.to_string());
-                    ctx.history.log(ActionType::Sync, format!("Liberando espacio en carpeta: {}", name_display));
+                    ctx.history.log(ActionType::Sync, t!("activity-freeing-folder", name = name_display.as_str()));
+                    Self::mark_folder(ctx, &path, "online_only").await;
 
                     let mut stack = vec![path.clone()];
                     while let Some(current_dir) = stack.pop() {
@@ -537,23 +575,15 @@ impl MirrorManager {
         // If inode exists in DB with valid gdrive_id, file WILL exist in FUSE when accessed
         if let Ok(Some(inode)) = ctx.db.resolve_relative_path_to_inode(relative.to_str().unwrap_or("")).await {
             // Verificar si el archivo es puramente local (aún no subido)
-            let gdrive_id: Option<String> = sqlx::query_scalar("SELECT gdrive_id FROM inodes WHERE inode = ?")
-                .bind(inode as i64)
-                .fetch_optional(ctx.db.pool())
-                .await
-                .unwrap_or(None);
-
-            if let Some(id) = gdrive_id {
-                if id.starts_with("temp_") {
-                    let file_name = path.file_name()
-                        .map(|f| f.to_string_lossy())
-                        .unwrap_or_else(|| "unknown".into());
-                    warn!("Intento bloqueado de liberar espacio de archivo local no sincronizado: {:?}", path);
-                    if log_history {
-                        ctx.history.log(ActionType::Error, format!("No se puede liberar: {} (Pendiente a subir)", file_name));
-                    }
-                    return;
+            if ctx.db.is_pending_create(inode).await.unwrap_or(false) {
+                let file_name = path.file_name()
+                    .map(|f| f.to_string_lossy())
+                    .unwrap_or_else(|| "unknown".into());
+                warn!("Intento bloqueado de liberar espacio de archivo local no sincronizado: {:?}", path);
+                if log_history {
+                    ctx.history.log(ActionType::Error, t!("activity-cannot-free", name = file_name));
                 }
+                return;
             }
         }
 
@@ -611,7 +641,7 @@ impl MirrorManager {
                     let name_display = path.file_name()
                         .map(|n| n.to_string_lossy().to_string())
                         .unwrap_or_else(|| path.to_string_lossy().into_owned());
-                    ctx.history.log(ActionType::Sync, format!("Solo online: {}", name_display));
+                    ctx.history.log(ActionType::Sync, t!("activity-online-only", name = name_display.as_str()));
                 }
                 info!("☁️ Espacio liberado (External Temp): {:?}", relative);
 
@@ -670,7 +700,8 @@ impl MirrorManager {
                     let name_display = path.file_name()
                         .map(|n| n.to_string_lossy().to_string())
                         .unwrap_or_else(|| path_str.to_string());
-                    ctx.history.log(ActionType::Download, format!("Descargando carpeta: {}", name_display));
+                    ctx.history.log(ActionType::Download, t!("activity-downloading-folder", name = name_display.as_str()));
+                    Self::mark_folder(ctx, &path, "local_online").await;
 
                     let mut stack = vec![path.clone()];
                     while let Some(current_dir) = stack.pop() {
@@ -724,7 +755,7 @@ impl MirrorManager {
             let name_display = path.file_name()
                 .map(|n| n.to_string_lossy().to_string())
                 .unwrap_or_else(|| path.to_string_lossy().into_owned());
-            ctx.history.log(ActionType::Download, format!("Descargando: {}", name_display));
+            ctx.history.log(ActionType::Download, t!("activity-downloading", name = name_display.as_str()));
         }
         info!("📥 Iniciando descarga: {:?}", relative);
 
@@ -817,7 +848,7 @@ impl MirrorManager {
                   let name_display = path.file_name()
                       .map(|n| n.to_string_lossy().to_string())
                       .unwrap_or_else(|| path.to_string_lossy().into_owned());
-                  ctx.history.log(ActionType::Download, format!("Descargado: {}", name_display));
+                  ctx.history.log_file(ActionType::Download, t!("activity-downloaded", name = name_display.as_str()), Some(path.clone()));
               }
               info!("✅ Archivo descargado exitosamente (External Temp): {:?}", relative);
 
@@ -945,7 +976,7 @@ impl MirrorManager {
 
     async fn handle_local_rename(&self, old_path: &PathBuf, new_path: &PathBuf) {
         let old_name = old_path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
-        self.ctx.history.log(ActionType::Sync, format!("Moviendo: {}", old_name));
+        self.ctx.history.log(ActionType::Sync, t!("activity-moving", name = old_name.as_ref()));
         tracing::info!("🔄 DETECTADO RENOMBRADO INTELIGENTE: {:?} -> {:?}", old_path, new_path);
         
         // 1. Calcular relativas
@@ -984,7 +1015,7 @@ impl MirrorManager {
         if let Ok(attrs) = db.get_attrs(inode).await {
             if !attrs.can_move {
                 warn!("⛔ Bloqueando movimiento de archivo compartido (ReadOnly). Revirtiendo físicamente: {:?} -> {:?}", new_path, old_path);
-                self.ctx.history.log(ActionType::Error, format!("Movimiento bloqueado: {}", old_name));
+                self.ctx.history.log(ActionType::Error, t!("activity-move-blocked", name = old_name.as_ref()));
 
                 // Intentar moverlo de vuelta físicamente
                 if let Err(e) = tokio::fs::rename(new_path, old_path).await {
@@ -1026,8 +1057,17 @@ impl MirrorManager {
         
         info!("📝 Preparando Move en DB: inode={} -> new_parent={}, new_name={}", inode, new_parent_inode, new_name);
 
-        // A. Verificar si el destino ya existe en la DB
-        if let Ok(Some(existing_dest_inode)) = db.lookup(new_parent_inode, &new_name).await {
+        // A. Verificar si el destino ya existe en la DB. Un archivo local
+        // renombrado encima de un archivo de Drive pasa a ser el destino (mismo
+        // ID y enlaces) y solo se sube su contenido
+        let mut in_place = false;
+        if let Ok(Some(existing_dest_inode)) = db.lookup(new_parent_inode, &new_name).await
+            && tokio::fs::symlink_metadata(new_path).await.is_ok_and(|meta| meta.is_file())
+            && db.can_replace_in_place(inode, existing_dest_inode).await.unwrap_or(false)
+        {
+            in_place = self.adopt_replaced(inode, existing_dest_inode, new_path).await;
+        }
+        if !in_place && let Ok(Some(existing_dest_inode)) = db.lookup(new_parent_inode, &new_name).await {
             warn!("⚠️ Conflicto detectado en Rename. El destino '{}' ya existe (inode={}). Eliminando anterior...", new_name, existing_dest_inode);
             
             // Resolvemos gdrive_id para aplicar soft_delete si es posible
@@ -1038,7 +1078,9 @@ impl MirrorManager {
                 .unwrap_or(None);
 
             if let Some(gid) = existing_gdrive_id {
-                let _ = db.soft_delete_by_gdrive_id(&gid).await;
+                if db.soft_delete_by_gdrive_id(&gid).await.is_ok() {
+                    let _ = db.record_upload_op(existing_dest_inode, UploadOp::Delete).await;
+                }
             } else {
                 // Si no tiene gdrive_id, es un dentry local puro, lo borramos de dentry
                 let _ = sqlx::query("DELETE FROM dentry WHERE child_inode = ?")
@@ -1061,9 +1103,11 @@ impl MirrorManager {
         }
 
         // 6. Marcar DIRTY y burbujear estado a ancestros
-        if let Err(e) = db.set_dirty_and_bubble(inode).await {
+        if let Err(e) = db.mark_dirty(inode).await {
              error!("Error marcando dirty tras Rename: {:?}", e);
         }
+        let op = if in_place { UploadOp::Write } else { UploadOp::Rename };
+        let _ = db.record_upload_op(inode, op).await;
 
         // 7. Reparar target de symlink si el archivo movido es online_only.
         // El kernel mueve el *archivo* symlink correctamente, pero su contenido (el target path)
@@ -1115,6 +1159,42 @@ impl MirrorManager {
         info!("✅ Renombrado local procesado exitosamente (pendiente confirmación de Drive).");
     }
 
+    /// Rename encima de un archivo de Drive en el espejo: `inode` pasa a ser el
+    /// archivo `replaced` (ver `MetadataRepository::adopt_replaced_file`). Se
+    /// copia el archivo real del espejo a la caché bajo el ID adoptado para que
+    /// FUSE y el uploader vean la versión nueva. Retorna `false` si no se pudo
+    /// (el rename sigue como sobrescritura normal).
+    async fn adopt_replaced(&self, inode: u64, replaced: u64, new_path: &Path) -> bool {
+        if let Err(e) = tokio::fs::File::open(new_path).await {
+            error!("Error leyendo {:?} para reemplazar el destino: {:?}", new_path, e);
+            return false;
+        }
+        let db = &self.ctx.db;
+        let gdrive_id = match db.adopt_replaced_file(inode, replaced).await {
+            Ok(gdrive_id) => gdrive_id,
+            Err(e) => {
+                error!("Error adoptando inode {} como nueva versión de {}: {:?}", inode, replaced, e);
+                return false;
+            }
+        };
+        let cached = async {
+            db.clear_cached_chunks(inode).await?;
+            let len = crate::utils::cache_crypto::import_file(new_path, &self.ctx.cache_dir.join(&gdrive_id)).await?;
+            if len > 0 {
+                db.add_cached_chunk(inode, 0, len - 1).await?;
+            }
+            anyhow::Ok(())
+        };
+        if let Err(e) = cached.await {
+            error!("Error copiando {:?} a la caché tras reemplazar el destino: {:?}", new_path, e);
+        }
+
+        let name = new_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        self.ctx.history.log_file(ActionType::Upload, t!("activity-modified", name = name.as_str()), Some(new_path.to_path_buf()));
+        info!("💾 Inode {} reemplaza a {} como nueva versión de {}", inode, replaced, gdrive_id);
+        true
+    }
+
     async fn handle_local_change(&self, path: &PathBuf, relative_path: &str, is_dir: bool) {
         // 1. Procesar el nodo principal
         let is_new = self.process_local_change(path, relative_path, is_dir).await;
@@ -1194,6 +1274,12 @@ impl MirrorManager {
             }
         }
 
+        // Ignorados: se quedan como archivo real en el espejo, sin pasar a Drive
+        if self.ctx.ignore_rules.is_ignored_path(Path::new(relative_path)) {
+            tracing::debug!("🙈 Ignorando cambio local por ignore_patterns: {}", relative_path);
+            return false;
+        }
+
         tracing::debug!("📝 Cambio local detectado: {} (dir={})", relative_path, is_dir);
 
         let db = &self.ctx.db;
@@ -1233,13 +1319,17 @@ impl MirrorManager {
         };
 
         let is_new = existing_inode.is_none();
+        if is_new && self.parent_excluded(parent_inode).await {
+            warn!("🚫 {} está en una carpeta excluida: se queda solo en local", relative_path);
+            return false;
+        }
         let inode = if let Some(i) = existing_inode {
             // UPDATE
             i
         } else {
-            // CREATE - Generar ID temporal
-            let temp_id = format!("temp_{}", uuid::Uuid::new_v4());
-            match db.get_or_create_inode(&temp_id).await {
+            // CREATE - Generar ID temporal (Drive asigna el real al subir)
+            let temp_id = format!("{}{}", crate::gdrive::id_pool::TEMP_ID_PREFIX, uuid::Uuid::new_v4());
+            match db.create_local_inode(&temp_id).await {
                 Ok(i) => i,
                 Err(e) => {
                     error!("Error creando inode temporal: {:?}", e);
@@ -1275,24 +1365,39 @@ impl MirrorManager {
         if let Err(e) = db.set_availability(inode, "local_online", true).await {
             error!("Error asegurando availability='local_online': {:?}", e);
         }
-        // Luego set_dirty_and_bubble (detecta estado previo automáticamente)
-        if let Err(e) = db.set_dirty_and_bubble(inode).await {
+        // Luego mark_dirty (detecta estado previo automáticamente)
+        if let Err(e) = db.mark_dirty(inode).await {
              error!("Error marcando dirty: {:?}", e);
         }
+        let op = if is_new { UploadOp::Create } else { UploadOp::Write };
+        let _ = db.record_upload_op(inode, op).await;
         
         let name_display = PathBuf::from(relative_path).file_name()
             .map(|n| n.to_string_lossy().to_string())
             .unwrap_or_else(|| relative_path.to_string());
+        let mirror_file = Some(self.ctx.mirror_path.join(relative_path));
         if is_new {
-            self.ctx.history.log(ActionType::Create, format!("Local Creado: {}", name_display));
+            self.ctx.history.log_file(ActionType::Create, t!("activity-local-created", name = name_display.as_str()), mirror_file);
         } else {
-            self.ctx.history.log(ActionType::Upload, format!("Modificado: {}", name_display));
+            self.ctx.history.log_file(ActionType::Upload, t!("activity-modified", name = name_display.as_str()), mirror_file);
         }
         info!("✅ Cambio local registrado: {} (inode={})", relative_path, inode);
         
         is_new
     }
     
+    /// `true` si `parent` es una carpeta excluida (`Config.excluded_folders`):
+    /// registrar algo dentro lo subiría y el escaneo lo volvería a retirar
+    async fn parent_excluded(&self, parent: u64) -> bool {
+        if self.ctx.exclusions.is_empty() {
+            return false;
+        }
+        match self.ctx.db.get_gdrive_id(parent).await {
+            Ok(Some(gdrive_id)) => self.ctx.exclusions.covers(&gdrive_id),
+            _ => false,
+        }
+    }
+
     async fn handle_local_delete(&self, relative_path: &str) {
         tracing::debug!("🗑️ Eliminación local detectada: {}", relative_path);
         
@@ -1335,10 +1440,11 @@ impl MirrorManager {
                 if let Err(e) = db.soft_delete_by_gdrive_id(&gid).await {
                     error!("Error realizando soft delete en DB: {:?}", e);
                 } else {
+                    let _ = db.record_upload_op(inode, UploadOp::Delete).await;
                     let name_display = PathBuf::from(relative_path).file_name()
                         .map(|n| n.to_string_lossy().to_string())
                         .unwrap_or_else(|| relative_path.to_string());
-                    self.ctx.history.log(ActionType::Delete, format!("Eliminado: {}", name_display));
+                    self.ctx.history.log(ActionType::Delete, t!("activity-deleted", name = name_display.as_str()));
                     info!("✅ Eliminación registrada (Cascada): {} (id={})", relative_path, gid);
                 }
             } else {
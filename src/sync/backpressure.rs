@@ -0,0 +1,116 @@
+//! Monitor de contrapresión: mide el trabajo pendiente de subir y el espacio
+//! libre de `cache_dir` y publica en `utils::backpressure` si FUSE debe frenar
+//! las escrituras
+//!
+//! Corre cada `CHECK_INTERVAL`: una suma en SQLite y un `statvfs`, baratos
+//! frente a lo que puede escribirse en ese tiempo. Las subidas del uploader y
+//! las limpiezas de caché se notan en la siguiente medida.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::db::MetadataRepository;
+use crate::gui::history::{ActionHistory, ActionType};
+use crate::i18n::t;
+use crate::utils::backpressure::{self, Limits, Pressure};
+
+/// Intervalo entre medidas
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct BackpressureMonitor {
+    db: Arc<MetadataRepository>,
+    cache_dir: PathBuf,
+    limits: Limits,
+    history: ActionHistory,
+}
+
+impl BackpressureMonitor {
+    pub fn new(db: Arc<MetadataRepository>, cache_dir: PathBuf, limits: Limits, history: ActionHistory) -> Self {
+        Self { db, cache_dir, limits, history }
+    }
+
+    /// Inicia el monitor en un task de Tokio separado
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!(
+                "🚦 Monitor de contrapresión iniciado (pendiente máx.: {} MB, libre mín.: {} MB)",
+                self.limits.max_dirty_bytes / (1024 * 1024),
+                self.limits.min_free_bytes / (1024 * 1024)
+            );
+
+            loop {
+                match self.check_once().await {
+                    Ok(pressure) => {
+                        if backpressure::set(pressure) {
+                            self.log_transition(pressure);
+                        }
+                    }
+                    Err(e) => debug!("No se pudo medir la contrapresión: {:?}", e),
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                    _ = crate::utils::shutdown::wait_for_shutdown() => {
+                        // Nadie más liberará a las escrituras en espera
+                        backpressure::set(None);
+                        break;
+                    }
+                }
+            }
+        })
+    }
+
+    /// Mide una vez y retorna el motivo de contrapresión, si lo hay
+    pub async fn check_once(&self) -> anyhow::Result<Option<Pressure>> {
+        let dirty_bytes = if self.limits.max_dirty_bytes > 0 {
+            self.db.dirty_backlog_bytes().await?
+        } else {
+            0
+        };
+        let free_bytes = if self.limits.min_free_bytes > 0 {
+            backpressure::available_space(&self.cache_dir)?
+        } else {
+            u64::MAX
+        };
+        Ok(self.limits.evaluate(dirty_bytes, free_bytes))
+    }
+
+    fn log_transition(&self, pressure: Option<Pressure>) {
+        match pressure {
+            Some(Pressure::DirtyBacklog) => self.history.log(
+                ActionType::Error,
+                t!("activity-backpressure-backlog", mb = self.limits.max_dirty_bytes / (1024 * 1024)),
+            ),
+            Some(Pressure::LowDiskSpace) => self.history.log(
+                ActionType::Error,
+                t!("activity-backpressure-disk", mb = self.limits.min_free_bytes / (1024 * 1024)),
+            ),
+            None => self.history.log(ActionType::Sync, t!("activity-backpressure-relieved")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::UploadOp;
+
+    #[tokio::test]
+    async fn test_check_once_reports_backlog_until_uploaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap());
+        let inode = db.create_local_inode("temp_grande").await.unwrap();
+        db.upsert_file_metadata(inode, 2 * 1024 * 1024, 100, 0o644, false, None, true, false, true).await.unwrap();
+        db.mark_dirty(inode).await.unwrap();
+        db.record_upload_op(inode, UploadOp::Create).await.unwrap();
+
+        let limits = Limits { max_dirty_bytes: 1024 * 1024, min_free_bytes: 0 };
+        let monitor = BackpressureMonitor::new(db.clone(), dir.path().to_path_buf(), limits, ActionHistory::new());
+        assert_eq!(monitor.check_once().await.unwrap(), Some(Pressure::DirtyBacklog));
+
+        db.clear_dirty(inode).await.unwrap();
+        assert_eq!(monitor.check_once().await.unwrap(), None);
+    }
+}
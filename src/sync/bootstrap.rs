@@ -2,42 +2,14 @@ use anyhow::Result;
 use std::collections::HashMap;
 use std::sync::Arc;
 use crate::db::MetadataRepository;
-use crate::gdrive::client::DriveClient;
+use crate::gdrive::DriveApi;
+use crate::i18n::t;
 
-/// Asegura que el inode raíz (1) exista en la base de datos.
-/// Esto es necesario porque GDrive no tiene un "archivo" para el root,
-/// pero FUSE siempre consulta inode=1 como punto de entrada.
+/// Asegura que el inode raíz (1) exista en la base de datos
+/// (`MetadataRepository::ensure_root`) y corrige permisos de versiones anteriores.
 async fn ensure_root_exists(db: &Arc<MetadataRepository>) -> Result<()> {
     let pool = db.pool();
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)?
-        .as_secs() as i64;
-
-    // Insertar en tabla inodes (el root tiene gdrive_id = "root")
-    sqlx::query(
-        r#"
-        INSERT OR IGNORE INTO inodes (inode, gdrive_id, generation, created_at)
-        VALUES (1, 'root', 0, ?)
-        "#
-    )
-    .bind(now)
-    .execute(pool)
-    .await?;
-
-    // Insertar en tabla attrs
-    sqlx::query(
-        r#"
-        INSERT OR IGNORE INTO attrs (inode, size, mtime, ctime, mode, is_dir, mime_type)
-        VALUES (1, 4096, ?, ?, 493, 1, 'application/vnd.google-apps.folder')
-        "#
-    )
-    .bind(now)
-    .bind(now)
-    .execute(pool)
-    .await?;
-
-    // Inicializar dir_counters para root
-    db.ensure_dir_counter(1).await?;
+    db.ensure_root().await?;
 
     tracing::debug!("Inode raíz (1) verificado/creado en la base de datos");
 
@@ -54,6 +26,39 @@ async fn ensure_root_exists(db: &Arc<MetadataRepository>) -> Result<()> {
     Ok(())
 }
 
+/// Clave de `sync_meta` con el número de archivos del último escaneo completo
+const LAST_SCAN_TOTAL_KEY: &str = "last_scan_total";
+
+/// Clave de `sync_meta` con el `ScanCheckpoint` del escaneo en curso
+const SCAN_CHECKPOINT_KEY: &str = "scan_checkpoint";
+
+/// Progreso del escaneo progresivo tras la última página aplicada a la DB.
+/// Si el proceso muere a mitad, el siguiente escaneo continúa desde
+/// `page_token` en vez de volver a leer todo Drive.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+struct ScanCheckpoint {
+    /// Token de la siguiente página de `files.list`
+    page_token: String,
+    pages: u32,
+    scanned: usize,
+    /// Compartidos no propios de las páginas ya leídas (se vinculan al final)
+    shared_non_owned: Vec<(u64, String)>,
+    /// Carpetas excluidas en el `q` del escaneo: Drive exige el mismo filtro en cada página
+    #[serde(default)]
+    excluded_parents: Vec<String>,
+}
+
+async fn load_scan_checkpoint(db: &MetadataRepository) -> Option<ScanCheckpoint> {
+    let raw = db.get_sync_meta(SCAN_CHECKPOINT_KEY).await.ok()??;
+    match serde_json::from_str(&raw) {
+        Ok(checkpoint) => Some(checkpoint),
+        Err(e) => {
+            tracing::warn!("⚠️ Checkpoint de escaneo ilegible, se escanea desde el principio: {}", e);
+            None
+        }
+    }
+}
+
 /// Si el archivo es un shortcut de Google Drive, retorna (target_id, target_mime_type).
 pub fn resolve_shortcut_info(file: &google_drive3::api::File) -> Option<(String, String)> {
     if file.mime_type.as_deref() != Some("application/vnd.google-apps.shortcut") {
@@ -65,58 +70,100 @@ pub fn resolve_shortcut_info(file: &google_drive3::api::File) -> Option<(String,
     Some((target_id, target_mime))
 }
 
-/// Helper: procesa un archivo de Drive e inserta inode + attrs.
-/// Retorna (inode, is_dir).
-async fn insert_file_metadata(
-    db: &Arc<MetadataRepository>,
-    file: &google_drive3::api::File,
-) -> Result<Option<(u64, bool)>> {
-    let id = match &file.id {
-        Some(id) => id,
-        None => return Ok(None),
-    };
-
-    let inode = db.get_or_create_inode(id).await?;
-
-    let shortcut_info = resolve_shortcut_info(file);
-    let effective_mime = shortcut_info.as_ref()
-        .map(|(_, mime)| mime.as_str())
-        .or(file.mime_type.as_deref());
-
-    let is_dir = effective_mime == Some("application/vnd.google-apps.folder");
-    let size = file.size.unwrap_or(0);
+/// Permisos (`canEdit`, `canTrash`) y propietarios de un archivo de Drive.
+/// Si Drive no informa una capability se asume permitida.
+pub fn capabilities_for(file: &google_drive3::api::File) -> crate::db::FileCapabilities {
+    let caps = file.capabilities.as_ref();
+    let owners = file.owners.iter().flatten()
+        .filter_map(|user| match (&user.display_name, &user.email_address) {
+            (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+            (Some(name), None) => Some(name.clone()),
+            (None, Some(email)) => Some(email.clone()),
+            (None, None) => None,
+        })
+        .collect();
+
+    crate::db::FileCapabilities {
+        can_edit: caps.and_then(|c| c.can_edit).unwrap_or(true),
+        can_trash: caps.and_then(|c| c.can_trash).unwrap_or(true),
+        owners,
+    }
+}
+
+/// Modo POSIX de un archivo de Drive: sin permiso de edición se expone de
+/// solo lectura (el kernel rechaza la escritura por `default_permissions`)
+pub fn posix_mode(is_dir: bool, can_edit: bool) -> u32 {
+    match (is_dir, can_edit) {
+        (true, true) => 0o755,
+        (true, false) => 0o555,
+        (false, true) => 0o644,
+        (false, false) => 0o444,
+    }
+}
+
+/// Carpeta de un equipo de la sección "Ordenadores" (copias de Backup and Sync
+/// o Drive para escritorio): carpeta propia sin padres que no es la raíz de
+/// "Mi unidad" (`root_id`) y que Drive no deja mover. Una carpeta propia
+/// huérfana (su padre era de otro usuario y lo borró) también llega sin
+/// padres, pero sí se puede mover: no es un equipo.
+/// Cuelga del directorio virtual `COMPUTERS_GDRIVE_ID`, visible según `Config.computers_mode`.
+pub fn is_computer_folder(file: &google_drive3::api::File, root_id: &str) -> bool {
+    file.mime_type.as_deref() == Some("application/vnd.google-apps.folder")
+        && file.owned_by_me == Some(true)
+        && file.parents.as_ref().is_none_or(|parents| parents.is_empty())
+        && file.id.as_deref().is_some_and(|id| id != root_id && id != "root")
+        && file.capabilities.as_ref().and_then(|c| c.can_move_item_within_drive) == Some(false)
+}
+
+/// Helper: construye la fila de `attrs` para inserción masiva a partir de un archivo de Drive.
+/// Para shortcuts usa el mime type del destino.
+fn bulk_metadata_for(inode: u64, file: &google_drive3::api::File) -> crate::db::BulkFileMetadata {
+    let effective_mime = resolve_shortcut_info(file)
+        .map(|(_, mime)| mime)
+        .or_else(|| file.mime_type.clone());
+
+    let is_dir = effective_mime.as_deref() == Some("application/vnd.google-apps.folder");
     let mtime = file.modified_time
         .as_ref()
         .map(|t| t.timestamp())
         .unwrap_or(0);
-    let mode = if is_dir { 0o755 } else { 0o644 };
+    let mtime_nsec = file.modified_time
+        .as_ref()
+        .map(|t| t.timestamp_subsec_nanos())
+        .unwrap_or(0);
     let can_move = file.capabilities.as_ref()
         .and_then(|c| c.can_move_item_within_drive)
         .unwrap_or(true);
-    let shared = file.shared.unwrap_or(false);
-
-    db.upsert_file_metadata(
-        inode, size, mtime, mode, is_dir,
-        effective_mime, can_move, shared,
-        file.owned_by_me.unwrap_or(true),
-    ).await?;
-
-    if let Some((target_id, _)) = &shortcut_info {
-        db.set_shortcut_target_id(inode, target_id).await?;
-    }
-
-    if is_dir {
-        db.ensure_dir_counter(inode).await?;
+    let can_edit = file.capabilities.as_ref()
+        .and_then(|c| c.can_edit)
+        .unwrap_or(true);
+    let marks = crate::gdrive::app_properties::AppMarks::from_file(file);
+
+    crate::db::BulkFileMetadata {
+        inode,
+        size: file.size.unwrap_or(0),
+        mtime,
+        mtime_nsec,
+        mode: posix_mode(is_dir, can_edit),
+        is_dir,
+        mime_type: effective_mime,
+        can_move,
+        shared: file.shared.unwrap_or(false),
+        owned_by_me: file.owned_by_me.unwrap_or(true),
+        starred: file.starred.unwrap_or(false),
+        created_time: file.created_time.as_ref().map(|t| t.timestamp()),
+        origin_client: marks.origin_client,
+        pin_hint: marks.pinned,
+        description: file.description.clone(),
+        folder_color: if is_dir { file.folder_color_rgb.clone() } else { None },
     }
-
-    Ok(Some((inode, is_dir)))
 }
 
 /// Bootstrap Fase 1: Solo los hijos directos del root.
 /// Retorna rápidamente (~1 segundo) permitiendo que la app funcione de inmediato.
 pub async fn bootstrap_level1(
     db: &Arc<MetadataRepository>,
-    client: &Arc<DriveClient>,
+    client: &Arc<dyn DriveApi>,
     root_id: &str,
 ) -> Result<()> {
     tracing::info!("Bootstrap nivel 1: cargando hijos directos del root...");
@@ -127,13 +174,48 @@ pub async fn bootstrap_level1(
     let root_children = client.list_root_children(root_id).await?;
     tracing::info!("Bootstrap nivel 1: {} items encontrados en root", root_children.len());
 
-    // Insertar inodes + attrs + dentries para nivel 1
+    // Insertar inodes + attrs + dentries para nivel 1 (transacciones por lotes)
+    let ids: Vec<String> = root_children.iter().filter_map(|f| f.id.clone()).collect();
+    let inodes = db.get_or_create_inodes_bulk(&ids).await?;
+
+    let mut metadata_buffer = Vec::with_capacity(root_children.len());
+    let mut dentry_buffer = Vec::with_capacity(root_children.len());
+    let mut shortcut_targets = Vec::new();
+    let mut web_links = Vec::new();
+    let mut thumbnail_links = Vec::new();
+    let mut capabilities = Vec::with_capacity(root_children.len());
+
     for file in &root_children {
-        if let Some((inode, _is_dir)) = insert_file_metadata(db, file).await? {
-            if let Some(name) = &file.name {
-                db.upsert_dentry(1, inode, name).await?;
-            }
+        let Some(&inode) = file.id.as_ref().and_then(|id| inodes.get(id)) else {
+            continue;
+        };
+
+        metadata_buffer.push(bulk_metadata_for(inode, file));
+        capabilities.push((inode, capabilities_for(file)));
+        if let Some(name) = &file.name {
+            dentry_buffer.push(crate::db::BulkDentry {
+                parent_inode: 1, child_inode: inode, name: name.clone(),
+            });
+        }
+        if let Some((target_id, _)) = resolve_shortcut_info(file) {
+            shortcut_targets.push((inode, target_id));
+        }
+        if let Some(link) = &file.web_view_link {
+            web_links.push((inode, link.clone()));
         }
+        if let Some(link) = &file.thumbnail_link {
+            thumbnail_links.push((inode, link.clone()));
+        }
+    }
+
+    db.upsert_bulk_file_metadata(&metadata_buffer).await?;
+    db.upsert_bulk_dentries(&dentry_buffer).await?;
+    db.set_bulk_web_view_links(&web_links).await?;
+    db.set_bulk_thumbnail_links(&thumbnail_links).await?;
+    db.upsert_bulk_capabilities(&capabilities).await?;
+    if !shortcut_targets.is_empty() {
+        db.set_bulk_shortcut_targets(&shortcut_targets).await?;
+        db.resolve_shortcut_sizes().await?;
     }
 
     // Recalcular contadores del root después de insertar nivel 1
@@ -148,10 +230,11 @@ pub async fn bootstrap_level1(
 /// Elimina el blackout del bootstrap: los archivos aparecen en el espejo a medida que se escanean.
 pub async fn bootstrap_remaining_bfs(
     db: &Arc<MetadataRepository>,
-    client: &Arc<DriveClient>,
+    client: &Arc<dyn DriveApi>,
     root_id: &str,
     history: &crate::gui::history::ActionHistory,
     mirror_sender: &tokio::sync::mpsc::Sender<crate::mirror::MirrorCommand>,
+    exclusions: &crate::sync::exclusions::Exclusions,
 ) -> Result<()> {
     tracing::info!("Escaneo progresivo: iniciando...");
     ensure_root_exists(db).await?;
@@ -165,19 +248,68 @@ pub async fn bootstrap_remaining_bfs(
 
     // Archivos compartidos no propios para resolución de huérfanos al final
     let mut shared_non_owned: Vec<(u64, String)> = Vec::new();
-    // Shortcuts pendientes de resolución de size al final
-    let mut shortcut_targets: Vec<(u64, String)> = Vec::new();
+    // Hubo shortcuts (su target se guarda por página; el size se resuelve al final)
+    let mut has_shortcuts = false;
 
     let mut page_token: Option<String> = None;
     let mut total_scanned: usize = 0;
     let mut page_number: u32 = 0;
+    let mut excluded_parents = exclusions.query_parents();
+
+    // Reanudar un escaneo interrumpido desde la última página aplicada
+    let mut resumed = false;
+    if let Some(checkpoint) = load_scan_checkpoint(db).await {
+        tracing::info!(
+            "🔁 Escaneo progresivo: reanudando en la página {} ({} archivos ya escaneados)",
+            checkpoint.pages + 1, checkpoint.scanned
+        );
+        history.log(
+            crate::gui::history::ActionType::Sync,
+            t!("activity-scan-resumed", page = checkpoint.pages + 1, count = checkpoint.scanned),
+        );
+        page_token = Some(checkpoint.page_token);
+        page_number = checkpoint.pages;
+        total_scanned = checkpoint.scanned;
+        shared_non_owned = checkpoint.shared_non_owned;
+        excluded_parents = checkpoint.excluded_parents;
+        has_shortcuts = true;
+        resumed = true;
+    }
+
+    // Total estimado para el progreso n/total: Drive no expone un conteo de archivos,
+    // así que se usa el total del último escaneo completo (0 = desconocido)
+    let expected_total: usize = db.get_sync_meta(LAST_SCAN_TOTAL_KEY).await?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    history.set_scanning_expected(expected_total);
+
+    // Las carpetas de "Ordenadores" no tienen padre en Drive: se agrupan aquí
+    let computers_inode = db.computers_inode().await?;
 
     loop {
         // Obtener una página de la API
-        let (page_files, next_token) = client.fetch_files_page(page_token.as_deref()).await?;
+        let (mut page_files, next_token) = match client.fetch_files_page(page_token.as_deref(), &excluded_parents).await {
+            Ok(page) => page,
+            // Drive rechaza un pageToken caducado: volver a empezar sin checkpoint
+            Err(e) if resumed && matches!(e.downcast_ref::<crate::gdrive::DriveError>(), Some(crate::gdrive::DriveError::ApiError(_))) => {
+                tracing::warn!("⚠️ No se pudo reanudar el escaneo ({}), se empieza desde el principio", e);
+                db.delete_sync_meta(SCAN_CHECKPOINT_KEY).await?;
+                resumed = false;
+                page_token = None;
+                page_number = 0;
+                total_scanned = 0;
+                shared_non_owned.clear();
+                excluded_parents = exclusions.query_parents();
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        resumed = false;
         if page_files.is_empty() && next_token.is_none() {
             break;
         }
+        // Lo que el `q` no pudo omitir (subcarpetas aún desconocidas de una excluida)
+        page_files.retain(|file| !exclusions.excludes(file));
 
         page_number += 1;
         let page_count = page_files.len();
@@ -205,6 +337,10 @@ pub async fn bootstrap_remaining_bfs(
         // 3. Upsert metadatos + dentries para archivos de esta página
         let mut metadata_buffer = Vec::with_capacity(page_count);
         let mut dentry_buffer = Vec::with_capacity(page_count);
+        let mut web_links = Vec::with_capacity(page_count);
+        let mut thumbnail_links = Vec::new();
+        let mut capabilities = Vec::with_capacity(page_count);
+        let mut shortcut_targets = Vec::new();
 
         for file in &page_files {
             let id = match &file.id {
@@ -217,37 +353,31 @@ pub async fn bootstrap_remaining_bfs(
                 None => continue,
             };
 
-            let shortcut_info = resolve_shortcut_info(file);
-            let effective_mime = shortcut_info.as_ref()
-                .map(|(_, mime)| mime.clone())
-                .or_else(|| file.mime_type.clone());
-
-            let is_dir = effective_mime.as_deref() == Some("application/vnd.google-apps.folder");
-            let size = file.size.unwrap_or(0);
-            let mtime = file.modified_time
-                .as_ref()
-                .map(|t| t.timestamp())
-                .unwrap_or(0);
-            let mode = if is_dir { 0o755 } else { 0o644 };
-            let can_move = file.capabilities.as_ref()
-                .and_then(|c| c.can_move_item_within_drive)
-                .unwrap_or(true);
-            let shared = file.shared.unwrap_or(false);
-            let owned = file.owned_by_me.unwrap_or(true);
-
-            if let Some((target_id, _)) = &shortcut_info {
-                shortcut_targets.push((inode, target_id.clone()));
+            let metadata = bulk_metadata_for(inode, file);
+            let owned = metadata.owned_by_me;
+            capabilities.push((inode, capabilities_for(file)));
+
+            if let Some((target_id, _)) = resolve_shortcut_info(file) {
+                shortcut_targets.push((inode, target_id));
             }
 
-            metadata_buffer.push(crate::db::BulkFileMetadata {
-                inode, size, mtime, mode, is_dir,
-                mime_type: effective_mime,
-                can_move, shared,
-                owned_by_me: owned,
-            });
+            if let Some(link) = &file.web_view_link {
+                web_links.push((inode, link.clone()));
+            }
+            if let Some(link) = &file.thumbnail_link {
+                thumbnail_links.push((inode, link.clone()));
+            }
+
+            metadata_buffer.push(metadata);
 
             // Dentry: vincular hijo con padre
-            if let Some(parents) = &file.parents {
+            if is_computer_folder(file, root_id) {
+                if let Some(name) = &file.name {
+                    dentry_buffer.push(crate::db::BulkDentry {
+                        parent_inode: computers_inode, child_inode: inode, name: name.clone(),
+                    });
+                }
+            } else if let Some(parents) = &file.parents {
                 if let Some(name) = &file.name {
                     for parent_id in parents {
                         let parent_inode = if parent_id == "root" || parent_id == root_id {
@@ -280,15 +410,39 @@ pub async fn bootstrap_remaining_bfs(
         if !dentry_buffer.is_empty() {
             db.upsert_bulk_dentries(&dentry_buffer).await?;
         }
+        db.set_bulk_web_view_links(&web_links).await?;
+        db.set_bulk_thumbnail_links(&thumbnail_links).await?;
+        db.upsert_bulk_capabilities(&capabilities).await?;
+        if !shortcut_targets.is_empty() {
+            db.set_bulk_shortcut_targets(&shortcut_targets).await?;
+            has_shortcuts = true;
+        }
 
         // Reportar progreso a GUI
-        tracing::info!("Escaneo progresivo: página {}, {} archivos escaneados", page_number, total_scanned);
+        if expected_total > 0 {
+            tracing::info!("Escaneo progresivo: página {}, {}/~{} archivos escaneados", page_number, total_scanned, expected_total);
+        } else {
+            tracing::info!("Escaneo progresivo: página {}, {} archivos escaneados", page_number, total_scanned);
+        }
+        history.set_scanning_pages(page_number as usize);
         history.set_scanning_total(total_scanned);
 
         page_token = next_token;
-        if page_token.is_none() {
+        let Some(token) = &page_token else {
             break;
-        }
+        };
+
+        // La página ya está en la DB: guardar desde dónde seguir
+        let checkpoint = ScanCheckpoint {
+            page_token: token.clone(),
+            pages: page_number,
+            scanned: total_scanned,
+            shared_non_owned: std::mem::take(&mut shared_non_owned),
+            excluded_parents: std::mem::take(&mut excluded_parents),
+        };
+        db.set_sync_meta(SCAN_CHECKPOINT_KEY, &serde_json::to_string(&checkpoint)?).await?;
+        shared_non_owned = checkpoint.shared_non_owned;
+        excluded_parents = checkpoint.excluded_parents;
 
         tokio::task::yield_now().await;
     }
@@ -313,10 +467,9 @@ pub async fn bootstrap_remaining_bfs(
         }
     }
 
-    // Post-procesamiento: resolver shortcuts (guardar target_id y copiar size del target)
-    if !shortcut_targets.is_empty() {
-        tracing::info!("Escaneo: resolviendo {} shortcuts...", shortcut_targets.len());
-        db.set_bulk_shortcut_targets(&shortcut_targets).await?;
+    // Post-procesamiento: resolver shortcuts (copiar size del target)
+    if has_shortcuts {
+        tracing::info!("Escaneo: resolviendo shortcuts...");
         let resolved = db.resolve_shortcut_sizes().await?;
         tracing::info!("Escaneo: {} shortcuts resueltos con size del target", resolved);
     }
@@ -327,10 +480,14 @@ pub async fn bootstrap_remaining_bfs(
 
     // Señalar fin de escaneo
     history.set_scanning_total(0);
+    history.set_scanning_expected(0);
+    history.set_scanning_pages(0);
+    db.delete_sync_meta(SCAN_CHECKPOINT_KEY).await?;
+    let _ = db.set_sync_meta(LAST_SCAN_TOTAL_KEY, &total_scanned.to_string()).await;
     tracing::info!("Escaneo progresivo completado: {} archivos en total.", total_scanned);
     history.log(
         crate::gui::history::ActionType::Sync,
-        format!("Escaneo completado: {} archivos", total_scanned),
+        t!("activity-scan-complete", count = total_scanned),
     );
 
     Ok(())
@@ -340,18 +497,19 @@ pub async fn bootstrap_remaining_bfs(
 /// Útil cuando la base de datos tiene datos antiguos o incompletos
 pub async fn repair_ownership_metadata(
     db: &Arc<MetadataRepository>,
-    client: &Arc<DriveClient>,
+    client: &Arc<dyn DriveApi>,
+    exclusions: &crate::sync::exclusions::Exclusions,
 ) -> Result<()> {
     tracing::info!("Iniciando REPARACIÓN de metadatos de propiedad...");
 
     // 1. Obtener lista mínima de Google Drive (solo IDs y propiedad)
-    let files = client.list_all_files().await?;
+    let files = client.list_all_files(&exclusions.query_parents()).await?;
     let total = files.len();
 
     let mut repaired_count = 0;
     let mut buffer = Vec::with_capacity(500);
 
-    for file in files {
+    for file in files.into_iter().filter(|file| !exclusions.excludes(file)) {
         if let Some(id) = file.id {
             // Solo actualizamos si el inodo existe localmente
             if let Some(inode) = db.get_inode_by_gdrive_id(&id).await? {
@@ -375,3 +533,198 @@ pub async fn repair_ownership_metadata(
     tracing::info!("Reparación completada: {}/{} archivos procesados", repaired_count, total);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gdrive::mock::{MockDriveClient, MOCK_ROOT_ID};
+    use crate::sync::exclusions::Exclusions;
+    use rstest::rstest;
+
+    /// Drive con `nota.txt` en el root y `Docs/informe.pdf`
+    fn sample_drive(page_size: usize) -> MockDriveClient {
+        let mock = MockDriveClient::new().with_page_size(page_size);
+        mock.add_file("file-nota", "nota.txt", MOCK_ROOT_ID, b"hola");
+        mock.add_folder("folder-docs", "Docs", MOCK_ROOT_ID);
+        mock.add_file("file-informe", "informe.pdf", "folder-docs", b"%PDF-1.7");
+        mock
+    }
+
+    async fn open_db(dir: &tempfile::TempDir) -> Arc<MetadataRepository> {
+        Arc::new(MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap())
+    }
+
+    #[rstest::rstest]
+    #[case::editable_file(false, true, 0o644)]
+    #[case::read_only_file(false, false, 0o444)]
+    #[case::editable_dir(true, true, 0o755)]
+    #[case::read_only_dir(true, false, 0o555)]
+    fn test_posix_mode(#[case] is_dir: bool, #[case] can_edit: bool, #[case] expected: u32) {
+        assert_eq!(posix_mode(is_dir, can_edit), expected);
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_stores_capabilities_of_read_only_share() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(&dir).await;
+        let mock = sample_drive(1000);
+        mock.share_read_only("file-nota", "ana@example.com");
+        let client: Arc<dyn DriveApi> = Arc::new(mock);
+
+        bootstrap_level1(&db, &client, MOCK_ROOT_ID).await.unwrap();
+
+        let nota = db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        assert_eq!(db.get_attrs(nota).await.unwrap().mode & 0o777, 0o444);
+        let caps = db.get_capabilities(nota).await.unwrap().expect("capabilities guardadas");
+        assert!(!caps.can_edit && !caps.can_trash);
+        assert_eq!(caps.owners, ["ana@example.com"]);
+
+        let docs = db.lookup(1, "Docs").await.unwrap().unwrap();
+        assert_eq!(db.get_attrs(docs).await.unwrap().mode & 0o777, 0o755);
+        assert!(db.can_trash(docs).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_level1_loads_only_root_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(&dir).await;
+        let client: Arc<dyn DriveApi> = Arc::new(sample_drive(1000));
+
+        bootstrap_level1(&db, &client, MOCK_ROOT_ID).await.unwrap();
+
+        let nota = db.lookup(1, "nota.txt").await.unwrap().expect("nota.txt en root");
+        let attrs = db.get_attrs(nota).await.unwrap();
+        assert_eq!(attrs.size, 4);
+        assert!(attrs.created_time.is_some(), "createdTime de Drive");
+        let docs = db.lookup(1, "Docs").await.unwrap().expect("Docs en root");
+        assert_eq!(db.is_dir(docs).await.unwrap(), Some(true));
+        assert_eq!(db.lookup(docs, "informe.pdf").await.unwrap(), None, "nivel 1 no baja a subcarpetas");
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_bfs_scans_all_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(&dir).await;
+        let mock = Arc::new(sample_drive(1));
+        let client: Arc<dyn DriveApi> = mock.clone();
+        let history = crate::gui::history::ActionHistory::new();
+        let (mirror_tx, mut mirror_rx) = tokio::sync::mpsc::channel(8);
+
+        bootstrap_remaining_bfs(&db, &client, MOCK_ROOT_ID, &history, &mirror_tx, &Exclusions::default()).await.unwrap();
+
+        assert_eq!(mock.calls("fetch_files_page"), 3, "una llamada por página");
+        let docs = db.lookup(1, "Docs").await.unwrap().expect("Docs en root");
+        let informe = db.lookup(docs, "informe.pdf").await.unwrap().expect("informe.pdf en Docs");
+        assert_eq!(db.get_gdrive_id(informe).await.unwrap().as_deref(), Some("file-informe"));
+        assert_eq!(db.get_sync_meta(LAST_SCAN_TOTAL_KEY).await.unwrap().as_deref(), Some("3"));
+        assert!(matches!(mirror_rx.try_recv(), Ok(crate::mirror::MirrorCommand::Refresh)));
+    }
+
+    #[rstest]
+    #[case::computer("folder-laptop", None, Some(false), true)]
+    #[case::orphaned_folder("folder-huerfana", None, Some(true), false)]
+    #[case::unknown_capabilities("folder-laptop", None, None, false)]
+    #[case::my_drive_root(MOCK_ROOT_ID, None, Some(false), false)]
+    #[case::in_my_drive("folder-docs", Some(MOCK_ROOT_ID), Some(false), false)]
+    fn test_is_computer_folder(
+        #[case] id: &str,
+        #[case] parent: Option<&str>,
+        #[case] can_move: Option<bool>,
+        #[case] expected: bool,
+    ) {
+        let file = google_drive3::api::File {
+            id: Some(id.to_string()),
+            mime_type: Some("application/vnd.google-apps.folder".to_string()),
+            owned_by_me: Some(true),
+            parents: parent.map(|p| vec![p.to_string()]),
+            capabilities: Some(google_drive3::api::FileCapabilities {
+                can_move_item_within_drive: can_move,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(is_computer_folder(&file, MOCK_ROOT_ID), expected);
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_bfs_groups_computers_outside_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(&dir).await;
+        let mock = sample_drive(1000);
+        mock.add_computer("folder-laptop", "Mi portátil");
+        mock.add_file("file-backup", "foto.jpg", "folder-laptop", b"jpeg");
+        let client: Arc<dyn DriveApi> = Arc::new(mock);
+        let (mirror_tx, _mirror_rx) = tokio::sync::mpsc::channel(8);
+
+        bootstrap_remaining_bfs(&db, &client, MOCK_ROOT_ID, &crate::gui::history::ActionHistory::new(), &mirror_tx, &Exclusions::default()).await.unwrap();
+
+        assert_eq!(db.lookup(1, "Mi portátil").await.unwrap(), None, "nunca en la raíz");
+        let backup = db.get_inode_by_gdrive_id("file-backup").await.unwrap().unwrap();
+        assert_eq!(db.resolve_inode_to_relative_path(backup).await.unwrap(), None, "oculto por defecto");
+
+        db.set_computers_visible(true).await.unwrap();
+        assert_eq!(db.resolve_inode_to_relative_path(backup).await.unwrap().as_deref(), Some("Computers/Mi portátil/foto.jpg"));
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_bfs_resumes_from_last_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(&dir).await;
+        let mock = Arc::new(sample_drive(1));
+        let client: Arc<dyn DriveApi> = mock.clone();
+        let history = crate::gui::history::ActionHistory::new();
+        let (mirror_tx, _mirror_rx) = tokio::sync::mpsc::channel(8);
+
+        mock.fail_after("fetch_files_page", 1, "503 Service Unavailable");
+        assert!(bootstrap_remaining_bfs(&db, &client, MOCK_ROOT_ID, &history, &mirror_tx, &Exclusions::default()).await.is_err());
+        let checkpoint = load_scan_checkpoint(&db).await.expect("checkpoint tras la primera página");
+        assert_eq!((checkpoint.pages, checkpoint.scanned), (1, 1));
+
+        bootstrap_remaining_bfs(&db, &client, MOCK_ROOT_ID, &history, &mirror_tx, &Exclusions::default()).await.unwrap();
+
+        assert_eq!(mock.calls("fetch_files_page"), 4, "la primera página no se vuelve a pedir");
+        let docs = db.lookup(1, "Docs").await.unwrap().expect("Docs en root");
+        assert!(db.lookup(docs, "informe.pdf").await.unwrap().is_some(), "la página previa sigue aplicada");
+        assert_eq!(db.get_sync_meta(LAST_SCAN_TOTAL_KEY).await.unwrap().as_deref(), Some("3"));
+        assert!(load_scan_checkpoint(&db).await.is_none(), "el escaneo completo borra el checkpoint");
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_bfs_restarts_on_rejected_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(&dir).await;
+        let mock = Arc::new(sample_drive(1));
+        let client: Arc<dyn DriveApi> = mock.clone();
+        let (mirror_tx, _mirror_rx) = tokio::sync::mpsc::channel(8);
+        let checkpoint = ScanCheckpoint { page_token: "2".into(), pages: 2, scanned: 2, ..Default::default() };
+        db.set_sync_meta(SCAN_CHECKPOINT_KEY, &serde_json::to_string(&checkpoint).unwrap()).await.unwrap();
+        mock.fail_next_with("fetch_files_page", crate::gdrive::DriveError::ApiError("400 Invalid pageToken".into()));
+
+        bootstrap_remaining_bfs(&db, &client, MOCK_ROOT_ID, &crate::gui::history::ActionHistory::new(), &mirror_tx, &Exclusions::default()).await.unwrap();
+
+        assert_eq!(mock.calls("fetch_files_page"), 4, "token rechazado + las tres páginas desde el principio");
+        assert_eq!(db.get_sync_meta(LAST_SCAN_TOTAL_KEY).await.unwrap().as_deref(), Some("3"));
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_bfs_skips_excluded_folder_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(&dir).await;
+        let mock = sample_drive(1000);
+        mock.add_folder("folder-sub", "Sub", "folder-docs");
+        mock.add_file("file-deep", "deep.txt", "folder-sub", b"x");
+        let client: Arc<dyn DriveApi> = Arc::new(mock);
+        let (mirror_tx, _mirror_rx) = tokio::sync::mpsc::channel(8);
+        let exclusions = Exclusions::from_ids(&["folder-docs"]);
+
+        bootstrap_remaining_bfs(&db, &client, MOCK_ROOT_ID, &crate::gui::history::ActionHistory::new(), &mirror_tx, &exclusions).await.unwrap();
+
+        let docs = db.lookup(1, "Docs").await.unwrap().expect("la carpeta excluida sigue visible");
+        assert_eq!(db.lookup(docs, "informe.pdf").await.unwrap(), None);
+        assert_eq!(db.lookup(docs, "Sub").await.unwrap(), None);
+        // Sub nunca llegó (la omitió el q), así que su contenido queda fuera del árbol
+        let deep = db.get_inode_by_gdrive_id("file-deep").await.unwrap().unwrap();
+        assert_eq!(db.resolve_inode_to_relative_path(deep).await.unwrap(), None);
+        assert!(db.lookup(1, "nota.txt").await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,239 @@
+//! Resolución de conflictos de sincronización
+//!
+//! El uploader, al detectar que el original cambió en Drive mientras había
+//! ediciones locales, sube la versión local como copia "(Conflicto local …)"
+//! y lo registra en la tabla `conflicts`. Desde el centro de conflictos de la
+//! GUI el usuario decide: quedarse con la copia local (su contenido pasa al
+//! original y la copia va a la papelera), con la remota (la copia va a la
+//! papelera) o descartar el aviso dejando ambos archivos.
+
+use anyhow::{Context, Result, bail};
+use std::path::Path;
+use tracing::info;
+
+use crate::db::MetadataRepository;
+use crate::gdrive::{DriveApi, DriveError};
+
+/// Tamaño máximo de cada versión para mostrar diferencias
+const MAX_DIFF_BYTES: u64 = 1024 * 1024;
+/// Bloque de descarga al copiar la versión local sobre el original
+const DOWNLOAD_CHUNK: u32 = 10 * 1024 * 1024;
+
+/// Cómo resolvió el usuario un conflicto
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// El contenido de la copia local reemplaza al original
+    KeepLocal,
+    /// Se mantiene el original de Drive y la copia va a la papelera
+    KeepRemote,
+    /// Se conservan ambos archivos; solo desaparece el aviso
+    Dismiss,
+}
+
+impl ConflictResolution {
+    /// Valor guardado en `conflicts.resolution`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::KeepLocal => "keep_local",
+            Self::KeepRemote => "keep_remote",
+            Self::Dismiss => "dismissed",
+        }
+    }
+}
+
+/// Aplica `resolution` al conflicto `id` y lo marca como resuelto. La copia
+/// local se prepara para subirla en `cache_dir`, no en el `/tmp` compartido
+pub async fn resolve(
+    db: &MetadataRepository,
+    client: &dyn DriveApi,
+    cache_dir: &Path,
+    id: i64,
+    resolution: ConflictResolution,
+) -> Result<()> {
+    let conflict = db.get_conflict(id).await?
+        .with_context(|| format!("Conflicto {} no encontrado", id))?;
+
+    match resolution {
+        ConflictResolution::KeepLocal => {
+            // Una edición local posterior del original la pisaría el contenido de la copia
+            if db.is_dirty(conflict.inode).await? {
+                bail!("{} tiene cambios locales sin subir; espera a que se sincronicen", conflict.path);
+            }
+            let content = download_all(client, &conflict.conflict_gdrive_id, None).await?;
+            let staging = cache_dir.join(format!(".conflict-{}", uuid::Uuid::new_v4()));
+            let result = match write_private(&staging, content).await {
+                Ok(()) => client.update_file_content(&conflict.original_gdrive_id, &staging, None, None).await,
+                Err(e) => Err(e),
+            };
+            let _ = tokio::fs::remove_file(&staging).await;
+            result.context("Error reemplazando el original con la copia local")?;
+            trash_conflict_copy(client, &conflict.conflict_gdrive_id).await?;
+        }
+        ConflictResolution::KeepRemote => {
+            trash_conflict_copy(client, &conflict.conflict_gdrive_id).await?;
+        }
+        ConflictResolution::Dismiss => {}
+    }
+
+    db.resolve_conflict(id, resolution.as_str()).await?;
+    info!("🤝 Conflicto {} resuelto ({}): {}", id, resolution.as_str(), conflict.path);
+    Ok(())
+}
+
+/// Diferencias línea a línea entre el original de Drive y la copia local.
+/// Falla si alguna versión no es texto UTF-8 o es demasiado grande.
+pub async fn diff(db: &MetadataRepository, client: &dyn DriveApi, id: i64) -> Result<String> {
+    let conflict = db.get_conflict(id).await?
+        .with_context(|| format!("Conflicto {} no encontrado", id))?;
+
+    let remote = download_text(client, &conflict.original_gdrive_id).await?;
+    let local = download_text(client, &conflict.conflict_gdrive_id).await?;
+    let lines = crate::utils::text_diff::line_diff(&remote, &local)
+        .context("Demasiadas diferencias para mostrarlas")?;
+    Ok(crate::utils::text_diff::render(&lines))
+}
+
+/// Manda la copia de conflicto a la papelera (ya borrada cuenta como hecho)
+async fn trash_conflict_copy(client: &dyn DriveApi, gdrive_id: &str) -> Result<()> {
+    match client.trash_file(gdrive_id).await {
+        Ok(()) | Err(DriveError::NotFound(_)) => Ok(()),
+        Err(e) => Err(anyhow::Error::new(e).context("Error enviando la copia de conflicto a la papelera")),
+    }
+}
+
+/// Crea `path` solo legible por el usuario (cifrado si corresponde) con `content`
+async fn write_private(path: &Path, mut content: Vec<u8>) -> Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .await
+        .context("Error creando la copia local para subirla")?;
+    crate::utils::cache_crypto::init_file(&file)?;
+    crate::utils::cache_crypto::encrypt_at(&file, 0, &mut content)?;
+    file.write_all(&content).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+/// Descarga una versión para comparar, con tope de tamaño y validación UTF-8
+async fn download_text(client: &dyn DriveApi, gdrive_id: &str) -> Result<String> {
+    let size = client.get_file_metadata(gdrive_id).await?.size.unwrap_or(0) as u64;
+    if size > MAX_DIFF_BYTES {
+        bail!("Archivo demasiado grande para comparar ({} KB)", size / 1024);
+    }
+    let content = download_all(client, gdrive_id, Some(size)).await?;
+    String::from_utf8(content).map_err(|_| anyhow::anyhow!("No es un archivo de texto"))
+}
+
+/// Descarga completa de un archivo de Drive en memoria. `size` evita volver
+/// a pedir los metadatos si el llamador ya los tiene
+async fn download_all(client: &dyn DriveApi, gdrive_id: &str, size: Option<u64>) -> Result<Vec<u8>> {
+    let size = match size {
+        Some(size) => size,
+        None => client.get_file_metadata(gdrive_id).await?.size.unwrap_or(0) as u64,
+    };
+    let mut content = Vec::with_capacity(size as usize);
+    while (content.len() as u64) < size {
+        let offset = content.len() as u64;
+        let chunk = client
+            .download_chunk(gdrive_id, offset, DOWNLOAD_CHUNK.min((size - offset) as u32))
+            .await?;
+        if chunk.is_empty() {
+            break;
+        }
+        content.extend_from_slice(&chunk);
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_fixtures;
+    use crate::gdrive::mock::{MOCK_ROOT_ID, MockDriveClient};
+    use rstest::rstest;
+    use std::sync::Arc;
+
+    struct Fixture {
+        dir: tempfile::TempDir,
+        mock: Arc<MockDriveClient>,
+        db: Arc<MetadataRepository>,
+        conflict_id: i64,
+        inode: u64,
+    }
+
+    /// `nota.txt` editada en Drive y la versión local subida como copia de conflicto
+    async fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let mock = Arc::new(MockDriveClient::new());
+        mock.add_file("file-nota", "nota.txt", MOCK_ROOT_ID, b"uno\ndos remoto\ntres\n");
+        mock.add_file("file-copia", "nota (Conflicto local).txt", MOCK_ROOT_ID, b"uno\ndos local\ntres\n");
+        let db = test_fixtures::synced_repo(&dir, &mock).await;
+        let inode = db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        let conflict_id = db
+            .record_conflict(inode, "file-nota", "file-copia", "nota (Conflicto local).txt", 100, Some(200))
+            .await
+            .unwrap();
+        Fixture { dir, mock, db, conflict_id, inode }
+    }
+
+    #[rstest]
+    #[case::keep_local(ConflictResolution::KeepLocal, b"uno\ndos local\ntres\n", true)]
+    #[case::keep_remote(ConflictResolution::KeepRemote, b"uno\ndos remoto\ntres\n", true)]
+    #[case::dismiss(ConflictResolution::Dismiss, b"uno\ndos remoto\ntres\n", false)]
+    #[tokio::test]
+    async fn test_resolve(
+        #[case] resolution: ConflictResolution,
+        #[case] original_after: &[u8],
+        #[case] copy_trashed: bool,
+    ) {
+        let fx = fixture().await;
+        resolve(&fx.db, fx.mock.as_ref(), fx.dir.path(), fx.conflict_id, resolution).await.unwrap();
+
+        assert_eq!(fx.mock.content("file-nota").as_deref(), Some(original_after));
+        let staged = std::fs::read_dir(fx.dir.path()).unwrap()
+            .filter(|entry| entry.as_ref().unwrap().file_name().to_string_lossy().starts_with(".conflict-"))
+            .count();
+        assert_eq!(staged, 0, "la copia preparada se borra");
+        assert_eq!(fx.mock.file("file-copia").unwrap().trashed == Some(true), copy_trashed);
+        assert!(fx.db.list_conflicts().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_keep_local_refused_while_original_dirty() {
+        let fx = fixture().await;
+        fx.db.mark_dirty(fx.inode).await.unwrap();
+
+        assert!(resolve(&fx.db, fx.mock.as_ref(), fx.dir.path(), fx.conflict_id, ConflictResolution::KeepLocal).await.is_err());
+        assert_eq!(fx.mock.calls("update_file_content"), 0);
+        assert_eq!(fx.db.list_conflicts().await.unwrap().len(), 1, "sigue pendiente");
+    }
+
+    #[tokio::test]
+    async fn test_write_private_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".conflict-prueba");
+
+        write_private(&path, b"secreto".to_vec()).await.unwrap();
+
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert_eq!(crate::utils::cache_crypto::read_file(&path).await.unwrap(), b"secreto");
+        assert!(write_private(&path, Vec::new()).await.is_err(), "no reutiliza un archivo existente");
+    }
+
+    #[tokio::test]
+    async fn test_diff_between_remote_and_local_copy() {
+        let fx = fixture().await;
+        let diff = diff(&fx.db, fx.mock.as_ref(), fx.conflict_id).await.unwrap();
+        assert_eq!(diff, " uno\n-dos remoto\n+dos local\n tres\n");
+
+        fx.mock.modify_remote("file-copia", &[0xff, 0xfe, 0x00]);
+        let err = super::diff(&fx.db, fx.mock.as_ref(), fx.conflict_id).await.unwrap_err();
+        assert!(err.to_string().contains("No es un archivo de texto"));
+    }
+}
@@ -0,0 +1,225 @@
+//! Monitor de conectividad: detecta el modo offline y la reconexión
+//!
+//! El estado vive en `utils::network`. Pasa a offline cuando cualquier componente
+//! reporta un error de red o NetworkManager anuncia que no hay conexión, y vuelve
+//! a online solo cuando una sonda contra la API responde (NetworkManager puede
+//! decir "conectado" detrás de un portal cautivo). Al reconectar, syncer y
+//! uploader, que esperan en `wait_for_online()`, reanudan de inmediato.
+//!
+//! Con `with_pause_on_metered(true)` también sigue la propiedad `Metered` de
+//! NetworkManager: en una conexión medida pausa subidas y precarga
+//! (`network::set_metered_pause`) y las reanuda al pasar a una conexión libre.
+
+use futures_util::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info};
+
+use crate::gdrive::DriveApi;
+use crate::i18n::t;
+use crate::gui::history::{ActionHistory, ActionType};
+use crate::utils::network;
+
+/// Intervalo entre sondas mientras se está offline
+const PROBE_INTERVAL_SECS: u64 = 15;
+
+/// `NM_STATE_CONNECTED_GLOBAL`: conectividad completa según NetworkManager
+const NM_STATE_CONNECTED_GLOBAL: u32 = 70;
+
+/// `NM_STATE_DISCONNECTED`: estados iguales o menores no tienen red
+const NM_STATE_DISCONNECTED: u32 = 20;
+
+/// `NM_METERED_YES`: la conexión está marcada como medida
+const NM_METERED_YES: u32 = 1;
+
+/// `NM_METERED_GUESS_YES`: NetworkManager supone que es medida (ej: tethering)
+const NM_METERED_GUESS_YES: u32 = 3;
+
+type NmStateStream = zbus::proxy::SignalStream<'static>;
+type NmMeteredStream = zbus::proxy::PropertyStream<'static, u32>;
+
+/// Monitor de conectividad con Google Drive
+pub struct ConnectivityMonitor {
+    client: Arc<dyn DriveApi>,
+    history: ActionHistory,
+    probe_interval: Duration,
+    pause_on_metered: bool,
+}
+
+impl ConnectivityMonitor {
+    /// Crea un nuevo monitor
+    pub fn new(client: Arc<dyn DriveApi>, history: ActionHistory) -> Self {
+        Self {
+            client,
+            history,
+            probe_interval: Duration::from_secs(PROBE_INTERVAL_SECS),
+            pause_on_metered: false,
+        }
+    }
+
+    /// Pausa subidas y precarga mientras la conexión sea medida
+    pub fn with_pause_on_metered(mut self, enabled: bool) -> Self {
+        self.pause_on_metered = enabled;
+        self
+    }
+
+    /// Inicia el monitor en un task de Tokio separado
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("📶 Monitor de conectividad iniciado");
+
+            // Sin NetworkManager (u otro bus) el monitor funciona solo con sondas
+            let nm = match nm_proxy().await {
+                Ok(proxy) => Some(proxy),
+                Err(e) => {
+                    debug!("NetworkManager no disponible, solo sondas de API: {:?}", e);
+                    None
+                }
+            };
+            let mut nm_states = match &nm {
+                Some(proxy) => proxy.receive_signal("StateChanged").await.ok(),
+                None => None,
+            };
+            let mut nm_metered = match &nm {
+                Some(proxy) if self.pause_on_metered => {
+                    if let Ok(metered) = proxy.get_property::<u32>("Metered").await {
+                        self.apply_metered(metered);
+                    }
+                    Some(proxy.receive_property_changed::<u32>("Metered").await)
+                }
+                _ => None,
+            };
+
+            loop {
+                if network::is_online() {
+                    tokio::select! {
+                        _ = network::wait_for_offline() => {
+                            self.history.log(ActionType::Error, t!("activity-offline-queued"));
+                        }
+                        state = next_nm_state(&mut nm_states) => {
+                            if state <= NM_STATE_DISCONNECTED {
+                                network::set_offline("NetworkManager sin conexión");
+                                self.history.log(ActionType::Error, t!("activity-offline-queued"));
+                            }
+                        }
+                        metered = next_metered(&mut nm_metered) => self.apply_metered(metered),
+                        _ = crate::utils::shutdown::wait_for_shutdown() => break,
+                    }
+                    continue;
+                }
+
+                tokio::select! {
+                    _ = tokio::time::sleep(self.probe_interval) => {}
+                    state = next_nm_state(&mut nm_states) => {
+                        if state < NM_STATE_CONNECTED_GLOBAL {
+                            continue;
+                        }
+                        debug!("📶 NetworkManager reporta conexión, sondeando API");
+                    }
+                    metered = next_metered(&mut nm_metered) => {
+                        self.apply_metered(metered);
+                        continue;
+                    }
+                    _ = crate::utils::shutdown::wait_for_shutdown() => break,
+                }
+
+                if self.probe().await && network::set_online() {
+                    self.history.log(ActionType::Sync, t!("activity-connection-restored"));
+                }
+            }
+
+            info!("🛑 Monitor de conectividad: Shutdown detectado, deteniendo.");
+        })
+    }
+
+    /// Aplica el valor de `Metered` (`NMMetered`) de NetworkManager
+    fn apply_metered(&self, metered: u32) {
+        if !network::set_metered_pause(is_metered(metered)) {
+            return;
+        }
+        if network::is_metered_paused() {
+            self.history.log(ActionType::Sync, t!("activity-metered-paused"));
+        } else {
+            self.history.log(ActionType::Sync, t!("activity-metered-resumed"));
+        }
+    }
+
+    /// Petición ligera a la API: solo un error de red cuenta como offline
+    async fn probe(&self) -> bool {
+        match self.client.get_start_page_token().await {
+            Ok(_) => true,
+            Err(e) if network::is_network_error(&e) => {
+                debug!("📴 Sonda de conectividad sin respuesta: {:#}", e);
+                false
+            }
+            // La API respondió (aunque sea con error): hay red
+            Err(_) => true,
+        }
+    }
+}
+
+/// Proxy de NetworkManager en el bus de sistema
+async fn nm_proxy() -> zbus::Result<zbus::Proxy<'static>> {
+    let connection = zbus::Connection::system().await?;
+    zbus::Proxy::new(
+        &connection,
+        "org.freedesktop.NetworkManager",
+        "/org/freedesktop/NetworkManager",
+        "org.freedesktop.NetworkManager",
+    )
+    .await
+}
+
+/// `NM_METERED_YES` y `NM_METERED_GUESS_YES` cuentan como conexión medida;
+/// `UNKNOWN` no, para no pausar sin motivo
+fn is_metered(value: u32) -> bool {
+    matches!(value, NM_METERED_YES | NM_METERED_GUESS_YES)
+}
+
+/// Próximo estado de NetworkManager; nunca retorna si no hay suscripción
+async fn next_nm_state(stream: &mut Option<NmStateStream>) -> u32 {
+    loop {
+        let Some(signals) = stream else {
+            return std::future::pending().await;
+        };
+        match signals.next().await {
+            Some(message) => match message.body().deserialize::<u32>() {
+                Ok(state) => return state,
+                Err(e) => debug!("Señal StateChanged ilegible: {:?}", e),
+            },
+            None => *stream = None, // El bus se cerró: seguir solo con sondas
+        }
+    }
+}
+
+/// Próximo valor de `Metered`; nunca retorna si no hay suscripción
+async fn next_metered(stream: &mut Option<NmMeteredStream>) -> u32 {
+    loop {
+        let Some(changes) = stream else {
+            return std::future::pending().await;
+        };
+        match changes.next().await {
+            Some(change) => match change.get().await {
+                Ok(metered) => return metered,
+                Err(e) => debug!("Propiedad Metered ilegible: {:?}", e),
+            },
+            None => *stream = None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::unknown(0, false)]
+    #[case::yes(1, true)]
+    #[case::no(2, false)]
+    #[case::guess_yes(3, true)]
+    #[case::guess_no(4, false)]
+    fn test_is_metered(#[case] value: u32, #[case] expected: bool) {
+        assert_eq!(is_metered(value), expected);
+    }
+}
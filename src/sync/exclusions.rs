@@ -0,0 +1,145 @@
+//! Carpetas excluidas (`Config.excluded_folders`)
+//!
+//! El contenido de una carpeta excluida no se lista ni se sincroniza: el
+//! escaneo progresivo y "Reconstruir metadatos" piden a Drive que omita sus
+//! hijos (`not 'id' in parents` en `files.list`) y escaneo y syncer descartan
+//! los que llegan igualmente (subcarpetas aún desconocidas, cambios remotos).
+//! La carpeta misma sigue visible, vacía, y no admite contenido nuevo: FUSE
+//! y el espejo rechazan crear dentro. Lo que ya estaba indexado antes de
+//! excluirla se retira con "Reconstruir metadatos".
+
+use anyhow::Result;
+use google_drive3::api::File;
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use crate::db::MetadataRepository;
+
+const FOLDER_MIME: &str = "application/vnd.google-apps.folder";
+
+/// IDs de Drive de las carpetas excluidas y de sus subcarpetas conocidas
+#[derive(Debug, Default)]
+pub struct Exclusions {
+    folders: RwLock<HashSet<String>>,
+}
+
+impl Exclusions {
+    /// Resuelve las rutas de la configuración contra la DB y las añade. Una
+    /// ruta que aún no está indexada (primer arranque, carpeta profunda) se
+    /// avisa y se ignora hasta el siguiente arranque. FUSE y el espejo se
+    /// crean antes (comparten el `Arc`), así que se cargan en el sitio.
+    pub async fn load(&self, db: &MetadataRepository, paths: &[String]) -> Result<()> {
+        let mut folders = HashSet::new();
+        for path in paths {
+            let parts: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+            if parts.is_empty() {
+                continue;
+            }
+            match db.resolve_path_components(&parts).await? {
+                Some((inode, gdrive_id)) => {
+                    folders.insert(gdrive_id);
+                    folders.extend(db.folder_ids_in_subtree(inode).await?);
+                }
+                None => tracing::warn!("⚠️ Carpeta excluida {:?} no encontrada (aún sin indexar)", path),
+            }
+        }
+        if !folders.is_empty() {
+            tracing::info!("🚫 {} carpetas excluidas de la sincronización", folders.len());
+        }
+        self.folders.write().unwrap_or_else(|poisoned| poisoned.into_inner()).extend(folders);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn from_ids(ids: &[&str]) -> Self {
+        Self { folders: RwLock::new(ids.iter().map(|id| id.to_string()).collect()) }
+    }
+
+    /// Carpetas cuyos hijos se piden omitir en `files.list` (orden estable)
+    pub fn query_parents(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.read().iter().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// `true` si los hijos de la carpeta de Drive `gdrive_id` están excluidos:
+    /// lo que se cree dentro nunca llegaría a sincronizarse
+    pub fn covers(&self, gdrive_id: &str) -> bool {
+        self.read().contains(gdrive_id)
+    }
+
+    /// Sin carpetas excluidas (lo habitual): nada que comprobar
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// `true` si `file` está dentro de una carpeta excluida. Las subcarpetas
+    /// descubiertas así quedan excluidas también.
+    pub fn excludes(&self, file: &File) -> bool {
+        let Some(parents) = file.parents.as_ref() else {
+            return false;
+        };
+        let excluded = {
+            let folders = self.read();
+            !folders.is_empty() && parents.iter().any(|parent| folders.contains(parent))
+        };
+        if excluded
+            && file.mime_type.as_deref() == Some(FOLDER_MIME)
+            && let (Some(id), Ok(mut folders)) = (&file.id, self.folders.write())
+        {
+            folders.insert(id.clone());
+        }
+        excluded
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, HashSet<String>> {
+        self.folders.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gdrive::mock::{MockDriveClient, MOCK_ROOT_ID};
+    use crate::gdrive::DriveApi;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_resolve_includes_known_subfolders() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(MetadataRepository::new(&dir.path().join("metadata.db")).await.unwrap());
+        let mock = MockDriveClient::new();
+        mock.add_folder("folder-archivo", "Archivo", MOCK_ROOT_ID);
+        mock.add_folder("folder-2019", "2019", "folder-archivo");
+        mock.add_folder("folder-docs", "Docs", MOCK_ROOT_ID);
+        let client: Arc<dyn DriveApi> = Arc::new(mock);
+        let (mirror_tx, _mirror_rx) = tokio::sync::mpsc::channel(8);
+        crate::sync::bootstrap::bootstrap_remaining_bfs(
+            &db, &client, MOCK_ROOT_ID, &crate::gui::history::ActionHistory::new(), &mirror_tx, &Exclusions::default(),
+        ).await.unwrap();
+
+        let paths = vec!["/Archivo/".to_string(), "No/Existe".to_string()];
+        let exclusions = Exclusions::default();
+        exclusions.load(&db, &paths).await.unwrap();
+
+        assert_eq!(exclusions.query_parents(), ["folder-2019", "folder-archivo"]);
+        assert!(exclusions.covers("folder-archivo"));
+        assert!(!exclusions.covers("folder-docs"));
+    }
+
+    #[test]
+    fn test_excludes_learns_nested_folders() {
+        let exclusions = Exclusions::from_ids(&["folder-x"]);
+        let file = |id: &str, parent: &str, mime: &str| File {
+            id: Some(id.into()),
+            parents: Some(vec![parent.into()]),
+            mime_type: Some(mime.into()),
+            ..Default::default()
+        };
+
+        assert!(!exclusions.excludes(&file("folder-x", MOCK_ROOT_ID, FOLDER_MIME)), "la carpeta misma sigue visible");
+        assert!(exclusions.excludes(&file("folder-sub", "folder-x", FOLDER_MIME)));
+        assert!(exclusions.excludes(&file("file-deep", "folder-sub", "text/plain")), "la subcarpeta quedó excluida");
+        assert!(!exclusions.excludes(&file("file-ok", MOCK_ROOT_ID, "text/plain")));
+    }
+}
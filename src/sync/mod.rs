@@ -1,3 +1,13 @@
+pub mod backpressure;
 pub mod bootstrap;
+pub mod conflicts;
+pub mod connectivity;
+pub mod exclusions;
+pub mod prefetcher;
+pub mod schedule;
 pub mod syncer;
+pub mod thumbnails;
+pub mod trigger;
+pub mod upload_progress;
 pub mod uploader;
+pub mod verifier;
@@ -0,0 +1,275 @@
+//! Precarga en background de la caché para los directorios más usados
+//!
+//! FUSE registra en `dir_access_log` cada directorio que el usuario abre (o en el
+//! que abre un archivo). Al arrancar y luego en cada ronda en la que no hay
+//! actividad, el prefetcher descarga los archivos de esos directorios hasta
+//! llenar un presupuesto de espacio, para que los documentos habituales abran
+//! al instante y sigan disponibles sin conexión.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::fuse::content_store::{self, ContentStore};
+use crate::fuse::file_locks::FileLocks;
+use crate::fuse::inflight::{Claim, InflightDownloads};
+use crate::db::{MetadataRepository, PrefetchCandidate};
+use crate::gdrive::DriveApi;
+use crate::i18n::t;
+use crate::gui::history::{ActionHistory, ActionType};
+
+/// Espera tras el arranque antes de la primera ronda (deja pasar el sync inicial)
+pub(crate) const STARTUP_DELAY_SECS: u64 = 60;
+
+/// Solo cuentan los directorios abiertos en los últimos 30 días
+pub(crate) const ACCESS_WINDOW_SECS: i64 = 30 * 24 * 3600;
+
+/// Directorios considerados por ronda
+pub(crate) const MAX_HOT_DIRS: u32 = 20;
+
+/// Tamaño de cada descarga parcial
+const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Precargador de caché por uso de directorios
+pub struct Prefetcher {
+    db: Arc<MetadataRepository>,
+    client: Arc<dyn DriveApi>,
+    cache_dir: PathBuf,
+    interval: Duration,
+    budget_bytes: u64,
+    history: ActionHistory,
+    sync_paused: Arc<AtomicBool>,
+    /// Los mismos locks de caché que FUSE (`GDriveFS::with_file_locks`)
+    file_locks: FileLocks,
+    /// Registro de descargas compartido con FUSE y el uploader
+    inflight: Arc<InflightDownloads>,
+}
+
+impl Prefetcher {
+    /// Crea un nuevo prefetcher con un presupuesto de `budget_mb` MB
+    pub fn new(
+        db: Arc<MetadataRepository>,
+        client: Arc<dyn DriveApi>,
+        cache_dir: impl AsRef<Path>,
+        interval_secs: u64,
+        budget_mb: u64,
+        history: ActionHistory,
+        sync_paused: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            db,
+            client,
+            cache_dir: cache_dir.as_ref().to_path_buf(),
+            interval: Duration::from_secs(interval_secs),
+            budget_bytes: budget_mb * 1024 * 1024,
+            history,
+            sync_paused,
+            file_locks: FileLocks::default(),
+            inflight: Arc::new(InflightDownloads::default()),
+        }
+    }
+
+    /// Registra sus descargas donde FUSE y el uploader las ven
+    pub fn with_inflight(mut self, inflight: Arc<InflightDownloads>) -> Self {
+        self.inflight = inflight;
+        self
+    }
+
+    /// Escribe en la caché bajo los mismos locks por inode que FUSE
+    pub fn with_file_locks(mut self, file_locks: FileLocks) -> Self {
+        self.file_locks = file_locks;
+        self
+    }
+
+    /// Inicia el loop de precarga en un task de Tokio separado
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("🔥 Prefetcher iniciado (intervalo: {:?}, presupuesto: {} MB)", self.interval, self.budget_bytes / 1024 / 1024);
+
+            let mut delay = Duration::from_secs(STARTUP_DELAY_SECS);
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = crate::utils::shutdown::wait_for_shutdown() => {
+                        info!("🛑 Prefetcher: Shutdown detectado, deteniendo.");
+                        break;
+                    }
+                }
+                delay = self.interval;
+
+                if !self.is_idle().await {
+                    debug!("🔥 Prefetch aplazado: hay actividad en curso");
+                    continue;
+                }
+
+                match self.prefetch_once().await {
+                    Ok((files, bytes)) if files > 0 => {
+                        info!("🔥 Prefetch: {} archivos precargados ({} KB)", files, bytes / 1024);
+                        self.history.log(ActionType::Download, t!("activity-prefetched", count = files));
+                    }
+                    Ok(_) => debug!("🔥 Prefetch: nada que precargar"),
+                    Err(e) => warn!("⚠️ Error en prefetch: {:?}", e),
+                }
+            }
+        })
+    }
+
+    /// Con conexión no medida, sin sync pausado, transferencias activas ni subidas pendientes
+    async fn is_idle(&self) -> bool {
+        if !crate::utils::network::transfers_allowed()
+            || self.sync_paused.load(Ordering::Relaxed)
+            || !self.history.active_transfers().is_empty()
+        {
+            return false;
+        }
+        matches!(self.db.count_dirty().await, Ok((0, 0)))
+    }
+
+    /// Precarga los archivos de los directorios más usados que quepan en el presupuesto.
+    /// Retorna (archivos descargados, bytes descargados)
+    pub async fn prefetch_once(&self) -> Result<(usize, u64)> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        let candidates = self.db.list_prefetch_candidates(now - ACCESS_WINDOW_SECS, MAX_HOT_DIRS).await?;
+
+        let mut files = 0;
+        let mut downloaded = 0;
+        for candidate in select_within_budget(&candidates, self.budget_bytes) {
+            // La precarga cede ante el usuario: se retoma en la próxima ronda
+            if crate::utils::shutdown::is_shutdown_requested()
+                || self.sync_paused.load(Ordering::Relaxed)
+                || crate::utils::network::is_metered_paused()
+            {
+                break;
+            }
+
+            match self.fetch_file(candidate).await {
+                Ok(bytes) => {
+                    files += 1;
+                    downloaded += bytes;
+                }
+                Err(e) if crate::utils::network::report_error(&e) => break,
+                Err(e) => warn!("⚠️ No se pudo precargar inode {}: {:?}", candidate.inode, e),
+            }
+        }
+
+        Ok((files, downloaded))
+    }
+
+    /// Descarga los rangos que falten de un archivo (igual que una lectura FUSE)
+    async fn fetch_file(&self, candidate: &PrefetchCandidate) -> Result<u64> {
+        use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+        let missing = self.db.get_missing_ranges(candidate.inode, 0, candidate.size - 1).await?;
+        if missing.is_empty() {
+            return Ok(0);
+        }
+
+        // Otro archivo idéntico ya en caché: se enlaza sin descargar
+        let cache_path = self.cache_dir.join(&candidate.gdrive_id);
+        let store = ContentStore::new(&self.cache_dir);
+        if store.adopt(&self.db, candidate.inode, candidate.size, &cache_path).await? {
+            return Ok(0);
+        }
+        // El uploader no sube un archivo a medio precargar
+        let _hydration = self.inflight.hydrate(candidate.inode);
+        content_store::release_shared(&self.db, candidate.inode, &cache_path).await?;
+
+        // Sin truncar: FUSE puede estar escribiendo otros rangos del mismo archivo
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&cache_path)
+            .await?;
+        // Solo una caché vacía recibe clave: los rangos que ya escribió FUSE
+        // están en claro y con clave se descifrarían como basura
+        {
+            let _guard = self.file_locks.lock(candidate.inode).await;
+            if file.metadata().await?.len() == 0 {
+                crate::utils::cache_crypto::init_file(&file)?;
+            }
+        }
+
+        let mut downloaded = 0;
+        for (range_start, range_end) in missing {
+            let mut start = range_start;
+            while start <= range_end {
+                let end = (start + CHUNK_SIZE - 1).min(range_end);
+                // Lo que ya está bajando una lectura FUSE no se repite
+                let _claim = match self.inflight.claim(candidate.inode, start, end) {
+                    Claim::Owned(guard) => guard,
+                    Claim::Busy(waiters) => {
+                        InflightDownloads::wait(waiters).await;
+                        start = end + 1;
+                        continue;
+                    }
+                };
+                let mut data = self.client.download_chunk(&candidate.gdrive_id, start, (end - start + 1) as u32).await?;
+
+                let _guard = self.file_locks.lock(candidate.inode).await;
+                crate::utils::cache_crypto::encrypt_at(&file, start, &mut data)?;
+                file.seek(std::io::SeekFrom::Start(start)).await?;
+                file.write_all(&data).await?;
+                file.flush().await?;
+                self.db.add_cached_chunk(candidate.inode, start, end).await?;
+
+                downloaded += end - start + 1;
+                start = end + 1;
+            }
+        }
+
+        debug!("🔥 Precargado inode {} ({} bytes)", candidate.inode, downloaded);
+        if let Err(e) = store.publish(&self.db, &self.file_locks, candidate.inode, &cache_path).await {
+            debug!("No se pudo publicar la caché de inode {}: {:?}", candidate.inode, e);
+        }
+        Ok(downloaded)
+    }
+}
+
+/// Candidatos a descargar en orden de prioridad, sin superar `budget` bytes.
+/// Los archivos ya cacheados cuentan para el presupuesto; uno que no cabe se
+/// salta para dejar sitio a otros más pequeños.
+fn select_within_budget(candidates: &[PrefetchCandidate], budget: u64) -> Vec<&PrefetchCandidate> {
+    let mut used = 0u64;
+    let mut selected = Vec::new();
+    for candidate in candidates {
+        if used + candidate.size > budget {
+            continue;
+        }
+        used += candidate.size;
+        if candidate.cached_bytes < candidate.size {
+            selected.push(candidate);
+        }
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    fn candidate(inode: u64, size: u64, cached_bytes: u64) -> PrefetchCandidate {
+        PrefetchCandidate { inode, gdrive_id: format!("file_{}", inode), size, cached_bytes }
+    }
+
+    #[rstest]
+    #[case::all_fit(vec![candidate(2, 10, 0), candidate(3, 20, 0)], 100, vec![2, 3])]
+    #[case::cached_counts_toward_budget(vec![candidate(2, 60, 60), candidate(3, 60, 0)], 100, vec![])]
+    #[case::partial_cache_is_completed(vec![candidate(2, 50, 10)], 100, vec![2])]
+    #[case::skips_too_large(vec![candidate(2, 200, 0), candidate(3, 20, 0)], 100, vec![3])]
+    #[case::disabled(vec![candidate(2, 10, 0)], 0, vec![])]
+    fn test_select_within_budget(
+        #[case] candidates: Vec<PrefetchCandidate>,
+        #[case] budget: u64,
+        #[case] expected: Vec<u64>,
+    ) {
+        let selected: Vec<u64> = select_within_budget(&candidates, budget).iter().map(|c| c.inode).collect();
+        assert_eq!(selected, expected);
+    }
+}
@@ -0,0 +1,157 @@
+//! Franjas de sincronización automática
+//!
+//! `Config.sync_schedule` decide cuándo corren solos los ciclos de syncer y
+//! uploader: siempre, solo dentro de una franja de hora local (`08:00`–`20:00`;
+//! si el fin es anterior al inicio, la franja cruza la medianoche) o nunca
+//! (solo manual). "Sincronizar ahora" (`sync::trigger`) ejecuta un ciclo
+//! también fuera de la franja. Fuera de ella los cambios locales quedan en
+//! cola (dirty) y las lecturas FUSE siguen descargando bajo demanda.
+
+use anyhow::{Context, Result};
+use google_drive3::chrono::{Local, Timelike};
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::config::SyncSchedule;
+
+const SECS_PER_DAY: u32 = 24 * 3600;
+
+/// `Config.sync_schedule` ya validado
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Schedule {
+    #[default]
+    Always,
+    /// Segundos desde la medianoche local: abierta en `[start, end)`
+    Window { start: u32, end: u32 },
+    Manual,
+}
+
+impl Schedule {
+    pub fn from_config(schedule: &SyncSchedule) -> Result<Self> {
+        Ok(match schedule {
+            SyncSchedule::Always => Self::Always,
+            SyncSchedule::Window { start, end } => {
+                let (start, end) = (parse_time(start)?, parse_time(end)?);
+                anyhow::ensure!(start != end, "la franja empieza y termina a la misma hora");
+                Self::Window { start, end }
+            }
+            SyncSchedule::Manual => Self::Manual,
+        })
+    }
+
+    /// Tiempo hasta que se permitan ciclos automáticos, a `secs` segundos
+    /// desde la medianoche (cero = permitidos ya, None = nunca)
+    pub fn until_open(&self, secs: u32) -> Option<Duration> {
+        match *self {
+            Self::Always => Some(Duration::ZERO),
+            Self::Manual => None,
+            Self::Window { start, end } => {
+                let open = if start < end {
+                    (start..end).contains(&secs)
+                } else {
+                    secs >= start || secs < end
+                };
+                let wait = if open { 0 } else { (start + SECS_PER_DAY - secs) % SECS_PER_DAY };
+                Some(Duration::from_secs(wait as u64))
+            }
+        }
+    }
+
+    fn until_open_now(&self) -> Option<Duration> {
+        self.until_open(Local::now().num_seconds_from_midnight())
+    }
+
+    /// Espera al próximo ciclo: `wait` y, si entonces la franja está cerrada,
+    /// hasta que se abra. Retorna `true` si lo adelantó "Sincronizar ahora"
+    /// (el llamador reinicia su backoff).
+    pub async fn wait_next_cycle(&self, wait: Duration, sync_now: &mut watch::Receiver<u64>) -> bool {
+        let mut wait = Some(wait);
+        loop {
+            let sleep = async {
+                match wait {
+                    Some(duration) => tokio::time::sleep(duration).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = sleep => {}
+                _ = sync_now.changed() => return true,
+                _ = crate::utils::shutdown::wait_for_shutdown() => return false,
+            }
+
+            match self.until_open_now() {
+                Some(until) if until.is_zero() => return false,
+                until => {
+                    tracing::debug!("🕗 Fuera de la franja de sincronización (próxima apertura: {:?})", until);
+                    wait = until;
+                }
+            }
+        }
+    }
+}
+
+/// Hora local `HH:MM` en segundos desde la medianoche
+fn parse_time(value: &str) -> Result<u32> {
+    let (hours, minutes) = value.split_once(':')
+        .with_context(|| format!("hora {:?} sin formato HH:MM", value))?;
+    let hours: u32 = hours.parse().with_context(|| format!("hora {:?} sin formato HH:MM", value))?;
+    let minutes: u32 = minutes.parse().with_context(|| format!("hora {:?} sin formato HH:MM", value))?;
+    anyhow::ensure!(hours < 24 && minutes < 60, "hora {:?} fuera de rango", value);
+    Ok((hours * 60 + minutes) * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    fn window(start: &str, end: &str) -> Schedule {
+        Schedule::from_config(&SyncSchedule::Window { start: start.into(), end: end.into() }).unwrap()
+    }
+
+    const fn at(hours: u32, minutes: u32) -> u32 {
+        (hours * 60 + minutes) * 60
+    }
+
+    #[rstest]
+    #[case::inside(window("08:00", "20:00"), at(12, 0), Some(0))]
+    #[case::at_start(window("08:00", "20:00"), at(8, 0), Some(0))]
+    #[case::at_end_is_closed(window("08:00", "20:00"), at(20, 0), Some(12 * 3600))]
+    #[case::before(window("08:00", "20:00"), at(7, 30), Some(1800))]
+    #[case::overnight_inside(window("22:00", "06:00"), at(1, 0), Some(0))]
+    #[case::overnight_closed(window("22:00", "06:00"), at(12, 0), Some(10 * 3600))]
+    #[case::always(Schedule::Always, at(3, 0), Some(0))]
+    #[case::manual(Schedule::Manual, at(12, 0), None)]
+    fn test_until_open(#[case] schedule: Schedule, #[case] now: u32, #[case] expected: Option<u64>) {
+        assert_eq!(schedule.until_open(now), expected.map(Duration::from_secs));
+    }
+
+    #[rstest]
+    #[case::no_colon("0800")]
+    #[case::hour_out_of_range("24:00")]
+    #[case::minutes_out_of_range("08:60")]
+    #[case::not_a_number("ocho:00")]
+    fn test_invalid_window_time(#[case] time: &str) {
+        let schedule = SyncSchedule::Window { start: time.into(), end: "20:00".into() };
+        assert!(Schedule::from_config(&schedule).is_err());
+    }
+
+    #[test]
+    fn test_empty_window_is_invalid() {
+        let schedule = SyncSchedule::Window { start: "08:00".into(), end: "08:00".into() };
+        assert!(Schedule::from_config(&schedule).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_manual_waits_for_sync_now() {
+        // Un disparador propio: el global lo tocan otros tests en paralelo
+        let trigger = crate::sync::trigger::SyncTrigger::default();
+        let mut sync_now = trigger.subscribe();
+        let waiting = Schedule::Manual.wait_next_cycle(Duration::ZERO, &mut sync_now);
+        tokio::pin!(waiting);
+
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut waiting).await.is_err(), "sin ciclos automáticos");
+        trigger.request();
+        assert!(tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap());
+    }
+}
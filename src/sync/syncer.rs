@@ -10,7 +10,13 @@ use tokio::sync::RwLock;
 use futures::stream::{self, StreamExt};
 
 use crate::db::MetadataRepository;
-use crate::gdrive::client::DriveClient;
+use crate::fuse::file_locks::FileLocks;
+use crate::fuse::kernel_notify::{KernelEvent, KernelNotifier};
+use crate::fuse::meta_cache::MetadataCache;
+use crate::gdrive::{DriveApi, DriveError};
+use crate::gdrive::app_properties::{self, AppMarks};
+use crate::sync::exclusions::Exclusions;
+use crate::sync::schedule::Schedule;
 
 /// Clave en sync_meta para el page token de changes
 const SYNC_META_PAGE_TOKEN: &str = "changes_page_token";
@@ -23,25 +29,44 @@ const MAX_BACKOFF_SECS: u64 = 300;
 /// Período de gracia para tombstones en días
 const TOMBSTONE_GRACE_DAYS: i64 = 7;
 
+use crate::i18n::t;
 use crate::gui::history::{ActionHistory, ActionType, TransferOp};
 use std::sync::atomic::{AtomicBool, Ordering};
 
+/// Resultado de `BackgroundSyncer::rebuild_metadata`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RebuildReport {
+    /// Archivos y carpetas listados en Drive
+    pub checked: usize,
+    /// Los que faltaban en la DB local
+    pub added: usize,
+    /// Los que la DB tenía pero ya no están en Drive (van a tombstone)
+    pub removed: usize,
+}
+
 /// Sincronizador en background que detecta cambios de Google Drive
 pub struct BackgroundSyncer {
     db: Arc<MetadataRepository>,
-    client: Arc<DriveClient>,
+    client: Arc<dyn DriveApi>,
     interval: Duration,
     history: ActionHistory,
     sync_paused: Arc<AtomicBool>,
     root_id_cache: Arc<RwLock<Option<String>>>,
     mirror_tx: tokio::sync::mpsc::Sender<crate::mirror::manager::MirrorCommand>,
+    metadata_cache: Option<Arc<MetadataCache>>,
+    kernel_notifier: Option<Arc<KernelNotifier>>,
+    cache_dir: Option<std::path::PathBuf>,
+    schedule: Schedule,
+    exclusions: Arc<Exclusions>,
+    file_locks: FileLocks,
+    pin_hints: bool,
 }
 
 impl BackgroundSyncer {
     /// Crea un nuevo sincronizador
     pub fn new(
         db: Arc<MetadataRepository>,
-        client: Arc<DriveClient>,
+        client: Arc<dyn DriveApi>,
         interval_secs: u64,
         history: ActionHistory,
         sync_paused: Arc<AtomicBool>,
@@ -55,15 +80,69 @@ impl BackgroundSyncer {
             sync_paused,
             root_id_cache: Arc::new(RwLock::new(None)),
             mirror_tx,
+            metadata_cache: None,
+            kernel_notifier: None,
+            cache_dir: None,
+            schedule: Schedule::Always,
+            exclusions: Arc::new(Exclusions::default()),
+            file_locks: FileLocks::default(),
+            pin_hints: false,
         }
     }
 
+    /// Carpetas excluidas: sus cambios se descartan (`Config.excluded_folders`)
+    pub fn with_exclusions(mut self, exclusions: Arc<Exclusions>) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    /// Mantener aquí lo que otro dispositivo mantiene (`Config.sync_pins_across_devices`)
+    pub fn with_pin_hints(mut self, enabled: bool) -> Self {
+        self.pin_hints = enabled;
+        self
+    }
+
+    /// Franja en la que corren los ciclos automáticos (`Config.sync_schedule`)
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Locks de caché de FUSE: descartar una caché obsoleta espera a sus escritores
+    pub fn with_file_locks(mut self, file_locks: FileLocks) -> Self {
+        self.file_locks = file_locks;
+        self
+    }
+
+    /// Directorio de caché de contenido; al purgar tombstones y al cambiar el
+    /// contenido remoto se eliminan sus archivos
+    pub fn with_cache_dir(mut self, cache_dir: impl AsRef<std::path::Path>) -> Self {
+        self.cache_dir = Some(cache_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Invalida la caché de metadatos de FUSE al aplicar cambios remotos
+    pub fn with_metadata_cache(mut self, cache: Arc<MetadataCache>) -> Self {
+        self.metadata_cache = Some(cache);
+        self
+    }
+
+    /// Avisa al kernel de las entradas que crea, mueve o elimina un cambio
+    /// remoto, para que los listados y los watchers del montaje lo vean ya
+    pub fn with_kernel_notifier(mut self, notifier: Arc<KernelNotifier>) -> Self {
+        self.kernel_notifier = Some(notifier);
+        self
+    }
+
     /// Inicia el loop de sincronización en un task de Tokio separado
     pub fn spawn(self) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             tracing::info!("🔄 Background Syncer iniciado (intervalo: {:?})", self.interval);
             
             let mut current_backoff = self.interval;
+            let mut sync_now = crate::sync::trigger::subscribe();
+            // Fuera de la franja, el primer ciclo también espera
+            self.schedule.wait_next_cycle(Duration::ZERO, &mut sync_now).await;
             
             loop {
                 // Verificar si se solicitó shutdown
@@ -78,21 +157,60 @@ impl BackgroundSyncer {
                     continue;
                 }
 
+                // Sin conexión: esperar a la reconexión y sincronizar de inmediato
+                if !crate::utils::network::is_online() {
+                    tokio::select! {
+                        _ = crate::utils::network::wait_for_online() => {}
+                        _ = crate::utils::shutdown::wait_for_shutdown() => {}
+                    }
+                    current_backoff = self.interval;
+                    continue;
+                }
+
+                if crate::sync::trigger::take_rebuild_request() {
+                    match self.rebuild_metadata().await {
+                        Ok(report) => {
+                            tracing::info!("🧰 Metadatos reconstruidos: {:?}", report);
+                            self.history.log(
+                                ActionType::Sync,
+                                t!("activity-rebuild-complete", checked = report.checked, added = report.added, removed = report.removed),
+                            );
+                        }
+                        Err(e) => {
+                            tracing::error!("❌ Error reconstruyendo metadatos: {:?}", e);
+                            self.history.log(ActionType::Error, t!("activity-rebuild-failed", error = e.to_string()));
+                        }
+                    }
+                }
+
                 match self.sync_once().await {
                     Ok(changes_count) => {
                         if changes_count > 0 {
                             tracing::info!("✅ Sincronización completada: {} cambios procesados", changes_count);
                             self.history.log(
                                 ActionType::Sync, 
-                                format!("Sincronizados {} cambios remotos", changes_count)
+                                t!("activity-remote-synced", count = changes_count)
                             );
                         }
                         // Reset backoff en caso de éxito
                         current_backoff = self.interval;
                     }
+                    Err(e) if crate::utils::network::report_error(&e) => {
+                        tracing::warn!("📴 Sincronización remota en espera de conexión");
+                        continue;
+                    }
+                    Err(e) if DriveError::find(&e).is_some_and(DriveError::is_rate_limited) => {
+                        // Límite de peticiones: no es un fallo que mostrar en la actividad reciente.
+                        // El page token no avanzó, el próximo ciclo retoma los mismos cambios.
+                        let retry_after = DriveError::find(&e).and_then(DriveError::retry_after);
+                        current_backoff = retry_after
+                            .unwrap_or(current_backoff * 2)
+                            .min(Duration::from_secs(MAX_BACKOFF_SECS));
+                        tracing::warn!("🐢 Límite de peticiones de Drive: próxima sincronización en {:?}", current_backoff);
+                    }
                     Err(e) => {
                         tracing::error!("❌ Error en sincronización: {:?}", e);
-                        self.history.log(ActionType::Error, "Error en sincronización remota");
+                        self.history.log(ActionType::Error, t!("activity-remote-sync-error"));
                         
                         // Exponential backoff
                         current_backoff = std::cmp::min(
@@ -103,7 +221,10 @@ impl BackgroundSyncer {
                     }
                 }
                 
-                sleep(current_backoff).await;
+                // "Sincronizar ahora": ciclo inmediato con el backoff reiniciado
+                if self.schedule.wait_next_cycle(current_backoff, &mut sync_now).await {
+                    current_backoff = self.interval;
+                }
             }
         })
     }
@@ -144,6 +265,14 @@ impl BackgroundSyncer {
             
             let root_id_arc = Arc::new(root_id.clone());
 
+            // Los cambios se aplican en paralelo: si un archivo aparece varias
+            // veces en la página, una versión vieja podría pisar a la nueva
+            let changes = latest_per_file(changes);
+            for _ in changes.len()..changes_count {
+                self.history.increment_applied();
+                total_applied += 1;
+            }
+
             let process_results = stream::iter(changes)
                 .map(|change| {
                     let root_id_ref = root_id_arc.clone();
@@ -163,16 +292,28 @@ impl BackgroundSyncer {
                 total_applied += 1;
             }
 
-            // 4. Guardar nuevo token (puede ser de siguiente página o start page)
-            if let Some(new_token) = next_token {
-                self.db.set_sync_meta(SYNC_META_PAGE_TOKEN, &new_token).await?;
-                tracing::debug!("Nuevo pageToken guardado: {}", new_token);
-                page_token = new_token;
+            // 4. Guardar nuevo token tras cada página (puede ser de siguiente página
+            // o start page): si el proceso muere a mitad, se reanuda desde aquí
+            match next_token {
+                Some(new_token) => {
+                    self.db.set_sync_meta(SYNC_META_PAGE_TOKEN, &new_token).await?;
+                    tracing::debug!("Nuevo pageToken guardado: {}", new_token);
+                    page_token = new_token;
+                }
+                // Sin token no hay forma de pedir la página siguiente: repetir la
+                // misma entraría en bucle
+                None if has_more => anyhow::bail!("Drive indicó más páginas de cambios sin nextPageToken"),
+                None => {}
             }
 
             if !has_more {
                 break;
             }
+            if crate::utils::shutdown::is_shutdown_requested() {
+                tracing::info!("🛑 Shutdown a mitad de la paginación de cambios ({} aplicados); se reanuda en el próximo arranque", total_applied);
+                break;
+            }
+            tracing::debug!("📄 Más páginas de cambios pendientes ({} leídos)", total_fetched);
         }
 
         if total_fetched > 0 {
@@ -181,13 +322,178 @@ impl BackgroundSyncer {
 
         // 5. Purgar tombstones expirados (cada ciclo, es barato)
         let purged = self.db.purge_expired_tombstones(TOMBSTONE_GRACE_DAYS).await?;
-        if purged > 0 {
-            tracing::info!("Purgados {} tombstones expirados", purged);
+        if !purged.is_empty() {
+            let reclaimed = self.remove_cached_content(&purged).await;
+            tracing::info!(
+                "🧹 Purgados {} tombstones expirados ({:.1} MB de caché liberados)",
+                purged.len(),
+                reclaimed as f64 / (1024.0 * 1024.0)
+            );
+        }
+
+        // 6. Anclados en otros dispositivos. Un fallo aquí no invalida lo ya
+        // sincronizado: se reintenta en el siguiente ciclo
+        if let Err(e) = self.publish_pin_hints().await {
+            tracing::warn!("⚠️ No se publicaron las marcas de anclado pendientes: {:?}", e);
+        }
+        if self.pin_hints
+            && let Err(e) = self.apply_pin_hints().await
+        {
+            tracing::warn!("⚠️ No se aplicaron los anclados de otros dispositivos: {:?}", e);
         }
 
         Ok(total_fetched)
     }
 
+    /// Reintenta publicar en Drive las marcas de anclado que el usuario cambió
+    /// aquí sin red (`MetadataRepository::unpublished_pin_hints`). Retorna
+    /// cuántas se publicaron
+    async fn publish_pin_hints(&self) -> Result<usize> {
+        let pending = self.db.unpublished_pin_hints().await?;
+        for (inode, gdrive_id, pinned) in &pending {
+            self.client.set_app_property(gdrive_id, app_properties::PIN_KEY, app_properties::pin_value(*pinned)).await?;
+            self.db.pin_hint_published(*inode, *pinned).await?;
+        }
+        Ok(pending.len())
+    }
+
+    /// Mantiene en este dispositivo lo que otra instalación marcó como
+    /// mantenido (`app_properties::PIN_KEY`); el espejo lo descarga en el
+    /// Refresh. Retorna cuántos elementos se marcaron
+    async fn apply_pin_hints(&self) -> Result<usize> {
+        let pending = self.db.pending_pin_hints().await?;
+        for &(inode, is_dir) in &pending {
+            self.db.keep_on_device(inode, is_dir).await?;
+        }
+        if !pending.is_empty() {
+            tracing::info!("📌 {} elementos mantenidos en otro dispositivo se mantendrán también aquí", pending.len());
+            self.history.log(ActionType::Download, t!("activity-pins-from-other-device", count = pending.len()));
+            let _ = self.mirror_tx.send(crate::mirror::manager::MirrorCommand::Refresh).await;
+        }
+        Ok(pending.len())
+    }
+
+    /// Compara todo Drive (`files.list`) con la DB y repara las diferencias sin
+    /// recrear inodes, de modo que la caché y el anclado sobreviven: cada archivo
+    /// listado se aplica como un cambio (altas, metadatos, movimientos; la caché
+    /// se invalida si cambió el contenido) y lo que la DB tiene pero Drive ya no
+    /// lista se trata como enviado a la papelera (tombstone recuperable).
+    ///
+    /// Los tombstones solo se aplican si todas las páginas se listaron y
+    /// aplicaron: con un fallo parcial, lo que faltara en `remote_ids` se daría
+    /// por borrado sin estarlo.
+    pub async fn rebuild_metadata(&self) -> Result<RebuildReport> {
+        let root_id = self.get_cached_root_id().await?;
+        let mut report = RebuildReport::default();
+        let mut remote_ids = std::collections::HashSet::new();
+        let mut page_token: Option<String> = None;
+        let excluded_parents = self.exclusions.query_parents();
+        let mut failed = 0usize;
+
+        loop {
+            let (files, next_token) = self.client
+                .fetch_files_page(page_token.as_deref(), &excluded_parents)
+                .await
+                .context("Listado de Drive incompleto; no se elimina nada de la DB local")?;
+
+            let mut changes = Vec::with_capacity(files.len());
+            for file in files.into_iter().filter(|file| !self.exclusions.excludes(file)) {
+                report.checked += 1;
+                let Some(id) = file.id.clone() else { continue };
+                if self.db.get_inode_by_gdrive_id(&id).await?.is_none() {
+                    report.added += 1;
+                }
+                remote_ids.insert(id.clone());
+                changes.push(google_drive3::api::Change {
+                    file_id: Some(id),
+                    removed: Some(false),
+                    file: Some(file),
+                    ..Default::default()
+                });
+            }
+
+            let results = stream::iter(changes)
+                .map(|change| self.process_change(change, &root_id))
+                .buffer_unordered(4)
+                .collect::<Vec<_>>()
+                .await;
+            for res in results {
+                if let Err(e) = res {
+                    tracing::warn!("Error reparando metadatos de un archivo: {:?}", e);
+                    failed += 1;
+                }
+            }
+
+            page_token = next_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+
+        if failed > 0 {
+            self.db.rebuild_all_dir_counters().await?;
+            let _ = self.mirror_tx.send(crate::mirror::manager::MirrorCommand::Refresh).await;
+            anyhow::bail!("{} archivos no se pudieron reparar; no se elimina nada de la DB local", failed);
+        }
+
+        // Solo con el listado completo: un Drive vacío con DB llena apunta a otra
+        // cuenta o a una respuesta anómala, no a que se borrara todo
+        let local_ids = self.db.remote_ids_in_tree().await?;
+        if remote_ids.is_empty() && !local_ids.is_empty() {
+            anyhow::bail!("Drive no listó ningún archivo; no se elimina nada de la DB local");
+        }
+        for id in local_ids.into_iter().filter(|id| !remote_ids.contains(id) && id != &root_id) {
+            let gone = google_drive3::api::Change {
+                file_id: Some(id.clone()),
+                removed: Some(false),
+                file: Some(google_drive3::api::File { id: Some(id), trashed: Some(true), ..Default::default() }),
+                ..Default::default()
+            };
+            self.process_change(gone, &root_id).await?;
+            report.removed += 1;
+        }
+
+        self.db.rebuild_all_dir_counters().await?;
+        let _ = self.mirror_tx.send(crate::mirror::manager::MirrorCommand::Refresh).await;
+        Ok(report)
+    }
+
+    /// Descarta la caché de un archivo cuyo contenido cambió en Drive: sin
+    /// chunks ni bytes viejos, la próxima lectura descarga la versión nueva
+    /// (los bytes viejos quedarían servidos o subidos con el tamaño nuevo)
+    async fn discard_stale_cache(&self, inode: u64, gdrive_id: &str) {
+        let _guard = self.file_locks.lock(inode).await;
+        let _ = self.db.clear_chunks(inode).await;
+        if let Some(cache_dir) = &self.cache_dir {
+            match tokio::fs::remove_file(cache_dir.join(gdrive_id)).await {
+                Ok(()) => tracing::debug!("🧹 Caché obsoleta eliminada: {}", gdrive_id),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => tracing::warn!("⚠️ No se pudo eliminar caché obsoleta de {}: {}", gdrive_id, e),
+            }
+        }
+    }
+
+    /// Elimina los archivos de caché de los gdrive_id purgados.
+    /// Retorna los bytes liberados.
+    async fn remove_cached_content(&self, gdrive_ids: &[String]) -> u64 {
+        let Some(cache_dir) = &self.cache_dir else {
+            return 0;
+        };
+
+        let mut reclaimed = 0u64;
+        for gdrive_id in gdrive_ids {
+            let path = cache_dir.join(gdrive_id);
+            let Ok(metadata) = tokio::fs::metadata(&path).await else {
+                continue;
+            };
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => reclaimed += metadata.len(),
+                Err(e) => tracing::warn!("⚠️ No se pudo eliminar caché {:?}: {}", path, e),
+            }
+        }
+        reclaimed
+    }
+
     /// Obtiene el root_id cacheado o lo descarga
     async fn get_cached_root_id(&self) -> Result<String> {
         {
@@ -218,6 +524,49 @@ impl BackgroundSyncer {
 
     /// Procesa un cambio individual de la API
     async fn process_change(&self, change: google_drive3::api::Change, root_id: &str) -> Result<()> {
+        if self.metadata_cache.is_none() && self.kernel_notifier.is_none() {
+            return self.apply_change(change, root_id).await;
+        }
+
+        // Resolver la dentry antes de aplicar: un hard delete la elimina de la DB
+        let file_id = change.file_id.clone().context("Cambio sin file_id")?;
+        let inode_before = self.db.get_inode_by_gdrive_id(&file_id).await.ok().flatten();
+        let dentry_before = match inode_before {
+            Some(inode) => self.db.get_dentry(inode).await.ok().flatten(),
+            None => None,
+        };
+
+        let result = self.apply_change(change, root_id).await;
+
+        let inode_after = self.db.get_inode_by_gdrive_id(&file_id).await.ok().flatten();
+        let dentry_after = match inode_after {
+            Some(inode) => self.db.get_dentry(inode).await.ok().flatten(),
+            None => None,
+        };
+
+        if let Some(cache) = &self.metadata_cache {
+            if let Some(inode) = inode_before {
+                cache.invalidate_inode(inode);
+            }
+            // La carpeta de origen y la de destino cambian de mtime; en la de
+            // destino puede existir ahora un nombre que antes no
+            for (parent, _) in dentry_before.iter().chain(&dentry_after) {
+                cache.invalidate_attrs(*parent);
+                cache.invalidate_missing(*parent);
+            }
+        }
+        if let Some(notifier) = self.kernel_notifier.clone() {
+            let events = kernel_events(inode_before, dentry_before, dentry_after);
+            if !events.is_empty() {
+                // Invalidar una dentry espera el lock de su carpeta en el kernel
+                let _ = tokio::task::spawn_blocking(move || notifier.send(&events)).await;
+            }
+        }
+        result
+    }
+
+    /// Aplica un cambio remoto a la DB local
+    async fn apply_change(&self, change: google_drive3::api::Change, root_id: &str) -> Result<()> {
         let file_id = change.file_id.as_deref()
             .context("Cambio sin file_id")?;
 
@@ -226,8 +575,13 @@ impl BackgroundSyncer {
             tracing::debug!("Cambio detectado: REMOVED (hard delete) file_id={}", file_id);
             let path_to_delete = self.get_relative_path_for_deletion(file_id).await;
             // El archivo fue eliminado permanentemente de Drive (incluyendo papelera vacía)
-            // → Hard delete: eliminar completamente de la DB local
-            self.db.hard_delete_by_gdrive_id(file_id).await?;
+            // → Hard delete inmediato, sin tombstone: eliminar de la DB local junto con
+            // todo su contenido y liberar la caché en disco
+            let removed = self.db.hard_delete_subtree_by_gdrive_id(file_id).await?;
+            let reclaimed = self.remove_cached_content(&removed).await;
+            if reclaimed > 0 {
+                tracing::info!("🗑️ Caché liberada tras eliminación permanente: {:.1} MB", reclaimed as f64 / (1024.0 * 1024.0));
+            }
             if let Some(p) = path_to_delete {
                 let _ = self.mirror_tx.send(crate::mirror::manager::MirrorCommand::RemoteDeleted { paths: vec![p] }).await;
             }
@@ -236,9 +590,10 @@ impl BackgroundSyncer {
 
         // Caso 2: Archivo con datos
         if let Some(file) = change.file {
-            // Verificar si está en la papelera
-            if file.trashed == Some(true) {
-                tracing::debug!("Cambio detectado: TRASHED file_id={}", file_id);
+            // Verificar si está en la papelera o entró en una carpeta excluida
+            // (si la DB lo tenía, sale del árbol local como si fuera a la papelera)
+            if file.trashed == Some(true) || self.exclusions.excludes(&file) {
+                tracing::debug!("Cambio detectado: TRASHED/EXCLUDED file_id={}", file_id);
                 let path_to_delete = self.get_relative_path_for_deletion(file_id).await;
                 // Usar soft_delete_remote: NO marca dirty porque la eliminación
                 // ya ocurrió en GDrive y no necesita re-subirse por el uploader.
@@ -273,7 +628,12 @@ impl BackgroundSyncer {
                 .as_ref()
                 .map(|t| t.timestamp())
                 .unwrap_or(0);
-            let mode = if is_dir { 0o755 } else { 0o644 };
+            let mtime_nsec = file.modified_time
+                .as_ref()
+                .map(|t| t.timestamp_subsec_nanos())
+                .unwrap_or(0);
+            let capabilities = crate::sync::bootstrap::capabilities_for(&file);
+            let mode = crate::sync::bootstrap::posix_mode(is_dir, capabilities.can_edit);
 
             let can_move = file.capabilities.as_ref()
                 .and_then(|c| c.can_move_item_within_drive)
@@ -284,6 +644,13 @@ impl BackgroundSyncer {
             // Obtener o crear inode
             let inode = self.db.get_or_create_inode(file_id).await?;
 
+            // Con cambios locales sin subir, tamaño, fecha, caché y `remote_md5`
+            // locales se conservan: si el contenido remoto también cambió, el
+            // uploader lo detecta al comparar `remote_md5` y lo resuelve como
+            // conflicto (copia "(Conflicto local …)" + tabla `conflicts`)
+            let is_dirty = self.db.is_dirty(inode).await.unwrap_or(false);
+            let keep_local = is_dirty && !is_dir;
+
             // Invalidar caché si el contenido del archivo cambió remotamente.
             // Compara size y md5 anteriores contra los nuevos antes de actualizar.
             if !is_dir {
@@ -304,7 +671,14 @@ impl BackgroundSyncer {
                     _ => false,
                 };
 
-                if size_changed || md5_changed {
+                if keep_local {
+                    if size_changed || md5_changed {
+                        tracing::warn!(
+                            "⚔️ inode {} cambió en Drive con ediciones locales pendientes: se resolverá como conflicto al subir",
+                            inode
+                        );
+                    }
+                } else if size_changed || md5_changed {
                     tracing::info!(
                         "🔄 Contenido remoto cambió para inode {}: size {}→{}, md5_changed={}. Invalidando caché.",
                         inode,
@@ -312,11 +686,17 @@ impl BackgroundSyncer {
                         size,
                         md5_changed
                     );
-                    let _ = self.db.clear_chunks(inode).await;
+                    self.discard_stale_cache(inode, file_id).await;
                 }
             }
 
-            // Actualizar metadatos
+            // Actualizar metadatos (con ediciones locales, tamaño y fecha son los locales)
+            let (size, mtime, mtime_nsec) = if keep_local {
+                let local = self.db.get_attrs(inode).await?;
+                (local.size, local.mtime, local.mtime_nsec as u32)
+            } else {
+                (size, mtime, mtime_nsec)
+            };
             self.db.upsert_file_metadata(
                 inode,
                 size,
@@ -328,6 +708,17 @@ impl BackgroundSyncer {
                 shared,
                 file.owned_by_me.unwrap_or(true),
             ).await?;
+            self.db.set_mtime(inode, mtime, mtime_nsec).await?;
+            if let Some(created) = &file.created_time {
+                self.db.set_created_time(inode, created.timestamp()).await?;
+            }
+            self.db.set_starred(inode, file.starred.unwrap_or(false)).await?;
+            self.db.set_app_marks(inode, &AppMarks::from_file(&file)).await?;
+            self.db.set_description(inode, file.description.as_deref()).await?;
+            if is_dir {
+                self.db.set_folder_color(inode, file.folder_color_rgb.as_deref()).await?;
+            }
+            self.db.upsert_capabilities(inode, &capabilities).await?;
 
             // Resolver shortcut: guardar target_id y copiar size del target
             if let Some((target_id, _)) = &shortcut_info {
@@ -335,15 +726,26 @@ impl BackgroundSyncer {
                 let _ = self.db.resolve_shortcut_sizes().await;
             }
 
+            if let Some(link) = &file.web_view_link {
+                self.db.set_web_view_link(inode, link).await?;
+            }
+            if let Some(link) = &file.thumbnail_link {
+                self.db.set_thumbnail_link(inode, Some(link)).await?;
+            }
+
             // Actualizar dentry (árbol de directorios)
             // IMPORTANTE: Si el archivo tiene cambios locales pendientes (dirty),
             // NO sobreescribir la dentry. El cambio remoto es probablemente un eco
             // de una operación previa nuestra, y el estado local (posiblemente un
             // segundo movimiento) tiene prioridad.
-            let is_dirty = self.db.is_dirty(inode).await.unwrap_or(false);
             let owned = file.owned_by_me.unwrap_or(true);
+            let dentry_before = self.db.get_dentry(inode).await?;
             if !is_dirty {
-                if let Some(parents) = &file.parents {
+                if crate::sync::bootstrap::is_computer_folder(&file, root_id) {
+                    // Equipo de "Ordenadores": nunca en la raíz, bajo el directorio virtual
+                    let computers_inode = self.db.computers_inode().await?;
+                    self.db.upsert_remote_dentry(computers_inode, inode, name).await?;
+                } else if let Some(parents) = &file.parents {
                     for parent_id in parents {
                         // Google Drive usa "root" o el ID canónico (root_id) para el "My Drive" del usuario
                         // Ambos deben mapearse al inode 1 (root del filesystem local)
@@ -361,11 +763,11 @@ impl BackgroundSyncer {
                                 pi
                             }
                         };
-                        self.db.upsert_dentry(parent_inode, inode, name).await?;
+                        self.db.upsert_remote_dentry(parent_inode, inode, name).await?;
                     }
                 } else {
                     // Sin padres → colgar del root
-                    self.db.upsert_dentry(1, inode, name).await?;
+                    self.db.upsert_remote_dentry(1, inode, name).await?;
                 }
             } else {
                 tracing::debug!(
@@ -374,13 +776,28 @@ impl BackgroundSyncer {
                 );
             }
 
+            // Lo que llega a una carpeta "Mantener siempre en este dispositivo"
+            // se descarga también: el espejo lo materializa en el próximo Refresh
+            if !is_dir && !is_dirty
+                && let Some((parent, _)) = self.db.get_dentry(inode).await?
+                && dentry_before.as_ref().map(|(p, _)| *p) != Some(parent)
+                && self.db.folder_keeps_content(parent).await?
+                && self.db.get_availability(inode).await? != "local_online"
+            {
+                tracing::info!("📌 {} entra en una carpeta mantenida en el dispositivo", name);
+                self.db.set_availability(inode, "local_online", true).await?;
+            }
+
             // Asegurar dir_counters para directorios nuevos
             if is_dir {
                 self.db.ensure_dir_counter(inode).await?;
             }
 
-            // Actualizar remote_md5 si está disponible (para detección de conflictos)
-            if let Some(md5) = file.md5_checksum.clone() {
+            // Actualizar remote_md5 si está disponible (para detección de conflictos).
+            // Con ediciones locales se conserva el de la versión en la que se basan
+            if let Some(md5) = file.md5_checksum.clone()
+                && !keep_local
+            {
                 self.db.set_remote_md5(inode, &md5).await?;
             }
 
@@ -432,7 +849,7 @@ impl BackgroundSyncer {
                         .file_name()
                         .map(|n| n.to_string_lossy().to_string())
                         .unwrap_or_else(|| local_file.relative_path.clone());
-                    self.history.log(ActionType::Download, format!("Descargando: {}", name_display));
+                    self.history.log(ActionType::Download, t!("activity-downloading", name = name_display.as_str()));
                     tracing::info!("📥 Descargando actualización para: {}", local_file.relative_path);
                     
                     // Descargar archivo usando chunks (con tracking de progreso)
@@ -480,7 +897,7 @@ impl BackgroundSyncer {
                         mtime,
                     ).await?;
                     
-                    self.history.log(ActionType::Download, format!("Descargado: {}", name_display));
+                    self.history.log_file(ActionType::Download, t!("activity-downloaded", name = name_display.as_str()), Some(local_path.clone()));
                     tracing::info!("✅ Archivo actualizado localmente: {}", local_file.relative_path);
                 }
             }
@@ -509,6 +926,46 @@ async fn should_protect_local_file(api_size: u64, local_path: &std::path::Path)
     }
 }
 
+/// Deja solo el último cambio de cada archivo, en el orden de la página
+fn latest_per_file(changes: Vec<google_drive3::api::Change>) -> Vec<google_drive3::api::Change> {
+    let mut seen = std::collections::HashSet::new();
+    let mut latest: Vec<_> = changes
+        .into_iter()
+        .rev()
+        .filter(|change| change.file_id.as_ref().is_none_or(|id| seen.insert(id.clone())))
+        .collect();
+    latest.reverse();
+    latest
+}
+
+/// Avisos al kernel para un cambio remoto, según la dentry del archivo antes
+/// y después de aplicarlo
+fn kernel_events(
+    inode: Option<u64>,
+    before: Option<(u64, String)>,
+    after: Option<(u64, String)>,
+) -> Vec<KernelEvent> {
+    let mut events = Vec::new();
+    if let Some(inode) = inode {
+        events.push(KernelEvent::InvalidInode(inode));
+    }
+    if before == after {
+        return events;
+    }
+    if let Some((parent, name)) = before {
+        events.push(match (inode, &after) {
+            (Some(child), None) => KernelEvent::Delete { parent, child, name },
+            _ => KernelEvent::InvalidEntry { parent, name },
+        });
+        events.push(KernelEvent::InvalidInode(parent));
+    }
+    if let Some((parent, name)) = after {
+        events.push(KernelEvent::InvalidEntry { parent, name });
+        events.push(KernelEvent::InvalidInode(parent));
+    }
+    events
+}
+
 #[cfg(test)]
 mod tests {
     use rstest::*;
@@ -551,4 +1008,385 @@ mod tests {
         let result = rt.block_on(super::should_protect_local_file(0, std::path::Path::new("/tmp/no_existe_xyz_test")));
         assert!(!result, "No debe proteger archivo que no existe");
     }
+
+    #[rstest]
+    #[case::new_file(None, None, Some((1, "a")), vec![
+        KernelEvent::InvalidEntry { parent: 1, name: "a".into() },
+        KernelEvent::InvalidInode(1),
+    ])]
+    #[case::content_only(Some(9), Some((1, "a")), Some((1, "a")), vec![KernelEvent::InvalidInode(9)])]
+    #[case::moved(Some(9), Some((1, "a")), Some((2, "b")), vec![
+        KernelEvent::InvalidInode(9),
+        KernelEvent::InvalidEntry { parent: 1, name: "a".into() },
+        KernelEvent::InvalidInode(1),
+        KernelEvent::InvalidEntry { parent: 2, name: "b".into() },
+        KernelEvent::InvalidInode(2),
+    ])]
+    #[case::removed(Some(9), Some((1, "a")), None, vec![
+        KernelEvent::InvalidInode(9),
+        KernelEvent::Delete { parent: 1, child: 9, name: "a".into() },
+        KernelEvent::InvalidInode(1),
+    ])]
+    fn test_kernel_events(
+        #[case] inode: Option<u64>,
+        #[case] before: Option<(u64, &str)>,
+        #[case] after: Option<(u64, &str)>,
+        #[case] expected: Vec<KernelEvent>,
+    ) {
+        let owned = |d: Option<(u64, &str)>| d.map(|(parent, name)| (parent, name.to_string()));
+        assert_eq!(super::kernel_events(inode, owned(before), owned(after)), expected);
+    }
+
+    #[test]
+    fn test_latest_per_file_keeps_last_change_in_order() {
+        let change = |id: &str, name: &str| google_drive3::api::Change {
+            file_id: Some(id.to_string()),
+            file: Some(google_drive3::api::File { name: Some(name.to_string()), ..Default::default() }),
+            ..Default::default()
+        };
+        let latest = super::latest_per_file(vec![change("a", "a1"), change("b", "b1"), change("a", "a2")]);
+        let names: Vec<_> = latest.iter().map(|c| c.file.as_ref().unwrap().name.clone().unwrap()).collect();
+        assert_eq!(names, vec!["b1", "a2"]);
+    }
+
+    // ============================================================
+    // Procesamiento de cambios contra un Drive falso
+    // ============================================================
+
+    use super::{BackgroundSyncer, RebuildReport};
+    use crate::fuse::kernel_notify::KernelEvent;
+    use crate::sync::exclusions::Exclusions;
+    use crate::db::MetadataRepository;
+    use crate::db::test_fixtures;
+    use crate::gdrive::mock::{MockDriveClient, MOCK_ROOT_ID};
+    use crate::mirror::manager::MirrorCommand;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        mock: Arc<MockDriveClient>,
+        db: Arc<MetadataRepository>,
+        syncer: BackgroundSyncer,
+        mirror_rx: tokio::sync::mpsc::Receiver<MirrorCommand>,
+    }
+
+    /// Drive con `nota.txt` ya volcado a la DB y el page token al día
+    async fn synced_fixture() -> Fixture {
+        synced_fixture_with(MockDriveClient::new()).await
+    }
+
+    async fn synced_fixture_with(mock: MockDriveClient) -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let mock = Arc::new(mock);
+        mock.add_file("file-nota", "nota.txt", MOCK_ROOT_ID, b"hola");
+        let db = test_fixtures::synced_repo(&dir, &mock).await;
+        let client: Arc<dyn crate::gdrive::DriveApi> = mock.clone();
+
+        let (mirror_tx, mirror_rx) = tokio::sync::mpsc::channel(16);
+        let syncer = BackgroundSyncer::new(
+            db.clone(),
+            client,
+            60,
+            crate::gui::history::ActionHistory::new(),
+            Arc::new(AtomicBool::new(false)),
+            mirror_tx,
+        );
+        assert_eq!(syncer.sync_once().await.unwrap(), 0, "el primer ciclo solo obtiene el token");
+
+        Fixture { _dir: dir, mock, db, syncer, mirror_rx }
+    }
+
+    #[tokio::test]
+    async fn test_sync_applies_new_remote_file() {
+        let fx = synced_fixture().await;
+        let file = fx.mock.add_file("file-nuevo", "nuevo.txt", MOCK_ROOT_ID, b"contenido");
+
+        assert_eq!(fx.syncer.sync_once().await.unwrap(), 1);
+
+        let inode = fx.db.lookup(1, "nuevo.txt").await.unwrap().expect("nuevo.txt en root");
+        let attrs = fx.db.get_attrs(inode).await.unwrap();
+        assert_eq!(attrs.size, 9);
+        assert_eq!(attrs.created_time, file.created_time.map(|t| t.timestamp()));
+        assert_eq!(fx.db.get_remote_md5(inode).await.unwrap(), file.md5_checksum);
+        assert_eq!(fx.syncer.sync_once().await.unwrap(), 0, "el token avanzó");
+    }
+
+    #[tokio::test]
+    async fn test_sync_applies_folder_color_and_description() {
+        let fx = synced_fixture().await;
+        fx.mock.add_folder("folder-fotos", "Fotos", MOCK_ROOT_ID);
+        fx.syncer.sync_once().await.unwrap();
+        let folder = fx.db.lookup(1, "Fotos").await.unwrap().unwrap();
+        assert_eq!(fx.db.get_folder_color(folder).await.unwrap(), None);
+
+        fx.mock.set_folder_color("folder-fotos", "#f83a22");
+        crate::gdrive::DriveApi::set_description(fx.mock.as_ref(), "folder-fotos", Some("Viaje a Lisboa")).await.unwrap();
+        fx.syncer.sync_once().await.unwrap();
+
+        assert_eq!(fx.db.get_folder_color(folder).await.unwrap().as_deref(), Some("#f83a22"));
+        assert_eq!(fx.db.get_description(folder).await.unwrap().as_deref(), Some("Viaje a Lisboa"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_puts_new_computer_under_virtual_dir() {
+        let fx = synced_fixture().await;
+        fx.db.set_computers_visible(true).await.unwrap();
+        fx.mock.add_computer("folder-laptop", "Mi portátil");
+
+        fx.syncer.sync_once().await.unwrap();
+
+        assert_eq!(fx.db.lookup(1, "Mi portátil").await.unwrap(), None, "sin padres no significa raíz");
+        let computers = fx.db.lookup(1, crate::db::COMPUTERS_DIR_NAME).await.unwrap().unwrap();
+        assert!(fx.db.lookup(computers, "Mi portátil").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_sync_remote_edit_invalidates_cache() {
+        let mut fx = synced_fixture().await;
+        let cache_dir = fx._dir.path().join("cache");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        fx.syncer.cache_dir = Some(cache_dir.clone());
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        std::fs::write(cache_dir.join("file-nota"), b"hola").unwrap();
+        fx.db.add_cached_chunk(inode, 0, 3).await.unwrap();
+
+        fx.mock.modify_remote("file-nota", b"hola de nuevo");
+        fx.syncer.sync_once().await.unwrap();
+
+        assert!(!fx.db.has_any_chunks(inode).await.unwrap(), "la caché vieja no debe servirse");
+        assert!(!cache_dir.join("file-nota").exists(), "ni quedar en disco con el tamaño nuevo");
+        assert_eq!(fx.db.get_attrs(inode).await.unwrap().size, 13);
+    }
+
+    #[tokio::test]
+    async fn test_sync_remote_edit_keeps_local_edits_of_dirty_file() {
+        let fx = synced_fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        let known_md5 = fx.db.get_remote_md5(inode).await.unwrap();
+        fx.db.set_size(inode, 12).await.unwrap();
+        fx.db.add_cached_chunk(inode, 0, 11).await.unwrap();
+        fx.db.mark_dirty(inode).await.unwrap();
+
+        fx.mock.modify_remote("file-nota", b"remoto");
+        fx.syncer.sync_once().await.unwrap();
+
+        assert_eq!(fx.db.get_attrs(inode).await.unwrap().size, 12, "el tamaño local se conserva");
+        assert!(fx.db.get_missing_ranges(inode, 0, 11).await.unwrap().is_empty(), "la edición local sigue en caché");
+        assert_eq!(fx.db.get_remote_md5(inode).await.unwrap(), known_md5, "el uploader debe ver el conflicto");
+        assert!(fx.db.is_dirty(inode).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sync_trash_then_permanent_delete() {
+        let mut fx = synced_fixture().await;
+
+        fx.mock.trash_remote("file-nota");
+        fx.syncer.sync_once().await.unwrap();
+
+        assert_eq!(fx.db.lookup(1, "nota.txt").await.unwrap(), None);
+        assert!(fx.db.has_tombstone("file-nota").await.unwrap());
+        match fx.mirror_rx.try_recv() {
+            Ok(MirrorCommand::RemoteDeleted { paths }) => assert_eq!(paths, vec!["nota.txt".to_string()]),
+            other => panic!("se esperaba RemoteDeleted, llegó {:?}", other),
+        }
+
+        fx.mock.remove_remote("file-nota");
+        fx.syncer.sync_once().await.unwrap();
+
+        assert_eq!(fx.db.get_inode_by_gdrive_id("file-nota").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_sync_notifies_kernel_of_remote_changes() {
+        use std::io::Read;
+        let fx = synced_fixture().await;
+        let (mut reader, writer) = std::io::pipe().unwrap();
+        let notifier = Arc::new(crate::fuse::kernel_notify::KernelNotifier::default());
+        notifier.attach_fd(writer.into());
+        let syncer = fx.syncer.with_kernel_notifier(notifier);
+        fx.mock.add_file("file-nuevo", "nuevo.txt", MOCK_ROOT_ID, b"contenido");
+
+        assert_eq!(syncer.sync_once().await.unwrap(), 1);
+        drop(syncer);
+
+        let mut written = Vec::new();
+        reader.read_to_end(&mut written).unwrap();
+        // inval_entry(1, "nuevo.txt") + inval_inode(1)
+        assert_eq!(written.len(), (16 + 16 + 10) + 40);
+        assert_eq!(&written[32..42], b"nuevo.txt\0");
+    }
+
+    #[tokio::test]
+    async fn test_sync_keeps_files_arriving_in_kept_folder() {
+        let fx = synced_fixture().await;
+        fx.mock.add_folder("folder-fotos", "Fotos", MOCK_ROOT_ID);
+        fx.syncer.sync_once().await.unwrap();
+        let fotos = fx.db.lookup(1, "Fotos").await.unwrap().unwrap();
+        fx.db.set_folder_availability(fotos, "local_online").await.unwrap();
+
+        fx.mock.add_file("file-playa", "playa.jpg", "folder-fotos", b"jpg");
+        fx.mock.move_remote("file-nota", "folder-fotos");
+        fx.syncer.sync_once().await.unwrap();
+
+        for name in ["playa.jpg", "nota.txt"] {
+            let inode = fx.db.lookup(fotos, name).await.unwrap().unwrap();
+            assert_eq!(fx.db.get_availability(inode).await.unwrap(), "local_online", "{}", name);
+        }
+    }
+
+    #[rstest]
+    #[case::enabled(true)]
+    #[case::disabled(false)]
+    #[tokio::test]
+    async fn test_sync_applies_pins_from_other_devices(#[case] enabled: bool) {
+        use crate::gdrive::DriveApi;
+        use crate::gdrive::app_properties::{PIN_KEY, PIN_VALUE};
+        let Fixture { _dir, mock, db, syncer, mut mirror_rx } = synced_fixture().await;
+        let syncer = syncer.with_pin_hints(enabled);
+        mock.set_app_property("file-nota", PIN_KEY, Some(PIN_VALUE)).await.unwrap();
+
+        syncer.sync_once().await.unwrap();
+
+        let nota = db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        let expected = if enabled { "local_online" } else { "online_only" };
+        assert_eq!(db.get_availability(nota).await.unwrap(), expected);
+        assert_eq!(matches!(mirror_rx.try_recv(), Ok(MirrorCommand::Refresh)), enabled);
+        assert_eq!(syncer.apply_pin_hints().await.unwrap(), usize::from(!enabled), "ya aplicado");
+    }
+
+    #[tokio::test]
+    async fn test_sync_publishes_pending_pin_hint() {
+        use crate::gdrive::DriveApi;
+        use crate::gdrive::app_properties::{AppMarks, PIN_KEY, PIN_VALUE};
+        let fx = synced_fixture().await;
+        let syncer = fx.syncer.with_pin_hints(true);
+        let nota = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        fx.mock.set_app_property("file-nota", PIN_KEY, Some(PIN_VALUE)).await.unwrap();
+        // Liberado aquí sin red: Drive conserva la marca vieja
+        fx.db.set_pin_hint(nota, false).await.unwrap();
+        fx.mock.fail_next("set_app_property", "503 Service Unavailable");
+
+        syncer.sync_once().await.unwrap();
+        assert_eq!(fx.db.get_availability(nota).await.unwrap(), "online_only", "no se vuelve a anclar");
+        assert_eq!(fx.db.unpublished_pin_hints().await.unwrap().len(), 1);
+
+        syncer.sync_once().await.unwrap();
+        assert!(fx.db.unpublished_pin_hints().await.unwrap().is_empty());
+        let file = fx.mock.get_file_metadata("file-nota").await.unwrap();
+        assert!(!AppMarks::from_file(&file).pinned);
+    }
+
+    #[tokio::test]
+    async fn test_sync_failure_keeps_page_token() {
+        let fx = synced_fixture().await;
+        fx.mock.add_file("file-nuevo", "nuevo.txt", MOCK_ROOT_ID, b"contenido");
+        fx.mock.fail_next("list_changes", "503 Service Unavailable");
+
+        assert!(fx.syncer.sync_once().await.is_err());
+        assert_eq!(fx.db.lookup(1, "nuevo.txt").await.unwrap(), None);
+
+        // El siguiente ciclo reanuda desde el mismo token y no pierde el cambio
+        assert_eq!(fx.syncer.sync_once().await.unwrap(), 1);
+        assert!(fx.db.lookup(1, "nuevo.txt").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_sync_consumes_all_change_pages_in_one_cycle() {
+        let fx = synced_fixture_with(MockDriveClient::new().with_page_size(2)).await;
+        for i in 0..5 {
+            fx.mock.add_file(&format!("file-{}", i), &format!("f{}.txt", i), MOCK_ROOT_ID, b"x");
+        }
+        let calls_before = fx.mock.calls("list_changes");
+
+        assert_eq!(fx.syncer.sync_once().await.unwrap(), 5);
+
+        assert_eq!(fx.mock.calls("list_changes") - calls_before, 3, "tres páginas de 2+2+1");
+        for i in 0..5 {
+            assert!(fx.db.lookup(1, &format!("f{}.txt", i)).await.unwrap().is_some());
+        }
+        assert_eq!(fx.syncer.sync_once().await.unwrap(), 0, "el token quedó en newStartPageToken");
+    }
+
+    #[tokio::test]
+    async fn test_sync_commits_page_token_per_page() {
+        let fx = synced_fixture_with(MockDriveClient::new().with_page_size(2)).await;
+        for i in 0..5 {
+            fx.mock.add_file(&format!("file-{}", i), &format!("f{}.txt", i), MOCK_ROOT_ID, b"x");
+        }
+        fx.mock.fail_after("list_changes", 1, "503 Service Unavailable");
+
+        assert!(fx.syncer.sync_once().await.is_err());
+        assert!(fx.db.lookup(1, "f1.txt").await.unwrap().is_some(), "la primera página ya se aplicó");
+        assert_eq!(fx.db.lookup(1, "f2.txt").await.unwrap(), None);
+
+        // Se reanuda desde la segunda página, sin releer la primera
+        assert_eq!(fx.syncer.sync_once().await.unwrap(), 3);
+        assert!(fx.db.lookup(1, "f4.txt").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_metadata_repairs_drift_keeping_inodes() {
+        let fx = synced_fixture().await;
+        let nota = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        fx.db.add_cached_chunk(nota, 0, 4).await.unwrap();
+        // Deriva: un cambio perdido (archivo nuevo) y un archivo local que Drive ya no tiene
+        fx.mock.add_file("file-perdido", "perdido.txt", MOCK_ROOT_ID, b"x");
+        let fantasma = fx.db.get_or_create_inode("file-fantasma").await.unwrap();
+        fx.db.upsert_file_metadata(fantasma, 1, 0, 0o644, false, Some("text/plain"), true, false, true).await.unwrap();
+        fx.db.upsert_remote_dentry(1, fantasma, "fantasma.txt").await.unwrap();
+
+        let report = fx.syncer.rebuild_metadata().await.unwrap();
+
+        assert_eq!(report, RebuildReport { checked: 2, added: 1, removed: 1 });
+        assert!(fx.db.lookup(1, "perdido.txt").await.unwrap().is_some());
+        assert_eq!(fx.db.lookup(1, "fantasma.txt").await.unwrap(), None);
+        assert!(fx.db.has_tombstone("file-fantasma").await.unwrap(), "recuperable, no borrado definitivo");
+        assert_eq!(fx.db.lookup(1, "nota.txt").await.unwrap(), Some(nota), "mismo inode");
+        assert!(fx.db.has_any_chunks(nota).await.unwrap(), "la caché sin cambios sobrevive");
+    }
+
+    #[tokio::test]
+    async fn test_excluded_folder_contents_are_dropped() {
+        let mut fx = synced_fixture().await;
+        fx.mock.add_folder("folder-archivo", "Archivo", MOCK_ROOT_ID);
+        fx.syncer.sync_once().await.unwrap();
+        let archivo = fx.db.lookup(1, "Archivo").await.unwrap().unwrap();
+        fx.syncer = fx.syncer.with_exclusions(Arc::new(Exclusions::from_ids(&["folder-archivo"])));
+
+        fx.mock.add_file("file-viejo", "viejo.txt", "folder-archivo", b"x");
+        fx.mock.move_remote("file-nota", "folder-archivo");
+        fx.syncer.sync_once().await.unwrap();
+
+        assert_eq!(fx.db.lookup(archivo, "viejo.txt").await.unwrap(), None, "no se sincroniza");
+        assert_eq!(fx.db.lookup(archivo, "nota.txt").await.unwrap(), None);
+        assert_eq!(fx.db.lookup(1, "nota.txt").await.unwrap(), None, "movido a una carpeta excluida = fuera del árbol");
+        assert!(fx.db.lookup(1, "Archivo").await.unwrap().is_some(), "la carpeta sigue visible");
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_metadata_partial_listing_removes_nothing() {
+        let fx = synced_fixture_with(MockDriveClient::new().with_page_size(1)).await;
+        fx.mock.add_file("file-otra", "otra.txt", MOCK_ROOT_ID, b"x");
+        fx.syncer.sync_once().await.unwrap();
+        fx.mock.fail_after("fetch_files_page", 1, "500 error interno");
+
+        assert!(fx.syncer.rebuild_metadata().await.is_err());
+
+        assert!(fx.db.lookup(1, "nota.txt").await.unwrap().is_some());
+        assert!(fx.db.lookup(1, "otra.txt").await.unwrap().is_some());
+        assert!(!fx.db.has_tombstone("file-otra").await.unwrap());
+        assert!(!fx.db.has_tombstone("file-nota").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_metadata_keeps_unuploaded_local_changes() {
+        let fx = synced_fixture().await;
+        // ID reservado con generate_ids: parece de Drive pero aún no existe allí
+        let nueva = fx.db.create_local_inode("mock-file-reservado").await.unwrap();
+        fx.db.upsert_dentry(1, nueva, "nueva.txt").await.unwrap();
+
+        assert_eq!(fx.syncer.rebuild_metadata().await.unwrap().removed, 0);
+        assert_eq!(fx.db.lookup(1, "nueva.txt").await.unwrap(), Some(nueva));
+    }
 }
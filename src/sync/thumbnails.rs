@@ -0,0 +1,366 @@
+//! Miniaturas de Drive en la caché de miniaturas del escritorio
+//!
+//! Nautilus genera las miniaturas leyendo el archivo entero: con un vídeo
+//! "Online Only" eso es descargarlo. Drive ya tiene miniatura de imágenes,
+//! vídeos, PDF y documentos (`thumbnailLink`, que guardan bootstrap y syncer).
+//! `ThumbnailFetcher` la descarga para los archivos de los directorios más
+//! usados (`dir_access_log`, como el prefetcher) y la escribe en la caché de
+//! Freedesktop (`~/.cache/thumbnails/{normal,large}/<md5 de la URI>.png`, con
+//! `Thumb::URI` y `Thumb::MTime`) para la ruta del espejo y la del montaje
+//! FUSE. Nautilus la encuentra sin leer el archivo.
+//!
+//! `thumbnailLink` caduca en horas: si la descarga falla se pide uno nuevo con
+//! files.get; si Drive ya no tiene miniatura se olvida el enlace guardado.
+//!
+//! Con la caché cifrada (`encrypt_cache`) no se escribe ninguna: la caché de
+//! miniaturas del escritorio es texto plano y una miniatura deja ver el
+//! contenido del archivo que la caché cifrada protege.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::db::{MetadataRepository, ThumbnailCandidate};
+use crate::gdrive::DriveApi;
+use crate::sync::prefetcher::{ACCESS_WINDOW_SECS, MAX_HOT_DIRS, STARTUP_DELAY_SECS};
+
+/// Miniaturas descargadas como mucho por ronda
+const MAX_PER_ROUND: usize = 200;
+
+/// Tamaños de la especificación: (subdirectorio, lado máximo en píxeles)
+const SIZES: [(&str, i32); 2] = [("normal", 128), ("large", 256)];
+
+/// Caché de miniaturas de Freedesktop
+#[derive(Debug, Clone)]
+pub struct ThumbnailCache {
+    root: PathBuf,
+}
+
+impl ThumbnailCache {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self { root: root.as_ref().to_path_buf() }
+    }
+
+    /// Ruta de la miniatura de `uri` en un subdirectorio de tamaño
+    pub fn path_for(&self, size: &str, uri: &str) -> PathBuf {
+        use md5::{Digest, Md5};
+        self.root.join(size).join(format!("{:x}.png", Md5::digest(uri.as_bytes())))
+    }
+
+    /// Hay miniatura de `uri` (la validez de `Thumb::MTime` la lleva la DB)
+    pub fn contains(&self, uri: &str) -> bool {
+        SIZES.iter().all(|(size, _)| self.path_for(size, uri).exists())
+    }
+
+    /// Escribe las miniaturas de `uri` a partir de la imagen de Drive
+    pub async fn write(&self, uri: &str, mtime: i64, image: Vec<u8>) -> Result<()> {
+        let cache = self.clone();
+        let uri = uri.to_string();
+        tokio::task::spawn_blocking(move || cache.write_blocking(&uri, mtime, image))
+            .await
+            .context("Error en tarea de escritura de miniatura")?
+    }
+
+    fn write_blocking(&self, uri: &str, mtime: i64, image: Vec<u8>) -> Result<()> {
+        use gtk4::gdk_pixbuf::{InterpType, Pixbuf};
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+        let pixbuf = Pixbuf::from_read(std::io::Cursor::new(image)).context("Imagen de miniatura ilegible")?;
+        let mtime = mtime.to_string();
+        let options = [
+            ("tEXt::Thumb::URI", uri),
+            ("tEXt::Thumb::MTime", mtime.as_str()),
+            ("tEXt::Software", "G-DriveXP"),
+        ];
+
+        for (size, max_side) in SIZES {
+            let (width, height) = (pixbuf.width(), pixbuf.height());
+            // Solo se reduce: la especificación no amplía imágenes pequeñas
+            let scaled = if width > max_side || height > max_side {
+                let scale = max_side as f64 / width.max(height) as f64;
+                let (w, h) = (((width as f64 * scale).round() as i32).max(1), ((height as f64 * scale).round() as i32).max(1));
+                pixbuf.scale_simple(w, h, InterpType::Bilinear).context("Error escalando miniatura")?
+            } else {
+                pixbuf.clone()
+            };
+
+            let path = self.path_for(size, uri);
+            let dir = path.parent().expect("ruta con directorio");
+            std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+            // Temporal + rename: Nautilus nunca lee una miniatura a medias
+            let tmp = dir.join(format!(".gdrivexp-{}.png", uuid::Uuid::new_v4()));
+            scaled.savev(&tmp, "png", &options).context("Error guardando miniatura")?;
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+            if let Err(e) = std::fs::rename(&tmp, &path) {
+                let _ = std::fs::remove_file(&tmp);
+                return Err(e.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// URI `file://` de una ruta, escapada como la calcula GLib (y Nautilus)
+pub fn file_uri(path: &Path) -> Result<String> {
+    Ok(gtk4::glib::filename_to_uri(path, None)?.to_string())
+}
+
+/// `thumbnailLink` con el tamaño pedido (Drive entrega `=s220` por defecto)
+fn sized_link(link: &str, max_side: i32) -> String {
+    match link.rsplit_once("=s") {
+        Some((base, size)) if size.bytes().all(|b| b.is_ascii_digit()) => format!("{}=s{}", base, max_side),
+        _ => format!("{}=s{}", link, max_side),
+    }
+}
+
+/// Descarga de miniaturas de Drive para los directorios más usados
+pub struct ThumbnailFetcher {
+    db: Arc<MetadataRepository>,
+    client: Arc<dyn DriveApi>,
+    /// Raíces en las que el usuario ve los archivos (espejo y montaje FUSE)
+    roots: Vec<PathBuf>,
+    cache: ThumbnailCache,
+    interval: Duration,
+    /// La caché de contenido está cifrada: no se escriben miniaturas en claro
+    encrypted_cache: bool,
+}
+
+impl ThumbnailFetcher {
+    pub fn new(
+        db: Arc<MetadataRepository>,
+        client: Arc<dyn DriveApi>,
+        mirror_path: impl AsRef<Path>,
+        fuse_mount_path: impl AsRef<Path>,
+        interval_secs: u64,
+    ) -> Self {
+        Self {
+            db,
+            client,
+            roots: vec![mirror_path.as_ref().to_path_buf(), fuse_mount_path.as_ref().to_path_buf()],
+            cache: ThumbnailCache::new(crate::utils::paths::thumbnails_dir()),
+            interval: Duration::from_secs(interval_secs),
+            encrypted_cache: false,
+        }
+    }
+
+    /// Con la caché cifrada no se escriben miniaturas (serían texto plano)
+    pub fn with_encrypted_cache(mut self, encrypted: bool) -> Self {
+        self.encrypted_cache = encrypted;
+        self
+    }
+
+    /// Caché de miniaturas distinta de la del usuario (tests)
+    #[cfg(test)]
+    pub fn with_cache(mut self, cache: ThumbnailCache) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Inicia el loop en un task de Tokio separado
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            if self.encrypted_cache {
+                info!("🔐 Miniaturas de Drive desactivadas: la caché está cifrada y las miniaturas se guardan en claro");
+                return;
+            }
+            info!("🖼️ Miniaturas de Drive activadas (intervalo: {:?})", self.interval);
+
+            let mut delay = Duration::from_secs(STARTUP_DELAY_SECS);
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = crate::utils::shutdown::wait_for_shutdown() => {
+                        info!("🛑 Miniaturas: Shutdown detectado, deteniendo.");
+                        break;
+                    }
+                }
+                delay = self.interval;
+
+                if !crate::utils::network::transfers_allowed() {
+                    continue;
+                }
+                match self.fetch_once().await {
+                    Ok(written) if written > 0 => info!("🖼️ {} miniaturas de Drive escritas", written),
+                    Ok(_) => debug!("🖼️ Miniaturas al día"),
+                    Err(e) => warn!("⚠️ Error descargando miniaturas: {:?}", e),
+                }
+            }
+        })
+    }
+
+    /// Escribe las miniaturas que falten o estén desactualizadas.
+    /// Retorna el número de archivos con miniatura nueva.
+    pub async fn fetch_once(&self) -> Result<usize> {
+        if self.encrypted_cache {
+            return Ok(0);
+        }
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as i64;
+        let candidates = self.db.list_thumbnail_candidates(now - ACCESS_WINDOW_SECS, MAX_HOT_DIRS).await?;
+
+        let mut written = 0;
+        for candidate in &candidates {
+            if written >= MAX_PER_ROUND
+                || crate::utils::shutdown::is_shutdown_requested()
+                || !crate::utils::network::transfers_allowed()
+            {
+                break;
+            }
+
+            // La URI cambia con cada renombrado o movimiento: se comprueba la ruta actual
+            let Some(relative) = self.db.resolve_inode_to_relative_path(candidate.inode).await? else {
+                continue;
+            };
+            let uris = self.roots
+                .iter()
+                .map(|root| file_uri(&root.join(&relative)))
+                .collect::<Result<Vec<_>>>()?;
+            if candidate.thumbnail_mtime == Some(candidate.mtime) && uris.iter().all(|uri| self.cache.contains(uri)) {
+                continue;
+            }
+
+            let image = match self.download(candidate).await {
+                Ok(Some(image)) => image,
+                Ok(None) => continue,
+                Err(e) if crate::utils::network::report_error(&e) => break,
+                Err(e) => {
+                    debug!("No se pudo descargar la miniatura de inode {}: {:?}", candidate.inode, e);
+                    continue;
+                }
+            };
+            for uri in &uris {
+                if let Err(e) = self.cache.write(uri, candidate.mtime, image.clone()).await {
+                    debug!("No se pudo escribir la miniatura de {}: {:?}", uri, e);
+                }
+            }
+            self.db.set_thumbnail_mtime(candidate.inode, candidate.mtime).await?;
+            written += 1;
+        }
+
+        Ok(written)
+    }
+
+    /// Imagen de la miniatura, renovando el enlace si caducó.
+    /// None si Drive ya no tiene miniatura del archivo.
+    async fn download(&self, candidate: &ThumbnailCandidate) -> Result<Option<Vec<u8>>> {
+        let max_side = SIZES[SIZES.len() - 1].1;
+        match self.client.download_thumbnail(&sized_link(&candidate.link, max_side)).await {
+            Ok(image) => return Ok(Some(image)),
+            Err(e) if crate::utils::network::is_network_error(&e) => return Err(e),
+            Err(e) => debug!("🖼️ Enlace de miniatura caducado para inode {}: {}", candidate.inode, e),
+        }
+
+        let fresh = self.client.get_thumbnail_link(&candidate.gdrive_id).await?;
+        self.db.set_thumbnail_link(candidate.inode, fresh.as_deref()).await?;
+        match fresh {
+            Some(link) => Ok(Some(self.client.download_thumbnail(&sized_link(&link, max_side)).await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gdrive::DriveError;
+    use crate::gdrive::mock::{MockDriveClient, MOCK_ROOT_ID};
+    use rstest::*;
+
+    #[rstest]
+    #[case::default_size("https://lh3.example/abc=s220", "https://lh3.example/abc=s256")]
+    #[case::without_size("https://lh3.example/abc", "https://lh3.example/abc=s256")]
+    #[case::not_a_size("https://lh3.example/a=sb", "https://lh3.example/a=sb=s256")]
+    fn test_sized_link(#[case] link: &str, #[case] expected: &str) {
+        assert_eq!(sized_link(link, 256), expected);
+    }
+
+    #[test]
+    fn test_path_for_uses_md5_of_uri() {
+        let cache = ThumbnailCache::new("/home/ana/.cache/thumbnails");
+        let uri = file_uri(Path::new("/home/ana/GoogleDrive/Fotos de 2024/playa.jpg")).unwrap();
+        assert_eq!(uri, "file:///home/ana/GoogleDrive/Fotos%20de%202024/playa.jpg");
+        // md5 calculado con `printf %s "$uri" | md5sum`
+        assert_eq!(
+            cache.path_for("large", "file:///home/ana/foto.png"),
+            PathBuf::from("/home/ana/.cache/thumbnails/large/8faecfd4ef179b3641fcd0fcacf36522.png")
+        );
+    }
+
+    fn png(width: i32, height: i32) -> Vec<u8> {
+        let pixbuf = gtk4::gdk_pixbuf::Pixbuf::new(gtk4::gdk_pixbuf::Colorspace::Rgb, false, 8, width, height).unwrap();
+        pixbuf.fill(0x3366ccff);
+        pixbuf.save_to_bufferv("png", &[]).unwrap()
+    }
+
+    /// Una foto con miniatura en Drive, en el root (directorio abierto por el usuario)
+    async fn fixture() -> (tempfile::TempDir, Arc<MetadataRepository>, Arc<MockDriveClient>, ThumbnailFetcher, u64) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(MetadataRepository::in_memory().await.unwrap());
+        let mock = Arc::new(MockDriveClient::new());
+        mock.add_file("file-foto", "foto.jpg", MOCK_ROOT_ID, b"jpeg");
+        mock.set_thumbnail("file-foto", &png(400, 300));
+
+        let inode = db.get_or_create_inode("file-foto").await.unwrap();
+        db.upsert_file_metadata(inode, 4, 1_700_000_000, 0o644, false, Some("image/jpeg"), true, false, true).await.unwrap();
+        db.upsert_dentry(1, inode, "foto.jpg").await.unwrap();
+        db.set_thumbnail_link(inode, mock.file("file-foto").unwrap().thumbnail_link.as_deref()).await.unwrap();
+        db.record_dir_access(1).await.unwrap();
+
+        let fetcher = ThumbnailFetcher::new(db.clone(), mock.clone(), dir.path().join("GoogleDrive"), dir.path().join("FUSE_Mount"), 60)
+            .with_cache(ThumbnailCache::new(dir.path().join("thumbnails")));
+        (dir, db, mock, fetcher, inode)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_once_writes_thumbnails_for_mirror_and_mount() {
+        let _lock = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        let (dir, _db, mock, fetcher, _) = fixture().await;
+
+        assert_eq!(fetcher.fetch_once().await.unwrap(), 1);
+
+        let cache = ThumbnailCache::new(dir.path().join("thumbnails"));
+        for root in ["GoogleDrive", "FUSE_Mount"] {
+            let uri = file_uri(&dir.path().join(root).join("foto.jpg")).unwrap();
+            assert!(cache.contains(&uri), "sin miniatura para {}", uri);
+            let large = gtk4::gdk_pixbuf::Pixbuf::from_file(cache.path_for("large", &uri)).unwrap();
+            assert_eq!((large.width(), large.height()), (256, 192));
+            assert_eq!(large.option("tEXt::Thumb::URI").as_deref(), Some(uri.as_str()));
+            assert_eq!(large.option("tEXt::Thumb::MTime").as_deref(), Some("1700000000"));
+        }
+
+        // Ya al día: no se vuelve a descargar
+        assert_eq!(fetcher.fetch_once().await.unwrap(), 0);
+        assert_eq!(mock.calls("download_thumbnail"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_once_skips_with_encrypted_cache() {
+        let _lock = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        let (dir, _db, mock, fetcher, _) = fixture().await;
+        let fetcher = fetcher.with_encrypted_cache(true);
+
+        assert_eq!(fetcher.fetch_once().await.unwrap(), 0);
+
+        assert_eq!(mock.calls("download_thumbnail"), 0);
+        assert!(!dir.path().join("thumbnails").exists(), "no se escribe nada en claro");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_once_refreshes_expired_link() {
+        let _lock = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        let (_dir, db, mock, fetcher, inode) = fixture().await;
+        mock.set_thumbnail("file-foto", &png(64, 64));
+        mock.fail_next_with("download_thumbnail", DriveError::NotFound("404 enlace caducado".into()));
+
+        assert_eq!(fetcher.fetch_once().await.unwrap(), 1);
+
+        assert_eq!(mock.calls("get_thumbnail_link"), 1);
+        let candidates = db.list_thumbnail_candidates(0, 10).await.unwrap();
+        assert_eq!(Some(candidates[0].link.clone()), mock.file("file-foto").unwrap().thumbnail_link);
+        assert_eq!(candidates[0].thumbnail_mtime, Some(1_700_000_000));
+        assert_eq!(candidates[0].inode, inode);
+    }
+}
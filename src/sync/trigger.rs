@@ -0,0 +1,87 @@
+//! Sincronización inmediata a petición del usuario
+//!
+//! "Sincronizar ahora" (menú de la bandeja, ventana, IPC `ForceSync`) no espera
+//! al próximo intervalo ni a la franja de `sync::schedule`: syncer y uploader esperan entre ciclos también en
+//! `subscribe().changed()`, ejecutan un ciclo al recibir la petición y
+//! reinician su backoff. Es un contador en un canal `watch`, así que una
+//! petición hecha durante un ciclo no se pierde: se atiende al terminarlo.
+//!
+//! "Reconstruir metadatos" usa el mismo despertador: marca la petición y el
+//! syncer, al empezar el ciclo, la consume y compara todo Drive con la DB.
+
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::watch;
+
+/// Canal de peticiones "Sincronizar ahora". El proceso usa una sola instancia
+/// (`request_sync_now`/`subscribe`); los tests crean la suya para no recibir
+/// las peticiones de otros tests en paralelo.
+#[derive(Debug)]
+pub struct SyncTrigger {
+    /// Número de peticiones recibidas (solo importa que cambie)
+    tx: watch::Sender<u64>,
+}
+
+impl Default for SyncTrigger {
+    fn default() -> Self {
+        Self { tx: watch::channel(0).0 }
+    }
+}
+
+impl SyncTrigger {
+    /// Pide un ciclo inmediato de sincronización remota y de subida
+    pub fn request(&self) {
+        self.tx.send_modify(|count| *count = count.wrapping_add(1));
+        tracing::info!("⚡ Sincronización inmediata solicitada");
+    }
+
+    /// Receptor para esperar la próxima petición (ignora las anteriores)
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.tx.subscribe()
+    }
+}
+
+static SYNC_NOW: LazyLock<SyncTrigger> = LazyLock::new(SyncTrigger::default);
+
+/// Pide un ciclo inmediato de sincronización remota y de subida
+pub fn request_sync_now() {
+    SYNC_NOW.request();
+}
+
+/// Receptor para esperar la próxima petición del proceso
+pub fn subscribe() -> watch::Receiver<u64> {
+    SYNC_NOW.subscribe()
+}
+
+/// Hay una reconstrucción de metadatos pendiente
+static REBUILD_METADATA: AtomicBool = AtomicBool::new(false);
+
+/// Pide al syncer una reconstrucción de metadatos en su próximo ciclo (inmediato)
+pub fn request_rebuild_metadata() {
+    REBUILD_METADATA.store(true, Ordering::SeqCst);
+    tracing::info!("🧰 Reconstrucción de metadatos solicitada");
+    request_sync_now();
+}
+
+/// Consume la petición de reconstrucción, si la hay
+pub fn take_rebuild_request() -> bool {
+    REBUILD_METADATA.swap(false, Ordering::SeqCst)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_wakes_subscribers_once() {
+        let trigger = SyncTrigger::default();
+        trigger.request();
+        let mut rx = trigger.subscribe();
+        assert!(!rx.has_changed().unwrap(), "las peticiones previas no cuentan");
+
+        trigger.request();
+        trigger.request();
+        tokio::time::timeout(std::time::Duration::from_secs(1), rx.changed()).await.unwrap().unwrap();
+        assert!(!rx.has_changed().unwrap(), "varias peticiones seguidas = un ciclo");
+    }
+}
@@ -0,0 +1,121 @@
+//! Progreso de las subidas en curso por inode
+//!
+//! El uploader registra aquí cada archivo que sube y lo actualiza desde el
+//! callback de progreso de `DriveApi`. IPC lo consulta para reportar
+//! `SyncStatus::Uploading(percent)` en vez del `Syncing` genérico. Con
+//! `with_status_events` avisa de cada punto porcentual ganado (y del fin de la
+//! subida) para que D-Bus emita `StatusChanged` sin saturar el canal.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::broadcast;
+
+/// Subidas en curso: inode → (bytes enviados, total)
+#[derive(Debug, Clone, Default)]
+pub struct UploadProgress {
+    uploads: Arc<Mutex<HashMap<u64, (u64, u64)>>>,
+    status_tx: Option<broadcast::Sender<u64>>,
+}
+
+/// Mantiene registrada la subida de un inode mientras vive
+pub struct UploadGuard {
+    progress: UploadProgress,
+    inode: u64,
+}
+
+impl UploadProgress {
+    /// Notifica por `tx` (inodes) los cambios de porcentaje
+    pub fn with_status_events(mut self, tx: broadcast::Sender<u64>) -> Self {
+        self.status_tx = Some(tx);
+        self
+    }
+
+    /// Registra la subida de `inode` (`total` bytes) hasta soltar el guard
+    pub fn start(&self, inode: u64, total: u64) -> UploadGuard {
+        lock(&self.uploads).insert(inode, (0, total));
+        self.notify(inode);
+        UploadGuard { progress: self.clone(), inode }
+    }
+
+    /// Actualiza lo enviado de `inode`. Solo avisa si cambia el porcentaje
+    pub fn update(&self, inode: u64, sent: u64, total: u64) {
+        let changed = match lock(&self.uploads).get_mut(&inode) {
+            Some(entry) => {
+                let before = percent(entry.0, entry.1);
+                *entry = (sent, total);
+                before != percent(sent, total)
+            }
+            None => false,
+        };
+        if changed {
+            self.notify(inode);
+        }
+    }
+
+    /// Porcentaje enviado si `inode` se está subiendo
+    pub fn percent(&self, inode: u64) -> Option<u8> {
+        lock(&self.uploads).get(&inode).map(|&(sent, total)| percent(sent, total))
+    }
+
+    fn notify(&self, inode: u64) {
+        if let Some(tx) = &self.status_tx {
+            // Error = sin receptores activos, no es relevante
+            let _ = tx.send(inode);
+        }
+    }
+}
+
+impl Drop for UploadGuard {
+    fn drop(&mut self) {
+        lock(&self.progress.uploads).remove(&self.inode);
+        self.progress.notify(self.inode);
+    }
+}
+
+/// Porcentaje entero de `sent` sobre `total` (un archivo vacío aún no está al 100%)
+fn percent(sent: u64, total: u64) -> u8 {
+    if total == 0 {
+        return 0;
+    }
+    (sent.min(total) * 100 / total) as u8
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::empty(0, 0, 0)]
+    #[case::start(0, 1000, 0)]
+    #[case::partial(455, 1000, 45)]
+    #[case::done(1000, 1000, 100)]
+    #[case::overshoot(1200, 1000, 100)]
+    fn test_percent(#[case] sent: u64, #[case] total: u64, #[case] expected: u8) {
+        assert_eq!(percent(sent, total), expected);
+    }
+
+    #[test]
+    fn test_guard_tracks_upload_and_notifies_percent_changes() {
+        let (tx, mut rx) = broadcast::channel(16);
+        let progress = UploadProgress::default().with_status_events(tx);
+
+        let guard = progress.start(7, 1000);
+        assert_eq!(progress.percent(7), Some(0));
+        progress.update(7, 5, 1000);
+        progress.update(7, 500, 1000);
+        assert_eq!(progress.percent(7), Some(50));
+        drop(guard);
+
+        assert_eq!(progress.percent(7), None);
+        let events: Vec<u64> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(events, vec![7, 7, 7], "inicio, 50% y fin; el 0,5% no cambia el porcentaje");
+
+        progress.update(7, 1000, 1000);
+        assert_eq!(progress.percent(7), None, "sin guard no se registra");
+    }
+}
@@ -7,34 +7,66 @@ use anyhow::{Context, Result};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::sleep;
 use tracing::{debug, error, info, warn};
 use futures::stream::{self, StreamExt};
 
 use crate::db::MetadataRepository;
-use crate::gdrive::client::DriveClient;
+use crate::fuse::file_locks::FileLocks;
+use crate::fuse::inflight::InflightDownloads;
+use crate::fuse::shortcuts;
+use crate::gdrive::{DriveApi, DriveError};
+use crate::gdrive::api::ProgressCallback;
+use crate::gdrive::id_pool::is_temp_id;
+use crate::sync::schedule::Schedule;
+use crate::sync::upload_progress::UploadProgress;
+use crate::utils::ignore::IgnoreRules;
+use crate::utils::quota::StorageQuota;
+
+/// Tamaño de cada descarga al completar una caché parcial antes de subirla
+const HYDRATE_CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Subdirectorio de `cache_dir` con las copias que se están subiendo
+const UPLOAD_SNAPSHOT_DIR: &str = ".uploads";
 
 /// Intervalo máximo de backoff en segundos
 const MAX_BACKOFF_SECS: u64 = 300;
 
+/// Espera tras el primer fallo de un archivo; se duplica en cada reintento
+const RETRY_BASE_SECS: u64 = 30;
+
+/// Espera máxima entre reintentos de un mismo archivo
+const MAX_RETRY_BACKOFF_SECS: u64 = 3600;
+
+use crate::i18n::t;
 use crate::gui::history::{ActionHistory, ActionType, TransferOp};
 
 /// Uploader en background que sube archivos dirty a Google Drive
 pub struct Uploader {
     db: Arc<MetadataRepository>,
-    client: Arc<DriveClient>,
+    client: Arc<dyn DriveApi>,
     interval: Duration,
     cache_dir: std::path::PathBuf,
     mirror_path: std::path::PathBuf,
     history: ActionHistory,
     root_id: String,
+    status_tx: Option<tokio::sync::broadcast::Sender<u64>>,
+    ignore_rules: Arc<IgnoreRules>,
+    schedule: Schedule,
+    /// Descargas en curso de FUSE y el prefetcher (`GDriveFS::with_inflight`)
+    inflight: Arc<InflightDownloads>,
+    /// Locks de caché compartidos con FUSE (`GDriveFS::with_file_locks`)
+    file_locks: FileLocks,
+    /// Porcentaje de las subidas en curso (lo consulta IPC)
+    upload_progress: UploadProgress,
+    /// Almacenamiento de Drive agotado, compartido con FUSE y la GUI
+    storage_quota: StorageQuota,
 }
 
 impl Uploader {
     /// Crea un nuevo uploader
     pub fn new(
         db: Arc<MetadataRepository>,
-        client: Arc<DriveClient>,
+        client: Arc<dyn DriveApi>,
         interval_secs: u64,
         cache_dir: impl AsRef<Path>,
         mirror_path: impl AsRef<Path>,
@@ -49,6 +81,74 @@ impl Uploader {
             mirror_path: mirror_path.as_ref().to_path_buf(),
             history,
             root_id,
+            status_tx: None,
+            ignore_rules: Arc::new(IgnoreRules::default()),
+            schedule: Schedule::Always,
+            inflight: Arc::new(InflightDownloads::default()),
+            file_locks: FileLocks::default(),
+            upload_progress: UploadProgress::default(),
+            storage_quota: StorageQuota::default(),
+        }
+    }
+
+    /// Publica los inodes cuyo estado de sincronización cambia (ej: señal D-Bus `StatusChanged`)
+    pub fn with_status_events(mut self, tx: tokio::sync::broadcast::Sender<u64>) -> Self {
+        self.status_tx = Some(tx);
+        self
+    }
+
+    /// Archivos que nunca se crean en Drive (`Config.ignore_patterns`)
+    pub fn with_ignore_rules(mut self, rules: Arc<IgnoreRules>) -> Self {
+        self.ignore_rules = rules;
+        self
+    }
+
+    /// Franja en la que corren los ciclos automáticos (`Config.sync_schedule`)
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Archivos en hidratación, que no se suben hasta completar su descarga
+    pub fn with_inflight(mut self, inflight: Arc<InflightDownloads>) -> Self {
+        self.inflight = inflight;
+        self
+    }
+
+    /// Completa cachés parciales bajo los mismos locks por inode que FUSE
+    pub fn with_file_locks(mut self, file_locks: FileLocks) -> Self {
+        self.file_locks = file_locks;
+        self
+    }
+
+    /// Registro donde se publica el progreso de cada subida
+    pub fn with_upload_progress(mut self, upload_progress: UploadProgress) -> Self {
+        self.upload_progress = upload_progress;
+        self
+    }
+
+    /// Estado de almacenamiento lleno que consultan FUSE y la GUI
+    pub fn with_storage_quota(mut self, storage_quota: StorageQuota) -> Self {
+        self.storage_quota = storage_quota;
+        self
+    }
+
+    /// Callback de subida de `inode`: avanza el transfer del panel de actividad
+    /// y el porcentaje que reporta IPC
+    fn progress_callback(&self, inode: u64, transfer_id: u64) -> ProgressCallback {
+        let history = self.history.clone();
+        let upload_progress = self.upload_progress.clone();
+        Box::new(move |sent, total| {
+            history.update_transfer_progress(transfer_id, sent);
+            upload_progress.update(inode, sent, total);
+        })
+    }
+
+    /// Notifica un cambio de estado a los suscriptores (si los hay)
+    fn notify_status_change(&self, inode: u64) {
+        if let Some(tx) = &self.status_tx {
+            // Error = sin receptores activos, no es relevante
+            let _ = tx.send(inode);
         }
     }
 
@@ -56,8 +156,13 @@ impl Uploader {
     pub fn spawn(self) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!("📤 Uploader iniciado (intervalo: {:?})", self.interval);
+            // Copias de subidas que un cierre brusco dejó a medias
+            let _ = tokio::fs::remove_dir_all(self.cache_dir.join(UPLOAD_SNAPSHOT_DIR)).await;
             
             let mut current_backoff = self.interval;
+            let mut sync_now = crate::sync::trigger::subscribe();
+            // Fuera de la franja, el primer ciclo también espera
+            self.schedule.wait_next_cycle(Duration::ZERO, &mut sync_now).await;
 
             loop {
                 if crate::utils::shutdown::is_shutdown_requested() {
@@ -65,6 +170,17 @@ impl Uploader {
                     break;
                 }
 
+                // Sin conexión (o en conexión medida) los cambios quedan en cola (dirty)
+                // y se suben al reconectar
+                if !crate::utils::network::transfers_allowed() {
+                    tokio::select! {
+                        _ = crate::utils::network::wait_for_transfers_allowed() => {}
+                        _ = crate::utils::shutdown::wait_for_shutdown() => {}
+                    }
+                    current_backoff = self.interval;
+                    continue;
+                }
+
                 match self.upload_cycle().await {
                     Ok(uploaded_count) => {
                         if uploaded_count > 0 {
@@ -73,6 +189,14 @@ impl Uploader {
                         // Reset backoff en caso de éxito
                         current_backoff = self.interval;
                     }
+                    Err(e) if DriveError::find(&e).is_some_and(DriveError::is_rate_limited) => {
+                        // Límite de peticiones: esperar lo que pida Drive (o el doble del backoff)
+                        let retry_after = DriveError::find(&e).and_then(DriveError::retry_after);
+                        current_backoff = retry_after
+                            .unwrap_or(current_backoff * 2)
+                            .min(Duration::from_secs(MAX_BACKOFF_SECS));
+                        warn!("🐢 Límite de peticiones de Drive: próximo ciclo de upload en {:?}", current_backoff);
+                    }
                     Err(e) => {
                         error!("❌ Error en ciclo de upload: {:?}", e);
                         
@@ -85,7 +209,10 @@ impl Uploader {
                     }
                 }
                 
-                sleep(current_backoff).await;
+                // "Sincronizar ahora": ciclo inmediato con el backoff reiniciado
+                if self.schedule.wait_next_cycle(current_backoff, &mut sync_now).await {
+                    current_backoff = self.interval;
+                }
             }
         })
     }
@@ -93,37 +220,81 @@ impl Uploader {
     /// Ejecuta un ciclo de upload
     /// Retorna el número de archivos subidos
     async fn upload_cycle(&self) -> Result<usize> {
-        // 1. Obtener archivos dirty de FUSE
+        // 1. Obtener archivos dirty de FUSE, en el orden del diario de operaciones
         let dirty_files = self.get_dirty_files().await?;
+        let dirty_count = dirty_files.len();
+        let batches = plan_upload_batches(dirty_files, &self.db.pending_upload_ops().await?);
         
         let mut uploaded_count = 0;
+        let mut rate_limited = None;
         
-        // 2. Procesar archivos FUSE
-        let upload_results = stream::iter(dirty_files)
-            .map(|(inode, gdrive_id, is_delete)| async move {
-                let res = self.upload_file(inode, &gdrive_id, is_delete).await;
-                (inode, res)
-            })
-            .buffer_unordered(4) // Concurrencia máxima de 4
-            .collect::<Vec<_>>()
-            .await;
-
-        for (inode, result) in upload_results {
-            match result {
-                Ok(()) => {
-                    uploaded_count += 1;
-                }
-                Err(e) => {
-                    if e.to_string().contains("DEFERRED_PARENT_TEMP") {
-                        debug!("⏳ Inode {} aplazado: directorio padre aún no sincronizado", inode);
-                    } else {
-                        warn!("Error subiendo inode {}: {:?}", inode, e);
+        // 2. Procesar archivos FUSE: cada lote termina antes de empezar el siguiente
+        for batch in batches {
+            let barrier = batch.iter().any(|file| file.structural);
+            let upload_results = stream::iter(batch)
+                .map(|file| self.process_dirty_file(file))
+                .buffer_unordered(4) // Concurrencia máxima de 4
+                .collect::<Vec<_>>()
+                .await;
+
+            let barrier_failed = barrier && upload_results.iter().any(|(_, result)| result.is_err());
+            for (inode, result) in upload_results {
+                match result {
+                    Ok(()) => {
+                        uploaded_count += 1;
+                    }
+                    Err(e) => {
+                        if let Some(drive_error) = DriveError::find(&e).filter(|d| d.is_rate_limited()) {
+                            // No es culpa del archivo: queda en cola sin gastar reintentos
+                            debug!("🐢 Inode {} queda en cola: límite de peticiones de Drive", inode);
+                            rate_limited = rate_limited.max(Some(drive_error.retry_after()));
+                        } else if e.to_string().contains("DEFERRED_PARENT_TEMP") {
+                            debug!("⏳ Inode {} aplazado: directorio padre aún no sincronizado", inode);
+                        } else if e.to_string().contains("DEFERRED_PARENT_DELETE") {
+                            debug!("⏳ Inode {} aplazado: carpeta padre pendiente de papelera", inode);
+                        } else if e.to_string().contains("DEFERRED_HYDRATION") {
+                            debug!("⏳ Inode {} aplazado: su caché aún se está descargando", inode);
+                        } else if e.is::<crate::utils::network::MeteredPause>() {
+                            debug!("⏸️ Inode {} queda en cola: conexión medida", inode);
+                        } else if crate::utils::network::report_error(&e) {
+                            debug!("📴 Inode {} queda en cola: sin conexión", inode);
+                        } else {
+                            warn!("Error subiendo inode {}: {:?}", inode, e);
+                            self.record_upload_error(inode, &e).await;
+                        }
                     }
                 }
             }
+
+            // Los lotes siguientes dependen del orden: quedan para el próximo ciclo
+            if rate_limited.is_some() {
+                break;
+            }
+            // Sin el renombrado o la eliminación, los padres de los lotes
+            // siguientes pueden faltar o estar desfasados en Drive
+            if barrier_failed {
+                debug!("⏳ Falló una barrera del diario: el resto de lotes queda para el próximo ciclo");
+                break;
+            }
+        }
+
+        if let Err(e) = self.db.prune_upload_ops().await {
+            debug!("No se pudo depurar el diario de operaciones: {:?}", e);
+        }
+
+        // Drive pide frenar: el ciclo termina aquí y el bucle espera `retry_after`
+        if let Some(retry_after) = rate_limited {
+            return Err(DriveError::RateLimited {
+                retry_after,
+                message: format!("{} archivos en cola", dirty_count.saturating_sub(uploaded_count)),
+            }
+            .into());
         }
         
         // 3. Procesar archivos de Local Sync
+        if !crate::utils::network::transfers_allowed() {
+            return Ok(uploaded_count);
+        }
         match self.upload_local_sync_files().await {
             Ok(count) => uploaded_count += count,
             Err(e) => {
@@ -138,22 +309,81 @@ impl Uploader {
         Ok(uploaded_count)
     }
 
+    /// Pasa el inode al estado `error`, programa su próximo reintento con
+    /// backoff exponencial (o ninguno si el error es permanente) y lo registra
+    /// en la actividad reciente (solo la primera vez que aparece cada mensaje)
+    async fn record_upload_error(&self, inode: u64, error: &anyhow::Error) {
+        let message = &format!("{:#}", error);
+        let attempt = match self.db.sync_state(inode).await {
+            Ok(Some(state)) => state.retry_count + 1,
+            _ => 1,
+        };
+        let permanent = is_permanent_upload_error(error);
+        let retry_at = (!permanent).then(|| {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            (now + retry_backoff(attempt)).as_secs() as i64
+        });
+
+        // Almacenamiento lleno: FUSE deja de aceptar datos nuevos hasta que haya espacio
+        if DriveError::find(error).is_some_and(DriveError::is_storage_full)
+            && self.storage_quota.mark_full(inode)
+        {
+            self.history.log(ActionType::Error, t!("activity-storage-full"));
+        }
+
+        if permanent {
+            warn!("⛔ Inode {} con error permanente, no se reintentará: {}", inode, message);
+        } else {
+            info!("🔁 Inode {} reintentará en {:?} (intento {})", inode, retry_backoff(attempt), attempt);
+        }
+
+        match self.db.set_upload_error(inode, message, retry_at).await {
+            Ok(true) => {
+                let name = self.get_file_name(inode).await.unwrap_or_else(|_| format!("inode {}", inode));
+                let path = self.mirror_file_path(inode).await;
+                self.history.log_file(ActionType::Error, t!("activity-upload-error", name = name.as_str(), error = message.as_str()), path);
+            }
+            Ok(false) => {}
+            Err(e) => debug!("No se pudo registrar el error de inode {}: {:?}", inode, e),
+        }
+        self.notify_status_change(inode);
+    }
+
     /// Obtiene la lista de archivos dirty desde la base de datos
     async fn get_dirty_files(&self) -> Result<Vec<(u64, String, bool)>> {
-        let rows = sqlx::query_as::<_, (i64, String, Option<i64>)>(
-            "SELECT i.inode, i.gdrive_id, s.deleted_at 
-             FROM inodes i 
-             INNER JOIN sync_state s ON i.inode = s.inode 
-             WHERE s.dirty = 1"
-        )
-        .fetch_all(self.db.pool())
-        .await?;
-        
-        Ok(rows.into_iter()
-            .map(|(inode, gdrive_id, deleted_at)| {
-                (inode as u64, gdrive_id, deleted_at.is_some())
-            })
-            .collect())
+        self.db.list_dirty_inodes().await
+    }
+
+    /// Sube un archivo dirty y, si termina bien, consume sus operaciones del
+    /// diario hasta la última que vio el ciclo
+    async fn process_dirty_file(&self, file: DirtyFile) -> (u64, Result<()>) {
+        let DirtyFile { inode, gdrive_id, is_delete, last_seq, .. } = file;
+        // Conexión perdida a mitad de ciclo: el resto queda en cola sin gastar reintentos
+        if !crate::utils::network::is_online() {
+            return (inode, Err(crate::utils::network::Offline.into()));
+        }
+        if crate::utils::network::is_metered_paused() {
+            return (inode, Err(crate::utils::network::MeteredPause.into()));
+        }
+        // Marcar como "subiendo" para que IPC reporte Syncing
+        if let Err(e) = self.db.set_uploading(inode, true).await {
+            debug!("No se pudo marcar inode {} como uploading: {:?}", inode, e);
+        }
+        self.notify_status_change(inode);
+        let res = self.upload_file(inode, &gdrive_id, is_delete).await;
+        if let Err(e) = self.db.set_uploading(inode, false).await {
+            debug!("No se pudo desmarcar inode {} como uploading: {:?}", inode, e);
+        }
+        if res.is_ok()
+            && let Some(seq) = last_seq
+            && let Err(e) = self.db.consume_upload_ops(inode, seq).await
+        {
+            debug!("No se pudo consumir el diario de inode {}: {:?}", inode, e);
+        }
+        self.notify_status_change(inode);
+        (inode, res)
     }
 
     /// Sube un archivo individual a Google Drive
@@ -162,7 +392,7 @@ impl Uploader {
         if let Ok(name) = self.get_file_name(inode).await {
             if name == ".hidden" || name == ".gdrivexp_hidden_manifest" {
                 info!("⏭️ Uploader: ignorando archivo de control interno '{}' (inode={}), limpiando dirty", name, inode);
-                self.db.clear_dirty_and_bubble(inode).await?;
+                self.db.clear_dirty(inode).await?;
                 return Ok(());
             }
         }
@@ -174,45 +404,70 @@ impl Uploader {
 
         // Caso 2: Archivo nuevo o modificado
 
-        // Verificar si es un archivo temporal (recién creado)
-        let is_temp = gdrive_id.starts_with("temp_");
+        // Verificar si es un archivo recién creado que aún no existe en Drive
+        let is_new = self.db.is_pending_create(inode).await?;
+
+        // Encolado antes de que existieran los patrones (o antes de cambiarlos)
+        if is_new && self.matches_ignore_rules(inode).await? {
+            info!("🙈 Uploader: inode {} coincide con ignore_patterns, queda solo en local", inode);
+            self.db.mark_ignored(inode).await?;
+            return Ok(());
+        }
         
-        if is_temp {
+        if is_new {
             // Archivo nuevo: crear en GDrive
             self.create_file(inode, gdrive_id).await
+        } else if self.inflight.is_hydrating(inode) {
+            // Con la caché a medias se subiría un archivo truncado: esperar a la descarga
+            anyhow::bail!("DEFERRED_HYDRATION")
         } else {
             // Archivo existente: actualizar en GDrive
             self.update_file(inode, gdrive_id).await
         }
     }
 
-    /// Crea un nuevo archivo en Google Drive
-    async fn create_file(&self, inode: u64, temp_gdrive_id: &str) -> Result<()> {
+    /// Crea un nuevo archivo en Google Drive. Si `gdrive_id` es un ID
+    /// reservado (`files.generateIds`) Drive lo usa tal cual: inode y caché
+    /// conservan su nombre. Con un `temp_<uuid>` Drive asigna uno nuevo.
+    async fn create_file(&self, inode: u64, gdrive_id: &str) -> Result<()> {
         info!("📤 Creando nuevo archivo en GDrive (inode={})", inode);
         
         // Obtener metadatos del archivo
         let attrs = self.db.get_attrs(inode).await?;
         let name = self.get_file_name(inode).await?;
         let parent_gdrive_id = self.get_parent_gdrive_id(inode).await?;
+        // "Computers" no existe en Drive: los equipos se añaden desde Drive para escritorio
+        if parent_gdrive_id == crate::db::COMPUTERS_GDRIVE_ID {
+            anyhow::bail!("Permisos insuficientes: no se puede crear contenido directamente en {}", crate::db::COMPUTERS_DIR_NAME);
+        }
     
-    if parent_gdrive_id.starts_with("temp_") {
+    if self.db.parent_pending_create(inode).await? {
         anyhow::bail!("DEFERRED_PARENT_TEMP");
     }
+
+        // Un archivo eliminado en Drive (ver `update_file`) no recupera su ID
+        let reserved_id = (self.db.is_pending_create(inode).await? && !is_temp_id(gdrive_id))
+            .then_some(gdrive_id);
         
         // Validar si es una carpeta
         if attrs.is_dir {
             // Caso carpeta: crear solo con metadatos
-            let real_gdrive_id = self.client.create_folder(
+            let create_result = self.client.create_folder(
                 &name,
                 &parent_gdrive_id,
-            ).await.context("Error creando carpeta")?;
+                reserved_id,
+            ).await;
+            let real_gdrive_id = match create_result {
+                Ok(id) => id,
+                Err(e) if self.adopt_if_created(inode, reserved_id).await => {
+                    warn!("⚠️ {:?}", e.context("Error creando carpeta"));
+                    return Ok(());
+                }
+                Err(e) => return Err(e.context("Error creando carpeta")),
+            };
 
             // Actualizar DB y retornar
-            sqlx::query("UPDATE inodes SET gdrive_id = ? WHERE inode = ?")
-                .bind(&real_gdrive_id)
-                .bind(inode as i64)
-                .execute(self.db.pool())
-                .await?;
+            self.db.mark_created(inode, &real_gdrive_id).await?;
             
             // Optimistic Locking: Verificar si el estado cambió mientras creábamos la carpeta
             let current_name = self.get_file_name(inode).await?;
@@ -222,16 +477,21 @@ impl Uploader {
                 warn!("⚠️ Modificación concurrente detectada durante creación de carpeta (inode={}). Manteniendo dirty=1.", inode);
                 // No limpiamos el flag dirty, para que el próximo ciclo procese los cambios nuevos
             } else {
-                self.db.clear_dirty_and_bubble(inode).await?;
+                self.db.clear_dirty(inode).await?;
             }
             
             info!("✅ Carpeta creada en GDrive: {} (inode={})", real_gdrive_id, inode);
-            self.history.log(ActionType::Create, format!("Carpeta creada: {}", name));
+            self.history.log(ActionType::Create, t!("activity-folder-created", name = name.as_str()));
             return Ok(());
         }
 
+        // `Informe.gdoc` vacío (plantilla de "Nuevo documento"): subirlo no sirve de nada
+        if let Some((doc_name, mime_type)) = shortcuts::workspace_template(&name).filter(|_| attrs.size == 0) {
+            return self.create_workspace_file(inode, gdrive_id, reserved_id, doc_name, mime_type, &parent_gdrive_id).await;
+        }
+
         // Ruta del archivo en caché
-        let cache_path = self.cache_dir.join(temp_gdrive_id);
+        let cache_path = self.cache_dir.join(gdrive_id);
 
         if !cache_path.exists() {
             // El archivo fue copiado directamente al directorio mirror (no a través de FUSE),
@@ -244,18 +504,17 @@ impl Uploader {
             match mirror_source {
                 Some(ref src) if src.exists() => {
                     info!("📂 Copiando desde mirror a caché antes de subir: {:?}", src);
-                    tokio::fs::copy(src, &cache_path).await
+                    let content = tokio::fs::read(src).await
+                        .context("Error leyendo archivo del mirror")?;
+                    crate::utils::cache_crypto::write_file(&cache_path, content).await
                         .context("Error copiando archivo desde mirror a caché")?;
                 }
                 _ => {
                     warn!("Archivo de caché no existe y no se encontró en mirror: {:?}", cache_path);
                     // Contenido perdido: actualizar size a 0 para mantener consistencia DB↔Drive
                     // y limpiar dirty para no reintentar indefinidamente
-                    sqlx::query("UPDATE attrs SET size = 0 WHERE inode = ?")
-                        .bind(inode as i64)
-                        .execute(self.db.pool())
-                        .await?;
-                    self.db.clear_dirty_and_bubble(inode).await?;
+                    self.db.set_size(inode, 0).await?;
+                    self.db.clear_dirty(inode).await?;
                     info!("⚠️ Contenido perdido para inode={}: dirty limpiado, size→0", inode);
                     return Ok(());
                 }
@@ -263,32 +522,38 @@ impl Uploader {
         }
         
         // Subir archivo usando la API (con tracking de progreso)
-        let file_size = tokio::fs::metadata(&cache_path).await.map(|m| m.len()).unwrap_or(0);
+        let snapshot = self.snapshot_cache(inode, &cache_path).await?;
+        let file_size = tokio::fs::metadata(&snapshot).await.map(|m| m.len()).unwrap_or(0);
         let transfer_id = self.history.start_transfer(&name, TransferOp::Upload, file_size);
-        
-        let history_clone = self.history.clone();
-        let progress_cb = Box::new(move |offset: u64| {
-            history_clone.update_transfer_progress(transfer_id, offset);
-        });
+        let _upload = self.upload_progress.start(inode, file_size);
+        let progress_cb = self.progress_callback(inode, transfer_id);
 
         let upload_result = self.client.upload_file(
-            &cache_path,
+            &snapshot,
             &name,
             attrs.mime_type.as_deref(),
             &parent_gdrive_id,
-            Some(progress_cb as Box<dyn Fn(u64) + Send + Sync>),
+            reserved_id,
+            drive_mtime(attrs.mtime, attrs.mtime_nsec),
+            Some(progress_cb),
         ).await;
 
         self.history.complete_transfer(transfer_id);
         
-        let real_gdrive_id = upload_result.context("Error subiendo archivo nuevo")?;
+        let real_gdrive_id = match upload_result {
+            Ok(id) => id,
+            Err(e) if self.adopt_if_created(inode, reserved_id).await => {
+                warn!("⚠️ {:?}", e.context("Error subiendo archivo nuevo"));
+                return Ok(());
+            }
+            Err(e) => return Err(e.context("Error subiendo archivo nuevo")),
+        };
+        // Drive aceptó contenido: si se creía lleno, ya hay espacio
+        self.storage_quota.mark_available();
         
-        // Actualizar el gdrive_id en la base de datos
-        sqlx::query("UPDATE inodes SET gdrive_id = ? WHERE inode = ?")
-            .bind(&real_gdrive_id)
-            .bind(inode as i64)
-            .execute(self.db.pool())
-            .await?;
+        // Ya existe en Drive: con ID reservado el gdrive_id no cambia; si Drive
+        // asignó otro, la caché pasa al nuevo nombre
+        self.switch_gdrive_id(inode, gdrive_id, &real_gdrive_id).await?;
         
         // Marcar como limpio (no dirty)
         // Optimistic Locking: Verificar si el estado cambió mientras subíamos el archivo
@@ -299,22 +564,154 @@ impl Uploader {
             warn!("⚠️ Modificación concurrente detectada durante creación de archivo (inode={}). Manteniendo dirty=1.", inode);
             // No limpiamos el flag dirty, para que el próximo ciclo procese los cambios nuevos
         } else {
-            self.db.clear_dirty_and_bubble(inode).await?;
+            self.db.clear_dirty(inode).await?;
         }
         
         info!("✅ Archivo creado en GDrive: {} (inode={})", real_gdrive_id, inode);
-        self.history.log(ActionType::Create, format!("Archivo creado: {}", name));
+        let path = self.mirror_file_path(inode).await;
+        self.history.log_file(ActionType::Create, t!("activity-file-created", name = name.as_str()), path);
         
         Ok(())
     }
 
+    /// Crea el documento de Google que pide un archivo vacío `*.gdoc`/`*.gsheet`/
+    /// `*.gslides` y convierte el inode en ese documento: nombre sin la extensión
+    /// (FUSE lo muestra como `nombre.html`, el acceso directo de siempre), MIME
+    /// type de Workspace y sin caché. Con ID reservado, si la respuesta se
+    /// pierde pero Drive llegó a crearlo (`adopt_if_created`), la conversión
+    /// sigue igual: reintentar crearía un duplicado o daría 409.
+    async fn create_workspace_file(
+        &self,
+        inode: u64,
+        gdrive_id: &str,
+        reserved_id: Option<&str>,
+        doc_name: &str,
+        mime_type: &str,
+        parent_gdrive_id: &str,
+    ) -> Result<()> {
+        let created = self.client
+            .create_workspace_file(doc_name, mime_type, parent_gdrive_id, reserved_id)
+            .await;
+        let real_gdrive_id = match created {
+            Ok(id) => id,
+            Err(e) => match reserved_id {
+                Some(id) if self.adopt_if_created(inode, reserved_id).await => {
+                    warn!("⚠️ {:?}", e.context("Error creando documento de Google"));
+                    id.to_string()
+                }
+                _ => return Err(e.context("Error creando documento de Google")),
+            },
+        };
+
+        // Creado en el espejo: el archivo vacío quedaría como uno nuevo sin registrar
+        let mirror_template = self.mirror_file_path(inode).await;
+
+        self.db.mark_created(inode, &real_gdrive_id).await?;
+        self.db.clear_chunks(inode).await?;
+        let _ = tokio::fs::remove_file(self.cache_dir.join(gdrive_id)).await;
+        self.db.set_mime_type(inode, mime_type).await?;
+        self.db.set_size(inode, 0).await?;
+        // Un documento de Google no tiene contenido que mantener en el dispositivo
+        self.db.set_availability(inode, "online_only", true).await?;
+        if let Some(parent) = self.db.get_parent_inode(inode).await? {
+            self.db.upsert_remote_dentry(parent, inode, doc_name).await?;
+        }
+        self.db.clear_dirty(inode).await?;
+
+        if let Some(path) = mirror_template
+            && tokio::fs::symlink_metadata(&path).await.is_ok_and(|meta| meta.is_file() && meta.len() == 0)
+        {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+
+        info!("✅ Documento de Google creado en GDrive: {} '{}' (inode={})", real_gdrive_id, doc_name, inode);
+        let path = self.mirror_file_path(inode).await;
+        self.history.log_file(ActionType::Create, t!("activity-workspace-file-created", name = doc_name), path);
+        Ok(())
+    }
+
+    /// Reemplaza el `gdrive_id` de un inode recién creado en Drive, llevando
+    /// su archivo de caché de `cache_dir/<old_id>` a `cache_dir/<new_id>`.
+    /// Se enlaza el nombre nuevo antes de cambiar la DB y se borra el viejo
+    /// después: un lector concurrente encuentra el contenido con cualquiera de
+    /// los dos IDs. Los chunks (`file_cache_chunks`, por inode) se conservan;
+    /// si la caché no se pudo mover se descartan para que la próxima lectura
+    /// descargue de Drive en lugar de ver un archivo vacío.
+    async fn switch_gdrive_id(&self, inode: u64, old_id: &str, new_id: &str) -> Result<()> {
+        if old_id == new_id {
+            return self.db.mark_created(inode, new_id).await;
+        }
+
+        let old_path = self.cache_dir.join(old_id);
+        let new_path = self.cache_dir.join(new_id);
+        let moved = match tokio::fs::hard_link(&old_path, &new_path).await {
+            Ok(()) => true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => false,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                // Resto de una descarga anterior con el ID nuevo: manda la copia local
+                tokio::fs::rename(&old_path, &new_path).await.is_ok()
+            }
+            Err(e) => {
+                warn!("⚠️ No se pudo enlazar la caché {:?} -> {:?}: {}", old_path, new_path, e);
+                tokio::fs::rename(&old_path, &new_path).await.is_ok()
+            }
+        };
+
+        self.db.mark_created(inode, new_id).await?;
+        if moved {
+            let _ = tokio::fs::remove_file(&old_path).await;
+            debug!("🔀 Caché renombrada: {} -> {} (inode={})", old_id, new_id, inode);
+        } else {
+            self.db.clear_chunks(inode).await?;
+            let _ = tokio::fs::remove_file(&old_path).await;
+        }
+        Ok(())
+    }
+
+    /// Tras un error al crear con ID reservado, comprueba si Drive llegó a
+    /// crear el archivo (respuesta perdida: reintentar daría 409 fileIdInUse).
+    /// Si existe, lo da por creado y lo deja dirty para que el próximo ciclo
+    /// suba nombre, ubicación y contenido como actualización.
+    async fn adopt_if_created(&self, inode: u64, reserved_id: Option<&str>) -> bool {
+        let Some(id) = reserved_id else {
+            return false;
+        };
+        if self.client.get_file_metadata(id).await.is_err() {
+            return false;
+        }
+        match self.db.mark_created(inode, id).await {
+            Ok(()) => {
+                info!("🆔 {} ya existía en Drive: se actualizará en el próximo ciclo (inode={})", id, inode);
+                true
+            }
+            Err(e) => {
+                error!("Error marcando inode {} como creado: {:?}", inode, e);
+                false
+            }
+        }
+    }
+
     /// Actualiza un archivo existente en Google Drive
     async fn update_file(&self, inode: u64, gdrive_id: &str) -> Result<()> {
         info!("📤 Actualizando archivo en GDrive: {} (inode={})", gdrive_id, inode);
         
         // 1. Obtener Metadatos remotos completos (Name, Parent, MD5)
-        let remote_meta = self.client.get_file_metadata(gdrive_id).await?;
+        let remote_meta = match self.client.get_file_metadata(gdrive_id).await {
+            Ok(meta) => meta,
+            Err(e) if matches!(DriveError::find(&e), Some(DriveError::NotFound(_)))
+                && self.cache_dir.join(gdrive_id).exists() =>
+            {
+                // Eliminado para siempre en Drive mientras había cambios locales:
+                // no perderlos, subir la copia local como archivo nuevo
+                warn!("⚠️ {} ya no existe en Drive: se sube la copia local como archivo nuevo (inode={})", gdrive_id, inode);
+                let name = self.get_file_name(inode).await.unwrap_or_else(|_| gdrive_id.to_string());
+                self.history.log(ActionType::Sync, t!("activity-restored-in-drive", name = name.as_str()));
+                return self.create_file(inode, gdrive_id).await;
+            }
+            Err(e) => return Err(e),
+        };
         let current_remote_md5 = remote_meta.md5_checksum;
+        let remote_mtime = remote_meta.modified_time.map(|t| t.timestamp());
         let current_remote_name = remote_meta.name.unwrap_or_default();
 
         let known_md5 = self.db.get_remote_md5(inode).await?;
@@ -329,7 +726,7 @@ impl Uploader {
                         warn!("⚠️ CONFLICTO DETECTADO: archivo remoto cambió desde la última sync");
                         warn!("   - MD5 conocido: {}", known);
                         warn!("   - MD5 actual:   {}", current);
-                        return self.handle_conflict(inode, gdrive_id).await;
+                        return self.handle_conflict(inode, gdrive_id, remote_mtime, current, remote_meta.size).await;
                     }
                 }
             }
@@ -338,11 +735,9 @@ impl Uploader {
         
         // 3. Detectar Cambio de Nombre (Rename) y MTime local vs remoto
         let local_name = self.get_file_name(inode).await?;
-        let local_mtime: i64 = sqlx::query_scalar("SELECT mtime FROM attrs WHERE inode = ?")
-            .bind(inode as i64)
-            .fetch_optional(self.db.pool())
-            .await?
-            .unwrap_or(0);
+        let (local_mtime, local_mtime_nsec) = self.db.get_attrs(inode).await
+            .map(|a| (a.mtime, a.mtime_nsec))
+            .unwrap_or((0, 0));
         
         let mut metadata_updated = false;
         let mut new_name: Option<&str> = None;
@@ -359,25 +754,18 @@ impl Uploader {
         // --------------------------------
 
         // Persistir capacidades actualizadas en la DB (para que MirrorManager/FUSE las conozcan)
-        if let Err(e) = sqlx::query("UPDATE attrs SET can_move = ? WHERE inode = ?")
-            .bind(can_move)
-            .bind(inode as i64)
-            .execute(self.db.pool())
-            .await {
+        if let Err(e) = self.db.set_can_move(inode, can_move).await {
             error!("Error actualizando can_move en DB: {:?}", e);
         }
 
         if local_name != current_remote_name {
             if !can_rename {
                 warn!("⛔ PERMISO DENEGADO: No se puede renombrar '{}'. Revertiendo cambio local.", current_remote_name);
-                // Rollback nombre
-                sqlx::query("UPDATE dentry SET name = ? WHERE child_inode = ?")
-                    .bind(&current_remote_name)
-                    .bind(inode as i64)
-                    .execute(self.db.pool())
-                    .await?;
+                // Rollback nombre (con sufijo si otro archivo ocupa ya ese nombre)
+                let parent_inode = self.db.get_parent_inode(inode).await?.unwrap_or(1);
+                self.db.upsert_remote_dentry(parent_inode, inode, &current_remote_name).await?;
                 // Limpiar dirty
-                self.db.clear_dirty_and_bubble(inode).await?;
+                self.db.clear_dirty(inode).await?;
                 return Ok(());
             }
 
@@ -391,8 +779,7 @@ impl Uploader {
              // Tolerancia de 2 segundos para evitar loops por diferencias de precisión
              if (local_mtime - remote_secs).abs() > 2 {
                  info!("🔄 Detectado cambio de fecha: Remote={} vs Local={}", remote_secs, local_mtime);
-                 use google_drive3::chrono::TimeZone;
-                 let dt = google_drive3::chrono::Utc.timestamp_opt(local_mtime, 0).single()
+                 let dt = drive_mtime(local_mtime, local_mtime_nsec)
                      .ok_or_else(|| anyhow::anyhow!("Invalid timestamp"))?;
                  new_mtime = Some(dt);
                  metadata_updated = true;
@@ -403,13 +790,16 @@ impl Uploader {
         let remote_parents = remote_meta.parents.clone().unwrap_or_default();
         let local_parent_id = self.get_parent_gdrive_id(inode).await?;
         
-        if local_parent_id.starts_with("temp_") {
+        if self.db.parent_pending_create(inode).await? {
             anyhow::bail!("DEFERRED_PARENT_TEMP");
         }
         
         // Verificar si el padre local está en la lista de padres remotos
         // Manejar el caso especial de "root" vs ID real del root
-        let is_in_remote = if local_parent_id == "root" {
+        let is_in_remote = if local_parent_id == crate::db::COMPUTERS_GDRIVE_ID {
+            // Equipo de "Ordenadores": en Drive no tiene padres
+            remote_parents.is_empty()
+        } else if local_parent_id == "root" {
             // Obtener el ID real del root para comparar correctamente
             match self.client.get_root_file_id().await {
                 Ok(root_id) => remote_parents.contains(&root_id) || remote_parents.contains(&"root".to_string()),
@@ -437,28 +827,19 @@ impl Uploader {
                 let unauthorized_rel = self.db.resolve_inode_to_relative_path(inode).await?.unwrap_or_default();
                 
                 // 2. Rollback DB: Restaurar el padre remoto en la base de datos local
-                let target_parent_inode = if let Some(parent_id) = remote_parents.first() {
-                     sqlx::query_scalar::<_, i64>("SELECT inode FROM inodes WHERE gdrive_id = ?")
-                        .bind(parent_id)
-                        .fetch_optional(self.db.pool())
-                        .await?
-                        .unwrap_or(1)
-                } else {
-                     1 
+                let target_parent_inode = match remote_parents.first() {
+                    Some(parent_id) => self.db.get_inode_by_gdrive_id(parent_id).await?.unwrap_or(1),
+                    None => 1,
                 };
 
-                sqlx::query("UPDATE dentry SET parent_inode = ?, name = ? WHERE child_inode = ?")
-                    .bind(target_parent_inode)
-                    .bind(&current_remote_name) // También restauramos el nombre por si hubo rename simultáneo
-                    .bind(inode as i64)
-                    .execute(self.db.pool())
-                    .await?;
+                // También restauramos el nombre por si hubo rename simultáneo
+                self.db.upsert_remote_dentry(target_parent_inode, inode, &current_remote_name).await?;
 
                 // 3. Obtener la ruta "correcta" restaurada
                 let correct_rel = self.db.resolve_inode_to_relative_path(inode).await?.unwrap_or_default();
 
                 // 4. Limpiar dirty
-                self.db.clear_dirty_and_bubble(inode).await?;
+                self.db.clear_dirty(inode).await?;
 
                 if !unauthorized_rel.is_empty() && !correct_rel.is_empty() && unauthorized_rel != correct_rel {
                     warn!("🔄 Ejecutando Rollback Físico: {} -> {}", unauthorized_rel, correct_rel);
@@ -470,7 +851,7 @@ impl Uploader {
                     }
                 }
 
-                self.history.log(ActionType::Sync, format!("Movimiento bloqueado y revertido: {}", current_remote_name));
+                self.history.log(ActionType::Sync, t!("activity-move-reverted", name = current_remote_name.as_str()));
                 return Ok(());
             }
 
@@ -510,11 +891,11 @@ impl Uploader {
             if metadata_updated {
                 info!("✅ Renombrado completado sin cambios de contenido (sin caché).");
                 // Marcar como limpio
-                self.db.clear_dirty_and_bubble(inode).await?;
+                self.db.clear_dirty(inode).await?;
                 if add_parent.is_some() {
-                    self.history.log(ActionType::Sync, format!("Movido: {} → {}", current_remote_name, local_name));
+                    self.history.log(ActionType::Sync, t!("activity-moved", from = current_remote_name.as_str(), to = local_name.as_str()));
                 } else {
-                    self.history.log(ActionType::Sync, format!("Renombrado: {} → {}", current_remote_name, local_name));
+                    self.history.log(ActionType::Sync, t!("activity-renamed", from = current_remote_name.as_str(), to = local_name.as_str()));
                 }
                 return Ok(());
             }
@@ -526,16 +907,22 @@ impl Uploader {
             // y permitir que se muestre como CloudOnly/Synced.
             info!("⚠️ Corrigiendo estado inconsistente: dirty=1 pero sin caché local. Reseteando a dirty=0.");
             
-            self.db.clear_dirty_and_bubble(inode).await?;
+            self.db.clear_dirty(inode).await?;
                 
-            self.history.log(ActionType::Sync, format!("Estado corregido (sin caché): {}", gdrive_id));
+            self.history.log(ActionType::Sync, t!("activity-state-fixed", name = gdrive_id));
 
             return Ok(()); 
         }
+
+        // La caché debe cubrir todo el archivo: lo nunca descargado se subiría como ceros
+        self.hydrate_missing_ranges(inode, gdrive_id, &cache_path, remote_meta.size.unwrap_or(0).max(0) as u64).await?;
         
+        // Lo que se compara y se sube es una copia: FUSE puede seguir escribiendo la caché
+        let snapshot = self.snapshot_cache(inode, &cache_path).await?;
+
         // 5. OPTIMIZACIÓN: Verificar si el contenido local es idéntico al remoto
         // Esto evita re-subir archivos que solo fueron "tocados" o migrados sin cambios reales
-        match crate::utils::hash::compute_file_md5(&cache_path).await {
+        match crate::utils::hash::compute_file_md5(&snapshot).await {
             Ok(local_md5) => {
                 // Verificar contra el MD5 remoto actual (si existe)
                 if let Some(remote_md5) = &current_remote_md5 {
@@ -545,9 +932,9 @@ impl Uploader {
                          // Actualizar estado para reflejar que está sincronizado
                          self.db.set_remote_md5(inode, remote_md5).await?;
                          
-                         self.db.clear_dirty_and_bubble(inode).await?;
+                         self.db.clear_dirty(inode).await?;
                             
-                         self.history.log(ActionType::Sync, format!("Verificado sin cambios: {}", gdrive_id));
+                         self.history.log(ActionType::Sync, t!("activity-verified-unchanged", name = gdrive_id));
                          return Ok(());
                      }
                 }
@@ -569,39 +956,38 @@ impl Uploader {
                  tokio::fs::remove_file(&cache_path).await.ok();
              }
 
-             self.db.clear_dirty_and_bubble(inode).await?;
+             self.db.clear_dirty(inode).await?;
              
              return Ok(());
         }
 
         // 6. Guardia anti-0-bytes: no sobrescribir archivo remoto con cache vacío
-        let file_size = tokio::fs::metadata(&cache_path).await.map(|m| m.len()).unwrap_or(0);
+        let file_size = tokio::fs::metadata(&snapshot).await.map(|m| m.len()).unwrap_or(0);
         let remote_size = remote_meta.size.unwrap_or(0);
 
         if should_block_zero_byte_upload(file_size, remote_size) {
             warn!("🛡️ BLOQUEADO: upload de 0 bytes para archivo que en Drive pesa {} bytes (gdrive_id={}). Limpiando cache corrupto.", remote_size, gdrive_id);
             let _ = tokio::fs::remove_file(&cache_path).await;
-            self.db.clear_dirty_and_bubble(inode).await?;
+            self.db.clear_dirty(inode).await?;
             return Ok(());
         }
 
         // 7. Actualizar contenido usando la API (con tracking de progreso)
         let transfer_id = self.history.start_transfer(&local_name, TransferOp::Upload, file_size);
-        
-        let history_clone = self.history.clone();
-        let progress_cb = Box::new(move |offset: u64| {
-            history_clone.update_transfer_progress(transfer_id, offset);
-        });
+        let _upload = self.upload_progress.start(inode, file_size);
+        let progress_cb = self.progress_callback(inode, transfer_id);
 
         let update_result = self.client.update_file_content(
             gdrive_id, 
-            &cache_path,
-            Some(progress_cb as Box<dyn Fn(u64) + Send + Sync>),
+            &snapshot,
+            drive_mtime(local_mtime, local_mtime_nsec),
+            Some(progress_cb),
         ).await;
 
         self.history.complete_transfer(transfer_id);
         
         update_result.context("Error actualizando archivo")?;
+        self.storage_quota.mark_available();
         
         // 6. Obtener el nuevo MD5 tras la actualización
         if let Some(new_md5) = self.client.get_file_md5(gdrive_id).await? {
@@ -617,75 +1003,218 @@ impl Uploader {
             warn!("⚠️ Modificación concurrente detectada durante actualización (inode={}). Manteniendo dirty=1.", inode);
             // No limpiamos el flag dirty, para que el próximo ciclo procese los cambios nuevos
         } else {
-            self.db.clear_dirty_and_bubble(inode).await?;
+            self.db.clear_dirty(inode).await?;
         }
         
         info!("✅ Archivo actualizado en GDrive: {} (inode={})", gdrive_id, inode);
+        let path = self.mirror_file_path(inode).await;
         if add_parent.is_some() {
-            self.history.log(ActionType::Sync, format!("Movido: {} → {}", current_remote_name, local_name));
+            self.history.log_file(ActionType::Sync, t!("activity-moved", from = current_remote_name.as_str(), to = local_name.as_str()), path);
         } else {
-            self.history.log(ActionType::Upload, format!("Subido: {}", local_name));
+            self.history.log_file(ActionType::Upload, t!("activity-uploaded", name = local_name.as_str()), path);
         }
         
         Ok(())
     }
 
+    /// Copia de la caché de `inode` tomada con su lock: la subida lee un
+    /// contenido fijo aunque FUSE siga escribiendo o truncando el original.
+    /// Se borra al soltarla.
+    async fn snapshot_cache(&self, inode: u64, cache_path: &Path) -> Result<tempfile::TempPath> {
+        let dir = self.cache_dir.join(UPLOAD_SNAPSHOT_DIR);
+        tokio::fs::create_dir_all(&dir).await?;
+        let snapshot = tempfile::Builder::new().tempfile_in(&dir)?.into_temp_path();
+        let _guard = self.file_locks.lock(inode).await;
+        crate::utils::cache_crypto::copy_file(cache_path, &snapshot).await
+            .context("Error copiando la caché para subirla")?;
+        Ok(snapshot)
+    }
+
+    /// Descarga de Drive los rangos que la caché nunca tuvo (un archivo leído
+    /// a medias y luego editado) antes de subir el archivo completo. Solo
+    /// rellena huecos reales del archivo (`SEEK_HOLE`): un rango con datos sin
+    /// chunk registrado (cachés anteriores al registro de chunks) es una
+    /// edición local y no se pisa.
+    async fn hydrate_missing_ranges(&self, inode: u64, gdrive_id: &str, cache_path: &Path, remote_size: u64) -> Result<()> {
+        use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+        // Más allá del tamaño remoto no hay nada que bajar: es una extensión local
+        let size = (self.db.get_attrs(inode).await?.size.max(0) as u64).min(remote_size);
+        if size == 0 {
+            return Ok(());
+        }
+        let missing = {
+            let file = tokio::fs::File::open(cache_path).await?;
+            let mut holes = Vec::new();
+            for (start, end) in self.db.get_missing_ranges(inode, 0, size - 1).await? {
+                holes.extend(hole_ranges(&file, start, end)?);
+            }
+            holes
+        };
+        if missing.is_empty() {
+            return Ok(());
+        }
+        info!("💧 Completando caché parcial de inode {} antes de subir ({} rangos)", inode, missing.len());
+
+        for (range_start, range_end) in missing {
+            let mut start = range_start;
+            while start <= range_end {
+                let end = (start + HYDRATE_CHUNK_SIZE - 1).min(range_end);
+                let data = self.client.download_chunk(gdrive_id, start, (end - start + 1) as u32).await?;
+
+                let _guard = self.file_locks.lock(inode).await;
+                let mut file = tokio::fs::OpenOptions::new().write(true).open(cache_path).await?;
+                // Lo que FUSE escribió mientras se descargaba no se pisa
+                let mut gaps = Vec::new();
+                for (gap_start, gap_end) in self.db.get_missing_ranges(inode, start, end).await? {
+                    gaps.extend(hole_ranges(&file, gap_start, gap_end)?);
+                }
+                for (gap_start, gap_end) in gaps {
+                    let from = (gap_start - start) as usize;
+                    let to = ((gap_end - start + 1) as usize).min(data.len());
+                    if from >= to {
+                        continue;
+                    }
+                    let mut part = data[from..to].to_vec();
+                    crate::utils::cache_crypto::encrypt_at(&file, gap_start, &mut part)?;
+                    file.seek(std::io::SeekFrom::Start(gap_start)).await?;
+                    file.write_all(&part).await?;
+                    file.flush().await?;
+                    self.db.add_cached_chunk(inode, gap_start, gap_start + part.len() as u64 - 1).await?;
+                }
+                start = end + 1;
+            }
+        }
+        Ok(())
+    }
+
     /// Elimina un archivo en Google Drive (moverlo a la papelera)
     async fn delete_file(&self, inode: u64, gdrive_id: &str) -> Result<()> {
+        // Descendiente de una carpeta eliminada: la papelera de Drive es recursiva,
+        // basta con enviar la carpeta eliminada más alta
+        match self.db.deleted_parent_pending(inode).await? {
+            Some(true) => anyhow::bail!("DEFERRED_PARENT_DELETE"),
+            Some(false) => {
+                debug!("Inode {} ya eliminado en Drive junto con su carpeta padre", inode);
+                self.db.clear_dirty(inode).await?;
+                return Ok(());
+            }
+            None => {}
+        }
+
+        if self.db.is_purge_requested(inode).await? {
+            return self.purge_file(inode, gdrive_id).await;
+        }
+
         info!("🗑️ Eliminando archivo en GDrive: {} (inode={})", gdrive_id, inode);
         
-        // No eliminar archivos temporales que nunca se subieron
-        if gdrive_id.starts_with("temp_") {
+        // No eliminar archivos que nunca llegaron a crearse en Drive
+        if self.db.is_pending_create(inode).await? {
             debug!("Archivo temporal nunca subido, marcando como limpio directamente");
+        } else if !self.db.can_trash(inode).await? {
+            // Drive ya informó que no se puede eliminar (compartido sin permisos):
+            // restaurar sin intentar una papelera que fallaría
+            warn!("⚠️ Sin permiso para eliminar {} en Drive: restaurando localmente", gdrive_id);
+            self.db.restore_by_gdrive_id(gdrive_id).await?;
+            self.db.clear_dirty(inode).await?;
+            self.history.log(
+                ActionType::Sync,
+                t!("activity-shared-restored", name = gdrive_id)
+            );
+            return Ok(());
         } else {
             // Intentar mover a papelera en GDrive
             match self.client.trash_file(gdrive_id).await {
                 Ok(()) => {
                     info!("✅ Archivo eliminado en GDrive: {}", gdrive_id);
-                    self.history.log(ActionType::Delete, format!("Archivo eliminado: {}", gdrive_id));
+                    self.history.log(ActionType::Delete, t!("activity-file-deleted", name = gdrive_id));
                 }
-                Err(crate::gdrive::DriveError::InsufficientPermissions(msg)) => {
+                Err(DriveError::InsufficientPermissions(msg)) => {
                     // Error permanente: no podemos eliminar archivos compartidos
                     warn!("⚠️ No se puede eliminar archivo compartido: {}", msg);
                     warn!("   Restaurando archivo localmente para mantener consistencia con Drive");
                     
                     // RESTAURAR: deshacer el soft delete (eliminar deleted_at)
-                    sqlx::query("UPDATE sync_state SET deleted_at = NULL WHERE inode = ?")
-                        .bind(inode as i64)
-                        .execute(self.db.pool())
-                        .await?;
+                    self.db.clear_deleted_at(inode).await?;
                     
                     // Marcar como limpio (no reintentar)
-                    self.db.clear_dirty_and_bubble(inode).await?;
+                    self.db.clear_dirty(inode).await?;
                     
                     self.history.log(
                         ActionType::Sync, 
-                        format!("Archivo compartido restaurado: {} (sin permisos de eliminación)", gdrive_id)
+                        t!("activity-shared-restored", name = gdrive_id)
                     );
                     
                     return Ok(());
                 }
-                Err(crate::gdrive::DriveError::NotFound(_)) => {
+                Err(DriveError::NotFound(_)) => {
                     // Archivo ya no existe en Drive: limpiar estado local y continuar
                     info!("ℹ️ Archivo ya eliminado en Drive: {}. Limpiando estado local.", gdrive_id);
-                    self.history.log(ActionType::Delete, format!("Archivo ya eliminado en Drive: {}", gdrive_id));
+                    self.history.log(ActionType::Delete, t!("activity-already-deleted", name = gdrive_id));
                     // Continuar para limpiar dirty flag abajo
                 }
                 Err(e) => {
-                    // Otros errores transitorios: propagar para reintentar
-                    return Err(anyhow::anyhow!("Error moviendo archivo a papelera: {:?}", e));
+                    // Otros errores: propagar (con su tipo) para reintentar o esperar
+                    return Err(anyhow::Error::new(e).context("Error moviendo archivo a papelera"));
                 }
             }
         }
         
         // Marcar como limpio (eliminación exitosa)
-        self.db.clear_dirty_and_bubble(inode).await?;
+        self.db.clear_dirty(inode).await?;
+
+        // Si era carpeta, su contenido viajó con ella a la papelera
+        let descendants = self.db.clear_deleted_subtree_dirty(inode).await?;
+        if descendants > 0 {
+            debug!("🗑️ {} descendientes eliminados junto con inode={}", descendants, inode);
+        }
         
         Ok(())
     }
 
+    /// Elimina definitivamente un elemento que el usuario borró de `.Trash`
+    /// (`files.delete`, esté o no ya en la papelera de Drive) y lo saca de la
+    /// DB junto con su contenido y su caché
+    async fn purge_file(&self, inode: u64, gdrive_id: &str) -> Result<()> {
+        let name = self.db.get_trashed_name(inode).await?.unwrap_or_else(|| gdrive_id.to_string());
+        info!("🔥 Eliminando definitivamente en GDrive: {} (inode={})", name, inode);
+
+        if self.db.is_pending_create(inode).await? {
+            debug!("Archivo nunca subido, solo se elimina en local");
+        } else {
+            match self.client.delete_file(gdrive_id).await {
+                Ok(()) => {}
+                Err(DriveError::NotFound(_)) => {
+                    info!("ℹ️ Archivo ya eliminado en Drive: {}", name);
+                }
+                Err(e) => {
+                    return Err(anyhow::Error::new(e).context("Error eliminando archivo definitivamente"));
+                }
+            }
+        }
+
+        let removed = self.db.hard_delete_subtree_by_gdrive_id(gdrive_id).await?;
+        for id in &removed {
+            if let Err(e) = tokio::fs::remove_file(self.cache_dir.join(id)).await
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                warn!("⚠️ No se pudo eliminar caché de {}: {}", id, e);
+            }
+        }
+        self.history.log(ActionType::Delete, t!("activity-file-purged", name = name));
+        Ok(())
+    }
+
     /// Maneja un conflicto de sincronización creando una copia del archivo local
-    async fn handle_conflict(&self, inode: u64, gdrive_id: &str) -> Result<()> {
+    /// y registrándolo para el centro de conflictos de la GUI
+    async fn handle_conflict(
+        &self,
+        inode: u64,
+        gdrive_id: &str,
+        remote_mtime: Option<i64>,
+        remote_md5: &str,
+        remote_size: Option<i64>,
+    ) -> Result<()> {
         warn!("📥 Resolviendo conflicto de sincronización para inode={}", inode);
         
         // 1. Obtener nombre original del archivo
@@ -737,59 +1266,82 @@ impl Uploader {
         let attrs = self.db.get_attrs(inode).await?;
         
         // Crear el archivo de conflicto en GDrive
+        let snapshot = self.snapshot_cache(inode, &cache_path).await?;
         let conflict_gdrive_id = self.client.upload_file(
-            &cache_path,
+            &snapshot,
             &conflict_name,
             attrs.mime_type.as_deref(),
             &parent_gdrive_id,
             None,
+            drive_mtime(attrs.mtime, attrs.mtime_nsec),
+            None,
         ).await.context("Error subiendo copia de conflicto")?;
         
         // 5. Marcar el archivo original como limpio (no lo modificamos)
-        self.db.clear_dirty_and_bubble(inode).await?;
+        self.db.clear_dirty(inode).await?;
+        self.db.record_conflict(inode, gdrive_id, &conflict_gdrive_id, &conflict_name, attrs.mtime, remote_mtime).await?;
+
+        // 6. El original vuelve a la versión de Drive (el syncer conservó la local
+        // mientras estaba dirty): la próxima lectura la descarga
+        {
+            let _guard = self.file_locks.lock(inode).await;
+            self.db.clear_chunks(inode).await?;
+            let _ = tokio::fs::remove_file(&cache_path).await;
+        }
+        self.db.set_remote_md5(inode, remote_md5).await?;
+        if let Some(size) = remote_size {
+            self.db.set_size(inode, size).await?;
+        }
+        if let Some(mtime) = remote_mtime {
+            self.db.set_mtime(inode, mtime, 0).await?;
+        }
         
         warn!("✅ Conflicto resuelto: copia local guardada como {}", conflict_gdrive_id);
         warn!("   El archivo original permanece sin cambios en la nube");
-        self.history.log(ActionType::Conflict, format!("Conflicto resuelto: {}", conflict_name));
+        let path = self.mirror_file_path(inode).await;
+        self.history.log_file(ActionType::Conflict, t!("activity-conflict-copy", name = conflict_name.as_str()), path);
         
         Ok(())
     }
 
-    /// Obtiene el nombre de un archivo desde la base de datos
+    /// Ruta del archivo en el espejo (para abrirlo desde el historial)
+    async fn mirror_file_path(&self, inode: u64) -> Option<std::path::PathBuf> {
+        let relative = self.db.resolve_inode_to_relative_path(inode).await.ok()??;
+        Some(self.mirror_path.join(relative))
+    }
+
+    /// Si el nombre coincide con `ignore_patterns` o la carpeta padre está ignorada
+    async fn matches_ignore_rules(&self, inode: u64) -> Result<bool> {
+        if self.ignore_rules.is_ignored_name(&self.get_file_name(inode).await?) {
+            return Ok(true);
+        }
+        match self.db.get_parent_inode(inode).await? {
+            Some(parent) => self.db.is_ignored(parent).await,
+            None => Ok(false),
+        }
+    }
+
+    /// Obtiene el nombre de un archivo tal como se llama en Google Drive
     async fn get_file_name(&self, inode: u64) -> Result<String> {
-        let name = sqlx::query_scalar::<_, String>(
-            "SELECT name FROM dentry WHERE child_inode = ? LIMIT 1"
-        )
-        .bind(inode as i64)
-        .fetch_optional(self.db.pool())
-        .await?
-        .unwrap_or_else(|| format!("file_{}", inode));
-        
+        // Nombre en Drive: los duplicados se ven localmente con sufijo `(n)`
+        // pero no deben renombrarse en remoto
+        let name = self.db.get_remote_name(inode).await?
+            .unwrap_or_else(|| format!("file_{}", inode));
+
         Ok(name)
     }
 
     /// Obtiene el gdrive_id del directorio padre
     async fn get_parent_gdrive_id(&self, inode: u64) -> Result<String> {
-        let parent_inode = sqlx::query_scalar::<_, i64>(
-            "SELECT parent_inode FROM dentry WHERE child_inode = ? LIMIT 1"
-        )
-        .bind(inode as i64)
-        .fetch_optional(self.db.pool())
-        .await?
-        .unwrap_or(1); // Default a root
-        
+        let parent_inode = self.db.get_parent_inode(inode).await?
+            .unwrap_or(1); // Default a root
+
         if parent_inode == 1 {
             return Ok("root".to_string());
         }
-        
-        let parent_gdrive_id = sqlx::query_scalar::<_, String>(
-            "SELECT gdrive_id FROM inodes WHERE inode = ?"
-        )
-        .bind(parent_inode)
-        .fetch_one(self.db.pool())
-        .await?;
-        
-        Ok(parent_gdrive_id)
+
+        self.db.get_gdrive_id(parent_inode).await?
+            .ok_or_else(|| anyhow::anyhow!("Directorio padre {} no encontrado", parent_inode))
     }
 
     // ============================================================
@@ -914,14 +1466,16 @@ impl Uploader {
                 let file_size = tokio::fs::metadata(local_path).await.map(|m| m.len()).unwrap_or(0);
                 let transfer_id = self.history.start_transfer(file_name, TransferOp::Upload, file_size);
                 let history_ref = self.history.clone();
-                let progress_cb = Box::new(move |bytes: u64| {
-                    history_ref.update_transfer_progress(transfer_id, bytes);
+                let progress_cb = Box::new(move |sent: u64, _total: u64| {
+                    history_ref.update_transfer_progress(transfer_id, sent);
                 });
                 let upload_result = self.client.upload_file(
                     local_path,
                     file_name,
                     mime_type.as_deref(),
                     &parent_gdrive_id,
+                    None,
+                    local_file_mtime(local_path).await,
                     Some(progress_cb),
                 ).await;
                 self.history.complete_transfer(transfer_id);
@@ -937,7 +1491,7 @@ impl Uploader {
                 }
                 
                 info!("✅ Archivo local sync creado: {}", gdrive_id);
-                self.history.log(ActionType::Create, format!("Local sync: {}", file.relative_path));
+                self.history.log(ActionType::Create, t!("activity-local-sync", path = file.relative_path.as_str()));
                 
                 Ok(())
             }
@@ -961,10 +1515,10 @@ impl Uploader {
 
                 let transfer_id = self.history.start_transfer(file_name, TransferOp::Upload, file_size);
                 let history_ref = self.history.clone();
-                let progress_cb = Box::new(move |bytes: u64| {
-                    history_ref.update_transfer_progress(transfer_id, bytes);
+                let progress_cb = Box::new(move |sent: u64, _total: u64| {
+                    history_ref.update_transfer_progress(transfer_id, sent);
                 });
-                let update_result = self.client.update_file_content(gdrive_id, local_path, Some(progress_cb)).await;
+                let update_result = self.client.update_file_content(gdrive_id, local_path, local_file_mtime(local_path).await, Some(progress_cb)).await;
                 self.history.complete_transfer(transfer_id);
                 update_result.context("Error actualizando archivo local sync")?;
                 
@@ -977,7 +1531,7 @@ impl Uploader {
                 }
                 
                 info!("✅ Archivo local sync actualizado: {}", gdrive_id);
-                self.history.log(ActionType::Upload, format!("Local sync: {}", file.relative_path));
+                self.history.log(ActionType::Upload, t!("activity-local-sync", path = file.relative_path.as_str()));
                 
                 Ok(())
             }
@@ -991,11 +1545,168 @@ fn should_block_zero_byte_upload(local_size: u64, remote_size: i64) -> bool {
     local_size == 0 && remote_size > 0
 }
 
+/// Inode dirty listo para subir
+#[derive(Debug, Clone, PartialEq)]
+struct DirtyFile {
+    inode: u64,
+    gdrive_id: String,
+    is_delete: bool,
+    /// Última operación del diario vista por este ciclo (None = dirty sin diario)
+    last_seq: Option<i64>,
+    /// Renombrado o eliminación pendiente: se sube en un lote propio (barrera)
+    structural: bool,
+}
+
+/// Ordena los inodes dirty por su última operación en el diario y los agrupa
+/// en lotes que se suben uno tras otro. Cada inode se sube con su estado
+/// actual, así que su posición es la de su última operación: un archivo nuevo
+/// renombrado sobre otro se crea después de enviar el anterior a la papelera.
+/// Renombrados y eliminaciones van en un lote propio (barrera); creaciones y
+/// escrituras entre barreras se suben en paralelo. Los dirty sin diario
+/// (espejo, reintentos, versiones anteriores) van primero.
+fn plan_upload_batches(
+    dirty: Vec<(u64, String, bool)>,
+    pending: &std::collections::HashMap<u64, crate::db::PendingOps>,
+) -> Vec<Vec<DirtyFile>> {
+    let mut files: Vec<DirtyFile> = dirty
+        .into_iter()
+        .map(|(inode, gdrive_id, is_delete)| {
+            let ops = pending.get(&inode);
+            DirtyFile {
+                inode,
+                gdrive_id,
+                is_delete,
+                last_seq: ops.map(|o| o.last_seq),
+                structural: ops.is_some_and(|o| o.structural),
+            }
+        })
+        .collect();
+    files.sort_by_key(|file| (file.last_seq.unwrap_or(0), file.inode));
+
+    let mut batches = Vec::new();
+    let mut current = Vec::new();
+    for file in files {
+        if file.structural {
+            if !current.is_empty() {
+                batches.push(std::mem::take(&mut current));
+            }
+            batches.push(vec![file]);
+        } else {
+            current.push(file);
+        }
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
+
+/// Tramos de `[start, end]` (inclusivo) que en `file` son hueco o quedan
+/// más allá del final, según `SEEK_DATA`/`SEEK_HOLE`
+fn hole_ranges(file: &impl std::os::fd::AsRawFd, start: u64, end: u64) -> std::io::Result<Vec<(u64, u64)>> {
+    let mut holes = Vec::new();
+    let mut pos = start;
+    for (data_start, data_end) in crate::utils::cache_crypto::data_segments(file, start, end + 1)? {
+        if data_start > pos {
+            holes.push((pos, data_start - 1));
+        }
+        pos = data_end;
+    }
+    if pos <= end {
+        holes.push((pos, end));
+    }
+    Ok(holes)
+}
+
+/// Espera antes del intento `attempt` (1 = primer reintento) de un archivo
+fn retry_backoff(attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.saturating_sub(1).min(16);
+    Duration::from_secs(RETRY_BASE_SECS.saturating_mul(factor).min(MAX_RETRY_BACKOFF_SECS))
+}
+
+/// Indica si reintentar la subida no puede cambiar el resultado: un error de
+/// Drive permanente (`DriveError::is_permanent`: permisos, archivo inexistente,
+/// límites) o la copia local desaparecida. Se clasifica por el tipo del error,
+/// nunca por su texto. El archivo queda en la lista de problemas hasta que
+/// cambie o el usuario pida reintentar.
+fn is_permanent_upload_error(error: &anyhow::Error) -> bool {
+    if let Some(drive_error) = DriveError::find(error) {
+        return drive_error.is_permanent();
+    }
+    error.chain().any(|cause| {
+        cause.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound)
+    })
+}
+
+/// `modifiedTime` para Drive a partir del mtime local (segundos + nanosegundos)
+fn drive_mtime(secs: i64, nsec: i64) -> Option<google_drive3::chrono::DateTime<google_drive3::chrono::Utc>> {
+    use google_drive3::chrono::TimeZone;
+    google_drive3::chrono::Utc.timestamp_opt(secs, nsec.clamp(0, 999_999_999) as u32).single()
+}
+
+/// mtime de un archivo de Local Sync en disco
+async fn local_file_mtime(path: &std::path::Path) -> Option<google_drive3::chrono::DateTime<google_drive3::chrono::Utc>> {
+    let modified = tokio::fs::metadata(path).await.ok()?.modified().ok()?;
+    Some(modified.into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::UploadOp;
+    use crate::db::test_fixtures;
     use rstest::*;
 
+    #[rstest]
+    #[case::whole_seconds(1_700_000_000, 0, "2023-11-14T22:13:20.000Z")]
+    #[case::millis(1_700_000_000, 123_456_789, "2023-11-14T22:13:20.123Z")]
+    fn test_drive_mtime(#[case] secs: i64, #[case] nsec: i64, #[case] expected: &str) {
+        use google_drive3::chrono::SecondsFormat;
+        let dt = drive_mtime(secs, nsec).unwrap();
+        assert_eq!(dt.to_rfc3339_opts(SecondsFormat::Millis, true), expected);
+    }
+
+    /// Inodes de cada lote de `plan_upload_batches`
+    fn batch_inodes(batches: &[Vec<DirtyFile>]) -> Vec<Vec<u64>> {
+        batches.iter().map(|b| b.iter().map(|f| f.inode).collect()).collect()
+    }
+
+    #[rstest]
+    #[case::no_journal(vec![], vec![vec![1, 2, 3]])]
+    #[case::ordered_by_last_op(vec![(1, 30, false), (2, 10, false), (3, 20, false)], vec![vec![2, 3, 1]])]
+    #[case::rename_is_barrier(vec![(1, 10, false), (2, 20, true), (3, 30, false)], vec![vec![1], vec![2], vec![3]])]
+    #[case::delete_before_rename_over_it(vec![(1, 30, true), (2, 20, true), (3, 5, false)], vec![vec![3], vec![2], vec![1]])]
+    #[case::unjournaled_first(vec![(1, 10, true)], vec![vec![2, 3], vec![1]])]
+    fn test_plan_upload_batches(#[case] ops: Vec<(u64, i64, bool)>, #[case] expected: Vec<Vec<u64>>) {
+        let dirty = (1..=3).map(|inode| (inode, format!("id-{}", inode), false)).collect();
+        let pending = ops
+            .into_iter()
+            .map(|(inode, last_seq, structural)| (inode, crate::db::PendingOps { last_seq, structural }))
+            .collect();
+
+        assert_eq!(batch_inodes(&plan_upload_batches(dirty, &pending)), expected);
+    }
+
+    #[rstest]
+    #[case::first(1, 30)]
+    #[case::second(2, 60)]
+    #[case::fifth(5, 480)]
+    #[case::capped(8, 3600)]
+    #[case::huge(u32::MAX, 3600)]
+    fn test_retry_backoff(#[case] attempt: u32, #[case] expected_secs: u64) {
+        assert_eq!(retry_backoff(attempt), Duration::from_secs(expected_secs));
+    }
+
+    #[rstest]
+    #[case::permissions(DriveError::from_response(403, None, r#"{"error": {"errors": [{"reason": "insufficientFilePermissions"}]}}"#).into(), true)]
+    #[case::missing_cache(anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NotFound)).context("Error leyendo archivo local"), true)]
+    #[case::rate_limit(DriveError::from_response(403, None, r#"{"error": {"errors": [{"reason": "userRateLimitExceeded"}]}}"#).into(), false)]
+    #[case::server(anyhow::Error::new(DriveError::from_response(503, None, "Service Unavailable")).context("Error en upload simple"), false)]
+    #[case::untyped_text(anyhow::anyhow!("insufficientFilePermissions: No such file or directory"), false)]
+    fn test_is_permanent_upload_error(#[case] error: anyhow::Error, #[case] expected: bool) {
+        assert_eq!(is_permanent_upload_error(&error), expected);
+    }
+
     #[rstest]
     #[case::block_zero_local_real_remote(0, 1024, true)]
     #[case::allow_both_zero(0, 0, false)]
@@ -1010,4 +1721,515 @@ mod tests {
     ) {
         assert_eq!(should_block_zero_byte_upload(local_size, remote_size), expected);
     }
+
+    // ============================================================
+    // Ciclos de subida contra un Drive falso
+    // ============================================================
+
+    use crate::gdrive::mock::{MockDriveClient, MOCK_ROOT_ID};
+
+    struct Fixture {
+        dir: tempfile::TempDir,
+        mock: Arc<MockDriveClient>,
+        db: Arc<MetadataRepository>,
+        uploader: Uploader,
+    }
+
+    impl Fixture {
+        fn cache_path(&self, gdrive_id: &str) -> std::path::PathBuf {
+            self.dir.path().join("cache").join(gdrive_id)
+        }
+
+        /// Edición local de un archivo: nuevo contenido en caché (registrado
+        /// como cacheado, igual que `write` en FUSE) + dirty
+        async fn edit_locally(&self, inode: u64, gdrive_id: &str, content: &[u8]) {
+            crate::utils::cache_crypto::write_file(&self.cache_path(gdrive_id), content.to_vec()).await.unwrap();
+            self.db.set_size(inode, content.len() as i64).await.unwrap();
+            self.db.add_cached_chunk(inode, 0, content.len() as u64 - 1).await.unwrap();
+            self.db.mark_dirty(inode).await.unwrap();
+        }
+    }
+
+    /// Drive con `nota.txt` ya sincronizado (md5 remoto conocido)
+    async fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("cache")).unwrap();
+        std::fs::create_dir_all(dir.path().join("mirror")).unwrap();
+        let mock = Arc::new(MockDriveClient::new().with_client_id("esta-instalacion"));
+        let nota = mock.add_file("file-nota", "nota.txt", MOCK_ROOT_ID, b"hola");
+        let db = test_fixtures::synced_repo(&dir, &mock).await;
+        let client: Arc<dyn DriveApi> = mock.clone();
+        let inode = db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        db.set_remote_md5(inode, nota.md5_checksum.as_deref().unwrap()).await.unwrap();
+
+        let uploader = Uploader::new(
+            db.clone(),
+            client,
+            60,
+            dir.path().join("cache"),
+            dir.path().join("mirror"),
+            ActionHistory::new(),
+            MOCK_ROOT_ID.to_string(),
+        );
+        Fixture { dir, mock, db, uploader }
+    }
+
+    #[tokio::test]
+    async fn test_upload_creates_new_file() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+
+        let inode = fx.db.create_local_inode("temp_nuevo").await.unwrap();
+        fx.db.upsert_file_metadata(inode, 5, 1_700_000_000, 0o644, false, Some("text/plain"), true, false, true)
+            .await
+            .unwrap();
+        fx.db.upsert_dentry(1, inode, "nuevo.txt").await.unwrap();
+        fx.edit_locally(inode, "temp_nuevo", b"hola!").await;
+
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+
+        let remote = fx.mock.find_by_name_prefix("nuevo.txt").expect("nuevo.txt subido");
+        let marks = crate::gdrive::app_properties::AppMarks::from_file(&remote);
+        assert_eq!(marks.origin_client.as_deref(), Some("esta-instalacion"), "marcado con la instalación");
+        let remote_id = remote.id.unwrap();
+        assert_eq!(fx.mock.content(&remote_id).as_deref(), Some(&b"hola!"[..]));
+        assert_eq!(fx.db.get_gdrive_id(inode).await.unwrap(), Some(remote_id));
+        assert!(!fx.db.is_dirty(inode).await.unwrap());
+    }
+
+    #[rstest]
+    #[case::empty_template(b"", true)]
+    #[case::file_with_content(b"{\"doc_id\": \"x\"}", false)]
+    #[tokio::test]
+    async fn test_new_gdoc_file_creates_google_document(#[case] content: &[u8], #[case] becomes_document: bool) {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+
+        let inode = fx.db.create_local_inode("temp_informe").await.unwrap();
+        fx.db.upsert_file_metadata(inode, 0, 1_700_000_000, 0o644, false, None, true, false, true)
+            .await
+            .unwrap();
+        fx.db.upsert_dentry(1, inode, "Informe.gdoc").await.unwrap();
+        if content.is_empty() {
+            crate::utils::cache_crypto::write_file(&fx.cache_path("temp_informe"), Vec::new()).await.unwrap();
+            fx.db.mark_dirty(inode).await.unwrap();
+        } else {
+            fx.edit_locally(inode, "temp_informe", content).await;
+        }
+
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+
+        assert!(!fx.db.is_dirty(inode).await.unwrap());
+        let remote_id = fx.db.get_gdrive_id(inode).await.unwrap().unwrap();
+        let attrs = fx.db.get_attrs(inode).await.unwrap();
+        if becomes_document {
+            let remote = fx.mock.find_by_name_prefix("Informe").expect("documento creado");
+            assert_eq!(remote.name.as_deref(), Some("Informe"));
+            assert_eq!(remote.mime_type.as_deref(), Some("application/vnd.google-apps.document"));
+            assert_eq!(remote.id, Some(remote_id));
+            assert_eq!(attrs.mime_type.as_deref(), Some("application/vnd.google-apps.document"));
+            assert_eq!(fx.db.lookup(1, "Informe").await.unwrap(), Some(inode));
+            assert_eq!(fx.db.lookup(1, "Informe.gdoc").await.unwrap(), None);
+            assert!(!fx.cache_path("temp_informe").exists(), "sin caché");
+        } else {
+            assert_eq!(fx.mock.content(&remote_id).as_deref(), Some(content));
+            assert_eq!(fx.db.lookup(1, "Informe.gdoc").await.unwrap(), Some(inode));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_gdoc_file_adopts_document_created_with_lost_response() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let reserved = fx.mock.generate_ids(1).await.unwrap().remove(0);
+
+        let inode = fx.db.create_local_inode(&reserved).await.unwrap();
+        fx.db.upsert_file_metadata(inode, 0, 1_700_000_000, 0o644, false, None, true, false, true)
+            .await
+            .unwrap();
+        fx.db.upsert_dentry(1, inode, "Informe.gdoc").await.unwrap();
+        crate::utils::cache_crypto::write_file(&fx.cache_path(&reserved), Vec::new()).await.unwrap();
+        fx.db.mark_dirty(inode).await.unwrap();
+        // Drive lo creó con el ID reservado, pero la respuesta no llegó
+        fx.mock.create_workspace_file("Informe", "application/vnd.google-apps.document", MOCK_ROOT_ID, Some(&reserved))
+            .await
+            .unwrap();
+        fx.mock.fail_next("create_workspace_file", "503 Service Unavailable");
+
+        fx.uploader.upload_cycle().await.unwrap();
+
+        assert!(!fx.db.is_dirty(inode).await.unwrap(), "no se sube la plantilla como contenido");
+        assert_eq!(fx.db.get_gdrive_id(inode).await.unwrap().as_deref(), Some(reserved.as_str()));
+        assert_eq!(fx.db.lookup(1, "Informe").await.unwrap(), Some(inode));
+        let attrs = fx.db.get_attrs(inode).await.unwrap();
+        assert_eq!(attrs.mime_type.as_deref(), Some("application/vnd.google-apps.document"));
+        assert_eq!(fx.mock.calls("create_workspace_file"), 2, "sin reintento que lo duplique");
+    }
+
+    #[tokio::test]
+    async fn test_upload_skips_ignored_new_file() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let rules = IgnoreRules::new(&crate::utils::ignore::default_patterns()).unwrap();
+        let uploader = fx.uploader.with_ignore_rules(Arc::new(rules));
+
+        let inode = fx.db.create_local_inode("temp_swap").await.unwrap();
+        fx.db.upsert_file_metadata(inode, 5, 1_700_000_000, 0o644, false, Some("text/plain"), true, false, true)
+            .await
+            .unwrap();
+        fx.db.upsert_dentry(1, inode, ".nota.txt.swp").await.unwrap();
+        fx.db.mark_dirty(inode).await.unwrap();
+
+        uploader.upload_cycle().await.unwrap();
+
+        assert!(fx.mock.find_by_name_prefix(".nota.txt.swp").is_none(), "nunca llega a Drive");
+        assert!(fx.db.is_ignored(inode).await.unwrap());
+        assert!(fx.db.is_pending_create(inode).await.unwrap());
+        assert!(!fx.db.is_dirty(inode).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_upload_moves_temp_cache_to_drive_id() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+
+        let inode = fx.db.create_local_inode("temp_cache").await.unwrap();
+        fx.db.upsert_file_metadata(inode, 5, 1_700_000_000, 0o644, false, Some("text/plain"), true, false, true)
+            .await
+            .unwrap();
+        fx.db.upsert_dentry(1, inode, "cache.txt").await.unwrap();
+        fx.edit_locally(inode, "temp_cache", b"hola!").await;
+        fx.db.add_cached_chunk(inode, 0, 5).await.unwrap();
+
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+
+        let remote_id = fx.db.get_gdrive_id(inode).await.unwrap().unwrap();
+        assert_ne!(remote_id, "temp_cache");
+        assert!(!fx.cache_path("temp_cache").exists(), "sin archivo huérfano con el ID temporal");
+        let cached = crate::utils::cache_crypto::read_file(&fx.cache_path(&remote_id)).await.unwrap();
+        assert_eq!(cached, b"hola!");
+        assert!(fx.db.has_any_chunks(inode).await.unwrap(), "la lectura sigue sirviéndose de caché");
+    }
+
+    #[tokio::test]
+    async fn test_upload_keeps_reserved_id() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let reserved = fx.mock.generate_ids(1).await.unwrap().remove(0);
+
+        let inode = fx.db.create_local_inode(&reserved).await.unwrap();
+        fx.db.upsert_file_metadata(inode, 5, 1_700_000_000, 0o644, false, Some("text/plain"), true, false, true)
+            .await
+            .unwrap();
+        fx.db.upsert_dentry(1, inode, "reservado.txt").await.unwrap();
+        fx.edit_locally(inode, &reserved, b"hola!").await;
+
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+
+        assert_eq!(fx.mock.content(&reserved).as_deref(), Some(&b"hola!"[..]), "creado con el ID reservado");
+        assert_eq!(fx.db.get_gdrive_id(inode).await.unwrap().as_deref(), Some(reserved.as_str()));
+        assert!(!fx.db.is_pending_create(inode).await.unwrap());
+        assert!(fx.dir.path().join("cache").join(&reserved).exists(), "la caché sigue en su ruta");
+    }
+
+    #[tokio::test]
+    async fn test_upload_updates_existing_file() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+
+        fx.edit_locally(inode, "file-nota", b"hola, editado").await;
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+
+        assert_eq!(fx.mock.content("file-nota").as_deref(), Some(&b"hola, editado"[..]));
+        assert_eq!(
+            fx.db.get_remote_md5(inode).await.unwrap(),
+            fx.mock.file("file-nota").unwrap().md5_checksum,
+            "el md5 conocido se actualiza tras subir"
+        );
+        assert!(!fx.db.is_dirty(inode).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_upload_waits_for_hydration() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let mut fx = fixture().await;
+        let inflight = Arc::new(InflightDownloads::default());
+        fx.uploader.inflight = inflight.clone();
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+
+        let hydration = inflight.hydrate(inode);
+        fx.edit_locally(inode, "file-nota", b"hola, editado").await;
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 0);
+        assert_eq!(fx.mock.calls("update_file_content"), 0, "no se sube con la caché a medias");
+        assert!(fx.db.is_dirty(inode).await.unwrap());
+        assert_eq!(fx.db.sync_state(inode).await.unwrap().unwrap().retry_count, 0, "aplazar no gasta reintentos");
+
+        drop(hydration);
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+        assert_eq!(fx.mock.content("file-nota").as_deref(), Some(&b"hola, editado"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_upload_hydrates_partial_cache_first() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+
+        // Solo se leyeron los 2 primeros bytes y se editó uno de ellos
+        crate::utils::cache_crypto::write_file(&fx.cache_path("file-nota"), b"hX".to_vec()).await.unwrap();
+        fx.db.add_cached_chunk(inode, 0, 1).await.unwrap();
+        fx.db.mark_dirty(inode).await.unwrap();
+
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+
+        assert_eq!(fx.mock.calls("download_chunk"), 1);
+        assert_eq!(fx.mock.content("file-nota").as_deref(), Some(&b"hXla"[..]), "no se sube truncado");
+        assert!(fx.db.get_missing_ranges(inode, 0, 3).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_upload_keeps_untracked_local_edits() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+
+        // Caché editada sin chunks registrados (anterior al registro de chunks)
+        crate::utils::cache_crypto::write_file(&fx.cache_path("file-nota"), b"HOLA".to_vec()).await.unwrap();
+        fx.db.mark_dirty(inode).await.unwrap();
+
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+
+        assert_eq!(fx.mock.calls("download_chunk"), 0);
+        assert_eq!(fx.mock.content("file-nota").as_deref(), Some(&b"HOLA"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_upload_conflict_keeps_remote_and_uploads_copy() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+
+        // Otro cliente edita el archivo antes de que subamos la edición local
+        fx.mock.modify_remote("file-nota", b"version remota");
+        fx.edit_locally(inode, "file-nota", b"version local").await;
+        fx.uploader.upload_cycle().await.unwrap();
+
+        assert_eq!(fx.mock.content("file-nota").as_deref(), Some(&b"version remota"[..]));
+        assert_eq!(fx.mock.calls("update_file_content"), 0);
+        let copy = fx.mock.find_by_name_prefix("nota (Conflicto local ").expect("copia de conflicto");
+        assert!(copy.name.unwrap().ends_with(").txt"));
+        let copy_id = copy.id.unwrap();
+        assert_eq!(fx.mock.content(&copy_id).as_deref(), Some(&b"version local"[..]));
+        assert!(!fx.db.is_dirty(inode).await.unwrap());
+
+        let conflicts = fx.db.list_conflicts().await.unwrap();
+        assert_eq!(conflicts.len(), 1, "el conflicto queda registrado para la GUI");
+        assert_eq!(conflicts[0].inode, inode);
+        assert_eq!(conflicts[0].original_gdrive_id, "file-nota");
+        assert_eq!(conflicts[0].conflict_gdrive_id, copy_id);
+
+        // El original vuelve a la versión de Drive
+        assert_eq!(fx.db.get_attrs(inode).await.unwrap().size, 14);
+        assert!(!fx.db.has_any_chunks(inode).await.unwrap());
+        assert!(!fx.cache_path("file-nota").exists());
+        assert_eq!(fx.db.get_remote_md5(inode).await.unwrap(), fx.mock.file("file-nota").unwrap().md5_checksum);
+    }
+
+    #[tokio::test]
+    async fn test_upload_rate_limit_keeps_file_queued() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        fx.edit_locally(inode, "file-nota", b"hola editado").await;
+        fx.mock.fail_next_with(
+            "get_file_metadata",
+            DriveError::RateLimited { retry_after: Some(Duration::from_secs(7)), message: "429".into() },
+        );
+
+        let error = fx.uploader.upload_cycle().await.unwrap_err();
+
+        assert_eq!(DriveError::find(&error).and_then(DriveError::retry_after), Some(Duration::from_secs(7)));
+        let state = fx.db.sync_state(inode).await.unwrap().unwrap();
+        assert!(state.dirty);
+        assert_eq!(state.retry_count, 0, "un límite de peticiones no gasta reintentos del archivo");
+
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+        assert_eq!(fx.mock.content("file-nota").as_deref(), Some(&b"hola editado"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_upload_restores_file_deleted_remotely() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+
+        // Eliminado para siempre en Drive con una edición local pendiente
+        fx.mock.remove_remote("file-nota");
+        fx.edit_locally(inode, "file-nota", b"cambios sin perder").await;
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+
+        let restored = fx.mock.find_by_name_prefix("nota.txt").expect("nota.txt subida de nuevo");
+        let restored_id = restored.id.unwrap();
+        assert_ne!(restored_id, "file-nota");
+        assert_eq!(fx.mock.content(&restored_id).as_deref(), Some(&b"cambios sin perder"[..]));
+        assert_eq!(fx.db.get_gdrive_id(inode).await.unwrap(), Some(restored_id));
+        assert!(!fx.db.is_dirty(inode).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_new_file_renamed_over_existing_waits_for_trash() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let nota = fx.db.get_inode_by_gdrive_id("file-nota").await.unwrap().unwrap();
+
+        // Crear borrador.txt y renombrarlo sobre nota.txt antes del ciclo
+        let nuevo = fx.db.create_local_inode("temp_borrador").await.unwrap();
+        fx.db.upsert_file_metadata(nuevo, 5, 1_700_000_000, 0o644, false, Some("text/plain"), true, false, true)
+            .await
+            .unwrap();
+        fx.db.upsert_dentry(1, nuevo, "borrador.txt").await.unwrap();
+        fx.edit_locally(nuevo, "temp_borrador", b"nueva").await;
+        fx.db.record_upload_op(nuevo, UploadOp::Create).await.unwrap();
+        fx.db.soft_delete_by_gdrive_id("file-nota").await.unwrap();
+        fx.db.record_upload_op(nota, UploadOp::Delete).await.unwrap();
+        fx.db.upsert_dentry(1, nuevo, "nota.txt").await.unwrap();
+        fx.db.mark_dirty(nuevo).await.unwrap();
+        fx.db.record_upload_op(nuevo, UploadOp::Rename).await.unwrap();
+
+        // La papelera va primero: si Drive frena, la creación espera al próximo ciclo
+        fx.mock.fail_next_with("trash_file", DriveError::RateLimited { retry_after: None, message: "lento".into() });
+        assert!(fx.uploader.upload_cycle().await.is_err());
+        assert_eq!(fx.mock.calls("upload_file"), 0, "no crear nota.txt mientras la anterior sigue en Drive");
+
+        fx.uploader.upload_cycle().await.unwrap();
+        assert_eq!(fx.mock.file("file-nota").unwrap().trashed, Some(true));
+        let remote_id = fx.db.get_gdrive_id(nuevo).await.unwrap().unwrap();
+        assert_eq!(fx.mock.file(&remote_id).unwrap().name.as_deref(), Some("nota.txt"));
+        assert!(fx.db.pending_upload_ops().await.unwrap().is_empty(), "diario consumido");
+    }
+
+    #[tokio::test]
+    async fn test_failed_barrier_ends_cycle() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let nota = fx.db.get_inode_by_gdrive_id("file-nota").await.unwrap().unwrap();
+        fx.db.soft_delete_by_gdrive_id("file-nota").await.unwrap();
+        fx.db.record_upload_op(nota, UploadOp::Delete).await.unwrap();
+        let nuevo = fx.db.create_local_inode("temp_nuevo").await.unwrap();
+        fx.db.upsert_file_metadata(nuevo, 5, 1_700_000_000, 0o644, false, Some("text/plain"), true, false, true)
+            .await
+            .unwrap();
+        fx.db.upsert_dentry(1, nuevo, "nota.txt").await.unwrap();
+        fx.edit_locally(nuevo, "temp_nuevo", b"nueva").await;
+        fx.db.record_upload_op(nuevo, UploadOp::Create).await.unwrap();
+
+        fx.mock.fail_next("trash_file", "500 backendError");
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 0);
+
+        assert_eq!(fx.mock.calls("upload_file"), 0, "los lotes tras la barrera fallida esperan");
+        assert!(fx.db.sync_state(nuevo).await.unwrap().unwrap().dirty);
+    }
+
+    #[tokio::test]
+    async fn test_delete_without_trash_permission_restores_without_calling_drive() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        let read_only = crate::db::FileCapabilities { can_edit: false, can_trash: false, owners: Vec::new() };
+        fx.db.upsert_capabilities(inode, &read_only).await.unwrap();
+
+        fx.db.soft_delete_by_gdrive_id("file-nota").await.unwrap();
+        fx.db.mark_dirty(inode).await.unwrap();
+        fx.uploader.upload_cycle().await.unwrap();
+
+        assert_eq!(fx.mock.calls("trash_file"), 0, "sin intento de papelera condenado a fallar");
+        assert_eq!(fx.db.lookup(1, "nota.txt").await.unwrap(), Some(inode), "restaurado localmente");
+        assert!(!fx.db.is_dirty(inode).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_permanent_delete_removes_trashed_file() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        crate::utils::cache_crypto::write_file(&fx.cache_path("file-nota"), b"hola".to_vec()).await.unwrap();
+
+        fx.db.soft_delete_by_gdrive_id("file-nota").await.unwrap();
+        fx.db.record_upload_op(inode, UploadOp::Delete).await.unwrap();
+        fx.uploader.upload_cycle().await.unwrap();
+        assert_eq!(fx.mock.file("file-nota").unwrap().trashed, Some(true));
+        assert_eq!(fx.db.list_trashed().await.unwrap().len(), 1, "sigue en .Trash");
+
+        assert!(fx.db.request_permanent_delete(inode).await.unwrap());
+        assert!(fx.db.list_trashed().await.unwrap().is_empty());
+        fx.uploader.upload_cycle().await.unwrap();
+
+        assert_eq!(fx.mock.calls("delete_file"), 1);
+        assert!(fx.mock.file("file-nota").is_none());
+        assert_eq!(fx.db.get_inode_by_gdrive_id("file-nota").await.unwrap(), None);
+        assert!(!fx.cache_path("file-nota").exists(), "sin caché huérfana");
+        assert!(fx.uploader.history.recent(5).iter().any(|e| e.description.contains("nota.txt")), "con el nombre, no el gdrive_id");
+    }
+
+    #[tokio::test]
+    async fn test_upload_quota_error_is_permanent() {
+        let _net = crate::utils::network::TEST_STATE_LOCK.lock().await;
+        crate::utils::network::set_online();
+        crate::utils::network::set_metered_pause(false);
+        let fx = fixture().await;
+        let inode = fx.db.lookup(1, "nota.txt").await.unwrap().unwrap();
+        fx.edit_locally(inode, "file-nota", b"no cabe").await;
+        fx.mock.fail_next_with("update_file_content", DriveError::StorageFull("403 storageQuotaExceeded".into()));
+
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 0);
+
+        let state = fx.db.sync_state(inode).await.unwrap().unwrap();
+        assert_eq!(state.status, crate::db::UploadStatus::Error);
+        let problems = fx.db.list_upload_problems().await.unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].permanent, "sin reintento hasta que el usuario lo pida");
+        assert!(fx.uploader.storage_quota.is_full(), "FUSE debe rechazar escrituras nuevas");
+
+        // Con espacio liberado, reintentar los rechazados y subir bien limpia el aviso
+        for rejected in fx.uploader.storage_quota.clone().take_rejected() {
+            fx.db.retry_upload_now(rejected).await.unwrap();
+        }
+        assert_eq!(fx.uploader.upload_cycle().await.unwrap(), 1);
+        assert!(!fx.uploader.storage_quota.is_full());
+    }
 }
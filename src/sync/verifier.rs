@@ -0,0 +1,256 @@
+//! Verificador en background de la integridad de la caché de contenido
+//!
+//! `file_cache_chunks` puede sobrevivir a un archivo de caché truncado o dañado
+//! (disco lleno, apagado brusco). FUSE sirve esos rangos sin volver a descargarlos,
+//! así que una caché inconsistente produce lecturas erróneas silenciosas.
+//! El verificador revisa periódicamente una muestra de archivos cacheados y
+//! descarta los que no coinciden con la DB o con los metadatos remotos.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::db::{CachedFileSample, MetadataRepository};
+use crate::fuse::content_store::ContentStore;
+use crate::fuse::file_locks::FileLocks;
+use crate::gdrive::DriveApi;
+use crate::i18n::t;
+use crate::gui::history::{ActionHistory, ActionType};
+
+/// Archivos revisados por ronda (la muestra es aleatoria en cada ronda)
+const DEFAULT_SAMPLE_SIZE: u32 = 20;
+
+/// Resultado de comparar un archivo de caché con lo que registra la DB
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CacheVerdict {
+    /// Tamaños consistentes; sin MD5 que comparar (caché parcial o MD5 desconocido)
+    Healthy,
+    /// Hay chunks registrados pero el archivo de caché no existe
+    Missing,
+    /// El archivo es más corto que el último chunk registrado
+    Truncated { len: u64, expected: u64 },
+    /// Los chunks llegan más allá del tamaño remoto actual
+    BeyondRemoteSize { cached: u64, size: u64 },
+    /// Caché completa con MD5 conocido: hay que calcular el hash
+    NeedsChecksum,
+}
+
+/// Verificador periódico de la caché de contenido
+pub struct CacheVerifier {
+    db: Arc<MetadataRepository>,
+    cache_dir: PathBuf,
+    store: ContentStore,
+    interval: Duration,
+    history: ActionHistory,
+    sample_size: u32,
+    client: Option<Arc<dyn DriveApi>>,
+    file_locks: FileLocks,
+}
+
+impl CacheVerifier {
+    /// Crea un nuevo verificador
+    pub fn new(
+        db: Arc<MetadataRepository>,
+        cache_dir: impl AsRef<Path>,
+        interval_secs: u64,
+        history: ActionHistory,
+    ) -> Self {
+        Self {
+            db,
+            cache_dir: cache_dir.as_ref().to_path_buf(),
+            store: ContentStore::new(&cache_dir),
+            interval: Duration::from_secs(interval_secs),
+            history,
+            sample_size: DEFAULT_SAMPLE_SIZE,
+            client: None,
+            file_locks: FileLocks::default(),
+        }
+    }
+
+    /// Confirma con la API los MD5 que no coinciden antes de descartar la caché
+    /// (el `remote_md5` de la DB puede estar desactualizado)
+    pub fn with_drive_client(mut self, client: Arc<dyn DriveApi>) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Publica en el almacén bajo los mismos locks por inode que FUSE
+    pub fn with_file_locks(mut self, file_locks: FileLocks) -> Self {
+        self.file_locks = file_locks;
+        self
+    }
+
+    /// Inicia el loop de verificación en un task de Tokio separado
+    pub fn spawn(self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!("🩺 Verificador de caché iniciado (intervalo: {:?}, muestra: {})", self.interval, self.sample_size);
+
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(self.interval) => {}
+                    _ = crate::utils::shutdown::wait_for_shutdown() => {
+                        info!("🛑 Verificador de caché: Shutdown detectado, deteniendo.");
+                        break;
+                    }
+                }
+
+                match self.verify_once().await {
+                    Ok((checked, invalidated)) if invalidated > 0 => {
+                        warn!("🩺 Verificación de caché: {} de {} archivos descartados", invalidated, checked);
+                    }
+                    Ok((checked, _)) => debug!("🩺 Verificación de caché: {} archivos correctos", checked),
+                    Err(e) => warn!("⚠️ Error verificando caché: {:?}", e),
+                }
+            }
+        })
+    }
+
+    /// Revisa una muestra de archivos cacheados.
+    /// Retorna (archivos revisados, archivos descartados)
+    pub async fn verify_once(&self) -> Result<(usize, usize)> {
+        let samples = self.db.sample_cached_files(self.sample_size).await?;
+        let mut invalidated = 0;
+
+        for sample in &samples {
+            let cache_path = self.cache_dir.join(&sample.gdrive_id);
+            let file_len = tokio::fs::metadata(&cache_path).await.ok().map(|m| m.len());
+
+            let reason = match check_cache_file(sample, file_len) {
+                CacheVerdict::Healthy => None,
+                CacheVerdict::NeedsChecksum => self.checksum_mismatch(sample, &cache_path).await,
+                CacheVerdict::Missing => Some("archivo de caché inexistente".to_string()),
+                CacheVerdict::Truncated { len, expected } => {
+                    Some(format!("caché truncada ({} de {} bytes)", len, expected))
+                }
+                CacheVerdict::BeyondRemoteSize { cached, size } => {
+                    Some(format!("chunks hasta {} bytes con tamaño remoto {}", cached, size))
+                }
+            };
+
+            if let Some(reason) = reason {
+                self.invalidate(sample, &cache_path, &reason).await?;
+                invalidated += 1;
+            }
+        }
+
+        // Entradas del almacén que ya no enlaza ninguna caché (descartadas o reemplazadas)
+        if let Err(e) = self.store.collect_garbage().await {
+            debug!("No se pudo limpiar el almacén de contenido: {:?}", e);
+        }
+
+        Ok((samples.len(), invalidated))
+    }
+
+    /// Calcula el MD5 de una caché completa. Retorna el motivo si no coincide con
+    /// el remoto; si solo la DB estaba desactualizada, la corrige.
+    async fn checksum_mismatch(&self, sample: &CachedFileSample, cache_path: &Path) -> Option<String> {
+        let expected = sample.remote_md5.as_deref()?;
+        // Hasta publicar: una escritura entre medias dejaría otro contenido bajo este MD5
+        let guard = self.file_locks.lock(sample.inode).await;
+        let actual = match crate::utils::hash::compute_file_md5(cache_path).await {
+            Ok(md5) => md5,
+            Err(e) => return Some(format!("caché ilegible: {:#}", e)),
+        };
+        if actual == expected {
+            // Verificada: las copias idénticas pueden enlazarla en vez de descargarla
+            if let Err(e) = self.store.publish_verified(&self.db, sample.inode, cache_path, expected).await {
+                debug!("No se pudo publicar la caché de inode {}: {:?}", sample.inode, e);
+            }
+            return None;
+        }
+        drop(guard);
+
+        // Confirmar con Drive: si el remoto coincide con la caché, la DB era la desactualizada
+        if let Some(client) = &self.client {
+            match client.get_file_md5(&sample.gdrive_id).await {
+                Ok(Some(remote)) if remote == actual => {
+                    debug!("🩺 MD5 de DB desactualizado para inode {}, corrigiendo", sample.inode);
+                    if let Err(e) = self.db.set_remote_md5(sample.inode, &remote).await {
+                        warn!("⚠️ No se pudo actualizar MD5 de inode {}: {:?}", sample.inode, e);
+                    }
+                    return None;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    // Sin confirmación no se descarta nada: se revisará en otra ronda
+                    debug!("No se pudo confirmar MD5 remoto de inode {}: {:?}", sample.inode, e);
+                    return None;
+                }
+            }
+        }
+
+        Some(format!("MD5 {} no coincide con {}", actual, expected))
+    }
+
+    /// Descarta la caché de un archivo: la próxima lectura la descarga de nuevo
+    async fn invalidate(&self, sample: &CachedFileSample, cache_path: &Path, reason: &str) -> Result<()> {
+        warn!("💀 Caché inválida para inode {} ({}): {}. Descartando.", sample.inode, sample.gdrive_id, reason);
+
+        self.db.clear_chunks(sample.inode).await?;
+        match tokio::fs::remove_file(cache_path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("⚠️ No se pudo eliminar caché {:?}: {}", cache_path, e),
+        }
+
+        let name = self.db.get_file_name(sample.inode).await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| sample.gdrive_id.clone());
+        self.history.log(ActionType::Error, t!("activity-cache-discarded", name = name, reason = reason));
+        Ok(())
+    }
+}
+
+/// Compara el tamaño del archivo de caché con los chunks y el tamaño remoto
+fn check_cache_file(sample: &CachedFileSample, file_len: Option<u64>) -> CacheVerdict {
+    let Some(len) = file_len else {
+        return CacheVerdict::Missing;
+    };
+
+    let expected = sample.max_cached_end + 1;
+    if len < expected {
+        return CacheVerdict::Truncated { len, expected };
+    }
+    if expected > sample.size {
+        return CacheVerdict::BeyondRemoteSize { cached: expected, size: sample.size };
+    }
+    if sample.size > 0 && sample.cached_bytes >= sample.size && sample.remote_md5.is_some() {
+        return CacheVerdict::NeedsChecksum;
+    }
+    CacheVerdict::Healthy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    fn sample(size: u64, max_cached_end: u64, cached_bytes: u64, remote_md5: Option<&str>) -> CachedFileSample {
+        CachedFileSample {
+            inode: 2,
+            gdrive_id: "file_doc".to_string(),
+            size,
+            remote_md5: remote_md5.map(str::to_string),
+            max_cached_end,
+            cached_bytes,
+        }
+    }
+
+    #[rstest]
+    #[case::missing(sample(100, 99, 100, None), None, CacheVerdict::Missing)]
+    #[case::truncated(sample(100, 99, 100, None), Some(50), CacheVerdict::Truncated { len: 50, expected: 100 })]
+    #[case::beyond_remote(sample(10, 99, 100, None), Some(100), CacheVerdict::BeyondRemoteSize { cached: 100, size: 10 })]
+    #[case::complete_with_md5(sample(100, 99, 100, Some("abc")), Some(100), CacheVerdict::NeedsChecksum)]
+    #[case::complete_without_md5(sample(100, 99, 100, None), Some(100), CacheVerdict::Healthy)]
+    #[case::partial(sample(100, 49, 50, Some("abc")), Some(100), CacheVerdict::Healthy)]
+    fn test_check_cache_file(
+        #[case] sample: CachedFileSample,
+        #[case] file_len: Option<u64>,
+        #[case] expected: CacheVerdict,
+    ) {
+        assert_eq!(check_cache_file(&sample, file_len), expected);
+    }
+}
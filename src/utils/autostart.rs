@@ -0,0 +1,126 @@
+//! Inicio automático al iniciar sesión (XDG autostart)
+//!
+//! Activarlo escribe `~/.config/autostart/org.gnome.FedoraDrive.desktop`, que
+//! lanza este ejecutable con `--start-hidden`: la aplicación arranca solo con
+//! el icono de bandeja y sin presentar la ventana. Desactivarlo borra el
+//! archivo. El estado es la existencia del archivo, no hay campo en la config.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Argumento que arranca directamente en la bandeja, sin ventana
+pub const START_HIDDEN_FLAG: &str = "--start-hidden";
+
+/// Nombre de la entrada: el id de la aplicación, igual que el `.desktop` instalado
+const DESKTOP_FILE_NAME: &str = "org.gnome.FedoraDrive.desktop";
+
+/// Directorio XDG de autostart (`$XDG_CONFIG_HOME/autostart`)
+pub fn autostart_dir() -> PathBuf {
+    dirs::config_dir()
+        .or_else(|| dirs::home_dir().map(|h| h.join(".config")))
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+        .join("autostart")
+}
+
+/// Si el inicio automático está activado
+pub fn is_enabled() -> bool {
+    is_enabled_in(&autostart_dir())
+}
+
+/// Activa o desactiva el inicio automático con el ejecutable actual
+pub fn set_enabled(enabled: bool) -> Result<()> {
+    if enabled {
+        let exe = std::env::current_exe().context("No se pudo determinar el ejecutable actual")?;
+        enable_in(&autostart_dir(), &exe)
+    } else {
+        disable_in(&autostart_dir())
+    }
+}
+
+fn is_enabled_in(dir: &Path) -> bool {
+    dir.join(DESKTOP_FILE_NAME).is_file()
+}
+
+fn enable_in(dir: &Path, exe: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Error creando {:?}", dir))?;
+    let path = dir.join(DESKTOP_FILE_NAME);
+    std::fs::write(&path, desktop_entry(exe)).with_context(|| format!("Error escribiendo {:?}", path))?;
+    tracing::info!("🚀 Inicio automático activado: {:?}", path);
+    Ok(())
+}
+
+fn disable_in(dir: &Path) -> Result<()> {
+    let path = dir.join(DESKTOP_FILE_NAME);
+    match std::fs::remove_file(&path) {
+        Ok(()) => {
+            tracing::info!("🚀 Inicio automático desactivado");
+            Ok(())
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Error borrando {:?}", path)),
+    }
+}
+
+/// Contenido de la entrada de autostart para `exe`
+fn desktop_entry(exe: &Path) -> String {
+    format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=G-DriveXP\n\
+         Comment=Cliente de Google Drive para Linux\n\
+         Exec={} {}\n\
+         Icon=org.gnome.FedoraDrive\n\
+         Terminal=false\n\
+         NoDisplay=true\n\
+         X-GNOME-Autostart-enabled=true\n",
+        quote_exec_arg(&exe.to_string_lossy()),
+        START_HIDDEN_FLAG,
+    )
+}
+
+/// Entrecomilla un argumento de `Exec=` si lo necesita (especificación Desktop Entry)
+fn quote_exec_arg(arg: &str) -> String {
+    const RESERVED: &[char] = &[' ', '\t', '"', '\'', '\\', '>', '<', '~', '|', '&', ';', '$', '*', '?', '#', '(', ')', '`'];
+    if !arg.contains(RESERVED) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::plain("/usr/bin/g-drive-xp", "/usr/bin/g-drive-xp")]
+    #[case::space("/home/ana/Mis Apps/g-drive-xp", "\"/home/ana/Mis Apps/g-drive-xp\"")]
+    #[case::dollar("/opt/$app/g-drive-xp", "\"/opt/\\$app/g-drive-xp\"")]
+    fn test_quote_exec_arg(#[case] arg: &str, #[case] expected: &str) {
+        assert_eq!(quote_exec_arg(arg), expected);
+    }
+
+    #[test]
+    fn test_enable_and_disable() {
+        let dir = tempfile::tempdir().unwrap();
+        let autostart = dir.path().join("autostart");
+        assert!(!is_enabled_in(&autostart));
+
+        enable_in(&autostart, Path::new("/usr/bin/g-drive-xp")).unwrap();
+        assert!(is_enabled_in(&autostart));
+        let entry = std::fs::read_to_string(autostart.join(DESKTOP_FILE_NAME)).unwrap();
+        assert!(entry.contains("\nExec=/usr/bin/g-drive-xp --start-hidden\n"), "{}", entry);
+
+        disable_in(&autostart).unwrap();
+        assert!(!is_enabled_in(&autostart));
+        disable_in(&autostart).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+//! Contrapresión sobre las escrituras locales
+//!
+//! Copiar 200 GB al montaje llenaría `cache_dir` mucho antes de que el
+//! uploader pudiera subirlos. `sync::backpressure::BackpressureMonitor` mide
+//! periódicamente los datos pendientes de subir y el espacio libre del disco
+//! de la caché y publica aquí si hay que frenar; FUSE consulta `current()`
+//! antes de aceptar datos nuevos y, según `Config.backpressure_mode`, espera
+//! en `wait_for_relief` o falla con `ENOSPC`. La GUI muestra el motivo.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Motivo por el que se frenan las escrituras
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pressure {
+    /// Los datos pendientes de subir superan `max_dirty_backlog_mb`
+    DirtyBacklog,
+    /// Quedan menos de `min_free_space_mb` libres en el disco de `cache_dir`
+    LowDiskSpace,
+}
+
+/// Límites de `Config` en bytes (0 = sin límite)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_dirty_bytes: u64,
+    pub min_free_bytes: u64,
+}
+
+impl Limits {
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        Self::from_mb(config.max_dirty_backlog_mb, config.min_free_space_mb)
+    }
+
+    /// Un valor en MB demasiado grande para `u64` en bytes queda en `u64::MAX`
+    fn from_mb(max_dirty_mb: u64, min_free_mb: u64) -> Self {
+        Self {
+            max_dirty_bytes: max_dirty_mb.saturating_mul(1024 * 1024),
+            min_free_bytes: min_free_mb.saturating_mul(1024 * 1024),
+        }
+    }
+
+    /// Sin ningún límite activo no hace falta medir nada
+    pub fn is_disabled(&self) -> bool {
+        self.max_dirty_bytes == 0 && self.min_free_bytes == 0
+    }
+
+    /// Motivo de contrapresión para las medidas dadas. El disco lleno tiene
+    /// prioridad: es el que hace fallar las escrituras en la caché.
+    pub fn evaluate(&self, dirty_bytes: u64, free_bytes: u64) -> Option<Pressure> {
+        if self.min_free_bytes > 0 && free_bytes < self.min_free_bytes {
+            Some(Pressure::LowDiskSpace)
+        } else if self.max_dirty_bytes > 0 && dirty_bytes > self.max_dirty_bytes {
+            Some(Pressure::DirtyBacklog)
+        } else {
+            None
+        }
+    }
+}
+
+/// 0 = sin contrapresión; si no, `Pressure` codificado con `encode`
+static PRESSURE: AtomicU8 = AtomicU8::new(0);
+
+/// Despierta a las escrituras que esperan en `wait_for_relief`
+static RELIEF_NOTIFY: Notify = Notify::const_new();
+
+fn encode(pressure: Option<Pressure>) -> u8 {
+    match pressure {
+        None => 0,
+        Some(Pressure::DirtyBacklog) => 1,
+        Some(Pressure::LowDiskSpace) => 2,
+    }
+}
+
+fn decode(value: u8) -> Option<Pressure> {
+    match value {
+        1 => Some(Pressure::DirtyBacklog),
+        2 => Some(Pressure::LowDiskSpace),
+        _ => None,
+    }
+}
+
+/// Motivo de contrapresión actual (`None` = se aceptan escrituras)
+#[inline]
+pub fn current() -> Option<Pressure> {
+    decode(PRESSURE.load(Ordering::SeqCst))
+}
+
+/// Publica el motivo de contrapresión. Retorna `true` si es una transición.
+pub fn set(pressure: Option<Pressure>) -> bool {
+    let changed = PRESSURE.swap(encode(pressure), Ordering::SeqCst) != encode(pressure);
+    if changed {
+        match pressure {
+            Some(Pressure::DirtyBacklog) => {
+                tracing::warn!("🚦 Demasiados datos pendientes de subir: se frenan las escrituras locales")
+            }
+            Some(Pressure::LowDiskSpace) => {
+                tracing::warn!("🚦 Poco espacio libre en el disco de la caché: se frenan las escrituras locales")
+            }
+            None => {
+                tracing::info!("🚦 Escrituras locales aceptadas de nuevo");
+                RELIEF_NOTIFY.notify_waiters();
+            }
+        }
+    }
+    changed
+}
+
+/// Espera a que no haya contrapresión, como mucho `timeout`. Retorna `true`
+/// si se puede escribir.
+pub async fn wait_for_relief(timeout: Duration) -> bool {
+    let wait = async {
+        loop {
+            let notified = RELIEF_NOTIFY.notified();
+            if current().is_none() {
+                return;
+            }
+            notified.await;
+        }
+    };
+    tokio::time::timeout(timeout, wait).await.is_ok()
+}
+
+/// Bytes disponibles para el usuario en el sistema de archivos de `path`
+pub fn available_space(path: &Path) -> std::io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    #[rstest]
+    #[case::within_limits(10 * MB, 500 * MB, None)]
+    #[case::backlog(101 * MB, 500 * MB, Some(Pressure::DirtyBacklog))]
+    #[case::low_disk(10 * MB, 49 * MB, Some(Pressure::LowDiskSpace))]
+    #[case::both_reports_disk(101 * MB, 49 * MB, Some(Pressure::LowDiskSpace))]
+    fn test_evaluate(#[case] dirty: u64, #[case] free: u64, #[case] expected: Option<Pressure>) {
+        let limits = Limits { max_dirty_bytes: 100 * MB, min_free_bytes: 50 * MB };
+        assert_eq!(limits.evaluate(dirty, free), expected);
+    }
+
+    #[test]
+    fn test_evaluate_zero_disables_limit() {
+        let limits = Limits { max_dirty_bytes: 0, min_free_bytes: 0 };
+        assert!(limits.is_disabled());
+        assert_eq!(limits.evaluate(u64::MAX, 0), None);
+    }
+
+    #[test]
+    fn test_from_mb_saturates_huge_values() {
+        let limits = Limits::from_mb(u64::MAX, 50);
+        assert_eq!(limits.max_dirty_bytes, u64::MAX);
+        assert_eq!(limits.min_free_bytes, 50 * MB);
+    }
+
+    #[test]
+    fn test_available_space_of_temp_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(available_space(dir.path()).unwrap() > 0);
+        assert!(available_space(&dir.path().join("no-existe")).is_err());
+    }
+}
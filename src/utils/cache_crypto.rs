@@ -0,0 +1,748 @@
+//! Cifrado en reposo de la caché de contenido (`cache_dir/<gdrive_id>`)
+//!
+//! Cada archivo de caché tiene su propia clave ChaCha20, envuelta con
+//! ChaCha20-Poly1305 bajo una clave maestra guardada en el keyring y almacenada
+//! en el xattr `user.gdrivexp.key` del propio archivo (sigue al archivo en
+//! renombrados y desaparece con él). El cifrado de flujo es posicional: los
+//! offsets no cambian, así que FUSE sigue leyendo y escribiendo rangos sueltos.
+//!
+//! Solo protege la confidencialidad en reposo: el nonce de cada archivo es
+//! fijo, así que reescribir un rango en el sitio reutiliza el mismo flujo (quien
+//! vea ambas versiones del disco obtiene el XOR de los dos contenidos), y no hay
+//! comprobación de integridad (un byte alterado se descifra alterado). Por eso
+//! una caché que se separa de sus copias (`copy_file`) recibe clave nueva.
+//!
+//! Un archivo sin xattr es texto plano (cachés previas a activar el cifrado, o
+//! cifrado desactivado): se lee y escribe tal cual. Por eso la clave maestra se
+//! carga siempre que exista, aunque el cifrado de archivos nuevos esté apagado.
+//!
+//! Las extensiones (truncate a un tamaño mayor, escrituras más allá del final)
+//! dejan huecos sin asignar también en archivos cifrados: solo se escriben
+//! ceros cifrados en los bloques parciales de los bordes, y al leer los huecos
+//! (`SEEK_HOLE`/`SEEK_DATA`) no se descifran. Si el sistema de archivos no
+//! informa de huecos, se rellenan enteros como antes.
+
+use anyhow::{Context, Result};
+use chacha20::ChaCha20;
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20poly1305::aead::Aead;
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit};
+use std::ffi::CStr;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Cifrador global (instalado por `init` al arrancar)
+static CIPHER: OnceLock<CacheCipher> = OnceLock::new();
+
+/// Xattr con la clave del archivo envuelta por la clave maestra
+const KEY_XATTR: &CStr = c"user.gdrivexp.key";
+
+/// Versión del formato envuelto: versión (1) + nonce (12) + clave cifrada (32) + tag (16)
+const WRAP_VERSION: u8 = 1;
+const WRAPPED_LEN: usize = 1 + 12 + 32 + 16;
+
+/// Tamaño de bloque al rellenar con ceros cifrados
+const ZERO_BLOCK: usize = 1024 * 1024;
+
+type FileKey = [u8; 32];
+
+struct CacheCipher {
+    master: [u8; 32],
+    /// Asignar clave a los archivos de caché nuevos
+    encrypt_new_files: bool,
+}
+
+impl CacheCipher {
+    fn wrap(&self, key: &FileKey) -> Result<[u8; WRAPPED_LEN]> {
+        let mut nonce = [0u8; 12];
+        getrandom::getrandom(&mut nonce).context("Error generando nonce")?;
+        let sealed = ChaCha20Poly1305::new((&self.master).into())
+            .encrypt((&nonce).into(), key.as_slice())
+            .map_err(|_| anyhow::anyhow!("Error envolviendo clave de caché"))?;
+
+        let mut wrapped = [0u8; WRAPPED_LEN];
+        wrapped[0] = WRAP_VERSION;
+        wrapped[1..13].copy_from_slice(&nonce);
+        wrapped[13..].copy_from_slice(&sealed);
+        Ok(wrapped)
+    }
+
+    fn unwrap(&self, wrapped: &[u8]) -> Option<FileKey> {
+        if wrapped.len() != WRAPPED_LEN || wrapped[0] != WRAP_VERSION {
+            return None;
+        }
+        let key = ChaCha20Poly1305::new((&self.master).into())
+            .decrypt(wrapped[1..13].into(), &wrapped[13..])
+            .ok()?;
+        key.try_into().ok()
+    }
+}
+
+/// Carga la clave maestra del keyring e instala el cifrador.
+/// Con `encrypt_new_files` genera la clave maestra si no existe.
+/// Retorna si hay cifrador activo (para leer cachés cifradas).
+pub fn init(encrypt_new_files: bool) -> Result<bool> {
+    let storage = crate::auth::TokenStorage::new();
+    let master = match storage.load_cache_master_key()? {
+        Some(key) => key.try_into()
+            .map_err(|_| anyhow::anyhow!("Clave maestra de caché con longitud inválida"))?,
+        None if encrypt_new_files => {
+            let mut key = [0u8; 32];
+            getrandom::getrandom(&mut key).context("Error generando clave maestra")?;
+            storage.save_cache_master_key(&key)?;
+            key
+        }
+        None => return Ok(false),
+    };
+
+    let _ = CIPHER.set(CacheCipher { master, encrypt_new_files });
+    Ok(true)
+}
+
+/// Asigna clave propia a un archivo de caché recién creado (vacío).
+/// No hace nada si el cifrado está desactivado o el archivo ya tiene clave.
+/// Falla si el archivo ya tiene contenido: esos bytes están en claro y con la
+/// clave puesta se descifrarían como basura.
+pub fn init_file(file: &impl AsRawFd) -> io::Result<()> {
+    let Some(cipher) = CIPHER.get().filter(|c| c.encrypt_new_files) else {
+        return Ok(());
+    };
+    assign_key(cipher, file)
+}
+
+/// Pone una clave nueva envuelta por `cipher` en un archivo vacío sin clave
+fn assign_key(cipher: &CacheCipher, file: &impl AsRawFd) -> io::Result<()> {
+    if wrapped_key(file)?.is_some() {
+        return Ok(());
+    }
+    if fd_len(file)? != 0 {
+        return Err(io::Error::other("el archivo de caché ya tiene contenido en claro"));
+    }
+
+    let mut key: FileKey = [0u8; 32];
+    getrandom::getrandom(&mut key).map_err(io::Error::other)?;
+    let wrapped = cipher.wrap(&key).map_err(io::Error::other)?;
+
+    // XATTR_CREATE: si otro escritor ganó la carrera, se conserva su clave
+    let ret = unsafe {
+        libc::fsetxattr(
+            file.as_raw_fd(),
+            KEY_XATTR.as_ptr(),
+            wrapped.as_ptr() as *const libc::c_void,
+            wrapped.len(),
+            libc::XATTR_CREATE,
+        )
+    };
+    if ret != 0 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::EEXIST) {
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Tamaño actual del archivo según `fstat`
+fn fd_len(file: &impl AsRawFd) -> io::Result<u64> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(file.as_raw_fd(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stat.st_size as u64)
+}
+
+/// Clave envuelta tal como está en el xattr (None = texto plano)
+fn wrapped_key(file: &impl AsRawFd) -> io::Result<Option<Vec<u8>>> {
+    let mut wrapped = vec![0u8; WRAPPED_LEN];
+    let len = unsafe {
+        libc::fgetxattr(
+            file.as_raw_fd(),
+            KEY_XATTR.as_ptr(),
+            wrapped.as_mut_ptr() as *mut libc::c_void,
+            wrapped.len(),
+        )
+    };
+    if len < 0 {
+        let err = io::Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::ENODATA) | Some(libc::ENOTSUP) => Ok(None),
+            _ => Err(err),
+        };
+    }
+    wrapped.truncate(len as usize);
+    Ok(Some(wrapped))
+}
+
+/// Clave del archivo (None = texto plano)
+fn file_key(file: &impl AsRawFd) -> io::Result<Option<FileKey>> {
+    let Some(wrapped) = wrapped_key(file)? else {
+        return Ok(None);
+    };
+
+    // Archivo cifrado sin clave maestra usable: error, nunca servir el cifrado como datos
+    CIPHER.get()
+        .and_then(|cipher| cipher.unwrap(&wrapped))
+        .map(Some)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "clave de caché ilegible (¿clave maestra distinta?)"))
+}
+
+/// XOR del flujo ChaCha20 de `key` a partir de `offset` (cifra y descifra)
+fn apply_keystream(key: &FileKey, offset: u64, buf: &mut [u8]) {
+    let mut stream = ChaCha20::new(key.into(), &[0u8; 12].into());
+    stream.seek(offset);
+    stream.apply_keystream(buf);
+}
+
+/// Clave de un archivo abierto, para recorridos largos sin releer el xattr
+pub struct FileCipher(FileKey);
+
+impl FileCipher {
+    /// Cifra o descifra `buf` situado en `offset`
+    pub fn apply(&self, offset: u64, buf: &mut [u8]) {
+        apply_keystream(&self.0, offset, buf);
+    }
+
+    /// Descifra `buf`, leído de `file` en `offset`, sin tocar los huecos
+    /// (se leen como ceros y así deben quedar)
+    pub fn decrypt_read(&self, file: &impl AsRawFd, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+        for (start, end) in data_segments(file, offset, offset + buf.len() as u64)? {
+            self.apply(start, &mut buf[(start - offset) as usize..(end - offset) as usize]);
+        }
+        Ok(())
+    }
+}
+
+/// Cifrador del archivo (None = texto plano)
+pub fn file_cipher(file: &impl AsRawFd) -> io::Result<Option<FileCipher>> {
+    Ok(file_key(file)?.map(FileCipher))
+}
+
+/// Cifra `buf` (contenido plano en `offset`) antes de escribirlo en el archivo.
+/// Sin clave lo deja intacto.
+pub fn encrypt_at(file: &impl AsRawFd, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+    if let Some(cipher) = file_cipher(file)? {
+        cipher.apply(offset, buf);
+    }
+    Ok(())
+}
+
+/// Descifra `buf` (leído del archivo en `offset`). Sin clave lo deja intacto.
+pub fn decrypt_at(file: &impl AsRawFd, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+    if let Some(cipher) = file_cipher(file)? {
+        cipher.decrypt_read(file, offset, buf)?;
+    }
+    Ok(())
+}
+
+/// Tramos con datos asignados dentro de `[start, end)`, según
+/// `SEEK_DATA`/`SEEK_HOLE`. Sin soporte del sistema de archivos, todo el
+/// rango cuenta como datos. Conserva la posición del descriptor.
+pub fn data_segments(file: &impl AsRawFd, start: u64, end: u64) -> io::Result<Vec<(u64, u64)>> {
+    let fd = file.as_raw_fd();
+    let saved = unsafe { libc::lseek(fd, 0, libc::SEEK_CUR) };
+    let mut segments = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        let data = unsafe { libc::lseek(fd, pos as libc::off_t, libc::SEEK_DATA) };
+        if data < 0 {
+            let err = io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::ENXIO) => break, // Solo hueco hasta el final
+                Some(libc::EINVAL) | Some(libc::EOPNOTSUPP) => {
+                    segments.push((pos, end));
+                    break;
+                }
+                _ => {
+                    unsafe { libc::lseek(fd, saved, libc::SEEK_SET) };
+                    return Err(err);
+                }
+            }
+        }
+        let data = data as u64;
+        if data >= end {
+            break;
+        }
+        let hole = unsafe { libc::lseek(fd, data as libc::off_t, libc::SEEK_HOLE) };
+        let hole = if hole < 0 { end } else { (hole as u64).min(end) };
+        segments.push((data, hole));
+        pos = hole;
+    }
+    if saved >= 0 {
+        unsafe { libc::lseek(fd, saved, libc::SEEK_SET) };
+    }
+    Ok(segments)
+}
+
+/// Lee un archivo de caché completo en claro (las subidas usan `PlainReader`)
+#[cfg(test)]
+pub async fn read_file(path: &Path) -> io::Result<Vec<u8>> {
+    let file = tokio::fs::File::open(path).await?;
+    let mut data = tokio::fs::read(path).await?;
+    decrypt_at(&file, 0, &mut data)?;
+    Ok(data)
+}
+
+/// Lector en claro de un archivo de caché que descifra según la posición,
+/// sin cargarlo entero en memoria (para las subidas, que piden `Read + Seek`)
+pub struct PlainReader {
+    file: std::fs::File,
+    cipher: Option<FileCipher>,
+    pos: u64,
+    size: u64,
+}
+
+impl PlainReader {
+    /// Abre `path` y lee su clave (si está cifrado)
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let cipher = file_cipher(&file)?;
+        let size = file.metadata()?.len();
+        Ok(Self { file, cipher, pos: 0, size })
+    }
+
+    /// Tamaño del archivo al abrirlo
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl io::Read for PlainReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = io::Read::read(&mut self.file, buf)?;
+        if let Some(cipher) = &self.cipher {
+            cipher.decrypt_read(&self.file, self.pos, &mut buf[..n])?;
+        }
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl io::Seek for PlainReader {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.pos = io::Seek::seek(&mut self.file, pos)?;
+        Ok(self.pos)
+    }
+}
+
+/// Crea (o reemplaza) un archivo de caché con `data`, cifrado si corresponde
+pub async fn write_file(path: &Path, mut data: Vec<u8>) -> io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    // Archivo nuevo (no truncado) para no reutilizar la clave anterior con otro contenido;
+    // quien lo tenga abierto sigue leyendo la versión vieja con su propia clave
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let mut file = tokio::fs::File::create(path).await?;
+    init_file(&file)?;
+    encrypt_at(&file, 0, &mut data)?;
+    file.write_all(&data).await?;
+    file.flush().await
+}
+
+/// Crea (o reemplaza) un archivo de caché con el contenido en claro de `from`
+/// (ej. un archivo del espejo), cifrándolo por tramos sin cargarlo entero en
+/// memoria. Devuelve los bytes copiados.
+pub async fn import_file(from: &Path, to: &Path) -> io::Result<u64> {
+    // Archivo nuevo, como en `write_file`: la clave anterior no se reutiliza
+    match tokio::fs::remove_file(to).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let (from, to) = (from.to_path_buf(), to.to_path_buf());
+    tokio::task::spawn_blocking(move || {
+        use std::io::{Read, Write};
+
+        let mut src = std::fs::File::open(&from)?;
+        let mut dst = std::fs::File::create(&to)?;
+        init_file(&dst)?;
+        let cipher = file_cipher(&dst)?;
+        let mut buf = vec![0u8; ZERO_BLOCK];
+        let mut pos = 0u64;
+        loop {
+            let n = src.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            if let Some(cipher) = &cipher {
+                cipher.apply(pos, &mut buf[..n]);
+            }
+            dst.write_all(&buf[..n])?;
+            pos += n as u64;
+        }
+        dst.flush()?;
+        Ok(pos)
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Copia un archivo de caché con clave propia: descifra cada tramo con la
+/// clave del original y lo vuelve a cifrar con una nueva, así las dos copias
+/// pueden divergir sin reutilizar el mismo flujo. Conserva los huecos (copiarlos
+/// como ceros físicos los haría descifrarse como basura).
+pub async fn copy_file(from: &Path, to: &Path) -> io::Result<()> {
+    let (from, to) = (from.to_path_buf(), to.to_path_buf());
+    tokio::task::spawn_blocking(move || {
+        use std::io::{Read, Seek, SeekFrom, Write};
+
+        let mut src = std::fs::File::open(&from)?;
+        let mut dst = std::fs::File::create(&to)?;
+        let src_cipher = file_cipher(&src)?;
+        let dst_cipher = match &src_cipher {
+            Some(_) => {
+                // El original se pudo descifrar: la clave maestra está cargada
+                let cipher = CIPHER.get().ok_or_else(|| io::Error::other("sin clave maestra de caché"))?;
+                let mut key: FileKey = [0u8; 32];
+                getrandom::getrandom(&mut key).map_err(io::Error::other)?;
+                let wrapped = cipher.wrap(&key).map_err(io::Error::other)?;
+                let ret = unsafe {
+                    libc::fsetxattr(dst.as_raw_fd(), KEY_XATTR.as_ptr(), wrapped.as_ptr() as *const libc::c_void, wrapped.len(), 0)
+                };
+                if ret != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Some(FileCipher(key))
+            }
+            None => None,
+        };
+
+        let len = src.metadata()?.len();
+        dst.set_len(len)?;
+        let mut buf = vec![0u8; ZERO_BLOCK];
+        for (start, end) in data_segments(&src, 0, len)? {
+            src.seek(SeekFrom::Start(start))?;
+            dst.seek(SeekFrom::Start(start))?;
+            let mut pos = start;
+            while pos < end {
+                let n = (end - pos).min(buf.len() as u64) as usize;
+                src.read_exact(&mut buf[..n])?;
+                if let (Some(src_cipher), Some(dst_cipher)) = (&src_cipher, &dst_cipher) {
+                    src_cipher.apply(pos, &mut buf[..n]);
+                    dst_cipher.apply(pos, &mut buf[..n]);
+                }
+                dst.write_all(&buf[..n])?;
+                pos += n as u64;
+            }
+        }
+        dst.flush()
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Hace que `[from, to)` (más allá del final anterior, `from`) se lea como
+/// ceros y deja el archivo con al menos `to` bytes. Los bloques completos
+/// quedan como hueco (sin asignar); en archivos cifrados los bloques parciales
+/// de los bordes, que sí se asignan, llevan ceros cifrados porque unos ceros
+/// físicos se descifrarían como basura.
+pub async fn fill_zeros(file: &mut tokio::fs::File, from: u64, to: u64) -> io::Result<()> {
+    if file.metadata().await?.len() < to {
+        file.set_len(to).await?;
+    }
+    let Some(cipher) = file_cipher(file)? else {
+        return Ok(()); // Texto plano: el hueco ya se lee como ceros
+    };
+    if from >= to {
+        return Ok(());
+    }
+
+    let block = hole_block_size(file).await?;
+    let head_end = from.next_multiple_of(block).min(to);
+    let tail_start = (to / block * block).max(head_end);
+    write_zeros(file, &cipher, from, head_end).await?;
+    write_zeros(file, &cipher, tail_start, to).await?;
+
+    // Sin soporte de huecos el tramo central se leería como datos: rellenarlo
+    if head_end < tail_start && !data_segments(file, head_end, tail_start)?.is_empty() {
+        write_zeros(file, &cipher, head_end, tail_start).await?;
+    }
+    Ok(())
+}
+
+/// Prepara una escritura de `len` bytes en `offset`: rellena el hueco entre el
+/// final actual y `offset` y, en archivos cifrados, cifra los ceros de los
+/// bloques parciales que la escritura va a asignar dentro de un hueco
+pub async fn prepare_write(file: &mut tokio::fs::File, offset: u64, len: u64) -> io::Result<()> {
+    let old_len = file.metadata().await?.len();
+    if offset > old_len {
+        fill_zeros(file, old_len, offset).await?;
+    }
+    let Some(cipher) = file_cipher(file)? else {
+        return Ok(());
+    };
+
+    let block = hole_block_size(file).await?;
+    let end = offset + len;
+    let head = (offset / block * block, offset);
+    let tail = (end, end.next_multiple_of(block).min(old_len.max(offset)));
+    // Ambos bordes se miran antes de escribir: pueden caer en el mismo bloque
+    let mut in_hole = Vec::new();
+    for (start, stop) in [head, tail] {
+        if start < stop && data_segments(file, start, stop)?.is_empty() {
+            in_hole.push((start, stop));
+        }
+    }
+    for (start, stop) in in_hole {
+        write_zeros(file, &cipher, start, stop).await?;
+    }
+    Ok(())
+}
+
+/// Granularidad de los huecos: el tamaño de bloque preferido del archivo
+async fn hole_block_size(file: &tokio::fs::File) -> io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Ok(file.metadata().await?.blksize().max(512))
+}
+
+/// Escribe ceros cifrados en `[from, to)`
+async fn write_zeros(file: &mut tokio::fs::File, cipher: &FileCipher, from: u64, to: u64) -> io::Result<()> {
+    use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+    if from >= to {
+        return Ok(());
+    }
+    file.seek(io::SeekFrom::Start(from)).await?;
+    let mut pos = from;
+    while pos < to {
+        let len = ((to - pos) as usize).min(ZERO_BLOCK);
+        let mut block = vec![0u8; len];
+        cipher.apply(pos, &mut block);
+        file.write_all(&block).await?;
+        pos += len as u64;
+    }
+    file.flush().await
+}
+
+/// Trunca o extiende un archivo de caché; la extensión se lee como ceros.
+/// Con `create` crea el archivo (con clave nueva) si no existe.
+pub async fn set_len(path: &Path, size: u64, create: bool) -> io::Result<()> {
+    let existed = path.exists();
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create(create)
+        .open(path)
+        .await?;
+    if !existed {
+        init_file(&file)?;
+    }
+
+    let old_len = file.metadata().await?.len();
+    if size > old_len {
+        fill_zeros(&mut file, old_len, size).await
+    } else {
+        file.set_len(size).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    fn cipher() -> CacheCipher {
+        CacheCipher { master: [7u8; 32], encrypt_new_files: true }
+    }
+
+    #[test]
+    fn test_wrap_roundtrip() {
+        let key = [42u8; 32];
+        let wrapped = cipher().wrap(&key).unwrap();
+        assert_eq!(cipher().unwrap(&wrapped), Some(key));
+    }
+
+    #[rstest]
+    #[case::tampered(13)]
+    #[case::version(0)]
+    fn test_unwrap_rejects_modified_key(#[case] byte: usize) {
+        let mut wrapped = cipher().wrap(&[42u8; 32]).unwrap();
+        wrapped[byte] ^= 1;
+        assert_eq!(cipher().unwrap(&wrapped), None);
+    }
+
+    #[test]
+    fn test_unwrap_rejects_other_master_key() {
+        let wrapped = cipher().wrap(&[42u8; 32]).unwrap();
+        let other = CacheCipher { master: [8u8; 32], encrypt_new_files: true };
+        assert_eq!(other.unwrap(&wrapped), None);
+    }
+
+    #[rstest]
+    #[case::start(0, 100)]
+    #[case::unaligned(37, 200)]
+    #[case::block_boundary(64, 64)]
+    fn test_keystream_is_positional(#[case] offset: usize, #[case] len: usize) {
+        let key = [3u8; 32];
+        let plain: Vec<u8> = (0..512u32).map(|i| (i % 251) as u8).collect();
+
+        let mut whole = plain.clone();
+        apply_keystream(&key, 0, &mut whole);
+
+        // Cifrar un rango suelto debe coincidir con el mismo rango del archivo entero
+        let mut part = plain[offset..offset + len].to_vec();
+        apply_keystream(&key, offset as u64, &mut part);
+        assert_eq!(part, whole[offset..offset + len]);
+
+        apply_keystream(&key, offset as u64, &mut part);
+        assert_eq!(part, plain[offset..offset + len]);
+    }
+
+    /// Archivo de caché cifrado con una clave propia. El cifrador global de los
+    /// tests no cifra archivos nuevos, así que no afecta a otros tests. `None`
+    /// si el sistema de archivos no admite xattrs de usuario.
+    async fn encrypted_file(path: &Path) -> Option<tokio::fs::File> {
+        let _ = CIPHER.set(CacheCipher { master: [7u8; 32], encrypt_new_files: false });
+        let file = tokio::fs::OpenOptions::new().create(true).truncate(true).read(true).write(true).open(path).await.unwrap();
+        let wrapped = CIPHER.get().unwrap().wrap(&[5u8; 32]).unwrap();
+        let ret = unsafe {
+            libc::fsetxattr(file.as_raw_fd(), KEY_XATTR.as_ptr(), wrapped.as_ptr() as *const libc::c_void, wrapped.len(), 0)
+        };
+        (ret == 0).then_some(file)
+    }
+
+    #[tokio::test]
+    async fn test_assign_key_refuses_plaintext_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        std::fs::write(&path, b"en claro").unwrap();
+        let file = std::fs::File::open(&path).unwrap();
+
+        assert!(assign_key(&cipher(), &file).is_err());
+        assert!(wrapped_key(&file).unwrap().is_none(), "sigue en claro");
+    }
+
+    /// El sistema de archivos de `dir` informa de huecos (tmpfs, ext4, btrfs...)
+    async fn supports_holes(dir: &Path) -> bool {
+        let file = tokio::fs::File::create(dir.join("probe")).await.unwrap();
+        file.set_len(1024 * 1024).await.unwrap();
+        data_segments(&file, 0, 1024 * 1024).unwrap().is_empty()
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_extension_reads_zeros_and_stays_sparse() {
+        use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let Some(mut file) = encrypted_file(&path).await else { return };
+
+        let mut data = b"hola".to_vec();
+        encrypt_at(&file, 0, &mut data).unwrap();
+        file.write_all(&data).await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+
+        let size = 8 * 1024 * 1024 + 10;
+        set_len(&path, size, false).await.unwrap();
+
+        // Escritura dentro del hueco: sus bordes no pueden descifrarse como basura
+        let mut file = tokio::fs::OpenOptions::new().write(true).open(&path).await.unwrap();
+        let offset = 3 * 1024 * 1024 + 100;
+        prepare_write(&mut file, offset, 5).await.unwrap();
+        let mut data = b"medio".to_vec();
+        encrypt_at(&file, offset, &mut data).unwrap();
+        file.seek(io::SeekFrom::Start(offset)).await.unwrap();
+        file.write_all(&data).await.unwrap();
+        file.flush().await.unwrap();
+
+        let mut expected = vec![0u8; size as usize];
+        expected[..4].copy_from_slice(b"hola");
+        expected[offset as usize..offset as usize + 5].copy_from_slice(b"medio");
+        assert!(read_file(&path).await.unwrap() == expected, "ceros en claro fuera de lo escrito");
+
+        if supports_holes(dir.path()).await {
+            use std::os::unix::fs::MetadataExt;
+            let allocated = tokio::fs::metadata(&path).await.unwrap().blocks() * 512;
+            assert!(allocated < 1024 * 1024, "solo los bloques de los bordes: {} bytes", allocated);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_data_segments_skip_holes() {
+        let dir = tempfile::tempdir().unwrap();
+        if !supports_holes(dir.path()).await {
+            return;
+        }
+        let path = dir.path().join("sparse");
+        std::fs::write(&path, b"x").unwrap();
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(4 * 1024 * 1024).unwrap();
+
+        let segments = data_segments(&file, 0, 4 * 1024 * 1024).unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].0, 0);
+        assert!(segments[0].1 < 1024 * 1024);
+        assert!(data_segments(&file, 2 * 1024 * 1024, 3 * 1024 * 1024).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_plain_reader_decrypts_by_position() {
+        use std::io::{Read, Seek, SeekFrom};
+        use tokio::io::AsyncWriteExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let Some(mut file) = encrypted_file(&path).await else { return };
+        let plain: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        let mut data = plain.clone();
+        encrypt_at(&file, 0, &mut data).unwrap();
+        file.write_all(&data).await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+
+        let mut reader = PlainReader::open(&path).unwrap();
+        assert_eq!(reader.size(), plain.len() as u64);
+        let mut head = vec![0u8; 1000];
+        reader.read_exact(&mut head).unwrap();
+        assert!(head == plain[..1000]);
+
+        // Tras un seek (reintento de un tramo resumable) sigue descifrando bien
+        reader.seek(SeekFrom::Start(500)).unwrap();
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert!(rest == plain[500..]);
+    }
+
+    #[tokio::test]
+    async fn test_import_file_streams_plain_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let from = dir.path().join("espejo.bin");
+        let plain: Vec<u8> = (0..(ZERO_BLOCK as u32 + 1000)).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&from, &plain).unwrap();
+        let to = dir.path().join("cache");
+        std::fs::write(&to, b"version anterior").unwrap();
+
+        assert_eq!(import_file(&from, &to).await.unwrap(), plain.len() as u64);
+
+        assert!(read_file(&to).await.unwrap() == plain);
+    }
+
+    #[tokio::test]
+    async fn test_copy_file_rekeys_and_keeps_holes() {
+        use tokio::io::AsyncWriteExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("original");
+        let Some(mut file) = encrypted_file(&path).await else { return };
+        let mut data = b"hola".to_vec();
+        encrypt_at(&file, 0, &mut data).unwrap();
+        file.write_all(&data).await.unwrap();
+        file.flush().await.unwrap();
+        drop(file);
+        set_len(&path, 4 * 1024 * 1024, false).await.unwrap();
+
+        let copy = dir.path().join("copia");
+        copy_file(&path, &copy).await.unwrap();
+
+        assert!(read_file(&copy).await.unwrap() == read_file(&path).await.unwrap());
+        let file = std::fs::File::open(&copy).unwrap();
+        let original = std::fs::File::open(&path).unwrap();
+        assert!(file_cipher(&file).unwrap().is_some(), "la copia sigue cifrada");
+        assert_ne!(file_key(&file).unwrap(), file_key(&original).unwrap(), "con clave propia");
+        if supports_holes(dir.path()).await {
+            assert!(data_segments(&file, 1024 * 1024, 4 * 1024 * 1024).unwrap().is_empty());
+        }
+    }
+}
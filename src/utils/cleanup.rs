@@ -3,32 +3,37 @@ use std::process::Command;
 use std::fs;
 use std::path::PathBuf;
 
-/// Ejecuta un "Hard Reset" de la aplicación.
+use crate::config::Config;
+
+/// Ejecuta un "Hard Reset" de la aplicación con las rutas efectivas de
+/// `config` (las de `config.json`, la CLI y `GDRIVEXP_*`).
 /// 
 /// Acciones:
 /// 1. Desmonta FUSE (lazy unmount si es necesario).
 /// 2. Elimina la base de datos local.
 /// 3. Elimina tokens de autenticación.
 /// 4. Limpia la caché.
-/// 5. Limpia y recrea el directorio espejo (~/GoogleDrive).
-pub fn perform_hard_reset() -> Result<()> {
+/// 5. Limpia y recrea el directorio espejo.
+pub fn perform_hard_reset(config: &Config) -> Result<()> {
     tracing::warn!("⚠️ INICIANDO PROTOCOLO HARD RESET");
 
-    let home = std::env::var("HOME").context("No se pudo obtener HOME")?;
-    
     // Rutas críticas
-    let db_path = PathBuf::from(format!("{}/.config/fedoradrive/metadata.db", home));
-    let tokens_path = PathBuf::from(format!("{}/.config/fedoradrive/tokens.json", home));
-    let cache_dir = PathBuf::from(format!("{}/.cache/fedoradrive", home));
-    let mirror_dir = PathBuf::from(format!("{}/GoogleDrive", home));
+    let db_path = &config.db_path;
+    let tokens_path = super::paths::tokens_path();
+    let cache_dir = &config.cache_dir;
+    let mirror_dir = &config.mirror_path;
+
+    // 1. Sin el montaje: `rm -rf` del espejo no debe atravesar FUSE
+    super::mount::unmount_and_wait(&config.fuse_mount_path)
+        .context("Fallo al desmontar FUSE")?;
 
     // 2. Eliminar Base de Datos y sus archivos de Journaling (WAL/SHM)
     if db_path.exists() {
         tracing::info!("Eliminando base de datos: {:?}", db_path);
-        fs::remove_file(&db_path).context("Fallo al eliminar metadata.db")?;
+        fs::remove_file(db_path).context("Fallo al eliminar metadata.db")?;
 
-        let db_wal = PathBuf::from(format!("{}/.config/fedoradrive/metadata.db-wal", home));
-        let db_shm = PathBuf::from(format!("{}/.config/fedoradrive/metadata.db-shm", home));
+        let db_wal = PathBuf::from(format!("{}-wal", db_path.display()));
+        let db_shm = PathBuf::from(format!("{}-shm", db_path.display()));
         
         if db_wal.exists() {
             let _ = fs::remove_file(&db_wal);
@@ -47,8 +52,10 @@ pub fn perform_hard_reset() -> Result<()> {
     // 4. Limpiar Caché
     if cache_dir.exists() {
         tracing::info!("Limpiando caché: {:?}", cache_dir);
-        fs::remove_dir_all(&cache_dir).context("Fallo al eliminar directorio de caché")?;
+        fs::remove_dir_all(cache_dir).context("Fallo al eliminar directorio de caché")?;
     }
+    // Sin caché, la clave maestra de cifrado ya no protege nada
+    crate::auth::TokenStorage::new().delete_cache_master_key();
 
     // 5. Limpiar y Recrear Mirror
     if mirror_dir.exists() {
@@ -56,14 +63,14 @@ pub fn perform_hard_reset() -> Result<()> {
         // Usamos rm -rf via shell para ser más robustos con permisos o symlinks rotos
         let _ = Command::new("rm")
             .arg("-rf")
-            .arg(&mirror_dir)
+            .arg(mirror_dir)
             .status();
     }
     
     // Recrear directorios básicos
     tracing::info!("Recreando estructura de directorios limpia...");
-    fs::create_dir_all(&mirror_dir).context("Fallo al recrear ~/GoogleDrive")?;
-    fs::create_dir_all(&cache_dir).context("Fallo al recrear caché")?;
+    fs::create_dir_all(mirror_dir).context("Fallo al recrear el directorio espejo")?;
+    fs::create_dir_all(cache_dir).context("Fallo al recrear caché")?;
 
     tracing::info!("✅ Hard Reset completado con éxito.");
     Ok(())
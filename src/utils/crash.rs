@@ -0,0 +1,106 @@
+//! Red de seguridad ante fallos del backend
+//!
+//! Si el backend muere (panic o error fatal) sin desmontar, el punto de montaje
+//! queda como endpoint zombi (ENOTCONN) hasta el próximo arranque. El hook de
+//! panic deja constancia en el log; `super::supervisor::BackendSupervisor`
+//! espera el hilo del backend y, si termina de forma anormal, llama
+//! `cleanup_after_crash` antes de reintentar o de ofrecer reiniciar la aplicación.
+
+use std::sync::Mutex;
+
+/// Mensaje del último panic (para mostrarlo en la GUI)
+static LAST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+/// Instala el hook de panic: registra el panic en el log (el hook por defecto
+/// solo escribe en stderr) y lo guarda para el supervisor
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = panic_message(info.payload());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}", l.file(), l.line()))
+            .unwrap_or_default();
+        let thread = std::thread::current().name().unwrap_or("sin nombre").to_string();
+
+        tracing::error!("💥 Panic en hilo '{}' ({}): {}", thread, location, message);
+        if let Ok(mut last) = LAST_PANIC.lock() {
+            *last = Some(message);
+        }
+        default_hook(info);
+    }));
+}
+
+/// Último panic registrado, si hubo alguno
+pub fn last_panic() -> Option<String> {
+    LAST_PANIC.lock().ok().and_then(|last| last.clone())
+}
+
+/// Texto de un payload de panic (`&str` o `String`; otros tipos no se pueden mostrar)
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "panic sin mensaje".to_string()
+    }
+}
+
+/// Limpieza tras una terminación anormal del backend: desmonta FUSE (lazy,
+/// también un endpoint zombi) y elimina el socket IPC para que Nautilus no
+/// quede conectándose a un servidor muerto
+pub fn cleanup_after_crash(fuse_mount_path: &std::path::Path) {
+    tracing::warn!("🧯 Backend terminado de forma anormal: desmontando {:?}", fuse_mount_path);
+    if super::mount::is_mounted(fuse_mount_path)
+        && let Err(e) = super::mount::unmount(fuse_mount_path)
+    {
+        tracing::error!("No se pudo desmontar tras el fallo: {:?}", e);
+    }
+
+    let socket_path = crate::ipc::get_socket_path();
+    match std::fs::remove_file(&socket_path) {
+        Ok(()) => tracing::info!("🧹 Socket IPC eliminado: {:?}", socket_path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => tracing::warn!("No se pudo eliminar el socket IPC {:?}: {}", socket_path, e),
+    }
+}
+
+/// Reinicia la aplicación: lanza una copia nueva con los mismos argumentos y
+/// termina este proceso. La copia espera un segundo para que GApplication no
+/// la trate como segunda instancia de la actual.
+pub fn restart_application() -> ! {
+    match std::env::current_exe() {
+        Ok(exe) => {
+            let spawned = std::process::Command::new("sh")
+                .arg("-c")
+                .arg("sleep 1 && exec \"$0\" \"$@\"")
+                .arg(exe)
+                .args(std::env::args_os().skip(1))
+                .spawn();
+            if let Err(e) = spawned {
+                tracing::error!("No se pudo relanzar la aplicación: {:?}", e);
+            }
+        }
+        Err(e) => tracing::error!("No se pudo determinar el ejecutable actual: {:?}", e),
+    }
+    tracing::info!("🔁 Reiniciando aplicación...");
+    std::process::exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message_from_payload() {
+        let static_str = std::panic::catch_unwind(|| panic!("fallo estático")).unwrap_err();
+        assert_eq!(panic_message(static_str.as_ref()), "fallo estático");
+
+        let formatted = std::panic::catch_unwind(|| panic!("fallo {}", 42)).unwrap_err();
+        assert_eq!(panic_message(formatted.as_ref()), "fallo 42");
+
+        let other = std::panic::catch_unwind(|| std::panic::panic_any(7_u8)).unwrap_err();
+        assert_eq!(panic_message(other.as_ref()), "panic sin mensaje");
+    }
+}
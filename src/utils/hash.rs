@@ -12,8 +12,13 @@ pub async fn compute_file_md5(path: impl AsRef<Path>) -> Result<String> {
         let mut file = std::fs::File::open(&path)
             .with_context(|| format!("Error abriendo archivo para MD5: {:?}", path))?;
 
+        // Archivos de caché cifrados: el hash es del contenido en claro
+        let cipher = crate::utils::cache_crypto::file_cipher(&file)
+            .with_context(|| format!("Error leyendo clave de caché: {:?}", path))?;
+
         let mut hasher = Md5::new();
         let mut buffer = [0; 8192]; // 8KB buffer
+        let mut offset = 0u64;
 
         loop {
             let count = file.read(&mut buffer)
@@ -21,7 +26,12 @@ pub async fn compute_file_md5(path: impl AsRef<Path>) -> Result<String> {
             if count == 0 {
                 break;
             }
+            if let Some(cipher) = &cipher {
+                cipher.decrypt_read(&file, offset, &mut buffer[..count])
+                    .context("Error descifrando chunk para MD5")?;
+            }
             hasher.update(&buffer[..count]);
+            offset += count as u64;
         }
 
         let result = hasher.finalize();
@@ -0,0 +1,121 @@
+//! Patrones de archivos locales que nunca se suben
+//!
+//! Editores y herramientas de compilación dejan archivos temporales (`.swp`,
+//! `~`, `.tmp`, `node_modules`…) junto a los documentos. Cada patrón es un
+//! glob sobre un solo nombre (sin `/`, como `.gitignore` sin barras): una ruta
+//! se ignora si su nombre o el de cualquier carpeta que la contiene coincide.
+//! Los archivos ignorados siguen accesibles en FUSE y en el espejo, pero no
+//! llegan a Drive.
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Patrones predeterminados de `Config.ignore_patterns`
+pub fn default_patterns() -> Vec<String> {
+    [
+        "*.swp", "*.swo", "*~", ".#*", "*.tmp", ".~lock.*#", "~$*", "node_modules", "__pycache__",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect()
+}
+
+/// Conjunto compilado de patrones
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    set: GlobSet,
+}
+
+impl Default for IgnoreRules {
+    /// Sin patrones: no se ignora nada
+    fn default() -> Self {
+        Self { set: GlobSet::empty() }
+    }
+}
+
+impl IgnoreRules {
+    /// Compila los patrones; el error nombra el patrón inválido
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            builder.add(compile(pattern)?);
+        }
+        Ok(Self { set: builder.build()? })
+    }
+
+    /// Si un nombre de archivo o carpeta coincide con algún patrón
+    pub fn is_ignored_name(&self, name: &str) -> bool {
+        self.set.is_match(name)
+    }
+
+    /// Si una ruta relativa (al espejo o a la raíz de FUSE) queda ignorada por
+    /// su nombre o por el de alguna carpeta que la contiene
+    pub fn is_ignored_path(&self, relative: &Path) -> bool {
+        relative
+            .components()
+            .any(|component| component.as_os_str().to_str().is_some_and(|name| self.is_ignored_name(name)))
+    }
+}
+
+/// Comprueba un patrón sin compilar el conjunto (validación de la configuración)
+pub fn validate_pattern(pattern: &str) -> Result<()> {
+    compile(pattern).map(|_| ())
+}
+
+fn compile(pattern: &str) -> Result<globset::Glob> {
+    if pattern.contains('/') {
+        anyhow::bail!("el patrón «{}» no puede contener '/'", pattern);
+    }
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("patrón «{}» inválido", pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[fixture]
+    fn rules() -> IgnoreRules {
+        IgnoreRules::new(&default_patterns()).unwrap()
+    }
+
+    #[rstest]
+    #[case::vim_swap(".informe.txt.swp", true)]
+    #[case::backup("informe.txt~", true)]
+    #[case::emacs_lock(".#informe.txt", true)]
+    #[case::libreoffice_lock(".~lock.informe.odt#", true)]
+    #[case::office_owner("~$informe.docx", true)]
+    #[case::temp("descarga.tmp", true)]
+    #[case::node_modules("node_modules", true)]
+    #[case::document("informe.txt", false)]
+    #[case::tilde_inside("a~b.txt", false)]
+    #[case::tmp_prefix("tmp.txt", false)]
+    fn test_default_patterns(rules: IgnoreRules, #[case] name: &str, #[case] ignored: bool) {
+        assert_eq!(rules.is_ignored_name(name), ignored);
+    }
+
+    #[rstest]
+    #[case::inside_ignored_dir("web/node_modules/react/index.js", true)]
+    #[case::ignored_leaf("docs/.notas.md.swp", true)]
+    #[case::regular("web/src/index.js", false)]
+    fn test_is_ignored_path(rules: IgnoreRules, #[case] path: &str, #[case] ignored: bool) {
+        assert_eq!(rules.is_ignored_path(Path::new(path)), ignored);
+    }
+
+    #[rstest]
+    #[case::slash("build/*.o")]
+    #[case::unclosed_class("[abc")]
+    fn test_invalid_patterns(#[case] pattern: &str) {
+        assert!(validate_pattern(pattern).is_err());
+        assert!(IgnoreRules::new(&[pattern.to_string()]).is_err());
+    }
+
+    #[rstest]
+    fn test_empty_rules_ignore_nothing() {
+        assert!(!IgnoreRules::default().is_ignored_name("archivo.swp"));
+    }
+}
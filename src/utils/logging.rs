@@ -0,0 +1,205 @@
+//! Registro en archivo con rotación y paquete de diagnóstico
+//!
+//! Además de stderr (que se pierde al lanzar la app desde el escritorio), los
+//! logs se escriben en `~/.local/state/gdrivexp/logs/` con rotación diaria.
+//! La escritura es síncrona (sin `WorkerGuard`): varios caminos terminan con
+//! `process::exit`, y un writer con buffer perdería las últimas líneas.
+
+use anyhow::{Context, Result};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Prefijo de los archivos de log (`g-drive-xp.AAAA-MM-DD.log`)
+const LOG_FILE_PREFIX: &str = "g-drive-xp";
+
+/// Extensión de los archivos de log
+const LOG_FILE_SUFFIX: &str = "log";
+
+/// Días de log que se conservan
+const MAX_LOG_FILES: usize = 7;
+
+/// Directorio de logs (XDG state dir)
+pub fn log_dir() -> PathBuf {
+    super::paths::state_dir().join("logs")
+}
+
+/// Inicializa tracing: stderr + archivo rotativo, con el nivel de la configuración
+/// (`RUST_LOG` tiene prioridad). Sin directorio de logs usable sigue solo con stderr.
+pub fn init(level: &str) -> Result<()> {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .or_else(|_| tracing_subscriber::EnvFilter::try_new(filter_directive(level)))
+        .unwrap_or_else(|_| "g_drive_xp=info".into());
+
+    let file_appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir());
+
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer());
+
+    match file_appender {
+        Ok(appender) => {
+            registry
+                .with(tracing_subscriber::fmt::layer().with_ansi(false).with_writer(appender))
+                .init();
+        }
+        Err(e) => {
+            registry.init();
+            tracing::warn!("⚠️ No se pudo abrir el log en {:?}, solo stderr: {}", log_dir(), e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Directiva de `EnvFilter` para un nivel de la configuración. Un nivel simple
+/// (`debug`) aplica solo a este crate; una directiva completa se usa tal cual.
+fn filter_directive(level: &str) -> String {
+    let level = level.trim();
+    if level.contains('=') || level.contains(',') {
+        level.to_string()
+    } else {
+        format!("g_drive_xp={}", level)
+    }
+}
+
+/// Archivos de log existentes, del más antiguo al más reciente
+pub fn log_files() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(log_dir()) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(LOG_FILE_PREFIX) && name.ends_with(LOG_FILE_SUFFIX))
+        })
+        .collect();
+    // La fecha va en el nombre: el orden alfabético es el cronológico
+    files.sort();
+    files
+}
+
+/// Últimos `max_bytes` del log más reciente, empezando en una línea completa
+pub fn read_recent(max_bytes: u64) -> Result<String> {
+    match log_files().last() {
+        Some(path) => read_tail(path, max_bytes),
+        None => Ok(String::new()),
+    }
+}
+
+fn read_tail(path: &Path, max_bytes: u64) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(max_bytes);
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let text = String::from_utf8_lossy(&bytes);
+
+    // Descartar la línea cortada por el inicio del rango
+    if start > 0
+        && let Some(newline) = text.find('\n')
+    {
+        return Ok(text[newline + 1..].to_string());
+    }
+    Ok(text.into_owned())
+}
+
+/// Empaqueta logs, configuración y datos de la versión en `dest` (`.tar.gz`)
+/// para adjuntar a un reporte de errores. La configuración no contiene
+/// credenciales (los tokens viven en el keyring).
+pub fn export_diagnostics(dest: &Path) -> Result<()> {
+    // Directorio de nombre impredecible y solo del usuario (no un `/tmp/...-{pid}`
+    // que otro usuario podría crear antes como enlace); se borra al soltarlo
+    let base = staging_base();
+    std::fs::create_dir_all(&base).context("No se pudo crear el directorio temporal")?;
+    let staging = tempfile::Builder::new()
+        .prefix("diagnostico-")
+        .tempdir_in(&base)
+        .context("No se pudo crear el directorio temporal")?;
+    build_bundle(staging.path(), dest)?;
+
+    tracing::info!("🩺 Diagnóstico exportado a {:?}", dest);
+    Ok(())
+}
+
+/// `$XDG_RUNTIME_DIR/gdrivexp` (tmpfs del usuario) o, sin él, la caché de la aplicación
+fn staging_base() -> PathBuf {
+    dirs::runtime_dir()
+        .map(|dir| dir.join("gdrivexp"))
+        .unwrap_or_else(super::paths::cache_dir)
+}
+
+fn build_bundle(staging: &Path, dest: &Path) -> Result<()> {
+    let bundle = staging.join("gdrivexp-diagnostico");
+    std::fs::create_dir_all(bundle.join("logs")).context("No se pudo crear el directorio temporal")?;
+
+    for log in log_files() {
+        if let Some(name) = log.file_name() {
+            std::fs::copy(&log, bundle.join("logs").join(name))?;
+        }
+    }
+
+    if let Ok(config_path) = crate::config::Config::config_path()
+        && config_path.exists()
+    {
+        std::fs::copy(&config_path, bundle.join("config.json"))?;
+    }
+
+    let info = format!(
+        "G-DriveXP {}\nSO: {} {}\nConexión: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        super::network::pause_reason().unwrap_or("Conectado"),
+    );
+    std::fs::write(bundle.join("info.txt"), info)?;
+
+    let status = std::process::Command::new("tar")
+        .arg("-czf")
+        .arg(dest)
+        .arg("-C")
+        .arg(staging)
+        .arg("gdrivexp-diagnostico")
+        .status()
+        .context("No se pudo ejecutar tar")?;
+    if !status.success() {
+        anyhow::bail!("tar terminó con error ({})", status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::simple("debug", "g_drive_xp=debug")]
+    #[case::trimmed(" info \n", "g_drive_xp=info")]
+    #[case::directive("g_drive_xp=trace,fuse3=debug", "g_drive_xp=trace,fuse3=debug")]
+    fn test_filter_directive(#[case] level: &str, #[case] expected: &str) {
+        assert_eq!(filter_directive(level), expected);
+    }
+
+    #[rstest]
+    #[case::whole_file(1024, "primera\nsegunda\ntercera\n")]
+    #[case::drops_cut_line(12, "tercera\n")]
+    fn test_read_tail(#[case] max_bytes: u64, #[case] expected: &str) {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("test.log");
+        std::fs::write(&path, "primera\nsegunda\ntercera\n").unwrap();
+
+        assert_eq!(read_tail(&path, max_bytes).unwrap(), expected);
+    }
+}
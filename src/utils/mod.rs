@@ -1,4 +1,19 @@
 pub mod hash;
+pub mod ignore;
+pub mod autostart;
+pub mod backpressure;
+pub mod cache_crypto;
 pub mod cleanup;
+pub mod crash;
+pub mod logging;
 pub mod mount;
+pub mod names;
+pub mod network;
+pub mod paths;
+pub mod proxy;
+pub mod quota;
 pub mod shutdown;
+pub mod state_archive;
+pub mod suspend;
+pub mod supervisor;
+pub mod text_diff;
@@ -1,8 +1,36 @@
 //! Utilidades para gestión de puntos de montaje FUSE
+//!
+//! Montar sobre un directorio con archivos los oculta sin avisar. Antes de
+//! montar se comprueba con `hidden_entries`; si hay algo, el arranque se
+//! detiene hasta que el usuario elige otro directorio o pide migrarlo a Drive
+//! (`request_migration`). La migración aparta el contenido con
+//! `stage_for_migration` y, ya montado, `migrate_staged` lo copia a través de
+//! FUSE a la raíz de Mi unidad.
+//!
+//! Si la sesión se pierde con la aplicación en marcha (error de FUSE, vuelta
+//! de una suspensión), `main.rs` limpia el montaje y remonta con
+//! `remount_delay` entre intentos; `is_healthy` detecta el endpoint roto.
 
 use anyhow::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Carpeta junto al punto de montaje (mismo sistema de archivos, así apartar
+/// es un `rename`) donde espera el contenido a migrar. Contiene
+/// `.gdrive_tmp_ops` para que el watcher del espejo la ignore cuando el
+/// montaje está dentro del espejo
+const MIGRATION_DIR: &str = ".gdrive_tmp_ops_mount_migration";
+
+/// Espera antes del primer reintento de remontaje (se duplica en cada fallo)
+const INITIAL_REMOUNT_DELAY: Duration = Duration::from_secs(1);
+
+/// Espera máxima entre reintentos de remontaje
+const MAX_REMOUNT_DELAY: Duration = Duration::from_secs(60);
+
+/// El usuario pidió migrar a Drive el contenido del punto de montaje
+static MIGRATION_REQUESTED: AtomicBool = AtomicBool::new(false);
 
 /// Verifica si un directorio está montado como punto de montaje FUSE
 /// Detecta TANTO montajes normales COMO endpoints rotos (error 107 / ENOTCONN)
@@ -184,3 +212,209 @@ pub fn cleanup_if_needed<P: AsRef<Path>>(path: P) -> Result<()> {
 
     Ok(())
 }
+
+/// `false` si la sesión FUSE de `path` se perdió: nada montado o endpoint
+/// roto (`ENOTCONN`, típico tras suspender). Hace `stat` sobre el montaje:
+/// llamarlo fuera del runtime y con timeout
+pub fn is_healthy<P: AsRef<Path>>(path: P) -> bool {
+    match std::fs::metadata(path.as_ref()) {
+        Ok(_) => is_mounted(path),
+        Err(_) => false,
+    }
+}
+
+/// Espera antes del remontaje número `attempt` (empieza en 1)
+pub fn remount_delay(attempt: u32) -> Duration {
+    let factor = 2_u32.saturating_pow(attempt.saturating_sub(1));
+    INITIAL_REMOUNT_DELAY.saturating_mul(factor).min(MAX_REMOUNT_DELAY)
+}
+
+/// Nombres de las entradas que quedarían ocultas al montar sobre `path`
+/// (ordenados). Vacío si el directorio no existe
+pub fn hidden_entries<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<String>> {
+    let entries = match std::fs::read_dir(path.as_ref()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut names = entries
+        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    names.sort();
+    Ok(names)
+}
+
+/// Pide que el próximo arranque migre a Drive el contenido del punto de montaje
+pub fn request_migration() {
+    MIGRATION_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Consume la petición de migración (solo vale para un arranque)
+pub fn take_migration_request() -> bool {
+    MIGRATION_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+/// Carpeta temporal de la migración del punto de montaje `mount`
+pub fn migration_dir(mount: &Path) -> PathBuf {
+    mount.parent().unwrap_or(mount).join(MIGRATION_DIR)
+}
+
+/// Aparta el contenido de `mount` en `migration_dir` (sin copiar) para poder
+/// montar. Retorna cuántas entradas movió
+pub fn stage_for_migration(mount: &Path) -> std::io::Result<usize> {
+    let staging = migration_dir(mount);
+    std::fs::create_dir_all(&staging)?;
+    let mut moved = 0;
+    for entry in std::fs::read_dir(mount)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_dir = entry.file_type()?.is_dir();
+        // Un arranque anterior pudo dejar algo con el mismo nombre sin migrar
+        let target = name_candidates(&name, is_dir)
+            .map(|candidate| staging.join(candidate))
+            .find(|path| path.symlink_metadata().is_err())
+            .expect("candidatos infinitos");
+        std::fs::rename(entry.path(), target)?;
+        moved += 1;
+    }
+    Ok(moved)
+}
+
+/// Copia a Drive, a través del montaje FUSE ya activo, lo apartado por
+/// `stage_for_migration` y lo borra de la carpeta temporal a medida que
+/// termina. Lo que falla se queda apartado y se reintenta en el siguiente
+/// arranque. Retorna los archivos copiados
+pub async fn migrate_staged(mount: &Path) -> Result<usize> {
+    let staging = migration_dir(mount);
+    if tokio::fs::symlink_metadata(&staging).await.is_err() {
+        return Ok(0);
+    }
+
+    let mut copied = 0;
+    let mut pending = vec![(staging.clone(), mount.to_path_buf())];
+    // Carpetas de origen, para borrarlas (de dentro hacia fuera) si quedan vacías
+    let mut visited = Vec::new();
+    while let Some((src_dir, dst_dir)) = pending.pop() {
+        let mut entries = tokio::fs::read_dir(&src_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let src = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let file_type = entry.file_type().await?;
+            let dst = free_path(&dst_dir, &name, file_type.is_dir()).await;
+
+            let result = if file_type.is_dir() {
+                tokio::fs::create_dir(&dst).await
+            } else if file_type.is_symlink() {
+                match tokio::fs::read_link(&src).await {
+                    Ok(target) => tokio::fs::symlink(target, &dst).await,
+                    Err(e) => Err(e),
+                }
+            } else if file_type.is_file() {
+                tokio::fs::copy(&src, &dst).await.map(|_| ())
+            } else {
+                tracing::warn!("⚠️ Migración: se omite {:?} (ni archivo ni carpeta)", src);
+                continue;
+            };
+
+            match result {
+                Ok(()) if file_type.is_dir() => pending.push((src, dst)),
+                Ok(()) => {
+                    copied += 1;
+                    if let Err(e) = tokio::fs::remove_file(&src).await {
+                        tracing::warn!("⚠️ Migración: {:?} copiado pero no borrado: {}", src, e);
+                    }
+                }
+                Err(e) => tracing::warn!("⚠️ Migración: no se pudo copiar {:?} a {:?}: {}", src, dst, e),
+            }
+        }
+        visited.push(src_dir);
+    }
+    for dir in visited.iter().rev() {
+        // Falla si quedó algo sin migrar: se conserva para el próximo arranque
+        let _ = tokio::fs::remove_dir(dir).await;
+    }
+    Ok(copied)
+}
+
+/// `name`, `name (2)`, `name (3)`...
+fn name_candidates(name: &str, is_dir: bool) -> impl Iterator<Item = String> + '_ {
+    std::iter::once(name.to_string())
+        .chain((2..).map(move |n| crate::utils::names::disambiguated_name(name, n, is_dir)))
+}
+
+/// Primera ruta libre para `name` dentro de `dir`
+async fn free_path(dir: &Path, name: &str, is_dir: bool) -> PathBuf {
+    for candidate in name_candidates(name, is_dir) {
+        let path = dir.join(candidate);
+        if tokio::fs::symlink_metadata(&path).await.is_err() {
+            return path;
+        }
+    }
+    unreachable!("candidatos infinitos")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::first(1, 1)]
+    #[case::second(2, 2)]
+    #[case::sixth(6, 32)]
+    #[case::capped(7, 60)]
+    #[case::huge(u32::MAX, 60)]
+    fn test_remount_delay(#[case] attempt: u32, #[case] secs: u64) {
+        assert_eq!(remount_delay(attempt), Duration::from_secs(secs));
+    }
+
+    #[test]
+    fn test_is_healthy_requires_a_mount() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!is_healthy(dir.path()), "un directorio sin montar no es una sesión viva");
+        assert!(!is_healthy(dir.path().join("no_existe")));
+    }
+
+    #[test]
+    fn test_hidden_entries_lists_files_and_ignores_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let mount = dir.path().join("FUSE_Mount");
+        assert!(hidden_entries(&mount).unwrap().is_empty());
+
+        std::fs::create_dir(&mount).unwrap();
+        assert!(hidden_entries(&mount).unwrap().is_empty());
+
+        std::fs::write(mount.join("notas.txt"), b"x").unwrap();
+        std::fs::create_dir(mount.join("Fotos")).unwrap();
+        assert_eq!(hidden_entries(&mount).unwrap(), vec!["Fotos", "notas.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_migration_moves_everything_and_keeps_name_clashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mount = dir.path().join("FUSE_Mount");
+        std::fs::create_dir_all(mount.join("Fotos/2024")).unwrap();
+        std::fs::write(mount.join("Fotos/2024/playa.jpg"), b"jpg").unwrap();
+        std::fs::write(mount.join("informe.pdf"), b"pdf").unwrap();
+
+        assert_eq!(stage_for_migration(&mount).unwrap(), 2);
+        assert!(hidden_entries(&mount).unwrap().is_empty(), "el punto de montaje queda libre");
+
+        // Lo que ya existe en el destino no se pisa
+        std::fs::write(mount.join("informe.pdf"), b"de Drive").unwrap();
+        assert_eq!(migrate_staged(&mount).await.unwrap(), 2);
+
+        assert_eq!(std::fs::read(mount.join("informe.pdf")).unwrap(), b"de Drive");
+        assert_eq!(std::fs::read(mount.join("informe (2).pdf")).unwrap(), b"pdf");
+        assert_eq!(std::fs::read(mount.join("Fotos/2024/playa.jpg")).unwrap(), b"jpg");
+        assert!(!migration_dir(&mount).exists(), "la carpeta temporal se borra al terminar");
+        assert_eq!(migrate_staged(&mount).await.unwrap(), 0);
+    }
+
+    #[test]
+    fn test_migration_request_is_consumed_once() {
+        request_migration();
+        assert!(take_migration_request());
+        assert!(!take_migration_request());
+    }
+}
@@ -0,0 +1,92 @@
+//! Traducción entre nombres de Google Drive y nombres locales
+//!
+//! Drive admite en un nombre caracteres que un sistema de archivos POSIX no
+//! puede representar (`/`, NUL) y no normaliza Unicode: un archivo subido desde
+//! macOS llega en NFD y no coincide byte a byte con el mismo nombre tecleado en
+//! NFC. Los nombres remotos se convierten con `local_name` al sincronizar; el
+//! original se conserva en `dentry.remote_name` para no renombrar en Drive.
+//! Para nombres elegidos localmente, `remote_name` deshace la sustitución de `/`.
+
+use unicode_normalization::UnicodeNormalization;
+
+/// Sustituto visible de `/` en nombres locales (FULLWIDTH SOLIDUS)
+pub const SLASH_SUBSTITUTE: char = '\u{FF0F}';
+
+/// Nombre local para un nombre de Drive: NFC, `/` → `／`, sin NUL.
+/// `.`, `..` y el nombre vacío no son entradas válidas y reciben un `_` delante.
+pub fn local_name(remote: &str) -> String {
+    let name: String = remote
+        .nfc()
+        .filter(|c| *c != '\0')
+        .map(|c| if c == '/' { SLASH_SUBSTITUTE } else { c })
+        .collect();
+
+    match name.as_str() {
+        "" | "." | ".." => format!("_{}", name),
+        _ => name,
+    }
+}
+
+/// Nombre a enviar a Drive para un nombre elegido localmente (FUSE, mirror)
+pub fn remote_name(local: &str) -> String {
+    local.replace(SLASH_SUBSTITUTE, "/")
+}
+
+/// Nombre local para el n-ésimo duplicado de `name` en una carpeta:
+/// `informe.pdf` → `informe (2).pdf`; carpetas y nombres sin extensión → `Carpeta (2)`
+pub fn disambiguated_name(name: &str, n: u32, is_dir: bool) -> String {
+    suffixed_name(name, &n.to_string(), is_dir)
+}
+
+/// `name` con `(suffix)` antes de la extensión: `informe (rev 3).pdf`.
+/// Un punto inicial (`.bashrc`) no separa extensión, y las carpetas no tienen.
+pub fn suffixed_name(name: &str, suffix: &str, is_dir: bool) -> String {
+    match name.rfind('.') {
+        Some(dot) if !is_dir && dot > 0 => format!("{} ({}){}", &name[..dot], suffix, &name[dot..]),
+        _ => format!("{} ({})", name, suffix),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::plain("informe.pdf", "informe.pdf")]
+    #[case::nfd("Cancio\u{301}n.txt", "Canción.txt")]
+    #[case::slash("Q1/Q2 ventas", "Q1／Q2 ventas")]
+    #[case::nul("a\0b", "ab")]
+    #[case::dot(".", "_.")]
+    #[case::dotdot("..", "_..")]
+    #[case::empty("", "_")]
+    fn test_local_name(#[case] remote: &str, #[case] expected: &str) {
+        assert_eq!(local_name(remote), expected);
+    }
+
+    #[rstest]
+    #[case::slash("Q1/Q2 ventas")]
+    #[case::plain("informe.pdf")]
+    fn test_slash_substitution_is_reversible(#[case] remote: &str) {
+        assert_eq!(remote_name(&local_name(remote)), remote);
+    }
+
+    #[rstest]
+    #[case::extension("informe.pdf", false, "informe (2).pdf")]
+    #[case::double_extension("datos.tar.gz", false, "datos.tar (2).gz")]
+    #[case::no_extension("LEEME", false, "LEEME (2)")]
+    #[case::dotfile(".bashrc", false, ".bashrc (2)")]
+    #[case::directory("Fotos 2024.01", true, "Fotos 2024.01 (2)")]
+    fn test_disambiguated_name(#[case] name: &str, #[case] is_dir: bool, #[case] expected: &str) {
+        assert_eq!(disambiguated_name(name, 2, is_dir), expected);
+    }
+
+    #[rstest]
+    #[case::extension("informe.pdf", "informe (rev 3).pdf")]
+    #[case::double_extension("datos.tar.gz", "datos.tar (rev 3).gz")]
+    #[case::no_extension("LEEME", "LEEME (rev 3)")]
+    #[case::dotfile(".bashrc", ".bashrc (rev 3)")]
+    fn test_suffixed_name(#[case] name: &str, #[case] expected: &str) {
+        assert_eq!(suffixed_name(name, "rev 3", false), expected);
+    }
+}
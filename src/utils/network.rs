@@ -0,0 +1,260 @@
+//! Estado global de conectividad con Google Drive
+//!
+//! Cualquier componente que reciba un error de red lo reporta con `report_error`
+//! y el estado pasa a offline; solo `sync::connectivity::ConnectivityMonitor`
+//! lo devuelve a online, tras una sonda exitosa contra la API. Mientras tanto
+//! syncer y uploader esperan en `wait_for_online()` en lugar de acumular errores,
+//! y FUSE sirve lo que esté en caché.
+//!
+//! Aparte, en una conexión medida (y con `pause_on_metered` activo) el monitor
+//! marca las transferencias en background como pausadas: subidas y precarga
+//! esperan en `wait_for_transfers_allowed()`. Las lecturas que pide el usuario
+//! por FUSE siguen descargando.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
+
+/// `true` mientras no hay conexión con Drive
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// `true` mientras las transferencias en background están pausadas por conexión medida
+static METERED_PAUSE: AtomicBool = AtomicBool::new(false);
+
+/// Despierta a quien espera un cambio de estado (online ↔ offline, medida ↔ libre)
+static STATE_NOTIFY: Notify = Notify::const_new();
+
+/// Textos de errores de conexión que llegan sin tipo (p. ej. el refresco del
+/// token OAuth2, que el cliente convierte a texto)
+const NETWORK_ERROR_MARKERS: &[&str] = &[
+    "error trying to connect",
+    "dns error",
+    "failed to lookup address",
+    "Network is unreachable",
+    "Connection refused",
+    "Connection reset",
+    "operation timed out",
+];
+
+/// Error para operaciones que necesitan la red mientras se está offline
+#[derive(Debug, thiserror::Error)]
+#[error("Sin conexión con Google Drive")]
+pub struct Offline;
+
+/// Error para transferencias en background aplazadas por conexión medida
+#[derive(Debug, thiserror::Error)]
+#[error("Transferencia aplazada: conexión medida")]
+pub struct MeteredPause;
+
+/// Verifica si hay conexión con Drive
+#[inline]
+pub fn is_online() -> bool {
+    !OFFLINE.load(Ordering::SeqCst)
+}
+
+/// Marca el estado offline. Retorna `true` si es una transición.
+pub fn set_offline(reason: &str) -> bool {
+    let changed = !OFFLINE.swap(true, Ordering::SeqCst);
+    if changed {
+        tracing::warn!("📴 Sin conexión con Google Drive ({}). Los cambios locales quedan en cola.", reason);
+        STATE_NOTIFY.notify_waiters();
+    }
+    changed
+}
+
+/// Marca el estado online. Retorna `true` si es una transición.
+pub fn set_online() -> bool {
+    let changed = OFFLINE.swap(false, Ordering::SeqCst);
+    if changed {
+        tracing::info!("📶 Conexión con Google Drive restablecida");
+        STATE_NOTIFY.notify_waiters();
+    }
+    changed
+}
+
+/// Verifica si las transferencias en background están pausadas por conexión medida
+#[inline]
+pub fn is_metered_paused() -> bool {
+    METERED_PAUSE.load(Ordering::SeqCst)
+}
+
+/// Pausa o reanuda las transferencias en background por conexión medida.
+/// Retorna `true` si es una transición.
+pub fn set_metered_pause(paused: bool) -> bool {
+    let changed = METERED_PAUSE.swap(paused, Ordering::SeqCst) != paused;
+    if changed {
+        if paused {
+            tracing::info!("⏸️ Conexión medida: subidas y precarga en pausa");
+        } else {
+            tracing::info!("▶️ Conexión sin límite de datos: subidas y precarga reanudadas");
+        }
+        STATE_NOTIFY.notify_waiters();
+    }
+    changed
+}
+
+/// Hay conexión y no es una conexión medida en pausa
+#[inline]
+pub fn transfers_allowed() -> bool {
+    is_online() && !is_metered_paused()
+}
+
+/// Motivo por el que las transferencias en background están detenidas (para GUI/bandeja)
+pub fn pause_reason() -> Option<&'static str> {
+    if !is_online() {
+        Some("Sin conexión")
+    } else if is_metered_paused() {
+        Some("Conexión medida")
+    } else {
+        None
+    }
+}
+
+/// Si `error` es de conectividad, pasa a offline. Retorna si lo era.
+pub fn report_error(error: &anyhow::Error) -> bool {
+    let is_network = is_network_error(error);
+    if is_network {
+        set_offline(&format!("{:#}", error));
+    }
+    is_network
+}
+
+/// Distingue los fallos de conexión (sin red, DNS, timeouts) de los errores de la API
+pub fn is_network_error(error: &anyhow::Error) -> bool {
+    let typed = error.chain().any(|cause| {
+        if cause.is::<Offline>() {
+            return true;
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            return e.is_connect() || e.is_timeout();
+        }
+        if let Some(e) = cause.downcast_ref::<hyper::Error>() {
+            return e.is_connect();
+        }
+        if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+            return is_network_io_error(e);
+        }
+        false
+    });
+
+    typed || {
+        let message = format!("{:#}", error);
+        NETWORK_ERROR_MARKERS.iter().any(|marker| message.contains(marker))
+    }
+}
+
+fn is_network_io_error(error: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        error.kind(),
+        ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::TimedOut
+            | ErrorKind::HostUnreachable
+            | ErrorKind::NetworkUnreachable
+            | ErrorKind::NetworkDown
+    )
+}
+
+/// Espera hasta que haya conexión (retorna de inmediato si ya la hay)
+pub async fn wait_for_online() {
+    loop {
+        let notified = STATE_NOTIFY.notified();
+        if is_online() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Espera hasta que haya conexión y no esté en pausa por conexión medida
+pub async fn wait_for_transfers_allowed() {
+    loop {
+        let notified = STATE_NOTIFY.notified();
+        if transfers_allowed() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Espera hasta perder la conexión
+pub async fn wait_for_offline() {
+    loop {
+        let notified = STATE_NOTIFY.notified();
+        if !is_online() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Serializa los tests que cambian o dependen del estado global (async para
+/// poder retenerlo mientras se espera la reconexión)
+#[cfg(test)]
+pub(crate) static TEST_STATE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    #[rstest]
+    #[case::offline(anyhow::Error::new(Offline), true)]
+    #[case::io_unreachable(anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NetworkUnreachable)), true)]
+    #[case::oauth_text(anyhow::anyhow!("Error de autenticación: error trying to connect: dns error"), true)]
+    #[case::context_chain(anyhow::Error::new(Offline).context("Error de red al descargar chunk"), true)]
+    #[case::api_error(anyhow::anyhow!("Error API Drive: 500 Internal Server Error - backendError"), false)]
+    #[case::not_found(anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NotFound)), false)]
+    fn test_is_network_error(#[case] error: anyhow::Error, #[case] expected: bool) {
+        assert_eq!(is_network_error(&error), expected);
+    }
+
+    #[test]
+    fn test_state_transitions() {
+        let _guard = TEST_STATE_LOCK.blocking_lock();
+        set_online();
+
+        assert!(set_offline("prueba"));
+        assert!(!set_offline("prueba"), "repetir el estado no es una transición");
+        assert!(!is_online());
+        assert!(set_online());
+        assert!(is_online());
+    }
+
+    #[test]
+    fn test_pause_reason() {
+        let _guard = TEST_STATE_LOCK.blocking_lock();
+        set_online();
+        set_metered_pause(false);
+        assert_eq!(pause_reason(), None);
+
+        assert!(set_metered_pause(true));
+        assert!(!set_metered_pause(true), "repetir el estado no es una transición");
+        assert!(!transfers_allowed());
+        assert_eq!(pause_reason(), Some("Conexión medida"));
+
+        set_offline("prueba");
+        assert_eq!(pause_reason(), Some("Sin conexión"), "offline tiene prioridad");
+
+        set_online();
+        set_metered_pause(false);
+        assert!(transfers_allowed());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_online_wakes_on_reconnect() {
+        let _guard = TEST_STATE_LOCK.lock().await;
+        set_offline("prueba");
+
+        let task = tokio::spawn(async {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            set_online();
+        });
+
+        let result = tokio::time::timeout(std::time::Duration::from_millis(100), wait_for_online()).await;
+        assert!(result.is_ok(), "wait_for_online no despertó al reconectar");
+        let _ = task.await;
+    }
+}
@@ -0,0 +1,309 @@
+//! Directorios de la aplicación (XDG) y migración del esquema anterior
+//!
+//! Configuración y credenciales van en `$XDG_CONFIG_HOME/gdrivexp`, la caché
+//! de contenido en `$XDG_CACHE_HOME/gdrivexp` y lo regenerable que no es
+//! caché (base de datos de metadatos, logs) en `$XDG_STATE_HOME/gdrivexp`.
+//! Las versiones anteriores usaban `~/.config/fedoradrive` (con la base de
+//! datos dentro), `~/.cache/fedoradrive` y `~/.local/state/fedoradrive`:
+//! `migrate_legacy_layout` los mueve una sola vez al arrancar, antes de leer
+//! la configuración. Si el destino ya existe no se toca nada y se sigue
+//! usando el nuevo.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Nombre del directorio de la aplicación en cada base XDG
+const APP_DIR: &str = "gdrivexp";
+
+/// Nombre usado por las versiones anteriores
+const LEGACY_DIR: &str = "fedoradrive";
+
+/// Nombre de la base de datos de metadatos
+const DB_FILE_NAME: &str = "metadata.db";
+
+/// Archivos de SQLite que acompañan a la base de datos
+const DB_SIDE_SUFFIXES: [&str; 3] = ["", "-wal", "-shm"];
+
+/// Directorio de configuración (`config.json`, `credentials.json`, `tokens.json`)
+pub fn config_dir() -> PathBuf {
+    Layout::xdg(APP_DIR).config
+}
+
+/// Directorio de la caché de contenido
+pub fn cache_dir() -> PathBuf {
+    Layout::xdg(APP_DIR).cache
+}
+
+/// Caché de miniaturas compartida del escritorio (especificación de Freedesktop)
+pub fn thumbnails_dir() -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+    dirs::cache_dir().unwrap_or_else(|| home.join(".cache")).join("thumbnails")
+}
+
+/// Directorio de estado (base de datos y logs)
+pub fn state_dir() -> PathBuf {
+    Layout::xdg(APP_DIR).state
+}
+
+/// Ruta predeterminada de la base de datos de metadatos
+pub fn db_path() -> PathBuf {
+    state_dir().join(DB_FILE_NAME)
+}
+
+/// Tokens OAuth2 persistidos por `yup-oauth2`
+pub fn tokens_path() -> PathBuf {
+    config_dir().join("tokens.json")
+}
+
+/// Secreto de la aplicación OAuth2 que instala el usuario
+pub fn credentials_path() -> PathBuf {
+    config_dir().join("credentials.json")
+}
+
+/// Directorios de una instalación
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Layout {
+    config: PathBuf,
+    cache: PathBuf,
+    state: PathBuf,
+}
+
+impl Layout {
+    fn xdg(app: &str) -> Self {
+        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("/tmp"));
+        Self {
+            config: dirs::config_dir().unwrap_or_else(|| home.join(".config")).join(app),
+            cache: dirs::cache_dir().unwrap_or_else(|| home.join(".cache")).join(app),
+            state: dirs::state_dir().unwrap_or_else(|| home.join(".local/state")).join(app),
+        }
+    }
+
+    /// La base de datos vivía en el directorio de configuración
+    fn legacy_db_path(&self) -> PathBuf {
+        self.config.join(DB_FILE_NAME)
+    }
+}
+
+/// Qué hizo la migración con cada directorio o archivo antiguo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutChange {
+    Moved { from: PathBuf, to: PathBuf },
+    /// Existen el antiguo y el nuevo: se usa el nuevo y el antiguo queda intacto
+    Kept { legacy: PathBuf, current: PathBuf },
+    Failed { from: PathBuf, error: String },
+    /// `config.json` apuntaba a las rutas predeterminadas antiguas
+    ConfigRewritten { path: PathBuf },
+}
+
+/// Mueve los directorios `fedoradrive` a los `gdrivexp`. Se ejecuta antes de
+/// inicializar el logging (el propio directorio de logs se mueve), así que
+/// devuelve lo ocurrido para registrarlo después.
+pub fn migrate_legacy_layout() -> Vec<LayoutChange> {
+    migrate_in(&Layout::xdg(LEGACY_DIR), &Layout::xdg(APP_DIR))
+}
+
+fn migrate_in(legacy: &Layout, current: &Layout) -> Vec<LayoutChange> {
+    let mut changes = Vec::new();
+
+    // La caché lleva claves en xattrs y puede pesar GB: solo renombrar. Si no
+    // se puede, `config.json` sigue apuntando a la antigua y nada se pierde.
+    migrate_dir(&legacy.config, &current.config, true, &mut changes);
+    migrate_dir(&legacy.cache, &current.cache, false, &mut changes);
+    migrate_dir(&legacy.state, &current.state, true, &mut changes);
+
+    // Con la configuración ya movida, la base de datos está en `current.config`
+    let moved_db = current.legacy_db_path();
+    let db = current.state.join(DB_FILE_NAME);
+    if moved_db.exists() {
+        if db.exists() {
+            changes.push(LayoutChange::Kept { legacy: moved_db.clone(), current: db.clone() });
+        } else {
+            for suffix in DB_SIDE_SUFFIXES {
+                let from = PathBuf::from(format!("{}{}", moved_db.display(), suffix));
+                let to = PathBuf::from(format!("{}{}", db.display(), suffix));
+                if from.exists() {
+                    push_result(move_path(&from, &to, true), from, to, &mut changes);
+                }
+            }
+        }
+    }
+
+    let config_file = current.config.join("config.json");
+    if config_file.exists() {
+        let rewrites = [
+            ("cache_dir", legacy.cache.clone(), current.cache.clone()),
+            ("db_path", legacy.legacy_db_path(), db.clone()),
+            ("db_path", moved_db.clone(), db),
+        ];
+        match rewrite_config(&config_file, &rewrites) {
+            Ok(true) => changes.push(LayoutChange::ConfigRewritten { path: config_file }),
+            Ok(false) => {}
+            Err(e) => changes.push(LayoutChange::Failed { from: config_file, error: format!("{:#}", e) }),
+        }
+    }
+
+    changes
+}
+
+fn migrate_dir(from: &Path, to: &Path, allow_copy: bool, changes: &mut Vec<LayoutChange>) {
+    if !from.exists() {
+        return;
+    }
+    if to.exists() {
+        changes.push(LayoutChange::Kept { legacy: from.to_path_buf(), current: to.to_path_buf() });
+        return;
+    }
+    push_result(move_path(from, to, allow_copy), from.to_path_buf(), to.to_path_buf(), changes);
+}
+
+fn push_result(result: Result<()>, from: PathBuf, to: PathBuf, changes: &mut Vec<LayoutChange>) {
+    changes.push(match result {
+        Ok(()) => LayoutChange::Moved { from, to },
+        Err(e) => LayoutChange::Failed { from, error: format!("{:#}", e) },
+    });
+}
+
+/// `rename`, o copia y borrado si el destino está en otro sistema de archivos
+fn move_path(from: &Path, to: &Path, allow_copy: bool) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("Error creando {:?}", parent))?;
+    }
+    match std::fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if allow_copy && e.raw_os_error() == Some(libc::EXDEV) => {
+            copy_recursive(from, to).with_context(|| format!("Error copiando {:?} a {:?}", from, to))?;
+            if from.is_dir() {
+                std::fs::remove_dir_all(from)?;
+            } else {
+                std::fs::remove_file(from)?;
+            }
+            Ok(())
+        }
+        Err(e) => Err(e).with_context(|| format!("Error moviendo {:?} a {:?}", from, to)),
+    }
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> Result<()> {
+    if !from.is_dir() {
+        std::fs::copy(from, to)?;
+        return Ok(());
+    }
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Cambia en `config.json` los campos que aún valen una ruta antigua que ya no
+/// existe. Se edita el JSON sin tipar para no depender de `Config`.
+fn rewrite_config(config_file: &Path, rewrites: &[(&str, PathBuf, PathBuf)]) -> Result<bool> {
+    let contents = std::fs::read_to_string(config_file)?;
+    let mut value: serde_json::Value = serde_json::from_str(&contents)?;
+    let Some(object) = value.as_object_mut() else {
+        return Ok(false);
+    };
+
+    let mut changed = false;
+    for (key, old, new) in rewrites {
+        let matches = object.get(*key).and_then(|v| v.as_str()).is_some_and(|v| Path::new(v) == old);
+        if matches && !old.exists() {
+            object.insert(key.to_string(), serde_json::Value::String(new.to_string_lossy().into_owned()));
+            changed = true;
+        }
+    }
+
+    if changed {
+        std::fs::write(config_file, serde_json::to_string_pretty(&value)?)?;
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(root: &Path, app: &str) -> Layout {
+        Layout {
+            config: root.join(".config").join(app),
+            cache: root.join(".cache").join(app),
+            state: root.join(".local/state").join(app),
+        }
+    }
+
+    /// Instalación antigua con configuración, base de datos, caché y logs
+    fn legacy_install(root: &Path) -> (Layout, Layout) {
+        let legacy = layout(root, LEGACY_DIR);
+        let current = layout(root, APP_DIR);
+        std::fs::create_dir_all(&legacy.config).unwrap();
+        std::fs::create_dir_all(&legacy.cache).unwrap();
+        std::fs::create_dir_all(legacy.state.join("logs")).unwrap();
+        std::fs::write(legacy.config.join("tokens.json"), "{}").unwrap();
+        std::fs::write(legacy.config.join("metadata.db"), "db").unwrap();
+        std::fs::write(legacy.config.join("metadata.db-wal"), "wal").unwrap();
+        std::fs::write(legacy.cache.join("file_1"), "contenido").unwrap();
+        std::fs::write(legacy.state.join("logs/g-drive-xp.log"), "log").unwrap();
+        let config = serde_json::json!({
+            "cache_dir": legacy.cache,
+            "db_path": legacy.legacy_db_path(),
+            "mirror_path": root.join("GoogleDrive"),
+            "sync_interval_secs": 60,
+        });
+        std::fs::write(legacy.config.join("config.json"), config.to_string()).unwrap();
+        (legacy, current)
+    }
+
+    #[test]
+    fn test_migrate_moves_everything_and_rewrites_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (legacy, current) = legacy_install(tmp.path());
+
+        let changes = migrate_in(&legacy, &current);
+
+        assert!(!legacy.config.exists() && !legacy.cache.exists() && !legacy.state.exists());
+        assert!(current.config.join("tokens.json").exists());
+        assert!(!current.config.join("metadata.db").exists());
+        assert_eq!(std::fs::read_to_string(current.state.join("metadata.db")).unwrap(), "db");
+        assert!(current.state.join("metadata.db-wal").exists());
+        assert!(current.cache.join("file_1").exists());
+        assert!(current.state.join("logs/g-drive-xp.log").exists());
+
+        let config: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(current.config.join("config.json")).unwrap()).unwrap();
+        assert_eq!(config["cache_dir"], current.cache.to_string_lossy().as_ref());
+        assert_eq!(config["db_path"], current.state.join("metadata.db").to_string_lossy().as_ref());
+        assert_eq!(config["mirror_path"], tmp.path().join("GoogleDrive").to_string_lossy().as_ref());
+        assert!(changes.contains(&LayoutChange::ConfigRewritten { path: current.config.join("config.json") }));
+
+        // Una segunda ejecución no encuentra nada que migrar
+        assert!(migrate_in(&legacy, &current).is_empty());
+    }
+
+    #[test]
+    fn test_migrate_keeps_legacy_when_new_exists() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (legacy, current) = legacy_install(tmp.path());
+        std::fs::create_dir_all(&current.cache).unwrap();
+
+        let changes = migrate_in(&legacy, &current);
+
+        assert!(changes.contains(&LayoutChange::Kept { legacy: legacy.cache.clone(), current: current.cache.clone() }));
+        assert!(legacy.cache.join("file_1").exists());
+        // La caché antigua sigue existiendo: config.json sigue apuntando a ella
+        let config: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(current.config.join("config.json")).unwrap()).unwrap();
+        assert_eq!(config["cache_dir"], legacy.cache.to_string_lossy().as_ref());
+    }
+
+    #[test]
+    fn test_rewrite_config_keeps_custom_paths() {
+        let tmp = tempfile::tempdir().unwrap();
+        let config_file = tmp.path().join("config.json");
+        std::fs::write(&config_file, r#"{"cache_dir": "/srv/cache", "db_path": "/srv/metadata.db"}"#).unwrap();
+
+        let rewrites = [("cache_dir", tmp.path().join("old"), tmp.path().join("new"))];
+        assert!(!rewrite_config(&config_file, &rewrites).unwrap());
+        assert!(std::fs::read_to_string(&config_file).unwrap().contains("/srv/cache"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Estado del almacenamiento de la cuenta de Google Drive
+//!
+//! Cuando una subida falla con `DriveError::StorageFull` el uploader marca el
+//! almacenamiento como lleno: el archivo queda en estado `error` (permanente)
+//! y, mientras dure, FUSE rechaza con `ENOSPC` las escrituras que harían
+//! crecer los datos pendientes, en lugar de aceptar bytes que nunca llegarán
+//! a Drive. La GUI muestra un aviso destacado.
+//!
+//! Drive no avisa cuando se libera espacio: el estado se limpia con la
+//! siguiente subida de contenido que termine bien o cuando el usuario pide
+//! reintentar desde la GUI, que reprograma los inodes rechazados
+//! (`take_rejected`).
+//!
+//! `main.rs` crea un `StorageQuota` y comparte clones con el uploader, FUSE y
+//! la GUI; no hay estado global.
+
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Almacenamiento de Drive agotado y los inodes cuya subida rechazó
+#[derive(Debug, Clone, Default)]
+pub struct StorageQuota {
+    /// `true` desde que Drive rechazó una subida por falta de almacenamiento
+    full: Arc<AtomicBool>,
+    rejected: Arc<Mutex<BTreeSet<u64>>>,
+}
+
+impl StorageQuota {
+    /// Verifica si el almacenamiento de Drive se sabe agotado
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.full.load(Ordering::SeqCst)
+    }
+
+    /// Drive rechazó la subida de `inode` por falta de almacenamiento.
+    /// Retorna `true` si el almacenamiento no se sabía agotado.
+    pub fn mark_full(&self, inode: u64) -> bool {
+        self.rejected.lock().unwrap().insert(inode);
+        self.set_full(true)
+    }
+
+    /// Drive aceptó contenido: ya hay espacio
+    pub fn mark_available(&self) -> bool {
+        self.set_full(false)
+    }
+
+    /// Limpia el estado y retorna los inodes rechazados, para reprogramarlos
+    pub fn take_rejected(&self) -> Vec<u64> {
+        self.set_full(false);
+        std::mem::take(&mut *self.rejected.lock().unwrap()).into_iter().collect()
+    }
+
+    /// Marca o limpia el almacenamiento agotado. Retorna `true` si es una transición.
+    fn set_full(&self, full: bool) -> bool {
+        let changed = self.full.swap(full, Ordering::SeqCst) != full;
+        if changed {
+            if full {
+                tracing::warn!("💾 Almacenamiento de Google Drive agotado: las escrituras nuevas fallarán con ENOSPC");
+            } else {
+                tracing::info!("💾 Almacenamiento de Google Drive disponible de nuevo");
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mark_full_reports_transitions() {
+        let quota = StorageQuota::default();
+
+        assert!(quota.mark_full(7));
+        assert!(!quota.mark_full(8), "ya estaba lleno");
+        assert!(quota.clone().is_full(), "los clones comparten el estado");
+        assert!(quota.mark_available());
+        assert!(!quota.is_full());
+        assert!(!quota.mark_available());
+    }
+
+    #[test]
+    fn test_take_rejected_clears_state() {
+        let quota = StorageQuota::default();
+        quota.mark_full(9);
+        quota.mark_full(3);
+
+        assert_eq!(quota.take_rejected(), vec![3, 9]);
+        assert!(!quota.is_full());
+        assert!(quota.take_rejected().is_empty());
+    }
+}
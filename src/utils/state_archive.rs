@@ -0,0 +1,455 @@
+//! Exportar e importar el estado de la aplicación para pasar a otro equipo
+//!
+//! El archivo es un `.tar.gz` (lo crea y lo abre el `tar` del sistema) con
+//! `manifest.json`, `config.json` (exclusiones y patrones ignorados incluidos),
+//! `credentials.json` si existe, `pins.json` (lo marcado "Mantener siempre en
+//! este dispositivo", por ID de Drive) y, si se pide, `metadata.db`: una copia
+//! consistente de la base de metadatos. Ni la caché de contenido ni los tokens
+//! OAuth viajan: el equipo nuevo vuelve a iniciar sesión y descarga lo mantenido.
+//!
+//! Al importar, las rutas de la configuración que cuelgan del `$HOME` de origen
+//! pasan al del equipo nuevo y lo que había se aparta con el sufijo
+//! `.pre-import`. Sin base de datos, los elementos mantenidos esperan en
+//! `sync_meta` (`IMPORTED_PINS_KEY`) hasta que el primer escaneo completo los
+//! conoce (`apply_imported_pins`).
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+use crate::cli::StateCommand;
+use crate::config::Config;
+use crate::db::MetadataRepository;
+use crate::i18n::t;
+
+/// Versión del formato del archivo; uno más nuevo no se importa
+const ARCHIVE_FORMAT: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const CONFIG_FILE: &str = "config.json";
+const CREDENTIALS_FILE: &str = "credentials.json";
+const PINS_FILE: &str = "pins.json";
+const DB_FILE: &str = "metadata.db";
+
+/// Sufijo con el que se aparta lo que la importación reemplaza
+const SET_ASIDE_SUFFIX: &str = ".pre-import";
+
+/// Clave en sync_meta de los elementos mantenidos importados sin base de datos
+pub const IMPORTED_PINS_KEY: &str = "imported_pins";
+
+/// Datos del archivo exportado
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Manifest {
+    format: u32,
+    app_version: String,
+    /// `$HOME` del equipo de origen, para trasladar las rutas de la configuración
+    home: PathBuf,
+    includes_db: bool,
+}
+
+/// Elemento marcado "Mantener siempre en este dispositivo"
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct KeptItem {
+    gdrive_id: String,
+    is_dir: bool,
+}
+
+/// Dónde vive el estado de esta instalación
+#[derive(Debug, Clone)]
+pub struct Locations {
+    pub config: PathBuf,
+    pub credentials: PathBuf,
+    pub home: PathBuf,
+}
+
+impl Locations {
+    /// Las rutas de esta sesión (XDG)
+    pub fn current() -> Result<Self> {
+        Ok(Self {
+            config: Config::config_path()?,
+            credentials: super::paths::credentials_path(),
+            home: dirs::home_dir().context("No se pudo determinar el directorio personal")?,
+        })
+    }
+}
+
+/// Resultado de una exportación
+#[derive(Debug, PartialEq)]
+pub struct ExportSummary {
+    pub kept: usize,
+    pub includes_db: bool,
+}
+
+/// Resultado de una importación
+#[derive(Debug, PartialEq)]
+pub struct ImportSummary {
+    pub kept: usize,
+    pub includes_db: bool,
+}
+
+/// Ejecuta `--export-state`/`--import-state` y termina sin abrir la interfaz
+pub fn run_cli(command: StateCommand) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let locations = Locations::current()?;
+    match command {
+        StateCommand::Export { dest, include_db } => {
+            let summary = runtime.block_on(export(&dest, include_db, &locations))?;
+            println!("{}", t!("cli-export-done", path = dest.display().to_string(), count = summary.kept));
+            if !summary.includes_db {
+                println!("{}", t!("cli-export-without-db"));
+            }
+        }
+        StateCommand::Import { archive } => {
+            // La app en marcha seguiría usando (y reescribiendo) la base y la configuración viejas
+            if std::os::unix::net::UnixStream::connect(crate::ipc::get_socket_path()).is_ok() {
+                anyhow::bail!("{}", t!("cli-import-app-running"));
+            }
+            let summary = runtime.block_on(import(&archive, &locations))?;
+            println!("{}", t!("cli-import-done", count = summary.kept));
+        }
+    }
+    Ok(())
+}
+
+/// Escribe en `dest` el archivo con el estado de esta instalación. Con
+/// `include_db`, falla si quedan cambios sin subir: su contenido solo está en
+/// la caché, que no se exporta
+pub async fn export(dest: &Path, include_db: bool, locations: &Locations) -> Result<ExportSummary> {
+    let config = read_config(&locations.config)?;
+    let db_path = config.clone().with_overrides().db_path;
+    let staging = Staging::new("export")?;
+    let mut members = vec![MANIFEST_FILE, CONFIG_FILE, PINS_FILE];
+
+    write_json(&staging.path.join(CONFIG_FILE), &config)?;
+    if locations.credentials.exists() {
+        std::fs::copy(&locations.credentials, staging.path.join(CREDENTIALS_FILE))
+            .context("No se pudo leer credentials.json")?;
+        members.push(CREDENTIALS_FILE);
+    }
+
+    let db = if db_path.exists() {
+        Some(MetadataRepository::new(&db_path).await?)
+    } else if include_db {
+        anyhow::bail!("No existe la base de datos {:?}", db_path);
+    } else {
+        None
+    };
+
+    let kept = match &db {
+        Some(db) => db.list_kept_on_device().await?
+            .into_iter()
+            .map(|(gdrive_id, is_dir)| KeptItem { gdrive_id, is_dir })
+            .collect(),
+        None => Vec::new(),
+    };
+    write_json(&staging.path.join(PINS_FILE), &kept)?;
+
+    if let (true, Some(db)) = (include_db, &db) {
+        let (fuse, local) = db.count_dirty().await?;
+        if fuse + local > 0 {
+            anyhow::bail!("Hay {} cambios sin subir a Drive; espera a que terminen para exportar la base de datos", fuse + local);
+        }
+        db.export_snapshot(&staging.path.join(DB_FILE)).await?;
+        members.push(DB_FILE);
+    }
+    if let Some(db) = db {
+        db.close().await;
+    }
+
+    let manifest = Manifest {
+        format: ARCHIVE_FORMAT,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        home: locations.home.clone(),
+        includes_db: include_db,
+    };
+    write_json(&staging.path.join(MANIFEST_FILE), &manifest)?;
+
+    let mut args: Vec<OsString> = vec!["-czf".into(), dest.into(), "-C".into(), staging.path.clone().into()];
+    args.extend(members.iter().map(OsString::from));
+    if let Err(e) = run_tar(args).await {
+        let _ = std::fs::remove_file(dest);
+        return Err(e);
+    }
+    // Puede llevar el secreto OAuth
+    std::fs::set_permissions(dest, std::fs::Permissions::from_mode(0o600))?;
+
+    tracing::info!("📦 Estado exportado a {:?} ({} elementos mantenidos, base de datos: {})", dest, kept.len(), include_db);
+    Ok(ExportSummary { kept: kept.len(), includes_db: include_db })
+}
+
+/// Restaura en esta instalación el estado de `archive`. La aplicación no
+/// debe estar en marcha
+pub async fn import(archive: &Path, locations: &Locations) -> Result<ImportSummary> {
+    let staging = Staging::new("import")?;
+    run_tar(vec!["-xzf".into(), archive.into(), "-C".into(), staging.path.clone().into()]).await?;
+
+    let manifest: Manifest = read_json(&staging.path.join(MANIFEST_FILE))
+        .context("El archivo no es una exportación de G-DriveXP")?;
+    if manifest.format > ARCHIVE_FORMAT {
+        anyhow::bail!("El archivo lo creó una versión más nueva de G-DriveXP ({})", manifest.app_version);
+    }
+
+    let mut config = read_config(&staging.path.join(CONFIG_FILE))?;
+    rehome_config(&mut config, &manifest.home, &locations.home);
+    set_aside(&locations.config)?;
+    write_json(&locations.config, &config)?;
+
+    let credentials = staging.path.join(CREDENTIALS_FILE);
+    if credentials.exists() && !locations.credentials.exists() {
+        std::fs::copy(&credentials, &locations.credentials).context("No se pudo copiar credentials.json")?;
+        std::fs::set_permissions(&locations.credentials, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    let kept: Vec<KeptItem> = read_json(&staging.path.join(PINS_FILE))?;
+    let db_path = config.with_overrides().db_path;
+    if manifest.includes_db {
+        for suffix in ["", "-wal", "-shm"] {
+            set_aside(&PathBuf::from(format!("{}{}", db_path.display(), suffix)))?;
+        }
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(staging.path.join(DB_FILE), &db_path).context("No se pudo copiar la base de datos")?;
+        let db = MetadataRepository::new(&db_path).await?;
+        db.forget_device_state().await?;
+        db.wal_checkpoint(true).await?;
+        db.close().await;
+    } else if !kept.is_empty() {
+        // La base de este equipo (nueva o no) aún no conoce esos archivos
+        let db = MetadataRepository::new(&db_path).await?;
+        db.set_sync_meta(IMPORTED_PINS_KEY, &serde_json::to_string(&kept)?).await?;
+        db.close().await;
+    }
+
+    tracing::info!("📦 Estado importado desde {:?} ({} elementos mantenidos, base de datos: {})", archive, kept.len(), manifest.includes_db);
+    Ok(ImportSummary { kept: kept.len(), includes_db: manifest.includes_db })
+}
+
+/// Marca "Mantener siempre en este dispositivo" lo importado sin base de datos
+/// y olvida la lista. Se llama cuando el escaneo completo ya indexó Drive: lo
+/// que entonces no aparece ya no existe (o está excluido) y se descarta
+pub async fn apply_imported_pins(db: &MetadataRepository) -> Result<usize> {
+    let Some(pending) = db.get_sync_meta(IMPORTED_PINS_KEY).await? else {
+        return Ok(0);
+    };
+    let kept: Vec<KeptItem> = serde_json::from_str(&pending).unwrap_or_default();
+    let mut applied = 0;
+    for item in &kept {
+        if let Some(inode) = db.get_inode_by_gdrive_id(&item.gdrive_id).await? {
+            db.keep_on_device(inode, item.is_dir).await?;
+            applied += 1;
+        }
+    }
+    db.delete_sync_meta(IMPORTED_PINS_KEY).await?;
+    tracing::info!("📌 {} de {} elementos mantenidos importados aplicados", applied, kept.len());
+    Ok(applied)
+}
+
+/// Configuración guardada (la predeterminada si no hay archivo)
+fn read_config(path: &Path) -> Result<Config> {
+    if !path.exists() {
+        return Config::default();
+    }
+    read_json(path).with_context(|| format!("Configuración inválida en {:?}", path))
+}
+
+/// Lleva al `$HOME` de este equipo las rutas que colgaban del de origen
+fn rehome_config(config: &mut Config, from: &Path, to: &Path) {
+    let rehome = |path: &mut PathBuf| {
+        if let Ok(rest) = path.strip_prefix(from) {
+            *path = to.join(rest);
+        }
+    };
+    rehome(&mut config.fuse_mount_path);
+    rehome(&mut config.mirror_path);
+    rehome(&mut config.cache_dir);
+    rehome(&mut config.db_path);
+    if let Some(ca) = config.ca_bundle_path.as_mut() {
+        rehome(ca);
+    }
+}
+
+/// Renombra `path` a `<path>.pre-import` si existe
+fn set_aside(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(SET_ASIDE_SUFFIX);
+    std::fs::rename(path, &aside).with_context(|| format!("No se pudo apartar {:?}", path))?;
+    tracing::info!("Apartado {:?} antes de importar", path);
+    Ok(())
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = std::fs::read_to_string(path).with_context(|| format!("No se pudo leer {:?}", path))?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(value)?).with_context(|| format!("No se pudo escribir {:?}", path))
+}
+
+async fn run_tar(args: Vec<OsString>) -> Result<()> {
+    let output = tokio::process::Command::new("tar")
+        .args(&args)
+        .output()
+        .await
+        .context("No se pudo ejecutar tar")?;
+    if !output.status.success() {
+        anyhow::bail!("tar falló: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Directorio temporal privado (puede contener el secreto OAuth); se borra al soltarse
+struct Staging {
+    path: PathBuf,
+}
+
+impl Staging {
+    fn new(purpose: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("gdrivexp-{}-{}", purpose, uuid::Uuid::new_v4()));
+        std::fs::create_dir(&path)?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o700))?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+
+    /// Instalación de prueba con su `$HOME` dentro de `root`
+    fn locations(root: &Path, user: &str) -> Locations {
+        let home = root.join(user);
+        Locations {
+            config: home.join(".config/gdrivexp/config.json"),
+            credentials: home.join(".config/gdrivexp/credentials.json"),
+            home,
+        }
+    }
+
+    /// Guarda en `locations` una configuración con todo bajo su `$HOME`, una
+    /// carpeta mantenida con un archivo en caché y un archivo suelto en Drive.
+    /// Retorna la ruta de la base de datos
+    async fn seed(locations: &Locations) -> PathBuf {
+        let mut config = Config::default().unwrap();
+        config.mirror_path = locations.home.join("GoogleDrive");
+        config.fuse_mount_path = locations.home.join("GoogleDrive/FUSE_Mount");
+        config.cache_dir = locations.home.join(".cache/gdrivexp");
+        config.db_path = locations.home.join(".local/state/gdrivexp/metadata.db");
+        config.excluded_folders = vec!["Fotos".to_string()];
+        write_json(&locations.config, &config).unwrap();
+        std::fs::write(&locations.credentials, b"{\"installed\": {}}").unwrap();
+
+        let db = MetadataRepository::new(&config.db_path).await.unwrap();
+        db.ensure_root().await.unwrap();
+        for (gdrive_id, name, is_dir, parent) in [("f_carpeta", "Carpeta", true, 1), ("f_a", "a.txt", false, 2), ("f_suelto", "suelto.txt", false, 1)] {
+            let inode = db.get_or_create_inode(gdrive_id).await.unwrap();
+            db.upsert_file_metadata(inode, 4, 0, 0o644, is_dir, None, true, false, true).await.unwrap();
+            db.upsert_dentry(parent, inode, name).await.unwrap();
+        }
+        db.keep_on_device(2, true).await.unwrap();
+        db.add_cached_chunk(3, 0, 3).await.unwrap();
+        db.close().await;
+        config.db_path
+    }
+
+    #[rstest]
+    #[case::with_db(true)]
+    #[case::without_db(false)]
+    #[tokio::test]
+    async fn test_export_import_round_trip_moves_home(#[case] include_db: bool) {
+        let dir = tempfile::tempdir().unwrap();
+        let old = locations(dir.path(), "antes");
+        let new = locations(dir.path(), "despues");
+        seed(&old).await;
+        let archive = dir.path().join("estado.tar.gz");
+
+        let exported = export(&archive, include_db, &old).await.unwrap();
+        assert_eq!(exported, ExportSummary { kept: 1, includes_db: include_db });
+        let mode = std::fs::metadata(&archive).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "lleva el secreto OAuth");
+
+        let imported = import(&archive, &new).await.unwrap();
+        assert_eq!(imported, ImportSummary { kept: 1, includes_db: include_db });
+
+        let config: Config = read_json(&new.config).unwrap();
+        assert_eq!(config.mirror_path, new.home.join("GoogleDrive"));
+        assert_eq!(config.db_path, new.home.join(".local/state/gdrivexp/metadata.db"));
+        assert_eq!(config.excluded_folders, vec!["Fotos".to_string()]);
+        assert!(new.credentials.exists());
+
+        let db = MetadataRepository::new(&config.db_path).await.unwrap();
+        if include_db {
+            assert_eq!(db.list_kept_on_device().await.unwrap(), vec![("f_carpeta".to_string(), true)]);
+            assert!(!db.has_any_chunks(3).await.unwrap(), "la caché no viaja");
+            assert_eq!(db.get_sync_meta(IMPORTED_PINS_KEY).await.unwrap(), None);
+        } else {
+            assert!(db.is_empty().await.unwrap());
+            // El primer escaneo indexa la carpeta
+            let inode = db.get_or_create_inode("f_carpeta").await.unwrap();
+            db.upsert_file_metadata(inode, 0, 0, 0o755, true, None, true, false, true).await.unwrap();
+            db.upsert_dentry(1, inode, "Carpeta").await.unwrap();
+
+            assert_eq!(apply_imported_pins(&db).await.unwrap(), 1);
+            assert_eq!(db.list_kept_on_device().await.unwrap(), vec![("f_carpeta".to_string(), true)]);
+            assert_eq!(apply_imported_pins(&db).await.unwrap(), 0, "solo una vez");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_with_db_refuses_pending_uploads() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = locations(dir.path(), "antes");
+        let db_path = seed(&old).await;
+        let db = MetadataRepository::new(&db_path).await.unwrap();
+        db.mark_dirty(3).await.unwrap();
+        db.close().await;
+        let archive = dir.path().join("estado.tar.gz");
+
+        assert!(export(&archive, true, &old).await.is_err());
+        assert!(!archive.exists());
+        assert_eq!(export(&archive, false, &old).await.unwrap().kept, 1, "sin la base no importa");
+    }
+
+    #[tokio::test]
+    async fn test_import_sets_aside_previous_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = locations(dir.path(), "antes");
+        let new = locations(dir.path(), "despues");
+        seed(&old).await;
+        write_json(&new.config, &serde_json::json!({ "previa": true })).unwrap();
+        let archive = dir.path().join("estado.tar.gz");
+        export(&archive, false, &old).await.unwrap();
+
+        import(&archive, &new).await.unwrap();
+
+        let aside = new.config.with_file_name("config.json.pre-import");
+        assert!(std::fs::read_to_string(aside).unwrap().contains("previa"));
+    }
+
+    #[rstest]
+    #[case::under_home("/home/antes/GoogleDrive", "/home/despues/GoogleDrive")]
+    #[case::elsewhere("/srv/drive", "/srv/drive")]
+    #[case::similar_prefix("/home/antes2/GoogleDrive", "/home/antes2/GoogleDrive")]
+    fn test_rehome_config(#[case] mirror: &str, #[case] expected: &str) {
+        let mut config = Config::default().unwrap();
+        config.mirror_path = PathBuf::from(mirror);
+        rehome_config(&mut config, Path::new("/home/antes"), Path::new("/home/despues"));
+        assert_eq!(config.mirror_path, PathBuf::from(expected));
+    }
+}
@@ -0,0 +1,321 @@
+//! Supervisor del backend con reintentos
+//!
+//! Un fallo transitorio al arrancar (p. ej. sin red durante la autenticación)
+//! dejaba la aplicación sin servicio hasta reiniciarla a mano. El supervisor
+//! ejecuta el backend en su propio hilo y, si termina con error, limpia el
+//! montaje y lo relanza con backoff exponencial. Cada transición se reporta
+//! con `BackendState`; la GUI puede adelantar el reintento ("Reconectar").
+//!
+//! Un panic no se reintenta: es un bug, no un fallo transitorio, y el estado
+//! global del proceso ya no es fiable. La GUI ofrece reiniciar la aplicación.
+//! Tampoco se reintentan solos los errores marcados con `needs_user_action`
+//! (configuración inválida, punto de montaje ocupado...): repetirlos no cambia
+//! nada, así que el supervisor espera a que el usuario pida reconectar.
+//!
+//! Si el backend llevaba `HEALTHY_RUN` funcionando antes de fallar, el backoff
+//! vuelve a empezar: un corte aislado tras horas de servicio no hereda las
+//! esperas largas de fallos antiguos.
+
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+/// Espera antes del primer reintento (se duplica en cada fallo)
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Espera máxima entre reintentos
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// Tiempo de servicio tras el que un fallo reinicia el backoff
+const HEALTHY_RUN: Duration = Duration::from_secs(600);
+
+/// Error del backend que no se resuelve reintentando: el supervisor espera a
+/// que el usuario pida reconectar. Se muestra igual que el error que envuelve.
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct NeedsUserAction(anyhow::Error);
+
+/// Marca `error` como no reintentable (ver `NeedsUserAction`)
+pub fn needs_user_action(error: anyhow::Error) -> anyhow::Error {
+    NeedsUserAction(error).into()
+}
+
+/// Un reintento automático puede resolver el error
+fn is_transient(error: &anyhow::Error) -> bool {
+    !error.chain().any(|cause| cause.is::<NeedsUserAction>())
+}
+
+/// Estado del backend visto por el supervisor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendState {
+    /// Arrancando (`attempt` empieza en 1)
+    Starting { attempt: u32 },
+    /// Sistema de archivos montado y activo (lo reporta el propio backend)
+    Running,
+    /// Terminó con error; se reintenta tras `retry_in` o al pedir reconexión
+    Retrying { attempt: u32, retry_in: Duration, error: String },
+    /// Terminó con un error que necesita al usuario; se reintenta solo al pedir reconexión
+    Stopped { attempt: u32, error: String },
+    /// Terminó con panic; no se reintenta
+    Crashed(String),
+}
+
+type BackendFn = Arc<dyn Fn() -> anyhow::Result<()> + Send + Sync>;
+
+/// Ejecuta el backend y lo relanza tras errores
+pub struct BackendSupervisor {
+    run: BackendFn,
+    on_state: Box<dyn Fn(BackendState) + Send>,
+    on_failure: Box<dyn Fn() + Send>,
+    reconnect_tx: Sender<()>,
+    reconnect_rx: Receiver<()>,
+    /// Tiempo de servicio tras el que un fallo reinicia el backoff (`HEALTHY_RUN`)
+    healthy_run: Duration,
+}
+
+impl BackendSupervisor {
+    pub fn new(run: impl Fn() -> anyhow::Result<()> + Send + Sync + 'static) -> Self {
+        let (reconnect_tx, reconnect_rx) = mpsc::channel();
+        Self {
+            run: Arc::new(run),
+            on_state: Box::new(|_| {}),
+            on_failure: Box::new(|| {}),
+            reconnect_tx,
+            reconnect_rx,
+            healthy_run: HEALTHY_RUN,
+        }
+    }
+
+    /// Callback para cada transición de estado
+    pub fn with_state_callback(mut self, on_state: impl Fn(BackendState) + Send + 'static) -> Self {
+        self.on_state = Box::new(on_state);
+        self
+    }
+
+    /// Limpieza tras cada terminación anormal (p. ej. `crash::cleanup_after_crash`)
+    pub fn with_failure_cleanup(mut self, on_failure: impl Fn() + Send + 'static) -> Self {
+        self.on_failure = Box::new(on_failure);
+        self
+    }
+
+    /// Canal para adelantar el reintento pendiente
+    pub fn reconnect_sender(&self) -> Sender<()> {
+        self.reconnect_tx.clone()
+    }
+
+    /// Inicia el supervisor en un hilo del sistema
+    pub fn spawn(self) -> std::thread::JoinHandle<()> {
+        std::thread::spawn(move || self.supervise())
+    }
+
+    fn supervise(self) {
+        let Self { run, on_state, on_failure, reconnect_tx, reconnect_rx, healthy_run } = self;
+        // Sin emisores externos (GUI cerrada) no hay a quién esperar
+        drop(reconnect_tx);
+
+        let mut attempt = 1;
+        loop {
+            on_state(BackendState::Starting { attempt });
+
+            let run = run.clone();
+            let started = Instant::now();
+            let backend = std::thread::Builder::new()
+                .name("backend".to_string())
+                .spawn(move || run());
+            let (error, transient) = match backend.map(|handle| handle.join()) {
+                Ok(Ok(Ok(()))) => return,
+                Ok(Ok(Err(e))) => {
+                    tracing::error!("❌ Error en el backend (intento {}): {:?}", attempt, e);
+                    (format!("{:#}", e), is_transient(&e))
+                }
+                Ok(Err(payload)) => {
+                    let message = super::crash::last_panic()
+                        .unwrap_or_else(|| super::crash::panic_message(payload.as_ref()));
+                    on_failure();
+                    on_state(BackendState::Crashed(message));
+                    return;
+                }
+                Err(e) => (format!("No se pudo iniciar el backend: {}", e), true),
+            };
+            on_failure();
+
+            // Tras un rato sirviendo, el fallo cuenta como el primero
+            if started.elapsed() >= healthy_run {
+                attempt = 1;
+            }
+
+            let reconnect = if transient {
+                let retry_in = retry_delay(attempt);
+                tracing::warn!("🔁 Reintentando el backend en {} s", retry_in.as_secs());
+                on_state(BackendState::Retrying { attempt, retry_in, error });
+                match reconnect_rx.recv_timeout(retry_in) {
+                    Ok(()) => Ok(true),
+                    Err(RecvTimeoutError::Timeout) => Ok(false),
+                    Err(RecvTimeoutError::Disconnected) => Err(()),
+                }
+            } else {
+                tracing::warn!("⏸️ El backend necesita una acción del usuario; se espera a \"Reconectar\"");
+                on_state(BackendState::Stopped { attempt, error });
+                reconnect_rx.recv().map(|()| true).map_err(|_| ())
+            };
+            match reconnect {
+                Ok(true) => {
+                    tracing::info!("🔌 Reconexión solicitada por el usuario");
+                    // Varios clics durante la espera cuentan como uno
+                    while reconnect_rx.try_recv().is_ok() {}
+                }
+                Ok(false) => {}
+                Err(()) => return,
+            }
+            attempt += 1;
+        }
+    }
+}
+
+/// Espera antes de relanzar tras el fallo número `attempt`
+fn retry_delay(attempt: u32) -> Duration {
+    let factor = 2_u32.saturating_pow(attempt.saturating_sub(1));
+    INITIAL_RETRY_DELAY.saturating_mul(factor).min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::*;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[rstest]
+    #[case::first(1, 5)]
+    #[case::second(2, 10)]
+    #[case::third(3, 20)]
+    #[case::capped(7, 300)]
+    #[case::no_overflow(64, 300)]
+    fn test_retry_delay(#[case] attempt: u32, #[case] expected_secs: u64) {
+        assert_eq!(retry_delay(attempt), Duration::from_secs(expected_secs));
+    }
+
+    /// Supervisor que registra los estados y reconecta en cuanto hay un reintento pendiente
+    fn recording_supervisor(
+        run: impl Fn() -> anyhow::Result<()> + Send + Sync + 'static,
+    ) -> (BackendSupervisor, Arc<Mutex<Vec<BackendState>>>, Arc<AtomicU32>) {
+        let states = Arc::new(Mutex::new(Vec::new()));
+        let failures = Arc::new(AtomicU32::new(0));
+        let supervisor = BackendSupervisor::new(run);
+        let reconnect = supervisor.reconnect_sender();
+        let states_cb = states.clone();
+        let failures_cb = failures.clone();
+        let supervisor = supervisor
+            .with_state_callback(move |state| {
+                if matches!(state, BackendState::Retrying { .. } | BackendState::Stopped { .. }) {
+                    let _ = reconnect.send(());
+                }
+                states_cb.lock().unwrap().push(state);
+            })
+            .with_failure_cleanup(move || {
+                failures_cb.fetch_add(1, Ordering::SeqCst);
+            });
+        (supervisor, states, failures)
+    }
+
+    #[test]
+    fn test_retries_until_backend_succeeds() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let calls_run = calls.clone();
+        let (supervisor, states, failures) = recording_supervisor(move || {
+            if calls_run.fetch_add(1, Ordering::SeqCst) < 2 {
+                anyhow::bail!("sin red");
+            }
+            Ok(())
+        });
+
+        supervisor.supervise();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert_eq!(failures.load(Ordering::SeqCst), 2);
+        let retrying = |attempt| BackendState::Retrying {
+            attempt,
+            retry_in: retry_delay(attempt),
+            error: "sin red".to_string(),
+        };
+        assert_eq!(
+            *states.lock().unwrap(),
+            vec![
+                BackendState::Starting { attempt: 1 },
+                retrying(1),
+                BackendState::Starting { attempt: 2 },
+                retrying(2),
+                BackendState::Starting { attempt: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_user_action_errors_wait_for_reconnect() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let calls_run = calls.clone();
+        let (supervisor, states, _) = recording_supervisor(move || {
+            if calls_run.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(needs_user_action(anyhow::anyhow!("configuración inválida")));
+            }
+            Ok(())
+        });
+
+        supervisor.supervise();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            *states.lock().unwrap(),
+            vec![
+                BackendState::Starting { attempt: 1 },
+                BackendState::Stopped { attempt: 1, error: "configuración inválida".to_string() },
+                BackendState::Starting { attempt: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_healthy_run_resets_backoff() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let calls_run = calls.clone();
+        let (mut supervisor, states, _) = recording_supervisor(move || {
+            if calls_run.fetch_add(1, Ordering::SeqCst) < 2 {
+                anyhow::bail!("corte");
+            }
+            Ok(())
+        });
+        supervisor.healthy_run = Duration::ZERO;
+
+        supervisor.supervise();
+
+        let retry_in: Vec<Duration> = states
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|state| match state {
+                BackendState::Retrying { attempt, retry_in, .. } => {
+                    assert_eq!(*attempt, 1, "cada fallo tras servir cuenta como el primero");
+                    Some(*retry_in)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(retry_in, vec![INITIAL_RETRY_DELAY; 2]);
+    }
+
+    #[test]
+    fn test_panic_is_not_retried() {
+        let (supervisor, states, failures) = recording_supervisor(|| panic!("fallo de prueba"));
+
+        supervisor.supervise();
+
+        assert_eq!(failures.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            *states.lock().unwrap(),
+            vec![
+                BackendState::Starting { attempt: 1 },
+                BackendState::Crashed("fallo de prueba".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Aviso de vuelta de la suspensión (logind)
+//!
+//! Tras suspender el portátil la sesión FUSE puede quedar muerta sin que la
+//! tarea de fuse3 termine: el punto de montaje responde `ENOTCONN`. logind
+//! emite `PrepareForSleep(false)` al despertar; `main.rs` lo usa para revisar
+//! el montaje (`mount::is_healthy`) y remontar si hace falta.
+
+use futures_util::StreamExt;
+
+type SleepStream = zbus::proxy::SignalStream<'static>;
+
+/// Suscripción a `PrepareForSleep` de logind. Sin bus de sistema (o sin
+/// logind) `next_resume` nunca retorna
+pub struct ResumeEvents {
+    _proxy: Option<zbus::Proxy<'static>>,
+    signals: Option<SleepStream>,
+}
+
+impl ResumeEvents {
+    /// Se suscribe a logind; los errores solo desactivan el aviso
+    pub async fn subscribe() -> Self {
+        let proxy = match login1_proxy().await {
+            Ok(proxy) => Some(proxy),
+            Err(e) => {
+                tracing::debug!("logind no disponible, sin aviso de reanudación: {:?}", e);
+                None
+            }
+        };
+        let signals = match &proxy {
+            Some(proxy) => proxy.receive_signal("PrepareForSleep").await.ok(),
+            None => None,
+        };
+        Self { _proxy: proxy, signals }
+    }
+
+    /// Espera a que el sistema vuelva de una suspensión o hibernación
+    pub async fn next_resume(&mut self) {
+        loop {
+            let Some(signals) = &mut self.signals else {
+                return std::future::pending().await;
+            };
+            match signals.next().await {
+                // `true` = a punto de suspender, `false` = de vuelta
+                Some(message) => match message.body().deserialize::<bool>() {
+                    Ok(false) => return,
+                    Ok(true) => tracing::debug!("💤 El sistema se suspende"),
+                    Err(e) => tracing::debug!("Señal PrepareForSleep ilegible: {:?}", e),
+                },
+                None => self.signals = None,
+            }
+        }
+    }
+}
+
+/// Proxy del gestor de logind en el bus de sistema
+async fn login1_proxy() -> zbus::Result<zbus::Proxy<'static>> {
+    let connection = zbus::Connection::system().await?;
+    zbus::Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )
+    .await
+}
@@ -0,0 +1,110 @@
+//! Diferencias línea a línea entre dos textos
+//!
+//! Para el centro de conflictos: muestra qué cambió entre la versión de Drive
+//! y la copia local. LCS clásico tras recortar prefijo y sufijo comunes, con
+//! un tope de líneas para acotar la tabla (archivos de texto normales, no logs
+//! de megas).
+
+/// Líneas máximas (tras recortar lo común) de cada lado
+pub const MAX_DIFF_LINES: usize = 2000;
+
+/// Línea del resultado
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Diferencias de `old` a `new`. `None` si la parte distinta supera
+/// `MAX_DIFF_LINES` de algún lado.
+pub fn line_diff<'a>(old: &'a str, new: &'a str) -> Option<Vec<DiffLine<'a>>> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let prefix = old_lines.iter().zip(&new_lines).take_while(|(a, b)| a == b).count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old_lines[prefix..old_lines.len() - suffix];
+    let b = &new_lines[prefix..new_lines.len() - suffix];
+    if a.len() > MAX_DIFF_LINES || b.len() > MAX_DIFF_LINES {
+        return None;
+    }
+
+    // lcs[i][j] = longitud de la subsecuencia común más larga de a[i..] y b[j..]
+    let width = b.len() + 1;
+    let mut lcs = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i * width + j] = if a[i] == b[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut result: Vec<DiffLine> = old_lines[..prefix].iter().map(|l| DiffLine::Same(l)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            result.push(DiffLine::Same(a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+            result.push(DiffLine::Removed(a[i]));
+            i += 1;
+        } else {
+            result.push(DiffLine::Added(b[j]));
+            j += 1;
+        }
+    }
+    result.extend(a[i..].iter().map(|l| DiffLine::Removed(l)));
+    result.extend(b[j..].iter().map(|l| DiffLine::Added(l)));
+    result.extend(old_lines[old_lines.len() - suffix..].iter().map(|l| DiffLine::Same(l)));
+    Some(result)
+}
+
+/// Texto estilo `diff -u` sin cabeceras: `-` quitada, `+` añadida, espacio igual
+pub fn render(lines: &[DiffLine]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        let (marker, text) = match line {
+            DiffLine::Same(text) => (' ', text),
+            DiffLine::Removed(text) => ('-', text),
+            DiffLine::Added(text) => ('+', text),
+        };
+        out.push(marker);
+        out.push_str(text);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::identical("a\nb\n", "a\nb\n", " a\n b\n")]
+    #[case::changed_line("a\nb\nc\n", "a\nB\nc\n", " a\n-b\n+B\n c\n")]
+    #[case::appended("a\n", "a\nb\n", " a\n+b\n")]
+    #[case::removed("a\nb\nc\n", "a\nc\n", " a\n-b\n c\n")]
+    #[case::from_empty("", "x\n", "+x\n")]
+    fn test_line_diff(#[case] old: &str, #[case] new: &str, #[case] expected: &str) {
+        assert_eq!(render(&line_diff(old, new).unwrap()), expected);
+    }
+
+    #[test]
+    fn test_line_diff_gives_up_on_huge_changes() {
+        let old = "a\n".repeat(MAX_DIFF_LINES + 1);
+        let new = "b\n".repeat(MAX_DIFF_LINES + 1);
+        assert_eq!(line_diff(&old, &new), None);
+        assert!(line_diff(&old, &old).is_some(), "lo común no cuenta para el tope");
+    }
+}